@@ -28,6 +28,11 @@ fn main() {
 		exit(1);
 	});
 	config.set_cfg(profile == "debug");
+	Config::embed().unwrap_or_else(|e| {
+		eprintln!("Cannot embed configuration file: {}", e);
+		exit(1);
+	});
+	version::set_env();
 
 	let target = Target::from_env()
 		.unwrap_or_else(|e| {
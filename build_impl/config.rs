@@ -1,8 +1,11 @@
 //! This file implements the configuration file for compilation.
 
+use super::util;
 use serde::Deserialize;
+use std::env;
 use std::fs;
 use std::io;
+use std::path::PathBuf;
 
 /// The path to the configuration file.
 pub const PATH: &str = "config.toml";
@@ -45,6 +48,15 @@ impl Config {
 			.map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))
 	}
 
+	/// Embeds the configuration file into the kernel image, to be exposed in `/proc/config.gz`.
+	pub fn embed() -> io::Result<()> {
+		let config = fs::read(PATH)?;
+		let out_path = PathBuf::from(env::var("OUT_DIR").unwrap()).join("config.gz");
+		fs::write(&out_path, util::gzip_store(&config))?;
+		println!("cargo:rustc-env=MAESTRO_CONFIG_GZ={}", out_path.display());
+		Ok(())
+	}
+
 	/// Sets the crate's cfg flags according to the configuration.
 	pub fn set_cfg(&self, debug: bool) {
 		if debug {
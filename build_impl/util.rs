@@ -32,3 +32,43 @@ pub fn list_c_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
 	list_c_files_impl(dir, &mut paths)?;
 	Ok(paths)
 }
+
+/// Computes the CRC32 checksum of the given data, as used by gzip.
+fn crc32(data: &[u8]) -> u32 {
+	let mut crc = !0u32;
+	for b in data {
+		crc ^= *b as u32;
+		for _ in 0..8 {
+			let mask = (crc & 1).wrapping_neg();
+			crc = (crc >> 1) ^ (0xedb88320 & mask);
+		}
+	}
+	!crc
+}
+
+/// Wraps the given data in the gzip format.
+///
+/// The data is stored without compression, which is enough for small files and does not require
+/// any external dependency.
+pub fn gzip_store(data: &[u8]) -> Vec<u8> {
+	// Header: magic, deflate method, no flags, no modification time, no extra flags, unknown OS
+	let mut out = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff];
+
+	let mut chunks = data.chunks(u16::MAX as usize).peekable();
+	if chunks.peek().is_none() {
+		// An empty final stored block
+		out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+	}
+	while let Some(chunk) = chunks.next() {
+		let last = chunks.peek().is_none();
+		let len = chunk.len() as u16;
+		out.push(last as u8);
+		out.extend_from_slice(&len.to_le_bytes());
+		out.extend_from_slice(&(!len).to_le_bytes());
+		out.extend_from_slice(chunk);
+	}
+
+	out.extend_from_slice(&crc32(data).to_le_bytes());
+	out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+	out
+}
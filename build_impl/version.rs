@@ -0,0 +1,77 @@
+//! The exact revision and build information of the kernel are embedded into its image so that bug
+//! reports can pin down the binary they refer to.
+
+use std::env;
+use std::process::Command;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+/// Returns the git revision the kernel is built from.
+///
+/// If the working tree has uncommitted changes, the revision is suffixed with `-dirty`.
+///
+/// If the revision cannot be retrieved, the function returns `unknown`.
+fn get_git_rev() -> String {
+	let Ok(out) = Command::new("git")
+		.args(["describe", "--always", "--dirty", "--abbrev=12"])
+		.output()
+	else {
+		return "unknown".to_owned();
+	};
+	let rev = String::from_utf8_lossy(&out.stdout).trim().to_owned();
+	if !out.status.success() || rev.is_empty() {
+		return "unknown".to_owned();
+	}
+	rev
+}
+
+/// Returns the build date in format `YYYY-MM-DD hh:mm:ss UTC`.
+///
+/// For reproducible builds, the date is taken from the `SOURCE_DATE_EPOCH` environment variable
+/// if set.
+fn get_build_date() -> String {
+	println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+	let timestamp = env::var("SOURCE_DATE_EPOCH")
+		.ok()
+		.and_then(|s| s.parse().ok())
+		.unwrap_or_else(|| {
+			SystemTime::now()
+				.duration_since(UNIX_EPOCH)
+				.map(|d| d.as_secs())
+				.unwrap_or(0)
+		});
+	let days = (timestamp / 86400) as i64;
+	let secs = timestamp % 86400;
+
+	// Conversion from a number of days since the epoch to a civil date
+	let z = days + 719468;
+	let era = z.div_euclid(146097);
+	let doe = z.rem_euclid(146097);
+	let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+	let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+	let mp = (5 * doy + 2) / 153;
+	let day = doy - (153 * mp + 2) / 5 + 1;
+	let month = if mp < 10 { mp + 3 } else { mp - 9 };
+	let year = yoe + era * 400 + (month <= 2) as i64;
+
+	format!(
+		"{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02} UTC",
+		secs / 3600,
+		(secs / 60) % 60,
+		secs % 60
+	)
+}
+
+/// Passes the version information to the rest of the codebase.
+pub fn set_env() {
+	// Rebuild when the current commit or the state of the working tree changes
+	println!("cargo:rerun-if-changed=.git/HEAD");
+	println!("cargo:rerun-if-changed=.git/index");
+
+	println!("cargo:rustc-env=MAESTRO_GIT_REV={}", get_git_rev());
+	println!(
+		"cargo:rustc-env=MAESTRO_PROFILE={}",
+		env::var("PROFILE").unwrap()
+	);
+	println!("cargo:rustc-env=MAESTRO_BUILD_DATE={}", get_build_date());
+}
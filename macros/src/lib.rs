@@ -21,8 +21,16 @@ pub fn derive_aml_parseable(input: TokenStream) -> TokenStream {
 /// Attribute macro to declare a system call.
 ///
 /// This macro allows to take the system call's arguments directly instead of taking the process's
-/// registers.
+/// registers. Up to six arguments are supported, taken in order from `ebx`, `ecx`, `edx`, `esi`,
+/// `edi` and `ebp`.
+///
+/// If the `strace` feature is enabled, the generated handler traces its arguments and return
+/// value through `trace_enter` and `trace_exit` in the kernel's `syscall` module.
+///
+/// The attribute takes the ID of the system call as argument, for example `#[syscall(0x003)]`.
+/// The handler is then registered in the system calls table, which is built by the linker. Without
+/// an ID, the handler is not registered.
 #[proc_macro_attribute]
-pub fn syscall(_metadata: TokenStream, input: TokenStream) -> TokenStream {
-	syscall::syscall(input)
+pub fn syscall(metadata: TokenStream, input: TokenStream) -> TokenStream {
+	syscall::syscall(metadata, input)
 }
@@ -4,10 +4,12 @@ use proc_macro::TokenStream;
 use proc_macro2::Ident;
 use proc_macro2::Span;
 use quote::quote;
+use syn::ext::IdentExt;
 use syn::parse_macro_input;
 use syn::AngleBracketedGenericArguments;
 use syn::FnArg;
 use syn::ItemFn;
+use syn::LitInt;
 use syn::Path;
 use syn::PathArguments;
 use syn::PathSegment;
@@ -21,7 +23,15 @@ const REGS: [&'static str; 6] = ["ebx", "ecx", "edx", "esi", "edi", "ebp"];
 // TODO Add support for mutable arguments
 
 /// Implementation of the syscall macro.
-pub fn syscall(input: TokenStream) -> TokenStream {
+///
+/// `metadata` is the ID of the system call. If empty, the handler is not registered in the system
+/// calls table.
+pub fn syscall(metadata: TokenStream, input: TokenStream) -> TokenStream {
+	let id = if metadata.is_empty() {
+		None
+	} else {
+		Some(parse_macro_input!(metadata as LitInt))
+	};
 	let input = parse_macro_input!(input as ItemFn);
 
 	// Check signature is valid
@@ -105,78 +115,72 @@ pub fn syscall(input: TokenStream) -> TokenStream {
 	let ident = input.sig.ident;
 	let code = input.block;
 
+	// Register the handler in the system calls table. Entries are sorted by the linker according
+	// to the name of their section, hence the fixed width of the ID
+	let entry = match id {
+		Some(id) => {
+			let id_val = match id.base10_parse::<u32>() {
+				Ok(id) => id,
+				Err(e) => return TokenStream::from(e.to_compile_error()),
+			};
+			let section = format!(".syscalls.{id_val:04x}");
+			let entry_ident = Ident::new(
+				&format!("SYSCALL_ENTRY_{}", ident.unraw().to_string().to_uppercase()),
+				Span::call_site(),
+			);
+			quote! {
+				#[used]
+				#[link_section = #section]
+				static #entry_ident: crate::syscall::SyscallEntry = crate::syscall::SyscallEntry {
+					id: #id,
+					handler: #ident,
+				};
+			}
+		}
+		None => proc_macro2::TokenStream::new(),
+	};
+
 	let toks = if cfg!(feature = "strace") {
 		let args_count = input.sig.inputs.len();
 
-		let mut strace_call_format = String::from("[strace PID: {}] {}(");
+		let mut strace_args_format = String::new();
 		for i in 0..args_count {
 			if i + 1 < args_count {
-				strace_call_format += "{:?}, ";
+				strace_args_format += "{:?}, ";
 			} else {
-				strace_call_format += "{:?}";
+				strace_args_format += "{:?}";
 			}
 		}
-		strace_call_format += ")";
 
 		let strace_args = args.iter().map(|(pat, ..)| pat).collect::<Vec<_>>();
 
 		quote! {
-			pub fn #ident(regs: &crate::process::regs::Regs) -> Result<i32, Errno> {
+			pub fn #ident(regs: &mut crate::process::regs::Regs) -> Result<i32, Errno> {
 				#args_tokens
 
-				crate::idt::wrap_disable_interrupts(|| {
-					let pid = {
-						let proc_mutex = crate::process::Process::current().unwrap();
-						let proc = proc_mutex.lock();
-
-						proc.pid
-					};
-
-					println!(
-						#strace_call_format,
-						pid,
-						stringify!(#ident),
-						#(#strace_args),*
-					);
-				});
-
+				crate::syscall::trace_enter(
+					stringify!(#ident),
+					format_args!(#strace_args_format, #(#strace_args),*),
+				);
 				let ret = (|| {
 					#code
 				})();
-
-				crate::idt::wrap_disable_interrupts(|| {
-					let pid = {
-						let proc_mutex = crate::process::Process::current().unwrap();
-						let proc = proc_mutex.lock();
-
-						proc.pid
-					};
-
-					match ret {
-						Ok(val) => println!(
-							"[strace PID: {}] -> Ok(0x{:x})",
-							pid,
-							val
-						),
-
-						Err(errno) => println!(
-							"[strace PID: {}] -> Err({})",
-							pid,
-							errno
-						),
-					}
-				});
+				crate::syscall::trace_exit(&ret);
 
 				ret
 			}
+
+			#entry
 		}
 	} else {
 		quote! {
-			pub fn #ident(regs: &crate::process::regs::Regs) -> Result<i32, Errno> {
+			pub fn #ident(regs: &mut crate::process::regs::Regs) -> Result<i32, Errno> {
 				#args_tokens
 
 				#code
 			}
+
+			#entry
 		}
 	};
 
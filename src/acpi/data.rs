@@ -5,8 +5,10 @@
 //! much memory, the ACPI data may be too high in memory to recover directly.
 //!
 //! The structure implemented in this module uses a temporary virtual memory
-//! context to get a copy of the data.
+//! context to get a copy of each table.
 
+use crate::acpi::dsdt::Dsdt;
+use crate::acpi::fadt::Fadt;
 use crate::acpi::rsdt::Rsdt;
 use crate::acpi::ACPITable;
 use crate::acpi::ACPITableHeader;
@@ -14,9 +16,10 @@ use crate::errno::Errno;
 use crate::memory;
 use crate::memory::malloc;
 use crate::memory::vmem;
+use crate::memory::vmem::VMem;
 use crate::util;
-use crate::util::boxed::Box;
 use crate::util::container::hashmap::HashMap;
+use crate::util::math;
 use core::ffi::c_void;
 use core::mem::size_of;
 use core::num::NonZeroUsize;
@@ -112,22 +115,53 @@ unsafe fn find_rsdp() -> Option<&'static mut Rsdp> {
 	None
 }
 
-/// Structure containing a copy of the ACPI data read from memory.
+/// The virtual address at which tables are mapped in the temporary virtual memory context.
+const TMP_MAP_ADDR: usize = memory::PAGE_SIZE;
+
+/// Copies the ACPI table at physical address `phys_addr`.
+///
+/// `vmem` is the temporary virtual memory context in which the table is mapped. It must be bound.
+fn copy_table(vmem: &dyn VMem, phys_addr: *const c_void) -> Result<malloc::Alloc<u8>, Errno> {
+	let begin = util::down_align(phys_addr, memory::PAGE_SIZE);
+	let off = phys_addr as usize - begin as usize;
+	let virt_addr = TMP_MAP_ADDR as *const c_void;
+
+	// Map the header to read the table's length
+	let pages = math::ceil_div(off + size_of::<ACPITableHeader>(), memory::PAGE_SIZE);
+	vmem.map_range(begin, virt_addr, pages, 0)?;
+	let len = unsafe {
+		// Safe because the header has been mapped
+		(*((TMP_MAP_ADDR + off) as *const ACPITableHeader)).get_length()
+	};
+	if len < size_of::<ACPITableHeader>() {
+		panic!("Invalid ACPI structure!");
+	}
+	let size = NonZeroUsize::new(len).unwrap();
+
+	// Map the whole table, then copy it
+	let pages = math::ceil_div(off + len, memory::PAGE_SIZE);
+	vmem.map_range(begin, virt_addr, pages, 0)?;
+	let mut table = unsafe {
+		// Safe because the memory is written right after
+		malloc::Alloc::<u8>::new(size)?
+	};
+	unsafe {
+		copy_nonoverlapping((TMP_MAP_ADDR + off) as *const u8, table.as_ptr_mut(), len);
+	}
+	vmem.unmap_range(virt_addr, pages)?;
+
+	Ok(table)
+}
+
+/// Structure containing a copy of the ACPI tables read from memory.
 #[derive(Debug)]
 pub struct ACPIData {
-	/// The offset of the data in the physical memory.
-	off: usize,
-	/// The pointer in the physical memory to the RSDT.
-	rsdt: *const Rsdt,
-
-	/// The buffer containing the ACPI data.
-	data: malloc::Alloc<u8>,
+	/// The copies of the tables, by signature.
+	tables: HashMap<[u8; 4], malloc::Alloc<u8>>,
 }
 
 impl ACPIData {
-	// TODO Clean
-	/// Reads the ACPI data from memory and returns a buffer containing it with its offset in
-	/// physical memory.
+	/// Reads the ACPI tables from memory and returns a structure containing a copy of them.
 	///
 	/// If no ACPI data is found, the function returns `None`.
 	///
@@ -144,127 +178,93 @@ impl ACPIData {
 		// Temporary vmem used to read the data, since it cannot be located anywhere on the
 		// physical memory.
 		let tmp_vmem = vmem::new()?;
-		let rsdt_phys_ptr = rsdp.rsdt_address as *const c_void;
-		let rsdt_map_begin = util::down_align(rsdt_phys_ptr, memory::PAGE_SIZE);
-		crate::println!("being: {:p}", rsdt_map_begin); // TODO rm
-												// Mapping the RSDT to make it readable
-		tmp_vmem.map_range(rsdt_map_begin, memory::PAGE_SIZE as _, 2, 0)?;
-
 		tmp_vmem.bind();
-		let (off, data) = {
-			let rsdt_ptr = (memory::PAGE_SIZE + (rsdt_phys_ptr as usize - rsdt_map_begin as usize))
-				as *const Rsdt;
-			let rsdt = unsafe {
-				// Safe because the pointer has been mapped before
-				&*rsdt_ptr
-			};
-
-			if !rsdt.header.check::<Rsdt>() {
-				panic!("Invalid ACPI structure!");
-			}
-
-			// Getting every ACPI tables
-			let mut tables = HashMap::new();
-			rsdt.foreach_table(|table_ptr| {
-				// Mapping the table to read its length
-				let table_map_begin = util::down_align(table_ptr, memory::PAGE_SIZE);
-				if tmp_vmem
-					.map_range(table_map_begin as _, (memory::PAGE_SIZE * 3) as _, 2, 0)
-					.is_err()
-				{
-					panic!("Unexpected error when reading ACPI data");
-				}
-
-				let table_offset = table_ptr as usize - table_map_begin as usize;
-				let table = unsafe {
-					// Safe because the pointer has been mapped before
-					&*(((memory::PAGE_SIZE * 3) + table_offset) as *const ACPITableHeader)
-				};
-
-				let b = unsafe {
-					let mut ptr = malloc::alloc(table.get_length().try_into().unwrap()).unwrap();
-					ptr::copy_nonoverlapping(
-						table as *const _ as *const _,
-						ptr.as_mut(),
-						table.get_length(),
-					);
-
-					Box::from_raw(ptr.as_ptr() as *mut ())
-				};
-				tables.insert(table.get_signature(), b).unwrap();
-			});
-
-			// FIXME: This is garbage due to a merge in order to make things compile.
-			// This whole function needs a rewrite
-			let lowest = core::ptr::null::<c_void>();
-			let highest = core::ptr::null::<c_void>();
-
-			// Mapping the full ACPI data
-			let begin = util::down_align(lowest, memory::PAGE_SIZE);
-			let end = util::align(highest, memory::PAGE_SIZE);
-			let pages = (end as usize - begin as usize) / memory::PAGE_SIZE;
-			tmp_vmem.map_range(begin, memory::PAGE_SIZE as _, pages, 0)?;
-
-			// FIXME: unwrap here is garbage
-			let size = NonZeroUsize::new(pages * memory::PAGE_SIZE).unwrap();
-			let mut dest = malloc::Alloc::<u8>::new_default(size)?;
-			let src = memory::PAGE_SIZE as *const u8;
-			unsafe {
-				copy_nonoverlapping(src, dest.as_ptr_mut(), size.get());
-			}
-
-			(begin as usize, dest)
-		};
+		let res = Self::read_tables(&*tmp_vmem, rsdp.rsdt_address as _);
 		crate::bind_vmem();
 
 		Ok(Some(Self {
-			off,
-			rsdt: rsdt_phys_ptr as _,
-
-			data,
+			tables: res?,
 		}))
 	}
 
-	// TODO Minimize duplicate code between get_table_*
-
-	/// Returns a reference to the ACPI table with type `T`.
+	/// Copies the RSDT at physical address `rsdt_addr` and every table it refers to, as well as
+	/// the DSDT.
 	///
-	/// If the table doesn't exist, the function returns `None`.
-	pub fn get_table_sized<T: ACPITable>(&self) -> Option<&T> {
-		let rsdt_ptr =
-			unsafe { self.data.as_ptr().add(self.rsdt as usize - self.off) as *const Rsdt };
+	/// `vmem` is the temporary virtual memory context in which tables are mapped. It must be
+	/// bound.
+	fn read_tables(
+		vmem: &dyn VMem,
+		rsdt_addr: *const c_void,
+	) -> Result<HashMap<[u8; 4], malloc::Alloc<u8>>, Errno> {
+		let rsdt_table = copy_table(vmem, rsdt_addr)?;
 		let rsdt = unsafe {
-			// Safe because the pointer has been mapped before
-			&*rsdt_ptr
+			// Safe because the table has been fully copied
+			&*(rsdt_table.as_ptr() as *const Rsdt)
 		};
+		if !rsdt.header.check::<Rsdt>() {
+			panic!("Invalid ACPI structure!");
+		}
 
-		let entries_len = rsdt.header.get_length() - size_of::<Rsdt>();
-		let entries_count = entries_len / size_of::<u32>();
-		let entries_ptr = (rsdt_ptr as usize + size_of::<Rsdt>()) as *const u32;
-
-		for i in 0..entries_count {
-			let header_ptr = unsafe {
-				(self
-					.data
-					.as_ptr()
-					.add(*entries_ptr.add(i) as usize - self.off) as usize) as *const ACPITableHeader
+		let mut tables = HashMap::new();
+		let mut res = Ok(());
+		rsdt.foreach_table(|table_ptr| {
+			if res.is_err() {
+				return;
+			}
+			res = copy_table(vmem, table_ptr as _).and_then(|table| {
+				let signature = unsafe {
+					// Safe because the table is at least as large as its header
+					*(*(table.as_ptr() as *const ACPITableHeader)).get_signature()
+				};
+				tables.insert(signature, table)?;
+				Ok(())
+			});
+		});
+		res?;
+
+		// The DSDT is referred to by the FADT instead of the RSDT
+		let fadt = tables.get(Fadt::get_expected_signature()).map(|fadt| unsafe {
+			// Safe because the table has been fully copied
+			&*(fadt.as_ptr() as *const Fadt)
+		});
+		if let Some(fadt) = fadt {
+			// The extended fields are absent from ACPI 1.0's FADT
+			let extended = fadt.header.get_length() >= size_of::<Fadt>();
+			let dsdt_addr = if extended && fadt.x_dsdt != 0 {
+				fadt.x_dsdt as usize
+			} else {
+				fadt.dsdt as usize
 			};
-			let header = unsafe { &*header_ptr };
+			if dsdt_addr != 0 {
+				let dsdt = copy_table(vmem, dsdt_addr as _)?;
+				tables.insert(*Dsdt::get_expected_signature(), dsdt)?;
+			}
+		}
 
-			if *header.get_signature() == *T::get_expected_signature() {
-				let table = unsafe {
-					let table_ptr = header_ptr as *const T;
-					&*table_ptr
-				};
-				if !table.get_header().check::<T>() {
-					panic!("Invalid ACPI structure!");
-				}
+		Ok(tables)
+	}
 
-				return Some(table);
-			}
+	/// Returns the header of the ACPI table with type `T`.
+	///
+	/// If the table doesn't exist, the function returns `None`.
+	fn get_header<T: ACPITable + ?Sized>(&self) -> Option<&ACPITableHeader> {
+		let table = self.tables.get(T::get_expected_signature())?;
+		let header = unsafe {
+			// Safe because the table is at least as large as its header
+			&*(table.as_ptr() as *const ACPITableHeader)
+		};
+		if !header.check::<T>() {
+			panic!("Invalid ACPI structure!");
 		}
+		Some(header)
+	}
 
-		None
+	/// Returns a reference to the ACPI table with type `T`.
+	///
+	/// If the table doesn't exist, the function returns `None`.
+	pub fn get_table_sized<T: ACPITable>(&self) -> Option<&T> {
+		let header = self.get_header::<T>()?;
+		Some(unsafe { &*(header as *const _ as *const T) })
 	}
 
 	/// Returns a reference to the ACPI table with type `T`.
@@ -275,41 +275,9 @@ impl ACPIData {
 	pub fn get_table_unsized<T: ACPITable + ?Sized + Pointee<Metadata = usize>>(
 		&self,
 	) -> Option<&T> {
-		let rsdt_ptr =
-			unsafe { self.data.as_ptr().add(self.rsdt as usize - self.off) as *const Rsdt };
-		let rsdt = unsafe {
-			// Safe because the pointer has been mapped before
-			&*rsdt_ptr
-		};
-
-		let entries_len = rsdt.header.get_length() - size_of::<Rsdt>();
-		let entries_count = entries_len / size_of::<u32>();
-		let entries_ptr = (rsdt_ptr as usize + size_of::<Rsdt>()) as *const u32;
-
-		for i in 0..entries_count {
-			let header_ptr = unsafe {
-				(self
-					.data
-					.as_ptr()
-					.add(*entries_ptr.add(i) as usize - self.off) as usize) as *const ACPITableHeader
-			};
-			let header = unsafe { &*header_ptr };
-
-			if *header.get_signature() == *T::get_expected_signature() {
-				let len = header.get_length();
-				let table = unsafe {
-					let table_ptr = ptr::from_raw_parts::<T>(header_ptr as *const (), len);
-					&*table_ptr
-				};
-
-				if !table.get_header().check::<T>() {
-					panic!("Invalid ACPI structure!");
-				}
-
-				return Some(table);
-			}
-		}
-
-		None
+		let header = self.get_header::<T>()?;
+		let len = header.get_length();
+		let table_ptr = ptr::from_raw_parts::<T>(header as *const _ as *const (), len);
+		Some(unsafe { &*table_ptr })
 	}
 }
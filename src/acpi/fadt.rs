@@ -1,9 +1,7 @@
 //! This module handles ACPI's Fixed ACPI Description Table (FADT).
 
-use super::dsdt::Dsdt;
 use super::ACPITable;
 use super::ACPITableHeader;
-use core::slice;
 
 /// TODO doc
 pub struct GenericAddr {
@@ -83,33 +81,6 @@ pub struct Fadt {
 	pub x_gpe1_block: GenericAddr,
 }
 
-impl Fadt {
-	/// Returns a reference to the DSDT if it exists.
-	pub fn get_dsdt(&self) -> Option<&Dsdt> {
-		let dsdt = if self.x_dsdt != 0 {
-			self.x_dsdt
-		} else {
-			self.dsdt as _
-		} as *const u8;
-		// TODO Add displacement relative to ACPI data buffer
-
-		if !dsdt.is_null() {
-			let dsdt = unsafe {
-				let dsdt_slice = slice::from_raw_parts(dsdt, 1);
-				&*(dsdt_slice as *const [u8] as *const [()] as *const Dsdt)
-			};
-
-			if !dsdt.get_header().check::<Dsdt>() {
-				panic!("Invalid ACPI structure!");
-			}
-
-			Some(dsdt)
-		} else {
-			None
-		}
-	}
-}
-
 impl ACPITable for Fadt {
 	fn get_expected_signature() -> &'static [u8; 4] {
 		&[b'F', b'A', b'C', b'P']
@@ -2,10 +2,23 @@
 
 use super::ACPITable;
 use super::ACPITableHeader;
+use core::ptr;
 
 /// The offset of the entries in the MADT.
 const ENTRIES_OFF: usize = 0x2c;
 
+/// Entry type: processor local APIC.
+const ENTRY_LOCAL_APIC: u8 = 0;
+/// Entry type: I/O APIC.
+const ENTRY_IO_APIC: u8 = 1;
+/// Entry type: interrupt source override.
+const ENTRY_INTERRUPT_OVERRIDE: u8 = 2;
+
+/// Local APIC flag: the CPU is enabled.
+const LOCAL_APIC_ENABLED: u32 = 0b1;
+/// Local APIC flag: the CPU is disabled but can be enabled at runtime.
+const LOCAL_APIC_ONLINE_CAPABLE: u32 = 0b10;
+
 /// Indicates that the system also has a PC-AT-compatible dual-8259 setup (which
 /// must be disabled when enabling ACPI APIC).
 const PCAT_COMPAT: u32 = 0b1;
@@ -24,19 +37,90 @@ pub struct Madt {
 	flags: u32,
 }
 
+/// An entry of the MADT describing a CPU or how external interrupts are routed.
+pub enum ApicEntry {
+	/// The local APIC of a CPU.
+	LocalApic {
+		/// The ID of the local APIC.
+		apic_id: u8,
+		/// Tells whether the CPU is enabled or can be enabled at runtime. If not, the CPU must not
+		/// be used.
+		usable: bool,
+	},
+	/// An I/O APIC.
+	IoApic {
+		/// The ID of the I/O APIC.
+		id: u8,
+		/// The physical address of the I/O APIC's registers.
+		addr: u32,
+		/// The first Global System Interrupt (GSI) handled by the I/O APIC.
+		gsi_base: u32,
+	},
+	/// An ISA IRQ that is not identity-mapped to a GSI.
+	InterruptOverride {
+		/// The ISA IRQ.
+		irq: u8,
+		/// The GSI the IRQ is mapped to.
+		gsi: u32,
+		/// The polarity and trigger mode of the interrupt, with the format of MPS INTI flags.
+		flags: u16,
+	},
+}
+
 impl Madt {
-	/// Executes the given closure for each entry in the MADT.
-	pub fn foreach_entry<F: Fn(&EntryHeader)>(&self, f: F) {
-		let entries_len = self.header.get_length() - ENTRIES_OFF;
+	/// Returns the physical address of the local APIC's registers.
+	pub fn get_local_apic_addr(&self) -> u32 {
+		self.local_apic_addr
+	}
+
+	/// Tells whether the system also has dual-8259 PICs, which must be disabled when using the
+	/// APIC.
+	pub fn has_pic(&self) -> bool {
+		self.flags & PCAT_COMPAT != 0
+	}
+
+	/// Executes the given closure for each entry of the MADT describing a CPU or interrupt
+	/// routing.
+	pub fn foreach_apic_entry<F: FnMut(ApicEntry)>(&self, mut f: F) {
+		let begin = self as *const _ as *const u8;
+		let entries_len = self.header.get_length().saturating_sub(ENTRIES_OFF);
+
+		// Reads a value at offset `off` in the entry at `entry`
+		let read_u32 = |entry: *const u8, off: usize| unsafe {
+			ptr::read_unaligned(entry.add(off) as *const u32)
+		};
 
 		let mut i = 0;
-		while i < entries_len {
-			let entry =
-				unsafe { &*((self as *const _ as usize + ENTRIES_OFF + i) as *const EntryHeader) };
+		while i + 2 <= entries_len {
+			let entry = unsafe { begin.add(ENTRIES_OFF + i) };
+			let (entry_type, len) = unsafe { (*entry, *entry.add(1) as usize) };
+			if len < 2 || i + len > entries_len {
+				break;
+			}
+
+			match entry_type {
+				ENTRY_LOCAL_APIC if len >= 8 => f(ApicEntry::LocalApic {
+					apic_id: unsafe { *entry.add(3) },
+					usable: read_u32(entry, 4) & (LOCAL_APIC_ENABLED | LOCAL_APIC_ONLINE_CAPABLE)
+						!= 0,
+				}),
 
-			f(entry);
+				ENTRY_IO_APIC if len >= 12 => f(ApicEntry::IoApic {
+					id: unsafe { *entry.add(2) },
+					addr: read_u32(entry, 4),
+					gsi_base: read_u32(entry, 8),
+				}),
 
-			i += entry.get_length() as usize;
+				ENTRY_INTERRUPT_OVERRIDE if len >= 10 => f(ApicEntry::InterruptOverride {
+					irq: unsafe { *entry.add(3) },
+					gsi: read_u32(entry, 4),
+					flags: unsafe { ptr::read_unaligned(entry.add(8) as *const u16) },
+				}),
+
+				_ => {}
+			}
+
+			i += len;
 		}
 	}
 }
@@ -47,24 +131,3 @@ impl ACPITable for Madt {
 	}
 }
 
-/// Represents an MADT entry header.
-#[repr(C)]
-#[derive(Debug)]
-pub struct EntryHeader {
-	/// The entry type.
-	entry_type: u8,
-	/// The entry length.
-	length: u8,
-}
-
-impl EntryHeader {
-	/// Returns the type of the entry.
-	pub fn get_type(&self) -> u8 {
-		self.entry_type
-	}
-
-	/// Returns the length of the entry.
-	pub fn get_length(&self) -> u8 {
-		self.length
-	}
-}
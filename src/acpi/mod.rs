@@ -4,13 +4,21 @@
 //! ACPI initialization is done through the following phases:
 //! - Read the `RSDP` table in order to get a pointer to the `RSDT`, referring to every other
 //!   available tables.
+//! - Register the CPUs and interrupt controllers described by the `MADT` and the NUMA topology
+//!   described by the `SRAT`.
 //! - TODO
 
+use crate::cpu::hotplug;
+use crate::idt::apic;
+use crate::memory::numa;
 use core::mem::size_of;
 use data::ACPIData;
 use dsdt::Dsdt;
 use fadt::Fadt;
+use madt::ApicEntry;
 use madt::Madt;
+use srat::Affinity;
+use srat::Srat;
 
 mod aml;
 mod data;
@@ -18,6 +26,7 @@ mod dsdt;
 mod fadt;
 mod madt;
 mod rsdt;
+mod srat;
 
 /// An ACPI table header.
 #[repr(C)]
@@ -114,13 +123,45 @@ pub fn init() {
 
 	if let Some(data) = data {
 		if let Some(madt) = data.get_table_sized::<Madt>() {
-			// Registering CPU cores
-			madt.foreach_entry(|e: &madt::EntryHeader| match e.get_type() {
-				0 => {
-					// TODO Register a new CPU
+			// Registering CPU cores and interrupt controllers
+			apic::set_local_apic(madt.get_local_apic_addr(), madt.has_pic());
+			madt.foreach_apic_entry(|e| match e {
+				ApicEntry::LocalApic {
+					apic_id,
+					usable,
+				} => {
+					if usable {
+						hotplug::add_cpu(apic_id);
+					}
 				}
 
-				_ => {}
+				ApicEntry::IoApic {
+					addr,
+					gsi_base,
+					..
+				} => apic::add_io_apic(addr, gsi_base),
+
+				ApicEntry::InterruptOverride {
+					irq,
+					gsi,
+					flags,
+				} => apic::add_override(irq, gsi, flags),
+			});
+		}
+
+		// Registering the NUMA topology
+		if let Some(srat) = data.get_table_sized::<Srat>() {
+			srat.foreach_affinity(|a| match a {
+				Affinity::Processor {
+					apic_id,
+					node,
+				} => numa::add_cpu(apic_id, node),
+
+				Affinity::Memory {
+					node,
+					base,
+					len,
+				} => numa::add_memory(node, base, len),
 			});
 		}
 
@@ -133,16 +174,8 @@ pub fn init() {
 		}
 
 		// Getting the DSDT
-		let dsdt = data.get_table_unsized::<Dsdt>().or_else(|| {
-			data.get_table_sized::<Fadt>()
-				.and_then(|fadt| fadt.get_dsdt())
-		});
-		if let Some(dsdt) = dsdt {
-			// Parsing AML code
-			let aml = dsdt.get_aml();
-			let _ast = aml::parse(aml);
-
-			// TODO
+		if let Some(_dsdt) = data.get_table_unsized::<Dsdt>() {
+			// TODO Parse AML code once the parser is implemented
 		}
 	}
 }
@@ -0,0 +1,100 @@
+//! This module handles ACPI's System Resource Affinity Table (SRAT), which describes the NUMA
+//! node of CPU cores and memory ranges.
+
+use super::ACPITable;
+use super::ACPITableHeader;
+use core::ptr;
+
+/// The offset of the entries in the SRAT.
+const ENTRIES_OFF: usize = 0x30;
+
+/// Entry type: processor local APIC affinity.
+const ENTRY_PROCESSOR: u8 = 0;
+/// Entry type: memory affinity.
+const ENTRY_MEMORY: u8 = 1;
+
+/// Entry flag: the entry is enabled.
+const FLAG_ENABLED: u32 = 0b1;
+
+/// The System Resource Affinity Table.
+#[repr(C)]
+#[derive(Debug)]
+pub struct Srat {
+	/// The table's header.
+	pub header: ACPITableHeader,
+
+	/// Reserved.
+	_reserved: [u8; 12],
+}
+
+/// An affinity described by an entry of the SRAT.
+pub enum Affinity {
+	/// The CPU core with the given APIC ID belongs to the given node.
+	Processor {
+		/// The APIC ID of the core.
+		apic_id: u32,
+		/// The ID of the node.
+		node: u32,
+	},
+	/// The range of physical memory belongs to the given node.
+	Memory {
+		/// The ID of the node.
+		node: u32,
+		/// The beginning of the range.
+		base: u64,
+		/// The size of the range in bytes.
+		len: u64,
+	},
+}
+
+impl Srat {
+	/// Executes the given closure for each enabled affinity in the SRAT.
+	pub fn foreach_affinity<F: FnMut(Affinity)>(&self, mut f: F) {
+		let begin = self as *const _ as *const u8;
+		let entries_len = self.header.get_length().saturating_sub(ENTRIES_OFF);
+
+		// Reads a value at offset `off` in the entry at `entry`
+		let read_u32 = |entry: *const u8, off: usize| unsafe {
+			ptr::read_unaligned(entry.add(off) as *const u32)
+		};
+
+		let mut i = 0;
+		while i + 2 <= entries_len {
+			let entry = unsafe { begin.add(ENTRIES_OFF + i) };
+			let (entry_type, len) = unsafe { (*entry, *entry.add(1) as usize) };
+			if len < 2 || i + len > entries_len {
+				break;
+			}
+
+			match entry_type {
+				ENTRY_PROCESSOR if len >= 16 && read_u32(entry, 4) & FLAG_ENABLED != 0 => {
+					let domain_hi = read_u32(entry, 8) & 0xffffff00;
+					f(Affinity::Processor {
+						apic_id: unsafe { *entry.add(3) } as _,
+						node: domain_hi | unsafe { *entry.add(2) } as u32,
+					});
+				}
+
+				ENTRY_MEMORY if len >= 40 && read_u32(entry, 28) & FLAG_ENABLED != 0 => {
+					let base = read_u32(entry, 8) as u64 | (read_u32(entry, 12) as u64) << 32;
+					let len = read_u32(entry, 16) as u64 | (read_u32(entry, 20) as u64) << 32;
+					f(Affinity::Memory {
+						node: read_u32(entry, 2),
+						base,
+						len,
+					});
+				}
+
+				_ => {}
+			}
+
+			i += len;
+		}
+	}
+}
+
+impl ACPITable for Srat {
+	fn get_expected_signature() -> &'static [u8; 4] {
+		&[b'S', b'R', b'A', b'T']
+	}
+}
@@ -13,6 +13,32 @@ fn parse_nbr(slice: &[u8]) -> Option<u32> {
 	str::from_utf8(slice).ok().and_then(|s| s.parse().ok())
 }
 
+/// Parses the size represented by the string in the given slice, with an optional `K`, `M` or
+/// `G` suffix.
+///
+/// If the slice doesn't contain a valid size, the function returns `None`.
+fn parse_size(slice: &[u8]) -> Option<u64> {
+	let (nbr, shift) = match slice.last()? {
+		b'K' | b'k' => (&slice[..(slice.len() - 1)], 10),
+		b'M' | b'm' => (&slice[..(slice.len() - 1)], 20),
+		b'G' | b'g' => (&slice[..(slice.len() - 1)], 30),
+		_ => (slice, 0),
+	};
+	let nbr: u64 = str::from_utf8(nbr).ok()?.parse().ok()?;
+	nbr.checked_mul(1 << shift)
+}
+
+/// Parses the region represented by the string in the given slice, in the form
+/// `<size>@<offset>`.
+///
+/// On success, the function returns the size and offset of the region.
+fn parse_region(slice: &[u8]) -> Option<(u64, u64)> {
+	let mut iter = slice.splitn(2, |c| *c == b'@');
+	let size = iter.next().and_then(parse_size)?;
+	let offset = iter.next().and_then(parse_size)?;
+	Some((size, offset))
+}
+
 /// Structure representing a command line parsing error.
 #[derive(Debug)]
 pub struct ParseError<'s> {
@@ -115,6 +141,23 @@ pub struct ArgsParser<'s> {
 	init: Option<&'s [u8]>,
 	/// Whether the kernel boots silently.
 	silent: bool,
+	/// The path to the console font to load, if specified.
+	font: Option<&'s [u8]>,
+	/// Whether access to RAM through `/dev/mem` is allowed.
+	iomem_relaxed: bool,
+	/// The number of bytes per second fed to the entropy pool by hardware random number
+	/// generators, if specified.
+	hwrng_rate: Option<u32>,
+	/// Whether the PIC is used instead of the APIC.
+	noapic: bool,
+	/// Whether the kernel waits for a debugger on the serial port at boot.
+	kgdb: bool,
+	/// The number of passes of the memory test at boot, if the memory test is enabled.
+	memtest: Option<u32>,
+	/// The size and physical offset of the crash kernel region, if specified.
+	crashkernel: Option<(u64, u64)>,
+	/// The size and physical offset of the pstore region, if specified.
+	pstore: Option<(u64, u64)>,
 }
 
 impl<'s> ArgsParser<'s> {
@@ -124,6 +167,14 @@ impl<'s> ArgsParser<'s> {
 			root: None,
 			init: None,
 			silent: false,
+			font: None,
+			iomem_relaxed: false,
+			hwrng_rate: None,
+			noapic: false,
+			kgdb: false,
+			memtest: None,
+			crashkernel: None,
+			pstore: None,
 		};
 
 		let mut iter = TokenIterator {
@@ -176,6 +227,100 @@ impl<'s> ArgsParser<'s> {
 
 				b"-silent" => s.silent = true,
 
+				b"-noapic" => s.noapic = true,
+
+				b"-kgdb" => s.kgdb = true,
+
+				b"-font" => {
+					let Some((_, font)) = iter.next() else {
+						return Err(ParseError {
+							cmdline,
+							err: "not enough arguments for `-font`",
+							token: Some((token.begin, token.s.len())),
+						});
+					};
+					s.font = Some(font.s);
+				}
+
+				b"-iomem" => {
+					let Some((_, mode)) = iter.next() else {
+						return Err(ParseError {
+							cmdline,
+							err: "not enough arguments for `-iomem`",
+							token: Some((token.begin, token.s.len())),
+						});
+					};
+					s.iomem_relaxed = match mode.s {
+						b"strict" => false,
+						b"relaxed" => true,
+						_ => {
+							return Err(ParseError {
+								cmdline,
+								err: "invalid `-iomem` mode",
+								token: Some((mode.begin, mode.s.len())),
+							});
+						}
+					};
+				}
+
+				b"-hwrng-rate" => {
+					let Some((_, rate)) = iter.next() else {
+						return Err(ParseError {
+							cmdline,
+							err: "not enough arguments for `-hwrng-rate`",
+							token: Some((token.begin, token.s.len())),
+						});
+					};
+					let Some(rate) = parse_nbr(rate.s) else {
+						return Err(ParseError {
+							cmdline,
+							err: "invalid rate",
+							token: Some((rate.begin, rate.s.len())),
+						});
+					};
+					s.hwrng_rate = Some(rate);
+				}
+
+				b"-memtest" => {
+					let Some((_, passes)) = iter.next() else {
+						return Err(ParseError {
+							cmdline,
+							err: "not enough arguments for `-memtest`",
+							token: Some((token.begin, token.s.len())),
+						});
+					};
+					let Some(passes) = parse_nbr(passes.s) else {
+						return Err(ParseError {
+							cmdline,
+							err: "invalid number of passes",
+							token: Some((passes.begin, passes.s.len())),
+						});
+					};
+					s.memtest = Some(passes);
+				}
+
+				arg if arg.starts_with(b"crashkernel=") => {
+					let Some(region) = parse_region(&arg[b"crashkernel=".len()..]) else {
+						return Err(ParseError {
+							cmdline,
+							err: "invalid `crashkernel` region",
+							token: Some((token.begin, token.s.len())),
+						});
+					};
+					s.crashkernel = Some(region);
+				}
+
+				arg if arg.starts_with(b"pstore=") => {
+					let Some(region) = parse_region(&arg[b"pstore=".len()..]) else {
+						return Err(ParseError {
+							cmdline,
+							err: "invalid `pstore` region",
+							token: Some((token.begin, token.s.len())),
+						});
+					};
+					s.pstore = Some(region);
+				}
+
 				_ => {
 					return Err(ParseError {
 						cmdline,
@@ -199,10 +344,51 @@ impl<'s> ArgsParser<'s> {
 		self.init
 	}
 
+	/// Returns the path to the console font to load if specified.
+	pub fn get_font_path(&self) -> Option<&'s [u8]> {
+		self.font
+	}
+
 	/// If `true`, the kernel doesn't print logs while booting.
 	pub fn is_silent(&self) -> bool {
 		self.silent
 	}
+
+	/// If `true`, `/dev/mem` allows to access the whole physical memory, including RAM.
+	pub fn is_iomem_relaxed(&self) -> bool {
+		self.iomem_relaxed
+	}
+
+	/// Returns the number of bytes per second fed to the entropy pool by hardware random number
+	/// generators, if specified.
+	pub fn get_hwrng_rate(&self) -> Option<u32> {
+		self.hwrng_rate
+	}
+
+	/// If `true`, interruptions are handled by the PIC even if the APIC is available.
+	pub fn is_apic_disabled(&self) -> bool {
+		self.noapic
+	}
+
+	/// If `true`, the kernel waits for a debugger on the serial port at boot.
+	pub fn is_kgdb_enabled(&self) -> bool {
+		self.kgdb
+	}
+
+	/// Returns the number of passes of the memory test at boot, if the memory test is enabled.
+	pub fn get_memtest_passes(&self) -> Option<u32> {
+		self.memtest
+	}
+
+	/// Returns the size and physical offset of the crash kernel region, if specified.
+	pub fn get_crashkernel(&self) -> Option<(u64, u64)> {
+		self.crashkernel
+	}
+
+	/// Returns the size and physical offset of the pstore region, if specified.
+	pub fn get_pstore(&self) -> Option<(u64, u64)> {
+		self.pstore
+	}
 }
 
 #[cfg(test)]
@@ -248,4 +434,75 @@ mod test {
 	fn cmdline7() {
 		assert!(ArgsParser::parse(b"-root 1 0 -init bleh -silent").is_ok());
 	}
+
+	#[test_case]
+	fn cmdline8() {
+		assert!(ArgsParser::parse(b"-iomem").is_err());
+		assert!(ArgsParser::parse(b"-iomem bleh").is_err());
+		assert!(!ArgsParser::parse(b"-iomem strict")
+			.unwrap()
+			.is_iomem_relaxed());
+		assert!(ArgsParser::parse(b"-iomem relaxed")
+			.unwrap()
+			.is_iomem_relaxed());
+	}
+
+	#[test_case]
+	fn cmdline9() {
+		assert!(ArgsParser::parse(b"-font").is_err());
+		assert_eq!(
+			ArgsParser::parse(b"-font /lib/font.psf")
+				.unwrap()
+				.get_font_path(),
+			Some(&b"/lib/font.psf"[..])
+		);
+	}
+
+	#[test_case]
+	fn cmdline10() {
+		assert!(!ArgsParser::parse(b"").unwrap().is_apic_disabled());
+		assert!(ArgsParser::parse(b"-noapic").unwrap().is_apic_disabled());
+	}
+
+	#[test_case]
+	fn cmdline11() {
+		assert!(!ArgsParser::parse(b"").unwrap().is_kgdb_enabled());
+		assert!(ArgsParser::parse(b"-root 1 0 -kgdb")
+			.unwrap()
+			.is_kgdb_enabled());
+	}
+
+	#[test_case]
+	fn cmdline12() {
+		assert!(ArgsParser::parse(b"-memtest").is_err());
+		assert!(ArgsParser::parse(b"-memtest bleh").is_err());
+		assert_eq!(
+			ArgsParser::parse(b"-memtest 2")
+				.unwrap()
+				.get_memtest_passes(),
+			Some(2)
+		);
+	}
+
+	#[test_case]
+	fn cmdline13() {
+		assert!(ArgsParser::parse(b"crashkernel=").is_err());
+		assert!(ArgsParser::parse(b"crashkernel=64M").is_err());
+		assert!(ArgsParser::parse(b"crashkernel=64X@16M").is_err());
+		assert_eq!(
+			ArgsParser::parse(b"-root 1 0 crashkernel=64M@16M")
+				.unwrap()
+				.get_crashkernel(),
+			Some((64 << 20, 16 << 20))
+		);
+		assert!(ArgsParser::parse(b"crashkernel=4096@0x1000").is_err());
+	}
+
+	#[test_case]
+	fn cmdline14() {
+		assert!(ArgsParser::parse(b"pstore=16K").is_err());
+		let args = ArgsParser::parse(b"crashkernel=64M@16M pstore=16K@128M").unwrap();
+		assert_eq!(args.get_crashkernel(), Some((64 << 20, 16 << 20)));
+		assert_eq!(args.get_pstore(), Some((16 << 10, 128 << 20)));
+	}
 }
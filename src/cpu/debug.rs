@@ -0,0 +1,143 @@
+//! Hardware debug registers `%dr0` to `%dr7`, used to implement breakpoints and watchpoints.
+//!
+//! `%dr0` to `%dr3` hold the linear addresses to watch. `%dr6` reports which condition triggered
+//! the last Debug (#DB) exception. `%dr7` enables each address and selects the access type and
+//! length to watch. `%dr4` and `%dr5` are aliases of `%dr6` and `%dr7` and cannot be used.
+//!
+//! Each process has its own set of registers, loaded when switching to it.
+
+use crate::memory;
+use core::arch::asm;
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering::Relaxed;
+
+/// The number of debug registers.
+pub const REGS_COUNT: usize = 8;
+
+/// `%dr7` bits that are reserved and always set.
+const DR7_RESERVED_SET: u32 = 1 << 10;
+/// `%dr7` flag: General Detect, makes accesses to debug registers trap.
+const DR7_GD: u32 = 1 << 13;
+/// `%dr7` bits enabling the breakpoints (local and global).
+const DR7_ENABLE_MASK: u32 = 0xff;
+/// `%dr7` bits that the kernel accepts from userspace: enable bits and condition/length fields.
+const DR7_USER_MASK: u32 = 0xffff0000 | DR7_ENABLE_MASK;
+/// `%dr6` value with no condition reported.
+const DR6_DEFAULT: u32 = 0xffff0ff0;
+
+/// Tells whether the debug registers currently hold the state of a process that uses them.
+static LOADED: AtomicBool = AtomicBool::new(false);
+
+/// Reads the debug register with index `n`.
+///
+/// # Safety
+///
+/// `n` must be lower than [`REGS_COUNT`] and must not be `4` or `5`.
+unsafe fn read(n: usize) -> u32 {
+	let val: u32;
+	match n {
+		0 => asm!("mov {}, dr0", out(reg) val),
+		1 => asm!("mov {}, dr1", out(reg) val),
+		2 => asm!("mov {}, dr2", out(reg) val),
+		3 => asm!("mov {}, dr3", out(reg) val),
+		6 => asm!("mov {}, dr6", out(reg) val),
+		7 => asm!("mov {}, dr7", out(reg) val),
+		_ => unreachable!(),
+	}
+	val
+}
+
+/// Writes `val` in the debug register with index `n`.
+///
+/// # Safety
+///
+/// `n` must be lower than [`REGS_COUNT`] and must not be `4` or `5`. The value must be valid for
+/// the register.
+unsafe fn write(n: usize, val: u32) {
+	match n {
+		0 => asm!("mov dr0, {}", in(reg) val),
+		1 => asm!("mov dr1, {}", in(reg) val),
+		2 => asm!("mov dr2, {}", in(reg) val),
+		3 => asm!("mov dr3, {}", in(reg) val),
+		6 => asm!("mov dr6, {}", in(reg) val),
+		7 => asm!("mov dr7, {}", in(reg) val),
+		_ => unreachable!(),
+	}
+}
+
+/// The state of the debug registers of a process.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DebugRegs {
+	/// The value of each register. Entries `4` and `5` are always zero.
+	regs: [u32; REGS_COUNT],
+}
+
+impl DebugRegs {
+	/// Tells whether at least one breakpoint is enabled.
+	pub fn is_enabled(&self) -> bool {
+		self.regs[7] & DR7_ENABLE_MASK != 0
+	}
+
+	/// Returns the value of the register with index `n`.
+	///
+	/// If the register does not exist, the function returns `None`.
+	pub fn get(&self, n: usize) -> Option<u32> {
+		self.regs.get(n).copied()
+	}
+
+	/// Sets the value of the register with index `n`.
+	///
+	/// If the register does not exist, cannot be set or if the value is invalid, the function
+	/// returns `false` and the state is left unchanged.
+	pub fn set(&mut self, n: usize, val: u32) -> bool {
+		let valid = match n {
+			// Breakpoints may only be placed in userspace
+			0..=3 => (val as usize) < memory::PROCESS_END as usize,
+			6 => true,
+			7 => {
+				// Watching I/O accesses is not permitted
+				let io = (0..4).any(|i| (val >> (16 + i * 4)) & 0b11 == 0b10);
+				val & !DR7_USER_MASK == 0 && !io
+			}
+			_ => false,
+		};
+		if valid {
+			self.regs[n] = val;
+		}
+		valid
+	}
+
+	/// Loads the state into the CPU's registers.
+	///
+	/// If neither the state nor the current content of the registers has breakpoints enabled, the
+	/// function does nothing to save the cost of writing to the registers.
+	pub fn load(&self) {
+		let enabled = self.is_enabled();
+		if !enabled && !LOADED.load(Relaxed) {
+			return;
+		}
+		unsafe {
+			// Disable breakpoints first so that none trigger while updating addresses
+			write(7, DR7_RESERVED_SET);
+			for (n, val) in self.regs[..4].iter().enumerate() {
+				write(n, *val);
+			}
+			write(6, DR6_DEFAULT);
+			write(7, (self.regs[7] & !DR7_GD) | DR7_RESERVED_SET);
+		}
+		LOADED.store(enabled, Relaxed);
+	}
+
+	/// Saves the status reported by the CPU in `%dr6` after a Debug exception, then clears it.
+	pub fn save_status(&mut self) {
+		self.regs[6] = unsafe { read(6) };
+		clear_status();
+	}
+}
+
+/// Clears the status reported by the CPU in `%dr6`.
+pub fn clear_status() {
+	unsafe {
+		write(6, DR6_DEFAULT);
+	}
+}
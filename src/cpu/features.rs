@@ -0,0 +1,191 @@
+//! Detection of CPU features through the `cpuid` instruction.
+//!
+//! Features are probed once at boot. Then, code paths depending on them can be selected using
+//! [`Alternative`].
+
+use core::arch::x86::__cpuid;
+use core::arch::x86::__cpuid_count;
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::AtomicU32;
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering::Relaxed;
+
+/// A CPU feature.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Feature {
+	/// SSE instructions.
+	Sse,
+	/// SSE2 instructions.
+	Sse2,
+	/// SSE3 instructions.
+	Sse3,
+	/// SSSE3 instructions.
+	Ssse3,
+	/// SSE4.1 instructions.
+	Sse41,
+	/// SSE4.2 instructions, including `crc32`.
+	Sse42,
+	/// AES-NI instructions.
+	Aes,
+	/// The `xsave` family of instructions.
+	Xsave,
+	/// AVX instructions.
+	Avx,
+	/// The `rdrand` instruction.
+	Rdrand,
+	/// The `rdseed` instruction.
+	Rdseed,
+	/// The `rdfsbase` family of instructions.
+	Fsgsbase,
+	/// Enhanced `rep movsb` and `rep stosb`.
+	Erms,
+	/// An on-chip local APIC.
+	Apic,
+	/// The kernel runs under a hypervisor.
+	Hypervisor,
+}
+
+impl Feature {
+	/// Returns the leaf, the register and the bit in the register telling whether the feature
+	/// is present.
+	///
+	/// Registers are numbered in the order `eax`, `ebx`, `ecx`, `edx`.
+	fn location(self) -> (u32, u8, u8) {
+		match self {
+			Self::Sse => (1, 3, 25),
+			Self::Sse2 => (1, 3, 26),
+			Self::Sse3 => (1, 2, 0),
+			Self::Ssse3 => (1, 2, 9),
+			Self::Sse41 => (1, 2, 19),
+			Self::Sse42 => (1, 2, 20),
+			Self::Aes => (1, 2, 25),
+			Self::Xsave => (1, 2, 26),
+			Self::Avx => (1, 2, 28),
+			Self::Rdrand => (1, 2, 30),
+			Self::Rdseed => (7, 1, 18),
+			Self::Fsgsbase => (7, 1, 0),
+			Self::Erms => (7, 1, 9),
+			Self::Apic => (1, 3, 9),
+			Self::Hypervisor => (1, 2, 31),
+		}
+	}
+}
+
+/// The list of all features.
+const FEATURES: [Feature; 15] = [
+	Feature::Sse,
+	Feature::Sse2,
+	Feature::Sse3,
+	Feature::Ssse3,
+	Feature::Sse41,
+	Feature::Sse42,
+	Feature::Aes,
+	Feature::Xsave,
+	Feature::Avx,
+	Feature::Rdrand,
+	Feature::Rdseed,
+	Feature::Fsgsbase,
+	Feature::Erms,
+	Feature::Apic,
+	Feature::Hypervisor,
+];
+
+/// Tells whether features have been probed.
+static PROBED: AtomicBool = AtomicBool::new(false);
+/// The bitfield of present features, indexed by their position in [`FEATURES`].
+static PRESENT: AtomicU32 = AtomicU32::new(0);
+
+extern "C" {
+	/// The implementation of `memcpy` in use.
+	static mut memcpy_impl: unsafe extern "C" fn();
+	/// Implementation of `memcpy` using `rep movsb`.
+	fn memcpy_erms();
+	/// The implementation of `memset` in use.
+	static mut memset_impl: unsafe extern "C" fn();
+	/// Implementation of `memset` using `rep stosb`.
+	fn memset_erms();
+}
+
+/// Probes the features of the CPU, then patches code paths accordingly.
+///
+/// This function must be called once at boot, before any other function of this module.
+pub fn init() {
+	// Safe because `cpuid` is available on every supported CPU
+	let max_leaf = unsafe { __cpuid(0) }.eax;
+	let leaf1 = unsafe { __cpuid(1) };
+	let leaf7 = (max_leaf >= 7).then(|| unsafe { __cpuid_count(7, 0) });
+
+	let mut present = 0;
+	for (i, f) in FEATURES.iter().enumerate() {
+		let (leaf, reg, bit) = f.location();
+		let res = match leaf {
+			1 => Some(leaf1),
+			7 => leaf7,
+			_ => None,
+		};
+		let Some(res) = res else {
+			continue;
+		};
+		let val = [res.eax, res.ebx, res.ecx, res.edx][reg as usize];
+		if val & (1 << bit) != 0 {
+			present |= 1 << i;
+		}
+	}
+	PRESENT.store(present, Relaxed);
+	PROBED.store(true, Relaxed);
+
+	if has(Feature::Erms) {
+		// Safe because no other CPU core is running and implementations have the same signature
+		unsafe {
+			memcpy_impl = memcpy_erms;
+			memset_impl = memset_erms;
+		}
+	}
+}
+
+/// Tells whether the CPU supports the feature `f`.
+pub fn has(f: Feature) -> bool {
+	debug_assert!(PROBED.load(Relaxed));
+	let i = FEATURES.iter().position(|f2| *f2 == f).unwrap();
+	PRESENT.load(Relaxed) & (1 << i) != 0
+}
+
+/// The value of [`Alternative`]'s index when no implementation has been selected yet.
+const UNSELECTED: usize = usize::MAX;
+
+/// A code path that has several implementations, each requiring a feature of the CPU.
+///
+/// The implementation is selected on first use, then reused. Thus, it must not be used before
+/// features are probed.
+pub struct Alternative<F: 'static + Copy> {
+	/// The list of implementations, by order of preference, with the feature they require.
+	///
+	/// The last implementation must require no feature.
+	candidates: &'static [(Option<Feature>, F)],
+	/// The index of the selected implementation.
+	selected: AtomicUsize,
+}
+
+impl<F: 'static + Copy> Alternative<F> {
+	/// Creates a new instance with the given implementations.
+	pub const fn new(candidates: &'static [(Option<Feature>, F)]) -> Self {
+		Self {
+			candidates,
+			selected: AtomicUsize::new(UNSELECTED),
+		}
+	}
+
+	/// Returns the implementation to use.
+	pub fn get(&self) -> F {
+		let mut i = self.selected.load(Relaxed);
+		if i == UNSELECTED {
+			i = self
+				.candidates
+				.iter()
+				.position(|(f, _)| f.map(has).unwrap_or(true))
+				.unwrap();
+			self.selected.store(i, Relaxed);
+		}
+		self.candidates[i].1
+	}
+}
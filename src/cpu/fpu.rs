@@ -0,0 +1,212 @@
+//! x87 FPU, MMX, SSE and AVX state management.
+//!
+//! The state is switched lazily: when a process is switched out, the Task Switched (TS) flag of
+//! `%cr0` is set, so that the next instruction using the FPU raises a Device Not Available (#NM)
+//! exception. Its handler then loads the state of the current process.
+//!
+//! If the CPU supports it, the state is saved with `xsave`, which covers AVX registers and skips
+//! components that are in their initial state. `xsaveopt` also skips components that have not
+//! been modified since they were loaded. Otherwise, `fxsave` is used.
+
+use super::features;
+use super::features::Feature;
+use crate::errno::AllocResult;
+use crate::memory;
+use crate::memory::buddy;
+use core::arch::asm;
+use core::arch::x86::__cpuid_count;
+use core::ffi::c_void;
+use core::ptr::NonNull;
+use core::slice;
+use core::sync::atomic::AtomicU8;
+use core::sync::atomic::Ordering::Relaxed;
+
+/// `%cr0` flag: Task Switched.
+const CR0_TS: u32 = 1 << 3;
+/// `%cr4` flag: enables `xsave` and `xrstor`.
+const CR4_OSXSAVE: u32 = 1 << 18;
+
+/// CPUID leaf 0xd, subleaf 1, `eax` flag: the CPU supports `xsaveopt`.
+const CPUID_XSAVEOPT: u32 = 1 << 0;
+
+/// State component: x87 FPU.
+const XCR0_X87: u32 = 1 << 0;
+/// State component: SSE.
+const XCR0_SSE: u32 = 1 << 1;
+/// State component: AVX.
+const XCR0_AVX: u32 = 1 << 2;
+
+/// The default value of the FCW.
+const DEFAULT_FCW: u16 = 0b1100111111;
+/// The default value of the MXCSR.
+const DEFAULT_MXCSR: u32 = 0b1111111000000;
+
+/// The offset of the FCW in the state.
+const FCW_OFF: usize = 0;
+/// The offset of the MXCSR in the state.
+const MXCSR_OFF: usize = 24;
+/// The offset of the `XSTATE_BV` field of the `xsave` header.
+const XSTATE_BV_OFF: usize = 512;
+
+/// The instruction used to save the state.
+#[derive(Clone, Copy, Eq, PartialEq)]
+#[repr(u8)]
+enum SaveMode {
+	/// `fxsave`/`fxrstor`.
+	Fx = 0,
+	/// `xsave`/`xrstor`.
+	Xsave = 1,
+	/// `xsaveopt`/`xrstor`.
+	XsaveOpt = 2,
+}
+
+/// The instruction used to save the state, as a [`SaveMode`].
+static SAVE_MODE: AtomicU8 = AtomicU8::new(SaveMode::Fx as _);
+/// The enabled state components, as `XCR0` flags.
+static XCR0: AtomicU8 = AtomicU8::new(0);
+
+/// Returns the instruction used to save the state.
+fn save_mode() -> SaveMode {
+	match SAVE_MODE.load(Relaxed) {
+		1 => SaveMode::Xsave,
+		2 => SaveMode::XsaveOpt,
+		_ => SaveMode::Fx,
+	}
+}
+
+/// Enables `xsave` if supported, then makes the next use of the FPU trap.
+///
+/// SSE must be enabled before calling this function.
+pub fn init() {
+	if features::has(Feature::Xsave) {
+		let mut xcr0 = XCR0_X87 | XCR0_SSE;
+		if features::has(Feature::Avx) {
+			xcr0 |= XCR0_AVX;
+		}
+
+		unsafe {
+			super::cr4_set(super::cr4_get() | CR4_OSXSAVE);
+			asm!("xsetbv", in("ecx") 0, in("eax") xcr0, in("edx") 0);
+		}
+
+		// The size of the state for the enabled components
+		let size = unsafe { __cpuid_count(0xd, 0) }.ebx as usize;
+		if size <= memory::PAGE_SIZE {
+			let opt = unsafe { __cpuid_count(0xd, 1) }.eax & CPUID_XSAVEOPT != 0;
+			let mode = if opt {
+				SaveMode::XsaveOpt
+			} else {
+				SaveMode::Xsave
+			};
+			XCR0.store(xcr0 as _, Relaxed);
+			SAVE_MODE.store(mode as _, Relaxed);
+		}
+	}
+
+	set_trap();
+}
+
+/// Tells whether the next use of the FPU raises an exception.
+pub fn is_trapping() -> bool {
+	unsafe { super::cr0_get() & CR0_TS != 0 }
+}
+
+/// Makes the next use of the FPU raise an exception.
+pub fn set_trap() {
+	unsafe {
+		super::cr0_set(CR0_TS);
+	}
+}
+
+/// Allows using the FPU without raising an exception.
+pub fn clear_trap() {
+	unsafe {
+		asm!("clts");
+	}
+}
+
+/// The saved FPU state of a process.
+///
+/// The state is stored in a dedicated page, which satisfies the alignment required by `xsave`.
+pub struct FpuState(NonNull<c_void>);
+
+impl FpuState {
+	/// Creates a state, with every component in its initial state.
+	pub fn new() -> AllocResult<Self> {
+		let mut s = Self(buddy::alloc_kernel(0)?);
+		s.reset();
+		Ok(s)
+	}
+
+	/// Returns the content of the state.
+	fn as_slice(&self) -> &[u8] {
+		unsafe { slice::from_raw_parts(self.0.as_ptr() as _, memory::PAGE_SIZE) }
+	}
+
+	/// Returns the content of the state.
+	fn as_slice_mut(&mut self) -> &mut [u8] {
+		unsafe { slice::from_raw_parts_mut(self.0.as_ptr() as _, memory::PAGE_SIZE) }
+	}
+
+	/// Puts every component in its initial state.
+	pub fn reset(&mut self) {
+		let buf = self.as_slice_mut();
+		buf.fill(0);
+		buf[FCW_OFF..(FCW_OFF + 2)].copy_from_slice(&DEFAULT_FCW.to_le_bytes());
+		buf[MXCSR_OFF..(MXCSR_OFF + 4)].copy_from_slice(&DEFAULT_MXCSR.to_le_bytes());
+		// Load the x87 and SSE components from the buffer. Other components are initialized
+		let xstate_bv = (XCR0_X87 | XCR0_SSE) as u64;
+		buf[XSTATE_BV_OFF..(XSTATE_BV_OFF + 8)].copy_from_slice(&xstate_bv.to_le_bytes());
+	}
+
+	/// Copies the state `other` into `self`.
+	pub fn copy_from(&mut self, other: &Self) {
+		self.as_slice_mut().copy_from_slice(other.as_slice());
+	}
+
+	/// Clones the state.
+	pub fn try_clone(&self) -> AllocResult<Self> {
+		let mut s = Self(buddy::alloc_kernel(0)?);
+		s.copy_from(self);
+		Ok(s)
+	}
+
+	/// Saves the state of the FPU into `self`.
+	///
+	/// The FPU must not be trapping.
+	pub fn save(&mut self) {
+		let ptr = self.0.as_ptr();
+		let mask = XCR0.load(Relaxed) as u32;
+		unsafe {
+			match save_mode() {
+				SaveMode::Fx => asm!("fxsave [{}]", in(reg) ptr),
+				SaveMode::Xsave => asm!("xsave [{}]", in(reg) ptr, in("eax") mask, in("edx") 0),
+				SaveMode::XsaveOpt => {
+					asm!("xsaveopt [{}]", in(reg) ptr, in("eax") mask, in("edx") 0)
+				}
+			}
+		}
+	}
+
+	/// Loads `self` into the FPU.
+	///
+	/// The FPU must not be trapping.
+	pub fn restore(&self) {
+		let ptr = self.0.as_ptr();
+		let mask = XCR0.load(Relaxed) as u32;
+		unsafe {
+			match save_mode() {
+				SaveMode::Fx => asm!("fxrstor [{}]", in(reg) ptr),
+				SaveMode::Xsave | SaveMode::XsaveOpt => {
+					asm!("xrstor [{}]", in(reg) ptr, in("eax") mask, in("edx") 0)
+				}
+			}
+		}
+	}
+}
+
+impl Drop for FpuState {
+	fn drop(&mut self) {
+		buddy::free_kernel(self.0.as_ptr(), 0);
+	}
+}
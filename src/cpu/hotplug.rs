@@ -0,0 +1,364 @@
+//! CPU hotplug allows to start and stop CPUs while the system is running.
+//!
+//! CPUs are numbered in the order they are listed by the MADT, except the boot CPU, which is
+//! always CPU `0`. The other CPUs are application processors (APs).
+//!
+//! An AP is started with an INIT IPI followed by Startup IPIs, which make it execute the
+//! trampoline (see `trampoline.s`) in real mode. The trampoline switches it to protected mode with
+//! the kernel's virtual memory, then calls [`ap_entry`].
+//!
+//! An online AP halts until it receives an IPI. To take it offline, its state is changed, then it
+//! is sent an IPI to wake it up. It then parks itself in a halt loop with interrupts disabled,
+//! where it stays until it is started again.
+//!
+//! APs are kept offline at boot, since the scheduler does not run processes on them yet.
+//!
+//! The boot CPU cannot be taken offline since it handles every interrupt.
+
+use crate::errno;
+use crate::errno::EResult;
+use crate::idt::apic;
+use crate::memory;
+use crate::memory::buddy;
+use crate::memory::buddy::FrameOrder;
+use crate::memory::vmem;
+use crate::process;
+use crate::time::hw::pit;
+use crate::util::lock::Mutex;
+use core::arch::asm;
+use core::ffi::c_void;
+use core::ptr;
+use core::sync::atomic::AtomicU8;
+use core::sync::atomic::Ordering::Acquire;
+use core::sync::atomic::Ordering::Release;
+
+/// The maximum number of CPUs that can be registered.
+pub const CPUS_MAX: usize = 32;
+
+/// The physical address the trampoline is copied to, which must be the same as in
+/// `trampoline.s`. This page of conventional memory is not used by the kernel.
+const TRAMPOLINE_ADDR: usize = 0x8000;
+/// The order of the stack of an AP.
+const STACK_ORDER: FrameOrder = 1;
+/// The maximum delay for an AP to change state, in milliseconds.
+const TIMEOUT: u32 = 100;
+
+/// `%cr0` flag: the FPU is not available until the flag is cleared.
+const CR0_TS: u32 = 1 << 3;
+
+/// CPU state: the CPU is stopped, or parked.
+const STATE_OFFLINE: u8 = 0;
+/// CPU state: the CPU has been sent the Startup IPIs and has not reached [`ap_entry`] yet.
+const STATE_STARTING: u8 = 1;
+/// CPU state: the CPU is running.
+const STATE_ONLINE: u8 = 2;
+/// CPU state: the CPU has been asked to park itself.
+const STATE_PARKING: u8 = 3;
+
+extern "C" {
+	static trampoline_begin: u8;
+	static trampoline_params: u8;
+	static trampoline_end: u8;
+}
+
+/// A pointer to a descriptor table, as loaded by the `lgdt` and `lidt` instructions.
+#[repr(C, packed)]
+#[derive(Clone, Copy, Default)]
+struct DescriptorTablePtr {
+	/// The size of the table in bytes, minus one.
+	limit: u16,
+	/// The address of the table.
+	base: u32,
+}
+
+/// The parameters of the trampoline. The layout must be the same as `trampoline_params` in
+/// `trampoline.s`.
+#[repr(C, packed)]
+struct TrampolineParams {
+	/// The GDT, at its physical address.
+	gdt_phys: DescriptorTablePtr,
+	/// The GDT, at its virtual address.
+	gdt_virt: DescriptorTablePtr,
+	/// The IDT.
+	idt: DescriptorTablePtr,
+	/// The value of `%cr0`, enabling paging.
+	cr0: u32,
+	/// The physical address of the kernel's page directory.
+	cr3: u32,
+	/// The value of `%cr4`.
+	cr4: u32,
+	/// The top of the stack of the CPU.
+	stack: u32,
+	/// The number of the CPU.
+	cpu: u32,
+}
+
+/// A registered CPU.
+#[derive(Clone, Copy)]
+struct Cpu {
+	/// The ID of the CPU's local APIC.
+	apic_id: u8,
+	/// The virtual address of the CPU's stack. Zero until the CPU is started for the first time.
+	stack: usize,
+}
+
+/// The registered CPUs, indexed by number.
+///
+/// The lock is held while a CPU is started or stopped so that operations do not overlap.
+static CPUS: Mutex<[Option<Cpu>; CPUS_MAX]> = Mutex::new([None; CPUS_MAX]);
+
+/// The initial state of a CPU.
+#[allow(clippy::declare_interior_mutable_const)]
+const STATE_INIT: AtomicU8 = AtomicU8::new(STATE_OFFLINE);
+/// The state of each CPU, indexed by number. APs use it to communicate with the CPU starting or
+/// stopping them, without taking locks.
+static STATES: [AtomicU8; CPUS_MAX] = [STATE_INIT; CPUS_MAX];
+
+/// Registers the CPU whose local APIC has ID `apic_id`.
+///
+/// If too many CPUs are registered, the CPU is ignored.
+pub fn add_cpu(apic_id: u8) {
+	let mut cpus = CPUS.lock();
+	if let Some(slot) = cpus.iter_mut().find(|c| c.is_none()) {
+		*slot = Some(Cpu {
+			apic_id,
+			stack: 0,
+		});
+	}
+}
+
+/// Makes the boot CPU the CPU `0`.
+///
+/// This function must be called once the CPUs are registered and the APIC is initialized.
+pub fn init() {
+	let mut cpus = CPUS.lock();
+	let Some(boot_apic_id) = apic::get_boot_apic_id() else {
+		// Without the local APIC, APs cannot be started
+		cpus.fill(None);
+		cpus[0] = Some(Cpu {
+			apic_id: 0,
+			stack: 0,
+		});
+		STATES[0].store(STATE_ONLINE, Release);
+		return;
+	};
+
+	let boot = cpus
+		.iter()
+		.position(|c| c.is_some_and(|c| c.apic_id == boot_apic_id));
+	match boot {
+		Some(i) => cpus[..=i].rotate_right(1),
+		// The MADT is missing or does not list the boot CPU
+		None => {
+			cpus.rotate_right(1);
+			cpus[0] = Some(Cpu {
+				apic_id: boot_apic_id,
+				stack: 0,
+			});
+		}
+	}
+	STATES[0].store(STATE_ONLINE, Release);
+
+	let count = cpus.iter().flatten().count();
+	crate::println!("{count} CPU(s) found");
+}
+
+/// Returns the number of registered CPUs.
+pub fn get_count() -> usize {
+	CPUS.lock().iter().flatten().count()
+}
+
+/// Tells whether the CPU `cpu` is online.
+pub fn is_online(cpu: usize) -> bool {
+	STATES
+		.get(cpu)
+		.is_some_and(|state| state.load(Acquire) == STATE_ONLINE)
+}
+
+/// Returns the mask of online CPUs, with one bit per CPU.
+pub fn get_online_mask() -> u32 {
+	(0..CPUS_MAX)
+		.filter(|cpu| is_online(*cpu))
+		.fold(0, |mask, cpu| mask | (1 << cpu))
+}
+
+/// Waits for the CPU `cpu` to reach the state `state`.
+///
+/// If the CPU does not reach the state within [`TIMEOUT`] milliseconds, the function returns
+/// [`errno::EIO`].
+fn wait_state(cpu: usize, state: u8) -> EResult<()> {
+	for _ in 0..TIMEOUT {
+		if STATES[cpu].load(Acquire) == state {
+			return Ok(());
+		}
+		pit::busy_wait(1);
+	}
+	if STATES[cpu].load(Acquire) == state {
+		return Ok(());
+	}
+	Err(errno!(EIO))
+}
+
+/// Writes the trampoline with its parameters to [`TRAMPOLINE_ADDR`], for the CPU `cpu` whose
+/// stack's top is at `stack`.
+///
+/// The function also maps the trampoline's page at its physical address in the kernel's virtual
+/// memory.
+fn setup_trampoline(cpu: usize, stack: usize) -> EResult<()> {
+	let mut gdt = DescriptorTablePtr::default();
+	let mut idt = DescriptorTablePtr::default();
+	unsafe {
+		asm!("sgdt [{}]", in(reg) &mut gdt);
+		asm!("sidt [{}]", in(reg) &mut idt);
+	}
+	let gdt_phys = DescriptorTablePtr {
+		limit: gdt.limit,
+		base: memory::kern_to_phys(gdt.base as *const c_void) as _,
+	};
+
+	let guard = crate::get_vmem().lock();
+	let kernel_vmem = guard.as_ref().unwrap();
+	// Safe since the stack is in kernelspace
+	let cr3 = unsafe { vmem::switch(&**kernel_vmem, || super::cr3_get()) };
+	let params = TrampolineParams {
+		gdt_phys,
+		gdt_virt: gdt,
+		idt,
+		// The FPU is not used by APs
+		cr0: unsafe { super::cr0_get() } & !CR0_TS,
+		cr3: cr3 as _,
+		cr4: unsafe { super::cr4_get() },
+		stack: stack as _,
+		cpu: cpu as _,
+	};
+
+	unsafe {
+		let begin = &trampoline_begin as *const u8;
+		let len = &trampoline_end as *const u8 as usize - begin as usize;
+		let params_off = &trampoline_params as *const u8 as usize - begin as usize;
+		let dest = memory::kern_to_virt(TRAMPOLINE_ADDR as *const u8) as *mut u8;
+		ptr::copy_nonoverlapping(begin, dest, len);
+		ptr::write_unaligned(dest.add(params_off) as *mut TrampolineParams, params);
+	}
+
+	let addr = TRAMPOLINE_ADDR as *const c_void;
+	kernel_vmem.map(addr, addr, 0)?;
+	Ok(())
+}
+
+/// Starts the AP `cpu`, then waits until it is online.
+fn start(cpu: usize, desc: &mut Cpu) -> EResult<()> {
+	if desc.stack == 0 {
+		desc.stack = buddy::alloc_kernel(STACK_ORDER)?.as_ptr() as usize;
+	}
+	setup_trampoline(cpu, desc.stack + buddy::get_frame_size(STACK_ORDER))?;
+
+	STATES[cpu].store(STATE_STARTING, Release);
+	let page = (TRAMPOLINE_ADDR / memory::PAGE_SIZE) as u8;
+	let res = apic::send_init(desc.apic_id).and_then(|_| {
+		pit::busy_wait(10);
+		// The Startup IPI is sent twice, as recommended by Intel
+		for _ in 0..2 {
+			apic::send_startup(desc.apic_id, page)?;
+			pit::busy_wait(1);
+		}
+		wait_state(cpu, STATE_ONLINE)
+	});
+	if res.is_err() {
+		// Stop the CPU in case it started anyway
+		let _ = apic::send_init(desc.apic_id);
+		STATES[cpu].store(STATE_OFFLINE, Release);
+	}
+
+	let guard = crate::get_vmem().lock();
+	let kernel_vmem = guard.as_ref().unwrap();
+	kernel_vmem.unmap(TRAMPOLINE_ADDR as *const c_void)?;
+	res
+}
+
+/// Tells whether processes or IRQs can be assigned to the CPU `cpu`.
+///
+/// Timers are not checked since they all use the boot CPU's local APIC timer.
+fn is_busy(cpu: usize) -> bool {
+	// The scheduler runs processes on every one of its cores
+	let cores_count = process::get_scheduler().lock().get_cpu_stats().len();
+	if cpu < cores_count {
+		return true;
+	}
+	(0..(apic::ISA_IRQS_COUNT as u8)).any(|irq| apic::get_irq_affinity(irq) & (1 << cpu) != 0)
+}
+
+/// Asks the AP `cpu`, described by `desc`, to park itself, then waits until it is parked.
+///
+/// Processes and IRQs are not migrated to other CPUs. Instead, if they can be assigned to the
+/// CPU, the function returns [`errno::EBUSY`].
+fn stop(cpu: usize, desc: &Cpu) -> EResult<()> {
+	// TODO Migrate processes and IRQs once they can be assigned to APs
+	if is_busy(cpu) {
+		return Err(errno!(EBUSY));
+	}
+	STATES[cpu].store(STATE_PARKING, Release);
+	// Wake the CPU up so that it notices the new state
+	let res = apic::send_wakeup(desc.apic_id).and_then(|_| wait_state(cpu, STATE_OFFLINE));
+	if res.is_err() {
+		// If the CPU parked in the meantime, the operation succeeded
+		let cancelled = STATES[cpu]
+			.compare_exchange(STATE_PARKING, STATE_ONLINE, Release, Acquire)
+			.is_ok();
+		if !cancelled {
+			return Ok(());
+		}
+	}
+	res
+}
+
+/// Sets whether the CPU `cpu` is online.
+///
+/// If the CPU does not exist, the function returns [`errno::ENODEV`]. If `online` is `false` and
+/// the CPU is the boot CPU, or processes or IRQs can be assigned to it, the function returns
+/// [`errno::EBUSY`]. If the CPU does not respond, the function returns [`errno::EIO`].
+pub fn set_online(cpu: usize, online: bool) -> EResult<()> {
+	let mut cpus = CPUS.lock();
+	let desc = cpus
+		.get_mut(cpu)
+		.and_then(Option::as_mut)
+		.ok_or_else(|| errno!(ENODEV))?;
+	if cpu == 0 {
+		return if online { Ok(()) } else { Err(errno!(EBUSY)) };
+	}
+	if online == is_online(cpu) {
+		return Ok(());
+	}
+	if online {
+		start(cpu, desc)
+	} else {
+		stop(cpu, desc)
+	}
+}
+
+/// Parks the current CPU until it receives an INIT IPI, with interrupts disabled.
+fn park() -> ! {
+	loop {
+		unsafe {
+			asm!("cli", "hlt");
+		}
+	}
+}
+
+/// The entry point of APs, called by the trampoline with the number of the CPU `cpu`.
+#[no_mangle]
+extern "C" fn ap_entry(cpu: u32) -> ! {
+	apic::init_ap();
+	let state = &STATES[cpu as usize];
+	state.store(STATE_ONLINE, Release);
+	// TODO Run processes once the scheduler handles several cores
+	while state.load(Acquire) == STATE_ONLINE {
+		// Halt until an IPI is received. `sti` takes effect after the next instruction, so an IPI
+		// sent after the state has been checked still wakes the CPU up from `hlt`
+		unsafe {
+			asm!("sti", "hlt", "cli");
+		}
+	}
+	state.store(STATE_OFFLINE, Release);
+	park()
+}
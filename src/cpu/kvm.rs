@@ -0,0 +1,116 @@
+//! Detection of the KVM hypervisor and paravirtual interfaces it offers to guests.
+//!
+//! When running under KVM, the hypervisor exposes its features through a range of `cpuid` leaves
+//! reserved for hypervisors. Each paravirtual interface is then enabled by writing the physical
+//! address of a shared structure to a model-specific register.
+
+use super::features;
+use super::features::Feature;
+use super::wrmsr;
+use crate::memory;
+use core::arch::x86::__cpuid;
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::AtomicU32;
+use core::sync::atomic::Ordering::Relaxed;
+
+/// The `cpuid` leaf returning the hypervisor's signature.
+const CPUID_SIGNATURE: u32 = 0x40000000;
+/// The `cpuid` leaf returning KVM's features.
+const CPUID_FEATURES: u32 = 0x40000001;
+/// The signature of KVM, in registers `ebx`, `ecx` and `edx`.
+const SIGNATURE: &[u8; 12] = b"KVMKVMKVM\0\0\0";
+
+/// Feature: the clock using [`MSR_SYSTEM_TIME_NEW`].
+pub const FEATURE_CLOCKSOURCE2: u32 = 1 << 3;
+/// Feature: asynchronous page faults.
+pub const FEATURE_ASYNC_PF: u32 = 1 << 4;
+/// Feature: paravirtual End-Of-Interrupt.
+pub const FEATURE_PV_EOI: u32 = 1 << 6;
+/// Feature: the clock is guaranteed to be monotonic across CPU cores when the structure has the
+/// stable flag.
+pub const FEATURE_CLOCKSOURCE_STABLE: u32 = 1 << 24;
+
+/// MSR enabling the paravirtual clock.
+pub const MSR_SYSTEM_TIME_NEW: u32 = 0x4b564d01;
+/// MSR enabling paravirtual End-Of-Interrupt.
+const MSR_PV_EOI_EN: u32 = 0x4b564d04;
+/// Flag enabling the interface associated with an MSR.
+pub const MSR_ENABLED: u64 = 1;
+
+/// Tells whether the kernel runs under KVM.
+static PRESENT: AtomicBool = AtomicBool::new(false);
+/// The bitfield of KVM features.
+static FEATURES: AtomicU32 = AtomicU32::new(0);
+
+/// The word shared with the hypervisor for paravirtual End-Of-Interrupt.
+///
+/// The hypervisor sets the bit `0` when injecting an interrupt whose acknowledgement can be
+/// skipped.
+static PV_EOI: AtomicU32 = AtomicU32::new(0);
+/// Tells whether paravirtual End-Of-Interrupt is enabled.
+static PV_EOI_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Detects KVM and its features.
+///
+/// This function must be called once at boot, after CPU features have been probed.
+pub fn init() {
+	if !features::has(Feature::Hypervisor) {
+		return;
+	}
+	// Safe because the hypervisor bit guarantees the leaves exist
+	let sig = unsafe { __cpuid(CPUID_SIGNATURE) };
+	let mut buf = [0; 12];
+	buf[0..4].copy_from_slice(&sig.ebx.to_le_bytes());
+	buf[4..8].copy_from_slice(&sig.ecx.to_le_bytes());
+	buf[8..12].copy_from_slice(&sig.edx.to_le_bytes());
+	if &buf != SIGNATURE || sig.eax < CPUID_FEATURES {
+		return;
+	}
+	let features = unsafe { __cpuid(CPUID_FEATURES) }.eax;
+	FEATURES.store(features, Relaxed);
+	PRESENT.store(true, Relaxed);
+}
+
+/// Tells whether the kernel runs under KVM.
+pub fn is_present() -> bool {
+	PRESENT.load(Relaxed)
+}
+
+/// Tells whether KVM offers all the features in the bitfield `features`.
+pub fn has(features: u32) -> bool {
+	is_present() && FEATURES.load(Relaxed) & features == features
+}
+
+/// Enables paravirtual End-Of-Interrupt, allowing to acknowledge most interrupts without exiting
+/// to the hypervisor.
+///
+/// This is useful only when interrupts are delivered by the local APIC.
+///
+/// If the feature is not available, the function does nothing.
+pub fn pv_eoi_enable() {
+	if !has(FEATURE_PV_EOI) {
+		return;
+	}
+	let phys_addr = memory::kern_to_phys(&PV_EOI as *const _) as u64;
+	// Safe because the feature is available and the word is 4 bytes aligned
+	unsafe {
+		wrmsr(MSR_PV_EOI_EN, phys_addr | MSR_ENABLED);
+	}
+	PV_EOI_ENABLED.store(true, Relaxed);
+}
+
+/// Acknowledges the current interrupt through paravirtual End-Of-Interrupt.
+///
+/// If the function returns `false`, the interrupt must be acknowledged to the local APIC as
+/// usual.
+pub fn pv_eoi_ack() -> bool {
+	PV_EOI_ENABLED.load(Relaxed) && PV_EOI.fetch_and(!1, Relaxed) & 1 != 0
+}
+
+// TODO Enable asynchronous page faults. When the host swaps out guest memory, it would notify the
+// guest instead of halting the vCPU, so that another process can run in the meantime. This is
+// not done yet because it requires:
+// - an interrupt vector for "page ready" notifications, while the IDT only has entries for
+// exceptions, ISA IRQs and system calls
+// - the page fault handler to be able to put the faulting process to sleep until the
+// notification for its token arrives
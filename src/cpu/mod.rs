@@ -1,13 +1,16 @@
 //! CPU-specific features.
 
+pub mod debug;
+pub mod features;
+pub mod fpu;
+pub mod hotplug;
+pub mod kvm;
 pub mod sse;
 
+use core::arch::asm;
 use core::ffi::c_void;
 
 extern "C" {
-	/// Tells whether the CPU has SSE.
-	fn cpuid_has_sse() -> bool;
-
 	/// Returns HWCAP bitmask for ELF.
 	pub fn get_hwcap() -> u32;
 
@@ -26,3 +29,13 @@ extern "C" {
 	/// Sets the content of the %cr4 register.
 	pub fn cr4_set(flags: u32);
 }
+
+/// Writes `val` to the model-specific register `msr`.
+///
+/// # Safety
+///
+/// Writing an MSR may change the behaviour of the CPU in any way. The caller must ensure the
+/// register exists and that the value is valid.
+pub unsafe fn wrmsr(msr: u32, val: u64) {
+	asm!("wrmsr", in("ecx") msr, in("eax") val as u32, in("edx") (val >> 32) as u32);
+}
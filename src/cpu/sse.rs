@@ -2,7 +2,7 @@
 
 /// Tells whether the CPU supports SSE.
 pub fn is_present() -> bool {
-	unsafe { super::cpuid_has_sse() }
+	super::features::has(super::features::Feature::Sse)
 }
 
 /// Enables SSE.
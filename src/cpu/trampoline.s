@@ -0,0 +1,113 @@
+/*
+ * This file implements the trampoline through which application processors start.
+ *
+ * A Startup IPI makes the processor start in real mode at the beginning of a page of conventional
+ * memory. The trampoline is copied to this page with its parameters, which are written by the
+ * boot processor. It switches the processor to protected mode, enables paging with the kernel's
+ * page directory, then calls `ap_entry` with the number of the processor.
+ *
+ * Since the trampoline runs at the address it is copied to, it must only refer to its own symbols
+ * relatively to `trampoline_begin`.
+ */
+
+/*
+ * The physical address the trampoline is copied to.
+ */
+.set TRAMPOLINE_ADDR,	0x8000
+
+.global trampoline_begin
+.global trampoline_params
+.global trampoline_end
+
+.extern ap_entry
+
+.section .text
+
+.code16
+trampoline_begin:
+	cli
+	cld
+	xor %ax, %ax
+	mov %ax, %ds
+
+	lgdtl (trampoline_gdt_phys - trampoline_begin + TRAMPOLINE_ADDR)
+	mov %cr0, %eax
+	or $1, %eax
+	mov %eax, %cr0
+
+	ljmpl $GDT_KERNEL_CS, $(trampoline_protected - trampoline_begin + TRAMPOLINE_ADDR)
+
+.code32
+trampoline_protected:
+	mov $GDT_KERNEL_DS, %ax
+	mov %ax, %ds
+	mov %ax, %es
+	mov %ax, %ss
+	xor %ax, %ax
+	mov %ax, %fs
+	mov %ax, %gs
+
+	/*
+	 * Enable paging. The page of the trampoline is identity mapped while the processor starts
+	 */
+	mov (trampoline_cr4 - trampoline_begin + TRAMPOLINE_ADDR), %eax
+	mov %eax, %cr4
+	mov (trampoline_cr3 - trampoline_begin + TRAMPOLINE_ADDR), %eax
+	mov %eax, %cr3
+	mov (trampoline_cr0 - trampoline_begin + TRAMPOLINE_ADDR), %eax
+	mov %eax, %cr0
+
+	lgdt (trampoline_gdt_virt - trampoline_begin + TRAMPOLINE_ADDR)
+	lidt (trampoline_idt - trampoline_begin + TRAMPOLINE_ADDR)
+
+	mov (trampoline_stack - trampoline_begin + TRAMPOLINE_ADDR), %esp
+	xor %ebp, %ebp
+	pushl (trampoline_cpu - trampoline_begin + TRAMPOLINE_ADDR)
+	mov $ap_entry, %eax
+	call *%eax
+	# `ap_entry` cannot return
+	ud2
+
+/*
+ * The parameters of the trampoline, written by the boot processor.
+ */
+.align 4
+trampoline_params:
+/*
+ * The GDT descriptor, with the physical address of the GDT.
+ */
+trampoline_gdt_phys:
+	.word 0
+	.long 0
+/*
+ * The GDT descriptor, with the virtual address of the GDT.
+ */
+trampoline_gdt_virt:
+	.word 0
+	.long 0
+/*
+ * The IDT descriptor.
+ */
+trampoline_idt:
+	.word 0
+	.long 0
+/*
+ * The values of the control registers.
+ */
+trampoline_cr0:
+	.long 0
+trampoline_cr3:
+	.long 0
+trampoline_cr4:
+	.long 0
+/*
+ * The top of the stack of the processor.
+ */
+trampoline_stack:
+	.long 0
+/*
+ * The number of the processor.
+ */
+trampoline_cpu:
+	.long 0
+trampoline_end:
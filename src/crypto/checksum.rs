@@ -1,6 +1,10 @@
 //! This module implements checksum algorithms. A checksum is a value allowing
 //! to verify the integrity of a structure.
 
+use crate::cpu::features::Alternative;
+use crate::cpu::features::Feature;
+use core::arch::asm;
+
 /// Computes a checksum on `data` according to RFC1071.
 pub fn compute_rfc1071(data: &[u8]) -> u16 {
 	let mut sum: u32 = 0;
@@ -64,6 +68,72 @@ pub fn compute_crc32(data: &[u8], table: &[u32; 256]) -> u32 {
 	!crc
 }
 
+/// The generator polynomial of CRC32C (Castagnoli), in reversed form.
+const CRC32C_POLYNOM: u32 = 0x82f63b78;
+
+/// Returns the lookup table of CRC32C.
+const fn crc32c_lookuptable() -> [u32; 256] {
+	let mut table = [0; 256];
+	let mut i = 0;
+	while i < table.len() {
+		let mut crc = i as u32;
+		let mut j = 0;
+		while j < 8 {
+			crc = if crc & 1 != 0 {
+				(crc >> 1) ^ CRC32C_POLYNOM
+			} else {
+				crc >> 1
+			};
+			j += 1;
+		}
+		table[i] = crc;
+		i += 1;
+	}
+	table
+}
+
+/// The lookup table of CRC32C.
+static CRC32C_TABLE: [u32; 256] = crc32c_lookuptable();
+
+/// Updates the CRC32C `crc` with `data`, using the lookup table.
+fn crc32c_sw(mut crc: u32, data: &[u8]) -> u32 {
+	for b in data {
+		let i = ((crc as usize) ^ (*b as usize)) & 0xff;
+		crc = CRC32C_TABLE[i] ^ (crc >> 8);
+	}
+	crc
+}
+
+/// Updates the CRC32C `crc` with `data`, using the `crc32` instruction of SSE4.2.
+fn crc32c_sse42(mut crc: u32, data: &[u8]) -> u32 {
+	let chunks = data.chunks_exact(4);
+	let remainder = chunks.remainder();
+	// Safe because the instruction is available when this implementation is selected
+	for c in chunks {
+		let val = u32::from_le_bytes([c[0], c[1], c[2], c[3]]);
+		unsafe {
+			asm!("crc32 {0:e}, {1:e}", inout(reg) crc, in(reg) val);
+		}
+	}
+	for b in remainder {
+		unsafe {
+			asm!("crc32 {0:e}, {1}", inout(reg) crc, in(reg_byte) *b);
+		}
+	}
+	crc
+}
+
+/// The implementation of CRC32C.
+static CRC32C: Alternative<fn(u32, &[u8]) -> u32> =
+	Alternative::new(&[(Some(Feature::Sse42), crc32c_sse42), (None, crc32c_sw)]);
+
+/// Computes the CRC32C (Castagnoli) checksum on the given data `data`.
+///
+/// If available, the function uses the instruction provided by the CPU.
+pub fn compute_crc32c(data: &[u8]) -> u32 {
+	!(CRC32C.get())(!0, data)
+}
+
 #[cfg(test)]
 mod test {
 	use super::*;
@@ -76,6 +146,13 @@ mod test {
 		}
 	}
 
+	#[test_case]
+	fn crc32c() {
+		assert_eq!(compute_crc32c(b""), 0);
+		assert_eq!(compute_crc32c(b"123456789"), 0xe3069283);
+		assert_eq!(!crc32c_sw(!0, b"123456789"), 0xe3069283);
+	}
+
 	// TODO More tests on RFC1071
 	// TODO Test CRC32
 }
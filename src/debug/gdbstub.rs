@@ -0,0 +1,616 @@
+//! A stub implementing the GDB remote serial protocol over the serial port, allowing to debug the
+//! kernel from another machine, or from the host of a virtual machine.
+//!
+//! The stub is enabled either by the `-kgdb` command line argument, in which case the kernel
+//! waits for the debugger early at boot, or by the `g` SysRq key. Once enabled, the kernel also
+//! breaks into the debugger when it panics or when the debugger sends an interrupt request.
+//!
+//! While the debugger is in control, interruptions are disabled and the serial port is polled.
+//! The stub does not use any lock that the rest of the kernel may hold, so that it works whatever
+//! state the kernel is in.
+
+use crate::cpu;
+use crate::device::serial;
+use crate::gdt;
+use crate::memory;
+use crate::memory::vmem;
+use crate::process::regs::Regs;
+use crate::util::lock::IntMutex;
+use core::arch::asm;
+use core::cmp::min;
+use core::ptr;
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering::Relaxed;
+
+/// The serial port used to communicate with the debugger.
+const PORT: u16 = serial::COM1;
+
+/// The character sent by the debugger to interrupt the execution of the kernel.
+pub const INTERRUPT_CHAR: u8 = 0x03;
+
+/// The maximum size of a packet's data, in bytes.
+const PACKET_SIZE: usize = 1024;
+/// The number of registers exchanged with the debugger, in the order defined by GDB for x86.
+const REGS_COUNT: usize = 16;
+/// The maximum number of software breakpoints.
+const BREAKPOINTS_COUNT: usize = 32;
+
+/// The opcode of the `int3` instruction.
+const INT3: u8 = 0xcc;
+/// Flag of `eflags` making the CPU raise a Debug exception after each instruction.
+const EFLAGS_TF: u32 = 1 << 8;
+
+/// The digits used to encode numbers in hexadecimal.
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+/// The stop reply sent to the debugger, reporting a `SIGTRAP`.
+const STOP_REPLY: &[u8] = b"S05";
+
+/// Tells whether the stub is enabled.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+/// Tells whether the debugger is in control, to avoid entering the stub recursively.
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+/// Tells whether the debugger requested to execute a single instruction.
+static STEPPING: AtomicBool = AtomicBool::new(false);
+
+/// Returns the value of the hexadecimal digit `c`.
+fn hex_digit(c: u8) -> Option<u8> {
+	match c {
+		b'0'..=b'9' => Some(c - b'0'),
+		b'a'..=b'f' => Some(c - b'a' + 10),
+		b'A'..=b'F' => Some(c - b'A' + 10),
+		_ => None,
+	}
+}
+
+/// Parses the hexadecimal number at the beginning of `s`.
+///
+/// The function returns the number and the rest of the string. If `s` does not begin with a
+/// hexadecimal digit or if the number overflows, the function returns `None`.
+fn parse_hex(s: &[u8]) -> Option<(usize, &[u8])> {
+	let len = s.iter().take_while(|c| hex_digit(**c).is_some()).count();
+	if len == 0 {
+		return None;
+	}
+	let n = s[..len].iter().try_fold(0usize, |n, c| {
+		n.checked_mul(16)?
+			.checked_add(hex_digit(*c).unwrap() as usize)
+	})?;
+	Some((n, &s[len..]))
+}
+
+/// Parses the byte encoded as two hexadecimal digits at the beginning of `s`.
+fn parse_byte(s: &[u8]) -> Option<u8> {
+	Some((hex_digit(*s.first()?)? << 4) | hex_digit(*s.get(1)?)?)
+}
+
+/// Parses the register value encoded as hexadecimal bytes in little-endian at the beginning of
+/// `s`.
+///
+/// The function returns the value and the rest of the string.
+fn parse_reg(s: &[u8]) -> Option<(u32, &[u8])> {
+	let mut bytes = [0; 4];
+	for (i, b) in bytes.iter_mut().enumerate() {
+		*b = parse_byte(s.get((i * 2)..)?)?;
+	}
+	Some((u32::from_le_bytes(bytes), &s[8..]))
+}
+
+/// Parses the arguments `addr,len` of a memory access.
+///
+/// The function returns the address, the length and the rest of the string.
+fn parse_mem_args(s: &[u8]) -> Option<(usize, usize, &[u8])> {
+	let (addr, s) = parse_hex(s)?;
+	let (len, s) = parse_hex(s.strip_prefix(b",")?)?;
+	Some((addr, len, s))
+}
+
+/// Computes the checksum of the data of a packet.
+fn checksum(data: &[u8]) -> u8 {
+	data.iter().fold(0, |sum, b| sum.wrapping_add(*b))
+}
+
+/// Tells whether the memory range of `len` bytes at address `addr` can be accessed without
+/// faulting.
+///
+/// Only kernelspace can be accessed since it is always mapped.
+fn is_accessible(addr: usize, len: usize) -> bool {
+	addr >= memory::PROCESS_END as usize && addr.checked_add(len).is_some()
+}
+
+/// Writes the byte `b` at address `addr`, even if the memory is read-only.
+///
+/// # Safety
+///
+/// The address must be accessible according to [`is_accessible`]. Overwriting memory in use is
+/// undefined.
+unsafe fn write_byte(addr: usize, b: u8) {
+	vmem::write_lock_wrap(|| ptr::write_volatile(addr as *mut u8, b));
+}
+
+/// Returns the value of the register `n` for a trap that occurred in kernelspace.
+///
+/// If the register does not exist, the function returns `None`.
+fn get_reg(regs: &Regs, n: usize) -> Option<u32> {
+	let val = match n {
+		0 => regs.eax,
+		1 => regs.ecx,
+		2 => regs.edx,
+		3 => regs.ebx,
+		4 => regs.esp,
+		5 => regs.ebp,
+		6 => regs.esi,
+		7 => regs.edi,
+		8 => regs.eip,
+		9 => regs.eflags,
+		10 => gdt::KERNEL_CS as _,
+		11..=13 => gdt::KERNEL_DS as _,
+		14 => regs.fs,
+		15 => regs.gs,
+		_ => return None,
+	};
+	Some(val)
+}
+
+/// Sets the value of the register `n` for a trap that occurred in kernelspace.
+///
+/// The stack pointer and segment registers cannot be changed, so writing them is ignored.
+///
+/// If the register does not exist, the function returns `false`.
+fn set_reg(regs: &mut Regs, n: usize, val: u32) -> bool {
+	match n {
+		0 => regs.eax = val,
+		1 => regs.ecx = val,
+		2 => regs.edx = val,
+		3 => regs.ebx = val,
+		5 => regs.ebp = val,
+		6 => regs.esi = val,
+		7 => regs.edi = val,
+		8 => regs.eip = val,
+		9 => regs.eflags = val,
+		4 | 10..=15 => {}
+		_ => return false,
+	}
+	true
+}
+
+/// Writes the byte `b` to the debugger.
+fn write(b: u8) {
+	serial::write_raw(PORT, b);
+}
+
+/// Waits for a byte from the debugger and returns it.
+fn read() -> u8 {
+	serial::read_raw(PORT)
+}
+
+/// Sends a packet with the given data to the debugger, until the debugger acknowledges it.
+fn send(data: &[u8]) {
+	let sum = checksum(data);
+	loop {
+		write(b'$');
+		data.iter().for_each(|b| write(*b));
+		write(b'#');
+		write(HEX_DIGITS[(sum >> 4) as usize]);
+		write(HEX_DIGITS[(sum & 0xf) as usize]);
+		loop {
+			match read() {
+				b'+' => return,
+				b'-' => break,
+				_ => {}
+			}
+		}
+	}
+}
+
+/// Waits for a valid packet from the debugger and acknowledges it.
+///
+/// The data of the packet is written to `buf` and the function returns its length.
+fn receive(buf: &mut [u8; PACKET_SIZE]) -> usize {
+	loop {
+		// Characters outside of packets, such as interrupt requests, are ignored
+		while read() != b'$' {}
+		let mut len = 0;
+		let mut overflow = false;
+		loop {
+			let c = read();
+			if c == b'#' {
+				break;
+			}
+			if len < buf.len() {
+				buf[len] = c;
+				len += 1;
+			} else {
+				overflow = true;
+			}
+		}
+		let sum = parse_byte(&[read(), read()]);
+		if !overflow && sum == Some(checksum(&buf[..len])) {
+			write(b'+');
+			return len;
+		}
+		write(b'-');
+	}
+}
+
+/// A reply being built to be sent to the debugger.
+struct Reply {
+	/// The buffer storing the data of the reply.
+	buf: [u8; PACKET_SIZE],
+	/// The length of the data.
+	len: usize,
+}
+
+impl Reply {
+	/// Clears the reply.
+	fn clear(&mut self) {
+		self.len = 0;
+	}
+
+	/// Returns the data of the reply.
+	fn as_slice(&self) -> &[u8] {
+		&self.buf[..self.len]
+	}
+
+	/// Appends `data` to the reply. Data that does not fit is dropped.
+	fn push(&mut self, data: &[u8]) {
+		let len = min(data.len(), self.buf.len() - self.len);
+		self.buf[self.len..(self.len + len)].copy_from_slice(&data[..len]);
+		self.len += len;
+	}
+
+	/// Appends the byte `b` encoded as two hexadecimal digits.
+	fn push_byte(&mut self, b: u8) {
+		self.push(&[HEX_DIGITS[(b >> 4) as usize], HEX_DIGITS[(b & 0xf) as usize]]);
+	}
+
+	/// Appends the value of a register encoded as hexadecimal bytes in little-endian.
+	fn push_reg(&mut self, val: u32) {
+		val.to_le_bytes().into_iter().for_each(|b| self.push_byte(b));
+	}
+}
+
+/// A software breakpoint.
+#[derive(Clone, Copy)]
+struct Breakpoint {
+	/// The address of the instruction.
+	addr: usize,
+	/// The byte replaced by the `int3` instruction.
+	orig: u8,
+}
+
+/// The set of software breakpoints.
+struct Breakpoints([Option<Breakpoint>; BREAKPOINTS_COUNT]);
+
+impl Breakpoints {
+	/// Tells whether a breakpoint is placed at address `addr`.
+	fn contains(&self, addr: usize) -> bool {
+		self.0.iter().flatten().any(|b| b.addr == addr)
+	}
+
+	/// Places a breakpoint at address `addr`.
+	///
+	/// If the address is not accessible or if no slot is left, the function returns `false`.
+	fn insert(&mut self, addr: usize) -> bool {
+		if !is_accessible(addr, 1) {
+			return false;
+		}
+		if self.contains(addr) {
+			return true;
+		}
+		let Some(slot) = self.0.iter_mut().find(|b| b.is_none()) else {
+			return false;
+		};
+		unsafe {
+			*slot = Some(Breakpoint {
+				addr,
+				orig: ptr::read_volatile(addr as *const u8),
+			});
+			write_byte(addr, INT3);
+		}
+		true
+	}
+
+	/// Removes the breakpoint at address `addr`.
+	///
+	/// If no breakpoint is placed at this address, the function returns `false`.
+	fn remove(&mut self, addr: usize) -> bool {
+		let slot = self
+			.0
+			.iter_mut()
+			.find(|b| matches!(b, Some(b) if b.addr == addr));
+		let Some(b) = slot.and_then(Option::take) else {
+			return false;
+		};
+		unsafe {
+			write_byte(b.addr, b.orig);
+		}
+		true
+	}
+
+	/// Removes all breakpoints.
+	fn clear(&mut self) {
+		for b in self.0.iter_mut().filter_map(Option::take) {
+			unsafe {
+				write_byte(b.addr, b.orig);
+			}
+		}
+	}
+}
+
+/// The action to perform after handling a command from the debugger.
+enum Action {
+	/// Sends the reply, then waits for the next command.
+	Reply,
+	/// Resumes execution. If `step` is `true`, only one instruction is executed.
+	Resume { step: bool },
+	/// Sends the reply if not empty, then resumes execution and forgets about the debugger.
+	Detach,
+}
+
+/// Handles the command `cmd`, writing the reply into `reply`.
+///
+/// An empty reply tells the debugger the command is not supported.
+fn handle(cmd: &[u8], reply: &mut Reply, bps: &mut Breakpoints, regs: &mut Regs) -> Action {
+	let Some((&c, args)) = cmd.split_first() else {
+		return Action::Reply;
+	};
+	match c {
+		b'?' => reply.push(STOP_REPLY),
+		b'g' => (0..REGS_COUNT).for_each(|n| reply.push_reg(get_reg(regs, n).unwrap())),
+		b'G' => {
+			let mut s = args;
+			for n in 0..REGS_COUNT {
+				let Some((val, rest)) = parse_reg(s) else {
+					break;
+				};
+				set_reg(regs, n, val);
+				s = rest;
+			}
+			reply.push(b"OK");
+		}
+		b'p' => match parse_hex(args).and_then(|(n, _)| get_reg(regs, n)) {
+			Some(val) => reply.push_reg(val),
+			None => reply.push(b"E01"),
+		},
+		b'P' => {
+			let res = parse_hex(args).and_then(|(n, s)| {
+				let (val, _) = parse_reg(s.strip_prefix(b"=")?)?;
+				Some(set_reg(regs, n, val))
+			});
+			match res {
+				Some(true) => reply.push(b"OK"),
+				_ => reply.push(b"E01"),
+			}
+		}
+		b'm' => match parse_mem_args(args) {
+			Some((addr, len, _)) if is_accessible(addr, len) => {
+				// The debugger handles replies shorter than requested
+				let len = min(len, PACKET_SIZE / 2);
+				for off in 0..len {
+					reply.push_byte(unsafe { ptr::read_volatile((addr + off) as *const u8) });
+				}
+			}
+			_ => reply.push(b"E14"),
+		},
+		b'M' => {
+			let res = parse_mem_args(args).and_then(|(addr, len, s)| {
+				let data = s.strip_prefix(b":")?;
+				(data.len() == len * 2 && is_accessible(addr, len)).then_some((addr, data))
+			});
+			let Some((addr, data)) = res else {
+				reply.push(b"E14");
+				return Action::Reply;
+			};
+			// Check all bytes before modifying memory
+			if data.chunks(2).any(|b| parse_byte(b).is_none()) {
+				reply.push(b"E01");
+				return Action::Reply;
+			}
+			for (off, b) in data.chunks(2).enumerate() {
+				unsafe {
+					write_byte(addr + off, parse_byte(b).unwrap());
+				}
+			}
+			reply.push(b"OK");
+		}
+		b'c' | b's' => {
+			if let Some((addr, _)) = parse_hex(args) {
+				regs.eip = addr as _;
+			}
+			return Action::Resume {
+				step: c == b's',
+			};
+		}
+		b'Z' | b'z' => {
+			// Only software breakpoints are supported
+			let Some(args) = args.strip_prefix(b"0,") else {
+				return Action::Reply;
+			};
+			let ok = parse_hex(args).is_some_and(|(addr, _)| {
+				if c == b'Z' {
+					bps.insert(addr)
+				} else {
+					bps.remove(addr)
+				}
+			});
+			if ok {
+				reply.push(b"OK");
+			} else {
+				reply.push(b"E01");
+			}
+		}
+		b'D' => {
+			bps.clear();
+			reply.push(b"OK");
+			return Action::Detach;
+		}
+		b'k' => {
+			bps.clear();
+			return Action::Detach;
+		}
+		// There is only one thread
+		b'H' | b'T' => reply.push(b"OK"),
+		b'q' => {
+			if args.starts_with(b"Supported") {
+				reply.push(b"PacketSize=");
+				reply.push_byte((PACKET_SIZE >> 8) as u8);
+				reply.push_byte(PACKET_SIZE as u8);
+			} else if args == b"Attached" {
+				reply.push(b"1");
+			}
+		}
+		_ => {}
+	}
+	Action::Reply
+}
+
+/// The state of the stub.
+struct Stub {
+	/// The buffer receiving the commands of the debugger.
+	input: [u8; PACKET_SIZE],
+	/// The reply to the current command.
+	reply: Reply,
+	/// Software breakpoints.
+	breakpoints: Breakpoints,
+	/// Tells whether a debugger is connected, waiting for the kernel to stop.
+	connected: bool,
+}
+
+impl Stub {
+	/// Gives control to the debugger until it resumes execution.
+	///
+	/// `regs` is the state of the registers at the time the kernel stopped, which the debugger
+	/// may modify.
+	///
+	/// The function returns `true` if the debugger requested to execute a single instruction.
+	fn run(&mut self, regs: &mut Regs) -> bool {
+		// A debugger that is not connected yet starts by asking why the kernel stopped
+		if self.connected {
+			send(STOP_REPLY);
+		}
+		loop {
+			let len = receive(&mut self.input);
+			self.connected = true;
+			self.reply.clear();
+			match handle(&self.input[..len], &mut self.reply, &mut self.breakpoints, regs) {
+				Action::Reply => send(self.reply.as_slice()),
+				Action::Resume {
+					step,
+				} => return step,
+				Action::Detach => {
+					if self.reply.len > 0 {
+						send(self.reply.as_slice());
+					}
+					self.connected = false;
+					return false;
+				}
+			}
+		}
+	}
+}
+
+/// The state of the stub.
+static STUB: IntMutex<Stub> = IntMutex::new(Stub {
+	input: [0; PACKET_SIZE],
+	reply: Reply {
+		buf: [0; PACKET_SIZE],
+		len: 0,
+	},
+	breakpoints: Breakpoints([None; BREAKPOINTS_COUNT]),
+	connected: false,
+});
+
+/// Tells whether the stub is enabled.
+pub fn is_enabled() -> bool {
+	ENABLED.load(Relaxed)
+}
+
+/// Breaks into the debugger.
+///
+/// If the stub is not enabled, or if the debugger is already in control, the function does
+/// nothing.
+pub fn breakpoint() {
+	if is_enabled() && !ACTIVE.load(Relaxed) {
+		unsafe {
+			asm!("int3");
+		}
+	}
+}
+
+/// Enables the stub, then breaks into the debugger, waiting for it to connect if necessary.
+///
+/// If no serial port is available, the function does nothing.
+pub fn enter() {
+	if serial::get(PORT).is_none() {
+		crate::println!("kgdb: no serial port available");
+		return;
+	}
+	if !ENABLED.swap(true, Relaxed) {
+		crate::println!("kgdb: waiting for the debugger on the serial port");
+	}
+	breakpoint();
+}
+
+/// Handles a Debug or Breakpoint exception raised in kernelspace.
+///
+/// Arguments:
+/// - `id` is the ID of the interrupt.
+/// - `regs` is the state of the registers at the time of the exception. The debugger may modify
+/// it.
+///
+/// If the exception is not meant for the debugger, the function returns `false`.
+pub fn handle_trap(id: u32, regs: &mut Regs) -> bool {
+	if !is_enabled() || ACTIVE.load(Relaxed) {
+		return false;
+	}
+	match id {
+		// Debug
+		0x01 => {
+			// Other Debug exceptions are watchpoints placed by processes
+			if !STEPPING.swap(false, Relaxed) {
+				return false;
+			}
+			cpu::debug::clear_status();
+		}
+		// Breakpoint
+		0x03 => {}
+		_ => return false,
+	}
+
+	ACTIVE.store(true, Relaxed);
+	let mut stub = STUB.lock();
+	// Resume at the instruction that has been replaced by the breakpoint, once it is removed
+	let addr = (regs.eip as usize).wrapping_sub(1);
+	if id == 0x03 && stub.breakpoints.contains(addr) {
+		regs.eip = addr as _;
+	}
+	let step = stub.run(regs);
+	drop(stub);
+	if step {
+		regs.eflags |= EFLAGS_TF;
+	} else {
+		regs.eflags &= !EFLAGS_TF;
+	}
+	STEPPING.store(step, Relaxed);
+	ACTIVE.store(false, Relaxed);
+	true
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test_case]
+	fn gdbstub_parse() {
+		assert_eq!(parse_hex(b"c0100000,4"), Some((0xc0100000, &b",4"[..])));
+		assert_eq!(parse_hex(b",4"), None);
+		assert_eq!(parse_hex(b"123456789"), None);
+		assert_eq!(
+			parse_mem_args(b"c0100000,4:deadbeef"),
+			Some((0xc0100000, 4, &b":deadbeef"[..]))
+		);
+		assert_eq!(parse_reg(b"78563412"), Some((0x12345678, &b""[..])));
+		assert_eq!(parse_reg(b"785634"), None);
+		assert_eq!(checksum(b"OK"), 0x9a);
+	}
+}
@@ -1,11 +1,85 @@
 //! Debugging tools for the kernel.
 
+pub mod gdbstub;
+
 use crate::elf;
+use crate::elf::ELF32Sym;
+use crate::errno;
+use crate::errno::EResult;
+use crate::file::perm::AccessProfile;
 use crate::memory;
 use crate::multiboot;
 use crate::util::DisplayableStr;
 use core::ffi::c_void;
 use core::ptr::null_mut;
+use core::sync::atomic::AtomicU32;
+use core::sync::atomic::Ordering::Relaxed;
+
+/// Restriction on the exposure of kernel addresses to userspace:
+/// - `0`: addresses are shown to everyone
+/// - `1`: addresses are shown to privileged users only
+/// - `2`: addresses are hidden from everyone
+static KPTR_RESTRICT: AtomicU32 = AtomicU32::new(0);
+
+/// Returns the current restriction on the exposure of kernel addresses to userspace.
+pub fn get_kptr_restrict() -> u32 {
+	KPTR_RESTRICT.load(Relaxed)
+}
+
+/// Sets the restriction on the exposure of kernel addresses to userspace.
+///
+/// If the value is invalid, the function returns [`errno::EINVAL`].
+pub fn set_kptr_restrict(val: u32) -> EResult<()> {
+	if val > 2 {
+		return Err(errno!(EINVAL));
+	}
+	KPTR_RESTRICT.store(val, Relaxed);
+	Ok(())
+}
+
+/// Tells whether kernel addresses can be shown to the agent with access profile `ap`.
+pub fn can_show_kernel_addr(ap: &AccessProfile) -> bool {
+	match get_kptr_restrict() {
+		0 => true,
+		1 => ap.is_privileged(),
+		_ => false,
+	}
+}
+
+/// Iterates over the symbols of the kernel, calling the given closure `f` with each symbol and
+/// its name. If `f` returns `false`, the iteration stops.
+pub fn foreach_symbol<F>(f: F)
+where
+	F: FnMut(&'static ELF32Sym, &'static [u8]) -> bool,
+{
+	let boot_info = multiboot::get_boot_info();
+	elf::foreach_kernel_symbol(
+		memory::kern_to_virt(boot_info.elf_sections),
+		boot_info.elf_num as usize,
+		boot_info.elf_shndx as usize,
+		boot_info.elf_entsize as usize,
+		f,
+	);
+}
+
+/// Returns the name of the kernel symbol containing the address `addr`, along with the offset of
+/// the address in the symbol.
+///
+/// If no named symbol contains the address, the function returns `None`.
+pub fn symbol_lookup(addr: *const c_void) -> Option<(&'static [u8], usize)> {
+	let boot_info = multiboot::get_boot_info();
+	let (sym, name) = elf::get_function_symbol(
+		memory::kern_to_virt(boot_info.elf_sections),
+		boot_info.elf_num as usize,
+		boot_info.elf_shndx as usize,
+		boot_info.elf_entsize as usize,
+		addr,
+	)?;
+	if name.is_empty() {
+		return None;
+	}
+	Some((name, addr as usize - sym.st_value as usize))
+}
 
 /// Fills the slice `stack` with the callstack starting at `frame`.
 ///
@@ -43,20 +117,16 @@ pub fn print_callstack(stack: &[*mut c_void]) {
 		return;
 	}
 
-	let boot_info = multiboot::get_boot_info();
 	for (i, pc) in stack.iter().enumerate() {
 		if pc.is_null() {
 			break;
 		}
 
-		let name = elf::get_function_name(
-			memory::kern_to_virt(boot_info.elf_sections),
-			boot_info.elf_num as usize,
-			boot_info.elf_shndx as usize,
-			boot_info.elf_entsize as usize,
-			*pc,
-		)
-		.unwrap_or(b"???");
-		crate::println!("{i}: {pc:p} -> {}", DisplayableStr(name));
+		match symbol_lookup(*pc) {
+			Some((name, off)) => {
+				crate::println!("{i}: {pc:p} -> {}+{off:#x}", DisplayableStr(name))
+			}
+			None => crate::println!("{i}: {pc:p} -> ???"),
+		}
 	}
 }
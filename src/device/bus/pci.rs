@@ -13,6 +13,7 @@
 use crate::device::bar::BARType;
 use crate::device::bar::BAR;
 use crate::device::driver;
+use crate::device::driver::DeferredProbes;
 use crate::device::manager;
 use crate::device::manager::PhysicalDevice;
 use crate::device::DeviceManager;
@@ -30,6 +31,9 @@ const CONFIG_ADDRESS_PORT: u16 = 0xcf8;
 /// The port used to retrieve the devices informations.
 const CONFIG_DATA_PORT: u16 = 0xcfc;
 
+/// Command register flag: the device can behave as a bus master.
+const COMMAND_BUS_MASTER: u16 = 1 << 2;
+
 /// Device class: Unclassified
 pub const CLASS_UNCLASSIFIED: u16 = 0x00;
 /// Device class: Mass Storage Controller
@@ -403,6 +407,11 @@ impl PCIDevice {
 		// Clear the Multi-Function flag
 		self.header_type & 0b01111111
 	}
+
+	/// Reads the current value of the command register from the device.
+	fn read_command(&self) -> u16 {
+		(read_long(self.bus, self.device, self.function, 1) & 0xffff) as _
+	}
 }
 
 impl PhysicalDevice for PCIDevice {
@@ -461,6 +470,17 @@ impl PhysicalDevice for PCIDevice {
 			None
 		}
 	}
+
+	fn enable_bus_master(&self) {
+		// The status register is left to zero since writing ones clears its bits
+		let command = self.command | COMMAND_BUS_MASTER;
+		write_long(self.bus, self.device, self.function, 1, command as _);
+	}
+
+	fn disable_bus_master(&self) {
+		let command = self.read_command() & !COMMAND_BUS_MASTER;
+		write_long(self.bus, self.device, self.function, 1, command as _);
+	}
 }
 
 /// This manager handles every devices connected to the PCI bus.
@@ -469,6 +489,10 @@ impl PhysicalDevice for PCIDevice {
 pub struct PCIManager {
 	/// The list of PCI devices.
 	devices: Vec<PCIDevice>,
+	/// The command registers of the devices, saved when the system is suspended.
+	saved_commands: Vec<u16>,
+	/// Deferred probes, identified by the index of the device in `devices`.
+	deferred: DeferredProbes<usize>,
 }
 
 impl PCIManager {
@@ -476,6 +500,8 @@ impl PCIManager {
 	pub fn new() -> Self {
 		Self {
 			devices: Vec::new(),
+			saved_commands: Vec::new(),
+			deferred: DeferredProbes::new(),
 		}
 	}
 
@@ -528,16 +554,41 @@ impl PCIManager {
 
 					// Registering the device
 					let dev = PCIDevice::new(bus, device, func, &data)?;
-					driver::on_plug(&dev);
+					let bound = driver::on_plug(&dev, self.devices.len(), &mut self.deferred);
 					manager::on_plug(&dev)?;
 					self.devices.push(dev)?;
+
+					// A device becoming available may be what deferred probes were waiting for
+					if bound {
+						self.probe_deferred();
+					}
 				}
 			}
 		}
 
+		self.probe_deferred();
+		for (i, driver) in self.deferred.iter() {
+			let dev = &self.devices[i];
+			crate::println!(
+				"{}: probe of device {:04x}:{:04x} is still deferred",
+				driver.lock().get_name(),
+				dev.vendor_id,
+				dev.device_id
+			);
+		}
+
 		Ok(())
 	}
 
+	/// Retries the probes that have been deferred by drivers.
+	pub fn probe_deferred(&mut self) {
+		if self.deferred.is_empty() {
+			return;
+		}
+		let devices = &self.devices;
+		self.deferred.retry(|i| devices.get(i).map(|dev| dev as &dyn PhysicalDevice));
+	}
+
 	/// Returns the list of PCI devices.
 	///
 	/// If the PCI hasn't been scanned, the function returns an empty vector.
@@ -555,4 +606,32 @@ impl DeviceManager for PCIManager {
 	fn on_unplug(&mut self, _dev: &dyn PhysicalDevice) -> Result<(), Errno> {
 		Ok(())
 	}
+
+	fn shutdown(&mut self) -> Result<(), Errno> {
+		for dev in self.devices.iter() {
+			dev.disable_bus_master();
+		}
+		Ok(())
+	}
+
+	fn suspend(&mut self) -> Result<(), Errno> {
+		let mut saved_commands = Vec::with_capacity(self.devices.len())?;
+		for dev in self.devices.iter() {
+			saved_commands.push(dev.read_command())?;
+		}
+		for dev in self.devices.iter() {
+			dev.disable_bus_master();
+		}
+		self.saved_commands = saved_commands;
+		Ok(())
+	}
+
+	fn resume(&mut self) -> Result<(), Errno> {
+		for (dev, command) in self.devices.iter().zip(self.saved_commands.iter()) {
+			// The status register is left to zero since writing ones clears its bits
+			write_long(dev.bus, dev.device, dev.function, 1, *command as _);
+		}
+		self.saved_commands.clear();
+		Ok(())
+	}
 }
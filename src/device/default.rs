@@ -12,18 +12,28 @@ use crate::errno;
 use crate::errno::EResult;
 use crate::errno::Errno;
 use crate::file::blocking::BlockHandler;
+use crate::file::blocking::PollTable;
 use crate::file::path::Path;
 use crate::logger::LOGGER;
+use crate::memory;
+use crate::memory::memmap;
+use crate::memory::reserved;
 use crate::process::mem_space::MemSpace;
 use crate::process::Process;
 use crate::syscall::ioctl;
+use crate::tty;
 use crate::util::io;
 use crate::util::io::IO;
 use crate::util::lock::IntMutex;
 use crate::util::ptr::arc::Arc;
+use core::cmp::max;
 use core::cmp::min;
 use core::ffi::c_void;
 use core::mem::ManuallyDrop;
+use core::ptr;
+use core::slice;
+use core::sync::atomic;
+use core::sync::atomic::AtomicBool;
 
 /// Structure representing a device which does nothing.
 #[derive(Default)]
@@ -97,6 +107,193 @@ impl IO for ZeroDeviceHandle {
 	}
 }
 
+/// Structure representing a device which gives null bytes and is always full.
+#[derive(Default)]
+pub struct FullDeviceHandle {}
+
+impl DeviceHandle for FullDeviceHandle {
+	fn ioctl(
+		&mut self,
+		_mem_space: Arc<IntMutex<MemSpace>>,
+		_request: ioctl::Request,
+		_argp: *const c_void,
+	) -> Result<u32, Errno> {
+		Err(errno!(ENOTTY))
+	}
+}
+
+impl IO for FullDeviceHandle {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, _offset: u64, buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+		buff.fill(0);
+		Ok((buff.len() as _, false))
+	}
+
+	fn write(&mut self, _offset: u64, _buff: &[u8]) -> Result<u64, Errno> {
+		Err(errno!(ENOSPC))
+	}
+
+	fn poll(&mut self, _mask: u32) -> Result<u32, Errno> {
+		Ok(io::POLLIN | io::POLLOUT)
+	}
+}
+
+/// Tells whether `/dev/mem` allows to access RAM. If not, only the first megabyte, the crash
+/// kernel region and regions that are not RAM (such as memory-mapped I/O) can be accessed.
+///
+/// This is set by the `-iomem` command line argument.
+pub static IOMEM_RELAXED: AtomicBool = AtomicBool::new(false);
+
+/// The end of the first megabyte of physical memory, which contains BIOS data and legacy devices
+/// memory, and can always be accessed through `/dev/mem`.
+const LOW_MEMORY_END: u64 = 0x100000;
+
+/// Checks that the current process is allowed to perform raw I/O.
+///
+/// If not, the function returns [`errno::EPERM`].
+fn check_raw_io() -> EResult<()> {
+	let proc_mutex = Process::current_assert();
+	let proc = proc_mutex.lock();
+	if !proc.access_profile.is_privileged() {
+		return Err(errno!(EPERM));
+	}
+	Ok(())
+}
+
+/// Device giving access to the physical memory.
+///
+/// Only the physical memory mapped in kernelspace can be accessed.
+#[derive(Default)]
+pub struct MemDeviceHandle {}
+
+impl MemDeviceHandle {
+	/// Checks access to the physical memory range starting at `offset` with size `len`, and
+	/// returns the pointer to the beginning of the range in virtual memory along with the
+	/// accessible size.
+	fn check_range(offset: u64, len: usize) -> EResult<(*mut u8, usize)> {
+		check_raw_io()?;
+
+		let limit = memory::get_kernelspace_size() as u64;
+		if offset >= limit {
+			return Ok((ptr::null_mut(), 0));
+		}
+		let len = min(len as u64, limit - offset);
+		let end = offset + len;
+		// The crash kernel region is meant to be accessed from userspace
+		let crashkernel = reserved::CRASHKERNEL.get().is_some_and(|r| r.contains(offset, end));
+		if !IOMEM_RELAXED.load(atomic::Ordering::Relaxed)
+			&& !crashkernel
+			&& end > LOW_MEMORY_END
+			&& memmap::is_ram(max(offset, LOW_MEMORY_END), end)
+		{
+			return Err(errno!(EPERM));
+		}
+
+		let ptr = memory::kern_to_virt(offset as usize as *const u8) as *mut u8;
+		Ok((ptr, len as _))
+	}
+}
+
+impl DeviceHandle for MemDeviceHandle {
+	fn ioctl(
+		&mut self,
+		_mem_space: Arc<IntMutex<MemSpace>>,
+		_request: ioctl::Request,
+		_argp: *const c_void,
+	) -> Result<u32, Errno> {
+		Err(errno!(ENOTTY))
+	}
+}
+
+impl IO for MemDeviceHandle {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, offset: u64, buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+		let (ptr, len) = Self::check_range(offset, buff.len())?;
+		if len == 0 {
+			return Ok((0, true));
+		}
+		// Safe because the range is mapped in kernelspace
+		let src = unsafe { slice::from_raw_parts(ptr, len) };
+		buff[..len].copy_from_slice(src);
+		Ok((len as _, false))
+	}
+
+	fn write(&mut self, offset: u64, buff: &[u8]) -> Result<u64, Errno> {
+		let (ptr, len) = Self::check_range(offset, buff.len())?;
+		if len == 0 {
+			return Err(errno!(ENOSPC));
+		}
+		// Safe because the range is mapped in kernelspace
+		let dst = unsafe { slice::from_raw_parts_mut(ptr, len) };
+		dst.copy_from_slice(&buff[..len]);
+		Ok(len as _)
+	}
+
+	fn poll(&mut self, _mask: u32) -> Result<u32, Errno> {
+		Ok(io::POLLIN | io::POLLOUT)
+	}
+}
+
+/// Device giving access to I/O ports. The offset in the file is the port number.
+#[derive(Default)]
+pub struct PortDeviceHandle {}
+
+impl DeviceHandle for PortDeviceHandle {
+	fn ioctl(
+		&mut self,
+		_mem_space: Arc<IntMutex<MemSpace>>,
+		_request: ioctl::Request,
+		_argp: *const c_void,
+	) -> Result<u32, Errno> {
+		Err(errno!(ENOTTY))
+	}
+}
+
+impl IO for PortDeviceHandle {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, offset: u64, buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+		check_raw_io()?;
+		let len = min(
+			buff.len() as u64,
+			(u16::MAX as u64 + 1).saturating_sub(offset),
+		) as usize;
+		for (i, b) in buff[..len].iter_mut().enumerate() {
+			*b = unsafe { crate::io::inb((offset as usize + i) as _) };
+		}
+		Ok((len as _, len == 0))
+	}
+
+	fn write(&mut self, offset: u64, buff: &[u8]) -> Result<u64, Errno> {
+		check_raw_io()?;
+		let len = min(
+			buff.len() as u64,
+			(u16::MAX as u64 + 1).saturating_sub(offset),
+		) as usize;
+		if len == 0 && !buff.is_empty() {
+			return Err(errno!(ENOSPC));
+		}
+		for (i, b) in buff[..len].iter().enumerate() {
+			unsafe {
+				crate::io::outb((offset as usize + i) as _, *b);
+			}
+		}
+		Ok(len as _)
+	}
+
+	fn poll(&mut self, _mask: u32) -> Result<u32, Errno> {
+		Ok(io::POLLIN | io::POLLOUT)
+	}
+}
+
 /// The random device allows to get random bytes.
 ///
 /// This device will block reading until enough entropy is available.
@@ -120,6 +317,15 @@ impl DeviceHandle for RandomDeviceHandle {
 	fn add_waiting_process(&mut self, proc: &mut Process, mask: u32) -> Result<(), Errno> {
 		self.block_handler.add_waiting_process(proc, mask)
 	}
+
+	fn register_poll(
+		&mut self,
+		_proc: &Process,
+		table: &PollTable,
+		mask: u32,
+	) -> Result<(), Errno> {
+		Ok(self.block_handler.register(table, mask)?)
+	}
 }
 
 impl IO for RandomDeviceHandle {
@@ -260,6 +466,19 @@ impl IO for KMsgDeviceHandle {
 pub(super) fn create() -> EResult<()> {
 	let _first_major = ManuallyDrop::new(id::alloc_major(DeviceType::Char, Some(1))?);
 
+	let mem_path = Path::from_str(b"/dev/mem", false)?;
+	let mem_device = Device::new(
+		DeviceID {
+			type_: DeviceType::Char,
+			major: 1,
+			minor: 1,
+		},
+		mem_path,
+		0o640,
+		MemDeviceHandle::default(),
+	)?;
+	device::register(mem_device)?;
+
 	let null_path = Path::from_str(b"/dev/null", false)?;
 	let null_device = Device::new(
 		DeviceID {
@@ -273,6 +492,19 @@ pub(super) fn create() -> EResult<()> {
 	)?;
 	device::register(null_device)?;
 
+	let port_path = Path::from_str(b"/dev/port", false)?;
+	let port_device = Device::new(
+		DeviceID {
+			type_: DeviceType::Char,
+			major: 1,
+			minor: 4,
+		},
+		port_path,
+		0o640,
+		PortDeviceHandle::default(),
+	)?;
+	device::register(port_device)?;
+
 	let zero_path = Path::from_str(b"/dev/zero", false)?;
 	let zero_device = Device::new(
 		DeviceID {
@@ -286,6 +518,19 @@ pub(super) fn create() -> EResult<()> {
 	)?;
 	device::register(zero_device)?;
 
+	let full_path = Path::from_str(b"/dev/full", false)?;
+	let full_device = Device::new(
+		DeviceID {
+			type_: DeviceType::Char,
+			major: 1,
+			minor: 7,
+		},
+		full_path,
+		0o666,
+		FullDeviceHandle::default(),
+	)?;
+	device::register(full_device)?;
+
 	let random_path = Path::from_str(b"/dev/random", false)?;
 	let random_device = Device::new(
 		DeviceID {
@@ -340,5 +585,26 @@ pub(super) fn create() -> EResult<()> {
 	)?;
 	device::register(current_tty_device)?;
 
+	// Virtual terminals
+	tty::init_vts()?;
+	let _fourth_major = ManuallyDrop::new(id::alloc_major(DeviceType::Char, Some(4))?);
+	for num in 1..=tty::VT_COUNT {
+		let Some(handle) = tty::vt_id(num).and_then(tty::get) else {
+			continue;
+		};
+		let path = crate::format!("/dev/tty{num}")?;
+		let vt_device = Device::new(
+			DeviceID {
+				type_: DeviceType::Char,
+				major: 4,
+				minor: num as _,
+			},
+			Path::from_str(path.as_bytes(), false)?,
+			0o620,
+			TTYDeviceHandle::new(Some(handle)),
+		)?;
+		device::register(vt_device)?;
+	}
+
 	Ok(())
 }
@@ -1,5 +1,9 @@
 //! A driver is a piece of software allowing to use a specific piece of
 //! hardware. Such a component is often located inside of a kernel module.
+//!
+//! When a driver cannot bind a device because a resource it requires (a bus, a clock, another
+//! device, ...) is not ready yet, it may defer the probe. The bus owning the device then keeps
+//! the probe in a [`DeferredProbes`] queue and retries it once other probes have succeeded.
 
 use crate::device::manager::PhysicalDevice;
 use crate::errno::AllocResult;
@@ -8,6 +12,17 @@ use crate::util::lock::Mutex;
 use crate::util::ptr::arc::Arc;
 use crate::util::ptr::arc::Weak;
 
+/// The result of a driver probing a device.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Probe {
+	/// The driver is not compatible with the device, or failed to initialize it.
+	Ignored,
+	/// The driver has been bound to the device.
+	Bound,
+	/// A resource required by the driver is not ready yet. The probe is to be retried later.
+	Deferred,
+}
+
 /// Trait representing a device driver.
 pub trait Driver {
 	/// Returns the name of the driver.
@@ -16,7 +31,7 @@ pub trait Driver {
 	/// Function called when a new device is plugged in.
 	///
 	/// If the driver is not compatible with the device, the function shall ignore it.
-	fn on_plug(&self, dev: &dyn PhysicalDevice);
+	fn on_plug(&self, dev: &dyn PhysicalDevice) -> Probe;
 
 	/// Function called when a device in unplugged.
 	fn on_unplug(&self, dev: &dyn PhysicalDevice);
@@ -55,16 +70,91 @@ pub fn get_by_name(name: &str) -> Option<Weak<Mutex<dyn Driver>>> {
 	None
 }
 
+/// A queue of deferred probes, for the devices owned by a bus.
+///
+/// `K` is the type of the key identifying a device on the bus.
+pub struct DeferredProbes<K> {
+	/// The deferred probes, with the key of the device and the driver to retry.
+	probes: Vec<(K, Arc<Mutex<dyn Driver>>)>,
+}
+
+impl<K: Copy> DeferredProbes<K> {
+	/// Creates an empty queue.
+	pub const fn new() -> Self {
+		Self {
+			probes: Vec::new(),
+		}
+	}
+
+	/// Tells whether the queue is empty.
+	pub fn is_empty(&self) -> bool {
+		self.probes.is_empty()
+	}
+
+	/// Adds a deferred probe of the device with key `key` by `driver`.
+	fn push(&mut self, key: K, driver: Arc<Mutex<dyn Driver>>) {
+		if let Err(e) = self.probes.push((key, driver.clone())) {
+			let driver = driver.lock();
+			crate::println!("{}: cannot defer probe: {e}", driver.get_name());
+		}
+	}
+
+	/// Retries deferred probes until none of them succeeds.
+	///
+	/// `get` returns the device with the given key. If the device has disappeared, the probe is
+	/// dropped.
+	pub fn retry<'d, F: Fn(K) -> Option<&'d dyn PhysicalDevice>>(&mut self, get: F) {
+		loop {
+			let probes = core::mem::take(&mut self.probes);
+			let mut progress = false;
+			for (key, driver) in probes {
+				let Some(dev) = get(key) else {
+					continue;
+				};
+				let res = driver.lock().on_plug(dev);
+				match res {
+					Probe::Ignored => {}
+					Probe::Bound => progress = true,
+					Probe::Deferred => self.push(key, driver),
+				}
+			}
+			if !progress || self.probes.is_empty() {
+				break;
+			}
+		}
+	}
+
+	/// Returns an iterator over the keys of the devices and the drivers of the deferred probes.
+	pub fn iter(&self) -> impl Iterator<Item = (K, &Arc<Mutex<dyn Driver>>)> + '_ {
+		self.probes.iter().map(|(key, driver)| (*key, driver))
+	}
+}
+
 /// Function that is called when a new device is plugged in.
 ///
-/// `dev` is the device that has been plugged in.
-pub fn on_plug(dev: &dyn PhysicalDevice) {
+/// Arguments:
+/// - `dev` is the device that has been plugged in.
+/// - `key` identifies the device on its bus.
+/// - `deferred` is the queue of the bus, on which deferred probes are inserted.
+///
+/// The function returns `true` if at least one driver has been bound to the device.
+pub fn on_plug<K: Copy>(
+	dev: &dyn PhysicalDevice,
+	key: K,
+	deferred: &mut DeferredProbes<K>,
+) -> bool {
 	let drivers = DRIVERS.lock();
 
+	let mut bound = false;
 	for i in 0..drivers.len() {
-		let manager = drivers[i].lock();
-		manager.on_plug(dev);
+		let res = drivers[i].lock().on_plug(dev);
+		match res {
+			Probe::Ignored => {}
+			Probe::Bound => bound = true,
+			Probe::Deferred => deferred.push(key, drivers[i].clone()),
+		}
 	}
+	bound
 }
 
 /// Function that is called when a device is plugged out.
@@ -0,0 +1,240 @@
+//! The hardware random number generators framework.
+//!
+//! Drivers for hardware sources of randomness register themselves with [`register`]. The source
+//! with the best quality is then used:
+//! - by the `/dev/hwrng` device, to give userspace direct access to hardware randomness
+//! - to periodically feed the kernel's entropy pool, at the rate given by [`FILL_RATE`]
+//!
+//! Feeding is performed from the clock's interrupt handler, so sources must never block.
+
+pub mod rdrand;
+pub mod virtio;
+
+use crate::crypto::rand;
+use crate::device;
+use crate::device::Device;
+use crate::device::DeviceHandle;
+use crate::device::DeviceID;
+use crate::device::DeviceType;
+use crate::errno;
+use crate::errno::AllocResult;
+use crate::errno::EResult;
+use crate::event;
+use crate::event::CallbackResult;
+use crate::file::path::Path;
+use crate::file::Mode;
+use crate::process::mem_space::MemSpace;
+use crate::syscall::ioctl;
+use crate::time::clock;
+use crate::time::clock::CLOCK_BOOTTIME;
+use crate::time::hw;
+use crate::time::unit::Timestamp;
+use crate::time::unit::TimestampScale;
+use crate::util::container::vec::Vec;
+use crate::util::io;
+use crate::util::io::IO;
+use crate::util::lock::IntMutex;
+use crate::util::ptr::arc::Arc;
+use core::cmp::min;
+use core::ffi::c_void;
+use core::mem::ManuallyDrop;
+use core::sync::atomic;
+use core::sync::atomic::AtomicU32;
+
+/// The major number of the `/dev/hwrng` device.
+const HWRNG_MAJOR: u32 = 10;
+/// The minor number of the `/dev/hwrng` device.
+const HWRNG_MINOR: u32 = 183;
+/// The mode of the device file of the `/dev/hwrng` device.
+const HWRNG_MODE: Mode = 0o600;
+
+/// The number of attempts to get data from a source before giving up on a read.
+const READ_ATTEMPTS: usize = 1000;
+/// The maximum number of bytes read from a source at once for `/dev/hwrng`. Sources are locked
+/// with interrupts disabled, so large reads are split.
+const READ_CHUNK: usize = 64;
+/// The maximum number of bytes fed to the entropy pool at once.
+const FILL_CHUNK: usize = 64;
+
+/// The default value of [`FILL_RATE`].
+pub const DEFAULT_FILL_RATE: u32 = 256;
+/// The number of bytes per second fed to the entropy pool. If zero, the pool is not fed.
+///
+/// This is set by the `-hwrng-rate` command line argument.
+pub static FILL_RATE: AtomicU32 = AtomicU32::new(DEFAULT_FILL_RATE);
+
+/// Trait representing a hardware random number generator.
+pub trait Rng {
+	/// Returns the name of the source.
+	fn get_name(&self) -> &str;
+	/// Returns the quality of the source, which is an estimation of the number of bits of entropy
+	/// per 1024 bits of data.
+	fn get_quality(&self) -> u16;
+
+	/// Reads random bytes into `buf`.
+	///
+	/// The function must not block. If no data is available, it returns zero.
+	///
+	/// On success, the function returns the number of bytes read.
+	fn read(&mut self, buf: &mut [u8]) -> EResult<usize>;
+}
+
+/// The list of registered sources, sorted by decreasing quality.
+static RNGS: IntMutex<Vec<Arc<IntMutex<dyn Rng>>>> = IntMutex::new(Vec::new());
+
+/// Registers the given source.
+pub fn register<R: 'static + Rng>(rng: R) -> AllocResult<()> {
+	crate::println!("hwrng: registered source `{}`", rng.get_name());
+	let quality = rng.get_quality();
+	let rng = Arc::new(IntMutex::new(rng))?;
+
+	let mut rngs = RNGS.lock();
+	let index = rngs
+		.iter()
+		.position(|r| r.lock().get_quality() < quality)
+		.unwrap_or(rngs.len());
+	rngs.insert(index, rng)
+}
+
+/// Returns the source with the best quality, if any.
+fn current() -> Option<Arc<IntMutex<dyn Rng>>> {
+	RNGS.lock().first().cloned()
+}
+
+/// Handle for the `/dev/hwrng` device.
+#[derive(Default)]
+struct HwRngDeviceHandle {}
+
+impl DeviceHandle for HwRngDeviceHandle {
+	fn ioctl(
+		&mut self,
+		_mem_space: Arc<IntMutex<MemSpace>>,
+		_request: ioctl::Request,
+		_argp: *const c_void,
+	) -> EResult<u32> {
+		Err(errno!(ENOTTY))
+	}
+}
+
+impl IO for HwRngDeviceHandle {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, _offset: u64, buff: &mut [u8]) -> EResult<(u64, bool)> {
+		if buff.is_empty() {
+			return Ok((0, false));
+		}
+		let rng = current().ok_or_else(|| errno!(ENODEV))?;
+		let mut total = 0;
+		let mut attempts = 0;
+		while total < buff.len() {
+			// The source is unlocked between chunks
+			let end = min(total + READ_CHUNK, buff.len());
+			let len = rng.lock().read(&mut buff[total..end])?;
+			if len > 0 {
+				total += len;
+				continue;
+			}
+			// Return what is available instead of waiting for more
+			if total > 0 {
+				break;
+			}
+			attempts += 1;
+			if attempts >= READ_ATTEMPTS {
+				return Err(errno!(EAGAIN));
+			}
+		}
+		Ok((total as _, false))
+	}
+
+	fn write(&mut self, _offset: u64, _buff: &[u8]) -> EResult<u64> {
+		Err(errno!(EINVAL))
+	}
+
+	fn poll(&mut self, _mask: u32) -> EResult<u32> {
+		Ok(io::POLLIN)
+	}
+}
+
+/// The state of the entropy pool feeder.
+struct Feeder {
+	/// The timestamp of the last feed, in milliseconds.
+	last: Timestamp,
+	/// The number of bytes to feed that could not be fed yet.
+	due: u64,
+}
+
+impl Feeder {
+	/// Feeds the entropy pool according to the time elapsed since the last feed.
+	fn tick(&mut self) {
+		let Ok(now) = clock::current_time(CLOCK_BOOTTIME, TimestampScale::Millisecond) else {
+			return;
+		};
+		let rate = FILL_RATE.load(atomic::Ordering::Relaxed) as u64;
+		self.due += now.saturating_sub(self.last) * rate / 1000;
+		self.last = now;
+		if self.due == 0 {
+			return;
+		}
+
+		let Some(rng) = current() else {
+			self.due = 0;
+			return;
+		};
+		let mut buf = [0u8; FILL_CHUNK];
+		let len = min(self.due, FILL_CHUNK as u64) as usize;
+		let Ok(len) = rng.lock().read(&mut buf[..len]) else {
+			return;
+		};
+		if len == 0 {
+			return;
+		}
+
+		let mut pool = rand::ENTROPY_POOL.lock();
+		if let Some(pool) = &mut *pool {
+			pool.write(&buf[..len]);
+		}
+		self.due -= len as u64;
+	}
+}
+
+/// Initializes the framework.
+///
+/// This function must be called before buses are scanned so that drivers can detect devices.
+pub(super) fn init() -> EResult<()> {
+	rdrand::init()?;
+	device::driver::register(virtio::VirtioRngDriver {})?;
+
+	// Periodically feed the entropy pool
+	let vector = {
+		let hw_clocks = hw::CLOCKS.lock();
+		hw_clocks
+			.get(b"rtc".as_slice())
+			.map(|rtc| rtc.get_interrupt_vector())
+	};
+	if let Some(vector) = vector {
+		let mut feeder = Feeder {
+			last: clock::current_time(CLOCK_BOOTTIME, TimestampScale::Millisecond)?,
+			due: 0,
+		};
+		let hook = event::register_callback(vector, move |_, _, _, _| {
+			feeder.tick();
+			CallbackResult::Continue
+		})?;
+		let _ = ManuallyDrop::new(hook);
+	}
+
+	let path = Path::from_str(b"/dev/hwrng", false)?;
+	let dev = Device::new(
+		DeviceID {
+			type_: DeviceType::Char,
+			major: HWRNG_MAJOR,
+			minor: HWRNG_MINOR,
+		},
+		path,
+		HWRNG_MODE,
+		HwRngDeviceHandle::default(),
+	)?;
+	device::register(dev)
+}
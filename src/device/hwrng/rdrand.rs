@@ -0,0 +1,61 @@
+//! Random number generator using the `rdrand` instruction, available on recent x86 CPUs.
+
+use super::Rng;
+use crate::cpu::features;
+use crate::cpu::features::Feature;
+use crate::errno::EResult;
+use core::arch::asm;
+
+/// The number of attempts to get a random value before giving up. Failures are rare and
+/// transient.
+const RETRIES: usize = 10;
+
+/// Returns a random value using `rdrand`, or `None` if the CPU could not provide one.
+fn rdrand() -> Option<u32> {
+	for _ in 0..RETRIES {
+		let val: u32;
+		let ok: u8;
+		// Safe because support for the instruction has been checked
+		unsafe {
+			asm!("rdrand {0}", "setc {1}", out(reg) val, out(reg_byte) ok);
+		}
+		if ok != 0 {
+			return Some(val);
+		}
+	}
+	None
+}
+
+/// The `rdrand` source.
+pub struct RdRand {}
+
+impl Rng for RdRand {
+	fn get_name(&self) -> &str {
+		"rdrand"
+	}
+
+	fn get_quality(&self) -> u16 {
+		// The output goes through a DRBG, making it less trustworthy than a true entropy source
+		512
+	}
+
+	fn read(&mut self, buf: &mut [u8]) -> EResult<usize> {
+		let mut len = 0;
+		for chunk in buf.chunks_mut(4) {
+			let Some(val) = rdrand() else {
+				break;
+			};
+			chunk.copy_from_slice(&val.to_ne_bytes()[..chunk.len()]);
+			len += chunk.len();
+		}
+		Ok(len)
+	}
+}
+
+/// Registers the source if supported by the CPU.
+pub(super) fn init() -> EResult<()> {
+	if features::has(Feature::Rdrand) {
+		super::register(RdRand {})?;
+	}
+	Ok(())
+}
@@ -0,0 +1,116 @@
+//! Driver for the virtio entropy device, using the legacy PCI interface.
+//!
+//! The device has a single virtqueue, on which the driver places buffers that the device fills
+//! with random bytes. The driver uses a single buffer and polls for its completion, so that
+//! reading never blocks.
+
+use super::Rng;
+use crate::device::bar::BAR;
+use crate::device::driver::Driver;
+use crate::device::driver::Probe;
+use crate::device::manager::PhysicalDevice;
+use crate::device::virtio;
+use crate::device::virtio::Virtqueue;
+use crate::errno::EResult;
+use core::cmp::min;
+
+/// The device ID of the transitional virtio entropy device.
+const DEVICE_ID: u16 = 0x1005;
+
+/// The size of the buffer filled by the device.
+const BUFFER_SIZE: usize = 64;
+
+/// The virtio entropy source.
+pub struct VirtioRng {
+	/// The BAR giving access to the device's registers.
+	bar: BAR,
+	/// The virtqueue, with a single buffer filled by the device.
+	queue: Virtqueue,
+
+	/// Tells whether a request is pending.
+	pending: bool,
+}
+
+impl VirtioRng {
+	/// Initializes the device.
+	fn new(bar: BAR) -> EResult<Self> {
+		// No feature is needed
+		virtio::init_device(&bar, 0);
+		let queue = Virtqueue::new(&bar, 0, 1, BUFFER_SIZE)?;
+		virtio::driver_ok(&bar);
+		Ok(Self {
+			bar,
+			queue,
+
+			pending: false,
+		})
+	}
+
+	/// Makes the buffer available to the device.
+	fn submit(&mut self) {
+		self.queue.push(0, BUFFER_SIZE, true);
+		self.pending = true;
+	}
+}
+
+impl Rng for VirtioRng {
+	fn get_name(&self) -> &str {
+		"virtio-rng"
+	}
+
+	fn get_quality(&self) -> u16 {
+		1000
+	}
+
+	fn read(&mut self, buf: &mut [u8]) -> EResult<usize> {
+		if !self.pending {
+			self.submit();
+		}
+		let Some((desc, len)) = self.queue.pop_used() else {
+			return Ok(0);
+		};
+		self.pending = false;
+
+		let len = min(len, buf.len());
+		buf[..len].copy_from_slice(&self.queue.buffer(desc)[..len]);
+		// Request more data right away so that it is ready for the next read
+		self.submit();
+		Ok(len)
+	}
+}
+
+impl Drop for VirtioRng {
+	fn drop(&mut self) {
+		virtio::reset(&self.bar);
+	}
+}
+
+/// The driver of the virtio entropy device.
+pub struct VirtioRngDriver {}
+
+impl Driver for VirtioRngDriver {
+	fn get_name(&self) -> &str {
+		"virtio-rng"
+	}
+
+	fn on_plug(&self, dev: &dyn PhysicalDevice) -> Probe {
+		if dev.get_vendor_id() != virtio::VENDOR_ID || dev.get_device_id() != DEVICE_ID {
+			return Probe::Ignored;
+		}
+		let Some(bar) = dev.get_bars().first().cloned().flatten() else {
+			return Probe::Ignored;
+		};
+		dev.enable_bus_master();
+
+		let res = VirtioRng::new(bar).and_then(|rng| Ok(super::register(rng)?));
+		match res {
+			Ok(()) => Probe::Bound,
+			Err(e) => {
+				crate::println!("virtio-rng: cannot initialize device: {e}");
+				Probe::Ignored
+			}
+		}
+	}
+
+	fn on_unplug(&self, _dev: &dyn PhysicalDevice) {}
+}
@@ -2,8 +2,72 @@
 
 use crate::device::manager::DeviceManager;
 use crate::device::manager::PhysicalDevice;
+use crate::errno;
+use crate::errno::EResult;
 use crate::errno::Errno;
+use crate::sysrq;
 use crate::tty;
+use crate::util::lock::IntMutex;
+use crate::vga;
+
+/// Keymap table: keys pressed without modifiers.
+pub const K_NORMTAB: u8 = 0;
+/// Keymap table: keys pressed with shift.
+pub const K_SHIFTTAB: u8 = 1;
+/// The number of keycodes in a keymap table.
+pub const NR_KEYS: usize = 128;
+
+/// Key type: a Latin-1 character.
+const KT_LATIN: u16 = 0x00;
+/// Key type: a Latin-1 letter, which is affected by caps lock.
+const KT_LETTER: u16 = 0x0b;
+/// Key types from this value on are Unicode characters.
+const KT_UNICODE: u16 = 0xf0;
+/// The value of a keymap entry that has not been set, in which case the default layout is used.
+pub const K_HOLE: u16 = 0x0200;
+
+/// The loaded keymap, as Linux keysyms, indexed by table then keycode.
+static KEYMAP: IntMutex<[[u16; NR_KEYS]; 2]> = IntMutex::new([[K_HOLE; NR_KEYS]; 2]);
+
+/// Returns the entry of the keymap at keycode `index` in the table `table`.
+///
+/// If the table or keycode doesn't exist, the function returns [`errno::EINVAL`].
+pub fn get_keymap_entry(table: u8, index: u8) -> EResult<u16> {
+	let keymap = KEYMAP.lock();
+	keymap
+		.get(table as usize)
+		.and_then(|t| t.get(index as usize))
+		.cloned()
+		.ok_or_else(|| errno!(EINVAL))
+}
+
+/// Sets the entry of the keymap at keycode `index` in the table `table` to `value`.
+///
+/// If the table or keycode doesn't exist, the function returns [`errno::EINVAL`].
+pub fn set_keymap_entry(table: u8, index: u8, value: u16) -> EResult<()> {
+	let mut keymap = KEYMAP.lock();
+	let entry = keymap
+		.get_mut(table as usize)
+		.and_then(|t| t.get_mut(index as usize))
+		.ok_or_else(|| errno!(EINVAL))?;
+	*entry = value;
+	Ok(())
+}
+
+/// Decodes the keysym `value`, returning the character and whether it is a letter.
+///
+/// If the keysym is not a character, the function returns `None`.
+fn decode_keysym(value: u16) -> Option<(char, bool)> {
+	match value >> 8 {
+		KT_LATIN => Some(((value & 0xff) as u8 as char, false)),
+		KT_LETTER => Some(((value & 0xff) as u8 as char, true)),
+		t if t >= KT_UNICODE => {
+			let c = char::from_u32((value ^ 0xf000) as _)?;
+			Some((c, c.is_alphabetic()))
+		}
+		_ => None,
+	}
+}
 
 /// Enumation of keyboard keys.
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -140,6 +204,37 @@ pub enum KeyboardKey {
 }
 
 impl KeyboardKey {
+	/// Returns the keycode of the key, as used by keymaps.
+	///
+	/// If the key cannot be mapped, the function returns `None`.
+	pub fn keycode(&self) -> Option<u8> {
+		match self {
+			Self::KeyF11 => Some(87),
+			Self::KeyF12 => Some(88),
+			k if (*k as u8) <= Self::KeyKeypadDot as u8 => Some(*k as u8 + 1),
+			_ => None,
+		}
+	}
+
+	/// Returns the character the key produces according to the loaded keymap.
+	///
+	/// Arguments:
+	/// - `shift` tells whether shift is pressed.
+	/// - `caps_lock` tells whether caps lock is enabled.
+	///
+	/// If the keymap has no character for the key, the function returns `None`.
+	pub fn get_keymap_char(&self, shift: bool, caps_lock: bool) -> Option<char> {
+		let keycode = self.keycode()? as usize;
+		let keymap = KEYMAP.lock();
+		let table = if shift { K_SHIFTTAB } else { K_NORMTAB } as usize;
+		let (c, letter) = decode_keysym(keymap[table][keycode])?;
+		if letter && caps_lock {
+			let (c, _) = decode_keysym(keymap[table ^ 1][keycode])?;
+			return Some(c);
+		}
+		Some(c)
+	}
+
 	// TODO Implement correctly with modifiers
 	/// Returns the TTY characters for the given current.
 	///
@@ -491,6 +586,8 @@ pub struct KeyboardManager {
 	right_alt: bool,
 	/// The right ctrl key state.
 	right_ctrl: bool,
+	/// The SysRq key state, which is the print screen key pressed along with alt.
+	sysrq: bool,
 
 	/// The number lock state.
 	number_lock: EnableKey,
@@ -510,6 +607,7 @@ impl KeyboardManager {
 			alt: false,
 			right_alt: false,
 			right_ctrl: false,
+			sysrq: false,
 
 			number_lock: EnableKey::new(),
 			caps_lock: EnableKey::new(),
@@ -541,6 +639,9 @@ impl KeyboardManager {
 			KeyboardKey::KeyLeftAlt => self.alt = action == KeyboardAction::Pressed,
 			KeyboardKey::KeyRightAlt => self.right_alt = action == KeyboardAction::Pressed,
 			KeyboardKey::KeyRightControl => self.right_ctrl = action == KeyboardAction::Pressed,
+			KeyboardKey::KeyPrintScreen => {
+				self.sysrq = (self.alt || self.right_alt) && action == KeyboardAction::Pressed
+			}
 
 			_ => {}
 		}
@@ -556,23 +657,46 @@ impl KeyboardManager {
 		}
 
 		if action == KeyboardAction::Pressed {
-			if self.ctrl && self.alt {
-				// TODO TTYs must be allocated first
-				// Switching TTY
-				let id = match key {
-					KeyboardKey::KeyF1 => Some(0),
-					KeyboardKey::KeyF2 => Some(1),
-					KeyboardKey::KeyF3 => Some(2),
-					KeyboardKey::KeyF4 => Some(3),
-					KeyboardKey::KeyF5 => Some(4),
-					KeyboardKey::KeyF6 => Some(5),
-					KeyboardKey::KeyF7 => Some(6),
-					KeyboardKey::KeyF8 => Some(7),
-					KeyboardKey::KeyF9 => Some(8),
-
-					_ => None,
-				};
+			let alt = self.alt || self.right_alt;
+			let shift = self.left_shift || self.right_shift;
+
+			// Magic SysRq command
+			if alt && self.sysrq && key != KeyboardKey::KeyPrintScreen {
+				if let Some(c) = key.get_keymap_char(false, false) {
+					if c.is_ascii() {
+						sysrq::handle(c as u8);
+					}
+				}
+				return;
+			}
+
+			// Switching virtual terminal
+			let vt = match key {
+				KeyboardKey::KeyF1 => Some(1),
+				KeyboardKey::KeyF2 => Some(2),
+				KeyboardKey::KeyF3 => Some(3),
+				KeyboardKey::KeyF4 => Some(4),
+				KeyboardKey::KeyF5 => Some(5),
+				KeyboardKey::KeyF6 => Some(6),
+				_ => None,
+			};
+			if let (true, Some(id)) = (alt, vt.and_then(tty::vt_id)) {
 				tty::switch(id);
+				return;
+			}
+
+			// Scrolling through the history
+			if shift && matches!(key, KeyboardKey::KeyPageUp | KeyboardKey::KeyPageDown) {
+				if let Some(tty_mutex) = tty::current() {
+					let mut tty = tty_mutex.lock();
+					let n = vga::HEIGHT as usize / 2;
+					if key == KeyboardKey::KeyPageUp {
+						tty.scroll_up(n);
+					} else {
+						tty.scroll_down(n);
+					}
+				}
+				return;
 			}
 
 			// Getting the tty
@@ -580,12 +704,18 @@ impl KeyboardManager {
 				let mut tty = tty_mutex.lock();
 
 				let ctrl = self.ctrl || self.right_ctrl;
-				let alt = self.alt || self.right_alt;
-				let shift = (self.left_shift || self.right_shift) != self.caps_lock.is_enabled();
+				let raw_shift = shift;
+				let shift = shift != self.caps_lock.is_enabled();
 
 				// Writing on TTY
 				// TODO Meta
-				if let Some(tty_chars) = key.get_tty_chars(shift, alt, ctrl, false) {
+				let keymap_char = (!ctrl && !alt)
+					.then(|| key.get_keymap_char(raw_shift, self.caps_lock.is_enabled()))
+					.flatten();
+				if let Some(c) = keymap_char {
+					let mut buf = [0; 4];
+					tty.input(c.encode_utf8(&mut buf).as_bytes());
+				} else if let Some(tty_chars) = key.get_tty_chars(shift, alt, ctrl, false) {
 					tty.input(tty_chars);
 				}
 			}
@@ -2,8 +2,10 @@
 //! device files.
 
 use crate::device::bar::BAR;
+use crate::errno::EResult;
 use crate::errno::Errno;
 use crate::util::container::hashmap::HashMap;
+use crate::util::container::vec::Vec;
 use crate::util::lock::Mutex;
 use crate::util::ptr::arc::Arc;
 use core::any::Any;
@@ -45,6 +47,15 @@ pub trait PhysicalDevice {
 	///
 	/// If the device doesn't use any, the function returns `None`.
 	fn get_interrupt_pin(&self) -> Option<u8>;
+
+	/// Allows the device to access the main memory by itself (DMA).
+	///
+	/// If not applicable, the function does nothing.
+	fn enable_bus_master(&self) {}
+	/// Forbids the device to access the main memory by itself (DMA).
+	///
+	/// If not applicable, the function does nothing.
+	fn disable_bus_master(&self) {}
 }
 
 /// Trait representing a structure managing the link between physical devices
@@ -55,6 +66,41 @@ pub trait DeviceManager: Any {
 
 	/// Function called when a device is plugged out.
 	fn on_unplug(&mut self, dev: &dyn PhysicalDevice) -> Result<(), Errno>;
+
+	/// Tells whether the manager depends on the manager with type `other`.
+	///
+	/// A manager is suspended and stopped before the managers it depends on, and resumed after
+	/// them. For example, the manager of a bus is a dependency of the managers of the devices
+	/// connected to it.
+	///
+	/// By default, the manager has no dependency.
+	fn depends_on(&self, _other: TypeId) -> bool {
+		false
+	}
+
+	/// Function called before the system powers off, to stop the managed devices.
+	///
+	/// By default, the function does nothing.
+	fn shutdown(&mut self) -> Result<(), Errno> {
+		Ok(())
+	}
+
+	/// Function called before the system is suspended.
+	///
+	/// The managed devices must not perform any DMA once the function returns.
+	///
+	/// By default, the function does nothing.
+	fn suspend(&mut self) -> Result<(), Errno> {
+		Ok(())
+	}
+
+	/// Function called when the system resumes from suspension, to restore the state the managed
+	/// devices had before [`DeviceManager::suspend`] was called.
+	///
+	/// By default, the function does nothing.
+	fn resume(&mut self) -> Result<(), Errno> {
+		Ok(())
+	}
 }
 
 /// The list of device managers.
@@ -105,3 +151,94 @@ pub fn on_unplug(dev: &dyn PhysicalDevice) -> Result<(), Errno> {
 
 	Ok(())
 }
+
+/// Returns the registered managers, sorted so that each manager comes after the managers it
+/// depends on.
+fn sorted_managers() -> EResult<Vec<Arc<Mutex<dyn DeviceManager>>>> {
+	let mut remaining = Vec::new();
+	{
+		let device_managers = DEVICE_MANAGERS.lock();
+		for (id, m) in device_managers.iter() {
+			remaining.push((*id, m.clone()))?;
+		}
+	}
+
+	let mut sorted = Vec::new();
+	while !remaining.is_empty() {
+		// Take a manager whose dependencies have all been sorted already. If there is a
+		// dependency cycle, the first remaining manager is taken
+		let i = remaining
+			.iter()
+			.position(|(_, m)| {
+				let m = m.lock();
+				remaining.iter().all(|(id, _)| !m.depends_on(*id))
+			})
+			.unwrap_or(0);
+		let (_, m) = remaining.remove(i);
+		sorted.push(m)?;
+	}
+	Ok(sorted)
+}
+
+/// Stops all devices before the system powers off.
+///
+/// Managers are stopped before their dependencies. Errors are logged and do not prevent the other
+/// managers from stopping their devices.
+pub fn shutdown() {
+	let managers = match sorted_managers() {
+		Ok(managers) => managers,
+		Err(e) => {
+			crate::println!("Cannot stop devices: {e}");
+			return;
+		}
+	};
+
+	for m in managers.iter().rev() {
+		let mut manager = m.lock();
+		if let Err(e) = manager.shutdown() {
+			crate::println!("Cannot stop devices: {e}");
+		}
+	}
+}
+
+/// Suspends all devices before the system is suspended.
+///
+/// Managers are suspended before their dependencies. If a manager fails, the managers that have
+/// already been suspended are resumed and the error is returned.
+pub fn suspend() -> EResult<()> {
+	let managers = sorted_managers()?;
+
+	for i in (0..managers.len()).rev() {
+		let res = managers[i].lock().suspend();
+		if let Err(e) = res {
+			for m in &managers[(i + 1)..] {
+				if let Err(e) = m.lock().resume() {
+					crate::println!("Cannot resume devices: {e}");
+				}
+			}
+			return Err(e);
+		}
+	}
+	Ok(())
+}
+
+/// Resumes all devices after the system has been suspended.
+///
+/// Managers are resumed after their dependencies. Errors are logged and do not prevent the other
+/// managers from resuming their devices.
+pub fn resume() {
+	let managers = match sorted_managers() {
+		Ok(managers) => managers,
+		Err(e) => {
+			crate::println!("Cannot resume devices: {e}");
+			return;
+		}
+	};
+
+	for m in managers.iter() {
+		let mut manager = m.lock();
+		if let Err(e) = manager.resume() {
+			crate::println!("Cannot resume devices: {e}");
+		}
+	}
+}
@@ -12,22 +12,28 @@
 //! created when devices are registered
 //! - **stage 2**: files management is initialized, device files can be created. When switching to
 //! that stage, the files of all device that are already registered are created
+//!
+//! Device files are created on the devtmpfs, which files management mounts on `/dev`.
 
 pub mod bar;
 pub mod bus;
 pub mod default;
 pub mod driver;
+pub mod hwrng;
 pub mod id;
 pub mod keyboard;
 pub mod manager;
 pub mod serial;
+pub mod sound;
 pub mod storage;
 pub mod tty;
+pub mod virtio;
 
 use crate::device::manager::DeviceManager;
 use crate::errno::EResult;
 use crate::errno::Errno;
 use crate::file;
+use crate::file::blocking::PollTable;
 use crate::file::path::Path;
 use crate::file::perm::AccessProfile;
 use crate::file::vfs;
@@ -128,6 +134,23 @@ pub trait DeviceHandle: IO {
 	fn add_waiting_process(&mut self, _proc: &mut Process, _mask: u32) -> Result<(), Errno> {
 		Ok(())
 	}
+
+	/// Registers the given poll table on the device.
+	///
+	/// Arguments:
+	/// - `proc` is the process waiting on the table.
+	/// - `table` is the table to register.
+	/// - `mask` is the mask of poll event to wait for.
+	///
+	/// If the device cannot block, the function does nothing.
+	fn register_poll(
+		&mut self,
+		_proc: &Process,
+		_table: &PollTable,
+		_mask: u32,
+	) -> Result<(), Errno> {
+		Ok(())
+	}
 }
 
 /// Structure representing a device, either a block device or a char device.
@@ -332,6 +355,14 @@ pub fn init() -> Result<(), Errno> {
 
 	let storage_manager = StorageManager::new()?;
 	manager::register(storage_manager)?;
+	storage::dm::init()?;
+	storage::loopdev::init()?;
+	storage::md::init()?;
+	hwrng::init()?;
+	sound::init()?;
+	virtio::init()?;
+	crate::net::tun::init()?;
+	serial::init()?;
 
 	bus::detect()?;
 
@@ -1,7 +1,16 @@
 //! This module implements Serial port communications.
 
+use crate::debug::gdbstub;
+use crate::errno::EResult;
+use crate::event;
+use crate::event::CallbackResult;
+use crate::idt;
 use crate::io;
+use crate::sysrq;
 use crate::util::lock::Mutex;
+use core::mem::ManuallyDrop;
+use core::sync::atomic;
+use core::sync::atomic::AtomicBool;
 
 /// The offset of COM1 registers.
 pub const COM1: u16 = 0x3f8;
@@ -71,6 +80,11 @@ const LINE_STATUS_TEMT: u8 = 0b1000000;
 /// word in the input buffer.
 const LINE_STATUS_IE: u8 = 0b10000000;
 
+/// The IRQ of COM1 and COM3.
+const COM1_IRQ: u8 = 4;
+/// The interrupt vector of COM1 and COM3.
+const COM1_VECTOR: u32 = 0x24;
+
 /// The UART's frequency.
 const UART_FREQUENCY: u32 = 115200; // TODO Replace by a rational number?
 
@@ -156,6 +170,76 @@ impl Serial {
 	}
 }
 
+/// Writes the byte `b` to the port whose registers are at offset `regs_off`, without locking it.
+///
+/// This function is meant for the kernel debugger, which must work whatever locks are held.
+pub fn write_raw(regs_off: u16, b: u8) {
+	while unsafe { io::inb(regs_off + LINE_STATUS_REG_OFF) } & LINE_STATUS_THRE == 0 {}
+	unsafe {
+		io::outb(regs_off + DATA_REG_OFF, b);
+	}
+}
+
+/// Waits for a byte on the port whose registers are at offset `regs_off` and returns it, without
+/// locking it.
+///
+/// This function is meant for the kernel debugger, which must work whatever locks are held.
+pub fn read_raw(regs_off: u16) -> u8 {
+	while unsafe { io::inb(regs_off + LINE_STATUS_REG_OFF) } & LINE_STATUS_DR == 0 {}
+	unsafe { io::inb(regs_off + DATA_REG_OFF) }
+}
+
+/// Tells whether a break has been received on COM1, making the next character a SysRq command.
+static SYSRQ_BREAK: AtomicBool = AtomicBool::new(false);
+
+/// Handles the data received on the port whose registers are at offset `regs_off`.
+///
+/// This function does not lock the port, since it is called from interrupt context while the
+/// port may be used for output.
+///
+/// Since serial input is not used otherwise, received data is discarded unless it follows a
+/// break, in which case it is handled as a SysRq command, or unless it is an interrupt request
+/// from the kernel debugger.
+fn receive(regs_off: u16) {
+	loop {
+		let status = unsafe { io::inb(regs_off + LINE_STATUS_REG_OFF) };
+		if status & (LINE_STATUS_DR | LINE_STATUS_BI) == 0 {
+			break;
+		}
+		// A break comes with a null character that must be read
+		let c = unsafe { io::inb(regs_off + DATA_REG_OFF) };
+		if status & LINE_STATUS_BI != 0 {
+			SYSRQ_BREAK.store(true, atomic::Ordering::Relaxed);
+		} else if SYSRQ_BREAK.swap(false, atomic::Ordering::Relaxed) {
+			sysrq::handle(c);
+		} else if c == gdbstub::INTERRUPT_CHAR {
+			gdbstub::breakpoint();
+		}
+	}
+}
+
+/// Enables input interrupts on COM1, to receive SysRq commands after a break.
+///
+/// If the port doesn't exist, the function does nothing.
+pub fn init() -> EResult<()> {
+	if get(COM1).is_none() {
+		return Ok(());
+	}
+	let hook = event::register_callback(COM1_VECTOR, |_, _, _, _| {
+		receive(COM1);
+		CallbackResult::Continue
+	})?;
+	let _ = ManuallyDrop::new(hook);
+	unsafe {
+		io::outb(
+			COM1 + INTERRUPT_REG_OFF,
+			INTERRUPT_DATA_AVAILABLE | INTERRUPT_ERROR,
+		);
+	}
+	idt::enable_irq(COM1_IRQ);
+	Ok(())
+}
+
 /// The list of serial ports.
 static mut PORTS: [Option<Mutex<Serial>>; 4] = [None, None, None, None];
 
@@ -0,0 +1,352 @@
+//! Driver for AC'97 sound cards, such as the Intel ICH audio controller.
+//!
+//! The controller has two sets of registers:
+//! - the Native Audio Mixer (NAM), controlling the codec: volume, sample rate, etc...
+//! - the Native Audio Bus Master (NABM), controlling DMA transfers
+//!
+//! Playback uses a ring of buffers described by a Buffer Descriptor List (BDL). The driver fills
+//! buffers and moves the Last Valid Index (LVI) forward while the controller plays them, moving
+//! the Current Index Value (CIV) forward. Interrupts are not used: the state of the ring is
+//! polled.
+
+use super::Pcm;
+use crate::device::bar::BAR;
+use crate::device::driver::Driver;
+use crate::device::driver::Probe;
+use crate::device::manager::PhysicalDevice;
+use crate::errno::EResult;
+use crate::memory;
+use crate::memory::buddy;
+use core::cmp::min;
+use core::ffi::c_void;
+use core::hint;
+use core::ptr;
+use core::ptr::NonNull;
+use core::sync::atomic;
+
+/// The PCI class of multimedia devices.
+const CLASS_MULTIMEDIA: u16 = 0x04;
+/// The PCI subclass of audio devices.
+const SUBCLASS_AUDIO: u16 = 0x01;
+
+/// NAM register: writing any value resets the codec.
+const NAM_RESET: usize = 0x00;
+/// NAM register: master volume.
+const NAM_MASTER_VOLUME: usize = 0x02;
+/// NAM register: PCM output volume.
+const NAM_PCM_OUT_VOLUME: usize = 0x18;
+/// NAM register: extended audio capabilities.
+const NAM_EXT_AUDIO_ID: usize = 0x28;
+/// NAM register: extended audio control.
+const NAM_EXT_AUDIO_CTRL: usize = 0x2a;
+/// NAM register: the sample rate of the front DAC.
+const NAM_FRONT_DAC_RATE: usize = 0x2c;
+
+/// Extended audio capability/control: Variable Rate Audio.
+const EXT_AUDIO_VRA: u16 = 1;
+
+/// NABM register: the physical address of the BDL of PCM output.
+const NABM_PO_BDBAR: usize = 0x10;
+/// NABM register: the index of the buffer being played.
+const NABM_PO_CIV: usize = 0x14;
+/// NABM register: the index of the last buffer to play.
+const NABM_PO_LVI: usize = 0x15;
+/// NABM register: the status of PCM output.
+const NABM_PO_SR: usize = 0x16;
+/// NABM register: the number of samples left to play in the current buffer.
+const NABM_PO_PICB: usize = 0x18;
+/// NABM register: the control of PCM output.
+const NABM_PO_CR: usize = 0x1b;
+/// NABM register: global control.
+const NABM_GLOB_CNT: usize = 0x2c;
+
+/// Status: the DMA controller is halted.
+const SR_DCH: u16 = 1 << 0;
+/// Status: the bits that are cleared by writing them.
+const SR_CLEAR: u16 = 0x1c;
+/// Control: run the DMA engine.
+const CR_RPBM: u8 = 1 << 0;
+/// Control: reset the registers of the DMA engine.
+const CR_RR: u8 = 1 << 1;
+/// Global control: leave the cold reset state.
+const GLOB_CNT_COLD_RESET: u32 = 1 << 1;
+
+/// Buffer descriptor flag: play silence once the buffer has been played if it is the last one.
+const BD_BUP: u16 = 1 << 14;
+
+/// The number of entries in the BDL.
+const BUFFERS_COUNT: usize = 32;
+/// The allocation order of a playback buffer.
+const BUFFER_ORDER: buddy::FrameOrder = 0;
+/// The size of a playback buffer in bytes.
+const BUFFER_SIZE: usize = memory::PAGE_SIZE;
+/// The number of attempts to wait for the reset of the DMA engine.
+const RESET_ATTEMPTS: usize = 1000;
+
+/// The sample rate used if the codec does not support variable rates.
+const FIXED_RATE: u32 = 48000;
+/// The minimum sample rate supported with variable rates.
+const MIN_RATE: u32 = 8000;
+
+/// An entry of the BDL.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct BufferDescriptor {
+	/// The physical address of the buffer.
+	addr: u32,
+	/// The number of 16 bits samples in the buffer.
+	samples: u16,
+	/// Flags.
+	flags: u16,
+}
+
+/// An AC'97 sound card.
+pub struct Ac97 {
+	/// The BAR giving access to mixer registers.
+	nam: BAR,
+	/// The BAR giving access to bus master registers.
+	nabm: BAR,
+	/// Tells whether the codec supports variable sample rates.
+	vra: bool,
+
+	/// The BDL.
+	bdl: NonNull<c_void>,
+	/// The playback buffers.
+	buffers: [NonNull<c_void>; BUFFERS_COUNT],
+
+	/// Tells whether the DMA engine has been started since the last reset.
+	started: bool,
+	/// The index of the buffer being filled.
+	write_index: usize,
+	/// The number of bytes written to the buffer being filled.
+	fill: usize,
+}
+
+impl Ac97 {
+	/// Initializes the card.
+	fn new(nam: BAR, nabm: BAR) -> EResult<Self> {
+		let bdl = buddy::alloc_kernel(0)?;
+		let mut buffers = [NonNull::dangling(); BUFFERS_COUNT];
+		for i in 0..BUFFERS_COUNT {
+			match buddy::alloc_kernel(BUFFER_ORDER) {
+				Ok(buf) => buffers[i] = buf,
+				Err(e) => {
+					for buf in &buffers[..i] {
+						buddy::free_kernel(buf.as_ptr(), BUFFER_ORDER);
+					}
+					buddy::free_kernel(bdl.as_ptr(), 0);
+					return Err(e.into());
+				}
+			}
+		}
+
+		// Bring the codec up
+		nabm.write::<u32>(NABM_GLOB_CNT, GLOB_CNT_COLD_RESET as _);
+		nam.write::<u16>(NAM_RESET, 0);
+		// Maximum volume, unmuted
+		nam.write::<u16>(NAM_MASTER_VOLUME, 0);
+		nam.write::<u16>(NAM_PCM_OUT_VOLUME, 0x0808);
+
+		let vra = nam.read::<u16>(NAM_EXT_AUDIO_ID) as u16 & EXT_AUDIO_VRA != 0;
+		if vra {
+			let ctrl = nam.read::<u16>(NAM_EXT_AUDIO_CTRL) as u16;
+			nam.write::<u16>(NAM_EXT_AUDIO_CTRL, (ctrl | EXT_AUDIO_VRA) as _);
+		}
+
+		let mut s = Self {
+			nam,
+			nabm,
+			vra,
+
+			bdl,
+			buffers,
+
+			started: false,
+			write_index: 0,
+			fill: 0,
+		};
+		s.reset();
+		Ok(s)
+	}
+
+	/// Returns a pointer to the descriptor at index `i` in the BDL.
+	fn descriptor(&self, i: usize) -> *mut BufferDescriptor {
+		(self.bdl.as_ptr() as *mut BufferDescriptor).wrapping_add(i)
+	}
+
+	/// Tells whether the DMA engine is halted.
+	fn is_halted(&self) -> bool {
+		!self.started || self.nabm.read::<u16>(NABM_PO_SR) as u16 & SR_DCH != 0
+	}
+
+	/// Returns the number of buffers that have been submitted to the controller and not
+	/// completely played yet.
+	fn queued_buffers(&self) -> usize {
+		if self.is_halted() {
+			return 0;
+		}
+		let civ = self.nabm.read::<u8>(NABM_PO_CIV) as usize;
+		let lvi = self.nabm.read::<u8>(NABM_PO_LVI) as usize;
+		(lvi + BUFFERS_COUNT - civ) % BUFFERS_COUNT + 1
+	}
+
+	/// Submits the buffer being filled to the controller.
+	fn submit(&mut self) {
+		let addr = memory::kern_to_phys(self.buffers[self.write_index].as_ptr()) as usize;
+		unsafe {
+			ptr::write_volatile(
+				self.descriptor(self.write_index),
+				BufferDescriptor {
+					addr: addr as _,
+					samples: (self.fill / 2) as _,
+					flags: BD_BUP,
+				},
+			);
+		}
+		atomic::fence(atomic::Ordering::SeqCst);
+
+		// If the engine is running but halted, updating the LVI resumes playback
+		self.nabm.write::<u8>(NABM_PO_LVI, self.write_index as _);
+		if !self.started {
+			self.nabm.write::<u8>(NABM_PO_CR, CR_RPBM as _);
+			self.started = true;
+		}
+
+		self.write_index = (self.write_index + 1) % BUFFERS_COUNT;
+		self.fill = 0;
+	}
+}
+
+impl Pcm for Ac97 {
+	fn get_name(&self) -> &str {
+		"ac97"
+	}
+
+	fn set_rate(&mut self, rate: u32) -> u32 {
+		if !self.vra {
+			return FIXED_RATE;
+		}
+		let rate = rate.clamp(MIN_RATE, FIXED_RATE);
+		self.nam.write::<u16>(NAM_FRONT_DAC_RATE, rate as _);
+		// The codec may round the value
+		self.nam.read::<u16>(NAM_FRONT_DAC_RATE) as _
+	}
+
+	fn get_buffer_size(&self) -> usize {
+		BUFFER_SIZE
+	}
+
+	fn get_buffers_count(&self) -> usize {
+		BUFFERS_COUNT
+	}
+
+	fn get_free_space(&self) -> usize {
+		let free_buffers = BUFFERS_COUNT - self.queued_buffers();
+		(free_buffers * BUFFER_SIZE).saturating_sub(self.fill)
+	}
+
+	fn get_pending(&self) -> usize {
+		let mut pending = self.fill;
+		if self.is_halted() {
+			return pending;
+		}
+
+		let civ = self.nabm.read::<u8>(NABM_PO_CIV) as usize;
+		pending += self.nabm.read::<u16>(NABM_PO_PICB) as usize * 2;
+		for i in 1..self.queued_buffers() {
+			let desc = unsafe { ptr::read_volatile(self.descriptor((civ + i) % BUFFERS_COUNT)) };
+			pending += desc.samples as usize * 2;
+		}
+		pending
+	}
+
+	fn write(&mut self, mut buf: &[u8]) {
+		while !buf.is_empty() {
+			let len = min(buf.len(), BUFFER_SIZE - self.fill);
+			unsafe {
+				let dst = (self.buffers[self.write_index].as_ptr() as *mut u8).add(self.fill);
+				ptr::copy_nonoverlapping(buf.as_ptr(), dst, len);
+			}
+			self.fill += len;
+			buf = &buf[len..];
+
+			if self.fill == BUFFER_SIZE {
+				self.submit();
+			}
+		}
+	}
+
+	fn flush(&mut self) {
+		if self.fill > 0 {
+			self.submit();
+		}
+	}
+
+	fn reset(&mut self) {
+		self.nabm.write::<u8>(NABM_PO_CR, CR_RR as _);
+		for _ in 0..RESET_ATTEMPTS {
+			if self.nabm.read::<u8>(NABM_PO_CR) as u8 & CR_RR == 0 {
+				break;
+			}
+			hint::spin_loop();
+		}
+
+		// The reset clears the address of the BDL
+		let bdl_phys = memory::kern_to_phys(self.bdl.as_ptr()) as usize;
+		self.nabm.write::<u32>(NABM_PO_BDBAR, bdl_phys as _);
+
+		self.started = false;
+		self.write_index = 0;
+		self.fill = 0;
+	}
+
+	fn tick(&mut self) {
+		self.nabm.write::<u16>(NABM_PO_SR, SR_CLEAR as _);
+		// Avoid holding back samples that do not fill a buffer when nothing else is playing
+		if self.is_halted() {
+			self.flush();
+		}
+	}
+}
+
+impl Drop for Ac97 {
+	fn drop(&mut self) {
+		// Reset the DMA engine so that it stops using the memory
+		self.nabm.write::<u8>(NABM_PO_CR, CR_RR as _);
+		for buf in &self.buffers {
+			buddy::free_kernel(buf.as_ptr(), BUFFER_ORDER);
+		}
+		buddy::free_kernel(self.bdl.as_ptr(), 0);
+	}
+}
+
+/// The driver of AC'97 sound cards.
+pub struct Ac97Driver {}
+
+impl Driver for Ac97Driver {
+	fn get_name(&self) -> &str {
+		"ac97"
+	}
+
+	fn on_plug(&self, dev: &dyn PhysicalDevice) -> Probe {
+		if dev.get_class() != CLASS_MULTIMEDIA || dev.get_subclass() != SUBCLASS_AUDIO {
+			return Probe::Ignored;
+		}
+		let bars = dev.get_bars();
+		let (Some(Some(nam)), Some(Some(nabm))) = (bars.first(), bars.get(1)) else {
+			return Probe::Ignored;
+		};
+		dev.enable_bus_master();
+
+		let res = Ac97::new(nam.clone(), nabm.clone()).and_then(super::register);
+		match res {
+			Ok(()) => Probe::Bound,
+			Err(e) => {
+				crate::println!("ac97: cannot initialize device: {e}");
+				Probe::Ignored
+			}
+		}
+	}
+
+	fn on_unplug(&self, _dev: &dyn PhysicalDevice) {}
+}
@@ -0,0 +1,438 @@
+//! The sound framework.
+//!
+//! Sound card drivers register a PCM playback interface with [`register`]. The first registered
+//! interface is exposed to userspace through the `/dev/dsp` device, which follows the OSS
+//! interface.
+//!
+//! Drivers always play signed 16 bits little-endian stereo samples. Other formats and numbers of
+//! channels requested by userspace are converted when writing.
+//!
+//! Drivers are updated from the clock's interrupt handler, which is also responsible for waking
+//! processes waiting for space in the playback buffers.
+
+pub mod ac97;
+
+use crate::device;
+use crate::device::Device;
+use crate::device::DeviceHandle;
+use crate::device::DeviceID;
+use crate::device::DeviceType;
+use crate::errno;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::event;
+use crate::event::CallbackResult;
+use crate::file::blocking::BlockHandler;
+use crate::file::blocking::PollTable;
+use crate::file::path::Path;
+use crate::file::Mode;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::mem_space::MemSpace;
+use crate::process::Process;
+use crate::syscall::ioctl;
+use crate::time::clock;
+use crate::time::clock::CLOCK_MONOTONIC;
+use crate::time::hw;
+use crate::time::unit::Timestamp;
+use crate::time::unit::TimestampScale;
+use crate::util::boxed::Box;
+use crate::util::io;
+use crate::util::io::IO;
+use crate::util::lock::IntMutex;
+use crate::util::ptr::arc::Arc;
+use core::cmp::min;
+use core::ffi::c_int;
+use core::ffi::c_void;
+use core::mem::ManuallyDrop;
+
+/// The major number of the `/dev/dsp` device.
+const DSP_MAJOR: u32 = 14;
+/// The minor number of the `/dev/dsp` device.
+const DSP_MINOR: u32 = 3;
+/// The mode of the device file of the `/dev/dsp` device.
+const DSP_MODE: Mode = 0o660;
+
+/// Sample format: unsigned 8 bits.
+const AFMT_U8: u32 = 0x08;
+/// Sample format: signed 16 bits little-endian.
+const AFMT_S16_LE: u32 = 0x10;
+
+/// The size of a frame in the format played by drivers, in bytes.
+const HW_FRAME_SIZE: usize = 4;
+/// The number of frames converted at once when writing.
+const CONVERT_FRAMES: usize = 64;
+/// The maximum duration without any sample being played while waiting for playback to end, in
+/// nanoseconds.
+const SYNC_TIMEOUT: Timestamp = 1_000_000_000;
+
+/// Structure describing the space available for playback, used by `SNDCTL_DSP_GETOSPACE`.
+#[repr(C)]
+struct AudioBufInfo {
+	/// The number of fragments that can be written without blocking.
+	fragments: c_int,
+	/// The total number of fragments.
+	fragstotal: c_int,
+	/// The size of a fragment in bytes.
+	fragsize: c_int,
+	/// The number of bytes that can be written without blocking.
+	bytes: c_int,
+}
+
+/// Trait representing the PCM playback interface of a sound card.
+///
+/// Sizes are given in bytes, in the format played by drivers.
+pub trait Pcm {
+	/// Returns the name of the sound card.
+	fn get_name(&self) -> &str;
+
+	/// Sets the sample rate in Hz to the closest value supported by the card, then returns it.
+	fn set_rate(&mut self, rate: u32) -> u32;
+	/// Returns the size of a playback buffer.
+	fn get_buffer_size(&self) -> usize;
+	/// Returns the number of playback buffers.
+	fn get_buffers_count(&self) -> usize;
+
+	/// Returns the number of bytes that can be written without blocking.
+	fn get_free_space(&self) -> usize;
+	/// Returns the number of bytes that have been written but not played yet.
+	fn get_pending(&self) -> usize;
+
+	/// Writes samples to the playback buffers.
+	///
+	/// The size of `buf` must not exceed the free space and must be a multiple of the size of a
+	/// frame.
+	fn write(&mut self, buf: &[u8]);
+	/// Starts playing the samples that have been written, even if they do not fill a buffer.
+	fn flush(&mut self);
+	/// Stops playback and discards pending samples.
+	fn reset(&mut self);
+
+	/// Called periodically to let the driver update its state.
+	fn tick(&mut self);
+}
+
+/// The state of the `/dev/dsp` device.
+struct Dsp {
+	/// The interface of the sound card.
+	pcm: Box<dyn Pcm>,
+
+	/// The format of samples written by userspace.
+	format: u32,
+	/// The number of channels of samples written by userspace.
+	channels: u32,
+
+	/// The handler of processes waiting for space in playback buffers.
+	block_handler: BlockHandler,
+}
+
+impl Dsp {
+	/// Returns the size of a frame written by userspace, in bytes.
+	fn frame_size(&self) -> usize {
+		let sample_size = match self.format {
+			AFMT_U8 => 1,
+			_ => 2,
+		};
+		sample_size * self.channels as usize
+	}
+
+	/// Converts a size in the format played by the driver to a size in the format written by
+	/// userspace.
+	fn to_user_size(&self, size: usize) -> usize {
+		size / HW_FRAME_SIZE * self.frame_size()
+	}
+
+	/// Converts the given frame written by userspace to the format played by the driver.
+	fn convert_frame(&self, frame: &[u8]) -> [u8; HW_FRAME_SIZE] {
+		let mut samples = [0i16; 2];
+		for (i, sample) in samples.iter_mut().enumerate() {
+			let channel = min(i, self.channels as usize - 1);
+			*sample = match self.format {
+				AFMT_U8 => ((frame[channel] as i16) - 128) << 8,
+				_ => i16::from_le_bytes([frame[channel * 2], frame[channel * 2 + 1]]),
+			};
+		}
+
+		let mut out = [0; HW_FRAME_SIZE];
+		out[..2].copy_from_slice(&samples[0].to_le_bytes());
+		out[2..].copy_from_slice(&samples[1].to_le_bytes());
+		out
+	}
+
+	/// Updates the state of the card and wakes processes waiting for space.
+	fn tick(&mut self) {
+		self.pcm.tick();
+		if self.pcm.get_free_space() > 0 {
+			self.block_handler.wake_processes(io::POLLOUT);
+		}
+	}
+}
+
+/// The `/dev/dsp` device, if a sound card is present.
+static DSP: IntMutex<Option<Dsp>> = IntMutex::new(None);
+
+/// Registers the given PCM interface.
+///
+/// If an interface is already registered, the new one is ignored.
+pub fn register<P: 'static + Pcm>(pcm: P) -> EResult<()> {
+	let mut dsp = DSP.lock();
+	if dsp.is_some() {
+		return Ok(());
+	}
+
+	crate::println!("sound: registered card `{}`", pcm.get_name());
+	let mut pcm = Box::new(pcm)?;
+	pcm.set_rate(48000);
+	*dsp = Some(Dsp {
+		pcm,
+
+		format: AFMT_S16_LE,
+		channels: 2,
+
+		block_handler: BlockHandler::new(),
+	});
+	drop(dsp);
+
+	let path = Path::from_str(b"/dev/dsp", false)?;
+	let dev = Device::new(
+		DeviceID {
+			type_: DeviceType::Char,
+			major: DSP_MAJOR,
+			minor: DSP_MINOR,
+		},
+		path,
+		DSP_MODE,
+		DspDeviceHandle::default(),
+	)?;
+	device::register(dev)
+}
+
+/// Reads the integer argument of an ioctl, passes it to `f`, then writes back the returned value.
+fn ioctl_int<F: FnOnce(c_int) -> c_int>(
+	mem_space: &IntMutex<MemSpace>,
+	argp: *const c_void,
+	f: F,
+) -> EResult<u32> {
+	let mut mem_space_guard = mem_space.lock();
+	let ptr: SyscallPtr<c_int> = (argp as usize).into();
+	let val = ptr
+		.get_mut(&mut mem_space_guard)?
+		.ok_or_else(|| errno!(EFAULT))?;
+	*val = f(*val);
+	Ok(0)
+}
+
+/// Waits until every sample that has been written is played, for the `SNDCTL_DSP_SYNC` ioctl.
+///
+/// The process sleeps between two ticks of the clock updating the card. If no sample is played
+/// for [`SYNC_TIMEOUT`], the card is considered stalled and the function returns
+/// [`errno::EIO`].
+fn sync() -> EResult<u32> {
+	let proc_mutex = Process::current_assert();
+	let table = PollTable::new(proc_mutex.lock().pid)?;
+	let mut last_pending = usize::MAX;
+	let mut deadline = 0;
+	loop {
+		let now = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Nanosecond)?;
+		{
+			let mut dsp = DSP.lock();
+			let dsp = dsp.as_mut().ok_or_else(|| errno!(ENODEV))?;
+			// Registering before checking so that no tick can be missed in between
+			dsp.block_handler.register(&table, io::POLLOUT)?;
+			dsp.pcm.flush();
+			dsp.tick();
+			let pending = dsp.pcm.get_pending();
+			if pending == 0 {
+				return Ok(0);
+			}
+			if pending < last_pending {
+				// Samples are being played
+				last_pending = pending;
+				deadline = now + SYNC_TIMEOUT;
+			} else if now >= deadline {
+				return Err(errno!(EIO));
+			}
+		}
+
+		if proc_mutex.lock().get_next_signal().is_some() {
+			return Err(errno!(ERESTARTSYS));
+		}
+		table.wait(&proc_mutex, Some(deadline))?;
+	}
+}
+
+/// Handle for the `/dev/dsp` device.
+#[derive(Default)]
+struct DspDeviceHandle {}
+
+impl DeviceHandle for DspDeviceHandle {
+	fn ioctl(
+		&mut self,
+		mem_space: Arc<IntMutex<MemSpace>>,
+		request: ioctl::Request,
+		argp: *const c_void,
+	) -> EResult<u32> {
+		if request.get_old_format() == ioctl::SNDCTL_DSP_SYNC {
+			return sync();
+		}
+
+		let mut dsp = DSP.lock();
+		let dsp = dsp.as_mut().ok_or_else(|| errno!(ENODEV))?;
+		match request.get_old_format() {
+			ioctl::SNDCTL_DSP_RESET => {
+				dsp.pcm.reset();
+				Ok(0)
+			}
+
+			ioctl::SNDCTL_DSP_SPEED => ioctl_int(&mem_space, argp, |rate| {
+				dsp.pcm.set_rate(rate.max(0) as _) as _
+			}),
+
+			ioctl::SNDCTL_DSP_STEREO => ioctl_int(&mem_space, argp, |stereo| {
+				dsp.channels = if stereo != 0 { 2 } else { 1 };
+				dsp.channels as c_int - 1
+			}),
+
+			ioctl::SNDCTL_DSP_GETBLKSIZE => ioctl_int(&mem_space, argp, |_| {
+				dsp.to_user_size(dsp.pcm.get_buffer_size()) as _
+			}),
+
+			ioctl::SNDCTL_DSP_SETFMT => ioctl_int(&mem_space, argp, |format| {
+				match format as u32 {
+					// Query the current format
+					0 => {}
+					AFMT_U8 | AFMT_S16_LE => dsp.format = format as _,
+					// Unsupported formats fall back to the native format
+					_ => dsp.format = AFMT_S16_LE,
+				}
+				dsp.format as _
+			}),
+
+			ioctl::SNDCTL_DSP_CHANNELS => ioctl_int(&mem_space, argp, |channels| {
+				dsp.channels = if channels == 1 { 1 } else { 2 };
+				dsp.channels as _
+			}),
+
+			ioctl::SNDCTL_DSP_POST => {
+				dsp.pcm.flush();
+				Ok(0)
+			}
+
+			ioctl::SNDCTL_DSP_GETFMTS => {
+				ioctl_int(&mem_space, argp, |_| (AFMT_U8 | AFMT_S16_LE) as _)
+			}
+
+			ioctl::SNDCTL_DSP_GETOSPACE => {
+				let free = dsp.pcm.get_free_space();
+				let buffer_size = dsp.pcm.get_buffer_size();
+
+				let mut mem_space_guard = mem_space.lock();
+				let info_ptr: SyscallPtr<AudioBufInfo> = (argp as usize).into();
+				let info = info_ptr
+					.get_mut(&mut mem_space_guard)?
+					.ok_or_else(|| errno!(EFAULT))?;
+				*info = AudioBufInfo {
+					fragments: (free / buffer_size) as _,
+					fragstotal: dsp.pcm.get_buffers_count() as _,
+					fragsize: dsp.to_user_size(buffer_size) as _,
+					bytes: dsp.to_user_size(free) as _,
+				};
+				Ok(0)
+			}
+
+			ioctl::SNDCTL_DSP_GETODELAY => ioctl_int(&mem_space, argp, |_| {
+				dsp.to_user_size(dsp.pcm.get_pending()) as _
+			}),
+
+			_ => Err(errno!(ENOTTY)),
+		}
+	}
+
+	fn add_waiting_process(&mut self, proc: &mut Process, mask: u32) -> Result<(), Errno> {
+		let mut dsp = DSP.lock();
+		let dsp = dsp.as_mut().ok_or_else(|| errno!(ENODEV))?;
+		dsp.block_handler.add_waiting_process(proc, mask)
+	}
+
+	fn register_poll(
+		&mut self,
+		_proc: &Process,
+		table: &PollTable,
+		mask: u32,
+	) -> Result<(), Errno> {
+		let mut dsp = DSP.lock();
+		let dsp = dsp.as_mut().ok_or_else(|| errno!(ENODEV))?;
+		Ok(dsp.block_handler.register(table, mask)?)
+	}
+}
+
+impl IO for DspDeviceHandle {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, _offset: u64, _buff: &mut [u8]) -> EResult<(u64, bool)> {
+		Err(errno!(EINVAL))
+	}
+
+	fn write(&mut self, _offset: u64, buff: &[u8]) -> EResult<u64> {
+		let mut dsp = DSP.lock();
+		let dsp = dsp.as_mut().ok_or_else(|| errno!(ENODEV))?;
+		let frame_size = dsp.frame_size();
+		if buff.len() < frame_size {
+			return Err(errno!(EINVAL));
+		}
+
+		// Only whole frames that fit in the free space are written
+		let frames = min(
+			buff.len() / frame_size,
+			dsp.pcm.get_free_space() / HW_FRAME_SIZE,
+		);
+		let mut converted = [0u8; CONVERT_FRAMES * HW_FRAME_SIZE];
+		for chunk in buff[..frames * frame_size].chunks(CONVERT_FRAMES * frame_size) {
+			let mut len = 0;
+			for frame in chunk.chunks(frame_size) {
+				converted[len..(len + HW_FRAME_SIZE)].copy_from_slice(&dsp.convert_frame(frame));
+				len += HW_FRAME_SIZE;
+			}
+			dsp.pcm.write(&converted[..len]);
+		}
+
+		Ok((frames * frame_size) as _)
+	}
+
+	fn poll(&mut self, mask: u32) -> EResult<u32> {
+		let dsp = DSP.lock();
+		let dsp = dsp.as_ref().ok_or_else(|| errno!(ENODEV))?;
+		let mut res = 0;
+		if mask & io::POLLOUT != 0 && dsp.pcm.get_free_space() > 0 {
+			res |= io::POLLOUT;
+		}
+		Ok(res)
+	}
+}
+
+/// Initializes the framework.
+///
+/// This function must be called before buses are scanned so that drivers can detect devices.
+pub(super) fn init() -> EResult<()> {
+	device::driver::register(ac97::Ac97Driver {})?;
+
+	// Periodically update the sound card
+	let vector = {
+		let hw_clocks = hw::CLOCKS.lock();
+		hw_clocks
+			.get(b"rtc".as_slice())
+			.map(|rtc| rtc.get_interrupt_vector())
+	};
+	if let Some(vector) = vector {
+		let hook = event::register_callback(vector, |_, _, _, _| {
+			if let Some(dsp) = &mut *DSP.lock() {
+				dsp.tick();
+			}
+			CallbackResult::Continue
+		})?;
+		let _ = ManuallyDrop::new(hook);
+	}
+
+	Ok(())
+}
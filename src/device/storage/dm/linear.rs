@@ -0,0 +1,43 @@
+//! The `linear` target maps its range of sectors onto a contiguous range of sectors of another
+//! block device.
+//!
+//! Arguments: `<device> <offset>`, where `offset` is the first sector of the range on the
+//! underlying device.
+
+use super::Target;
+use crate::device::Device;
+use crate::errno;
+use crate::errno::EResult;
+use crate::util::lock::Mutex;
+use crate::util::ptr::arc::Arc;
+
+/// The `linear` target.
+pub struct Linear {
+	/// The underlying device.
+	dev: Arc<Mutex<Device>>,
+	/// The offset of the first sector on the underlying device.
+	start: u64,
+}
+
+impl Linear {
+	/// Creates a new instance from the given table arguments.
+	pub fn new(args: &[&[u8]]) -> EResult<Self> {
+		let [dev, start] = args else {
+			return Err(errno!(EINVAL));
+		};
+		Ok(Self {
+			dev: super::get_device(dev)?,
+			start: super::parse_nbr(start)?,
+		})
+	}
+}
+
+impl Target for Linear {
+	fn read(&mut self, off: u64, buf: &mut [u8]) -> EResult<()> {
+		super::read_sectors(&self.dev, self.start + off, buf)
+	}
+
+	fn write(&mut self, off: u64, buf: &[u8]) -> EResult<()> {
+		super::write_sectors(&self.dev, self.start + off, buf)
+	}
+}
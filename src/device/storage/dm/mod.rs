@@ -0,0 +1,848 @@
+//! The device-mapper allows to create virtual block devices whose sectors are mapped onto other
+//! block devices, according to a table.
+//!
+//! A table is a list of *targets*, each covering a contiguous range of sectors of the mapped
+//! device. The following targets are supported:
+//! - `linear`: remaps a range of sectors onto a range of sectors of another device
+//! - `snapshot-origin`: forwards I/O to a device, copying chunks out to its snapshots before they
+//! get overwritten
+//! - `snapshot`: copy-on-write view of an origin device at the time the snapshot was created
+//!
+//! Mapped devices are configured from userspace through the control device
+//! (`/dev/mapper/control`), which uses the same ioctl interface as Linux, allowing to use
+//! `dmsetup` and LVM.
+//!
+//! A table is first loaded in the *inactive* slot of a mapped device. It becomes *active*, that is
+//! used for I/O, when the device is resumed.
+
+mod linear;
+mod snapshot;
+
+use super::StorageInterface;
+use crate::device;
+use crate::device::Device;
+use crate::device::DeviceHandle;
+use crate::device::DeviceID;
+use crate::device::DeviceType;
+use crate::errno;
+use crate::errno::EResult;
+use crate::file::path::Path;
+use crate::file::perm::AccessProfile;
+use crate::file::vfs;
+use crate::file::FileContent;
+use crate::file::Mode;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::mem_space::MemSpace;
+use crate::syscall::ioctl;
+use crate::util::boxed::Box;
+use crate::util::container::id_allocator::IDAllocator;
+use crate::util::container::map::Map;
+use crate::util::container::string::String;
+use crate::util::container::vec::Vec;
+use crate::util::io::IO;
+use crate::util::lock::IntMutex;
+use crate::util::lock::Mutex;
+use crate::util::ptr::arc::Arc;
+use crate::util::ptr::arc::Weak;
+use core::cmp::min;
+use core::ffi::c_void;
+use core::mem::size_of;
+use core::num::NonZeroU64;
+use core::ptr;
+use core::str;
+use linear::Linear;
+use snapshot::Snapshot;
+use snapshot::SnapshotOrigin;
+
+/// The major number of mapped devices.
+const DM_MAJOR: u32 = 253;
+/// The major number of the control device.
+const CONTROL_MAJOR: u32 = 10;
+/// The minor number of the control device.
+const CONTROL_MINOR: u32 = 236;
+/// The mode of the device file of the control device.
+const CONTROL_MODE: Mode = 0o600;
+/// The mode of the device file of a mapped device.
+const DEVICE_MODE: Mode = 0o660;
+/// The maximum number of mapped devices.
+const MAX_DEVICES: u32 = 255;
+
+/// The size of a sector in bytes. Tables are always expressed in sectors of this size, regardless
+/// of the block size of the underlying devices.
+pub const SECTOR_SIZE: u64 = 512;
+
+/// The version of the ioctl interface implemented by the kernel.
+const VERSION: [u32; 3] = [4, 0, 0];
+
+/// The size of the name field of [`DmIoctl`].
+const NAME_LEN: usize = 128;
+/// The size of the UUID field of [`DmIoctl`].
+const UUID_LEN: usize = 129;
+/// The size of the target type field of [`DmTargetSpec`].
+const TARGET_TYPE_LEN: usize = 16;
+
+/// Flag: the device is suspended. When passed to `DM_DEV_SUSPEND`, the device is suspended instead
+/// of being resumed.
+const DM_SUSPEND_FLAG: u32 = 1 << 1;
+/// Flag: the device exists.
+const DM_EXISTS_FLAG: u32 = 1 << 2;
+/// Flag: the device has an active table.
+const DM_ACTIVE_PRESENT_FLAG: u32 = 1 << 5;
+/// Flag: the device has an inactive table.
+const DM_INACTIVE_PRESENT_FLAG: u32 = 1 << 6;
+/// Flag: the buffer provided by userspace is too small to hold the result.
+const DM_BUFFER_FULL_FLAG: u32 = 1 << 8;
+
+/// The header of every request passed to the control device.
+#[derive(Clone)]
+#[repr(C)]
+struct DmIoctl {
+	/// The version of the interface. On return, the kernel writes its own version.
+	version: [u32; 3],
+	/// The total size of the buffer, including this header.
+	data_size: u32,
+	/// The offset of the request-specific data, relative to the beginning of the header.
+	data_start: u32,
+	/// The number of targets in the table.
+	target_count: u32,
+	/// The number of times the device is open.
+	open_count: i32,
+	/// Request flags.
+	flags: u32,
+	/// Event counter, unused.
+	event_nr: u32,
+	/// Padding.
+	padding: u32,
+	/// The device number of the mapped device.
+	dev: u64,
+	/// The name of the mapped device, nul-terminated.
+	name: [u8; NAME_LEN],
+	/// The UUID of the mapped device, nul-terminated.
+	uuid: [u8; UUID_LEN],
+	/// Padding.
+	data: [u8; 7],
+}
+
+/// The description of a target, as passed by `DM_TABLE_LOAD`.
+///
+/// The structure is followed by the nul-terminated parameters string of the target.
+#[derive(Clone)]
+#[repr(C)]
+struct DmTargetSpec {
+	/// The first sector of the mapped device covered by the target.
+	sector_start: u64,
+	/// The number of sectors covered by the target.
+	length: u64,
+	/// Unused.
+	status: i32,
+	/// The offset of the next target, relative to the beginning of this structure.
+	next: u32,
+	/// The name of the target type, nul-terminated.
+	target_type: [u8; TARGET_TYPE_LEN],
+}
+
+/// An entry of the list returned by `DM_LIST_DEVICES`.
+///
+/// The structure is followed by the nul-terminated name of the device.
+#[repr(C)]
+struct DmNameList {
+	/// The device number.
+	dev: u64,
+	/// The offset of the next entry, relative to the beginning of this structure. Zero on the
+	/// last entry.
+	next: u32,
+}
+
+/// Returns the slice before the first nul byte of `buf`.
+fn nul_terminated(buf: &[u8]) -> &[u8] {
+	let len = buf.iter().position(|b| *b == 0).unwrap_or(buf.len());
+	&buf[..len]
+}
+
+/// Parses the number `s`.
+///
+/// If the number is invalid, the function returns [`errno::EINVAL`].
+fn parse_nbr(s: &[u8]) -> EResult<u64> {
+	str::from_utf8(s)
+		.ok()
+		.and_then(|s| s.parse().ok())
+		.ok_or_else(|| errno!(EINVAL))
+}
+
+/// Returns the block device designated by the table argument `arg`.
+///
+/// The device can be given either in the form `major:minor` or as the path to a block device file.
+fn get_device(arg: &[u8]) -> EResult<Arc<Mutex<Device>>> {
+	let (major, minor) = if let Some(i) = arg.iter().position(|c| *c == b':') {
+		(
+			parse_nbr(&arg[..i])? as u32,
+			parse_nbr(&arg[(i + 1)..])? as u32,
+		)
+	} else {
+		let path = Path::from_str(arg, false)?;
+		let file_mutex = vfs::get_file_from_path(&path, &AccessProfile::KERNEL, true)?;
+		let file = file_mutex.lock();
+		match file.get_content() {
+			FileContent::BlockDevice {
+				major,
+				minor,
+			} => (*major, *minor),
+			_ => return Err(errno!(ENOTBLK)),
+		}
+	};
+	// A mapped device cannot use itself, or any other mapped device, since this could lead to a
+	// deadlock
+	if major == DM_MAJOR {
+		return Err(errno!(EINVAL));
+	}
+
+	device::get(&DeviceID {
+		type_: DeviceType::Block,
+		major,
+		minor,
+	})
+	.ok_or_else(|| errno!(ENXIO))
+}
+
+/// Reads sectors from the block device `dev`, starting at sector `off`.
+///
+/// The number of sectors to read is given by the size of `buf`.
+fn read_sectors(dev: &Mutex<Device>, off: u64, buf: &mut [u8]) -> EResult<()> {
+	let mut dev = dev.lock();
+	let (len, _) = dev.read(off * SECTOR_SIZE, buf)?;
+	if len < buf.len() as u64 {
+		return Err(errno!(EIO));
+	}
+	Ok(())
+}
+
+/// Writes sectors to the block device `dev`, starting at sector `off`.
+///
+/// The number of sectors to write is given by the size of `buf`.
+fn write_sectors(dev: &Mutex<Device>, off: u64, buf: &[u8]) -> EResult<()> {
+	let mut dev = dev.lock();
+	let len = dev.write(off * SECTOR_SIZE, buf)?;
+	if len < buf.len() as u64 {
+		return Err(errno!(EIO));
+	}
+	Ok(())
+}
+
+/// Returns the number of sectors of the block device `dev`.
+fn get_sectors_count(dev: &Mutex<Device>) -> u64 {
+	dev.lock().get_size() / SECTOR_SIZE
+}
+
+/// Trait representing a target, which handles I/O on a range of sectors of a mapped device.
+///
+/// Offsets are given in sectors, relative to the beginning of the range covered by the target.
+/// Buffers sizes are always multiples of [`SECTOR_SIZE`].
+pub trait Target {
+	/// Reads sectors starting at `off` into `buf`.
+	fn read(&mut self, off: u64, buf: &mut [u8]) -> EResult<()>;
+	/// Writes sectors starting at `off` from `buf`.
+	fn write(&mut self, off: u64, buf: &[u8]) -> EResult<()>;
+}
+
+/// Creates a target of the given type.
+///
+/// Arguments:
+/// - `type_name` is the name of the target type.
+/// - `args` is the list of arguments of the target.
+fn new_target(type_name: &[u8], args: &[&[u8]]) -> EResult<Box<dyn Target>> {
+	let target: Box<dyn Target> = match type_name {
+		b"linear" => Box::new(Linear::new(args)?)?,
+		b"snapshot-origin" => Box::new(SnapshotOrigin::new(args)?)?,
+		b"snapshot" => Box::new(Snapshot::new(args)?)?,
+		_ => return Err(errno!(EINVAL)),
+	};
+	Ok(target)
+}
+
+/// An entry of a table.
+struct TableEntry {
+	/// The first sector covered by the target.
+	start: u64,
+	/// The number of sectors covered by the target.
+	len: u64,
+	/// The target.
+	target: Box<dyn Target>,
+}
+
+/// A table, describing how sectors of a mapped device are mapped.
+///
+/// Entries are sorted and contiguous, the first one starting at sector zero.
+struct Table {
+	/// The list of entries.
+	entries: Vec<TableEntry>,
+}
+
+impl Table {
+	/// Parses the table passed by userspace with `DM_TABLE_LOAD`.
+	///
+	/// Arguments:
+	/// - `buf` is the buffer passed by userspace, beginning with the header.
+	/// - `hdr` is the header.
+	fn parse(buf: &[u8], hdr: &DmIoctl) -> EResult<Self> {
+		let mut entries = Vec::new();
+		let mut off = hdr.data_start as usize;
+		let mut sector = 0;
+		for _ in 0..hdr.target_count {
+			let spec_end = off
+				.checked_add(size_of::<DmTargetSpec>())
+				.ok_or_else(|| errno!(EINVAL))?;
+			if spec_end > buf.len() {
+				return Err(errno!(EINVAL));
+			}
+			// Safe because the range has been checked and the structure is read unaligned
+			let spec = unsafe { ptr::read_unaligned(buf[off..].as_ptr() as *const DmTargetSpec) };
+			if spec.sector_start != sector || spec.length == 0 {
+				return Err(errno!(EINVAL));
+			}
+
+			let params = nul_terminated(&buf[spec_end..]);
+			let args = params
+				.split(|c| c.is_ascii_whitespace())
+				.filter(|a| !a.is_empty())
+				.collect::<crate::errno::CollectResult<Vec<_>>>()
+				.0?;
+			let target = new_target(nul_terminated(&spec.target_type), &args)?;
+			entries.push(TableEntry {
+				start: spec.sector_start,
+				len: spec.length,
+				target,
+			})?;
+
+			sector = spec
+				.sector_start
+				.checked_add(spec.length)
+				.ok_or_else(|| errno!(EINVAL))?;
+			off = off
+				.checked_add(spec.next as usize)
+				.ok_or_else(|| errno!(EINVAL))?;
+		}
+
+		Ok(Self {
+			entries,
+		})
+	}
+
+	/// Performs an I/O operation on the sectors range starting at `offset` with `size` sectors.
+	///
+	/// For each target covering the range, `f` is called with the target, the offset relative to
+	/// the target and the range of bytes of the buffer corresponding to the target.
+	fn io<F>(&mut self, offset: u64, size: u64, mut f: F) -> EResult<()>
+	where
+		F: FnMut(&mut dyn Target, u64, core::ops::Range<usize>) -> EResult<()>,
+	{
+		if offset
+			.checked_add(size)
+			.map(|end| end > self.get_blocks_count())
+			.unwrap_or(true)
+		{
+			return Err(errno!(EINVAL));
+		}
+
+		let mut cur = offset;
+		let end = offset + size;
+		for e in self.entries.iter_mut() {
+			if cur >= end {
+				break;
+			}
+			let entry_end = e.start + e.len;
+			if cur >= entry_end {
+				continue;
+			}
+
+			let inner_off = cur - e.start;
+			let count = min(entry_end, end) - cur;
+			let buf_begin = ((cur - offset) * SECTOR_SIZE) as usize;
+			let buf_end = buf_begin + (count * SECTOR_SIZE) as usize;
+			f(e.target.as_mut(), inner_off, buf_begin..buf_end)?;
+
+			cur += count;
+		}
+
+		Ok(())
+	}
+}
+
+impl StorageInterface for Table {
+	fn get_block_size(&self) -> NonZeroU64 {
+		NonZeroU64::new(SECTOR_SIZE).unwrap()
+	}
+
+	fn get_blocks_count(&self) -> u64 {
+		self.entries.iter().map(|e| e.len).sum()
+	}
+
+	fn read(&mut self, buf: &mut [u8], offset: u64, size: u64) -> EResult<()> {
+		self.io(offset, size, |target, off, range| {
+			target.read(off, &mut buf[range])
+		})
+	}
+
+	fn write(&mut self, buf: &[u8], offset: u64, size: u64) -> EResult<()> {
+		self.io(offset, size, |target, off, range| {
+			target.write(off, &buf[range])
+		})
+	}
+}
+
+/// A mapped device.
+struct MappedDevice {
+	/// The name of the device.
+	name: String,
+	/// The minor number of the device.
+	minor: u32,
+
+	/// The table used for I/O.
+	active: Option<Table>,
+	/// The table loaded by userspace, which becomes active when the device is resumed.
+	inactive: Option<Table>,
+	/// Tells whether the device is suspended.
+	suspended: bool,
+}
+
+impl MappedDevice {
+	/// Returns the device ID of the device.
+	fn get_id(&self) -> DeviceID {
+		DeviceID {
+			type_: DeviceType::Block,
+			major: DM_MAJOR,
+			minor: self.minor,
+		}
+	}
+
+	/// Returns the table to be used for I/O.
+	///
+	/// While the device is suspended, I/O fails with [`errno::EAGAIN`] so that it does not reach a
+	/// table that is being replaced.
+	fn get_io_table(&mut self) -> EResult<&mut Table> {
+		if self.suspended {
+			return Err(errno!(EAGAIN));
+		}
+		self.active.as_mut().ok_or_else(|| errno!(ENXIO))
+	}
+
+	/// Returns the flags describing the state of the device, as returned to userspace.
+	fn get_flags(&self) -> u32 {
+		let mut flags = DM_EXISTS_FLAG;
+		if self.suspended {
+			flags |= DM_SUSPEND_FLAG;
+		}
+		if self.active.is_some() {
+			flags |= DM_ACTIVE_PRESENT_FLAG;
+		}
+		if self.inactive.is_some() {
+			flags |= DM_INACTIVE_PRESENT_FLAG;
+		}
+		flags
+	}
+
+	/// Fills the status fields of the header `hdr` with the state of the device.
+	fn fill_status(&self, hdr: &mut DmIoctl) {
+		hdr.dev = self.get_id().get_device_number();
+		let state_flags =
+			DM_SUSPEND_FLAG | DM_EXISTS_FLAG | DM_ACTIVE_PRESENT_FLAG | DM_INACTIVE_PRESENT_FLAG;
+		hdr.flags = (hdr.flags & !state_flags) | self.get_flags();
+		hdr.target_count = self
+			.active
+			.as_ref()
+			.map(|t| t.entries.len() as u32)
+			.unwrap_or(0);
+		hdr.open_count = 0;
+		hdr.name.fill(0);
+		let len = min(self.name.len(), NAME_LEN - 1);
+		hdr.name[..len].copy_from_slice(&self.name.as_bytes()[..len]);
+	}
+}
+
+/// Handle for the device file of a mapped device.
+struct MappedDeviceHandle {
+	/// The mapped device.
+	dev: Weak<Mutex<MappedDevice>>,
+}
+
+impl DeviceHandle for MappedDeviceHandle {
+	fn ioctl(
+		&mut self,
+		mem_space: Arc<IntMutex<MemSpace>>,
+		request: ioctl::Request,
+		argp: *const c_void,
+	) -> EResult<u32> {
+		match request.get_old_format() {
+			ioctl::BLKSSZGET => {
+				let mut mem_space_guard = mem_space.lock();
+				let size_ptr: SyscallPtr<u32> = (argp as usize).into();
+				let size_ref = size_ptr
+					.get_mut(&mut mem_space_guard)?
+					.ok_or_else(|| errno!(EFAULT))?;
+				*size_ref = SECTOR_SIZE as _;
+
+				Ok(0)
+			}
+
+			ioctl::BLKGETSIZE64 => {
+				let size = self.get_size();
+
+				let mut mem_space_guard = mem_space.lock();
+				let size_ptr: SyscallPtr<u64> = (argp as usize).into();
+				let size_ref = size_ptr
+					.get_mut(&mut mem_space_guard)?
+					.ok_or_else(|| errno!(EFAULT))?;
+				*size_ref = size;
+
+				Ok(0)
+			}
+
+			ioctl::BLKFLSBUF => {
+				super::check_privileged()?;
+				Ok(0)
+			}
+
+			_ => Err(errno!(ENOTTY)),
+		}
+	}
+}
+
+impl IO for MappedDeviceHandle {
+	fn get_size(&self) -> u64 {
+		let Some(dev) = self.dev.upgrade() else {
+			return 0;
+		};
+		let dev = dev.lock();
+		dev.active.as_ref().map(|t| t.get_size()).unwrap_or(0)
+	}
+
+	fn read(&mut self, offset: u64, buff: &mut [u8]) -> EResult<(u64, bool)> {
+		let dev = self.dev.upgrade().ok_or_else(|| errno!(ENODEV))?;
+		let mut dev = dev.lock();
+		let table = dev.get_io_table()?;
+
+		let size = table.get_size();
+		if offset >= size {
+			return Ok((0, true));
+		}
+		let len = min(buff.len() as u64, size - offset) as usize;
+		table.read_bytes(&mut buff[..len], offset)
+	}
+
+	fn write(&mut self, offset: u64, buff: &[u8]) -> EResult<u64> {
+		let dev = self.dev.upgrade().ok_or_else(|| errno!(ENODEV))?;
+		let mut dev = dev.lock();
+		let table = dev.get_io_table()?;
+
+		let size = table.get_size();
+		if offset >= size {
+			return Err(errno!(ENOSPC));
+		}
+		let len = min(buff.len() as u64, size - offset) as usize;
+		table.write_bytes(&buff[..len], offset)
+	}
+
+	fn poll(&mut self, _mask: u32) -> EResult<u32> {
+		Ok(0)
+	}
+}
+
+/// The state of the device-mapper.
+struct DeviceMapper {
+	/// The minor numbers allocator.
+	minors: IDAllocator,
+	/// The list of mapped devices, by minor number.
+	devices: Map<u32, Arc<Mutex<MappedDevice>>>,
+}
+
+impl DeviceMapper {
+	/// Returns the device designated by the header `hdr`, by name if specified, or else by device
+	/// number.
+	fn find(&self, hdr: &DmIoctl) -> EResult<Arc<Mutex<MappedDevice>>> {
+		let name = nul_terminated(&hdr.name);
+		self.devices
+			.iter()
+			.find(|(minor, dev)| {
+				if !name.is_empty() {
+					dev.lock().name.as_bytes() == name
+				} else {
+					device::id::makedev(DM_MAJOR, **minor) == hdr.dev
+				}
+			})
+			.map(|(_, dev)| dev.clone())
+			.ok_or_else(|| errno!(ENXIO))
+	}
+
+	/// Creates a new mapped device with the name given in the header `hdr`.
+	fn create(&mut self, hdr: &mut DmIoctl) -> EResult<()> {
+		let name = nul_terminated(&hdr.name);
+		if name.is_empty() || name.contains(&b'/') || name == b"." || name == b".." {
+			return Err(errno!(EINVAL));
+		}
+		if self.find(hdr).is_ok() {
+			return Err(errno!(EBUSY));
+		}
+
+		let minor = self.minors.alloc(None)?;
+		let dev = Arc::new(Mutex::new(MappedDevice {
+			name: String::try_from(name)?,
+			minor,
+
+			active: None,
+			inactive: None,
+			suspended: false,
+		}))?;
+
+		let res = (|| {
+			let path_str = crate::format!("/dev/mapper/{}", String::try_from(name)?)?;
+			let path = Path::from_str(path_str.as_bytes(), false)?;
+			let device = Device::new(
+				dev.lock().get_id(),
+				path,
+				DEVICE_MODE,
+				MappedDeviceHandle {
+					dev: Arc::downgrade(&dev),
+				},
+			)?;
+			device::register(device)?;
+
+			self.devices.insert(minor, dev.clone())?;
+			Ok(())
+		})();
+		if let Err(e) = res {
+			let _ = device::unregister(&dev.lock().get_id());
+			self.minors.free(minor);
+			return Err(e);
+		}
+
+		dev.lock().fill_status(hdr);
+		Ok(())
+	}
+
+	/// Removes the given mapped device.
+	fn remove(&mut self, dev: &Mutex<MappedDevice>) -> EResult<()> {
+		let id = dev.lock().get_id();
+		device::unregister(&id)?;
+		self.devices.remove(&id.minor);
+		self.minors.free(id.minor);
+		Ok(())
+	}
+
+	/// Writes the list of mapped devices at the beginning of `out`.
+	///
+	/// If the buffer is too small, the function returns `false`.
+	fn list(&self, out: &mut [u8]) -> bool {
+		let mut off = 0;
+		let mut prev: Option<usize> = None;
+		for (minor, dev) in self.devices.iter() {
+			let dev = dev.lock();
+			// Entries are aligned on 8 bytes
+			let entry_len = (size_of::<DmNameList>() + dev.name.len() + 1 + 7) & !7;
+			if off + entry_len > out.len() {
+				return false;
+			}
+
+			if let Some(prev) = prev {
+				let next = (off - prev) as u32;
+				out[(prev + 8)..(prev + 12)].copy_from_slice(&next.to_ne_bytes());
+			}
+			let dev_nbr = device::id::makedev(DM_MAJOR, *minor);
+			out[off..(off + 8)].copy_from_slice(&dev_nbr.to_ne_bytes());
+			out[(off + 8)..(off + 12)].copy_from_slice(&0u32.to_ne_bytes());
+			let name_off = off + size_of::<DmNameList>();
+			out[name_off..(name_off + dev.name.len())].copy_from_slice(dev.name.as_bytes());
+			out[name_off + dev.name.len()] = 0;
+
+			prev = Some(off);
+			off += entry_len;
+		}
+		// An empty list is represented by a single zeroed entry
+		if prev.is_none() && out.len() >= size_of::<DmNameList>() {
+			out[..size_of::<DmNameList>()].fill(0);
+		}
+		true
+	}
+}
+
+/// The device-mapper's state.
+static DEVICE_MAPPER: Mutex<Option<DeviceMapper>> = Mutex::new(None);
+
+/// Handle for the control device, through which mapped devices are configured.
+#[derive(Default)]
+struct ControlDeviceHandle {}
+
+impl ControlDeviceHandle {
+	/// Executes the command `request` with header `hdr` and the buffer `buf` passed by
+	/// userspace.
+	///
+	/// On success, the function returns the data to be written back to userspace after the header,
+	/// if any.
+	fn command(&self, request: u32, hdr: &mut DmIoctl, buf: &[u8]) -> EResult<Option<Vec<u8>>> {
+		let mut dm = DEVICE_MAPPER.lock();
+		let dm = dm.as_mut().ok_or_else(|| errno!(ENODEV))?;
+
+		match request {
+			ioctl::DM_VERSION => Ok(None),
+
+			ioctl::DM_REMOVE_ALL => {
+				while let Some(dev) = dm.devices.iter().next().map(|(_, d)| d.clone()) {
+					dm.remove(&dev)?;
+				}
+				Ok(None)
+			}
+
+			ioctl::DM_LIST_DEVICES => {
+				// Results are written right after the header
+				let out_start = (size_of::<DmIoctl>() + 7) & !7;
+				let out_len = (hdr.data_size as usize).saturating_sub(out_start);
+				let mut out = crate::vec![0; out_len]?;
+				if !dm.list(&mut out) {
+					hdr.flags |= DM_BUFFER_FULL_FLAG;
+				}
+				hdr.data_start = out_start as _;
+				Ok(Some(out))
+			}
+
+			ioctl::DM_DEV_CREATE => {
+				dm.create(hdr)?;
+				Ok(None)
+			}
+
+			ioctl::DM_DEV_REMOVE => {
+				let dev = dm.find(hdr)?;
+				dm.remove(&dev)?;
+				Ok(None)
+			}
+
+			ioctl::DM_DEV_SUSPEND => {
+				let dev_mutex = dm.find(hdr)?;
+				let mut dev = dev_mutex.lock();
+				if hdr.flags & DM_SUSPEND_FLAG != 0 {
+					dev.suspended = true;
+				} else {
+					if let Some(table) = dev.inactive.take() {
+						dev.active = Some(table);
+					}
+					dev.suspended = false;
+				}
+				dev.fill_status(hdr);
+				Ok(None)
+			}
+
+			ioctl::DM_DEV_STATUS => {
+				let dev = dm.find(hdr)?;
+				dev.lock().fill_status(hdr);
+				Ok(None)
+			}
+
+			ioctl::DM_TABLE_LOAD => {
+				let dev_mutex = dm.find(hdr)?;
+				let table = Table::parse(buf, hdr)?;
+				let mut dev = dev_mutex.lock();
+				dev.inactive = Some(table);
+				dev.fill_status(hdr);
+				Ok(None)
+			}
+
+			ioctl::DM_TABLE_CLEAR => {
+				let dev_mutex = dm.find(hdr)?;
+				let mut dev = dev_mutex.lock();
+				dev.inactive = None;
+				dev.fill_status(hdr);
+				Ok(None)
+			}
+
+			_ => Err(errno!(ENOTTY)),
+		}
+	}
+}
+
+impl DeviceHandle for ControlDeviceHandle {
+	fn ioctl(
+		&mut self,
+		mem_space: Arc<IntMutex<MemSpace>>,
+		request: ioctl::Request,
+		argp: *const c_void,
+	) -> EResult<u32> {
+		super::check_privileged()?;
+
+		// Copy the request from userspace
+		let (mut hdr, buf) = {
+			let mem_space_guard = mem_space.lock();
+			let hdr_ptr: SyscallPtr<DmIoctl> = (argp as usize).into();
+			let hdr = hdr_ptr
+				.get(&mem_space_guard)?
+				.ok_or_else(|| errno!(EFAULT))?
+				.clone();
+			if hdr.version[0] != VERSION[0] || (hdr.data_size as usize) < size_of::<DmIoctl>() {
+				return Err(errno!(EINVAL));
+			}
+
+			let buf_ptr: SyscallSlice<u8> = (argp as usize).into();
+			let buf = buf_ptr
+				.get(&mem_space_guard, hdr.data_size as _)?
+				.ok_or_else(|| errno!(EFAULT))?;
+			(hdr, Vec::from_slice(buf)?)
+		};
+
+		hdr.flags &= !DM_BUFFER_FULL_FLAG;
+		let out = self.command(request.get_old_format() as _, &mut hdr, &buf)?;
+		hdr.version = VERSION;
+
+		// Write the result back to userspace
+		let mut mem_space_guard = mem_space.lock();
+		if let Some(out) = out {
+			let out_ptr: SyscallSlice<u8> = (argp as usize + hdr.data_start as usize).into();
+			let out_ref = out_ptr
+				.get_mut(&mut mem_space_guard, out.len())?
+				.ok_or_else(|| errno!(EFAULT))?;
+			out_ref.copy_from_slice(&out);
+		}
+		let hdr_ptr: SyscallPtr<DmIoctl> = (argp as usize).into();
+		let hdr_ref = hdr_ptr
+			.get_mut(&mut mem_space_guard)?
+			.ok_or_else(|| errno!(EFAULT))?;
+		*hdr_ref = hdr;
+
+		Ok(0)
+	}
+}
+
+impl IO for ControlDeviceHandle {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, _offset: u64, _buff: &mut [u8]) -> EResult<(u64, bool)> {
+		Err(errno!(EINVAL))
+	}
+
+	fn write(&mut self, _offset: u64, _buff: &[u8]) -> EResult<u64> {
+		Err(errno!(EINVAL))
+	}
+
+	fn poll(&mut self, _mask: u32) -> EResult<u32> {
+		Ok(0)
+	}
+}
+
+/// Initializes the device-mapper, registering the control device.
+pub fn init() -> EResult<()> {
+	*DEVICE_MAPPER.lock() = Some(DeviceMapper {
+		minors: IDAllocator::new(MAX_DEVICES)?,
+		devices: Map::new(),
+	});
+
+	let path = Path::from_str(b"/dev/mapper/control", false)?;
+	let dev = Device::new(
+		DeviceID {
+			type_: DeviceType::Char,
+			major: CONTROL_MAJOR,
+			minor: CONTROL_MINOR,
+		},
+		path,
+		CONTROL_MODE,
+		ControlDeviceHandle::default(),
+	)?;
+	device::register(dev)
+}
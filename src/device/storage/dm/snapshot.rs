@@ -0,0 +1,231 @@
+//! Snapshots provide a frozen view of an origin device.
+//!
+//! The origin device is divided into chunks. Before a chunk of the origin is modified for the
+//! first time, it is copied to the COW (copy-on-write) device. The *exception store* keeps track
+//! of which chunks have been copied, and where.
+//!
+//! Two targets are involved:
+//! - `snapshot-origin <origin>`: used in place of the origin device. Writes go through it so that
+//! chunks are copied out to every snapshot of the origin before being overwritten
+//! - `snapshot <origin> <cow> <persistent> <chunk_size>`: the snapshot itself, where `chunk_size`
+//! is given in sectors and must be a power of two. Only transient exception stores (`N`) are
+//! supported, meaning the snapshot does not survive a reboot
+//!
+//! Writing to a snapshot is allowed: the written chunks are copied out first, then modified on the
+//! COW device only.
+
+use super::Target;
+use super::SECTOR_SIZE;
+use crate::device::Device;
+use crate::device::DeviceID;
+use crate::errno;
+use crate::errno::EResult;
+use crate::util::container::hashmap::HashMap;
+use crate::util::container::vec::Vec;
+use crate::util::lock::Mutex;
+use crate::util::ptr::arc::Arc;
+use crate::util::ptr::arc::Weak;
+use core::cmp::min;
+
+/// The exception store of a snapshot.
+struct ExceptionStore {
+	/// The ID of the origin device.
+	origin_id: DeviceID,
+	/// The origin device.
+	origin: Arc<Mutex<Device>>,
+	/// The device on which chunks are copied.
+	cow: Arc<Mutex<Device>>,
+
+	/// The size of a chunk, in sectors.
+	chunk_size: u64,
+	/// Mapping from chunks of the origin to chunks of the COW device.
+	exceptions: HashMap<u64, u64>,
+	/// The next free chunk on the COW device.
+	next_free: u64,
+}
+
+impl ExceptionStore {
+	/// Returns the chunk of the COW device holding the origin's chunk `chunk`, if it has been
+	/// copied out.
+	fn lookup(&self, chunk: u64) -> Option<u64> {
+		self.exceptions.get(&chunk).cloned()
+	}
+
+	/// Copies the origin's chunk `chunk` to the COW device, if not already done.
+	///
+	/// The function returns the chunk of the COW device holding the data.
+	///
+	/// If the COW device is full, the function returns [`errno::ENOSPC`].
+	fn copy_out(&mut self, chunk: u64) -> EResult<u64> {
+		if let Some(c) = self.lookup(chunk) {
+			return Ok(c);
+		}
+
+		let cow_chunks = super::get_sectors_count(&self.cow) / self.chunk_size;
+		if self.next_free >= cow_chunks {
+			return Err(errno!(ENOSPC));
+		}
+
+		// The last chunk of the origin might be partial
+		let origin_sectors = super::get_sectors_count(&self.origin);
+		let start = chunk * self.chunk_size;
+		let sectors = min(self.chunk_size, origin_sectors.saturating_sub(start));
+		let mut buf = crate::vec![0; (sectors * SECTOR_SIZE) as usize]?;
+		super::read_sectors(&self.origin, start, &mut buf)?;
+
+		let new_chunk = self.next_free;
+		super::write_sectors(&self.cow, new_chunk * self.chunk_size, &buf)?;
+		self.exceptions.insert(chunk, new_chunk)?;
+		self.next_free += 1;
+
+		Ok(new_chunk)
+	}
+}
+
+/// The list of exception stores of every snapshots, used by `snapshot-origin` targets to copy out
+/// chunks before they are overwritten.
+static STORES: Mutex<Vec<Weak<Mutex<ExceptionStore>>>> = Mutex::new(Vec::new());
+
+/// Calls `f` for each chunk overlapping the sectors range starting at `off` with `len` bytes.
+///
+/// Arguments given to `f` are:
+/// - The chunk number.
+/// - The offset of the first sector in the chunk.
+/// - The range of bytes of the buffer corresponding to the chunk.
+fn for_each_chunk<F>(chunk_size: u64, off: u64, len: usize, mut f: F) -> EResult<()>
+where
+	F: FnMut(u64, u64, core::ops::Range<usize>) -> EResult<()>,
+{
+	let mut i = 0;
+	while i < len {
+		let sector = off + i as u64 / SECTOR_SIZE;
+		let chunk = sector / chunk_size;
+		let inner = sector % chunk_size;
+
+		let n = min(((chunk_size - inner) * SECTOR_SIZE) as usize, len - i);
+		f(chunk, inner, i..(i + n))?;
+
+		i += n;
+	}
+	Ok(())
+}
+
+/// The `snapshot-origin` target.
+pub struct SnapshotOrigin {
+	/// The ID of the origin device.
+	origin_id: DeviceID,
+	/// The origin device.
+	origin: Arc<Mutex<Device>>,
+}
+
+impl SnapshotOrigin {
+	/// Creates a new instance from the given table arguments.
+	pub fn new(args: &[&[u8]]) -> EResult<Self> {
+		let [origin] = args else {
+			return Err(errno!(EINVAL));
+		};
+		let origin = super::get_device(origin)?;
+		let origin_id = origin.lock().get_id().clone();
+
+		Ok(Self {
+			origin_id,
+			origin,
+		})
+	}
+}
+
+impl Target for SnapshotOrigin {
+	fn read(&mut self, off: u64, buf: &mut [u8]) -> EResult<()> {
+		super::read_sectors(&self.origin, off, buf)
+	}
+
+	fn write(&mut self, off: u64, buf: &[u8]) -> EResult<()> {
+		{
+			let mut stores = STORES.lock();
+			// Remove stores of snapshots that no longer exist
+			stores.retain(|s| s.upgrade().is_some());
+
+			for store in stores.iter().filter_map(Weak::upgrade) {
+				let mut store = store.lock();
+				if store.origin_id != self.origin_id {
+					continue;
+				}
+
+				for_each_chunk(store.chunk_size, off, buf.len(), |chunk, _, _| {
+					store.copy_out(chunk)?;
+					Ok(())
+				})?;
+			}
+		}
+
+		super::write_sectors(&self.origin, off, buf)
+	}
+}
+
+/// The `snapshot` target.
+pub struct Snapshot {
+	/// The exception store.
+	store: Arc<Mutex<ExceptionStore>>,
+}
+
+impl Snapshot {
+	/// Creates a new instance from the given table arguments.
+	pub fn new(args: &[&[u8]]) -> EResult<Self> {
+		let [origin, cow, persistent, chunk_size] = args else {
+			return Err(errno!(EINVAL));
+		};
+		if !persistent.eq_ignore_ascii_case(b"n") {
+			return Err(errno!(EINVAL));
+		}
+		let chunk_size = super::parse_nbr(chunk_size)?;
+		if !chunk_size.is_power_of_two() {
+			return Err(errno!(EINVAL));
+		}
+
+		let origin = super::get_device(origin)?;
+		let origin_id = origin.lock().get_id().clone();
+		let store = Arc::new(Mutex::new(ExceptionStore {
+			origin_id,
+			origin,
+			cow: super::get_device(cow)?,
+
+			chunk_size,
+			exceptions: HashMap::new(),
+			next_free: 0,
+		}))?;
+		STORES.lock().push(Arc::downgrade(&store))?;
+
+		Ok(Self {
+			store,
+		})
+	}
+}
+
+impl Target for Snapshot {
+	fn read(&mut self, off: u64, buf: &mut [u8]) -> EResult<()> {
+		let store = self.store.lock();
+		let chunk_size = store.chunk_size;
+		for_each_chunk(
+			chunk_size,
+			off,
+			buf.len(),
+			|chunk, inner, range| match store.lookup(chunk) {
+				Some(c) => {
+					super::read_sectors(&store.cow, c * chunk_size + inner, &mut buf[range])
+				}
+				None => {
+					super::read_sectors(&store.origin, chunk * chunk_size + inner, &mut buf[range])
+				}
+			},
+		)
+	}
+
+	fn write(&mut self, off: u64, buf: &[u8]) -> EResult<()> {
+		let mut store = self.store.lock();
+		let chunk_size = store.chunk_size;
+		for_each_chunk(chunk_size, off, buf.len(), |chunk, inner, range| {
+			let c = store.copy_out(chunk)?;
+			super::write_sectors(&store.cow, c * chunk_size + inner, &buf[range])
+		})
+	}
+}
@@ -0,0 +1,288 @@
+//! A loop device exposes a regular file as a block device, which allows to mount filesystem images
+//! without a real disk.
+//!
+//! Loop devices are configured from userspace through the ioctl interface, using the same requests
+//! as Linux:
+//! - `LOOP_CTL_GET_FREE` on `/dev/loop-control` returns the index of an unused loop device
+//! - `LOOP_SET_FD` on `/dev/loopX` attaches the file open at the given file descriptor
+//! - `LOOP_CLR_FD` on `/dev/loopX` detaches the file
+
+use crate::device;
+use crate::device::id;
+use crate::device::Device;
+use crate::device::DeviceHandle;
+use crate::device::DeviceID;
+use crate::device::DeviceType;
+use crate::errno;
+use crate::errno::EResult;
+use crate::file::path::Path;
+use crate::file::File;
+use crate::file::FileContent;
+use crate::file::Mode;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::mem_space::MemSpace;
+use crate::process::Process;
+use crate::syscall::ioctl;
+use crate::util::io::IO;
+use crate::util::lock::IntMutex;
+use crate::util::lock::Mutex;
+use crate::util::ptr::arc::Arc;
+use core::ffi::c_int;
+use core::ffi::c_void;
+use core::mem::ManuallyDrop;
+
+/// The major number of loop devices.
+const LOOP_MAJOR: u32 = 7;
+/// The number of loop devices on the system.
+const LOOP_COUNT: usize = 8;
+/// The mode of the device file of a loop device.
+const LOOP_MODE: Mode = 0o660;
+/// The major number of the control device.
+const CONTROL_MAJOR: u32 = 10;
+/// The minor number of the control device.
+const CONTROL_MINOR: u32 = 237;
+/// The mode of the device file of the control device.
+const CONTROL_MODE: Mode = 0o660;
+
+/// The size of a sector in bytes.
+const SECTOR_SIZE: u32 = 512;
+
+/// A file attached to a loop device.
+struct Backing {
+	/// The backing file.
+	file: Arc<Mutex<File>>,
+	/// Tells whether the device is read-only, which is the case if the file was not open for
+	/// writing.
+	readonly: bool,
+}
+
+/// The default value for `LOOP_DEVICES`.
+#[allow(clippy::declare_interior_mutable_const)]
+const LOOP_DEVICES_INIT: Mutex<Option<Backing>> = Mutex::new(None);
+/// The files attached to each loop device.
+static LOOP_DEVICES: [Mutex<Option<Backing>>; LOOP_COUNT] = [LOOP_DEVICES_INIT; LOOP_COUNT];
+
+/// Attaches the file open at file descriptor `fd` of the current process to the loop device with
+/// index `index`.
+///
+/// If the file is neither a regular file nor a block device, or if it is a loop device, the
+/// function returns [`errno::EINVAL`].
+///
+/// If a file is already attached to the device, the function returns [`errno::EBUSY`].
+fn set_fd(index: usize, fd: c_int) -> EResult<()> {
+	let open_file_mutex = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+
+		let fds_mutex = proc.get_fds().unwrap();
+		let fds = fds_mutex.lock();
+		fds.get_fd(fd as _)
+			.ok_or_else(|| errno!(EBADF))?
+			.get_open_file()
+			.clone()
+	};
+	let (file, readonly) = {
+		let open_file = open_file_mutex.lock();
+		(open_file.get_file().clone(), !open_file.can_write())
+	};
+	// Only regular files and block devices can back a loop device. A loop device cannot use
+	// itself, or any other loop device, since this could lead to a deadlock
+	match file.lock().get_content() {
+		FileContent::Regular => {}
+		FileContent::BlockDevice {
+			major, ..
+		} if *major != LOOP_MAJOR => {}
+		_ => return Err(errno!(EINVAL)),
+	}
+
+	let mut dev = LOOP_DEVICES[index].lock();
+	if dev.is_some() {
+		return Err(errno!(EBUSY));
+	}
+	*dev = Some(Backing {
+		file,
+		readonly,
+	});
+	Ok(())
+}
+
+/// Handle for the device file of a loop device.
+struct LoopDeviceHandle {
+	/// The index of the loop device.
+	index: usize,
+}
+
+impl LoopDeviceHandle {
+	/// Returns the file attached to the device along with the read-only flag.
+	///
+	/// If no file is attached, the function returns [`errno::ENXIO`].
+	fn get_backing(&self) -> EResult<(Arc<Mutex<File>>, bool)> {
+		let dev = LOOP_DEVICES[self.index].lock();
+		let backing = dev.as_ref().ok_or_else(|| errno!(ENXIO))?;
+		Ok((backing.file.clone(), backing.readonly))
+	}
+}
+
+impl DeviceHandle for LoopDeviceHandle {
+	fn ioctl(
+		&mut self,
+		mem_space: Arc<IntMutex<MemSpace>>,
+		request: ioctl::Request,
+		argp: *const c_void,
+	) -> EResult<u32> {
+		match request.get_old_format() {
+			ioctl::BLKSSZGET => {
+				let mut mem_space_guard = mem_space.lock();
+				let size_ptr: SyscallPtr<u32> = (argp as usize).into();
+				let size_ref = size_ptr
+					.get_mut(&mut mem_space_guard)?
+					.ok_or_else(|| errno!(EFAULT))?;
+				*size_ref = SECTOR_SIZE;
+
+				Ok(0)
+			}
+
+			ioctl::BLKGETSIZE64 => {
+				let size = self.get_size();
+
+				let mut mem_space_guard = mem_space.lock();
+				let size_ptr: SyscallPtr<u64> = (argp as usize).into();
+				let size_ref = size_ptr
+					.get_mut(&mut mem_space_guard)?
+					.ok_or_else(|| errno!(EFAULT))?;
+				*size_ref = size;
+
+				Ok(0)
+			}
+
+			ioctl::BLKFLSBUF => Ok(0),
+
+			ioctl::LOOP_SET_FD => {
+				super::check_privileged()?;
+				set_fd(self.index, argp as usize as _)?;
+				Ok(0)
+			}
+
+			ioctl::LOOP_CLR_FD => {
+				super::check_privileged()?;
+				LOOP_DEVICES[self.index]
+					.lock()
+					.take()
+					.ok_or_else(|| errno!(ENXIO))?;
+				Ok(0)
+			}
+
+			_ => Err(errno!(ENOTTY)),
+		}
+	}
+}
+
+impl IO for LoopDeviceHandle {
+	fn get_size(&self) -> u64 {
+		self.get_backing()
+			.map(|(file, _)| file.lock().get_size())
+			.unwrap_or(0)
+	}
+
+	fn read(&mut self, offset: u64, buff: &mut [u8]) -> EResult<(u64, bool)> {
+		let (file, _) = self.get_backing()?;
+		let mut file = file.lock();
+		file.read(offset, buff)
+	}
+
+	fn write(&mut self, offset: u64, buff: &[u8]) -> EResult<u64> {
+		let (file, readonly) = self.get_backing()?;
+		if readonly {
+			return Err(errno!(EROFS));
+		}
+		// The size of the device is fixed by the size of the file
+		let mut file = file.lock();
+		if offset + buff.len() as u64 > file.get_size() {
+			return Err(errno!(ENOSPC));
+		}
+		file.write(offset, buff)
+	}
+
+	fn poll(&mut self, _mask: u32) -> EResult<u32> {
+		Ok(0)
+	}
+}
+
+/// Handle for the control device, through which free loop devices are found.
+#[derive(Default)]
+struct ControlDeviceHandle {}
+
+impl DeviceHandle for ControlDeviceHandle {
+	fn ioctl(
+		&mut self,
+		_mem_space: Arc<IntMutex<MemSpace>>,
+		request: ioctl::Request,
+		_argp: *const c_void,
+	) -> EResult<u32> {
+		match request.get_old_format() {
+			ioctl::LOOP_CTL_GET_FREE => {
+				let index = LOOP_DEVICES
+					.iter()
+					.position(|dev| dev.lock().is_none())
+					.ok_or_else(|| errno!(ENOSPC))?;
+				Ok(index as _)
+			}
+
+			_ => Err(errno!(ENOTTY)),
+		}
+	}
+}
+
+impl IO for ControlDeviceHandle {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, _offset: u64, _buff: &mut [u8]) -> EResult<(u64, bool)> {
+		Err(errno!(EINVAL))
+	}
+
+	fn write(&mut self, _offset: u64, _buff: &[u8]) -> EResult<u64> {
+		Err(errno!(EINVAL))
+	}
+
+	fn poll(&mut self, _mask: u32) -> EResult<u32> {
+		Ok(0)
+	}
+}
+
+/// Creates the device files of every loop devices and of the control device.
+pub fn init() -> EResult<()> {
+	let _major = ManuallyDrop::new(id::alloc_major(DeviceType::Block, Some(LOOP_MAJOR))?);
+
+	for index in 0..LOOP_COUNT {
+		let path_str = crate::format!("/dev/loop{index}")?;
+		let path = Path::from_str(path_str.as_bytes(), false)?;
+		let dev = Device::new(
+			DeviceID {
+				type_: DeviceType::Block,
+				major: LOOP_MAJOR,
+				minor: index as _,
+			},
+			path,
+			LOOP_MODE,
+			LoopDeviceHandle {
+				index,
+			},
+		)?;
+		device::register(dev)?;
+	}
+
+	let path = Path::from_str(b"/dev/loop-control", false)?;
+	let dev = Device::new(
+		DeviceID {
+			type_: DeviceType::Char,
+			major: CONTROL_MAJOR,
+			minor: CONTROL_MINOR,
+		},
+		path,
+		CONTROL_MODE,
+		ControlDeviceHandle::default(),
+	)?;
+	device::register(dev)
+}
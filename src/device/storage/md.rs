@@ -0,0 +1,980 @@
+//! Software RAID (multiple devices) allows to assemble several block devices into a single
+//! virtual block device.
+//!
+//! The following levels are supported:
+//! - RAID0: data is striped across members by chunks, without redundancy
+//! - RAID1: data is mirrored on every member
+//!
+//! Arrays are configured from userspace through the ioctl interface of `/dev/mdX` devices, using
+//! the same requests as Linux:
+//! - to create an array: `SET_ARRAY_INFO`, then `ADD_NEW_DISK` for each member, then `RUN_ARRAY`
+//! - to assemble an existing array: `ADD_NEW_DISK` for each member, then `RUN_ARRAY`
+//!
+//! Each member holds a superblock describing the array, followed by the data area. The on-disk
+//! format is specific to this kernel.
+//!
+//! When a RAID1 array is started with out-of-date members, or when a disk is added to a degraded
+//! array with `HOT_ADD_DISK`, the members are rebuilt from an in-sync member. The progress of the
+//! rebuild is saved in the superblock so that it can be resumed after a reboot.
+
+use super::StorageInterface;
+use super::StorageManager;
+use super::MAX_PARTITIONS;
+use crate::crypto::rand;
+use crate::device;
+use crate::device::id;
+use crate::device::Device;
+use crate::device::DeviceHandle;
+use crate::device::DeviceID;
+use crate::device::DeviceType;
+use crate::errno;
+use crate::errno::EResult;
+use crate::file::path::Path;
+use crate::file::Mode;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::mem_space::MemSpace;
+use crate::syscall::ioctl;
+use crate::time::clock;
+use crate::time::clock::CLOCK_REALTIME;
+use crate::time::unit::TimestampScale;
+use crate::util;
+use crate::util::container::vec::Vec;
+use crate::util::io::IO;
+use crate::util::lock::IntMutex;
+use crate::util::lock::Mutex;
+use crate::util::ptr::arc::Arc;
+use core::cmp::min;
+use core::ffi::c_int;
+use core::ffi::c_void;
+use core::mem::size_of;
+use core::mem::ManuallyDrop;
+use core::num::NonZeroU64;
+use core::ptr;
+
+/// The major number of RAID arrays.
+const MD_MAJOR: u32 = 9;
+/// The number of RAID arrays on the system.
+const MD_COUNT: usize = 4;
+/// The mode of the device file of a RAID array.
+const MD_MODE: Mode = 0o660;
+/// The maximum number of members in an array.
+const MAX_DISKS: u32 = 16;
+
+/// The size of a sector in bytes.
+const SECTOR_SIZE: u64 = 512;
+
+/// The magic number of the superblock.
+const SB_MAGIC: u32 = 0xa92b4efc;
+/// The version of the superblock format.
+const SB_VERSION: u32 = 1;
+/// The offset of the superblock on members, in sectors.
+const SB_OFFSET: u64 = 8;
+/// The offset of the data area on members, in sectors.
+const DATA_OFFSET: u64 = 16;
+/// Value of the recovery offset of members that are in sync.
+const IN_SYNC: u64 = u64::MAX;
+
+/// The number of sectors copied at once during a rebuild.
+const REBUILD_CHUNK: u64 = 128;
+/// The number of rebuild steps between two saves of the rebuild progress.
+const REBUILD_SAVE_INTERVAL: u64 = 64;
+
+/// Disk state: the disk is faulty.
+const MD_DISK_FAULTY: c_int = 1 << 0;
+/// Disk state: the disk is an active member of the array.
+const MD_DISK_ACTIVE: c_int = 1 << 1;
+/// Disk state: the disk is in sync with the array.
+const MD_DISK_SYNC: c_int = 1 << 2;
+
+/// Array state: the array is clean.
+const MD_SB_CLEAN: c_int = 1 << 0;
+
+/// The description of an array, as passed by `GET_ARRAY_INFO` and `SET_ARRAY_INFO`.
+#[derive(Clone, Default)]
+#[repr(C)]
+struct MduArrayInfo {
+	/// The major version of the superblock.
+	major_version: c_int,
+	/// The minor version of the superblock.
+	minor_version: c_int,
+	/// The patch version of the superblock.
+	patch_version: c_int,
+	/// The timestamp of the creation of the array.
+	ctime: c_int,
+	/// The RAID level.
+	level: c_int,
+	/// The size of the data area of each member, in KiB.
+	size: c_int,
+	/// The number of disks attached to the array.
+	nr_disks: c_int,
+	/// The number of members of the array.
+	raid_disks: c_int,
+	/// The minor number of the array.
+	md_minor: c_int,
+	/// If nonzero, the array has no superblock.
+	not_persistent: c_int,
+	/// The timestamp of the last update of the superblock.
+	utime: c_int,
+	/// The state of the array.
+	state: c_int,
+	/// The number of active members.
+	active_disks: c_int,
+	/// The number of working members.
+	working_disks: c_int,
+	/// The number of faulty members.
+	failed_disks: c_int,
+	/// The number of spare disks.
+	spare_disks: c_int,
+	/// The layout of the array, depending on the level.
+	layout: c_int,
+	/// The size of a chunk in bytes.
+	chunk_size: c_int,
+}
+
+/// The description of a disk, as passed by `ADD_NEW_DISK`.
+#[derive(Clone)]
+#[repr(C)]
+struct MduDiskInfo {
+	/// The number of the disk.
+	number: c_int,
+	/// The major number of the disk.
+	major: c_int,
+	/// The minor number of the disk.
+	minor: c_int,
+	/// The role of the disk in the array.
+	raid_disk: c_int,
+	/// The state of the disk.
+	state: c_int,
+}
+
+/// The superblock, written on each member of an array.
+#[derive(Clone)]
+#[repr(C)]
+struct Superblock {
+	/// The magic number, equal to [`SB_MAGIC`].
+	magic: u32,
+	/// The version of the superblock format.
+	version: u32,
+	/// The UUID of the array.
+	set_uuid: [u8; 16],
+	/// The timestamp of the creation of the array, in seconds.
+	ctime: u64,
+	/// The RAID level.
+	level: u32,
+	/// The number of members of the array.
+	raid_disks: u32,
+	/// The size of a chunk, in sectors.
+	chunk_size: u64,
+	/// The offset of the data area on the member, in sectors.
+	data_offset: u64,
+	/// The size of the data area on the member, in sectors.
+	data_size: u64,
+	/// The role of the member in the array.
+	dev_number: u32,
+	/// Padding.
+	padding: u32,
+	/// Counter incremented each time the array is started. Members with a lower value than the
+	/// others are out of date.
+	events: u64,
+	/// The sector up to which the member has been rebuilt. [`IN_SYNC`] if the member is in sync.
+	recovery_offset: u64,
+}
+
+impl Superblock {
+	/// Reads the superblock of the given device.
+	///
+	/// If the device does not contain a valid superblock, the function returns [`errno::EINVAL`].
+	fn read(dev: &Mutex<Device>) -> EResult<Self> {
+		let mut buf = [0u8; SECTOR_SIZE as usize];
+		let (len, _) = dev.lock().read(SB_OFFSET * SECTOR_SIZE, &mut buf)?;
+		if len < buf.len() as u64 {
+			return Err(errno!(EIO));
+		}
+		// Safe because the buffer is larger than the structure, which is read unaligned
+		let sb = unsafe { ptr::read_unaligned(buf.as_ptr() as *const Self) };
+		if sb.magic != SB_MAGIC || sb.version != SB_VERSION {
+			return Err(errno!(EINVAL));
+		}
+		Ok(sb)
+	}
+
+	/// Writes the superblock on the given device.
+	fn write(&self, dev: &Mutex<Device>) -> EResult<()> {
+		let mut buf = [0u8; SECTOR_SIZE as usize];
+		buf[..size_of::<Self>()].copy_from_slice(util::as_slice(self));
+		let len = dev.lock().write(SB_OFFSET * SECTOR_SIZE, &buf)?;
+		if len < buf.len() as u64 {
+			return Err(errno!(EIO));
+		}
+		Ok(())
+	}
+}
+
+/// A RAID level.
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum Level {
+	/// Striping.
+	Raid0,
+	/// Mirroring.
+	Raid1,
+}
+
+impl Level {
+	/// Returns the level corresponding to the given number.
+	///
+	/// If the level is not supported, the function returns [`errno::EINVAL`].
+	fn from_nbr(level: u32) -> EResult<Self> {
+		match level {
+			0 => Ok(Self::Raid0),
+			1 => Ok(Self::Raid1),
+			_ => Err(errno!(EINVAL)),
+		}
+	}
+
+	/// Returns the number of the level.
+	fn as_nbr(&self) -> u32 {
+		match self {
+			Self::Raid0 => 0,
+			Self::Raid1 => 1,
+		}
+	}
+}
+
+/// A member of an array.
+struct Member {
+	/// The underlying device.
+	dev: Arc<Mutex<Device>>,
+	/// The superblock of the member.
+	sb: Superblock,
+}
+
+impl Member {
+	/// Reads sectors of the data area, starting at sector `off`.
+	fn read(&self, off: u64, buf: &mut [u8]) -> EResult<()> {
+		let off = (self.sb.data_offset + off) * SECTOR_SIZE;
+		let (len, _) = self.dev.lock().read(off, buf)?;
+		if len < buf.len() as u64 {
+			return Err(errno!(EIO));
+		}
+		Ok(())
+	}
+
+	/// Writes sectors of the data area, starting at sector `off`.
+	fn write(&self, off: u64, buf: &[u8]) -> EResult<()> {
+		let off = (self.sb.data_offset + off) * SECTOR_SIZE;
+		let len = self.dev.lock().write(off, buf)?;
+		if len < buf.len() as u64 {
+			return Err(errno!(EIO));
+		}
+		Ok(())
+	}
+
+	/// Tells whether the sectors range starting at `off` with `len` sectors is up to date on the
+	/// member.
+	fn is_readable(&self, off: u64, len: u64) -> bool {
+		self.sb.recovery_offset >= off + len
+	}
+
+	/// Returns the state of the member, as returned to userspace.
+	fn get_state(&self) -> c_int {
+		if self.sb.recovery_offset == IN_SYNC {
+			MD_DISK_ACTIVE | MD_DISK_SYNC
+		} else {
+			0
+		}
+	}
+}
+
+/// Returns the number of sectors of the given device.
+fn get_sectors_count(dev: &Mutex<Device>) -> u64 {
+	dev.lock().get_size() / SECTOR_SIZE
+}
+
+/// A running array.
+struct Array {
+	/// The RAID level.
+	level: Level,
+	/// The size of a chunk, in sectors.
+	chunk_size: u64,
+	/// The size of the data area of each member, in sectors.
+	data_size: u64,
+	/// The members of the array, by role. `None` if the member is missing or has failed.
+	members: Vec<Option<Member>>,
+}
+
+impl Array {
+	/// Creates a new array. Previous data on the members is lost.
+	///
+	/// Arguments:
+	/// - `info` is the description of the array passed by userspace.
+	/// - `disks` is the list of disks, with their role in the array.
+	fn create(info: &MduArrayInfo, disks: Vec<(u32, Arc<Mutex<Device>>)>) -> EResult<Self> {
+		let level = Level::from_nbr(info.level as _)?;
+		let raid_disks = info.raid_disks as u32;
+		if !(2..=MAX_DISKS).contains(&raid_disks) || disks.len() != raid_disks as usize {
+			return Err(errno!(EINVAL));
+		}
+		let chunk_size = match level {
+			Level::Raid0 => {
+				let chunk_size = info.chunk_size as u64;
+				if chunk_size < SECTOR_SIZE || !chunk_size.is_power_of_two() {
+					return Err(errno!(EINVAL));
+				}
+				chunk_size / SECTOR_SIZE
+			}
+			Level::Raid1 => 0,
+		};
+
+		// The data area is limited by the smallest member
+		let data_size = disks
+			.iter()
+			.map(|(_, dev)| get_sectors_count(dev).saturating_sub(DATA_OFFSET))
+			.min()
+			.unwrap_or(0);
+		let data_size = match level {
+			Level::Raid0 => data_size - data_size % chunk_size,
+			Level::Raid1 => data_size,
+		};
+		if data_size == 0 {
+			return Err(errno!(ENOSPC));
+		}
+
+		let mut set_uuid = [0; 16];
+		{
+			let mut pool = rand::ENTROPY_POOL.lock();
+			if let Some(pool) = &mut *pool {
+				pool.read(&mut set_uuid, true);
+			}
+		}
+		let ctime = clock::current_time(CLOCK_REALTIME, TimestampScale::Second)?;
+
+		let mut members = Vec::new();
+		for _ in 0..raid_disks {
+			members.push(None)?;
+		}
+		for (role, dev) in disks {
+			let slot = members
+				.get_mut(role as usize)
+				.ok_or_else(|| errno!(EINVAL))?;
+			if slot.is_some() {
+				return Err(errno!(EINVAL));
+			}
+			// The first member of a mirror is the reference from which the others are
+			// synchronized
+			let recovery_offset = if level == Level::Raid1 && role != 0 {
+				0
+			} else {
+				IN_SYNC
+			};
+			*slot = Some(Member {
+				dev,
+				sb: Superblock {
+					magic: SB_MAGIC,
+					version: SB_VERSION,
+					set_uuid,
+					ctime,
+					level: level.as_nbr(),
+					raid_disks,
+					chunk_size,
+					data_offset: DATA_OFFSET,
+					data_size,
+					dev_number: role,
+					padding: 0,
+					events: 0,
+					recovery_offset,
+				},
+			});
+		}
+
+		Ok(Self {
+			level,
+			chunk_size,
+			data_size,
+			members,
+		})
+	}
+
+	/// Assembles an existing array from the superblocks of the given disks.
+	fn assemble(disks: Vec<(u32, Arc<Mutex<Device>>)>) -> EResult<Self> {
+		let mut sbs = Vec::new();
+		for (_, dev) in disks {
+			let sb = Superblock::read(&dev)?;
+			sbs.push((sb, dev))?;
+		}
+		let Some((reference, _)) = sbs.first() else {
+			return Err(errno!(EINVAL));
+		};
+		let level = Level::from_nbr(reference.level)?;
+		let set_uuid = reference.set_uuid;
+		let raid_disks = reference.raid_disks;
+		let chunk_size = reference.chunk_size;
+		let data_size = reference.data_size;
+		if !(2..=MAX_DISKS).contains(&raid_disks) {
+			return Err(errno!(EINVAL));
+		}
+
+		let mut members: Vec<Option<Member>> = Vec::new();
+		for _ in 0..raid_disks {
+			members.push(None)?;
+		}
+		let events = sbs.iter().map(|(sb, _)| sb.events).max().unwrap_or(0);
+		for (mut sb, dev) in sbs {
+			if sb.set_uuid != set_uuid
+				|| sb.raid_disks != raid_disks
+				|| sb.data_size != data_size
+				|| get_sectors_count(&dev) < sb.data_offset + data_size
+			{
+				return Err(errno!(EINVAL));
+			}
+			let slot = members
+				.get_mut(sb.dev_number as usize)
+				.ok_or_else(|| errno!(EINVAL))?;
+			if slot.is_some() {
+				return Err(errno!(EINVAL));
+			}
+
+			// A member which missed the last start of the array might be out of date
+			if sb.events < events {
+				if level == Level::Raid0 {
+					return Err(errno!(EINVAL));
+				}
+				sb.recovery_offset = 0;
+			}
+			*slot = Some(Member {
+				dev,
+				sb,
+			});
+		}
+
+		let complete = members.iter().all(Option::is_some);
+		let in_sync = members
+			.iter()
+			.flatten()
+			.any(|m| m.sb.recovery_offset == IN_SYNC);
+		match level {
+			Level::Raid0 if !complete => return Err(errno!(EINVAL)),
+			Level::Raid1 if !in_sync => return Err(errno!(EINVAL)),
+			_ => {}
+		}
+
+		Ok(Self {
+			level,
+			chunk_size,
+			data_size,
+			members,
+		})
+	}
+
+	/// Marks the start of the array on the superblocks of every members.
+	fn start(&mut self) -> EResult<()> {
+		let events =
+			self.members
+				.iter()
+				.flatten()
+				.map(|m| m.sb.events)
+				.max()
+				.unwrap_or(0) + 1;
+		for m in self.members.iter_mut().flatten() {
+			m.sb.events = events;
+			m.sb.write(&m.dev)?;
+		}
+		Ok(())
+	}
+
+	/// Rebuilds every members that are not in sync, from an in-sync member.
+	///
+	/// Rebuild starts from the recovery offset saved in the superblock of each member.
+	fn rebuild(&mut self) -> EResult<()> {
+		if self.level != Level::Raid1 {
+			return Ok(());
+		}
+		let src = self
+			.members
+			.iter()
+			.position(|m| matches!(m, Some(m) if m.sb.recovery_offset == IN_SYNC))
+			.ok_or_else(|| errno!(EIO))?;
+
+		let mut buf = crate::vec![0; (REBUILD_CHUNK * SECTOR_SIZE) as usize]?;
+		for i in 0..self.members.len() {
+			let mut off = match &self.members[i] {
+				Some(m) if m.sb.recovery_offset != IN_SYNC => m.sb.recovery_offset,
+				_ => continue,
+			};
+			while off < self.data_size {
+				let len = min(REBUILD_CHUNK, self.data_size - off);
+				let buf = &mut buf[..(len * SECTOR_SIZE) as usize];
+				if let Some(src) = &self.members[src] {
+					src.read(off, buf)?;
+				}
+
+				let Some(dst) = &mut self.members[i] else {
+					break;
+				};
+				dst.write(off, buf)?;
+				off += len;
+				dst.sb.recovery_offset = off;
+				if (off / REBUILD_CHUNK) % REBUILD_SAVE_INTERVAL == 0 {
+					dst.sb.write(&dst.dev)?;
+				}
+			}
+
+			if let Some(dst) = &mut self.members[i] {
+				dst.sb.recovery_offset = IN_SYNC;
+				dst.sb.write(&dst.dev)?;
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Adds the given disk to a degraded array, then rebuilds it.
+	fn hot_add(&mut self, dev: Arc<Mutex<Device>>) -> EResult<()> {
+		if self.level != Level::Raid1 {
+			return Err(errno!(EINVAL));
+		}
+		if get_sectors_count(&dev) < DATA_OFFSET + self.data_size {
+			return Err(errno!(ENOSPC));
+		}
+		let Some(reference) = self.members.iter().flatten().next() else {
+			return Err(errno!(EIO));
+		};
+		let mut sb = reference.sb.clone();
+		let role = self
+			.members
+			.iter()
+			.position(Option::is_none)
+			.ok_or_else(|| errno!(EBUSY))?;
+		sb.dev_number = role as _;
+		sb.data_offset = DATA_OFFSET;
+		sb.recovery_offset = 0;
+		sb.write(&dev)?;
+
+		self.members[role] = Some(Member {
+			dev,
+			sb,
+		});
+		self.rebuild()
+	}
+
+	/// Removes the member with the given role after a failure.
+	fn fail(&mut self, role: usize) {
+		crate::println!("md: member {role} failed, removing from array");
+		self.members[role] = None;
+	}
+
+	/// Fills the description of the array `info`.
+	fn fill_info(&self, info: &mut MduArrayInfo) {
+		let active = self.members.iter().flatten().filter(|m| m.get_state() != 0);
+		let active_disks = active.count() as c_int;
+		let nr_disks = self.members.iter().flatten().count() as c_int;
+		let reference = self.members.iter().flatten().next();
+
+		info.major_version = SB_VERSION as _;
+		info.ctime = reference.map(|m| m.sb.ctime as _).unwrap_or(0);
+		info.level = self.level.as_nbr() as _;
+		info.size = (self.data_size / 2) as _;
+		info.nr_disks = nr_disks;
+		info.raid_disks = self.members.len() as _;
+		info.state = MD_SB_CLEAN;
+		info.active_disks = active_disks;
+		info.working_disks = nr_disks;
+		info.failed_disks = self.members.len() as c_int - nr_disks;
+		info.chunk_size = (self.chunk_size * SECTOR_SIZE) as _;
+	}
+
+	/// Performs an I/O operation on the sectors range starting at `offset` with `size` sectors of
+	/// a RAID0 array.
+	///
+	/// For each chunk covering the range, `f` is called with the member, the offset on the member
+	/// and the range of bytes of the buffer corresponding to the chunk.
+	fn stripe_io<F>(&self, offset: u64, size: u64, mut f: F) -> EResult<()>
+	where
+		F: FnMut(&Member, u64, core::ops::Range<usize>) -> EResult<()>,
+	{
+		let disks = self.members.len() as u64;
+		let mut cur = offset;
+		let end = offset + size;
+		while cur < end {
+			let chunk = cur / self.chunk_size;
+			let inner = cur % self.chunk_size;
+			let member = self.members[(chunk % disks) as usize]
+				.as_ref()
+				.ok_or_else(|| errno!(EIO))?;
+			let member_off = (chunk / disks) * self.chunk_size + inner;
+
+			let count = min(self.chunk_size - inner, end - cur);
+			let buf_begin = ((cur - offset) * SECTOR_SIZE) as usize;
+			let buf_end = buf_begin + (count * SECTOR_SIZE) as usize;
+			f(member, member_off, buf_begin..buf_end)?;
+
+			cur += count;
+		}
+		Ok(())
+	}
+}
+
+impl StorageInterface for Array {
+	fn get_block_size(&self) -> NonZeroU64 {
+		NonZeroU64::new(SECTOR_SIZE).unwrap()
+	}
+
+	fn get_blocks_count(&self) -> u64 {
+		match self.level {
+			Level::Raid0 => self.data_size * self.members.len() as u64,
+			Level::Raid1 => self.data_size,
+		}
+	}
+
+	fn read(&mut self, buf: &mut [u8], offset: u64, size: u64) -> EResult<()> {
+		if offset
+			.checked_add(size)
+			.map(|end| end > self.get_blocks_count())
+			.unwrap_or(true)
+		{
+			return Err(errno!(EINVAL));
+		}
+
+		match self.level {
+			Level::Raid0 => self.stripe_io(offset, size, |member, off, range| {
+				member.read(off, &mut buf[range])
+			}),
+
+			Level::Raid1 => {
+				// Try every up-to-date member until one succeeds
+				for role in 0..self.members.len() {
+					let Some(member) = &self.members[role] else {
+						continue;
+					};
+					if !member.is_readable(offset, size) {
+						continue;
+					}
+					match member.read(offset, buf) {
+						Ok(()) => return Ok(()),
+						Err(_) => self.fail(role),
+					}
+				}
+				Err(errno!(EIO))
+			}
+		}
+	}
+
+	fn write(&mut self, buf: &[u8], offset: u64, size: u64) -> EResult<()> {
+		if offset
+			.checked_add(size)
+			.map(|end| end > self.get_blocks_count())
+			.unwrap_or(true)
+		{
+			return Err(errno!(EINVAL));
+		}
+
+		match self.level {
+			Level::Raid0 => self.stripe_io(offset, size, |member, off, range| {
+				member.write(off, &buf[range])
+			}),
+
+			Level::Raid1 => {
+				// Members being rebuilt are written too, so that they stay consistent
+				let mut written = false;
+				for role in 0..self.members.len() {
+					let Some(member) = &self.members[role] else {
+						continue;
+					};
+					match member.write(offset, buf) {
+						Ok(()) => written = true,
+						Err(_) => self.fail(role),
+					}
+				}
+				if !written {
+					return Err(errno!(EIO));
+				}
+				Ok(())
+			}
+		}
+	}
+}
+
+/// The state of an array device.
+struct MdDevice {
+	/// The description of the array to be created, set with `SET_ARRAY_INFO`.
+	info: Option<MduArrayInfo>,
+	/// The disks added with `ADD_NEW_DISK`, with their role, waiting for the array to be started.
+	pending: Vec<(u32, Arc<Mutex<Device>>)>,
+	/// The running array.
+	array: Option<Arc<Mutex<Array>>>,
+}
+
+impl MdDevice {
+	/// Creates a new instance.
+	const fn new() -> Self {
+		Self {
+			info: None,
+			pending: Vec::new(),
+			array: None,
+		}
+	}
+
+	/// Starts the array with the pending disks.
+	///
+	/// `index` is the index of the array device.
+	fn run(&mut self, index: usize) -> EResult<()> {
+		if self.array.is_some() {
+			return Err(errno!(EBUSY));
+		}
+
+		let disks = core::mem::take(&mut self.pending);
+		let mut array = match self.info.take() {
+			Some(info) => Array::create(&info, disks)?,
+			None => Array::assemble(disks)?,
+		};
+		array.start()?;
+		array.rebuild()?;
+
+		let array = Arc::new(Mutex::new(array))?;
+		self.array = Some(array.clone());
+		let prefix = crate::format!("/dev/md{index}p")?;
+		StorageManager::read_partitions(Arc::downgrade(&array) as _, MD_MAJOR, index as _, prefix)
+	}
+
+	/// Stops the array.
+	///
+	/// `index` is the index of the array device.
+	fn stop(&mut self, index: usize) -> EResult<()> {
+		self.info = None;
+		self.pending.clear();
+		if self.array.take().is_some() {
+			StorageManager::clear_partitions(MD_MAJOR, index as _)?;
+		}
+		Ok(())
+	}
+}
+
+/// The default value for `MD_DEVICES`.
+#[allow(clippy::declare_interior_mutable_const)]
+const MD_DEVICES_INIT: Mutex<MdDevice> = Mutex::new(MdDevice::new());
+/// The array devices.
+static MD_DEVICES: [Mutex<MdDevice>; MD_COUNT] = [MD_DEVICES_INIT; MD_COUNT];
+
+/// Returns the block device with the given major and minor numbers, to be used as a member.
+fn get_member_device(major: u32, minor: u32) -> EResult<Arc<Mutex<Device>>> {
+	// An array cannot use another array as member, since this could lead to a deadlock
+	if major == MD_MAJOR {
+		return Err(errno!(EINVAL));
+	}
+	device::get(&DeviceID {
+		type_: DeviceType::Block,
+		major,
+		minor,
+	})
+	.ok_or_else(|| errno!(ENXIO))
+}
+
+/// Handle for the device file of an array.
+struct MdDeviceHandle {
+	/// The index of the array.
+	index: usize,
+}
+
+impl MdDeviceHandle {
+	/// Returns the running array.
+	fn get_array(&self) -> Option<Arc<Mutex<Array>>> {
+		MD_DEVICES[self.index].lock().array.clone()
+	}
+}
+
+impl DeviceHandle for MdDeviceHandle {
+	fn ioctl(
+		&mut self,
+		mem_space: Arc<IntMutex<MemSpace>>,
+		request: ioctl::Request,
+		argp: *const c_void,
+	) -> EResult<u32> {
+		match request.get_old_format() {
+			ioctl::BLKSSZGET => {
+				let mut mem_space_guard = mem_space.lock();
+				let size_ptr: SyscallPtr<u32> = (argp as usize).into();
+				let size_ref = size_ptr
+					.get_mut(&mut mem_space_guard)?
+					.ok_or_else(|| errno!(EFAULT))?;
+				*size_ref = SECTOR_SIZE as _;
+
+				return Ok(0);
+			}
+
+			ioctl::BLKGETSIZE64 => {
+				let size = self.get_size();
+
+				let mut mem_space_guard = mem_space.lock();
+				let size_ptr: SyscallPtr<u64> = (argp as usize).into();
+				let size_ref = size_ptr
+					.get_mut(&mut mem_space_guard)?
+					.ok_or_else(|| errno!(EFAULT))?;
+				*size_ref = size;
+
+				return Ok(0);
+			}
+
+			ioctl::GET_ARRAY_INFO => {
+				let md = MD_DEVICES[self.index].lock();
+				let mut info = MduArrayInfo {
+					md_minor: (self.index * MAX_PARTITIONS) as _,
+					..Default::default()
+				};
+				match (&md.array, &md.info) {
+					(Some(array), _) => array.lock().fill_info(&mut info),
+					(None, Some(pending)) => {
+						info = pending.clone();
+						info.nr_disks = md.pending.len() as _;
+					}
+					(None, None) => return Err(errno!(ENODEV)),
+				}
+
+				let mut mem_space_guard = mem_space.lock();
+				let info_ptr: SyscallPtr<MduArrayInfo> = (argp as usize).into();
+				let info_ref = info_ptr
+					.get_mut(&mut mem_space_guard)?
+					.ok_or_else(|| errno!(EFAULT))?;
+				*info_ref = info;
+
+				return Ok(0);
+			}
+
+			_ => {}
+		}
+
+		// Other requests modify the array
+		super::check_privileged()?;
+		let mut md = MD_DEVICES[self.index].lock();
+		match request.get_old_format() {
+			ioctl::BLKFLSBUF => Ok(0),
+
+			ioctl::BLKRRPART => {
+				let array = md.array.clone().ok_or_else(|| errno!(ENXIO))?;
+				StorageManager::clear_partitions(MD_MAJOR, self.index as _)?;
+				let prefix = crate::format!("/dev/md{}p", self.index)?;
+				StorageManager::read_partitions(
+					Arc::downgrade(&array) as _,
+					MD_MAJOR,
+					self.index as _,
+					prefix,
+				)?;
+
+				Ok(0)
+			}
+
+			ioctl::SET_ARRAY_INFO => {
+				if md.array.is_some() {
+					return Err(errno!(EBUSY));
+				}
+				let info = {
+					let mem_space_guard = mem_space.lock();
+					let info_ptr: SyscallPtr<MduArrayInfo> = (argp as usize).into();
+					info_ptr
+						.get(&mem_space_guard)?
+						.ok_or_else(|| errno!(EFAULT))?
+						.clone()
+				};
+				Level::from_nbr(info.level as _)?;
+				md.info = Some(info);
+
+				Ok(0)
+			}
+
+			ioctl::ADD_NEW_DISK => {
+				if md.array.is_some() {
+					return Err(errno!(EBUSY));
+				}
+				let disk = {
+					let mem_space_guard = mem_space.lock();
+					let disk_ptr: SyscallPtr<MduDiskInfo> = (argp as usize).into();
+					disk_ptr
+						.get(&mem_space_guard)?
+						.ok_or_else(|| errno!(EFAULT))?
+						.clone()
+				};
+				if disk.state & MD_DISK_FAULTY != 0 || disk.raid_disk < 0 {
+					return Err(errno!(EINVAL));
+				}
+				let dev = get_member_device(disk.major as _, disk.minor as _)?;
+				md.pending.push((disk.raid_disk as _, dev))?;
+
+				Ok(0)
+			}
+
+			ioctl::HOT_ADD_DISK => {
+				let array = md.array.as_ref().ok_or_else(|| errno!(ENODEV))?;
+				let dev_nbr = argp as usize as u64;
+				let dev = get_member_device(id::major(dev_nbr), id::minor(dev_nbr))?;
+				array.lock().hot_add(dev)?;
+
+				Ok(0)
+			}
+
+			ioctl::RUN_ARRAY => {
+				md.run(self.index)?;
+				Ok(0)
+			}
+
+			ioctl::STOP_ARRAY => {
+				md.stop(self.index)?;
+				Ok(0)
+			}
+
+			_ => Err(errno!(ENOTTY)),
+		}
+	}
+}
+
+impl IO for MdDeviceHandle {
+	fn get_size(&self) -> u64 {
+		self.get_array()
+			.map(|array| array.lock().get_size())
+			.unwrap_or(0)
+	}
+
+	fn read(&mut self, offset: u64, buff: &mut [u8]) -> EResult<(u64, bool)> {
+		let array = self.get_array().ok_or_else(|| errno!(ENXIO))?;
+		let mut array = array.lock();
+		if offset + buff.len() as u64 > array.get_size() {
+			return Err(errno!(EINVAL));
+		}
+		array.read_bytes(buff, offset)
+	}
+
+	fn write(&mut self, offset: u64, buff: &[u8]) -> EResult<u64> {
+		let array = self.get_array().ok_or_else(|| errno!(ENXIO))?;
+		let mut array = array.lock();
+		if offset + buff.len() as u64 > array.get_size() {
+			return Err(errno!(EINVAL));
+		}
+		array.write_bytes(buff, offset)
+	}
+
+	fn poll(&mut self, _mask: u32) -> EResult<u32> {
+		Ok(0)
+	}
+}
+
+/// Creates the device files of every arrays.
+pub fn init() -> EResult<()> {
+	let _major = ManuallyDrop::new(id::alloc_major(DeviceType::Block, Some(MD_MAJOR))?);
+
+	for index in 0..MD_COUNT {
+		let path_str = crate::format!("/dev/md{index}")?;
+		let path = Path::from_str(path_str.as_bytes(), false)?;
+		let dev = Device::new(
+			DeviceID {
+				type_: DeviceType::Block,
+				major: MD_MAJOR,
+				minor: (index * MAX_PARTITIONS) as _,
+			},
+			path,
+			MD_MODE,
+			MdDeviceHandle {
+				index,
+			},
+		)?;
+		device::register(dev)?;
+	}
+
+	Ok(())
+}
@@ -1,6 +1,9 @@
 //! This module implements storage drivers.
 
+pub mod dm;
 pub mod ide;
+pub mod loopdev;
+pub mod md;
 pub mod partition;
 pub mod pata;
 pub mod ramdisk;
@@ -9,6 +12,7 @@ use crate::device;
 use crate::device::bus::pci;
 use crate::device::id;
 use crate::device::id::MajorBlock;
+use crate::device::manager;
 use crate::device::manager::DeviceManager;
 use crate::device::manager::PhysicalDevice;
 use crate::device::Device;
@@ -22,8 +26,14 @@ use crate::file::path::Path;
 use crate::file::Mode;
 use crate::memory::malloc;
 use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::mem_space::ptr::SyscallSlice;
 use crate::process::mem_space::MemSpace;
+use crate::process::Process;
 use crate::syscall::ioctl;
+use crate::time::clock;
+use crate::time::clock::CLOCK_MONOTONIC;
+use crate::time::unit::Timestamp;
+use crate::time::unit::TimestampScale;
 use crate::util::container::string::String;
 use crate::util::container::vec::Vec;
 use crate::util::io::IO;
@@ -33,6 +43,8 @@ use crate::util::math;
 use crate::util::ptr::arc::Arc;
 use crate::util::ptr::arc::Weak;
 use crate::util::TryClone;
+use core::any::Any;
+use core::any::TypeId;
 use core::cmp::min;
 use core::ffi::c_uchar;
 use core::ffi::c_ulong;
@@ -48,6 +60,21 @@ const STORAGE_MAJOR: u32 = 8;
 const STORAGE_MODE: Mode = 0o660;
 /// The maximum number of partitions in a disk.
 const MAX_PARTITIONS: usize = 16;
+/// The maximum duration of a request on a storage device, in milliseconds.
+const IO_TIMEOUT: Timestamp = 5000;
+/// The number of times a failed request is retried after resetting the device.
+const IO_RETRIES: usize = 2;
+
+/// The size of a sector of data transferred by an ATA command.
+pub const ATA_SECTOR_SIZE: usize = 512;
+/// ATA status bit: an error occurred.
+const ATA_STATUS_ERR: u8 = 0x01;
+/// ATA command: SMART.
+const ATA_CMD_SMART: u8 = 0xb0;
+/// Value of the LBA mid register required by SMART commands.
+const ATA_SMART_LBAM: u8 = 0x4f;
+/// Value of the LBA high register required by SMART commands.
+const ATA_SMART_LBAH: u8 = 0xc2;
 
 /// Hard drive geometry.
 #[derive(Debug)]
@@ -63,6 +90,69 @@ struct HdGeometry {
 	start: c_ulong,
 }
 
+/// The registers of a raw ATA command.
+///
+/// After completion of the command, `command` holds the status register and `feature` holds the
+/// error register.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AtaTaskFile {
+	/// The command register.
+	pub command: u8,
+	/// The features register.
+	pub feature: u8,
+	/// The sectors count register.
+	pub nsect: u8,
+	/// The LBA low register.
+	pub lbal: u8,
+	/// The LBA mid register.
+	pub lbam: u8,
+	/// The LBA high register.
+	pub lbah: u8,
+	/// The device register.
+	pub device: u8,
+}
+
+/// A point in time after which a request on a storage device is considered to have failed.
+///
+/// Since clocks are not updated while interrupts are disabled, the deadline is also reached after
+/// a number of polls of the device corresponding to the timeout, a poll taking at least about a
+/// microsecond.
+pub struct Deadline {
+	/// The timestamp of the deadline on the monotonic clock, in milliseconds.
+	end: Timestamp,
+	/// The remaining number of polls before the deadline is reached.
+	polls: u64,
+}
+
+impl Deadline {
+	/// Returns the deadline for a request starting now.
+	pub fn new() -> Self {
+		let now = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Millisecond).unwrap_or(0);
+		Self {
+			end: now + IO_TIMEOUT,
+			polls: IO_TIMEOUT * 1000,
+		}
+	}
+
+	/// Tells whether the deadline has been reached.
+	///
+	/// This function is meant to be called at each poll of the device.
+	pub fn is_reached(&mut self) -> bool {
+		self.polls = self.polls.saturating_sub(1);
+		if self.polls == 0 {
+			return true;
+		}
+		let now = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Millisecond).unwrap_or(0);
+		now >= self.end
+	}
+}
+
+impl Default for Deadline {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
 /// Trait representing a storage interface.
 ///
 /// A storage block is the atomic unit for I/O access on the storage device.
@@ -94,6 +184,38 @@ pub trait StorageInterface {
 	/// If the offset and size are out of bounds, the function returns an error.
 	fn write(&mut self, buf: &[u8], offset: u64, size: u64) -> Result<(), Errno>;
 
+	/// Writes the data held in the storage's cache to the medium.
+	///
+	/// By default, the function does nothing.
+	fn flush(&mut self) -> Result<(), Errno> {
+		Ok(())
+	}
+
+	/// Resets the device after a failed request, to bring it back to a usable state.
+	///
+	/// If the device cannot be recovered, the function returns [`errno::EIO`] and further requests
+	/// are expected to fail immediately.
+	///
+	/// By default, the device cannot be reset.
+	fn reset(&mut self) -> Result<(), Errno> {
+		Err(errno!(EIO))
+	}
+
+	/// Sends the raw ATA command `tf` to the device, on behalf of userspace.
+	///
+	/// If `buf` is not empty, the command is expected to return as many sectors of data, which
+	/// are written to `buf`. Its size must be a multiple of [`ATA_SECTOR_SIZE`].
+	///
+	/// On completion, `tf` holds the state of the registers, even if the command failed.
+	///
+	/// By default, the device does not support ATA commands.
+	///
+	/// There is no equivalent for NVMe admin commands (`NVME_IOCTL_ADMIN_CMD`) since the kernel
+	/// has no NVMe driver.
+	fn ata_passthrough(&mut self, _tf: &mut AtaTaskFile, _buf: &mut [u8]) -> EResult<()> {
+		Err(errno!(ENOTTY))
+	}
+
 	// Unit testing is done through ramdisk testing
 	/// Reads bytes from storage at offset `offset`, writing the data to `buf`.
 	///
@@ -220,6 +342,46 @@ pub trait StorageInterface {
 	}
 }
 
+/// Checks that the current process is allowed to perform administrative operations on storage
+/// devices.
+///
+/// If not, the function returns [`errno::EACCES`].
+fn check_privileged() -> EResult<()> {
+	let proc_mutex = Process::current_assert();
+	let proc = proc_mutex.lock();
+	if !proc.access_profile.is_privileged() {
+		return Err(errno!(EACCES));
+	}
+	Ok(())
+}
+
+/// Performs the request `f` on the storage interface `interface`, recovering from failures.
+///
+/// If the request fails with an I/O error or times out, the device is reset and the request is
+/// retried, up to [`IO_RETRIES`] times. If the device still fails, the function returns
+/// [`errno::EIO`].
+fn with_recovery<T, F: FnMut(&mut dyn StorageInterface) -> EResult<T>>(
+	interface: &mut dyn StorageInterface,
+	mut f: F,
+) -> EResult<T> {
+	let mut retries = 0;
+	loop {
+		let err = match f(interface) {
+			Err(e) if matches!(e.as_int(), errno::EIO | errno::ETIMEDOUT) => e,
+			res => return res,
+		};
+		if retries >= IO_RETRIES {
+			crate::println!("storage: request failed after {retries} retries ({err})");
+			return Err(errno!(EIO));
+		}
+		retries += 1;
+		if interface.reset().is_err() {
+			crate::println!("storage: cannot reset device after failed request ({err})");
+			return Err(errno!(EIO));
+		}
+	}
+}
+
 /// Handle for the device file of a whole storage device or a partition.
 pub struct StorageDeviceHandle {
 	/// A reference to the storage interface.
@@ -263,6 +425,15 @@ impl StorageDeviceHandle {
 	}
 }
 
+impl StorageDeviceHandle {
+	/// Sends the raw ATA command `tf` to the device. See
+	/// [`StorageInterface::ata_passthrough`].
+	fn ata_passthrough(&self, tf: &mut AtaTaskFile, buf: &mut [u8]) -> EResult<()> {
+		let interface = self.interface.upgrade().ok_or_else(|| errno!(ENODEV))?;
+		interface.lock().ata_passthrough(tf, buf)
+	}
+}
+
 impl DeviceHandle for StorageDeviceHandle {
 	fn ioctl(
 		&mut self,
@@ -308,8 +479,92 @@ impl DeviceHandle for StorageDeviceHandle {
 				Ok(0)
 			}
 
+			ioctl::HDIO_DRIVE_CMD => {
+				check_privileged()?;
+				let args_ptr: SyscallPtr<[u8; 4]> = (argp as usize).into();
+				let args = *args_ptr
+					.get(&mem_space.lock())?
+					.ok_or_else(|| errno!(EFAULT))?;
+
+				let mut tf = AtaTaskFile {
+					command: args[0],
+					feature: args[2],
+					..Default::default()
+				};
+				if args[0] == ATA_CMD_SMART {
+					tf.nsect = args[3];
+					tf.lbal = args[1];
+					tf.lbam = ATA_SMART_LBAM;
+					tf.lbah = ATA_SMART_LBAH;
+				} else {
+					tf.nsect = args[1];
+				}
+				// The size of the data is bounded by the number of sectors, which fits in a byte
+				let mut buf = crate::vec![0; args[3] as usize * ATA_SECTOR_SIZE]?;
+				self.ata_passthrough(&mut tf, &mut buf)?;
+
+				// Write to userspace
+				let mut mem_space_guard = mem_space.lock();
+				let out_ptr: SyscallSlice<u8> = (argp as usize).into();
+				let out = out_ptr
+					.get_mut(&mut mem_space_guard, 4 + buf.len())?
+					.ok_or_else(|| errno!(EFAULT))?;
+				out[..3].copy_from_slice(&[tf.command, tf.feature, tf.nsect]);
+				out[4..].copy_from_slice(&buf);
+
+				if tf.command & ATA_STATUS_ERR != 0 {
+					return Err(errno!(EIO));
+				}
+				Ok(0)
+			}
+
+			ioctl::HDIO_DRIVE_TASK => {
+				check_privileged()?;
+				let args_ptr: SyscallPtr<[u8; 7]> = (argp as usize).into();
+				let args = *args_ptr
+					.get(&mem_space.lock())?
+					.ok_or_else(|| errno!(EFAULT))?;
+
+				let mut tf = AtaTaskFile {
+					command: args[0],
+					feature: args[1],
+					nsect: args[2],
+					lbal: args[3],
+					lbam: args[4],
+					lbah: args[5],
+					device: args[6],
+				};
+				self.ata_passthrough(&mut tf, &mut [])?;
+
+				// Write to userspace
+				let mut mem_space_guard = mem_space.lock();
+				let args_ref = args_ptr
+					.get_mut(&mut mem_space_guard)?
+					.ok_or_else(|| errno!(EFAULT))?;
+				*args_ref = [
+					tf.command,
+					tf.feature,
+					tf.nsect,
+					tf.lbal,
+					tf.lbam,
+					tf.lbah,
+					tf.device,
+				];
+
+				if tf.command & ATA_STATUS_ERR != 0 {
+					return Err(errno!(EIO));
+				}
+				Ok(0)
+			}
+
 			ioctl::BLKRRPART => {
-				StorageManager::clear_partitions(self.major)?;
+				check_privileged()?;
+				// Only the whole device holds a partition table
+				if self.partition.is_some() {
+					return Err(errno!(EINVAL));
+				}
+
+				StorageManager::clear_partitions(self.major, self.storage_id)?;
 				StorageManager::read_partitions(
 					self.interface.clone(),
 					self.major,
@@ -353,6 +608,12 @@ impl DeviceHandle for StorageDeviceHandle {
 				Ok(0)
 			}
 
+			ioctl::BLKFLSBUF => {
+				// I/O is not buffered, so there is nothing to flush
+				check_privileged()?;
+				Ok(0)
+			}
+
 			_ => Err(errno!(ENOTTY)),
 		}
 	}
@@ -394,7 +655,7 @@ impl IO for StorageDeviceHandle {
 				return Err(errno!(EINVAL));
 			}
 
-			interface.read_bytes(buff, start + offset)
+			with_recovery(&mut *interface, |i| i.read_bytes(buff, start + offset))
 		} else {
 			Err(errno!(ENODEV))
 		}
@@ -420,7 +681,7 @@ impl IO for StorageDeviceHandle {
 				return Err(errno!(EINVAL));
 			}
 
-			interface.write_bytes(buff, start + offset)
+			with_recovery(&mut *interface, |i| i.write_bytes(buff, start + offset))
 		} else {
 			Err(errno!(ENODEV))
 		}
@@ -494,8 +755,7 @@ impl StorageManager {
 			let device = Device::new(
 				DeviceID {
 					type_: DeviceType::Block,
-					// TODO use a different major for different storage device types
-					major: STORAGE_MAJOR,
+					major,
 					minor: storage_id * MAX_PARTITIONS as u32 + part_nbr,
 				},
 				path,
@@ -508,15 +768,17 @@ impl StorageManager {
 		Ok(())
 	}
 
-	/// Clears device files for every partitions.
+	/// Clears device files for every partitions of a storage device.
 	///
-	/// `major` is the major number of the devices to be removed.
-	pub fn clear_partitions(major: u32) -> Result<(), Errno> {
+	/// Arguments:
+	/// - `major` is the major number of the devices to be removed.
+	/// - `storage_id` is the ID of the storage device in the manager.
+	pub fn clear_partitions(major: u32, storage_id: u32) -> Result<(), Errno> {
 		for i in 1..MAX_PARTITIONS {
 			device::unregister(&DeviceID {
 				type_: DeviceType::Block,
 				major,
-				minor: i as _,
+				minor: storage_id * MAX_PARTITIONS as u32 + i as u32,
 			})?;
 		}
 
@@ -565,6 +827,14 @@ impl StorageManager {
 		Ok(())
 	}
 
+	/// Flushes the caches of all interfaces to their medium.
+	pub fn flush(&mut self) -> EResult<()> {
+		for iface in self.interfaces.iter() {
+			iface.lock().flush()?;
+		}
+		Ok(())
+	}
+
 	// TODO Function to remove a device
 
 	/// Fills a random buffer `buff` of size `size` with seed `seed`.
@@ -675,6 +945,18 @@ impl StorageManager {
 	}
 }
 
+/// Flushes the caches of all storage devices to their medium.
+pub fn flush_all() -> EResult<()> {
+	let Some(manager_mutex) = manager::get::<StorageManager>() else {
+		return Ok(());
+	};
+	let mut manager = manager_mutex.lock();
+	(&mut *manager as &mut dyn Any)
+		.downcast_mut::<StorageManager>()
+		.unwrap()
+		.flush()
+}
+
 impl DeviceManager for StorageManager {
 	fn on_plug(&mut self, dev: &dyn PhysicalDevice) -> EResult<()> {
 		// Ignoring non-storage devices
@@ -704,4 +986,101 @@ impl DeviceManager for StorageManager {
 		// TODO remove device
 		todo!();
 	}
+
+	fn depends_on(&self, other: TypeId) -> bool {
+		other == TypeId::of::<pci::PCIManager>()
+	}
+
+	fn shutdown(&mut self) -> EResult<()> {
+		self.flush()
+	}
+
+	fn suspend(&mut self) -> EResult<()> {
+		// Data must reach the devices before they lose power
+		self.flush()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	/// A storage interface failing a given number of requests.
+	struct FlakyStorage {
+		/// The number of requests left to fail.
+		failures: usize,
+		/// The error returned by failing requests.
+		error: Errno,
+		/// Tells whether the device can be reset.
+		resettable: bool,
+		/// The number of times the device has been reset.
+		resets: usize,
+	}
+
+	impl StorageInterface for FlakyStorage {
+		fn get_block_size(&self) -> NonZeroU64 {
+			NonZeroU64::new(512).unwrap()
+		}
+
+		fn get_blocks_count(&self) -> u64 {
+			1
+		}
+
+		fn read(&mut self, _buf: &mut [u8], _offset: u64, _size: u64) -> Result<(), Errno> {
+			if self.failures > 0 {
+				self.failures -= 1;
+				return Err(self.error);
+			}
+			Ok(())
+		}
+
+		fn write(&mut self, _buf: &[u8], _offset: u64, _size: u64) -> Result<(), Errno> {
+			Ok(())
+		}
+
+		fn reset(&mut self) -> Result<(), Errno> {
+			self.resets += 1;
+			if self.resettable {
+				Ok(())
+			} else {
+				Err(errno!(EIO))
+			}
+		}
+	}
+
+	/// Performs a read on `storage` with recovery.
+	fn read(storage: &mut FlakyStorage) -> EResult<()> {
+		let mut buf = [0; 512];
+		with_recovery(storage, |s| s.read(&mut buf, 0, 1))
+	}
+
+	#[test_case]
+	fn storage_recovery() {
+		let mut storage = FlakyStorage {
+			failures: IO_RETRIES,
+			error: errno!(ETIMEDOUT),
+			resettable: true,
+			resets: 0,
+		};
+		assert!(read(&mut storage).is_ok());
+		assert_eq!(storage.resets, IO_RETRIES);
+
+		// Too many failures
+		storage.failures = IO_RETRIES + 1;
+		storage.resets = 0;
+		assert_eq!(read(&mut storage).unwrap_err().as_int(), errno::EIO);
+		assert_eq!(storage.resets, IO_RETRIES);
+
+		// The device cannot be recovered
+		storage.failures = 1;
+		storage.resettable = false;
+		assert_eq!(read(&mut storage).unwrap_err().as_int(), errno::EIO);
+
+		// Other errors are not retried
+		storage.failures = 1;
+		storage.error = errno!(EINVAL);
+		storage.resets = 0;
+		assert_eq!(read(&mut storage).unwrap_err().as_int(), errno::EINVAL);
+		assert_eq!(storage.resets, 0);
+	}
 }
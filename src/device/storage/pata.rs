@@ -19,9 +19,13 @@
 
 // TODO Add support for third and fourth bus
 
+use super::AtaTaskFile;
+use super::Deadline;
 use super::StorageInterface;
+use super::ATA_SECTOR_SIZE;
 use crate::device::storage::ide;
 use crate::errno;
+use crate::errno::EResult;
 use crate::errno::Errno;
 use crate::io;
 use crate::util::math;
@@ -139,6 +143,9 @@ pub struct PATAInterface {
 
 	/// The number of sectors on the disk.
 	sectors_count: u64,
+
+	/// Tells whether the drive failed to recover from an error. If set, every request fails.
+	failed: bool,
 }
 
 impl PATAInterface {
@@ -157,6 +164,8 @@ impl PATAInterface {
 			lba48: false,
 
 			sectors_count: 0,
+
+			failed: false,
 		};
 		s.identify()?;
 		Ok(s)
@@ -233,12 +242,20 @@ impl PATAInterface {
 	/// Waits until the drive is not busy anymore.
 	///
 	/// If the drive wasn't busy, the function doesn't do anything.
-	fn wait_busy(&self) {
+	///
+	/// If the drive is still busy when `deadline` is reached, the function returns
+	/// [`errno::ETIMEDOUT`].
+	fn wait_busy(&self, deadline: &mut Deadline) -> EResult<()> {
 		if self.is_floating() {
-			return;
+			return Ok(());
 		}
 
-		while self.get_status() & STATUS_BSY != 0 {}
+		while self.get_status() & STATUS_BSY != 0 {
+			if deadline.is_reached() {
+				return Err(errno!(ETIMEDOUT));
+			}
+		}
+		Ok(())
 	}
 
 	/// Sends the given command on the bus.
@@ -272,15 +289,19 @@ impl PATAInterface {
 	}
 
 	/// Flushes the drive's cache. The device is assumed to be selected.
-	fn cache_flush(&self) {
+	fn cache_flush(&self) -> EResult<()> {
 		self.send_command(COMMAND_CACHE_FLUSH);
-		self.wait_busy();
+		self.wait_busy(&mut Deadline::new())?;
+		if self.get_status() & (STATUS_ERR | STATUS_DF) != 0 {
+			return Err(errno!(EIO));
+		}
+		Ok(())
 	}
 
 	/// Resets both master and slave devices.
 	///
 	/// The current drive may not be selected anymore after this function returns.
-	fn reset(&self) {
+	fn soft_reset(&self) {
 		self.outb(PortOffset::Control(0), 1 << 2);
 		delay(5000);
 
@@ -292,7 +313,7 @@ impl PATAInterface {
 	///
 	/// On error, the function returns a string telling the cause.
 	fn identify(&mut self) -> Result<(), &'static str> {
-		self.reset();
+		self.soft_reset();
 		self.select(true);
 
 		if self.is_floating() {
@@ -312,7 +333,9 @@ impl PATAInterface {
 		if status == 0 {
 			return Err("Drive doesn't exist");
 		}
-		self.wait_busy();
+		let mut deadline = Deadline::new();
+		self.wait_busy(&mut deadline)
+			.map_err(|_| "Timeout while identifying the device")?;
 
 		let lba_mid = self.inb(PortOffset::Ata(LBA_MID_REGISTER_OFFSET));
 		let lba_hi = self.inb(PortOffset::Ata(LBA_HI_REGISTER_OFFSET));
@@ -331,6 +354,10 @@ impl PATAInterface {
 			if status & STATUS_DRQ != 0 {
 				break;
 			}
+
+			if deadline.is_reached() {
+				return Err("Timeout while identifying the device");
+			}
 		}
 
 		let mut data: [u16; 256] = [0; 256];
@@ -362,7 +389,10 @@ impl PATAInterface {
 	/// Waits for the drive to be ready for IO operation.
 	///
 	/// The device is assumed to be selected.
-	fn wait_io(&self) -> Result<(), Errno> {
+	///
+	/// If the drive is not ready when `deadline` is reached, the function returns
+	/// [`errno::ETIMEDOUT`].
+	fn wait_io(&self, deadline: &mut Deadline) -> Result<(), Errno> {
 		loop {
 			let status = self.get_status();
 
@@ -373,6 +403,10 @@ impl PATAInterface {
 			if (status & STATUS_ERR != 0) || (status & STATUS_DF != 0) {
 				return Err(crate::errno!(EIO));
 			}
+
+			if deadline.is_reached() {
+				return Err(errno!(ETIMEDOUT));
+			}
 		}
 	}
 }
@@ -389,6 +423,9 @@ impl StorageInterface for PATAInterface {
 	// TODO clean
 	fn read(&mut self, buf: &mut [u8], offset: u64, size: u64) -> Result<(), Errno> {
 		debug_assert!((buf.len() as u64) >= size * SECTOR_SIZE);
+		if self.failed {
+			return Err(errno!(EIO));
+		}
 
 		// If the offset and size are out of bounds of the disk, return an error
 		if offset >= self.sectors_count || offset + size > self.sectors_count {
@@ -476,8 +513,9 @@ impl StorageInterface for PATAInterface {
 				count = iter_max;
 			}
 
+			// Each sector is given the full timeout, since slow drives may take long to seek
 			for j in 0..count {
-				self.wait_io()?;
+				self.wait_io(&mut Deadline::new())?;
 
 				for k in 0..256 {
 					let index = (((i + j) * 256 + k) * 2) as usize;
@@ -498,6 +536,9 @@ impl StorageInterface for PATAInterface {
 	// TODO clean
 	fn write(&mut self, buf: &[u8], offset: u64, size: u64) -> Result<(), Errno> {
 		debug_assert!((buf.len() as u64) >= size * SECTOR_SIZE);
+		if self.failed {
+			return Err(errno!(EIO));
+		}
 
 		// If the offset and size are out of bounds of the disk, return an error
 		if offset >= self.sectors_count || offset + size > self.sectors_count {
@@ -585,8 +626,9 @@ impl StorageInterface for PATAInterface {
 				count = iter_max;
 			}
 
+			// Each sector is given the full timeout, since slow drives may take long to seek
 			for j in 0..count {
-				self.wait_io()?;
+				self.wait_io(&mut Deadline::new())?;
 
 				for k in 0..256 {
 					let index = (((i + j) * 256 + k) * 2) as usize;
@@ -597,10 +639,85 @@ impl StorageInterface for PATAInterface {
 				}
 			}
 
-			self.cache_flush();
+			self.cache_flush()?;
 			i += count;
 		}
 
 		Ok(())
 	}
+
+	fn flush(&mut self) -> Result<(), Errno> {
+		if self.failed {
+			return Err(errno!(EIO));
+		}
+		self.select(false);
+		self.cache_flush()
+	}
+
+	fn reset(&mut self) -> Result<(), Errno> {
+		if self.failed {
+			return Err(errno!(EIO));
+		}
+		self.soft_reset();
+		self.select(true);
+		// The drive must come back from the reset ready to accept commands
+		let res = self.wait_busy(&mut Deadline::new());
+		if res.is_err() || self.is_floating() || !self.is_ready() {
+			crate::println!("pata: drive does not respond after reset, giving up");
+			self.failed = true;
+			return Err(errno!(EIO));
+		}
+		Ok(())
+	}
+
+	fn ata_passthrough(&mut self, tf: &mut AtaTaskFile, buf: &mut [u8]) -> EResult<()> {
+		if self.failed {
+			return Err(errno!(EIO));
+		}
+		if buf.len() % ATA_SECTOR_SIZE != 0 {
+			return Err(errno!(EINVAL));
+		}
+
+		// Keep the LBA bit and the high bits of the address, but force the drive selection
+		let mut drive = (tf.device & 0x4f) | SELECT_MASTER;
+		if self.slave {
+			drive |= 1 << 4;
+		}
+		self.outb(PortOffset::Ata(DRIVE_REGISTER_OFFSET), drive);
+		self.outb(PortOffset::Ata(FEATURES_REGISTER_OFFSET), tf.feature);
+		self.outb(PortOffset::Ata(SECTORS_COUNT_REGISTER_OFFSET), tf.nsect);
+		self.outb(PortOffset::Ata(LBA_LO_REGISTER_OFFSET), tf.lbal);
+		self.outb(PortOffset::Ata(LBA_MID_REGISTER_OFFSET), tf.lbam);
+		self.outb(PortOffset::Ata(LBA_HI_REGISTER_OFFSET), tf.lbah);
+		self.send_command(tf.command);
+		delay(420);
+
+		let mut deadline = Deadline::new();
+		if buf.is_empty() {
+			self.wait_busy(&mut deadline)?;
+		}
+		for sector in buf.chunks_mut(ATA_SECTOR_SIZE) {
+			match self.wait_io(&mut deadline) {
+				Ok(()) => {}
+				// The command failed, the registers tell why
+				Err(e) if e.as_int() == errno::EIO => break,
+				Err(e) => return Err(e),
+			}
+			for word in sector.chunks_mut(2) {
+				let w = self.inw(PortOffset::Ata(DATA_REGISTER_OFFSET));
+				word.copy_from_slice(&w.to_le_bytes());
+			}
+		}
+
+		*tf = AtaTaskFile {
+			command: self.get_status(),
+			feature: self.get_error(),
+			nsect: self.inb(PortOffset::Ata(SECTORS_COUNT_REGISTER_OFFSET)),
+			lbal: self.inb(PortOffset::Ata(LBA_LO_REGISTER_OFFSET)),
+			lbam: self.inb(PortOffset::Ata(LBA_MID_REGISTER_OFFSET)),
+			lbah: self.inb(PortOffset::Ata(LBA_HI_REGISTER_OFFSET)),
+			device: self.inb(PortOffset::Ata(DRIVE_REGISTER_OFFSET)),
+		};
+		Ok(())
+	}
 }
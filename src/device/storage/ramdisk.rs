@@ -15,6 +15,7 @@ use crate::errno;
 use crate::errno::Errno;
 use crate::file::path::Path;
 use crate::memory::malloc;
+use crate::process::mem_space::ptr::SyscallPtr;
 use crate::process::mem_space::MemSpace;
 use crate::syscall::ioctl;
 use crate::util::container::string::String;
@@ -32,8 +33,6 @@ const RAM_DISK_COUNT: usize = 16;
 /// The size of the ramdisk in bytes.
 const RAM_DISK_SIZE: usize = 4 * 1024 * 1024;
 
-// TODO Add a mechanism to free when cleared?
-
 /// Structure representing a ram disk.
 struct RAMDisk {
 	/// The ram's data.
@@ -138,12 +137,45 @@ impl RAMDiskHandle {
 impl DeviceHandle for RAMDiskHandle {
 	fn ioctl(
 		&mut self,
-		_mem_space: Arc<IntMutex<MemSpace>>,
-		_request: ioctl::Request,
-		_argp: *const c_void,
+		mem_space: Arc<IntMutex<MemSpace>>,
+		request: ioctl::Request,
+		argp: *const c_void,
 	) -> Result<u32, Errno> {
-		// TODO
-		Err(errno!(EINVAL))
+		match request.get_old_format() {
+			ioctl::BLKSSZGET => {
+				let blk_size = self.disk.get_block_size().get();
+
+				let mut mem_space_guard = mem_space.lock();
+				let size_ptr: SyscallPtr<u32> = (argp as usize).into();
+				let size_ref = size_ptr
+					.get_mut(&mut mem_space_guard)?
+					.ok_or_else(|| errno!(EFAULT))?;
+				*size_ref = blk_size as _;
+
+				Ok(0)
+			}
+
+			ioctl::BLKGETSIZE64 => {
+				let mut mem_space_guard = mem_space.lock();
+				let size_ptr: SyscallPtr<u64> = (argp as usize).into();
+				let size_ref = size_ptr
+					.get_mut(&mut mem_space_guard)?
+					.ok_or_else(|| errno!(EFAULT))?;
+				*size_ref = RAM_DISK_SIZE as _;
+
+				Ok(0)
+			}
+
+			ioctl::BLKFLSBUF => {
+				// Flushing a ramdisk frees its content
+				super::check_privileged()?;
+				self.disk.data = None;
+
+				Ok(0)
+			}
+
+			_ => Err(errno!(ENOTTY)),
+		}
 	}
 }
 
@@ -1,27 +1,96 @@
 //! Each TTY or pseudo-TTY has to be associated with a device file in order to
 //! communicate with it.
 
+use crate::device::keyboard;
 use crate::device::DeviceHandle;
 use crate::errno;
 use crate::errno::Errno;
+use crate::file::blocking::PollTable;
 use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::mem_space::ptr::SyscallSlice;
 use crate::process::mem_space::MemSpace;
 use crate::process::pid::Pid;
 use crate::process::signal::Signal;
 use crate::process::signal::SignalHandler;
 use crate::process::Process;
 use crate::syscall::ioctl;
+use crate::tty;
 use crate::tty::termios;
 use crate::tty::termios::Termios;
 use crate::tty::TTYHandle;
 use crate::tty::WinSize;
 use crate::tty::TTY;
+use crate::util::container::vec::Vec;
 use crate::util::io;
 use crate::util::io::IO;
 use crate::util::lock::IntMutex;
 use crate::util::ptr::arc::Arc;
+use crate::vga::font;
+use crate::vga::font::Font;
 use core::ffi::c_void;
 
+/// The state of the virtual terminals, as returned by [`ioctl::VT_GETSTATE`].
+#[repr(C)]
+struct VtStat {
+	/// The number of the active virtual terminal.
+	v_active: u16,
+	/// The signal to send (unused).
+	v_signal: u16,
+	/// A bitmask of the existing virtual terminals.
+	v_state: u16,
+}
+
+/// An entry of the keymap, as used by [`ioctl::KDGKBENT`] and [`ioctl::KDSKBENT`].
+#[repr(C)]
+struct KbEntry {
+	/// The table of the keymap.
+	kb_table: u8,
+	/// The keycode.
+	kb_index: u8,
+	/// The keysym.
+	kb_value: u16,
+}
+
+/// Font operation: sets the console font.
+const KD_FONT_OP_SET: u32 = 0;
+/// The number of bytes between two glyphs in the data of a font operation.
+const FONT_OP_STRIDE: usize = 32;
+
+/// An operation on the console font, as used by [`ioctl::KDFONTOP`].
+#[repr(C)]
+struct ConsoleFontOp {
+	/// The operation to perform.
+	op: u32,
+	/// Flags for the operation (unused).
+	flags: u32,
+	/// The width of glyphs in pixels.
+	width: u32,
+	/// The height of glyphs in pixels.
+	height: u32,
+	/// The number of glyphs.
+	charcount: u32,
+	/// A pointer to the glyphs' bitmaps.
+	data: *mut u8,
+}
+
+/// An entry of a Unicode map, as used by [`ioctl::PIO_UNIMAP`].
+#[repr(C)]
+struct UniPair {
+	/// The Unicode character.
+	unicode: u16,
+	/// The glyph drawing the character.
+	fontpos: u16,
+}
+
+/// A list of entries of a Unicode map, as used by [`ioctl::PIO_UNIMAP`].
+#[repr(C)]
+struct UniMapDesc {
+	/// The number of entries.
+	entry_ct: u16,
+	/// A pointer to the entries.
+	entries: *mut UniPair,
+}
+
 /// Structure representing a TTY device's handle.
 pub struct TTYDeviceHandle {
 	/// The device's TTY. If `None`, using the current process's TTY.
@@ -186,6 +255,141 @@ impl DeviceHandle for TTYDeviceHandle {
 				Ok(0)
 			}
 
+			ioctl::VT_GETSTATE => {
+				let mut mem_space_guard = mem_space.lock();
+				let stat_ptr: SyscallPtr<VtStat> = (argp as usize).into();
+				let stat_ref = stat_ptr
+					.get_mut(&mut mem_space_guard)?
+					.ok_or_else(|| errno!(EFAULT))?;
+				// Bit 0 is unused, as virtual terminals are numbered from `1`
+				let v_state = (1..=tty::VT_COUNT).fold(0, |m, n| m | (1 << n));
+				*stat_ref = VtStat {
+					v_active: tty::current_vt() as _,
+					v_signal: 0,
+					v_state,
+				};
+
+				Ok(0)
+			}
+
+			ioctl::VT_ACTIVATE => {
+				let id = tty::vt_id(argp as usize).ok_or_else(|| errno!(ENXIO))?;
+
+				// Dropping to avoid deadlock since switching locks the target TTY
+				drop(tty);
+				drop(proc);
+				tty::switch(id);
+
+				Ok(0)
+			}
+
+			ioctl::KDGETMODE => {
+				let mut mem_space_guard = mem_space.lock();
+				let mode_ptr: SyscallPtr<i32> = (argp as usize).into();
+				let mode_ref = mode_ptr
+					.get_mut(&mut mem_space_guard)?
+					.ok_or_else(|| errno!(EFAULT))?;
+				*mode_ref = tty.get_mode();
+
+				Ok(0)
+			}
+
+			ioctl::KDSETMODE => {
+				tty.set_mode(argp as i32)?;
+				Ok(0)
+			}
+
+			ioctl::KDGKBENT => {
+				let mut mem_space_guard = mem_space.lock();
+				let entry_ptr: SyscallPtr<KbEntry> = (argp as usize).into();
+				let entry = entry_ptr
+					.get_mut(&mut mem_space_guard)?
+					.ok_or_else(|| errno!(EFAULT))?;
+				entry.kb_value = keyboard::get_keymap_entry(entry.kb_table, entry.kb_index)?;
+
+				Ok(0)
+			}
+
+			ioctl::KDSKBENT => {
+				if !proc.access_profile.is_privileged() {
+					return Err(errno!(EPERM));
+				}
+
+				let mem_space_guard = mem_space.lock();
+				let entry_ptr: SyscallPtr<KbEntry> = (argp as usize).into();
+				let entry = entry_ptr
+					.get(&mem_space_guard)?
+					.ok_or_else(|| errno!(EFAULT))?;
+				keyboard::set_keymap_entry(entry.kb_table, entry.kb_index, entry.kb_value)?;
+
+				Ok(0)
+			}
+
+			ioctl::KDFONTOP => {
+				if !proc.access_profile.is_privileged() {
+					return Err(errno!(EPERM));
+				}
+
+				let mem_space_guard = mem_space.lock();
+				let op_ptr: SyscallPtr<ConsoleFontOp> = (argp as usize).into();
+				let op = op_ptr
+					.get(&mem_space_guard)?
+					.ok_or_else(|| errno!(EFAULT))?;
+				if op.op != KD_FONT_OP_SET || op.width != 8 {
+					return Err(errno!(EINVAL));
+				}
+				let count = op.charcount as usize;
+				if count > font::MAX_GLYPHS {
+					return Err(errno!(EINVAL));
+				}
+				let data_ptr: SyscallSlice<u8> = (op.data as usize).into();
+				let data = data_ptr
+					.get(&mem_space_guard, count * FONT_OP_STRIDE)?
+					.ok_or_else(|| errno!(EFAULT))?;
+				let f = Font::from_raw(op.height as _, FONT_OP_STRIDE, count, data)?;
+				font::load(f);
+
+				Ok(0)
+			}
+
+			ioctl::PIO_UNIMAP => {
+				if !proc.access_profile.is_privileged() {
+					return Err(errno!(EPERM));
+				}
+
+				let mem_space_guard = mem_space.lock();
+				let desc_ptr: SyscallPtr<UniMapDesc> = (argp as usize).into();
+				let desc = desc_ptr
+					.get(&mem_space_guard)?
+					.ok_or_else(|| errno!(EFAULT))?;
+				let entries_ptr: SyscallSlice<UniPair> = (desc.entries as usize).into();
+				let entries = entries_ptr
+					.get(&mem_space_guard, desc.entry_ct as _)?
+					.ok_or_else(|| errno!(EFAULT))?;
+				let mut map = Vec::with_capacity(entries.len())?;
+				for e in entries {
+					// Glyphs beyond the ones the VGA text mode can use are ignored
+					let (Some(c), Ok(glyph)) =
+						(char::from_u32(e.unicode as _), e.fontpos.try_into())
+					else {
+						continue;
+					};
+					map.push((c, glyph))?;
+				}
+				font::add_unicode_map(&map)?;
+
+				Ok(0)
+			}
+
+			ioctl::PIO_UNIMAPCLR => {
+				if !proc.access_profile.is_privileged() {
+					return Err(errno!(EPERM));
+				}
+				font::set_unicode_map(Vec::new());
+
+				Ok(0)
+			}
+
 			_ => Err(errno!(EINVAL)),
 		}
 	}
@@ -196,6 +400,18 @@ impl DeviceHandle for TTYDeviceHandle {
 
 		tty.add_waiting_process(proc, mask)
 	}
+
+	fn register_poll(
+		&mut self,
+		proc: &Process,
+		table: &PollTable,
+		mask: u32,
+	) -> Result<(), Errno> {
+		let tty_mutex = self.tty.clone().unwrap_or_else(|| proc.get_tty());
+		let mut tty = tty_mutex.lock();
+
+		Ok(tty.register_poll(table, mask)?)
+	}
 }
 
 impl IO for TTYDeviceHandle {
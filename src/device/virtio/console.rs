@@ -0,0 +1,715 @@
+//! Driver for the virtio console device, using the legacy PCI interface.
+//!
+//! The device provides ports, each being a bidirectional channel between the guest and the host.
+//! Ports are used either as consoles or as general-purpose channels, for example by a guest
+//! agent answering requests from the hypervisor.
+//!
+//! Each port has a virtqueue for each direction. When the device supports multiple ports, the
+//! driver and the device also exchange control messages on a dedicated pair of virtqueues to
+//! announce ports and their properties.
+//!
+//! Ports are exposed as follows:
+//! - console ports as `/dev/hvcN`. Kernel output is also written to them
+//! - named ports as `/dev/virtio-ports/<name>`
+//! - other ports as `/dev/vportNpM`, where `N` is the index of the device and `M` the index of
+//! the port
+//!
+//! Like other drivers without interrupts, the driver is updated from the clock's interrupt
+//! handler, which wakes processes waiting on ports.
+
+use super::Virtqueue;
+use crate::device;
+use crate::device::bar::BAR;
+use crate::device::driver::Driver;
+use crate::device::driver::Probe;
+use crate::device::id;
+use crate::device::id::MajorBlock;
+use crate::device::manager::PhysicalDevice;
+use crate::device::Device;
+use crate::device::DeviceHandle;
+use crate::device::DeviceID;
+use crate::device::DeviceType;
+use crate::errno;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::event;
+use crate::event::CallbackResult;
+use crate::file::blocking::BlockHandler;
+use crate::file::blocking::PollTable;
+use crate::file::path::Path;
+use crate::file::Mode;
+use crate::process::mem_space::MemSpace;
+use crate::process::Process;
+use crate::syscall::ioctl;
+use crate::time::hw;
+use crate::time::hw::pit;
+use crate::util::container::bitfield::Bitfield;
+use crate::util::container::string::String;
+use crate::util::container::vec::Vec;
+use crate::util::io;
+use crate::util::io::IO;
+use crate::util::lock::IntMutex;
+use crate::util::lock::Mutex;
+use crate::util::ptr::arc::Arc;
+use crate::util::TryClone;
+use core::cmp::min;
+use core::ffi::c_void;
+use core::hint;
+use core::mem::ManuallyDrop;
+
+/// The device ID of the transitional virtio console device.
+const DEVICE_ID: u16 = 0x1003;
+
+/// Feature: the device supports multiple ports and control messages.
+const FEATURE_MULTIPORT: u32 = 1 << 1;
+/// Offset of the maximum number of ports in the device's configuration.
+const CONFIG_MAX_NR_PORTS: usize = super::REG_DEVICE_CONFIG + 4;
+
+/// Control event: the driver is ready.
+const EVENT_DEVICE_READY: u16 = 0;
+/// Control event: the device adds a port.
+const EVENT_DEVICE_ADD: u16 = 1;
+/// Control event: the device removes a port.
+const EVENT_DEVICE_REMOVE: u16 = 2;
+/// Control event: the driver has set up a port, or failed to.
+const EVENT_PORT_READY: u16 = 3;
+/// Control event: the port is a console.
+const EVENT_CONSOLE_PORT: u16 = 4;
+/// Control event: a side of the port has been opened or closed.
+const EVENT_PORT_OPEN: u16 = 6;
+/// Control event: the name of the port, following the message.
+const EVENT_PORT_NAME: u16 = 7;
+/// The size of the header of a control message.
+const CONTROL_MSG_SIZE: usize = 8;
+
+/// The maximum number of ports per device.
+const MAX_PORTS: usize = 8;
+/// The number of buffers in each virtqueue of a port.
+const PORT_BUFFERS: usize = 16;
+/// The size of a buffer of a port.
+const PORT_BUFFER_SIZE: usize = 256;
+/// The number of buffers in each control virtqueue.
+const CONTROL_BUFFERS: usize = 8;
+/// The size of a buffer of a control virtqueue.
+const CONTROL_BUFFER_SIZE: usize = 256;
+
+/// The number of attempts to find a free transmit buffer before giving up.
+const WRITE_ATTEMPTS: usize = 1000;
+/// The delay without control messages after which the device is considered set up, in
+/// milliseconds.
+const SETUP_IDLE: u32 = 10;
+/// The maximum delay for the device to announce its ports, in milliseconds. Ports announced later
+/// are set up on the next tick.
+const SETUP_TIMEOUT: u32 = 500;
+
+/// The major number of console devices.
+const HVC_MAJOR: u32 = 229;
+/// The mode of the device files of ports.
+const PORT_MODE: Mode = 0o600;
+
+/// A receive virtqueue.
+///
+/// Buffers filled by the device are handed back to it only once they have been entirely
+/// consumed.
+struct RxQueue {
+	/// The virtqueue.
+	queue: Virtqueue,
+	/// The buffer being consumed with the length of its data, if any.
+	current: Option<(u16, usize)>,
+	/// The offset of the next byte to be consumed in the current buffer.
+	off: usize,
+}
+
+impl RxQueue {
+	/// Sets up the queue at index `index` on the device.
+	fn new(bar: &BAR, index: u16, count: usize, buf_size: usize) -> EResult<Self> {
+		Ok(Self {
+			queue: Virtqueue::new(bar, index, count, buf_size)?,
+			current: None,
+			off: 0,
+		})
+	}
+
+	/// Makes all the buffers available to the device.
+	///
+	/// This function must be called once, after the driver is ready.
+	fn fill(&mut self) {
+		for desc in 0..self.queue.count() {
+			self.queue.push(desc as _, self.queue.buf_size(), true);
+		}
+	}
+
+	/// Tells whether data is available.
+	fn has_data(&self) -> bool {
+		self.current.is_some() || self.queue.has_used()
+	}
+
+	/// Reads available data into `buf`, returning the number of bytes read.
+	fn read(&mut self, buf: &mut [u8]) -> usize {
+		let mut total = 0;
+		while total < buf.len() {
+			let (desc, len) = match self.current {
+				Some(current) => current,
+				None => {
+					let Some(current) = self.queue.pop_used() else {
+						break;
+					};
+					self.current = Some(current);
+					self.off = 0;
+					current
+				}
+			};
+			let l = min(len - self.off, buf.len() - total);
+			buf[total..(total + l)].copy_from_slice(&self.queue.buffer(desc)[self.off..][..l]);
+			self.off += l;
+			total += l;
+			if self.off >= len {
+				self.current = None;
+				self.queue.push(desc, self.queue.buf_size(), true);
+			}
+		}
+		total
+	}
+
+	/// Reads the next buffer filled by the device into `buf`, returning the length of its data.
+	///
+	/// If no buffer is available, the function returns `None`.
+	fn read_message(&mut self, buf: &mut [u8]) -> Option<usize> {
+		let (desc, len) = self.queue.pop_used()?;
+		let len = min(len, buf.len());
+		buf[..len].copy_from_slice(&self.queue.buffer(desc)[..len]);
+		self.queue.push(desc, self.queue.buf_size(), true);
+		Some(len)
+	}
+}
+
+/// A transmit virtqueue.
+struct TxQueue {
+	/// The virtqueue.
+	queue: Virtqueue,
+	/// The set of buffers currently used by the device.
+	used: Bitfield,
+}
+
+impl TxQueue {
+	/// Sets up the queue at index `index` on the device.
+	fn new(bar: &BAR, index: u16, count: usize, buf_size: usize) -> EResult<Self> {
+		let queue = Virtqueue::new(bar, index, count, buf_size)?;
+		let used = Bitfield::new(queue.count())?;
+		Ok(Self {
+			queue,
+			used,
+		})
+	}
+
+	/// Takes back the buffers the device is done with.
+	fn reclaim(&mut self) {
+		while let Some((desc, _)) = self.queue.pop_used() {
+			self.used.clear(desc as _);
+		}
+	}
+
+	/// Tells whether a buffer is free for sending.
+	fn can_send(&mut self) -> bool {
+		self.reclaim();
+		self.used.find_clear().is_some()
+	}
+
+	/// Sends as much of `data` as fits in a buffer, returning the number of bytes sent.
+	///
+	/// If no buffer is free, the function returns zero.
+	fn send(&mut self, data: &[u8]) -> usize {
+		self.reclaim();
+		let Some(desc) = self.used.find_clear() else {
+			return 0;
+		};
+		let len = min(data.len(), self.queue.buf_size());
+		self.queue.buffer(desc as _)[..len].copy_from_slice(&data[..len]);
+		self.used.set(desc);
+		self.queue.push(desc as _, len, false);
+		len
+	}
+}
+
+/// A port of a virtio console device.
+struct Port {
+	/// The queue of data received from the host.
+	rx: RxQueue,
+	/// The queue of data sent to the host.
+	tx: TxQueue,
+
+	/// Tells whether the device has announced the port.
+	added: bool,
+	/// Tells whether the port is a console.
+	console: bool,
+	/// Tells whether the host side of the port is open.
+	host_connected: bool,
+	/// The name of the port, if any.
+	name: Option<String>,
+
+	/// The handler of processes waiting on the port.
+	block_handler: BlockHandler,
+}
+
+impl Port {
+	/// Sets up the port using the queues at indexes `rx` and `tx` on the device.
+	fn new(bar: &BAR, rx: u16, tx: u16) -> EResult<Self> {
+		Ok(Self {
+			rx: RxQueue::new(bar, rx, PORT_BUFFERS, PORT_BUFFER_SIZE)?,
+			tx: TxQueue::new(bar, tx, PORT_BUFFERS, PORT_BUFFER_SIZE)?,
+
+			added: false,
+			console: false,
+			host_connected: false,
+			name: None,
+
+			block_handler: BlockHandler::new(),
+		})
+	}
+}
+
+/// A virtio console device.
+struct VirtioConsole {
+	/// The BAR giving access to the device's registers.
+	bar: BAR,
+	/// The queues of control messages, if the device supports multiple ports.
+	control: Option<(RxQueue, TxQueue)>,
+	/// The ports of the device, by index.
+	ports: Vec<Port>,
+}
+
+impl VirtioConsole {
+	/// Initializes the device and sets up its ports.
+	fn new(bar: BAR) -> EResult<Self> {
+		let features = super::init_device(&bar, FEATURE_MULTIPORT);
+		let multiport = features & FEATURE_MULTIPORT != 0;
+		let ports_count = if multiport {
+			let max = bar.read::<u32>(CONFIG_MAX_NR_PORTS) as usize;
+			min(max, MAX_PORTS)
+		} else {
+			1
+		};
+
+		let (control, ports) = match Self::setup_queues(&bar, multiport, ports_count) {
+			Ok(queues) => queues,
+			Err(e) => {
+				super::reset(&bar);
+				return Err(e);
+			}
+		};
+		super::driver_ok(&bar);
+
+		let mut dev = Self {
+			bar,
+			control,
+			ports,
+		};
+		if let Some((rx, _)) = &mut dev.control {
+			rx.fill();
+		}
+		for port in dev.ports.iter_mut() {
+			port.rx.fill();
+		}
+
+		if multiport {
+			// The device announces its ports in response
+			dev.send_control(0, EVENT_DEVICE_READY, 1);
+			let mut idle = 0;
+			for _ in 0..SETUP_TIMEOUT {
+				if dev.process_control() > 0 {
+					idle = 0;
+				} else {
+					idle += 1;
+					if idle >= SETUP_IDLE {
+						break;
+					}
+				}
+				pit::busy_wait(1);
+			}
+		} else {
+			// Without control messages, the only port is a console
+			let port = &mut dev.ports[0];
+			port.added = true;
+			port.console = true;
+			port.host_connected = true;
+		}
+		Ok(dev)
+	}
+
+	/// Sets up the virtqueues of the device.
+	///
+	/// Arguments:
+	/// - `multiport` tells whether control queues are used.
+	/// - `ports_count` is the number of ports.
+	///
+	/// The function returns the control queues, if any, and the ports.
+	fn setup_queues(
+		bar: &BAR,
+		multiport: bool,
+		ports_count: usize,
+	) -> EResult<(Option<(RxQueue, TxQueue)>, Vec<Port>)> {
+		// Port 0 uses the queues 0 and 1, control uses the queues 2 and 3, then each port uses
+		// the next two queues
+		let mut ports = Vec::with_capacity(ports_count)?;
+		ports.push(Port::new(bar, 0, 1)?)?;
+		let control = if multiport {
+			Some((
+				RxQueue::new(bar, 2, CONTROL_BUFFERS, CONTROL_BUFFER_SIZE)?,
+				TxQueue::new(bar, 3, CONTROL_BUFFERS, CONTROL_BUFFER_SIZE)?,
+			))
+		} else {
+			None
+		};
+		for i in 1..ports_count {
+			let rx = (2 + i * 2) as u16;
+			ports.push(Port::new(bar, rx, rx + 1)?)?;
+		}
+		Ok((control, ports))
+	}
+
+	/// Sends a control message to the device.
+	///
+	/// Arguments:
+	/// - `id` is the index of the port the message is about.
+	/// - `event` is the event.
+	/// - `value` is the value associated with the event.
+	fn send_control(&mut self, id: u32, event: u16, value: u16) {
+		let Some((_, tx)) = &mut self.control else {
+			return;
+		};
+		let mut msg = [0; CONTROL_MSG_SIZE];
+		msg[0..4].copy_from_slice(&id.to_le_bytes());
+		msg[4..6].copy_from_slice(&event.to_le_bytes());
+		msg[6..8].copy_from_slice(&value.to_le_bytes());
+		for _ in 0..WRITE_ATTEMPTS {
+			if tx.send(&msg) > 0 {
+				return;
+			}
+			hint::spin_loop();
+		}
+	}
+
+	/// Handles the control message `msg`.
+	fn handle_control(&mut self, msg: &[u8]) {
+		if msg.len() < CONTROL_MSG_SIZE {
+			return;
+		}
+		let id = u32::from_le_bytes([msg[0], msg[1], msg[2], msg[3]]);
+		let event = u16::from_le_bytes([msg[4], msg[5]]);
+		let value = u16::from_le_bytes([msg[6], msg[7]]);
+		let Some(port) = self.ports.get_mut(id as usize) else {
+			if event == EVENT_DEVICE_ADD {
+				// Too many ports
+				self.send_control(id, EVENT_PORT_READY, 0);
+			}
+			return;
+		};
+
+		match event {
+			EVENT_DEVICE_ADD => {
+				// TODO Expose ports added after the device has been probed. This requires
+				// registering devices out of interrupt context
+				port.added = true;
+				self.send_control(id, EVENT_PORT_READY, 1);
+				// Device files cannot tell when they are opened, so the guest side is always
+				// considered open
+				self.send_control(id, EVENT_PORT_OPEN, 1);
+			}
+			EVENT_DEVICE_REMOVE => {
+				port.added = false;
+				port.host_connected = false;
+				port.block_handler.wake_processes(io::POLLIN | io::POLLHUP);
+			}
+			EVENT_CONSOLE_PORT => port.console = true,
+			EVENT_PORT_OPEN => {
+				port.host_connected = value != 0;
+				port.block_handler.wake_processes(io::POLLIN | io::POLLHUP);
+			}
+			EVENT_PORT_NAME => {
+				let name = &msg[CONTROL_MSG_SIZE..];
+				let len = name.iter().position(|c| *c == 0).unwrap_or(name.len());
+				port.name = String::try_from(&name[..len]).ok();
+			}
+			_ => {}
+		}
+	}
+
+	/// Handles pending control messages, returning the number of handled messages.
+	fn process_control(&mut self) -> usize {
+		let mut count = 0;
+		let mut msg = [0; CONTROL_BUFFER_SIZE];
+		loop {
+			let Some((rx, _)) = &mut self.control else {
+				break;
+			};
+			let Some(len) = rx.read_message(&mut msg) else {
+				break;
+			};
+			self.handle_control(&msg[..len]);
+			count += 1;
+		}
+		count
+	}
+
+	/// Handles control messages and wakes processes waiting on ports.
+	fn tick(&mut self) {
+		self.process_control();
+		for port in self.ports.iter_mut().filter(|p| p.added) {
+			if port.rx.has_data() {
+				port.block_handler.wake_processes(io::POLLIN);
+			}
+			if port.tx.can_send() {
+				port.block_handler.wake_processes(io::POLLOUT);
+			}
+		}
+	}
+}
+
+impl Drop for VirtioConsole {
+	fn drop(&mut self) {
+		super::reset(&self.bar);
+	}
+}
+
+/// The list of virtio console devices, by index.
+static CONSOLES: IntMutex<Vec<VirtioConsole>> = IntMutex::new(Vec::new());
+
+/// Writes `buf` to every console port.
+///
+/// This function does not block. If transmit buffers remain full, the rest of the data is
+/// dropped.
+pub fn write_console(buf: &[u8]) {
+	let mut consoles = CONSOLES.lock();
+	for dev in consoles.iter_mut() {
+		for port in dev.ports.iter_mut().filter(|p| p.added && p.console) {
+			let mut off = 0;
+			let mut attempts = 0;
+			while off < buf.len() && attempts < WRITE_ATTEMPTS {
+				let len = port.tx.send(&buf[off..]);
+				if len == 0 {
+					attempts += 1;
+					hint::spin_loop();
+				}
+				off += len;
+			}
+		}
+	}
+}
+
+/// Handle for the device of a port.
+struct PortDeviceHandle {
+	/// The index of the device.
+	dev: usize,
+	/// The index of the port on the device.
+	port: usize,
+}
+
+impl PortDeviceHandle {
+	/// Calls `f` with the port.
+	///
+	/// If the port has been removed, the function returns [`errno::ENODEV`].
+	fn with_port<R, F: FnOnce(&mut Port) -> EResult<R>>(&self, f: F) -> EResult<R> {
+		let mut consoles = CONSOLES.lock();
+		let port = consoles
+			.get_mut(self.dev)
+			.and_then(|dev| dev.ports.get_mut(self.port))
+			.filter(|port| port.added)
+			.ok_or_else(|| errno!(ENODEV))?;
+		f(port)
+	}
+}
+
+impl DeviceHandle for PortDeviceHandle {
+	fn ioctl(
+		&mut self,
+		_mem_space: Arc<IntMutex<MemSpace>>,
+		_request: ioctl::Request,
+		_argp: *const c_void,
+	) -> EResult<u32> {
+		// TODO Attach a TTY to console ports so that they support terminal ioctls
+		Err(errno!(ENOTTY))
+	}
+
+	fn add_waiting_process(&mut self, proc: &mut Process, mask: u32) -> Result<(), Errno> {
+		self.with_port(|port| port.block_handler.add_waiting_process(proc, mask))
+	}
+
+	fn register_poll(
+		&mut self,
+		_proc: &Process,
+		table: &PollTable,
+		mask: u32,
+	) -> Result<(), Errno> {
+		self.with_port(|port| Ok(port.block_handler.register(table, mask)?))
+	}
+}
+
+impl IO for PortDeviceHandle {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, _offset: u64, buff: &mut [u8]) -> EResult<(u64, bool)> {
+		self.with_port(|port| {
+			let len = port.rx.read(buff);
+			// Without data, reaching the end of file while the host side is closed
+			Ok((len as _, len == 0 && !port.host_connected))
+		})
+	}
+
+	fn write(&mut self, _offset: u64, buff: &[u8]) -> EResult<u64> {
+		self.with_port(|port| {
+			let mut total = 0;
+			while total < buff.len() {
+				let len = port.tx.send(&buff[total..]);
+				if len == 0 {
+					break;
+				}
+				total += len;
+			}
+			Ok(total as _)
+		})
+	}
+
+	fn poll(&mut self, mask: u32) -> EResult<u32> {
+		self.with_port(|port| {
+			let mut res = 0;
+			if mask & io::POLLIN != 0 && port.rx.has_data() {
+				res |= io::POLLIN;
+			}
+			if mask & io::POLLOUT != 0 && port.tx.can_send() {
+				res |= io::POLLOUT;
+			}
+			if !port.host_connected {
+				res |= io::POLLHUP;
+			}
+			Ok(res)
+		})
+	}
+}
+
+/// The major block of console devices.
+static HVC_MAJOR_BLOCK: Mutex<Option<MajorBlock>> = Mutex::new(None);
+/// The major block of other ports.
+static VPORT_MAJOR_BLOCK: Mutex<Option<MajorBlock>> = Mutex::new(None);
+
+/// Allocates a device ID in the major block `block`.
+///
+/// If the block is not allocated yet, it is allocated with the major number `major`. If `None`,
+/// any major number is used.
+fn alloc_id(block: &Mutex<Option<MajorBlock>>, major: Option<u32>) -> EResult<DeviceID> {
+	let mut block = block.lock();
+	if block.is_none() {
+		*block = Some(id::alloc_major(DeviceType::Char, major)?);
+	}
+	let block = block.as_mut().unwrap();
+	Ok(DeviceID {
+		type_: DeviceType::Char,
+		major: block.get_major(),
+		minor: block.alloc_minor(None)?,
+	})
+}
+
+/// Registers the device of the port at index `port` on the device at index `dev`.
+///
+/// `name` is the name of the port, if any.
+fn register_port(dev: usize, port: usize, console: bool, name: Option<&[u8]>) -> EResult<()> {
+	let (id, path) = if console {
+		let id = alloc_id(&HVC_MAJOR_BLOCK, Some(HVC_MAJOR))?;
+		let path = crate::format!("/dev/hvc{}", id.minor)?;
+		(id, path)
+	} else {
+		let id = alloc_id(&VPORT_MAJOR_BLOCK, None)?;
+		let path = match name {
+			// The name is used as a filename
+			Some(name) if !name.is_empty() && !name.contains(&b'/') => {
+				let mut path = String::try_from(b"/dev/virtio-ports/")?;
+				path.push_str(name)?;
+				path
+			}
+			_ => crate::format!("/dev/vport{dev}p{port}")?,
+		};
+		(id, path)
+	};
+	let path = Path::from_str(path.as_bytes(), false)?;
+	let handle = PortDeviceHandle {
+		dev,
+		port,
+	};
+	let dev = Device::new(id, path, PORT_MODE, handle)?;
+	device::register(dev)
+}
+
+/// Initializes the device using the BAR `bar`, then registers its ports.
+fn probe(bar: BAR) -> EResult<()> {
+	let console = VirtioConsole::new(bar)?;
+	// Collect the ports to register, so that devices are not registered while holding the lock
+	let mut ports = Vec::new();
+	for (i, port) in console.ports.iter().enumerate().filter(|(_, p)| p.added) {
+		let name = port.name.as_ref().map(|n| n.try_clone()).transpose()?;
+		ports.push((i, port.console, name))?;
+	}
+	let index = {
+		let mut consoles = CONSOLES.lock();
+		consoles.push(console)?;
+		consoles.len() - 1
+	};
+	for (i, console, name) in ports {
+		register_port(index, i, console, name.as_ref().map(|n| n.as_bytes()))?;
+	}
+	Ok(())
+}
+
+/// The driver of the virtio console device.
+pub struct VirtioConsoleDriver {}
+
+impl Driver for VirtioConsoleDriver {
+	fn get_name(&self) -> &str {
+		"virtio-console"
+	}
+
+	fn on_plug(&self, dev: &dyn PhysicalDevice) -> Probe {
+		if dev.get_vendor_id() != super::VENDOR_ID || dev.get_device_id() != DEVICE_ID {
+			return Probe::Ignored;
+		}
+		let Some(bar) = dev.get_bars().first().cloned().flatten() else {
+			return Probe::Ignored;
+		};
+		dev.enable_bus_master();
+
+		let res = probe(bar);
+		match res {
+			Ok(()) => Probe::Bound,
+			Err(e) => {
+				crate::println!("virtio-console: cannot initialize device: {e}");
+				Probe::Ignored
+			}
+		}
+	}
+
+	fn on_unplug(&self, _dev: &dyn PhysicalDevice) {}
+}
+
+/// Initializes the driver.
+pub(super) fn init() -> EResult<()> {
+	device::driver::register(VirtioConsoleDriver {})?;
+
+	// Periodically update devices
+	let vector = {
+		let hw_clocks = hw::CLOCKS.lock();
+		hw_clocks
+			.get(b"rtc".as_slice())
+			.map(|rtc| rtc.get_interrupt_vector())
+	};
+	if let Some(vector) = vector {
+		let hook = event::register_callback(vector, |_, _, _, _| {
+			for dev in CONSOLES.lock().iter_mut() {
+				dev.tick();
+			}
+			CallbackResult::Continue
+		})?;
+		let _ = ManuallyDrop::new(hook);
+	}
+
+	Ok(())
+}
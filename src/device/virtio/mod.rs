@@ -0,0 +1,298 @@
+//! Support for virtio devices, using the legacy PCI interface.
+//!
+//! A virtio device exchanges data with the driver through virtqueues. Each virtqueue is made of a
+//! table of descriptors pointing to buffers, a ring in which the driver places the descriptors
+//! it makes available to the device, and a ring in which the device places the descriptors it
+//! is done with.
+//!
+//! Drivers of this module use a fixed set of buffers per virtqueue, each descriptor always
+//! pointing to the same buffer.
+
+pub mod console;
+
+use crate::device::bar::BAR;
+use crate::errno;
+use crate::errno::EResult;
+use crate::memory;
+use crate::memory::buddy;
+use crate::util::math;
+use core::cmp::min;
+use core::ffi::c_void;
+use core::mem::size_of;
+use core::ptr;
+use core::ptr::NonNull;
+use core::slice;
+use core::sync::atomic;
+
+/// The vendor ID of virtio devices.
+pub const VENDOR_ID: u16 = 0x1af4;
+
+/// Register: the features offered by the device.
+const REG_DEVICE_FEATURES: usize = 0x00;
+/// Register: the features requested by the driver.
+const REG_GUEST_FEATURES: usize = 0x04;
+/// Register: the physical page number of the selected queue.
+const REG_QUEUE_ADDRESS: usize = 0x08;
+/// Register: the size of the selected queue.
+const REG_QUEUE_SIZE: usize = 0x0c;
+/// Register: the index of the selected queue.
+const REG_QUEUE_SELECT: usize = 0x0e;
+/// Register: writing the index of a queue notifies the device that buffers are available.
+const REG_QUEUE_NOTIFY: usize = 0x10;
+/// Register: the status of the device.
+const REG_DEVICE_STATUS: usize = 0x12;
+/// Register: the beginning of the device-specific configuration, when MSI-X is disabled.
+pub const REG_DEVICE_CONFIG: usize = 0x14;
+
+/// Device status: the driver has detected the device.
+const STATUS_ACKNOWLEDGE: u8 = 1;
+/// Device status: the driver knows how to drive the device.
+const STATUS_DRIVER: u8 = 2;
+/// Device status: the driver is ready.
+const STATUS_DRIVER_OK: u8 = 4;
+
+/// Descriptor flag: the buffer is write-only for the device.
+const VIRTQ_DESC_F_WRITE: u16 = 2;
+
+/// The alignment of the used ring in the legacy interface.
+const QUEUE_ALIGN: usize = 4096;
+
+/// A descriptor of a virtqueue.
+#[repr(C)]
+struct VirtqDesc {
+	/// The physical address of the buffer.
+	addr: u64,
+	/// The length of the buffer.
+	len: u32,
+	/// Descriptor flags.
+	flags: u16,
+	/// The index of the next descriptor, if chained.
+	next: u16,
+}
+
+/// An element of the used ring.
+#[repr(C)]
+struct VirtqUsedElem {
+	/// The index of the head of the used descriptors chain.
+	id: u32,
+	/// The number of bytes written by the device.
+	len: u32,
+}
+
+/// Resets the device, then negotiates features.
+///
+/// `features` is the bitfield of features supported by the driver.
+///
+/// The function returns the bitfield of features supported by both the driver and the device.
+pub fn init_device(bar: &BAR, features: u32) -> u32 {
+	bar.write::<u8>(REG_DEVICE_STATUS, 0);
+	bar.write::<u8>(REG_DEVICE_STATUS, STATUS_ACKNOWLEDGE as _);
+	bar.write::<u8>(REG_DEVICE_STATUS, (STATUS_ACKNOWLEDGE | STATUS_DRIVER) as _);
+	let features = bar.read::<u32>(REG_DEVICE_FEATURES) as u32 & features;
+	bar.write::<u32>(REG_GUEST_FEATURES, features as _);
+	features
+}
+
+/// Tells the device the driver is ready. Virtqueues must be set up beforehand.
+pub fn driver_ok(bar: &BAR) {
+	bar.write::<u8>(
+		REG_DEVICE_STATUS,
+		(STATUS_ACKNOWLEDGE | STATUS_DRIVER | STATUS_DRIVER_OK) as _,
+	);
+}
+
+/// Resets the device so that it stops using the memory of its virtqueues.
+pub fn reset(bar: &BAR) {
+	bar.write::<u8>(REG_DEVICE_STATUS, 0);
+}
+
+/// A virtqueue, with a buffer for each of its descriptors.
+///
+/// The device must be reset with [`reset`] before the queue is dropped.
+pub struct Virtqueue {
+	/// The BAR giving access to the device's registers.
+	bar: BAR,
+	/// The index of the queue on the device.
+	index: u16,
+
+	/// The memory of the virtqueue.
+	mem: NonNull<c_void>,
+	/// The allocation order of the virtqueue's memory.
+	mem_order: buddy::FrameOrder,
+	/// The size of the virtqueue, in number of descriptors.
+	size: usize,
+	/// The offset of the used ring in the virtqueue's memory.
+	used_off: usize,
+
+	/// The memory of the buffers.
+	bufs: NonNull<c_void>,
+	/// The allocation order of the buffers' memory.
+	bufs_order: buddy::FrameOrder,
+	/// The size of a buffer in bytes.
+	buf_size: usize,
+	/// The number of buffers, which is also the number of descriptors in use.
+	count: usize,
+
+	/// The index of the next element to be consumed in the used ring.
+	last_used: u16,
+}
+
+impl Virtqueue {
+	/// Sets up the queue at index `index` on the device.
+	///
+	/// Arguments:
+	/// - `count` is the number of buffers to allocate. If the queue is smaller, the number is
+	/// reduced accordingly.
+	/// - `buf_size` is the size of each buffer in bytes.
+	///
+	/// This function must be called before [`driver_ok`].
+	pub fn new(bar: &BAR, index: u16, count: usize, buf_size: usize) -> EResult<Self> {
+		bar.write::<u16>(REG_QUEUE_SELECT, index as _);
+		let size = bar.read::<u16>(REG_QUEUE_SIZE) as usize;
+		if size == 0 {
+			return Err(errno!(ENODEV));
+		}
+		let count = min(count, size);
+
+		let avail_off = size_of::<VirtqDesc>() * size;
+		let avail_size = size_of::<u16>() * (3 + size);
+		let used_off = math::ceil_div(avail_off + avail_size, QUEUE_ALIGN) * QUEUE_ALIGN;
+		let used_size = size_of::<u16>() * 3 + size_of::<VirtqUsedElem>() * size;
+		let pages = math::ceil_div(used_off + used_size, memory::PAGE_SIZE);
+		let mem_order = buddy::get_order(pages);
+		let mem = buddy::alloc_kernel(mem_order)?;
+		let bufs_order = buddy::get_order(math::ceil_div(count * buf_size, memory::PAGE_SIZE));
+		let bufs = match buddy::alloc_kernel(bufs_order) {
+			Ok(bufs) => bufs,
+			Err(e) => {
+				buddy::free_kernel(mem.as_ptr(), mem_order);
+				return Err(e.into());
+			}
+		};
+		unsafe {
+			ptr::write_bytes(mem.as_ptr() as *mut u8, 0, buddy::get_frame_size(mem_order));
+		}
+
+		let q = Self {
+			bar: bar.clone(),
+			index,
+
+			mem,
+			mem_order,
+			size,
+			used_off,
+
+			bufs,
+			bufs_order,
+			buf_size,
+			count,
+
+			last_used: 0,
+		};
+
+		// Each descriptor points to its own buffer
+		for i in 0..count {
+			let buf_addr = memory::kern_to_phys(q.buf_ptr(i as _)) as usize;
+			unsafe {
+				*q.field::<VirtqDesc>(size_of::<VirtqDesc>() * i) = VirtqDesc {
+					addr: buf_addr as _,
+					len: buf_size as _,
+					flags: 0,
+					next: 0,
+				};
+			}
+		}
+
+		let mem_phys = memory::kern_to_phys(q.mem.as_ptr()) as usize;
+		bar.write::<u32>(REG_QUEUE_ADDRESS, (mem_phys / memory::PAGE_SIZE) as _);
+		Ok(q)
+	}
+
+	/// Returns a pointer to the field at offset `off` in the virtqueue's memory.
+	fn field<T>(&self, off: usize) -> *mut T {
+		(self.mem.as_ptr() as usize + off) as *mut T
+	}
+
+	/// Returns a pointer to the buffer of the descriptor `desc`.
+	fn buf_ptr(&self, desc: u16) -> *mut u8 {
+		(self.bufs.as_ptr() as usize + desc as usize * self.buf_size) as *mut u8
+	}
+
+	/// Returns the number of buffers of the queue.
+	pub fn count(&self) -> usize {
+		self.count
+	}
+
+	/// Returns the size of a buffer in bytes.
+	pub fn buf_size(&self) -> usize {
+		self.buf_size
+	}
+
+	/// Returns the buffer of the descriptor `desc`.
+	///
+	/// The buffer must not be accessed while it is available to the device.
+	pub fn buffer(&mut self, desc: u16) -> &mut [u8] {
+		unsafe { slice::from_raw_parts_mut(self.buf_ptr(desc), self.buf_size) }
+	}
+
+	/// Makes the buffer of the descriptor `desc` available to the device, then notifies it.
+	///
+	/// Arguments:
+	/// - `len` is the length of the data in the buffer.
+	/// - `writable` tells whether the device writes to the buffer instead of reading from it.
+	pub fn push(&mut self, desc: u16, len: usize, writable: bool) {
+		let avail_off = size_of::<VirtqDesc>() * self.size;
+		unsafe {
+			let d = self.field::<VirtqDesc>(size_of::<VirtqDesc>() * desc as usize);
+			(*d).len = min(len, self.buf_size) as _;
+			(*d).flags = if writable { VIRTQ_DESC_F_WRITE } else { 0 };
+
+			let idx_ptr = self.field::<u16>(avail_off + size_of::<u16>());
+			let idx = ptr::read_volatile(idx_ptr);
+			let ring_off = avail_off + size_of::<u16>() * (2 + idx as usize % self.size);
+			ptr::write_volatile(self.field::<u16>(ring_off), desc);
+			atomic::fence(atomic::Ordering::SeqCst);
+			ptr::write_volatile(idx_ptr, idx.wrapping_add(1));
+			atomic::fence(atomic::Ordering::SeqCst);
+		}
+		self.bar.write::<u16>(REG_QUEUE_NOTIFY, self.index as _);
+	}
+
+	/// Tells whether the device has placed descriptors in the used ring that have not been
+	/// consumed yet.
+	pub fn has_used(&self) -> bool {
+		let idx_ptr = self.field::<u16>(self.used_off + size_of::<u16>());
+		unsafe { ptr::read_volatile(idx_ptr) != self.last_used }
+	}
+
+	/// Consumes the next descriptor in the used ring.
+	///
+	/// The function returns the descriptor and the number of bytes the device wrote to its
+	/// buffer. If the device is not done with any descriptor, the function returns `None`.
+	pub fn pop_used(&mut self) -> Option<(u16, usize)> {
+		if !self.has_used() {
+			return None;
+		}
+		atomic::fence(atomic::Ordering::SeqCst);
+		let elem_off = self.used_off
+			+ size_of::<u16>() * 2
+			+ size_of::<VirtqUsedElem>() * (self.last_used as usize % self.size);
+		let elem = unsafe { ptr::read_volatile(self.field::<VirtqUsedElem>(elem_off)) };
+		self.last_used = self.last_used.wrapping_add(1);
+		Some((elem.id as _, min(elem.len as usize, self.buf_size)))
+	}
+}
+
+impl Drop for Virtqueue {
+	fn drop(&mut self) {
+		buddy::free_kernel(self.bufs.as_ptr(), self.bufs_order);
+		buddy::free_kernel(self.mem.as_ptr(), self.mem_order);
+	}
+}
+
+/// Initializes virtio drivers.
+///
+/// This function must be called before buses are scanned so that drivers can detect devices.
+pub(super) fn init() -> EResult<()> {
+	console::init()
+}
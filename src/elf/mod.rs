@@ -148,6 +148,13 @@ pub const STT_FILE: u8 = 4;
 /// Thread-Local Storage (TLS) symbol.
 pub const STT_TLS: u8 = 6;
 
+/// The symbol is not visible outside of the object file containing its definition.
+pub const STB_LOCAL: u8 = 0;
+/// The symbol is visible to all object files being combined.
+pub const STB_GLOBAL: u8 = 1;
+/// Like global symbols, but with a lower precedence.
+pub const STB_WEAK: u8 = 2;
+
 /// No relocation.
 pub const R_386_NONE: u8 = 0;
 /// Relocation type.
@@ -345,6 +352,16 @@ impl ELF32Sym {
 	pub fn is_defined(&self) -> bool {
 		self.st_shndx != 0
 	}
+
+	/// Returns the type of the symbol (`STT_*`).
+	pub fn get_type(&self) -> u8 {
+		self.st_info & 0xf
+	}
+
+	/// Returns the binding of the symbol (`STB_*`).
+	pub fn get_bind(&self) -> u8 {
+		self.st_info >> 4
+	}
 }
 
 /// Returns a reference to the kernel section with name `name`.
@@ -455,31 +472,29 @@ pub fn get_symbol_name(strtab_section: &ELF32SectionHeader, offset: u32) -> &'st
 	}
 }
 
-/// Returns the name of the kernel function for the given instruction pointer.
+/// Iterates over the symbols of the kernel, calling the given closure `f` with each symbol and
+/// its name. Symbols without a name are given an empty name.
 ///
 /// Arguments:
 /// - `sections` is a pointer to the ELF sections of the kernel in the virtual memory.
 /// - `sections_count` is the number of sections in the kernel.
 /// - `shndx` is the index of the section containing section names.
 /// - `entsize` is the size of section entries.
-/// - `inst` is the pointer to the instruction on the virtual memory.
-///
-/// If the name cannot be retrieved, the function returns `None`.
-pub fn get_function_name(
+/// - `f` is the closure to be called for each symbols. If it returns `false`, the iteration
+///   stops.
+pub fn foreach_kernel_symbol<F>(
 	sections: *const c_void,
 	sections_count: usize,
 	shndx: usize,
 	entsize: usize,
-	inst: *const c_void,
-) -> Option<&'static [u8]> {
-	let strtab_section = get_section(
-		sections,
-		sections_count,
-		shndx,
-		entsize,
-		".strtab".as_bytes(),
-	)?;
-	let mut func_name: Option<&'static [u8]> = None;
+	mut f: F,
+) where
+	F: FnMut(&'static ELF32Sym, &'static [u8]) -> bool,
+{
+	let Some(strtab_section) = get_section(sections, sections_count, shndx, entsize, b".strtab")
+	else {
+		return;
+	};
 
 	foreach_sections(
 		sections,
@@ -497,14 +512,12 @@ pub fn get_function_name(
 			let mut i: usize = 0;
 			while i < hdr.sh_size as usize {
 				let sym = unsafe { &*(ptr.add(i) as *const ELF32Sym) };
-
-				let value = sym.st_value as usize;
-				let size = sym.st_size as usize;
-				if (inst as usize) >= value && (inst as usize) < (value + size) {
-					if sym.st_name != 0 {
-						func_name = Some(get_symbol_name(strtab_section, sym.st_name));
-					}
-
+				let name = if sym.st_name != 0 {
+					get_symbol_name(strtab_section, sym.st_name)
+				} else {
+					b""
+				};
+				if !f(sym, name) {
 					return false;
 				}
 
@@ -514,8 +527,58 @@ pub fn get_function_name(
 			true
 		},
 	);
+}
 
-	func_name
+/// Returns the kernel symbol containing the given instruction pointer, along with its name.
+///
+/// Arguments:
+/// - `sections` is a pointer to the ELF sections of the kernel in the virtual memory.
+/// - `sections_count` is the number of sections in the kernel.
+/// - `shndx` is the index of the section containing section names.
+/// - `entsize` is the size of section entries.
+/// - `inst` is the pointer to the instruction on the virtual memory.
+///
+/// If no symbol contains the instruction, the function returns `None`.
+pub fn get_function_symbol(
+	sections: *const c_void,
+	sections_count: usize,
+	shndx: usize,
+	entsize: usize,
+	inst: *const c_void,
+) -> Option<(&'static ELF32Sym, &'static [u8])> {
+	let mut symbol = None;
+	foreach_kernel_symbol(sections, sections_count, shndx, entsize, |sym, name| {
+		let value = sym.st_value as usize;
+		let size = sym.st_size as usize;
+		if (inst as usize) >= value && (inst as usize) < (value + size) {
+			symbol = Some((sym, name));
+			return false;
+		}
+		true
+	});
+	symbol
+}
+
+/// Returns the name of the kernel function for the given instruction pointer.
+///
+/// Arguments:
+/// - `sections` is a pointer to the ELF sections of the kernel in the virtual memory.
+/// - `sections_count` is the number of sections in the kernel.
+/// - `shndx` is the index of the section containing section names.
+/// - `entsize` is the size of section entries.
+/// - `inst` is the pointer to the instruction on the virtual memory.
+///
+/// If the name cannot be retrieved, the function returns `None`.
+pub fn get_function_name(
+	sections: *const c_void,
+	sections_count: usize,
+	shndx: usize,
+	entsize: usize,
+	inst: *const c_void,
+) -> Option<&'static [u8]> {
+	get_function_symbol(sections, sections_count, shndx, entsize, inst)
+		.map(|(_, name)| name)
+		.filter(|name| !name.is_empty())
 }
 
 /// Returns the kernel symbol with the name `name`.
@@ -2,6 +2,8 @@
 
 use core::fmt;
 use core::fmt::Formatter;
+#[cfg(config_debug_debug)]
+use core::panic::Location;
 
 /// Structure representing a location at which an errno was raised.
 #[cfg(config_debug_debug)]
@@ -26,6 +28,20 @@ impl fmt::Display for ErrnoLocation {
 	}
 }
 
+/// The maximum number of contexts an errno can carry.
+#[cfg(config_debug_debug)]
+const CONTEXT_MAX: usize = 4;
+
+/// A context attached to an errno while it is propagated, to help tracing where it comes from.
+#[cfg(config_debug_debug)]
+#[derive(Clone, Copy, Debug)]
+pub struct ErrnoContext {
+	/// A message describing the operation that failed.
+	pub msg: &'static str,
+	/// The location at which the context was attached.
+	pub location: &'static Location<'static>,
+}
+
 /// An Unix errno.
 #[derive(Clone, Copy, Debug)]
 pub struct Errno {
@@ -35,6 +51,11 @@ pub struct Errno {
 	/// The location at which the errno was raised.
 	#[cfg(config_debug_debug)]
 	location: ErrnoLocation,
+	/// The contexts attached to the errno, from the innermost to the outermost.
+	///
+	/// Contexts attached when the array is full are discarded.
+	#[cfg(config_debug_debug)]
+	context: [Option<ErrnoContext>; CONTEXT_MAX],
 }
 
 impl From<AllocError> for Errno {
@@ -65,6 +86,7 @@ impl Errno {
 		Self {
 			errno,
 			location,
+			context: [None; CONTEXT_MAX],
 		}
 	}
 
@@ -73,6 +95,46 @@ impl Errno {
 		self.errno
 	}
 
+	/// Attaches the context message `msg` to the errno, along with the location of the caller.
+	///
+	/// Contexts do not change the errno returned to userspace. They are recorded only in debug
+	/// mode, without allocating memory. Otherwise, the function does nothing.
+	#[cfg(not(config_debug_debug))]
+	#[inline(always)]
+	pub fn context(self, _msg: &'static str) -> Self {
+		self
+	}
+
+	/// Attaches the context message `msg` to the errno, along with the location of the caller.
+	///
+	/// Contexts do not change the errno returned to userspace. They are recorded only in debug
+	/// mode, without allocating memory. Otherwise, the function does nothing.
+	#[cfg(config_debug_debug)]
+	#[track_caller]
+	pub fn context(mut self, msg: &'static str) -> Self {
+		if let Some(slot) = self.context.iter_mut().find(|c| c.is_none()) {
+			*slot = Some(ErrnoContext {
+				msg,
+				location: Location::caller(),
+			});
+		}
+		self
+	}
+
+	/// Tells whether a context has been attached to the errno.
+	///
+	/// Outside of debug mode, the function always returns `false`.
+	pub fn has_context(&self) -> bool {
+		#[cfg(config_debug_debug)]
+		{
+			self.context[0].is_some()
+		}
+		#[cfg(not(config_debug_debug))]
+		{
+			false
+		}
+	}
+
 	/// Returns the error message for the given errno.
 	pub fn strerror(&self) -> &'static str {
 		match self.errno {
@@ -208,6 +270,10 @@ impl Errno {
 			ERFKILL => "Operation not possible due to RF-kill",
 			EHWPOISON => "Memory page has hardware error",
 
+			ERESTARTSYS | ERESTARTNOINTR | ERESTARTNOHAND | ERESTART_RESTARTBLOCK => {
+				"Interrupted system call should be restarted"
+			}
+
 			_ => "Unknown error",
 		}
 	}
@@ -235,7 +301,28 @@ impl fmt::Display for Errno {
 			self.errno,
 			self.strerror(),
 			self.location
-		)
+		)?;
+		for c in self.context.iter().flatten() {
+			write!(f, " <- {} (at: {})", c.msg, c.location)?;
+		}
+		Ok(())
+	}
+}
+
+/// Extension trait to attach contexts to errors of results. See [`Errno::context`].
+pub trait ResultContext {
+	/// Attaches the context message `msg` to the error, if any.
+	fn context(self, msg: &'static str) -> Self;
+}
+
+impl<T> ResultContext for EResult<T> {
+	#[track_caller]
+	#[inline(always)]
+	fn context(self, msg: &'static str) -> Self {
+		match self {
+			Ok(val) => Ok(val),
+			Err(e) => Err(e.context(msg)),
+		}
 	}
 }
 
@@ -535,6 +622,18 @@ pub const ERFKILL: i32 = 132;
 /// Memory page has hardware error.
 pub const EHWPOISON: i32 = 133;
 
+// Kernel-internal errnos, never returned to userspace
+
+/// Interrupted system call, to be restarted if the signal's handler has `SA_RESTART` or if no
+/// handler is called.
+pub const ERESTARTSYS: i32 = 512;
+/// Interrupted system call, to be restarted regardless of the signal's action.
+pub const ERESTARTNOINTR: i32 = 513;
+/// Interrupted system call, to be restarted only if no handler is called.
+pub const ERESTARTNOHAND: i32 = 514;
+/// Interrupted system call, to be resumed with `restart_syscall` if no handler is called.
+pub const ERESTART_RESTARTBLOCK: i32 = 516;
+
 /// An alias to [`core::result::Result`] with [`Errno`] as error type.
 pub type EResult<T> = Result<T, Errno>;
 
@@ -2,9 +2,9 @@
 
 use crate::crypto::rand;
 use crate::crypto::rand::EntropyPool;
+use crate::debug::gdbstub;
 use crate::errno::AllocResult;
 use crate::idt;
-use crate::idt::pic;
 use crate::process::regs::Regs;
 use crate::process::tss::TSS;
 use crate::util;
@@ -14,6 +14,8 @@ use crate::util::lock::*;
 use core::ffi::c_void;
 use core::intrinsics::unlikely;
 use core::ptr::NonNull;
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering::Relaxed;
 
 /// The list of interrupt error messages ordered by index of the corresponding
 /// interrupt vector.
@@ -120,6 +122,14 @@ const CALLBACKS_INIT: IntMutex<Vec<CallbackWrapper>> = IntMutex::new(Vec::new())
 static CALLBACKS: [IntMutex<Vec<CallbackWrapper>>; idt::ENTRIES_COUNT as _] =
 	[CALLBACKS_INIT; idt::ENTRIES_COUNT as _];
 
+/// The total number of interruptions handled since boot.
+static INTERRUPTS_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns the total number of interruptions handled since boot.
+pub fn get_interrupts_count() -> usize {
+	INTERRUPTS_COUNT.load(Relaxed)
+}
+
 /// Registers the given callback and returns a reference to it.
 ///
 /// The latest registered callback is executed last. Thus, callback that are registered before can
@@ -178,9 +188,17 @@ fn feed_entropy<T>(pool: &mut EntropyPool, val: &T) {
 /// - `code` is an optional code associated with the interrupt
 /// If the interrupt type doesn't have a code, the value is `0`
 /// - `regs` is the state of the registers at the moment of the interrupt
+/// For exceptions without code, changes to `ebp`, `eip` and `eflags` are applied on return
 /// - `ring` tells the ring at which the code was running
 #[no_mangle]
-extern "C" fn event_handler(id: u32, code: u32, ring: u32, regs: &Regs) {
+extern "C" fn event_handler(id: u32, code: u32, ring: u32, regs: &mut Regs) {
+	INTERRUPTS_COUNT.fetch_add(1, Relaxed);
+
+	// The debugger must not depend on locks held by the interrupted code
+	if ring < 3 && gdbstub::handle_trap(id, regs) {
+		return;
+	}
+
 	// Feed entropy pool
 	{
 		let mut pool = rand::ENTROPY_POOL.lock();
@@ -201,7 +219,7 @@ extern "C" fn event_handler(id: u32, code: u32, ring: u32, regs: &Regs) {
 			CallbackResult::Idle => {
 				// Unlock to avoid deadlocks
 				if id >= ERROR_MESSAGES.len() as u32 {
-					pic::end_of_interrupt((id - ERROR_MESSAGES.len() as u32) as _);
+					idt::end_of_interrupt((id - ERROR_MESSAGES.len() as u32) as _);
 				}
 				drop(callbacks);
 
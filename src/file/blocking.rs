@@ -1,12 +1,137 @@
 //! When a resource is blocking, a process trying to use it must be put in `Sleeping` state until
 //! the resource is available.
 
+use crate::errno::AllocResult;
 use crate::errno::Errno;
 use crate::process;
 use crate::process::pid::Pid;
+use crate::process::scheduler;
 use crate::process::Process;
+use crate::time::timer;
+use crate::time::unit::Timestamp;
 use crate::util::container::hashmap::HashMap;
+use crate::util::container::vec::Vec;
 use crate::util::io;
+use crate::util::lock::IntMutex;
+use crate::util::ptr::arc::Arc;
+use crate::util::ptr::arc::Weak;
+use core::sync::atomic;
+use core::sync::atomic::AtomicBool;
+
+/// The number of poll event bits for which occurrences are counted.
+const EVENTS_COUNT: usize = 16;
+
+/// Counters of the number of times each poll event occurred on a resource.
+///
+/// Comparing two snapshots allows an edge-triggered observer to detect events that occurred in
+/// between, even if the level of the resource is the same on both snapshots.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct EventCounters([u32; EVENTS_COUNT]);
+
+impl EventCounters {
+	/// Increments the counters of the events in the given mask.
+	pub fn increment(&mut self, mask: u32) {
+		for (i, count) in self.0.iter_mut().enumerate() {
+			if mask & (1 << i) != 0 {
+				*count = count.wrapping_add(1);
+			}
+		}
+	}
+
+	/// Returns the mask of events that occurred since the snapshot `prev` was taken.
+	pub fn since(&self, prev: &Self) -> u32 {
+		self.0
+			.iter()
+			.zip(prev.0.iter())
+			.enumerate()
+			.filter(|(_, (curr, prev))| curr != prev)
+			.fold(0, |mask, (i, _)| mask | (1 << i))
+	}
+}
+
+/// The part of a [`PollTable`] which is referenced by the resources it is registered on.
+#[derive(Debug)]
+struct Waker {
+	/// The PID of the process waiting on the table.
+	pid: Pid,
+	/// Tells whether an event occurred since the process last went to sleep.
+	woken: AtomicBool,
+}
+
+impl Waker {
+	/// Notifies the waker that an event occurred, waking up the process if sleeping.
+	fn wake(&self) {
+		self.woken.store(true, atomic::Ordering::Release);
+		if let Some(proc_mutex) = Process::get_by_pid(self.pid) {
+			proc_mutex.lock().wake();
+		}
+	}
+}
+
+/// A table allowing a process to wait for events on several resources at once, as done by `poll`
+/// and `select`.
+///
+/// Unlike [`BlockHandler::add_waiting_process`], registering the table on a resource doesn't make
+/// the process sleep. The process sleeps only when calling [`PollTable::wait`], until an event
+/// occurs on any of the resources. Registrations remain until the table is dropped.
+///
+/// To avoid missing an event, the table must be registered on a resource before checking its
+/// readiness.
+#[derive(Debug)]
+pub struct PollTable {
+	/// The waker, shared with the resources the table is registered on.
+	waker: Arc<Waker>,
+}
+
+impl PollTable {
+	/// Creates a table for the process with PID `pid`.
+	pub fn new(pid: Pid) -> AllocResult<Self> {
+		Ok(Self {
+			waker: Arc::new(Waker {
+				pid,
+				woken: AtomicBool::new(false),
+			})?,
+		})
+	}
+
+	/// Makes the process sleep until an event occurs on one of the resources the table is
+	/// registered on.
+	///
+	/// `deadline` is the timestamp on the monotonic clock, in nanoseconds, at which the process
+	/// is woken up even if no event occurred. If `None`, the process sleeps indefinitely.
+	///
+	/// If an event occurred since the last call, the function returns immediately.
+	///
+	/// Since this function ends the current tick, the caller must ensure no critical mutex is
+	/// locked.
+	pub fn wait(
+		&self,
+		proc_mutex: &IntMutex<Process>,
+		deadline: Option<Timestamp>,
+	) -> AllocResult<()> {
+		if let Some(deadline) = deadline {
+			timer::wake_at(self.waker.pid, deadline)?;
+		}
+		let sleep = {
+			let mut proc = proc_mutex.lock();
+			let woken = self.waker.woken.swap(false, atomic::Ordering::Acquire);
+			if !woken {
+				proc.set_state(process::State::Sleeping);
+			}
+			!woken
+		};
+		if sleep {
+			scheduler::end_tick();
+		}
+
+		if let Some(deadline) = deadline {
+			timer::cancel_wake(self.waker.pid, deadline);
+		}
+		// Events that occurred up to this point are caught by the caller's next readiness check
+		self.waker.woken.store(false, atomic::Ordering::Release);
+		Ok(())
+	}
+}
 
 /// Handler allowing to make a process sleep when waiting on a resource, then resume its execution
 /// when the resource is available.
@@ -14,6 +139,11 @@ use crate::util::io;
 pub struct BlockHandler {
 	/// The list of processes waiting on the resource, along with the mask of events to wait for.
 	waiting_procs: HashMap<Pid, u32>,
+	/// The list of poll tables registered on the resource, along with the mask of events to wait
+	/// for.
+	poll_tables: Vec<(Weak<Waker>, u32)>,
+	/// The number of occurrences of each event.
+	counters: EventCounters,
 }
 
 impl BlockHandler {
@@ -21,6 +151,8 @@ impl BlockHandler {
 	pub fn new() -> Self {
 		Self {
 			waiting_procs: HashMap::new(),
+			poll_tables: Vec::new(),
+			counters: EventCounters::default(),
 		}
 	}
 
@@ -37,8 +169,32 @@ impl BlockHandler {
 		Ok(())
 	}
 
+	/// Registers the given poll table on the resource.
+	///
+	/// `mask` is the mask of poll event to wait for. If the table is already registered, the mask
+	/// is added to the previous one.
+	pub fn register(&mut self, table: &PollTable, mask: u32) -> AllocResult<()> {
+		self.poll_tables.retain(|(waker, _)| waker.strong_count() > 0);
+		let prev = self.poll_tables.iter_mut().find(|(waker, _)| {
+			waker
+				.upgrade()
+				.is_some_and(|waker| waker.as_ptr() == table.waker.as_ptr())
+		});
+		match prev {
+			Some((_, m)) => *m |= mask,
+			None => self.poll_tables.push((Arc::downgrade(&table.waker), mask))?,
+		}
+		Ok(())
+	}
+
+	/// Returns the number of occurrences of each event on the resource.
+	pub fn get_event_counters(&self) -> EventCounters {
+		self.counters
+	}
+
 	/// Wakes processes for the events in the given mask.
 	pub fn wake_processes(&mut self, mask: u32) {
+		self.counters.increment(mask);
 		self.waiting_procs.retain(|pid, m| {
 			let Some(proc_mutex) = Process::get_by_pid(*pid) else {
 				return false;
@@ -54,6 +210,15 @@ impl BlockHandler {
 
 			false
 		});
+		self.poll_tables.retain(|(waker, m)| {
+			let Some(waker) = waker.upgrade() else {
+				return false;
+			};
+			if mask & *m != 0 {
+				waker.wake();
+			}
+			true
+		});
 	}
 }
 
@@ -62,3 +227,40 @@ impl Drop for BlockHandler {
 		self.wake_processes(io::POLLERR);
 	}
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test_case]
+	fn event_counters() {
+		let mut counters = EventCounters::default();
+		let prev = counters;
+		assert_eq!(counters.since(&prev), 0);
+
+		counters.increment(io::POLLIN);
+		assert_eq!(counters.since(&prev), io::POLLIN);
+		counters.increment(io::POLLIN | io::POLLOUT);
+		assert_eq!(counters.since(&prev), io::POLLIN | io::POLLOUT);
+		assert_eq!(counters.since(&counters), 0);
+	}
+
+	#[test_case]
+	fn poll_table_register() {
+		let mut handler = BlockHandler::new();
+		let table = PollTable::new(0).unwrap();
+		handler.register(&table, io::POLLIN).unwrap();
+		handler.register(&table, io::POLLOUT).unwrap();
+		assert_eq!(handler.poll_tables.len(), 1);
+		assert_eq!(handler.poll_tables[0].1, io::POLLIN | io::POLLOUT);
+
+		handler.wake_processes(io::POLLPRI);
+		assert!(!table.waker.woken.load(atomic::Ordering::Relaxed));
+		handler.wake_processes(io::POLLOUT);
+		assert!(table.waker.woken.load(atomic::Ordering::Relaxed));
+
+		drop(table);
+		handler.wake_processes(io::POLLIN);
+		assert!(handler.poll_tables.is_empty());
+	}
+}
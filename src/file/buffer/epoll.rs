@@ -0,0 +1,354 @@
+//! An epoll instance monitors a set of file descriptors, reporting the I/O events happening on
+//! them.
+//!
+//! Each registered file descriptor is either level-triggered, in which case it is reported as
+//! long as it is ready, or edge-triggered (`EPOLLET`), in which case it is reported only when an
+//! event occurs on it. To detect occurrences, each registration keeps the state of the file as
+//! it was when last checked.
+
+use super::Buffer;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::blocking::EventCounters;
+use crate::file::blocking::PollTable;
+use crate::file::open_file::OpenFile;
+use crate::process::mem_space::MemSpace;
+use crate::process::Process;
+use crate::syscall::ioctl;
+use crate::util::container::vec::Vec;
+use crate::util::io;
+use crate::util::io::IO;
+use crate::util::lock::IntMutex;
+use crate::util::lock::Mutex;
+use crate::util::ptr::arc::Arc;
+use crate::util::ptr::arc::Weak;
+use core::ffi::c_void;
+
+/// `epoll_ctl` operation: Registers a file descriptor.
+pub const EPOLL_CTL_ADD: i32 = 1;
+/// `epoll_ctl` operation: Unregisters a file descriptor.
+pub const EPOLL_CTL_DEL: i32 = 2;
+/// `epoll_ctl` operation: Modifies the events of a registered file descriptor.
+pub const EPOLL_CTL_MOD: i32 = 3;
+
+/// Epoll flag: Sets exclusive wakeup mode.
+pub const EPOLLEXCLUSIVE: u32 = 1 << 28;
+/// Epoll flag: Prevents the system from suspending while the event is pending.
+pub const EPOLLWAKEUP: u32 = 1 << 29;
+/// Epoll flag: Disables the registration after an event has been reported once.
+pub const EPOLLONESHOT: u32 = 1 << 30;
+/// Epoll flag: Reports events only when they occur instead of while they are set.
+pub const EPOLLET: u32 = 1 << 31;
+
+/// The mask of flags that are not events.
+const FLAGS_MASK: u32 = EPOLLEXCLUSIVE | EPOLLWAKEUP | EPOLLONESHOT | EPOLLET;
+
+/// An event, as exchanged with userspace.
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EpollEvent {
+	/// The mask of events.
+	pub events: u32,
+	/// Data associated with the file descriptor, returned as is to userspace.
+	pub data: u64,
+}
+
+/// Returns the mask of edge-triggered events to report.
+///
+/// Arguments:
+/// - `ready` is the mask of events currently set on the file.
+/// - `last_ready` is the mask of events that were set on the file when last checked.
+/// - `occurred` is the mask of events that occurred on the file since last checked.
+///
+/// An event is reported if it was not set on the last check, or if it occurred again since.
+fn edge_events(ready: u32, last_ready: u32, occurred: u32) -> u32 {
+	ready & (!last_ready | occurred)
+}
+
+/// A file descriptor registered on an epoll instance.
+struct Interest {
+	/// The ID of the file descriptor.
+	fd: u32,
+	/// The open file. The registration is removed when the file is closed.
+	file: Weak<Mutex<OpenFile>>,
+
+	/// The mask of events to look for, along with flags.
+	events: u32,
+	/// Data to be returned along with events.
+	data: u64,
+
+	/// The mask of events that were set on the file when last checked.
+	last_ready: u32,
+	/// The file's event counters when last checked.
+	last_counters: Option<EventCounters>,
+}
+
+impl Interest {
+	/// Creates a registration for the file `file` with the file descriptor `fd`, with the given
+	/// event.
+	fn new(fd: u32, file: &Arc<Mutex<OpenFile>>, event: &EpollEvent) -> Self {
+		let mut interest = Self {
+			fd,
+			file: Arc::downgrade(file),
+
+			events: 0,
+			data: 0,
+
+			last_ready: 0,
+			last_counters: None,
+		};
+		interest.set_event(&file.lock(), event);
+		interest
+	}
+
+	/// Sets the event to look for.
+	///
+	/// The edge-triggered state is reset so that events currently set are reported.
+	fn set_event(&mut self, file: &OpenFile, event: &EpollEvent) {
+		self.events = event.events;
+		self.data = event.data;
+		self.last_ready = 0;
+		self.last_counters = file.get_event_counters();
+	}
+
+	/// Returns the mask of events to poll the file with.
+	///
+	/// If the registration is disabled, the function returns zero.
+	fn get_mask(&self) -> u32 {
+		let events = self.events & !FLAGS_MASK;
+		if events == 0 {
+			return 0;
+		}
+		// Errors and hang ups are always reported
+		events | io::POLLERR | io::POLLHUP
+	}
+
+	/// Returns the mask of events currently set on the file.
+	///
+	/// If the file has been closed, the function returns `None`.
+	fn poll(&self) -> EResult<Option<u32>> {
+		let Some(file) = self.file.upgrade() else {
+			return Ok(None);
+		};
+		let mask = self.get_mask();
+		if mask == 0 {
+			return Ok(Some(0));
+		}
+		let ready = file.lock().poll(mask)? & mask;
+		Ok(Some(ready))
+	}
+
+	/// Returns the mask of events to report, then updates the state of the registration.
+	///
+	/// If the file has been closed, the function returns `None`.
+	fn check(&mut self) -> EResult<Option<u32>> {
+		let Some(file) = self.file.upgrade() else {
+			return Ok(None);
+		};
+		let mask = self.get_mask();
+		if mask == 0 {
+			return Ok(Some(0));
+		}
+		let mut file = file.lock();
+		let ready = file.poll(mask)? & mask;
+
+		let events = if self.events & EPOLLET != 0 {
+			let counters = file.get_event_counters();
+			let occurred = match (&counters, &self.last_counters) {
+				(Some(counters), Some(last)) => counters.since(last),
+				_ => 0,
+			};
+			let events = edge_events(ready, self.last_ready, occurred);
+			self.last_ready = ready;
+			self.last_counters = counters;
+			events
+		} else {
+			ready
+		};
+
+		if events != 0 && self.events & EPOLLONESHOT != 0 {
+			self.events &= FLAGS_MASK;
+		}
+		Ok(Some(events))
+	}
+}
+
+/// An epoll instance.
+#[derive(Default)]
+pub struct EpollBuffer {
+	/// The registered file descriptors, sorted by ID.
+	interests: Vec<Interest>,
+}
+
+impl EpollBuffer {
+	/// Removes the registrations of the files that have been closed.
+	fn remove_closed(&mut self) {
+		self.interests.retain(|i| i.file.upgrade().is_some());
+	}
+
+	/// Registers the file descriptor `fd`, pointing to the open file `file`, with the given event.
+	///
+	/// If the file descriptor is already registered, the function returns an error.
+	pub fn add(
+		&mut self,
+		fd: u32,
+		file: &Arc<Mutex<OpenFile>>,
+		event: &EpollEvent,
+	) -> EResult<()> {
+		self.remove_closed();
+		let Err(index) = self.interests.binary_search_by_key(&fd, |i| i.fd) else {
+			return Err(errno!(EEXIST));
+		};
+		self.interests
+			.insert(index, Interest::new(fd, file, event))?;
+		Ok(())
+	}
+
+	/// Modifies the event of the registered file descriptor `fd`.
+	///
+	/// If the file descriptor is not registered, the function returns an error.
+	pub fn modify(&mut self, fd: u32, event: &EpollEvent) -> EResult<()> {
+		self.remove_closed();
+		let index = self
+			.interests
+			.binary_search_by_key(&fd, |i| i.fd)
+			.map_err(|_| errno!(ENOENT))?;
+		let interest = &mut self.interests[index];
+		let file = interest.file.upgrade().ok_or_else(|| errno!(ENOENT))?;
+		interest.set_event(&file.lock(), event);
+		Ok(())
+	}
+
+	/// Unregisters the file descriptor `fd`.
+	///
+	/// If the file descriptor is not registered, the function returns an error.
+	pub fn remove(&mut self, fd: u32) -> EResult<()> {
+		let index = self
+			.interests
+			.binary_search_by_key(&fd, |i| i.fd)
+			.map_err(|_| errno!(ENOENT))?;
+		self.interests.remove(index);
+		Ok(())
+	}
+
+	/// Fills `events` with the events to report.
+	///
+	/// Registrations are checked only while `events` has room left, so that no edge-triggered
+	/// event is consumed without being reported.
+	///
+	/// The function returns the number of events written.
+	pub fn collect(&mut self, events: &mut [EpollEvent]) -> EResult<usize> {
+		self.remove_closed();
+		let mut count = 0;
+		for interest in self.interests.iter_mut() {
+			if count >= events.len() {
+				break;
+			}
+			let Some(ready) = interest.check()? else {
+				continue;
+			};
+			if ready != 0 {
+				events[count] = EpollEvent {
+					events: ready,
+					data: interest.data,
+				};
+				count += 1;
+			}
+		}
+		Ok(count)
+	}
+}
+
+impl Buffer for EpollBuffer {
+	fn get_capacity(&self) -> usize {
+		0
+	}
+
+	fn increment_open(&mut self, _read: bool, _write: bool) {}
+
+	fn decrement_open(&mut self, _read: bool, _write: bool) {}
+
+	fn add_waiting_process(&mut self, proc: &mut Process, _mask: u32) -> Result<(), Errno> {
+		for interest in self.interests.iter() {
+			let Some(file) = interest.file.upgrade() else {
+				continue;
+			};
+			let mask = interest.get_mask();
+			if mask != 0 {
+				file.lock().add_waiting_process(proc, mask)?;
+			}
+		}
+		Ok(())
+	}
+
+	fn register_poll(
+		&mut self,
+		proc: &Process,
+		table: &PollTable,
+		_mask: u32,
+	) -> Result<(), Errno> {
+		for interest in self.interests.iter() {
+			let Some(file) = interest.file.upgrade() else {
+				continue;
+			};
+			let mask = interest.get_mask();
+			if mask != 0 {
+				file.lock().register_poll(proc, table, mask)?;
+			}
+		}
+		Ok(())
+	}
+
+	fn ioctl(
+		&mut self,
+		_mem_space: Arc<IntMutex<MemSpace>>,
+		_request: ioctl::Request,
+		_argp: *const c_void,
+	) -> Result<u32, Errno> {
+		Err(errno!(ENOTTY))
+	}
+}
+
+impl IO for EpollBuffer {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, _: u64, _: &mut [u8]) -> Result<(u64, bool), Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn write(&mut self, _: u64, _: &[u8]) -> Result<u64, Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn poll(&mut self, mask: u32) -> Result<u32, Errno> {
+		for interest in self.interests.iter() {
+			if interest.poll()?.unwrap_or(0) != 0 {
+				return Ok(mask & io::POLLIN);
+			}
+		}
+		Ok(0)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test_case]
+	fn epoll_edge_events() {
+		// Becoming readable is reported once
+		assert_eq!(edge_events(io::POLLIN, 0, 0), io::POLLIN);
+		assert_eq!(edge_events(io::POLLIN, io::POLLIN, 0), 0);
+		// New data arriving while still readable is reported again
+		assert_eq!(edge_events(io::POLLIN, io::POLLIN, io::POLLIN), io::POLLIN);
+		// Space becoming available after the buffer was full
+		assert_eq!(
+			edge_events(io::POLLIN | io::POLLOUT, io::POLLIN, 0),
+			io::POLLOUT
+		);
+		// Events that occurred but are no longer set are not reported
+		assert_eq!(edge_events(0, io::POLLIN, io::POLLIN), 0);
+	}
+}
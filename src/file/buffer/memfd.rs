@@ -0,0 +1,175 @@
+//! A memfd is an anonymous regular file whose content resides in memory, created by the
+//! `memfd_create` system call.
+//!
+//! Seals can be placed on a memfd with `fcntl` to restrict the operations allowed on it, so that
+//! a process receiving the file from another can rely on its content not changing under its
+//! feet. Seals can only be added, never removed.
+
+use super::Buffer;
+use crate::errno;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::process::mem_space::MemSpace;
+use crate::syscall::ioctl;
+use crate::util::container::vec::Vec;
+use crate::util::io;
+use crate::util::io::IO;
+use crate::util::lock::IntMutex;
+use crate::util::ptr::arc::Arc;
+use core::cmp::min;
+use core::ffi::c_void;
+
+/// Seal: prevents adding seals.
+pub const F_SEAL_SEAL: u32 = 1;
+/// Seal: prevents reducing the size of the file.
+pub const F_SEAL_SHRINK: u32 = 2;
+/// Seal: prevents increasing the size of the file.
+pub const F_SEAL_GROW: u32 = 4;
+/// Seal: prevents modifying the content of the file.
+pub const F_SEAL_WRITE: u32 = 8;
+/// Seal: like [`F_SEAL_WRITE`], but existing shared writable mappings remain writable.
+pub const F_SEAL_FUTURE_WRITE: u32 = 16;
+/// The set of all valid seals.
+const F_SEAL_ALL: u32 =
+	F_SEAL_SEAL | F_SEAL_SHRINK | F_SEAL_GROW | F_SEAL_WRITE | F_SEAL_FUTURE_WRITE;
+
+/// The buffer holding the content of a memfd.
+#[derive(Debug)]
+pub struct MemfdBuffer {
+	/// The content of the file.
+	data: Vec<u8>,
+	/// The set of seals placed on the file.
+	seals: u32,
+}
+
+impl MemfdBuffer {
+	/// Creates a new empty buffer.
+	///
+	/// If `allow_sealing` is `false`, the [`F_SEAL_SEAL`] seal is set so that no seal can ever be
+	/// added.
+	pub fn new(allow_sealing: bool) -> Self {
+		Self {
+			data: Vec::new(),
+			seals: if allow_sealing { 0 } else { F_SEAL_SEAL },
+		}
+	}
+
+	/// Returns the set of seals placed on the file.
+	pub fn get_seals(&self) -> u32 {
+		self.seals
+	}
+
+	/// Adds the given set of seals to the file.
+	///
+	/// If `seals` contains an invalid seal, the function returns [`errno::EINVAL`].
+	///
+	/// If the file has the [`F_SEAL_SEAL`] seal, the function returns [`errno::EPERM`].
+	pub fn add_seals(&mut self, seals: u32) -> EResult<()> {
+		if seals & !F_SEAL_ALL != 0 {
+			return Err(errno!(EINVAL));
+		}
+		if self.seals & F_SEAL_SEAL != 0 {
+			return Err(errno!(EPERM));
+		}
+		self.seals |= seals;
+		Ok(())
+	}
+
+	/// Changes the size of the file to `size`, filling new space with zeros.
+	///
+	/// If a seal forbids the change, the function returns [`errno::EPERM`].
+	pub fn set_size(&mut self, size: u64) -> EResult<()> {
+		let size = usize::try_from(size).map_err(|_| errno!(EFBIG))?;
+		if size < self.data.len() && self.seals & F_SEAL_SHRINK != 0 {
+			return Err(errno!(EPERM));
+		}
+		if size > self.data.len() && self.seals & F_SEAL_GROW != 0 {
+			return Err(errno!(EPERM));
+		}
+		Ok(self.data.resize(size)?)
+	}
+}
+
+impl Buffer for MemfdBuffer {
+	fn get_capacity(&self) -> usize {
+		self.data.len()
+	}
+
+	fn increment_open(&mut self, _read: bool, _write: bool) {}
+
+	fn decrement_open(&mut self, _read: bool, _write: bool) {}
+
+	fn ioctl(
+		&mut self,
+		_mem_space: Arc<IntMutex<MemSpace>>,
+		_request: ioctl::Request,
+		_argp: *const c_void,
+	) -> Result<u32, Errno> {
+		Err(errno!(ENOTTY))
+	}
+}
+
+impl IO for MemfdBuffer {
+	fn get_size(&self) -> u64 {
+		self.data.len() as _
+	}
+
+	fn read(&mut self, offset: u64, buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+		let off = min(offset, self.data.len() as u64) as usize;
+		let len = min(buff.len(), self.data.len() - off);
+		buff[..len].copy_from_slice(&self.data[off..(off + len)]);
+		Ok((len as _, off + len >= self.data.len()))
+	}
+
+	fn write(&mut self, offset: u64, buff: &[u8]) -> Result<u64, Errno> {
+		if self.seals & (F_SEAL_WRITE | F_SEAL_FUTURE_WRITE) != 0 {
+			return Err(errno!(EPERM));
+		}
+		let end = offset
+			.checked_add(buff.len() as u64)
+			.ok_or_else(|| errno!(EFBIG))?;
+		if end > self.data.len() as u64 {
+			self.set_size(end)?;
+		}
+		let off = offset as usize;
+		self.data[off..(off + buff.len())].copy_from_slice(buff);
+		Ok(buff.len() as _)
+	}
+
+	fn poll(&mut self, mask: u32) -> Result<u32, Errno> {
+		Ok(mask & (io::POLLIN | io::POLLOUT))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test_case]
+	fn memfd_seals() {
+		let mut memfd = MemfdBuffer::new(true);
+		assert_eq!(memfd.write(4, b"abcd").unwrap(), 4);
+		let mut buf = [0xff; 8];
+		assert_eq!(memfd.read(0, &mut buf).unwrap(), (8, true));
+		assert_eq!(&buf, b"\0\0\0\0abcd");
+
+		memfd.add_seals(F_SEAL_GROW | F_SEAL_SHRINK).unwrap();
+		// Writing inside the file is still allowed
+		memfd.write(0, b"ef").unwrap();
+		assert!(memfd.write(6, b"ghi").is_err());
+		assert!(memfd.set_size(4).is_err());
+		assert_eq!(memfd.get_size(), 8);
+
+		memfd.add_seals(F_SEAL_WRITE | F_SEAL_SEAL).unwrap();
+		assert!(memfd.write(0, b"x").is_err());
+		assert!(memfd.add_seals(F_SEAL_WRITE).is_err());
+		assert_eq!(
+			memfd.get_seals(),
+			F_SEAL_SEAL | F_SEAL_SHRINK | F_SEAL_GROW | F_SEAL_WRITE
+		);
+
+		// Without sealing allowed, no seal can be added
+		let mut memfd = MemfdBuffer::new(false);
+		assert!(memfd.add_seals(F_SEAL_WRITE).is_err());
+	}
+}
@@ -1,5 +1,7 @@
 //! A buffer is an FIFO resource which may be blocking. The resource is represented by a file.
 
+pub mod epoll;
+pub mod memfd;
 pub mod pipe;
 pub mod socket;
 
@@ -7,12 +9,15 @@ use crate::errno::AllocError;
 use crate::errno::AllocResult;
 use crate::errno::Errno;
 use crate::file::blocking::BlockHandler;
+use crate::file::blocking::EventCounters;
+use crate::file::blocking::PollTable;
 use crate::file::FileLocation;
 use crate::process::mem_space::MemSpace;
 use crate::process::Process;
 use crate::syscall::ioctl;
 use crate::util::container::hashmap::HashMap;
 use crate::util::container::id_allocator::IDAllocator;
+use crate::util::container::vec::Vec;
 use crate::util::io::IO;
 use crate::util::lock::IntMutex;
 use crate::util::lock::Mutex;
@@ -52,6 +57,30 @@ pub trait Buffer: IO + Any {
 		Ok(())
 	}
 
+	/// Registers the given poll table on the buffer.
+	///
+	/// Arguments:
+	/// - `proc` is the process waiting on the table.
+	/// - `table` is the table to register.
+	/// - `mask` is the mask of poll event to wait for.
+	///
+	/// If the buffer cannot block, the function does nothing.
+	fn register_poll(
+		&mut self,
+		_proc: &Process,
+		_table: &PollTable,
+		_mask: u32,
+	) -> Result<(), Errno> {
+		Ok(())
+	}
+
+	/// Returns the number of occurrences of each poll event on the buffer.
+	///
+	/// If the buffer doesn't track events, the function returns `None`.
+	fn get_event_counters(&self) -> Option<EventCounters> {
+		None
+	}
+
 	/// Performs an ioctl operation on the file.
 	///
 	/// Arguments:
@@ -94,6 +123,16 @@ where
 	f(id_allocator)
 }
 
+/// Returns the locations of the files associated with all the buffers.
+pub fn list() -> AllocResult<Vec<FileLocation>> {
+	let buffers = BUFFERS.lock();
+	let mut list = Vec::with_capacity(buffers.len())?;
+	for loc in buffers.iter().map(|(loc, _)| loc) {
+		list.push(loc.clone())?;
+	}
+	Ok(list)
+}
+
 /// Returns the buffer associated with the file at location `loc`.
 ///
 /// If the buffer doesn't exist, the function creates it.
@@ -1,29 +1,112 @@
 //! A pipe is an object that links two file descriptors together. One reading
 //! and another writing, with a buffer in between.
+//!
+//! The buffer is a ring of page-sized segments. Data written to the pipe is appended to the last
+//! segment while it has room, and new segments are allocated as needed, up to the capacity of the
+//! pipe. Whole pages can also be inserted into the ring without being copied, which is used by
+//! `splice`.
+//!
+//! The capacity of a pipe can be changed with `fcntl`. Unprivileged processes cannot exceed the
+//! limit configured through `/proc/sys/fs/pipe-max-size`.
 
 use super::Buffer;
+use crate::errno::AllocResult;
+use crate::errno::EResult;
+use crate::file::blocking::PollTable;
 use crate::file::buffer::BlockHandler;
+use crate::file::buffer::EventCounters;
 use crate::file::Errno;
 use crate::limits;
+use crate::memory;
 use crate::process::mem_space::ptr::SyscallPtr;
 use crate::process::mem_space::MemSpace;
 use crate::process::Process;
 use crate::syscall::ioctl;
-use crate::util::container::ring_buffer::RingBuffer;
 use crate::util::container::vec::Vec;
 use crate::util::io;
 use crate::util::io::IO;
 use crate::util::lock::IntMutex;
 use crate::util::ptr::arc::Arc;
 use crate::util::TryDefault;
+use core::cmp::min;
 use core::ffi::c_int;
 use core::ffi::c_void;
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering::Relaxed;
+
+/// The default number of segments of a pipe.
+const DEFAULT_SEGMENTS: usize = 16;
+/// The default maximum capacity in bytes an unprivileged process can give to a pipe.
+const DEFAULT_MAX_CAPACITY: usize = 1024 * 1024;
+
+/// The maximum capacity in bytes an unprivileged process can give to a pipe.
+static MAX_CAPACITY: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_CAPACITY);
+
+/// Returns the maximum capacity in bytes an unprivileged process can give to a pipe.
+pub fn get_max_capacity() -> usize {
+	MAX_CAPACITY.load(Relaxed)
+}
+
+/// Sets the maximum capacity in bytes an unprivileged process can give to a pipe.
+///
+/// The value is rounded up like the capacity of pipes.
+///
+/// If the value is smaller than a page, the function returns [`errno::EINVAL`].
+pub fn set_max_capacity(size: usize) -> EResult<()> {
+	if size < memory::PAGE_SIZE {
+		return Err(errno!(EINVAL));
+	}
+	let size = size
+		.div_ceil(memory::PAGE_SIZE)
+		.checked_next_power_of_two()
+		.and_then(|segments| segments.checked_mul(memory::PAGE_SIZE))
+		.ok_or_else(|| errno!(EINVAL))?;
+	MAX_CAPACITY.store(size, Relaxed);
+	Ok(())
+}
+
+/// A page of data in the ring of a pipe.
+#[derive(Debug)]
+struct Segment {
+	/// The page.
+	data: Vec<u8>,
+	/// The offset of the next byte to read in the page.
+	start: usize,
+	/// The offset of the end of the data in the page.
+	end: usize,
+	/// Tells whether written data can be appended to the segment.
+	///
+	/// Pages inserted by `splice` are not merged with written data.
+	can_merge: bool,
+}
+
+impl Segment {
+	/// Returns the number of bytes to be read in the segment.
+	fn len(&self) -> usize {
+		self.end - self.start
+	}
+
+	/// Returns the number of bytes that can be appended to the segment.
+	fn room(&self) -> usize {
+		if self.can_merge {
+			self.data.len() - self.end
+		} else {
+			0
+		}
+	}
+}
 
 /// Structure representing a buffer buffer.
 #[derive(Debug)]
 pub struct PipeBuffer {
-	/// The buffer's buffer.
-	buffer: RingBuffer<u8, Vec<u8>>,
+	/// The ring of segments. Slots that are not in use are `None`.
+	ring: Vec<Option<Segment>>,
+	/// The index of the first segment in the ring.
+	head: usize,
+	/// The number of segments in use.
+	count: usize,
+	/// The length of the data in the ring in bytes.
+	data_len: usize,
 
 	/// The number of reading ends attached to the pipe.
 	read_ends: u32,
@@ -37,19 +120,214 @@ pub struct PipeBuffer {
 impl PipeBuffer {
 	/// Returns the length of the data to be read in the buffer.
 	pub fn get_data_len(&self) -> usize {
-		self.buffer.get_data_len()
+		self.data_len
+	}
+
+	/// Tells whether a reading end is attached to the pipe.
+	pub fn has_readers(&self) -> bool {
+		self.read_ends > 0
+	}
+
+	/// Tells whether a writing end is attached to the pipe.
+	pub fn has_writers(&self) -> bool {
+		self.write_ends > 0
 	}
 
 	/// Returns the available space in the buffer in bytes.
 	pub fn get_available_len(&self) -> usize {
-		self.buffer.get_available_len()
+		let tail_room = self.tail().map(Segment::room).unwrap_or(0);
+		(self.ring.len() - self.count) * memory::PAGE_SIZE + tail_room
+	}
+
+	/// Returns the last segment of the ring, if any.
+	fn tail(&self) -> Option<&Segment> {
+		let i = (self.head + self.count.checked_sub(1)?) % self.ring.len();
+		self.ring[i].as_ref()
+	}
+
+	/// Returns the last segment of the ring, if any.
+	fn tail_mut(&mut self) -> Option<&mut Segment> {
+		let i = (self.head + self.count.checked_sub(1)?) % self.ring.len();
+		self.ring[i].as_mut()
+	}
+
+	/// Appends the segment `seg` to the ring.
+	///
+	/// The ring must have a free slot.
+	fn push_segment(&mut self, seg: Segment) {
+		debug_assert!(self.count < self.ring.len());
+		let i = (self.head + self.count) % self.ring.len();
+		self.data_len += seg.len();
+		self.ring[i] = Some(seg);
+		self.count += 1;
+	}
+
+	/// Copies data from the ring into `buf` without consuming it, starting `off` bytes after the
+	/// beginning of the data.
+	///
+	/// The function returns the number of bytes copied.
+	pub fn peek(&self, mut off: usize, buf: &mut [u8]) -> usize {
+		let mut len = 0;
+		for i in 0..self.count {
+			if len >= buf.len() {
+				break;
+			}
+			let seg = self.ring[(self.head + i) % self.ring.len()]
+				.as_ref()
+				.unwrap();
+			if off >= seg.len() {
+				off -= seg.len();
+				continue;
+			}
+			let start = seg.start + off;
+			let l = min(buf.len() - len, seg.end - start);
+			buf[len..(len + l)].copy_from_slice(&seg.data[start..(start + l)]);
+			len += l;
+			off = 0;
+		}
+		len
+	}
+
+	/// Removes up to `len` bytes from the beginning of the ring, freeing the segments that have
+	/// been consumed.
+	///
+	/// The function returns the number of bytes removed.
+	fn discard_ring(&mut self, len: usize) -> usize {
+		let mut off = 0;
+		while off < len && self.count > 0 {
+			let seg = self.ring[self.head].as_mut().unwrap();
+			let l = min(len - off, seg.len());
+			seg.start += l;
+			off += l;
+			if seg.len() == 0 {
+				self.ring[self.head] = None;
+				self.head = (self.head + 1) % self.ring.len();
+				self.count -= 1;
+			}
+		}
+		self.data_len -= off;
+		off
+	}
+
+	/// Reads data from the ring into `buf`, freeing the segments that have been consumed.
+	///
+	/// The function returns the number of bytes read.
+	fn read_ring(&mut self, buf: &mut [u8]) -> usize {
+		let len = self.peek(0, buf);
+		self.discard_ring(len)
+	}
+
+	/// Removes up to `len` bytes from the beginning of the pipe's data, as if they were read.
+	///
+	/// The function returns the number of bytes removed.
+	pub fn consume(&mut self, len: usize) -> usize {
+		let len = self.discard_ring(len);
+		self.block_handler.wake_processes(io::POLLOUT);
+		len
+	}
+
+	/// Writes the data in `buf` to the ring, allocating segments as needed.
+	///
+	/// Writes of at most [`limits::PIPE_BUF`] bytes are atomic: if the ring does not have enough
+	/// room for the whole data, nothing is written.
+	///
+	/// The function returns the number of bytes written.
+	fn write_ring(&mut self, buf: &[u8]) -> AllocResult<usize> {
+		if buf.len() <= limits::PIPE_BUF && self.get_available_len() < buf.len() {
+			return Ok(0);
+		}
+		let mut off = 0;
+		while off < buf.len() {
+			if self.tail().map(Segment::room).unwrap_or(0) == 0 {
+				if self.count >= self.ring.len() {
+					break;
+				}
+				self.push_segment(Segment {
+					data: crate::vec![0; memory::PAGE_SIZE]?,
+					start: 0,
+					end: 0,
+					can_merge: true,
+				});
+			}
+			let seg = self.tail_mut().unwrap();
+			let len = min(buf.len() - off, seg.room());
+			seg.data[seg.end..(seg.end + len)].copy_from_slice(&buf[off..(off + len)]);
+			seg.end += len;
+			off += len;
+			self.data_len += len;
+		}
+		Ok(off)
+	}
+
+	/// Tells whether a page can be inserted in the ring with [`Self::insert_page`].
+	///
+	/// If the pipe has no reading end, the function returns [`errno::EPIPE`].
+	pub fn can_insert_page(&self) -> EResult<bool> {
+		if self.read_ends == 0 {
+			return Err(errno!(EPIPE));
+		}
+		Ok(self.count < self.ring.len())
+	}
+
+	/// Inserts the page `page` in the ring without copying it, the data being the first `len`
+	/// bytes of the page.
+	///
+	/// If the ring is full, the function returns [`errno::EAGAIN`].
+	pub fn insert_page(&mut self, page: Vec<u8>, len: usize) -> EResult<()> {
+		if !self.can_insert_page()? {
+			return Err(errno!(EAGAIN));
+		}
+		if len > 0 {
+			self.push_segment(Segment {
+				data: page,
+				start: 0,
+				end: len,
+				can_merge: false,
+			});
+			self.block_handler.wake_processes(io::POLLIN);
+		}
+		Ok(())
+	}
+
+	/// Sets the capacity of the pipe to at least `size` bytes.
+	///
+	/// The capacity is rounded up to a power of two number of pages.
+	///
+	/// If the pipe contains more data than the new capacity, the function returns
+	/// [`errno::EBUSY`].
+	///
+	/// On success, the function returns the new capacity.
+	pub fn set_capacity(&mut self, size: usize) -> EResult<usize> {
+		let segments = size.div_ceil(memory::PAGE_SIZE).max(1).next_power_of_two();
+		if segments < self.count {
+			return Err(errno!(EBUSY));
+		}
+		let mut ring = Vec::with_capacity(segments)?;
+		for i in 0..self.count {
+			let j = (self.head + i) % self.ring.len();
+			ring.push(self.ring[j].take())?;
+		}
+		for _ in self.count..segments {
+			ring.push(None)?;
+		}
+		self.ring = ring;
+		self.head = 0;
+		self.block_handler.wake_processes(io::POLLOUT);
+		Ok(self.get_capacity())
 	}
 }
 
 impl TryDefault for PipeBuffer {
 	fn try_default() -> Result<Self, Self::Error> {
+		let mut ring = Vec::with_capacity(DEFAULT_SEGMENTS)?;
+		for _ in 0..DEFAULT_SEGMENTS {
+			ring.push(None)?;
+		}
 		Ok(Self {
-			buffer: RingBuffer::new(crate::vec![0; limits::PIPE_BUF]?),
+			ring,
+			head: 0,
+			count: 0,
+			data_len: 0,
 
 			read_ends: 0,
 			write_ends: 0,
@@ -61,16 +339,19 @@ impl TryDefault for PipeBuffer {
 
 impl Buffer for PipeBuffer {
 	fn get_capacity(&self) -> usize {
-		self.buffer.get_size()
+		self.ring.len() * memory::PAGE_SIZE
 	}
 
 	fn increment_open(&mut self, read: bool, write: bool) {
+		// Wake processes waiting for the other end to be opened
 		if read {
 			self.read_ends += 1;
+			self.block_handler.wake_processes(io::POLLOUT);
 		}
 
 		if write {
 			self.write_ends += 1;
+			self.block_handler.wake_processes(io::POLLIN);
 		}
 	}
 
@@ -78,16 +359,18 @@ impl Buffer for PipeBuffer {
 		if read {
 			self.read_ends -= 1;
 
+			// Writers fail with `EPIPE`
 			if self.read_ends == 0 {
-				self.block_handler.wake_processes(io::POLLERR);
+				self.block_handler.wake_processes(io::POLLERR | io::POLLOUT);
 			}
 		}
 
 		if write {
 			self.write_ends -= 1;
 
+			// Readers reach the end of file
 			if self.write_ends == 0 {
-				self.block_handler.wake_processes(io::POLLERR);
+				self.block_handler.wake_processes(io::POLLHUP | io::POLLIN);
 			}
 		}
 	}
@@ -96,6 +379,19 @@ impl Buffer for PipeBuffer {
 		self.block_handler.add_waiting_process(proc, mask)
 	}
 
+	fn register_poll(
+		&mut self,
+		_proc: &Process,
+		table: &PollTable,
+		mask: u32,
+	) -> Result<(), Errno> {
+		Ok(self.block_handler.register(table, mask)?)
+	}
+
+	fn get_event_counters(&self) -> Option<EventCounters> {
+		Some(self.block_handler.get_event_counters())
+	}
+
 	fn ioctl(
 		&mut self,
 		mem_space: Arc<IntMutex<MemSpace>>,
@@ -109,7 +405,7 @@ impl Buffer for PipeBuffer {
 				let count_ref = count_ptr
 					.get_mut(&mut mem_space_guard)?
 					.ok_or_else(|| errno!(EFAULT))?;
-				*count_ref = self.get_available_len() as _;
+				*count_ref = self.get_data_len() as _;
 			}
 
 			_ => return Err(errno!(ENOTTY)),
@@ -126,7 +422,7 @@ impl IO for PipeBuffer {
 
 	/// Note: This implemention ignores the offset.
 	fn read(&mut self, _: u64, buf: &mut [u8]) -> Result<(u64, bool), Errno> {
-		let len = self.buffer.read(buf);
+		let len = self.read_ring(buf);
 		let eof = self.write_ends == 0 && self.get_data_len() == 0;
 
 		self.block_handler.wake_processes(io::POLLOUT);
@@ -136,15 +432,14 @@ impl IO for PipeBuffer {
 
 	/// Note: This implemention ignores the offset.
 	fn write(&mut self, _: u64, buf: &[u8]) -> Result<u64, Errno> {
-		if self.read_ends > 0 {
-			let len = self.buffer.write(buf);
+		if self.read_ends == 0 {
+			return Err(errno!(EPIPE));
+		}
+		let len = self.write_ring(buf)?;
 
-			self.block_handler.wake_processes(io::POLLIN);
+		self.block_handler.wake_processes(io::POLLIN);
 
-			Ok(len as _)
-		} else {
-			Err(errno!(EPIPE))
-		}
+		Ok(len as _)
 	}
 
 	fn poll(&mut self, mask: u32) -> Result<u32, Errno> {
@@ -156,10 +451,63 @@ impl IO for PipeBuffer {
 		if mask & io::POLLOUT != 0 && self.get_available_len() > 0 {
 			result |= io::POLLOUT;
 		}
-		if mask & io::POLLPRI != 0 && self.read_ends <= 0 {
-			result |= io::POLLPRI;
+		// Reported regardless of the mask
+		if self.write_ends == 0 {
+			result |= io::POLLHUP;
+		}
+		if self.read_ends == 0 {
+			result |= io::POLLERR;
 		}
 
 		Ok(result)
 	}
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test_case]
+	fn pipe_ring() {
+		let mut pipe = PipeBuffer::try_default().unwrap();
+		pipe.increment_open(true, true);
+		let data = crate::vec![0xaa; memory::PAGE_SIZE + 10].unwrap();
+		assert_eq!(pipe.write(0, &data).unwrap(), data.len() as u64);
+		assert_eq!(pipe.get_data_len(), data.len());
+
+		let page = crate::vec![0x55; memory::PAGE_SIZE].unwrap();
+		pipe.insert_page(page, 100).unwrap();
+		// Spliced pages are not merged with written data
+		pipe.write(0, b"abc").unwrap();
+		assert_eq!(pipe.count, 4);
+
+		// The pipe cannot shrink below its content
+		assert!(pipe.set_capacity(memory::PAGE_SIZE).is_err());
+		assert_eq!(
+			pipe.set_capacity(4 * memory::PAGE_SIZE).unwrap(),
+			4 * memory::PAGE_SIZE
+		);
+
+		let mut buf = crate::vec![0; memory::PAGE_SIZE * 2].unwrap();
+		// Peeking does not consume data
+		assert_eq!(pipe.peek(data.len() + 99, &mut buf[..2]), 2);
+		assert_eq!(&buf[..2], &[0x55, b'a']);
+		assert_eq!(pipe.get_data_len(), data.len() + 100 + 3);
+
+		let (len, _) = pipe.read(0, &mut buf).unwrap();
+		assert_eq!(len as usize, data.len() + 100 + 3);
+		assert_eq!(&buf[(data.len() - 1)..(data.len() + 1)], &[0xaa, 0x55]);
+		assert_eq!(&buf[(data.len() + 100)..(data.len() + 103)], b"abc");
+		assert_eq!(pipe.count, 0);
+		pipe.decrement_open(true, true);
+	}
+
+	#[test_case]
+	fn pipe_max_capacity() {
+		let prev = get_max_capacity();
+		assert!(set_max_capacity(memory::PAGE_SIZE - 1).is_err());
+		set_max_capacity(3 * memory::PAGE_SIZE).unwrap();
+		assert_eq!(get_max_capacity(), 4 * memory::PAGE_SIZE);
+		set_max_capacity(prev).unwrap();
+	}
+}
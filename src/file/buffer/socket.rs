@@ -3,30 +3,238 @@
 use super::Buffer;
 use crate::errno::AllocResult;
 use crate::errno::Errno;
+use crate::file::blocking::PollTable;
 use crate::file::buffer::BlockHandler;
+use crate::file::buffer::EventCounters;
+use crate::file::open_file::OpenFile;
+use crate::net;
+use crate::net::bridge;
+use crate::net::ip;
 use crate::net::osi;
+use crate::net::sockaddr::SockAddr;
+use crate::net::tcp::RateCounter;
+use crate::net::unix;
+use crate::net::unix::InFlight;
+use crate::net::unix::PassedFiles;
+use crate::net::Address;
+use crate::net::IfReq;
 use crate::net::SocketDesc;
 use crate::net::SocketDomain;
 use crate::net::SocketType;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::mem_space::ptr::SyscallString;
 use crate::process::mem_space::MemSpace;
 use crate::process::Process;
 use crate::syscall::ioctl;
+use crate::time::clock;
+use crate::time::clock::CLOCK_REALTIME;
+use crate::time::unit::TimeUnit;
+use crate::time::unit::TimestampScale;
+use crate::time::unit::Timeval;
+use crate::util::container::hashmap::HashMap;
 use crate::util::container::ring_buffer::RingBuffer;
 use crate::util::container::vec::Vec;
+use crate::util::io;
 use crate::util::io::IO;
 use crate::util::lock::IntMutex;
 use crate::util::lock::Mutex;
 use crate::util::ptr::arc::Arc;
+use crate::util::ptr::arc::Weak;
 use crate::util::TryDefault;
+use core::any::Any;
 use core::cmp::min;
 use core::ffi::c_int;
+use core::ffi::c_ulong;
 use core::ffi::c_void;
+use core::mem;
+use core::mem::size_of;
+use core::slice;
 
 /// The maximum size of a socket's buffers.
-const BUFFER_SIZE: usize = 65536;
+pub const BUFFER_SIZE: usize = 65536;
 
 /// Socket option level: Socket
 const SOL_SOCKET: c_int = 1;
+/// Socket option: the pending error, cleared when read.
+const SO_ERROR: c_int = 4;
+/// Socket option: receive the credentials of the sender as ancillary data.
+pub const SO_PASSCRED: c_int = 16;
+/// Socket option: the credentials of the peer, as recorded when the connection was established.
+const SO_PEERCRED: c_int = 17;
+/// Socket option: receive the timestamp of packets as ancillary data.
+pub const SO_TIMESTAMP: c_int = 29;
+
+/// The user and group ID reported when the real ID is unknown.
+const OVERFLOW_ID: u32 = 65534;
+
+/// Credentials of a process, as passed over Unix domain sockets.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct UCred {
+	/// The process's ID.
+	pub pid: i32,
+	/// The effective user ID.
+	pub uid: u32,
+	/// The effective group ID.
+	pub gid: u32,
+}
+
+impl UCred {
+	/// Returns the credentials of the process `proc`.
+	pub fn from_process(proc: &Process) -> Self {
+		Self {
+			pid: proc.pid as _,
+			uid: proc.access_profile.get_euid() as _,
+			gid: proc.access_profile.get_egid() as _,
+		}
+	}
+
+	/// Returns the credentials reported when the real ones are unknown.
+	pub fn unknown() -> Self {
+		Self {
+			pid: 0,
+			uid: OVERFLOW_ID,
+			gid: OVERFLOW_ID,
+		}
+	}
+
+	/// Returns the credentials as bytes.
+	pub fn as_bytes(&self) -> &[u8] {
+		unsafe { slice::from_raw_parts(self as *const _ as *const u8, size_of::<Self>()) }
+	}
+}
+
+/// The maximum length of the queue of connections waiting to be accepted.
+pub const SOMAXCONN: usize = 4096;
+
+/// Receive flag: process out-of-band data.
+pub const MSG_OOB: c_int = 0x1;
+/// Receive flag: return data without removing it from the queue.
+pub const MSG_PEEK: c_int = 0x2;
+/// Receive flag: ancillary data was truncated because the buffer was too small.
+pub const MSG_CTRUNC: c_int = 0x8;
+/// Receive flag: return the real length of the data, even if it was truncated.
+pub const MSG_TRUNC: c_int = 0x20;
+/// Receive flag: do not block.
+pub const MSG_DONTWAIT: c_int = 0x40;
+/// Receive flag: block until the full request is satisfied.
+pub const MSG_WAITALL: c_int = 0x100;
+
+/// A datagram waiting in the receive buffer.
+struct Datagram {
+	/// The length of the datagram in bytes.
+	len: usize,
+	/// The address of the sender.
+	src: Vec<u8>,
+	/// The time at which the datagram has been received, in nanoseconds.
+	stamp: u64,
+	/// The credentials of the sender, for Unix domain sockets.
+	cred: Option<UCred>,
+	/// The files passed along with the datagram.
+	files: InFlight,
+}
+
+/// The result of a receive operation on a socket.
+pub struct RecvResult {
+	/// The number of bytes written to the buffer.
+	pub len: usize,
+	/// The real length of the data. For datagrams, it is greater than `len` if the datagram has
+	/// been truncated.
+	pub full_len: usize,
+	/// The address of the sender, for datagrams.
+	pub src: Option<Vec<u8>>,
+	/// The time at which the data has been received, in nanoseconds.
+	pub stamp: Option<u64>,
+	/// The credentials of the sender, for Unix domain sockets.
+	pub cred: Option<UCred>,
+	/// The files passed along with the data. Files are not returned when peeking.
+	pub files: PassedFiles,
+}
+
+/// The state of a socket's connection.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SocketState {
+	/// The socket is not connected.
+	Unconnected,
+	/// The socket is waiting for incoming connections.
+	Listening,
+	/// A connection is being established.
+	Connecting,
+	/// The socket is connected.
+	Connected,
+	/// The connection has been closed by the peer.
+	Closed,
+}
+
+/// Identifies the connection of a socket to an IPv4 peer.
+#[derive(Clone, Copy, Eq, Hash, PartialEq)]
+pub struct ConnectionKey {
+	/// The transport protocol.
+	pub protocol: u8,
+	/// The address of the peer.
+	pub remote_addr: [u8; 4],
+	/// The port of the peer.
+	pub remote_port: u16,
+	/// The local port. Zero if the socket is not bound.
+	pub local_port: u16,
+}
+
+/// Connected sockets, to which errors reported asynchronously by the network are delivered.
+static CONNECTIONS: Mutex<HashMap<ConnectionKey, Weak<Mutex<dyn Buffer>>>> =
+	Mutex::new(HashMap::new());
+
+/// Registers the socket `sock_mutex` as connected, so that it receives the errors reported
+/// asynchronously by the network (ICMP) about its connection.
+///
+/// If the socket is not connected to an IPv4 peer, the function does nothing.
+pub fn register_connected(sock_mutex: &Arc<Mutex<dyn Buffer>>) -> AllocResult<()> {
+	let mut sock = sock_mutex.lock();
+	let Some(sock) = (&mut *sock as &mut dyn Any).downcast_mut::<Socket>() else {
+		return Ok(());
+	};
+	let key = sock.connection_key();
+	if key == sock.conn_key {
+		return Ok(());
+	}
+
+	let mut conns = CONNECTIONS.lock();
+	if let Some(old) = sock.conn_key.take() {
+		conns.remove(&old);
+	}
+	if let Some(key) = key {
+		conns.insert(key, Arc::downgrade(sock_mutex))?;
+		sock.conn_key = Some(key);
+	}
+	Ok(())
+}
+
+/// Delivers the error `errno`, reported asynchronously by the network, to the socket owning the
+/// connection `key`.
+///
+/// If no socket owns the connection, the error is ignored.
+pub fn deliver_error(key: ConnectionKey, errno: Errno) {
+	let sock_mutex = {
+		let conns = CONNECTIONS.lock();
+		conns
+			.get(&key)
+			.or_else(|| {
+				conns.get(&ConnectionKey {
+					local_port: 0,
+					..key
+				})
+			})
+			.cloned()
+	};
+	// The registry must not be locked when upgrading since dropping the last reference to the
+	// socket unregisters it
+	let Some(sock_mutex) = sock_mutex.and_then(|s| s.upgrade()) else {
+		return;
+	};
+	let mut sock = sock_mutex.lock();
+	if let Some(sock) = (&mut *sock as &mut dyn Any).downcast_mut::<Socket>() {
+		sock.on_error(errno);
+	}
+}
 
 /// Structure representing a socket.
 pub struct Socket {
@@ -34,11 +242,44 @@ pub struct Socket {
 	desc: SocketDesc,
 	/// The socket's network stack corresponding to the descriptor.
 	stack: Option<osi::Stack>,
+	/// The state of the socket's connection.
+	state: SocketState,
+	/// Incoming connections waiting to be accepted, if the socket is listening.
+	pending: Vec<Arc<Mutex<Socket>>>,
+	/// The maximum number of connections waiting to be accepted, as given to `listen`.
+	backlog: usize,
+	/// The number of connection requests received during the current second, if listening.
+	syn_rate: RateCounter,
+	/// The pending error, reported by the transport layer and not yet retrieved.
+	error: Option<Errno>,
 
 	/// The buffer containing received data. If `None`, reception has been shutdown.
 	receive_buffer: Option<RingBuffer<u8, Vec<u8>>>,
 	/// The buffer containing data to be transmitted. If `None`, transmission has been shutdown.
 	transmit_buffer: Option<RingBuffer<u8, Vec<u8>>>,
+	/// For connectionless sockets, the datagrams stored in the receive buffer, in order.
+	datagrams: Vec<Datagram>,
+	/// The offset in the receive buffer of the out-of-band mark, if any.
+	urgent_mark: Option<usize>,
+	/// For stream sockets, the time at which data was last received, in nanoseconds.
+	receive_stamp: Option<u64>,
+	/// The time at which the data last returned to userspace was received, in nanoseconds.
+	last_stamp: Option<u64>,
+	/// Tells whether the timestamp of received data is passed as ancillary data.
+	timestamping: bool,
+	/// For stream sockets, the credentials of the sender of the data last received.
+	receive_cred: Option<UCred>,
+	/// Tells whether the credentials of the sender are passed as ancillary data.
+	passcred: bool,
+	/// For stream sockets, the files passed along with the data in the receive buffer. They are
+	/// returned by the next read.
+	stream_files: Vec<InFlight>,
+
+	/// For Unix domain sockets, the credentials of the process that connected the socket or made
+	/// it listen.
+	cred: Option<UCred>,
+	/// For Unix domain sockets, the credentials of the peer when the connection was established.
+	peer_cred: Option<UCred>,
 
 	/// The number of entities owning a reference to the socket. When this count reaches zero, the
 	/// socket is closed.
@@ -49,6 +290,10 @@ pub struct Socket {
 
 	/// The address the socket is bound to.
 	sockname: Vec<u8>,
+	/// The address of the peer the socket is connected to.
+	peername: Vec<u8>,
+	/// The key under which the socket is registered as connected, if any.
+	conn_key: Option<ConnectionKey>,
 }
 
 impl Socket {
@@ -57,15 +302,33 @@ impl Socket {
 		Arc::new(Mutex::new(Self {
 			desc,
 			stack: None,
+			state: SocketState::Unconnected,
+			pending: Vec::new(),
+			backlog: 0,
+			syn_rate: RateCounter::new(),
+			error: None,
 
 			receive_buffer: Some(RingBuffer::new(crate::vec![0; BUFFER_SIZE]?)),
 			transmit_buffer: Some(RingBuffer::new(crate::vec![0; BUFFER_SIZE]?)),
+			datagrams: Vec::new(),
+			urgent_mark: None,
+			receive_stamp: None,
+			last_stamp: None,
+			timestamping: false,
+			receive_cred: None,
+			passcred: false,
+			stream_files: Vec::new(),
+
+			cred: None,
+			peer_cred: None,
 
 			open_count: 0,
 
 			block_handler: BlockHandler::new(),
 
 			sockname: Vec::new(),
+			peername: Vec::new(),
+			conn_key: None,
 		}))
 	}
 
@@ -81,6 +344,17 @@ impl Socket {
 		self.stack.as_ref()
 	}
 
+	/// Returns the state of the socket's connection.
+	#[inline(always)]
+	pub fn state(&self) -> SocketState {
+		self.state
+	}
+
+	/// Returns the pending error and clears it.
+	pub fn take_error(&mut self) -> Option<Errno> {
+		self.error.take()
+	}
+
 	/// Reads the given socket option.
 	///
 	/// Arguments:
@@ -90,13 +364,44 @@ impl Socket {
 	///
 	/// The function returns a value to be returned by the syscall on success.
 	pub fn get_opt(
-		&self,
-		_level: c_int,
-		_optname: c_int,
-		_optval: &mut [u8],
+		&mut self,
+		level: c_int,
+		optname: c_int,
+		optval: &mut [u8],
 	) -> Result<c_int, Errno> {
-		// TODO
-		todo!()
+		match (level, optname) {
+			(SOL_SOCKET, SO_ERROR) => {
+				let errno = self.take_error().map(|e| e.as_int()).unwrap_or(0);
+				let val = errno.to_ne_bytes();
+				let len = min(optval.len(), val.len());
+				optval[..len].copy_from_slice(&val[..len]);
+				Ok(0)
+			}
+			(SOL_SOCKET, SO_TIMESTAMP) => {
+				let val = (self.timestamping as c_int).to_ne_bytes();
+				let len = min(optval.len(), val.len());
+				optval[..len].copy_from_slice(&val[..len]);
+				Ok(0)
+			}
+			(SOL_SOCKET, SO_PASSCRED) => {
+				let val = (self.passcred as c_int).to_ne_bytes();
+				let len = min(optval.len(), val.len());
+				optval[..len].copy_from_slice(&val[..len]);
+				Ok(0)
+			}
+			(SOL_SOCKET, SO_PEERCRED) => {
+				if self.desc.domain != SocketDomain::AfUnix {
+					return Err(errno!(ENOPROTOOPT));
+				}
+				let cred = self.peer_cred.unwrap_or_else(UCred::unknown);
+				let val = cred.as_bytes();
+				let len = min(optval.len(), val.len());
+				optval[..len].copy_from_slice(&val[..len]);
+				Ok(0)
+			}
+			// TODO
+			_ => Err(errno!(ENOPROTOOPT)),
+		}
 	}
 
 	/// Writes the given socket option.
@@ -109,12 +414,73 @@ impl Socket {
 	/// The function returns a value to be returned by the syscall on success.
 	pub fn set_opt(
 		&mut self,
-		_level: c_int,
-		_optname: c_int,
-		_optval: &[u8],
+		level: c_int,
+		optname: c_int,
+		optval: &[u8],
 	) -> Result<c_int, Errno> {
-		// TODO
-		Ok(0)
+		match (level, optname) {
+			(SOL_SOCKET, SO_TIMESTAMP) => {
+				let val: [u8; 4] = optval
+					.get(..4)
+					.and_then(|v| v.try_into().ok())
+					.ok_or_else(|| errno!(EINVAL))?;
+				self.timestamping = c_int::from_ne_bytes(val) != 0;
+				Ok(0)
+			}
+			(SOL_SOCKET, SO_PASSCRED) => {
+				let val: [u8; 4] = optval
+					.get(..4)
+					.and_then(|v| v.try_into().ok())
+					.ok_or_else(|| errno!(EINVAL))?;
+				self.passcred = c_int::from_ne_bytes(val) != 0;
+				Ok(0)
+			}
+			// TODO
+			_ => Ok(0),
+		}
+	}
+
+	/// Tells whether the timestamp of received data is passed as ancillary data.
+	#[inline(always)]
+	pub fn is_timestamping(&self) -> bool {
+		self.timestamping
+	}
+
+	/// Tells whether the credentials of the sender are passed as ancillary data.
+	#[inline(always)]
+	pub fn is_passcred(&self) -> bool {
+		self.passcred
+	}
+
+	/// Returns the credentials of the process that connected the socket or made it listen.
+	///
+	/// When a Unix domain connection is established, the transport layer passes them to the
+	/// peer with [`Self::set_peer_cred`].
+	#[inline(always)]
+	pub fn cred(&self) -> Option<UCred> {
+		self.cred
+	}
+
+	/// Records the credentials of the process using the socket, for Unix domain sockets.
+	///
+	/// Credentials are recorded when the socket is connected or made listening.
+	pub fn set_cred(&mut self, cred: UCred) {
+		if self.desc.domain == SocketDomain::AfUnix {
+			self.cred = Some(cred);
+		}
+	}
+
+	/// Sets the credentials of the peer, returned by `SO_PEERCRED`.
+	#[inline(always)]
+	pub fn set_peer_cred(&mut self, cred: UCred) {
+		self.peer_cred = Some(cred);
+	}
+
+	/// Returns the time at which the data last returned to userspace was received, in
+	/// nanoseconds.
+	#[inline(always)]
+	pub fn last_stamp(&self) -> Option<u64> {
+		self.last_stamp
 	}
 
 	/// Writes the bound socket name into `sockaddr`.
@@ -123,11 +489,83 @@ impl Socket {
 	/// The function returns the length of the socket address.
 	pub fn read_sockname(&self, sockaddr: &mut [u8]) -> usize {
 		let len = min(sockaddr.len(), self.sockname.len());
-		sockaddr[..len].copy_from_slice(&self.sockname);
+		sockaddr[..len].copy_from_slice(&self.sockname[..len]);
 
 		self.sockname.len()
 	}
 
+	/// Writes the address of the peer into `sockaddr`.
+	/// If the buffer is too small, the address is truncated.
+	///
+	/// The function returns the length of the socket address.
+	pub fn read_peername(&self, sockaddr: &mut [u8]) -> usize {
+		let len = min(sockaddr.len(), self.peername.len());
+		sockaddr[..len].copy_from_slice(&self.peername[..len]);
+
+		self.peername.len()
+	}
+
+	/// Returns the address the socket is bound to. If not bound, the address is empty.
+	#[inline(always)]
+	pub fn sockname(&self) -> &[u8] {
+		&self.sockname
+	}
+
+	/// Returns the address of the peer the socket is connected to. If not connected, the address
+	/// is empty.
+	#[inline(always)]
+	pub fn peername(&self) -> &[u8] {
+		&self.peername
+	}
+
+	/// Returns the number of bytes waiting in the receive buffer.
+	pub fn receive_queue_len(&self) -> usize {
+		self.receive_buffer
+			.as_ref()
+			.map(|b| b.get_data_len())
+			.unwrap_or(0)
+	}
+
+	/// Returns the number of bytes waiting in the transmit buffer.
+	pub fn transmit_queue_len(&self) -> usize {
+		self.transmit_buffer
+			.as_ref()
+			.map(|b| b.get_data_len())
+			.unwrap_or(0)
+	}
+
+	/// Sets the address of the peer the socket is connected to.
+	pub fn set_peername(&mut self, sockaddr: &[u8]) -> AllocResult<()> {
+		self.peername = Vec::from_slice(sockaddr)?;
+		Ok(())
+	}
+
+	/// Returns the key identifying the connection of the socket, if it is connected to an IPv4
+	/// peer.
+	fn connection_key(&self) -> Option<ConnectionKey> {
+		let protocol = match self.desc.type_ {
+			SocketType::SockStream => ip::PROTO_TCP,
+			SocketType::SockDgram => ip::PROTO_UDP,
+			_ => return None,
+		};
+		let SockAddr {
+			port: remote_port,
+			addr: Address::IPv4(remote_addr),
+		} = SockAddr::from_bytes(&self.peername)?
+		else {
+			return None;
+		};
+		let local_port = SockAddr::from_bytes(&self.sockname)
+			.map(|a| a.port)
+			.unwrap_or(0);
+		Some(ConnectionKey {
+			protocol,
+			remote_addr,
+			remote_port,
+			local_port,
+		})
+	}
+
 	/// Tells whether the socket is bound.
 	pub fn is_bound(&self) -> bool {
 		!self.sockname.is_empty()
@@ -151,14 +589,421 @@ impl Socket {
 		Ok(())
 	}
 
+	/// Starts connecting the socket to the address `sockaddr`.
+	///
+	/// For connection-based sockets, the connection is established asynchronously. The transport
+	/// layer then reports the outcome with [`Self::on_connected`] or [`Self::on_error`].
+	pub fn connect(&mut self, sockaddr: &[u8]) -> Result<(), Errno> {
+		match self.state {
+			SocketState::Connecting => return Err(errno!(EALREADY)),
+			SocketState::Connected if self.desc.type_.is_stream() => return Err(errno!(EISCONN)),
+			SocketState::Listening => return Err(errno!(EINVAL)),
+			_ => {}
+		}
+
+		self.stack = Some(osi::Stack::new(&self.desc, sockaddr)?);
+		self.peername = Vec::from_slice(sockaddr)?;
+		self.error = None;
+		if self.desc.type_.is_stream() {
+			self.state = SocketState::Connecting;
+		// TODO initiate the connection through the transport layer
+		} else {
+			self.state = SocketState::Connected;
+		}
+
+		Ok(())
+	}
+
+	/// Makes the socket wait for incoming connections.
+	///
+	/// `backlog` is the maximum number of connections waiting to be accepted. It is clamped to
+	/// [`SOMAXCONN`].
+	pub fn listen(&mut self, backlog: c_int) -> Result<(), Errno> {
+		match self.state {
+			SocketState::Unconnected | SocketState::Listening => {
+				self.state = SocketState::Listening;
+				self.backlog = if backlog < 0 {
+					SOMAXCONN
+				} else {
+					min(backlog as usize, SOMAXCONN)
+				};
+				Ok(())
+			}
+			_ => Err(errno!(EINVAL)),
+		}
+	}
+
+	/// Returns the maximum number of connections waiting to be accepted.
+	#[inline(always)]
+	pub fn backlog(&self) -> usize {
+		self.backlog
+	}
+
+	/// Returns the counter of connection requests received during the current second.
+	#[inline(always)]
+	pub fn syn_rate(&mut self) -> &mut RateCounter {
+		&mut self.syn_rate
+	}
+
+	/// Tells whether the queue of connections waiting to be accepted is full.
+	pub fn is_accept_queue_full(&self) -> bool {
+		// Like Linux, one more connection than the backlog is accepted
+		self.pending.len() > self.backlog
+	}
+
+	/// Takes the oldest incoming connection waiting to be accepted.
+	pub fn accept(&mut self) -> Option<Arc<Mutex<Socket>>> {
+		if self.pending.is_empty() {
+			None
+		} else {
+			Some(self.pending.remove(0))
+		}
+	}
+
+	/// Called by the transport layer when a connection `conn` is established on a listening
+	/// socket.
+	///
+	/// If the socket is not listening or if the queue of connections waiting to be accepted is
+	/// full, the function returns an error and the connection must be refused.
+	pub fn on_incoming(&mut self, conn: Arc<Mutex<Socket>>) -> Result<(), Errno> {
+		if self.state != SocketState::Listening || self.is_accept_queue_full() {
+			return Err(errno!(ECONNREFUSED));
+		}
+		self.pending.push(conn)?;
+		self.block_handler.wake_processes(io::POLLIN);
+		Ok(())
+	}
+
+	/// Called by the transport layer when the connection has been established.
+	pub fn on_connected(&mut self) {
+		self.state = SocketState::Connected;
+		self.block_handler.wake_processes(io::POLLOUT);
+	}
+
+	/// Called by the transport layer when data `buf` is received.
+	///
+	/// `src` is the address of the sender. It is used only for connectionless sockets, for which
+	/// `buf` is a datagram. A datagram is either stored entirely or dropped.
+	///
+	/// `cred` is the credentials of the sender, and `files` the files passed along with the data,
+	/// for Unix domain sockets. If the data cannot be stored, the files are released.
+	///
+	/// The function returns the number of bytes that could be stored.
+	pub fn on_receive(
+		&mut self,
+		buf: &[u8],
+		src: &[u8],
+		cred: Option<UCred>,
+		files: InFlight,
+	) -> AllocResult<usize> {
+		let Some(receive_buffer) = &mut self.receive_buffer else {
+			return Ok(0);
+		};
+		let stamp = clock::current_time(CLOCK_REALTIME, TimestampScale::Nanosecond).unwrap_or(0);
+		let len = if self.desc.type_.is_stream() {
+			let len = receive_buffer.write(buf);
+			if len > 0 {
+				self.receive_stamp = Some(stamp);
+				self.receive_cred = cred;
+				if !files.files().is_empty() {
+					self.stream_files.push(files)?;
+				}
+			}
+			len
+		} else {
+			// A connected socket only receives datagrams from its peer
+			if (self.state == SocketState::Connected && src != self.peername.as_slice())
+				|| receive_buffer.get_available_len() < buf.len()
+			{
+				return Ok(0);
+			}
+			self.datagrams.push(Datagram {
+				len: buf.len(),
+				src: Vec::from_slice(src)?,
+				stamp,
+				cred,
+				files,
+			})?;
+			receive_buffer.write(buf)
+		};
+		if len > 0 {
+			self.block_handler.wake_processes(io::POLLIN);
+		}
+		Ok(len)
+	}
+
+	/// Called by the transport layer when urgent data is received. The out-of-band mark is set
+	/// at the end of the data currently in the receive buffer.
+	pub fn on_urgent(&mut self) {
+		if let Some(receive_buffer) = &self.receive_buffer {
+			self.urgent_mark = Some(receive_buffer.get_data_len());
+			self.block_handler.wake_processes(io::POLLPRI);
+		}
+	}
+
+	/// Returns the number of open files pointing to the socket.
+	#[inline(always)]
+	pub fn open_count(&self) -> u32 {
+		self.open_count
+	}
+
+	/// Returns the files in flight in the receive queue.
+	///
+	/// A file appears as many times as it is in flight.
+	pub fn in_flight(&self) -> AllocResult<Vec<Arc<Mutex<OpenFile>>>> {
+		let mut files = Vec::new();
+		let queues = self
+			.datagrams
+			.iter()
+			.map(|d| &d.files)
+			.chain(self.stream_files.iter());
+		for file in queues.flat_map(|files| files.files().iter()) {
+			files.push(file.clone())?;
+		}
+		Ok(files)
+	}
+
+	/// Removes the files in flight from the receive queue and releases them.
+	///
+	/// The data they were passed along with is left in place.
+	pub fn purge_in_flight(&mut self) {
+		for dgram in self.datagrams.iter_mut() {
+			unix::release(dgram.files.take());
+		}
+		// Dropping the files releases them
+		self.stream_files = Vec::new();
+	}
+
+	/// Tells whether the next byte to be read is at the out-of-band mark.
+	pub fn is_at_mark(&self) -> bool {
+		self.urgent_mark == Some(0)
+	}
+
+	/// Tells whether no more data can be received.
+	pub fn is_eof(&self) -> bool {
+		match &self.receive_buffer {
+			Some(b) => self.state == SocketState::Closed && b.is_empty(),
+			None => true,
+		}
+	}
+
+	/// Receives data from the socket into `buf`.
+	///
+	/// `flags` is a combination of [`MSG_PEEK`] and [`MSG_TRUNC`]. Other flags are handled by
+	/// the caller.
+	///
+	/// For stream sockets, reading stops at the out-of-band mark. For connectionless sockets, at
+	/// most one datagram is received and the part of it that does not fit in `buf` is discarded.
+	pub fn recv(&mut self, buf: &mut [u8], flags: c_int) -> Result<RecvResult, Errno> {
+		if let Some(errno) = self.error.take() {
+			return Err(errno);
+		}
+		let peek = flags & MSG_PEEK != 0;
+		let Some(receive_buffer) = &mut self.receive_buffer else {
+			return Ok(RecvResult {
+				len: 0,
+				full_len: 0,
+				src: None,
+				stamp: None,
+				cred: None,
+				files: PassedFiles::default(),
+			});
+		};
+
+		if !self.desc.type_.is_stream() {
+			let Some(dgram) = self.datagrams.first() else {
+				return Ok(RecvResult {
+					len: 0,
+					full_len: 0,
+					src: None,
+					stamp: None,
+					cred: None,
+					files: PassedFiles::default(),
+				});
+			};
+			self.last_stamp = Some(dgram.stamp);
+			let full_len = dgram.len;
+			let len = min(buf.len(), full_len);
+			if peek {
+				receive_buffer.peek(&mut buf[..len]);
+				let src = Vec::from_slice(&dgram.src)?;
+				return Ok(RecvResult {
+					len,
+					full_len,
+					src: Some(src),
+					stamp: Some(dgram.stamp),
+					cred: dgram.cred,
+					files: PassedFiles::default(),
+				});
+			}
+
+			receive_buffer.read(&mut buf[..len]);
+			discard(receive_buffer, full_len - len);
+			let mut dgram = self.datagrams.remove(0);
+			let files = PassedFiles(dgram.files.take());
+			return Ok(RecvResult {
+				len,
+				full_len,
+				src: Some(dgram.src),
+				stamp: Some(dgram.stamp),
+				cred: dgram.cred,
+				files,
+			});
+		}
+
+		// Do not read past the out-of-band mark
+		let max = match self.urgent_mark {
+			Some(mark) if mark > 0 => min(buf.len(), mark),
+			_ => buf.len(),
+		};
+		let len = if peek {
+			receive_buffer.peek(&mut buf[..max])
+		} else if flags & MSG_TRUNC != 0 {
+			// Data is discarded instead of being copied
+			discard(receive_buffer, max)
+		} else {
+			receive_buffer.read(&mut buf[..max])
+		};
+		if !peek && len > 0 {
+			// Once the data at the mark has been read, the mark is passed
+			self.urgent_mark = match self.urgent_mark {
+				Some(mark) if mark > 0 => Some(mark - len),
+				_ => None,
+			};
+		}
+
+		let mut files = PassedFiles::default();
+		if len > 0 {
+			self.last_stamp = self.receive_stamp;
+			if !peek {
+				for mut in_flight in mem::take(&mut self.stream_files) {
+					let mut taken = in_flight.take();
+					if let Err(e) = files.0.append(&mut taken) {
+						unix::release(taken);
+						return Err(e.into());
+					}
+				}
+			}
+		}
+		Ok(RecvResult {
+			len,
+			full_len: len,
+			src: None,
+			stamp: self.receive_stamp.filter(|_| len > 0),
+			cred: self.receive_cred.filter(|_| len > 0),
+			files,
+		})
+	}
+
+	/// Called by the transport layer when data has been transmitted, freeing space in the
+	/// transmit buffer.
+	pub fn on_transmitted(&mut self) {
+		self.block_handler.wake_processes(io::POLLOUT);
+	}
+
+	/// Called by the transport layer when an error occurs on the connection.
+	pub fn on_error(&mut self, errno: Errno) {
+		if self.state == SocketState::Connecting {
+			self.state = SocketState::Unconnected;
+		}
+		self.error = Some(errno);
+		self.block_handler.wake_processes(io::POLLERR);
+	}
+
+	/// Called by the transport layer when the peer closed the connection.
+	pub fn on_hangup(&mut self) {
+		self.state = SocketState::Closed;
+		self.block_handler.wake_processes(io::POLLIN | io::POLLHUP);
+	}
+
 	/// Shuts down the receive side of the socket.
 	pub fn shutdown_receive(&mut self) {
 		self.receive_buffer = None;
+		self.block_handler.wake_processes(io::POLLIN);
 	}
 
 	/// Shuts down the transmit side of the socket.
 	pub fn shutdown_transmit(&mut self) {
 		self.transmit_buffer = None;
+		self.block_handler.wake_processes(io::POLLOUT);
+	}
+}
+
+/// Handles the ioctl request `req` configuring bridges.
+fn bridge_ioctl(
+	mem_space: &Arc<IntMutex<MemSpace>>,
+	req: c_ulong,
+	argp: *const c_void,
+) -> Result<u32, Errno> {
+	if !Process::current_assert()
+		.lock()
+		.access_profile
+		.is_privileged()
+	{
+		return Err(errno!(EPERM));
+	}
+
+	let mem_space_guard = mem_space.lock();
+	match req {
+		ioctl::SIOCBRADDBR | ioctl::SIOCBRDELBR => {
+			let name_ptr: SyscallString = (argp as usize).into();
+			let name = name_ptr
+				.get(&mem_space_guard)?
+				.ok_or_else(|| errno!(EFAULT))?;
+			if req == ioctl::SIOCBRADDBR {
+				bridge::create(name)?;
+			} else {
+				bridge::delete(name)?;
+			}
+		}
+
+		_ => {
+			let ifreq_ptr: SyscallPtr<IfReq> = (argp as usize).into();
+			let ifreq = ifreq_ptr
+				.get(&mem_space_guard)?
+				.ok_or_else(|| errno!(EFAULT))?;
+			let port = u32::try_from(ifreq.ifindex())
+				.ok()
+				.and_then(|i| net::get_iface_name(i).transpose())
+				.transpose()?
+				.ok_or_else(|| errno!(ENODEV))?;
+			if req == ioctl::SIOCBRADDIF {
+				bridge::add_port(ifreq.name(), &port)?;
+			} else {
+				bridge::remove_port(ifreq.name(), &port)?;
+			}
+		}
+	}
+
+	Ok(0)
+}
+
+/// Removes `len` bytes from the beginning of the buffer `buf`.
+///
+/// The function returns the number of bytes removed.
+fn discard(buf: &mut RingBuffer<u8, Vec<u8>>, len: usize) -> usize {
+	let mut tmp = [0; 128];
+	let mut total = 0;
+	while total < len {
+		let l = min(tmp.len(), len - total);
+		let l = buf.read(&mut tmp[..l]);
+		if l == 0 {
+			break;
+		}
+		total += l;
+	}
+	total
+}
+
+impl Drop for Socket {
+	fn drop(&mut self) {
+		if let Some(key) = self.conn_key.take() {
+			CONNECTIONS.lock().remove(&key);
+		}
+		if let Some(name) = unix::abstract_name(&self.sockname) {
+			unix::unbind_abstract(name);
+		}
+		self.purge_in_flight();
 	}
 }
 
@@ -173,15 +1018,33 @@ impl TryDefault for Socket {
 		Ok(Self {
 			desc,
 			stack: None,
+			state: SocketState::Unconnected,
+			pending: Vec::new(),
+			backlog: 0,
+			syn_rate: RateCounter::new(),
+			error: None,
 
 			receive_buffer: Some(RingBuffer::new(crate::vec![0; BUFFER_SIZE]?)),
 			transmit_buffer: Some(RingBuffer::new(crate::vec![0; BUFFER_SIZE]?)),
+			datagrams: Vec::new(),
+			urgent_mark: None,
+			receive_stamp: None,
+			last_stamp: None,
+			timestamping: false,
+			receive_cred: None,
+			passcred: false,
+			stream_files: Vec::new(),
+
+			cred: None,
+			peer_cred: None,
 
 			open_count: 0,
 
 			block_handler: BlockHandler::new(),
 
 			sockname: Default::default(),
+			peername: Default::default(),
+			conn_key: None,
 		})
 	}
 }
@@ -207,14 +1070,63 @@ impl Buffer for Socket {
 		self.block_handler.add_waiting_process(proc, mask)
 	}
 
+	fn register_poll(
+		&mut self,
+		_proc: &Process,
+		table: &PollTable,
+		mask: u32,
+	) -> Result<(), Errno> {
+		Ok(self.block_handler.register(table, mask)?)
+	}
+
+	fn get_event_counters(&self) -> Option<EventCounters> {
+		Some(self.block_handler.get_event_counters())
+	}
+
 	fn ioctl(
 		&mut self,
-		_mem_space: Arc<IntMutex<MemSpace>>,
-		_request: ioctl::Request,
-		_argp: *const c_void,
+		mem_space: Arc<IntMutex<MemSpace>>,
+		request: ioctl::Request,
+		argp: *const c_void,
 	) -> Result<u32, Errno> {
-		// TODO
-		todo!();
+		let req = request.get_old_format();
+		if matches!(
+			req,
+			ioctl::SIOCBRADDBR | ioctl::SIOCBRDELBR | ioctl::SIOCBRADDIF | ioctl::SIOCBRDELIF
+		) {
+			return bridge_ioctl(&mem_space, req, argp);
+		}
+
+		if req == ioctl::SIOCGSTAMP {
+			let stamp = self.last_stamp.ok_or_else(|| errno!(ENOENT))?;
+			let mut mem_space_guard = mem_space.lock();
+			let tv_ptr: SyscallPtr<Timeval> = (argp as usize).into();
+			let tv = tv_ptr
+				.get_mut(&mut mem_space_guard)?
+				.ok_or_else(|| errno!(EFAULT))?;
+			*tv = Timeval::from_nano(stamp);
+			return Ok(0);
+		}
+
+		let val = match req {
+			ioctl::FIONREAD => match (&self.receive_buffer, self.datagrams.first()) {
+				(_, Some(dgram)) => dgram.len,
+				(Some(b), None) => b.get_data_len(),
+				(None, None) => 0,
+			},
+			ioctl::SIOCATMARK => self.is_at_mark() as _,
+
+			_ => return Err(errno!(ENOTTY)),
+		};
+
+		let mut mem_space_guard = mem_space.lock();
+		let val_ptr: SyscallPtr<c_int> = (argp as usize).into();
+		let val_ref = val_ptr
+			.get_mut(&mut mem_space_guard)?
+			.ok_or_else(|| errno!(EFAULT))?;
+		*val_ref = val as _;
+
+		Ok(0)
 	}
 }
 
@@ -224,13 +1136,16 @@ impl IO for Socket {
 	}
 
 	/// Note: This implemention ignores the offset.
-	fn read(&mut self, _: u64, _buf: &mut [u8]) -> Result<(u64, bool), Errno> {
+	fn read(&mut self, _: u64, buf: &mut [u8]) -> Result<(u64, bool), Errno> {
 		if !self.desc.type_.is_stream() {
 			// TODO error
 		}
+		if let Some(errno) = self.error.take() {
+			return Err(errno);
+		}
 
-		// TODO
-		todo!();
+		let len = self.recv(buf, 0)?.len;
+		Ok((len as _, self.is_eof()))
 	}
 
 	/// Note: This implemention ignores the offset.
@@ -244,8 +1159,56 @@ impl IO for Socket {
 		todo!();
 	}
 
-	fn poll(&mut self, _mask: u32) -> Result<u32, Errno> {
-		// TODO
-		todo!();
+	fn poll(&mut self, mask: u32) -> Result<u32, Errno> {
+		let mut result = 0;
+		if self.error.is_some() {
+			result |= io::POLLERR;
+		}
+		if self.urgent_mark.is_some() {
+			result |= io::POLLPRI;
+		}
+
+		let readable = self
+			.receive_buffer
+			.as_ref()
+			.map(|b| !b.is_empty())
+			.unwrap_or(true);
+		let writable = self
+			.transmit_buffer
+			.as_ref()
+			.map(|b| b.get_available_len() > 0)
+			.unwrap_or(false);
+		match self.state {
+			SocketState::Listening => {
+				if !self.pending.is_empty() {
+					result |= io::POLLIN;
+				}
+			}
+
+			SocketState::Connecting => {}
+
+			SocketState::Unconnected if self.desc.type_.is_stream() => result |= io::POLLHUP,
+
+			SocketState::Unconnected | SocketState::Connected => {
+				if readable {
+					result |= io::POLLIN;
+				}
+				if writable {
+					result |= io::POLLOUT;
+				}
+			}
+
+			// Reading returns end-of-file once the remaining data is consumed
+			SocketState::Closed => result |= io::POLLIN | io::POLLRDHUP | io::POLLHUP,
+		}
+		if self.receive_buffer.is_none() {
+			result |= io::POLLRDHUP;
+			if self.transmit_buffer.is_none() {
+				result |= io::POLLHUP;
+			}
+		}
+
+		// Errors and hang ups are always reported
+		Ok(result & (mask | io::POLLERR | io::POLLHUP))
 	}
 }
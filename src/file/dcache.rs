@@ -0,0 +1,154 @@
+//! The directory entry cache (dcache) remembers the results of looking up names in directories,
+//! so that path resolution does not have to query the filesystem for each component.
+//!
+//! A negative entry remembers that a name does not exist in a directory.
+//!
+//! Only filesystems requiring caching (see [`Filesystem::must_cache`]) use the cache, since the
+//! content of others can change without the VFS being aware of it.
+//!
+//! [`Filesystem::must_cache`]: crate::file::fs::Filesystem::must_cache
+
+use crate::file::INode;
+use crate::util::container::hashmap::HashMap;
+use crate::util::container::string::String;
+use crate::util::lock::Mutex;
+
+/// The maximum number of entries in the cache.
+const DCACHE_MAX: usize = 4096;
+
+/// The key of a cache entry.
+#[derive(Eq, Hash, PartialEq)]
+struct Key {
+	/// The ID of the mountpoint on which the directory is located.
+	mountpoint_id: u32,
+	/// The inode of the directory.
+	parent: INode,
+	/// The name of the entry in the directory.
+	name: String,
+}
+
+/// A cache entry.
+struct Entry {
+	/// The inode of the file. If `None`, the entry is negative.
+	inode: Option<INode>,
+	/// The value of the cache's clock when the entry was last used.
+	last_used: u64,
+}
+
+/// The directory entry cache.
+struct DCache {
+	/// The cache's entries.
+	entries: HashMap<Key, Entry>,
+	/// A counter incremented on each access, used to evict the least recently used entries.
+	clock: u64,
+}
+
+/// The directory entry cache.
+static DCACHE: Mutex<DCache> = Mutex::new(DCache {
+	entries: HashMap::new(),
+	clock: 0,
+});
+
+/// Builds the key for the entry `name` in the directory `parent`.
+///
+/// If the allocation fails, the function returns `None`.
+fn key(mountpoint_id: u32, parent: INode, name: &[u8]) -> Option<Key> {
+	Some(Key {
+		mountpoint_id,
+		parent,
+		name: String::try_from(name).ok()?,
+	})
+}
+
+/// Looks up the entry `name` in the directory `parent` on the mountpoint `mountpoint_id`.
+///
+/// If the entry is not cached, the function returns `None`. Else, it returns the inode of the
+/// file, or `None` if the file is known not to exist.
+pub fn lookup(mountpoint_id: u32, parent: INode, name: &[u8]) -> Option<Option<INode>> {
+	let key = key(mountpoint_id, parent, name)?;
+	let mut dcache = DCACHE.lock();
+	dcache.clock += 1;
+	let clock = dcache.clock;
+	let entry = dcache.entries.get_mut(&key)?;
+	entry.last_used = clock;
+	Some(entry.inode)
+}
+
+/// Inserts the entry `name` in the directory `parent` on the mountpoint `mountpoint_id`.
+///
+/// `inode` is the inode of the file. If `None`, the file does not exist.
+///
+/// Since caching is only an optimization, allocation failures are ignored.
+pub fn insert(mountpoint_id: u32, parent: INode, name: &[u8], inode: Option<INode>) {
+	let Some(key) = key(mountpoint_id, parent, name) else {
+		return;
+	};
+	let mut dcache = DCACHE.lock();
+	dcache.clock += 1;
+	let clock = dcache.clock;
+	if dcache.entries.len() >= DCACHE_MAX {
+		// Evict the entries that have not been used recently
+		let threshold = clock.saturating_sub((DCACHE_MAX / 2) as u64);
+		dcache.entries.retain(|_, e| e.last_used >= threshold);
+	}
+	let _ = dcache.entries.insert(
+		key,
+		Entry {
+			inode,
+			last_used: clock,
+		},
+	);
+}
+
+/// Removes the entry `name` in the directory `parent` on the mountpoint `mountpoint_id`.
+pub fn invalidate(mountpoint_id: u32, parent: INode, name: &[u8]) {
+	match key(mountpoint_id, parent, name) {
+		Some(key) => {
+			DCACHE.lock().entries.remove(&key);
+		}
+		// Cannot build the key, remove the whole directory instead
+		None => invalidate_dir(mountpoint_id, parent),
+	}
+}
+
+/// Removes all the entries of the directory `dir` on the mountpoint `mountpoint_id`.
+///
+/// This function must be called when the directory is removed since its inode may be reused.
+pub fn invalidate_dir(mountpoint_id: u32, dir: INode) {
+	DCACHE
+		.lock()
+		.entries
+		.retain(|k, _| k.mountpoint_id != mountpoint_id || k.parent != dir);
+}
+
+/// Removes all the entries of the mountpoint `mountpoint_id`.
+pub fn invalidate_mountpoint(mountpoint_id: u32) {
+	DCACHE
+		.lock()
+		.entries
+		.retain(|k, _| k.mountpoint_id != mountpoint_id);
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test_case]
+	fn dcache_lookup() {
+		let mp = u32::MAX;
+		assert_eq!(lookup(mp, 2, b"usr"), None);
+		insert(mp, 2, b"usr", Some(12));
+		insert(mp, 12, b"nonexistent", None);
+		assert_eq!(lookup(mp, 2, b"usr"), Some(Some(12)));
+		assert_eq!(lookup(mp, 12, b"nonexistent"), Some(None));
+
+		invalidate(mp, 2, b"usr");
+		assert_eq!(lookup(mp, 2, b"usr"), None);
+		invalidate_dir(mp, 12);
+		assert_eq!(lookup(mp, 12, b"nonexistent"), None);
+
+		insert(mp, 2, b"lib", Some(13));
+		invalidate_mountpoint(mp);
+		assert_eq!(lookup(mp, 2, b"lib"), None);
+	}
+}
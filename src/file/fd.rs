@@ -212,6 +212,37 @@ impl FileDescriptorTable {
 		Ok(&self.fds[i])
 	}
 
+	/// Creates a file descriptor pointing to the already existing open file description
+	/// `open_file`, and returns a pointer to it with its ID.
+	///
+	/// `flags` are the file descriptor's flags.
+	pub fn install_fd(
+		&mut self,
+		flags: i32,
+		open_file: Arc<Mutex<OpenFile>>,
+	) -> EResult<&FileDescriptor> {
+		let id = self.get_available_fd(None)?;
+		let i = self
+			.fds
+			.binary_search_by(|fd| fd.get_id().cmp(&id))
+			.unwrap_err();
+
+		let fd = FileDescriptor {
+			id,
+			flags,
+
+			open_file,
+		};
+		self.fds.insert(i, fd)?;
+
+		Ok(&self.fds[i])
+	}
+
+	/// Returns an iterator over the file descriptors, sorted by ID.
+	pub fn iter(&self) -> impl Iterator<Item = &FileDescriptor> {
+		self.fds.iter()
+	}
+
 	/// Returns an immutable reference to the file descriptor with ID `id`.
 	///
 	/// If the file descriptor doesn't exist, the function returns `None`.
@@ -297,6 +328,22 @@ impl FileDescriptorTable {
 		})
 	}
 
+	/// Closes every file descriptor that has the close-on-exec flag set.
+	///
+	/// Errors are ignored since the execution of the new program cannot be cancelled at this
+	/// point.
+	pub fn close_on_exec(&mut self) {
+		let mut i = 0;
+		while i < self.fds.len() {
+			if self.fds[i].get_flags() & FD_CLOEXEC == 0 {
+				i += 1;
+				continue;
+			}
+			let fd = self.fds.remove(i);
+			let _ = fd.close();
+		}
+	}
+
 	/// Closes the file descriptor with the ID `id`.
 	///
 	/// The function returns an Err if the file descriptor doesn't exist.
@@ -384,4 +431,32 @@ mod test {
 		assert!(fd3 >= 8);
 		assert_ne!(fd3, fd2);
 	}
+
+	#[test_case]
+	fn fd_dup_cloexec() {
+		let mut fds = FileDescriptorTable::default();
+		fds.create_fd(0, dummy_open_file()).unwrap();
+
+		let fd0 = fds.duplicate_fd(0, NewFDConstraint::Min(4), true).unwrap();
+		assert_eq!(fd0.get_id(), 4);
+		assert_eq!(fd0.get_flags(), FD_CLOEXEC);
+
+		// Replacing an existing file descriptor sets its flags
+		let fd1 = fds.duplicate_fd(0, NewFDConstraint::Fixed(4), false).unwrap();
+		assert_eq!(fd1.get_id(), 4);
+		assert_eq!(fd1.get_flags(), 0);
+	}
+
+	#[test_case]
+	fn fd_close_on_exec() {
+		let mut fds = FileDescriptorTable::default();
+		fds.create_fd(0, dummy_open_file()).unwrap();
+		fds.create_fd(FD_CLOEXEC, dummy_open_file()).unwrap();
+		fds.create_fd(0, dummy_open_file()).unwrap();
+
+		fds.close_on_exec();
+		assert!(fds.get_fd(0).is_some());
+		assert!(fds.get_fd(1).is_none());
+		assert!(fds.get_fd(2).is_some());
+	}
 }
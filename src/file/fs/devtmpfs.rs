@@ -0,0 +1,57 @@
+//! Devtmpfs is a tmpfs holding the files of the devices registered on the system.
+//!
+//! The filesystem is mounted on `/dev` at boot. Device files are created when devices are
+//! registered, and removed when they are unregistered.
+//!
+//! There is only one instance of the filesystem, shared by all its mountpoints.
+
+use super::options::MountOptions;
+use super::tmp::TmpFS;
+use super::tmp::DEFAULT_MAX_SIZE;
+use super::Filesystem;
+use super::FilesystemType;
+use crate::errno::Errno;
+use crate::file::path::Path;
+use crate::util::io::IO;
+use crate::util::lock::Mutex;
+use crate::util::ptr::arc::Arc;
+
+/// The path on which the filesystem is mounted at boot.
+pub const MOUNT_PATH: &[u8] = b"/dev";
+
+/// The instance of the filesystem, created at the first mount.
+static INSTANCE: Mutex<Option<Arc<Mutex<dyn Filesystem>>>> = Mutex::new(None);
+
+/// Structure representing the devtmpfs file system type.
+pub struct DevTmpFsType {}
+
+impl FilesystemType for DevTmpFsType {
+	fn get_name(&self) -> &'static [u8] {
+		b"devtmpfs"
+	}
+
+	fn detect(&self, _io: &mut dyn IO) -> Result<bool, Errno> {
+		Ok(false)
+	}
+
+	fn load_filesystem(
+		&self,
+		_io: &mut dyn IO,
+		_mountpath: Path,
+		_readonly: bool,
+		options: &MountOptions,
+	) -> Result<Arc<Mutex<dyn Filesystem>>, Errno> {
+		options.check(&[])?;
+		let mut instance = INSTANCE.lock();
+		if let Some(fs) = &*instance {
+			return Ok(fs.clone());
+		}
+		let fs: Arc<Mutex<dyn Filesystem>> = Arc::new(Mutex::new(TmpFS::new(
+			b"devtmpfs",
+			DEFAULT_MAX_SIZE,
+			false,
+		)?))?;
+		*instance = Some(fs.clone());
+		Ok(fs)
+	}
+}
@@ -7,10 +7,12 @@ use super::write;
 use super::Superblock;
 use crate::errno::Errno;
 use crate::util::io::IO;
-use core::mem::size_of;
 
 /// Structure representing a block group descriptor to be stored into the Block
 /// Group Descriptor Table (BGDT).
+///
+/// With the 64 bits feature, descriptors are larger. Only the first part of the structure,
+/// represented here, is used.
 #[repr(C, packed)]
 pub struct BlockGroupDescriptor {
 	/// The block address of the block usage bitmap.
@@ -39,7 +41,7 @@ impl BlockGroupDescriptor {
 	/// - `io` is the I/O interface.
 	pub fn read(i: u32, superblock: &Superblock, io: &mut dyn IO) -> Result<Self, Errno> {
 		let off = (superblock.get_bgdt_offset() * superblock.get_block_size() as u64)
-			+ (i as u64 * size_of::<Self>() as u64);
+			+ (i as u64 * superblock.get_group_descriptor_size());
 		unsafe { read::<Self>(off, io) }
 	}
 
@@ -51,7 +53,7 @@ impl BlockGroupDescriptor {
 	/// - `io` is the I/O interface.
 	pub fn write(&self, i: u32, superblock: &Superblock, io: &mut dyn IO) -> Result<(), Errno> {
 		let off = (superblock.get_bgdt_offset() * superblock.get_block_size() as u64)
-			+ (i as u64 * size_of::<Self>() as u64);
+			+ (i as u64 * superblock.get_group_descriptor_size());
 		write(self, off, io)
 	}
 }
@@ -0,0 +1,532 @@
+//! An extent tree (introduced by ext4) maps ranges of blocks of an inode's content to contiguous
+//! ranges of blocks on the device, instead of mapping each block individually through
+//! indirections.
+//!
+//! The root of the tree is stored in the inode, in place of the block pointers. Each node is
+//! made of a header followed by entries. The entries of leaves are extents, while the entries of
+//! other nodes are indexes pointing to the node's children.
+//!
+//! An extent may be uninitialized, meaning its blocks are allocated but must be read as zeros.
+
+use super::inode::Ext2INode;
+use super::read_block;
+use super::write_block;
+use super::zero_blocks;
+use super::Superblock;
+use crate::errno;
+use crate::errno::Errno;
+use crate::util::container::vec::Vec;
+use crate::util::io::IO;
+
+/// The signature of extent tree nodes.
+const EXTENT_MAGIC: u16 = 0xf30a;
+/// The size of a node's header and of an entry, in bytes.
+const ENTRY_SIZE: usize = 12;
+/// The maximum length of an initialized extent. Greater lengths denote uninitialized extents.
+const EXTENT_INIT_MAX_LEN: u16 = 32768;
+/// The maximum depth of a tree.
+const MAX_DEPTH: u16 = 5;
+
+/// Reads the little-endian 16 bits value at offset `off` in `buf`.
+fn get_u16(buf: &[u8], off: usize) -> u16 {
+	u16::from_le_bytes([buf[off], buf[off + 1]])
+}
+
+/// Reads the little-endian 32 bits value at offset `off` in `buf`.
+fn get_u32(buf: &[u8], off: usize) -> u32 {
+	u32::from_le_bytes([buf[off], buf[off + 1], buf[off + 2], buf[off + 3]])
+}
+
+/// An entry of a node: an extent for leaves, an index for other nodes.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Entry {
+	/// The first block of the content covered by the entry.
+	block: u32,
+	/// For extents, the length of the extent, including the uninitialized flag. Unused for
+	/// indexes.
+	len: u16,
+	/// For extents, the first block on the device. For indexes, the block of the child node.
+	start: u32,
+}
+
+impl Entry {
+	/// Tells whether the extent is uninitialized.
+	fn is_uninit(&self) -> bool {
+		self.len > EXTENT_INIT_MAX_LEN
+	}
+
+	/// Returns the number of blocks in the extent.
+	fn len(&self) -> u32 {
+		if self.is_uninit() {
+			(self.len - EXTENT_INIT_MAX_LEN) as _
+		} else {
+			self.len as _
+		}
+	}
+
+	/// Sets the number of blocks in the extent, keeping the uninitialized flag.
+	fn set_len(&mut self, len: u32) {
+		self.len = if self.is_uninit() {
+			len as u16 + EXTENT_INIT_MAX_LEN
+		} else {
+			len as u16
+		};
+	}
+
+	/// Returns the block following the last block of the content covered by the extent.
+	fn end(&self) -> u32 {
+		self.block + self.len()
+	}
+}
+
+/// A node of the tree.
+struct Node {
+	/// The depth of the node. Leaves are at depth zero.
+	depth: u16,
+	/// The maximum number of entries in the node.
+	max: u16,
+	/// The node's entries, sorted by block.
+	entries: Vec<Entry>,
+}
+
+impl Node {
+	/// Parses the node stored in `buf`.
+	fn parse(buf: &[u8]) -> Result<Self, Errno> {
+		let magic = get_u16(buf, 0);
+		let count = get_u16(buf, 2);
+		let max = get_u16(buf, 4);
+		let depth = get_u16(buf, 6);
+		if magic != EXTENT_MAGIC
+			|| count > max
+			|| ENTRY_SIZE * (max as usize + 1) > buf.len()
+			|| depth > MAX_DEPTH
+		{
+			return Err(errno!(EUCLEAN));
+		}
+
+		let mut entries = Vec::with_capacity(count as _)?;
+		for i in 0..(count as usize) {
+			let off = ENTRY_SIZE * (i + 1);
+			// The upper 16 bits of block addresses are not supported
+			let (entry, start_hi) = if depth == 0 {
+				let entry = Entry {
+					block: get_u32(buf, off),
+					len: get_u16(buf, off + 4),
+					start: get_u32(buf, off + 8),
+				};
+				(entry, get_u16(buf, off + 6))
+			} else {
+				let entry = Entry {
+					block: get_u32(buf, off),
+					len: 0,
+					start: get_u32(buf, off + 4),
+				};
+				(entry, get_u16(buf, off + 8))
+			};
+			if start_hi != 0 {
+				return Err(errno!(EUCLEAN));
+			}
+			entries.push(entry)?;
+		}
+
+		Ok(Self {
+			depth,
+			max,
+			entries,
+		})
+	}
+
+	/// Writes the node into `buf`.
+	///
+	/// The node must not contain more entries than its maximum.
+	fn write(&self, buf: &mut [u8]) {
+		debug_assert!(self.entries.len() <= self.max as usize);
+		buf[0..2].copy_from_slice(&EXTENT_MAGIC.to_le_bytes());
+		buf[2..4].copy_from_slice(&(self.entries.len() as u16).to_le_bytes());
+		buf[4..6].copy_from_slice(&self.max.to_le_bytes());
+		buf[6..8].copy_from_slice(&self.depth.to_le_bytes());
+		buf[ENTRY_SIZE..(ENTRY_SIZE * (self.max as usize + 1))].fill(0);
+
+		for (i, e) in self.entries.iter().enumerate() {
+			let off = ENTRY_SIZE * (i + 1);
+			buf[off..(off + 4)].copy_from_slice(&e.block.to_le_bytes());
+			if self.depth == 0 {
+				buf[(off + 4)..(off + 6)].copy_from_slice(&e.len.to_le_bytes());
+				buf[(off + 8)..(off + 12)].copy_from_slice(&e.start.to_le_bytes());
+			} else {
+				buf[(off + 4)..(off + 8)].copy_from_slice(&e.start.to_le_bytes());
+			}
+		}
+	}
+
+	/// Returns the index of the child node that may contain the block `blk`.
+	///
+	/// If the node has no entry, the function returns `None`.
+	fn child_index(&self, blk: u32) -> Option<usize> {
+		if self.entries.is_empty() {
+			return None;
+		}
+		Some(
+			self.entries
+				.iter()
+				.rposition(|e| e.block <= blk)
+				.unwrap_or(0),
+		)
+	}
+
+	/// Returns the extent containing the block `blk`, along with its index.
+	fn find_extent(&self, blk: u32) -> Option<(usize, &Entry)> {
+		self.entries
+			.iter()
+			.enumerate()
+			.find(|(_, e)| e.block <= blk && blk < e.end())
+	}
+}
+
+/// Returns the maximum number of entries in a node stored in a block.
+fn block_node_max(superblock: &Superblock) -> u16 {
+	(superblock.get_block_size() as usize / ENTRY_SIZE - 1) as _
+}
+
+/// Reads the node stored in the block `blk`, which must be at depth `depth`.
+fn read_node(
+	blk: u32,
+	depth: u16,
+	superblock: &Superblock,
+	io: &mut dyn IO,
+) -> Result<Node, Errno> {
+	if blk >= superblock.total_blocks {
+		return Err(errno!(EUCLEAN));
+	}
+	let mut buf = crate::vec![0u8; superblock.get_block_size() as _]?;
+	read_block(blk as _, superblock, io, &mut buf)?;
+	let node = Node::parse(&buf)?;
+	if node.depth != depth {
+		return Err(errno!(EUCLEAN));
+	}
+	Ok(node)
+}
+
+/// Writes the node `node` in the block `blk`.
+fn write_node(
+	node: &Node,
+	blk: u32,
+	superblock: &Superblock,
+	io: &mut dyn IO,
+) -> Result<(), Errno> {
+	let mut buf = crate::vec![0u8; superblock.get_block_size() as _]?;
+	node.write(&mut buf);
+	write_block(blk as _, superblock, io, &buf)
+}
+
+/// Allocates a zeroed block for the content or the tree of the inode `inode`.
+fn alloc_block(
+	inode: &mut Ext2INode,
+	superblock: &mut Superblock,
+	io: &mut dyn IO,
+) -> Result<u32, Errno> {
+	let blk = superblock.get_free_block(io)?;
+	superblock.mark_block_used(io, blk)?;
+	superblock.write(io)?;
+	zero_blocks(blk as _, 1, superblock, io)?;
+
+	inode.increment_used_sectors(superblock.get_block_size());
+	Ok(blk)
+}
+
+/// Frees `count` blocks of the inode `inode`, starting at `start`.
+fn free_blocks(
+	inode: &mut Ext2INode,
+	start: u32,
+	count: u32,
+	superblock: &mut Superblock,
+	io: &mut dyn IO,
+) -> Result<(), Errno> {
+	let blk_size = superblock.get_block_size();
+	for blk in start..(start + count) {
+		if blk >= superblock.total_blocks {
+			return Err(errno!(EUCLEAN));
+		}
+		superblock.free_block(io, blk)?;
+		inode.decrement_used_sectors(blk_size);
+	}
+	Ok(())
+}
+
+/// Returns the block on the device of the `blk`th block of the content of the inode `inode`.
+///
+/// If the block is not allocated or belongs to an uninitialized extent, the function returns
+/// `None`.
+pub fn lookup(
+	inode: &Ext2INode,
+	blk: u32,
+	superblock: &Superblock,
+	io: &mut dyn IO,
+) -> Result<Option<u32>, Errno> {
+	let mut node = Node::parse(inode.get_blocks_area())?;
+	while node.depth > 0 {
+		let Some(i) = node.child_index(blk) else {
+			return Ok(None);
+		};
+		node = read_node(node.entries[i].start, node.depth - 1, superblock, io)?;
+	}
+
+	let Some((_, extent)) = node.find_extent(blk) else {
+		return Ok(None);
+	};
+	if extent.is_uninit() {
+		return Ok(None);
+	}
+	let dev_blk = extent.start + (blk - extent.block);
+	if dev_blk >= superblock.total_blocks {
+		return Err(errno!(EUCLEAN));
+	}
+	Ok(Some(dev_blk))
+}
+
+/// Maps the `blk`th block of the content of the inode `inode` to an initialized block on the
+/// device, allocating it if necessary.
+///
+/// The function returns the block on the device.
+pub fn map(
+	inode: &mut Ext2INode,
+	blk: u32,
+	superblock: &mut Superblock,
+	io: &mut dyn IO,
+) -> Result<u32, Errno> {
+	// Find the path from the root to the leaf that may contain the block. For each node, the
+	// location of the node and the index of the next node in its entries are kept
+	let mut path: Vec<(u32, Node, usize)> = Vec::new();
+	let mut node = Node::parse(inode.get_blocks_area())?;
+	let mut loc = 0;
+	while node.depth > 0 {
+		let i = node.child_index(blk).ok_or_else(|| errno!(EUCLEAN))?;
+		let child_loc = node.entries[i].start;
+		let child = read_node(child_loc, node.depth - 1, superblock, io)?;
+		path.push((loc, node, i))?;
+		loc = child_loc;
+		node = child;
+	}
+
+	let dev_blk = match node.find_extent(blk).map(|(i, e)| (i, *e)) {
+		Some((_, extent)) if !extent.is_uninit() => return Ok(extent.start + (blk - extent.block)),
+
+		// Initialize the block by splitting the uninitialized extent around it
+		Some((i, extent)) => {
+			let dev_blk = extent.start + (blk - extent.block);
+			zero_blocks(dev_blk as _, 1, superblock, io)?;
+
+			node.entries.remove(i);
+			let parts = [
+				(extent.block, blk - extent.block, extent.start, true),
+				(blk, 1, dev_blk, false),
+				(blk + 1, extent.end() - blk - 1, dev_blk + 1, true),
+			];
+			let mut j = i;
+			for (block, len, start, uninit) in parts {
+				if len == 0 {
+					continue;
+				}
+				let flag = if uninit { EXTENT_INIT_MAX_LEN } else { 0 };
+				let entry = Entry {
+					block,
+					len: len as u16 + flag,
+					start,
+				};
+				node.entries.insert(j, entry)?;
+				j += 1;
+			}
+			dev_blk
+		}
+
+		None => {
+			let dev_blk = alloc_block(inode, superblock, io)?;
+			// Extend the previous extent if contiguous, else insert a new one
+			let i = node.entries.iter().position(|e| e.block > blk);
+			let i = i.unwrap_or(node.entries.len());
+			let prev = i.checked_sub(1).filter(|p| {
+				let e = &node.entries[*p];
+				!e.is_uninit()
+					&& e.end() == blk && e.start + e.len() == dev_blk
+					&& e.len < EXTENT_INIT_MAX_LEN
+			});
+			match prev {
+				Some(p) => node.entries[p].len += 1,
+				None => {
+					let entry = Entry {
+						block: blk,
+						len: 1,
+						start: dev_blk,
+					};
+					node.entries.insert(i, entry)?;
+				}
+			}
+			dev_blk
+		}
+	};
+
+	// Write the modified nodes back, splitting the ones that overflow
+	loop {
+		if node.entries.len() <= node.max as usize {
+			if path.is_empty() {
+				node.write(inode.get_blocks_area_mut());
+			} else {
+				write_node(&node, loc, superblock, io)?;
+			}
+			break;
+		}
+
+		let Some((parent_loc, mut parent, i)) = path.pop() else {
+			// The root overflows: move its entries to a new node and add a level to the tree
+			if node.depth >= MAX_DEPTH {
+				return Err(errno!(ENOSPC));
+			}
+			let child_blk = alloc_block(inode, superblock, io)?;
+			let child = Node {
+				depth: node.depth,
+				max: block_node_max(superblock),
+				entries: node.entries,
+			};
+			write_node(&child, child_blk, superblock, io)?;
+
+			let mut entries = Vec::new();
+			entries.push(Entry {
+				block: child.entries[0].block,
+				len: 0,
+				start: child_blk,
+			})?;
+			let root = Node {
+				depth: child.depth + 1,
+				max: node.max,
+				entries,
+			};
+			root.write(inode.get_blocks_area_mut());
+			break;
+		};
+
+		// Move the upper half of the node's entries to a new node
+		let new_blk = alloc_block(inode, superblock, io)?;
+		let mid = node.entries.len() / 2;
+		let mut upper = Node {
+			depth: node.depth,
+			max: block_node_max(superblock),
+			entries: Vec::with_capacity(node.entries.len() - mid)?,
+		};
+		upper.entries.extend_from_slice(&node.entries[mid..])?;
+		node.entries.truncate(mid);
+		write_node(&node, loc, superblock, io)?;
+		write_node(&upper, new_blk, superblock, io)?;
+
+		let entry = Entry {
+			block: upper.entries[0].block,
+			len: 0,
+			start: new_blk,
+		};
+		parent.entries.insert(i + 1, entry)?;
+		loc = parent_loc;
+		node = parent;
+	}
+
+	Ok(dev_blk)
+}
+
+/// Frees the blocks of the node `node` and its children covering the content of the inode
+/// `inode` starting at block `from`.
+///
+/// Children left without entries are freed and removed from `node`.
+fn truncate_node(
+	inode: &mut Ext2INode,
+	node: &mut Node,
+	from: u32,
+	superblock: &mut Superblock,
+	io: &mut dyn IO,
+) -> Result<(), Errno> {
+	if node.depth == 0 {
+		for i in 0..node.entries.len() {
+			let e = node.entries[i];
+			if e.block >= from {
+				free_blocks(inode, e.start, e.len(), superblock, io)?;
+			} else if e.end() > from {
+				let keep = from - e.block;
+				free_blocks(inode, e.start + keep, e.len() - keep, superblock, io)?;
+				node.entries[i].set_len(keep);
+			}
+		}
+		node.entries.retain(|e| e.block < from);
+		return Ok(());
+	}
+
+	for i in (0..node.entries.len()).rev() {
+		// Children before the one containing `from` are not affected
+		if node
+			.entries
+			.get(i + 1)
+			.is_some_and(|next| next.block <= from)
+		{
+			break;
+		}
+		let child_blk = node.entries[i].start;
+		let mut child = read_node(child_blk, node.depth - 1, superblock, io)?;
+		truncate_node(inode, &mut child, from, superblock, io)?;
+		if child.entries.is_empty() {
+			free_blocks(inode, child_blk, 1, superblock, io)?;
+			node.entries.remove(i);
+		} else {
+			write_node(&child, child_blk, superblock, io)?;
+		}
+	}
+	Ok(())
+}
+
+/// Frees the blocks of the content of the inode `inode` starting at block `from`.
+pub fn truncate(
+	inode: &mut Ext2INode,
+	from: u32,
+	superblock: &mut Superblock,
+	io: &mut dyn IO,
+) -> Result<(), Errno> {
+	let mut root = Node::parse(inode.get_blocks_area())?;
+	truncate_node(inode, &mut root, from, superblock, io)?;
+	if root.entries.is_empty() {
+		root.depth = 0;
+	}
+	root.write(inode.get_blocks_area_mut());
+	Ok(())
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test_case]
+	fn extent_node() {
+		let mut entries = Vec::new();
+		let extents = [(0, 8, 100), (8, 4 + EXTENT_INIT_MAX_LEN, 200), (20, 1, 300)];
+		for (block, len, start) in extents {
+			entries
+				.push(Entry {
+					block,
+					len,
+					start,
+				})
+				.unwrap();
+		}
+		let node = Node {
+			depth: 0,
+			max: 4,
+			entries,
+		};
+		let mut buf = [0; 60];
+		node.write(&mut buf);
+
+		let node = Node::parse(&buf).unwrap();
+		assert_eq!(node.entries.len(), 3);
+		assert_eq!(node.find_extent(3).map(|(i, _)| i), Some(0));
+		let (_, e) = node.find_extent(10).unwrap();
+		assert!(e.is_uninit());
+		assert_eq!(e.len(), 4);
+		assert_eq!(e.end(), 12);
+		assert!(node.find_extent(12).is_none());
+		assert_eq!(node.child_index(15), Some(1));
+	}
+}
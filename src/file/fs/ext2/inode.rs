@@ -2,6 +2,7 @@
 
 use super::block_group_descriptor::BlockGroupDescriptor;
 use super::directory_entry::DirectoryEntry;
+use super::extent;
 use super::read;
 use super::read_block;
 use super::write;
@@ -10,6 +11,7 @@ use super::zero_blocks;
 use super::Superblock;
 use crate::errno;
 use crate::errno::Errno;
+use crate::errno::ResultContext;
 use crate::file;
 use crate::file::FileType;
 use crate::file::Mode;
@@ -25,6 +27,7 @@ use core::mem::size_of;
 use core::num::NonZeroUsize;
 use core::ptr;
 use core::ptr::addr_of;
+use core::ptr::addr_of_mut;
 use core::ptr::copy_nonoverlapping;
 use core::slice;
 
@@ -99,6 +102,8 @@ const INODE_FLAG_HASH_INDEXED: u32 = 0x10000;
 const INODE_FLAG_AFS_DIRECTORY: u32 = 0x20000;
 /// Journal file data
 const INODE_FLAG_JOURNAL_FILE: u32 = 0x40000;
+/// The inode's content is mapped using an extent tree.
+const INODE_FLAG_EXTENTS: u32 = 0x80000;
 
 /// The size of a sector in bytes.
 const SECTOR_SIZE: u32 = 512;
@@ -107,6 +112,13 @@ const SECTOR_SIZE: u32 = 512;
 /// separate block.
 const SYMLINK_INODE_STORE_LIMIT: u64 = 60;
 
+/// The size of the base inode structure, after which extra fields may be stored.
+const INODE_BASE_SIZE: u64 = 128;
+/// The size of the extra fields written when creating an inode.
+const INODE_EXTRA_SIZE: u16 = 32;
+/// The offset of the creation timestamp in the inode, inside of the extra fields.
+const INODE_CRTIME_OFF: u64 = 0x90;
+
 /// The inode of the root directory.
 pub const ROOT_DIRECTORY_INODE: u32 = 2;
 /// The root directory's default mode.
@@ -224,7 +236,52 @@ impl Ext2INode {
 	pub fn read(i: u32, superblock: &Superblock, io: &mut dyn IO) -> Result<Self, Errno> {
 		let off = Self::get_disk_offset(i, superblock, io)?;
 
-		unsafe { read::<Self>(off, io) }
+		unsafe { read::<Self>(off, io) }.context("ext2: inode read")
+	}
+
+	/// Reads the creation timestamp of the `i`th inode, stored in the extra fields of the inode.
+	///
+	/// If the inode has no room for the timestamp, the function returns `None`.
+	pub fn read_crtime(
+		i: u32,
+		superblock: &Superblock,
+		io: &mut dyn IO,
+	) -> Result<Option<u32>, Errno> {
+		if (superblock.get_inode_size() as u64) < INODE_CRTIME_OFF + 4 {
+			return Ok(None);
+		}
+		let off = Self::get_disk_offset(i, superblock, io)?;
+
+		let extra_size = unsafe { read::<u16>(off + INODE_BASE_SIZE, io) }?;
+		if INODE_BASE_SIZE + (extra_size as u64) < INODE_CRTIME_OFF + 4 {
+			return Ok(None);
+		}
+		let crtime = unsafe { read::<u32>(off + INODE_CRTIME_OFF, io) }?;
+		Ok(Some(crtime))
+	}
+
+	/// Initializes the extra fields of the newly created `i`th inode, with `crtime` as the
+	/// creation timestamp.
+	///
+	/// If the inode has no room for extra fields, the function returns `false`.
+	pub fn init_extra(
+		i: u32,
+		superblock: &Superblock,
+		io: &mut dyn IO,
+		crtime: u32,
+	) -> Result<bool, Errno> {
+		if (superblock.get_inode_size() as u64) < INODE_BASE_SIZE + INODE_EXTRA_SIZE as u64 {
+			return Ok(false);
+		}
+		let off = Self::get_disk_offset(i, superblock, io)?;
+
+		// The fields left over by a previous inode are cleared
+		let mut extra = [0u8; INODE_EXTRA_SIZE as usize];
+		extra[..2].copy_from_slice(&INODE_EXTRA_SIZE.to_le_bytes());
+		let crtime_off = (INODE_CRTIME_OFF - INODE_BASE_SIZE) as usize;
+		extra[crtime_off..(crtime_off + 4)].copy_from_slice(&crtime.to_le_bytes());
+		write(&extra, off + INODE_BASE_SIZE, io)?;
+		Ok(true)
 	}
 
 	/// Returns the type of the file.
@@ -273,7 +330,7 @@ impl Ext2INode {
 	/// Arguments:
 	/// - `superblock` is the filesystem's superblock.
 	/// - `size` is the file's size.
-	fn set_size(&mut self, superblock: &Superblock, size: u64) {
+	pub fn set_size(&mut self, superblock: &Superblock, size: u64) {
 		let has_version = superblock.major_version >= 1;
 		let has_feature = superblock.write_required_features & super::WRITE_REQUIRED_64_BITS != 0;
 
@@ -285,17 +342,38 @@ impl Ext2INode {
 		}
 	}
 
+	/// Tells whether the inode's content is mapped using an extent tree instead of block
+	/// pointers.
+	fn has_extents(&self) -> bool {
+		self.flags & INODE_FLAG_EXTENTS != 0
+	}
+
+	/// Returns the area of the inode storing block pointers, or the root of the extent tree.
+	pub fn get_blocks_area(&self) -> &[u8; 60] {
+		// Safe because the block pointers are contiguous and the array has no alignment
+		// requirement
+		unsafe { &*(addr_of!(self.direct_block_ptrs) as *const [u8; 60]) }
+	}
+
+	/// Returns a mutable reference to the area of the inode storing block pointers, or the root
+	/// of the extent tree.
+	pub fn get_blocks_area_mut(&mut self) -> &mut [u8; 60] {
+		// Safe because the block pointers are contiguous and the array has no alignment
+		// requirement
+		unsafe { &mut *(addr_of_mut!(self.direct_block_ptrs) as *mut [u8; 60]) }
+	}
+
 	/// Increments the number of used sectors of one block.
 	///
 	/// `blk_size` is the size of a block.
-	fn increment_used_sectors(&mut self, blk_size: u32) {
+	pub fn increment_used_sectors(&mut self, blk_size: u32) {
 		self.used_sectors += math::ceil_div(blk_size, SECTOR_SIZE);
 	}
 
 	/// Decrements the number of used sectors of one block.
 	///
 	/// `blk_size` is the size of a block.
-	fn decrement_used_sectors(&mut self, blk_size: u32) {
+	pub fn decrement_used_sectors(&mut self, blk_size: u32) {
 		if self.used_sectors > 0 {
 			self.used_sectors -= math::ceil_div(blk_size, SECTOR_SIZE);
 		}
@@ -380,12 +458,16 @@ impl Ext2INode {
 	/// - `io` is the I/O interface.
 	///
 	/// If the block doesn't exist, the function returns `None`.
-	fn get_content_block_off(
+	pub fn get_content_block_off(
 		&self,
 		i: u32,
 		superblock: &Superblock,
 		io: &mut dyn IO,
 	) -> Result<Option<u32>, Errno> {
+		if self.has_extents() {
+			return extent::lookup(self, i, superblock, io);
+		}
+
 		let blk_size = superblock.get_block_size();
 		let entries_per_blk = blk_size / size_of::<u32>() as u32;
 
@@ -498,6 +580,10 @@ impl Ext2INode {
 		superblock: &mut Superblock,
 		io: &mut dyn IO,
 	) -> Result<u32, Errno> {
+		if self.has_extents() {
+			return extent::map(self, i, superblock, io);
+		}
+
 		let blk_size = superblock.get_block_size();
 		let entries_per_blk = blk_size / size_of::<u32>() as u32;
 
@@ -626,6 +712,9 @@ impl Ext2INode {
 
 			let next_off = off - blk_per_blk * inner_index;
 			if self.indirections_free(n - 1, b, next_off, superblock, io)? {
+				// Removing the entry pointing to the freed block
+				write::<u32>(&0, byte_off, io)?;
+
 				// Reading the current block
 				let mut buff =
 					malloc::Alloc::<u8>::new_default(NonZeroUsize::new(blk_size as _).unwrap())?;
@@ -643,6 +732,7 @@ impl Ext2INode {
 			Ok(false)
 		} else {
 			superblock.free_block(io, begin)?;
+			self.decrement_used_sectors(blk_size);
 			Ok(true)
 		}
 	}
@@ -668,9 +758,11 @@ impl Ext2INode {
 
 		// If direct block, handle it directly
 		if level == 0 {
-			superblock.free_block(io, self.direct_block_ptrs[i as usize])?;
-			self.direct_block_ptrs[i as usize] = 0;
-			self.decrement_used_sectors(blk_size);
+			if self.direct_block_ptrs[i as usize] != 0 {
+				superblock.free_block(io, self.direct_block_ptrs[i as usize])?;
+				self.direct_block_ptrs[i as usize] = 0;
+				self.decrement_used_sectors(blk_size);
+			}
 
 			return Ok(());
 		}
@@ -697,9 +789,8 @@ impl Ext2INode {
 		if let Some(begin) = Self::blk_offset_to_option(begin_id) {
 			let empty = self.indirections_free(level, begin, target, superblock, io)?;
 
-			// If the block has zero entries left, free it
+			// If the block has zero entries left, it has been freed
 			if empty {
-				superblock.free_block(io, begin)?;
 				match level {
 					1 => self.singly_indirect_block_ptr = 0,
 					2 => self.doubly_indirect_block_ptr = 0,
@@ -707,14 +798,33 @@ impl Ext2INode {
 
 					_ => unreachable!(),
 				}
-
-				self.decrement_used_sectors(blk_size);
 			}
 		}
 
 		Ok(())
 	}
 
+	/// Frees the content blocks of the file, from block offset `begin` to `end` (exclusive).
+	///
+	/// `end` must be the number of content blocks of the file.
+	fn free_content_blocks(
+		&mut self,
+		begin: u32,
+		end: u32,
+		superblock: &mut Superblock,
+		io: &mut dyn IO,
+	) -> Result<(), Errno> {
+		if self.has_extents() {
+			return extent::truncate(self, begin, superblock, io);
+		}
+
+		for i in begin..end {
+			// TODO Optimize
+			self.free_content_block(i, superblock, io)?;
+		}
+		Ok(())
+	}
+
 	/// Reads the content of the inode.
 	///
 	/// Arguments:
@@ -826,6 +936,115 @@ impl Ext2INode {
 		Ok(())
 	}
 
+	/// Allocates the content blocks covering `len` bytes at offset `off` that are not allocated
+	/// yet. The new blocks are filled with zeros.
+	///
+	/// Arguments:
+	/// - `superblock` is the filesystem's superblock.
+	/// - `io` is the I/O interface.
+	///
+	/// If the range ends past the end of the file, the size of the file is increased accordingly.
+	pub fn allocate_content(
+		&mut self,
+		off: u64,
+		len: u64,
+		superblock: &mut Superblock,
+		io: &mut dyn IO,
+	) -> Result<(), Errno> {
+		let blk_size = superblock.get_block_size() as u64;
+		let end = off.checked_add(len).ok_or_else(|| errno!(EFBIG))?;
+		for i in (off / blk_size)..end.div_ceil(blk_size) {
+			if self.get_content_block_off(i as _, superblock, io)?.is_none() {
+				self.alloc_content_block(i as _, superblock, io)?;
+			}
+		}
+
+		let size = self.get_size(superblock);
+		self.set_size(superblock, max(end, size));
+		Ok(())
+	}
+
+	/// Fills the content of the inode from offset `begin` to `end` (exclusive) with zeros.
+	///
+	/// The range must not span several blocks. If the block is not allocated, the function does
+	/// nothing.
+	fn zero_content(
+		&self,
+		begin: u64,
+		end: u64,
+		superblock: &Superblock,
+		io: &mut dyn IO,
+	) -> Result<(), Errno> {
+		if begin >= end {
+			return Ok(());
+		}
+		let blk_size = superblock.get_block_size();
+		let Some(blk) = self.get_content_block_off((begin / blk_size as u64) as _, superblock, io)?
+		else {
+			return Ok(());
+		};
+
+		let mut blk_buff =
+			malloc::Alloc::<u8>::new_default(NonZeroUsize::new(blk_size as _).unwrap())?;
+		read_block(blk as _, superblock, io, blk_buff.as_slice_mut())?;
+		let inner_begin = (begin % blk_size as u64) as usize;
+		let inner_end = inner_begin + (end - begin) as usize;
+		blk_buff.as_slice_mut()[inner_begin..inner_end].fill(0);
+		write_block(blk as _, superblock, io, blk_buff.as_slice_mut())
+	}
+
+	/// Deallocates the content of the inode covering `len` bytes at offset `off`, which then reads
+	/// as zeros.
+	///
+	/// Blocks entirely in the range are freed, while the parts of the range on the other blocks
+	/// are filled with zeros. The size of the file is not changed.
+	///
+	/// Arguments:
+	/// - `superblock` is the filesystem's superblock.
+	/// - `io` is the I/O interface.
+	///
+	/// Punching holes in files using extents is not supported.
+	pub fn punch_hole(
+		&mut self,
+		off: u64,
+		len: u64,
+		superblock: &mut Superblock,
+		io: &mut dyn IO,
+	) -> Result<(), Errno> {
+		if self.has_extents() {
+			return Err(errno!(EOPNOTSUPP));
+		}
+
+		let size = self.get_size(superblock);
+		let end = min(off.saturating_add(len), size);
+		if off >= end {
+			return Ok(());
+		}
+
+		let blk_size = superblock.get_block_size() as u64;
+		let begin_blk = off.div_ceil(blk_size);
+		// The data past the end of the file does not need to be kept
+		let end_blk = if end == size {
+			end.div_ceil(blk_size)
+		} else {
+			end / blk_size
+		};
+
+		if begin_blk >= end_blk {
+			// The range is inside a single block
+			return self.zero_content(off, end, superblock, io);
+		}
+		self.zero_content(off, begin_blk * blk_size, superblock, io)?;
+		self.zero_content(end_blk * blk_size, end, superblock, io)?;
+
+		for i in begin_blk..end_blk {
+			if self.get_content_block_off(i as _, superblock, io)?.is_some() {
+				self.free_content_block(i as _, superblock, io)?;
+			}
+		}
+		Ok(())
+	}
+
 	/// Truncates the file to the given size `size`.
 	///
 	/// Arguments:
@@ -846,22 +1065,25 @@ impl Ext2INode {
 			return Ok(());
 		}
 
-		// Changing the size
-		self.set_size(superblock, size);
-
 		// The size of a block
 		let blk_size = superblock.get_block_size();
 
+		// Zeroing the remaining part of the last block so that it reads as zeros if the file
+		// grows again
+		let last_blk_end = min(
+			math::ceil_div(size, blk_size as _) * blk_size as u64,
+			old_size,
+		);
+		self.zero_content(size, last_blk_end, superblock, io)?;
+
+		// Changing the size
+		self.set_size(superblock, size);
+
 		// The index of the beginning block to free
 		let begin = math::ceil_div(size, blk_size as _) as u32;
 		// The index of the end block to free
 		let end = math::ceil_div(old_size, blk_size as _) as u32;
-		for i in begin..end {
-			// TODO Optimize
-			self.free_content_block(i, superblock, io)?;
-		}
-
-		Ok(())
+		self.free_content_blocks(begin, end, superblock, io)
 	}
 
 	/// Frees all content blocks by doing redirections.
@@ -921,6 +1143,12 @@ impl Ext2INode {
 			}
 		}
 
+		if self.has_extents() {
+			extent::truncate(self, 0, superblock, io)?;
+			self.used_sectors = 0;
+			return Ok(());
+		}
+
 		for i in 0..(DIRECT_BLOCKS_COUNT as usize) {
 			if self.direct_block_ptrs[i] != 0 {
 				if self.direct_block_ptrs[i] >= superblock.total_blocks {
@@ -1119,6 +1347,9 @@ impl Ext2INode {
 			return Err(errno!(ENAMETOOLONG));
 		}
 
+		// The directory index is not maintained, so it becomes invalid
+		self.flags &= !INODE_FLAG_HASH_INDEXED;
+
 		let mut entry_size = 8 + name.len() as u16;
 		// Ensuring alignment of entries
 		if entry_size % 4 != 0 {
@@ -1171,6 +1402,8 @@ impl Ext2INode {
 		name: S,
 	) -> Result<(), Errno> {
 		debug_assert_eq!(self.get_type(), FileType::Directory);
+		// The directory index is not maintained, so it becomes invalid
+		self.flags &= !INODE_FLAG_HASH_INDEXED;
 
 		// Allocating a buffer
 		let blk_size = superblock.get_block_size();
@@ -1235,9 +1468,7 @@ impl Ext2INode {
 			// The number of content blocks in the inode
 			let blk_count = math::ceil_div(self.get_size(superblock), blk_size as u64) as u32;
 
-			for i in first_free_blk..blk_count {
-				self.free_content_block(i, superblock, io)?;
-			}
+			self.free_content_blocks(first_free_blk, blk_count, superblock, io)?;
 			self.set_size(superblock, first_free_blk as u64 * blk_size as u64);
 		}
 
@@ -1344,7 +1575,7 @@ impl Ext2INode {
 	/// Writes the inode on the device.
 	pub fn write(&self, i: u32, superblock: &Superblock, io: &mut dyn IO) -> Result<(), Errno> {
 		let off = Self::get_disk_offset(i, superblock, io)?;
-		write(self, off, io)
+		write(self, off, io).context("ext2: inode write")
 	}
 }
 
@@ -0,0 +1,619 @@
+//! The journal allows to update the filesystem's structures atomically, so that it remains
+//! consistent if the system stops in the middle of an operation.
+//!
+//! Blocks modified by an operation are first written into the journal (a log stored in the
+//! content of a dedicated inode), followed by a commit block. Then, they are written to their
+//! actual location (checkpoint). If the system stops before the checkpoint is complete, the
+//! committed blocks are copied again from the journal when the filesystem is mounted (replay).
+//!
+//! The on-disk format is the one of JBD/JBD2, used by ext3 and ext4. All fields of the journal
+//! are stored in big-endian.
+//!
+//! Each transaction is checkpointed as soon as it is committed, so the journal never contains
+//! more than one transaction written by this driver. Thus, revocation records are never
+//! written, but they are taken into account when replaying a journal written by another
+//! implementation.
+
+use super::inode::Ext2INode;
+use super::Superblock;
+use crate::errno;
+use crate::errno::Errno;
+use crate::util::container::hashmap::HashMap;
+use crate::util::container::vec::Vec;
+use crate::util::io::IO;
+use core::cmp::min;
+
+/// The signature of journal blocks.
+const JBD_MAGIC: u32 = 0xc03b3998;
+
+/// Block type: descriptor, listing the blocks of a transaction.
+const BLOCKTYPE_DESCRIPTOR: u32 = 1;
+/// Block type: commit, marking the end of a transaction.
+const BLOCKTYPE_COMMIT: u32 = 2;
+/// Block type: journal superblock, version 1.
+const BLOCKTYPE_SUPERBLOCK_V1: u32 = 3;
+/// Block type: journal superblock, version 2.
+const BLOCKTYPE_SUPERBLOCK_V2: u32 = 4;
+/// Block type: revocation records.
+const BLOCKTYPE_REVOKE: u32 = 5;
+
+/// Incompatible feature: the journal contains revocation records.
+const INCOMPAT_REVOKE: u32 = 0x1;
+
+/// Tag flag: the first four bytes of the block have been zeroed because they were equal to
+/// [`JBD_MAGIC`].
+const TAG_FLAG_ESCAPE: u16 = 0x1;
+/// Tag flag: the tag is not followed by a UUID, which is the same as the previous one.
+const TAG_FLAG_SAME_UUID: u16 = 0x2;
+/// Tag flag: the tag is the last of the descriptor block.
+const TAG_FLAG_LAST_TAG: u16 = 0x8;
+
+/// The size of the header of journal blocks.
+const HEADER_SIZE: usize = 12;
+/// The size of a descriptor block tag.
+const TAG_SIZE: usize = 8;
+/// The size of a UUID.
+const UUID_SIZE: usize = 16;
+
+/// Offset of the `s_blocksize` field in the journal superblock.
+const SB_BLOCKSIZE: usize = 12;
+/// Offset of the `s_maxlen` field in the journal superblock.
+const SB_MAXLEN: usize = 16;
+/// Offset of the `s_first` field in the journal superblock.
+const SB_FIRST: usize = 20;
+/// Offset of the `s_sequence` field in the journal superblock.
+const SB_SEQUENCE: usize = 24;
+/// Offset of the `s_start` field in the journal superblock.
+const SB_START: usize = 28;
+/// Offset of the `s_feature_incompat` field in the journal superblock.
+const SB_FEATURE_INCOMPAT: usize = 40;
+/// Offset of the `s_uuid` field in the journal superblock.
+const SB_UUID: usize = 48;
+
+/// Reads the big-endian 32 bits value at offset `off` in `buf`.
+fn get_u32(buf: &[u8], off: usize) -> u32 {
+	u32::from_be_bytes(buf[off..(off + 4)].try_into().unwrap())
+}
+
+/// Writes the big-endian 32 bits value `val` at offset `off` in `buf`.
+fn set_u32(buf: &mut [u8], off: usize, val: u32) {
+	buf[off..(off + 4)].copy_from_slice(&val.to_be_bytes());
+}
+
+/// Reads the big-endian 16 bits value at offset `off` in `buf`.
+fn get_u16(buf: &[u8], off: usize) -> u16 {
+	u16::from_be_bytes(buf[off..(off + 2)].try_into().unwrap())
+}
+
+/// Writes the big-endian 16 bits value `val` at offset `off` in `buf`.
+fn set_u16(buf: &mut [u8], off: usize, val: u16) {
+	buf[off..(off + 2)].copy_from_slice(&val.to_be_bytes());
+}
+
+/// Writes the header of a journal block of type `blocktype` for the transaction `sequence`.
+fn set_header(buf: &mut [u8], blocktype: u32, sequence: u32) {
+	set_u32(buf, 0, JBD_MAGIC);
+	set_u32(buf, 4, blocktype);
+	set_u32(buf, 8, sequence);
+}
+
+/// Returns the tags of the descriptor block `buf`.
+///
+/// Each tag is a tuple containing the block's location on the filesystem and the tag's flags.
+fn iter_tags(buf: &[u8]) -> impl Iterator<Item = (u32, u16)> + '_ {
+	let mut off = HEADER_SIZE;
+	let mut last = false;
+	core::iter::from_fn(move || {
+		if last || off + TAG_SIZE > buf.len() {
+			return None;
+		}
+		let blk = get_u32(buf, off);
+		// Reading only the lower half of the flags is compatible with JBD's 32 bits flags
+		let flags = get_u16(buf, off + 6);
+		off += TAG_SIZE;
+		if flags & TAG_FLAG_SAME_UUID == 0 {
+			off += UUID_SIZE;
+		}
+		last = flags & TAG_FLAG_LAST_TAG != 0;
+		Some((blk, flags))
+	})
+}
+
+/// A journal, stored in an inode of the filesystem.
+pub struct Journal {
+	/// The size of a block in bytes.
+	blk_size: u32,
+	/// The location on the device of each block of the journal.
+	blocks: Vec<u32>,
+	/// The content of the journal's superblock.
+	superblock: Vec<u8>,
+}
+
+impl Journal {
+	/// Loads the journal of the filesystem.
+	///
+	/// If the filesystem does not have a journal, the function returns `None`.
+	pub fn load(superblock: &Superblock, io: &mut dyn IO) -> Result<Option<Self>, Errno> {
+		if superblock.optional_features & super::OPTIONAL_FEATURE_JOURNAL == 0
+			|| superblock.journal_inode == 0
+		{
+			return Ok(None);
+		}
+		let blk_size = superblock.get_block_size();
+
+		// Locate the blocks of the journal
+		let inode = Ext2INode::read(superblock.journal_inode, superblock, io)?;
+		let count = inode.get_size(superblock) / blk_size as u64;
+		let mut blocks = Vec::with_capacity(count as _)?;
+		for i in 0..count {
+			let blk = inode
+				.get_content_block_off(i as _, superblock, io)?
+				.ok_or_else(|| errno!(EUCLEAN))?;
+			blocks.push(blk)?;
+		}
+		let first_blk = *blocks.first().ok_or_else(|| errno!(EUCLEAN))?;
+
+		// Read and check the journal's superblock
+		let mut sb = crate::vec![0; blk_size as _]?;
+		super::read_block(first_blk as _, superblock, io, &mut sb)?;
+		if get_u32(&sb, 0) != JBD_MAGIC {
+			return Err(errno!(EUCLEAN));
+		}
+		let incompat = match get_u32(&sb, 4) {
+			BLOCKTYPE_SUPERBLOCK_V1 => 0,
+			BLOCKTYPE_SUPERBLOCK_V2 => get_u32(&sb, SB_FEATURE_INCOMPAT),
+			_ => return Err(errno!(EUCLEAN)),
+		};
+		if incompat & !INCOMPAT_REVOKE != 0 {
+			return Err(errno!(EINVAL));
+		}
+		let maxlen = get_u32(&sb, SB_MAXLEN);
+		let first = get_u32(&sb, SB_FIRST);
+		if get_u32(&sb, SB_BLOCKSIZE) != blk_size
+			|| maxlen as usize > blocks.len()
+			|| first == 0
+			|| first >= maxlen
+		{
+			return Err(errno!(EUCLEAN));
+		}
+		blocks.truncate(maxlen as _);
+
+		Ok(Some(Self {
+			blk_size,
+			blocks,
+			superblock: sb,
+		}))
+	}
+
+	/// Returns the index of the first block of the log.
+	fn first(&self) -> u32 {
+		get_u32(&self.superblock, SB_FIRST)
+	}
+
+	/// Returns the index of the journal block following `i`, wrapping around the end of the log.
+	fn next(&self, i: u32) -> u32 {
+		if i + 1 >= self.blocks.len() as u32 {
+			self.first()
+		} else {
+			i + 1
+		}
+	}
+
+	/// Returns the number of tags that fit in a descriptor block.
+	fn tags_per_descriptor(&self) -> usize {
+		(self.blk_size as usize - HEADER_SIZE - UUID_SIZE) / TAG_SIZE
+	}
+
+	/// Returns the maximum number of filesystem blocks a single transaction can contain.
+	fn max_transaction_blocks(&self) -> usize {
+		let log_len = self.blocks.len() - self.first() as usize;
+		let tags = self.tags_per_descriptor();
+		// Each group of `tags` blocks requires a descriptor block, plus the commit block. One more
+		// block is kept to account for the last descriptor block
+		log_len.saturating_sub(2) * tags / (tags + 1)
+	}
+
+	/// Reads the `i`th block of the journal into `buf`.
+	fn read_block(&self, io: &mut dyn IO, i: u32, buf: &mut [u8]) -> Result<(), Errno> {
+		let off = self.blocks[i as usize] as u64 * self.blk_size as u64;
+		io.read(off, buf)?;
+		Ok(())
+	}
+
+	/// Writes `buf` into the `i`th block of the journal.
+	fn write_block(&self, io: &mut dyn IO, i: u32, buf: &[u8]) -> Result<(), Errno> {
+		let off = self.blocks[i as usize] as u64 * self.blk_size as u64;
+		io.write(off, buf)?;
+		Ok(())
+	}
+
+	/// Writes the journal's superblock, with the given start block and sequence number.
+	///
+	/// A start of zero means the journal is empty.
+	fn write_superblock(
+		&mut self,
+		io: &mut dyn IO,
+		start: u32,
+		sequence: u32,
+	) -> Result<(), Errno> {
+		set_u32(&mut self.superblock, SB_START, start);
+		set_u32(&mut self.superblock, SB_SEQUENCE, sequence);
+		let off = self.blocks[0] as u64 * self.blk_size as u64;
+		io.write(off, &self.superblock)?;
+		Ok(())
+	}
+
+	/// Replays the transactions committed in the journal but not checkpointed, then empties the
+	/// journal.
+	///
+	/// The function returns `true` if at least one transaction has been replayed.
+	pub fn recover(&mut self, io: &mut dyn IO) -> Result<bool, Errno> {
+		let start = get_u32(&self.superblock, SB_START);
+		let first_sequence = get_u32(&self.superblock, SB_SEQUENCE);
+		if start == 0 {
+			return Ok(false);
+		}
+		if start >= self.blocks.len() as u32 {
+			return Err(errno!(EUCLEAN));
+		}
+		let mut buf = crate::vec![0; self.blk_size as _]?;
+
+		// Find the end of the last committed transaction and the revoked blocks
+		let mut revoked = HashMap::<u32, u32>::new();
+		// Revocation records of the current transaction, only valid once committed
+		let mut pending_revoked = Vec::new();
+		let mut end_sequence = first_sequence;
+		let mut i = start;
+		// Bound the number of iterations in case the log is corrupted
+		for _ in 0..self.blocks.len() {
+			self.read_block(io, i, &mut buf)?;
+			if get_u32(&buf, 0) != JBD_MAGIC || get_u32(&buf, 8) != end_sequence {
+				break;
+			}
+			match get_u32(&buf, 4) {
+				BLOCKTYPE_DESCRIPTOR => {
+					for _ in iter_tags(&buf) {
+						i = self.next(i);
+					}
+				}
+				BLOCKTYPE_COMMIT => {
+					for blk in pending_revoked.iter() {
+						revoked.insert(*blk, end_sequence)?;
+					}
+					pending_revoked.clear();
+					end_sequence = end_sequence.wrapping_add(1);
+				}
+				BLOCKTYPE_REVOKE => {
+					let count = min(get_u32(&buf, HEADER_SIZE) as usize, buf.len());
+					let mut off = HEADER_SIZE + 4;
+					while off + 4 <= count {
+						pending_revoked.push(get_u32(&buf, off))?;
+						off += 4;
+					}
+				}
+				_ => break,
+			}
+			i = self.next(i);
+		}
+
+		// Copy the blocks of committed transactions to their location
+		let mut data = crate::vec![0; self.blk_size as _]?;
+		let mut sequence = first_sequence;
+		let mut i = start;
+		while sequence != end_sequence {
+			self.read_block(io, i, &mut buf)?;
+			match get_u32(&buf, 4) {
+				BLOCKTYPE_DESCRIPTOR => {
+					for (blk, flags) in iter_tags(&buf) {
+						i = self.next(i);
+						// Blocks revoked by this transaction or a later one must not be replayed
+						let revoked = revoked
+							.get(&blk)
+							.is_some_and(|seq| seq.wrapping_sub(sequence) as i32 >= 0);
+						if revoked {
+							continue;
+						}
+						self.read_block(io, i, &mut data)?;
+						if flags & TAG_FLAG_ESCAPE != 0 {
+							set_u32(&mut data, 0, JBD_MAGIC);
+						}
+						io.write(blk as u64 * self.blk_size as u64, &data)?;
+					}
+				}
+				BLOCKTYPE_COMMIT => sequence = sequence.wrapping_add(1),
+				_ => {}
+			}
+			i = self.next(i);
+		}
+
+		self.write_superblock(io, 0, end_sequence)?;
+		Ok(end_sequence != first_sequence)
+	}
+
+	/// Writes the blocks `blocks` into the log as a single transaction, followed by a commit
+	/// block.
+	///
+	/// Once this function returns, the transaction is guaranteed to be applied, either by the
+	/// checkpoint or by a replay.
+	fn write_log(&mut self, io: &mut dyn IO, blocks: &[(u32, Vec<u8>)]) -> Result<(), Errno> {
+		let sequence = get_u32(&self.superblock, SB_SEQUENCE);
+		let first = self.first();
+		let mut desc = crate::vec![0; self.blk_size as _]?;
+		let mut data = crate::vec![0; self.blk_size as _]?;
+
+		let mut i = first;
+		for chunk in blocks.chunks(self.tags_per_descriptor()) {
+			// Write the descriptor block
+			desc.fill(0);
+			set_header(&mut desc, BLOCKTYPE_DESCRIPTOR, sequence);
+			let mut off = HEADER_SIZE;
+			for (j, (blk, content)) in chunk.iter().enumerate() {
+				let mut flags = 0;
+				if get_u32(content, 0) == JBD_MAGIC {
+					flags |= TAG_FLAG_ESCAPE;
+				}
+				if j > 0 {
+					flags |= TAG_FLAG_SAME_UUID;
+				}
+				if j + 1 == chunk.len() {
+					flags |= TAG_FLAG_LAST_TAG;
+				}
+				set_u32(&mut desc, off, *blk);
+				set_u16(&mut desc, off + 6, flags);
+				off += TAG_SIZE;
+				if j == 0 {
+					desc[off..(off + UUID_SIZE)]
+						.copy_from_slice(&self.superblock[SB_UUID..(SB_UUID + UUID_SIZE)]);
+					off += UUID_SIZE;
+				}
+			}
+			self.write_block(io, i, &desc)?;
+			i = self.next(i);
+
+			// Write the blocks' content
+			for (_, content) in chunk {
+				data.copy_from_slice(content);
+				if get_u32(&data, 0) == JBD_MAGIC {
+					set_u32(&mut data, 0, 0);
+				}
+				self.write_block(io, i, &data)?;
+				i = self.next(i);
+			}
+		}
+
+		// The journal must be marked as non-empty before the transaction is committed
+		self.write_superblock(io, first, sequence)?;
+
+		desc.fill(0);
+		set_header(&mut desc, BLOCKTYPE_COMMIT, sequence);
+		self.write_block(io, i, &desc)
+	}
+
+	/// Commits the blocks `blocks` as a single transaction, then writes them to their location on
+	/// the device.
+	fn commit(&mut self, io: &mut dyn IO, blocks: &[(u32, Vec<u8>)]) -> Result<(), Errno> {
+		if blocks.is_empty() {
+			return Ok(());
+		}
+		self.write_log(io, blocks)?;
+
+		// Checkpoint
+		for (blk, content) in blocks {
+			io.write(*blk as u64 * self.blk_size as u64, content)?;
+		}
+		let sequence = get_u32(&self.superblock, SB_SEQUENCE);
+		self.write_superblock(io, 0, sequence.wrapping_add(1))
+	}
+}
+
+/// A transaction buffers the writes to the device until it is committed to the journal.
+///
+/// Reads return the content of the device, including the changes made in the transaction.
+///
+/// If the transaction grows too large to fit in the journal, the changes made so far are committed
+/// and a new transaction is started.
+pub struct Transaction<'a> {
+	/// The I/O interface of the device.
+	io: &'a mut dyn IO,
+	/// The journal.
+	journal: &'a mut Journal,
+
+	/// The modified blocks, with their location on the device.
+	blocks: Vec<(u32, Vec<u8>)>,
+	/// The index in `blocks` of each modified block, by location.
+	indexes: HashMap<u32, usize>,
+}
+
+impl<'a> Transaction<'a> {
+	/// Begins a transaction on the device `io` with the journal `journal`.
+	pub fn new(io: &'a mut dyn IO, journal: &'a mut Journal) -> Self {
+		Self {
+			io,
+			journal,
+
+			blocks: Vec::new(),
+			indexes: HashMap::new(),
+		}
+	}
+
+	/// Returns the I/O interface of the device, bypassing the transaction.
+	pub fn get_io(&mut self) -> &mut dyn IO {
+		self.io
+	}
+
+	/// Commits the transaction and writes the modified blocks to the device.
+	pub fn commit(&mut self) -> Result<(), Errno> {
+		// Write blocks in order to reduce seeking
+		self.blocks.sort_unstable_by_key(|(blk, _)| *blk);
+		self.journal.commit(self.io, &self.blocks)?;
+		self.blocks.clear();
+		self.indexes.clear();
+		Ok(())
+	}
+
+	/// Returns the buffered content of the block `blk`, reading it from the device if not
+	/// modified yet.
+	///
+	/// If `fill` is `true`, the block is about to be overwritten entirely, so its content is not
+	/// read.
+	fn get_block(&mut self, blk: u32, fill: bool) -> Result<&mut [u8], Errno> {
+		let i = match self.indexes.get(&blk) {
+			Some(i) => *i,
+			None => {
+				if self.blocks.len() >= self.journal.max_transaction_blocks() {
+					self.commit()?;
+				}
+				let blk_size = self.journal.blk_size;
+				let mut content = crate::vec![0; blk_size as _]?;
+				if !fill {
+					self.io.read(blk as u64 * blk_size as u64, &mut content)?;
+				}
+				self.blocks.push((blk, content))?;
+				let i = self.blocks.len() - 1;
+				self.indexes.insert(blk, i)?;
+				i
+			}
+		};
+		Ok(&mut self.blocks[i].1)
+	}
+}
+
+impl<'a> IO for Transaction<'a> {
+	fn get_size(&self) -> u64 {
+		self.io.get_size()
+	}
+
+	fn read(&mut self, offset: u64, buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+		let res = self.io.read(offset, buff)?;
+		if self.blocks.is_empty() {
+			return Ok(res);
+		}
+
+		// Apply the changes of the transaction
+		let blk_size = self.journal.blk_size as u64;
+		let end = offset + buff.len() as u64;
+		let mut off = offset;
+		while off < end {
+			let blk = off / blk_size;
+			let inner_off = (off % blk_size) as usize;
+			let len = min(blk_size - inner_off as u64, end - off) as usize;
+			if let Some(i) = self.indexes.get(&(blk as u32)) {
+				let content = &self.blocks[*i].1;
+				let buff_off = (off - offset) as usize;
+				buff[buff_off..(buff_off + len)]
+					.copy_from_slice(&content[inner_off..(inner_off + len)]);
+			}
+			off += len as u64;
+		}
+		Ok(res)
+	}
+
+	fn write(&mut self, offset: u64, buff: &[u8]) -> Result<u64, Errno> {
+		let blk_size = self.journal.blk_size as u64;
+		let end = offset + buff.len() as u64;
+		let mut off = offset;
+		while off < end {
+			let blk = off / blk_size;
+			let inner_off = (off % blk_size) as usize;
+			let len = min(blk_size - inner_off as u64, end - off) as usize;
+			let content = self.get_block(blk as _, len as u64 == blk_size)?;
+			let buff_off = (off - offset) as usize;
+			content[inner_off..(inner_off + len)]
+				.copy_from_slice(&buff[buff_off..(buff_off + len)]);
+			off += len as u64;
+		}
+		Ok(buff.len() as _)
+	}
+
+	fn poll(&mut self, mask: u32) -> Result<u32, Errno> {
+		self.io.poll(mask)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::util::TryClone;
+
+	/// A device stored in memory.
+	struct MemIO(Vec<u8>);
+
+	impl IO for MemIO {
+		fn get_size(&self) -> u64 {
+			self.0.len() as _
+		}
+
+		fn read(&mut self, offset: u64, buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+			let off = offset as usize;
+			buff.copy_from_slice(&self.0[off..(off + buff.len())]);
+			Ok((buff.len() as _, false))
+		}
+
+		fn write(&mut self, offset: u64, buff: &[u8]) -> Result<u64, Errno> {
+			let off = offset as usize;
+			self.0[off..(off + buff.len())].copy_from_slice(buff);
+			Ok(buff.len() as _)
+		}
+
+		fn poll(&mut self, _mask: u32) -> Result<u32, Errno> {
+			Ok(0)
+		}
+	}
+
+	/// Creates a device of 32 blocks of 1024 bytes, with a journal in blocks 16 to 31.
+	fn journal() -> (MemIO, Journal) {
+		let mut superblock = crate::vec![0; 1024].unwrap();
+		set_header(&mut superblock, BLOCKTYPE_SUPERBLOCK_V2, 0);
+		set_u32(&mut superblock, SB_BLOCKSIZE, 1024);
+		set_u32(&mut superblock, SB_MAXLEN, 16);
+		set_u32(&mut superblock, SB_FIRST, 1);
+		set_u32(&mut superblock, SB_SEQUENCE, 1);
+		let mut blocks = Vec::new();
+		for i in 16..32 {
+			blocks.push(i).unwrap();
+		}
+		let journal = Journal {
+			blk_size: 1024,
+			blocks,
+			superblock,
+		};
+		(MemIO(crate::vec![0; 32 * 1024].unwrap()), journal)
+	}
+
+	#[test_case]
+	fn journal_replay() {
+		let (mut io, mut journal) = journal();
+		let mut content = crate::vec![0xaa; 1024].unwrap();
+		set_u32(&mut content, 0, JBD_MAGIC);
+		let mut blocks = Vec::new();
+		blocks.push((3, content.try_clone().unwrap())).unwrap();
+		blocks.push((5, crate::vec![0x55; 1024].unwrap())).unwrap();
+
+		// Interrupted before the checkpoint
+		journal.write_log(&mut io, &blocks).unwrap();
+		assert!(io.0[3072..4096].iter().all(|b| *b == 0));
+
+		assert!(journal.recover(&mut io).unwrap());
+		assert_eq!(&io.0[3072..4096], content.as_slice());
+		assert!(io.0[5120..6144].iter().all(|b| *b == 0x55));
+		assert!(!journal.recover(&mut io).unwrap());
+	}
+
+	#[test_case]
+	fn journal_transaction() {
+		let (mut io, mut journal) = journal();
+		{
+			let mut tx = Transaction::new(&mut io, &mut journal);
+			tx.write(2050, b"abc").unwrap();
+			let mut buf = [0; 4];
+			tx.read(2049, &mut buf).unwrap();
+			assert_eq!(&buf, b"\0abc");
+			assert!(tx.get_io().read(2050, &mut buf[..3]).is_ok());
+			assert_eq!(&buf[..3], b"\0\0\0");
+			tx.commit().unwrap();
+		}
+		assert_eq!(&io.0[2050..2053], b"abc");
+		assert_eq!(get_u32(&journal.superblock, SB_START), 0);
+		assert_eq!(get_u32(&journal.superblock, SB_SEQUENCE), 2);
+	}
+}
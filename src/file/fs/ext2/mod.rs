@@ -25,16 +25,24 @@
 
 mod block_group_descriptor;
 mod directory_entry;
+mod extent;
 mod inode;
+mod journal;
+mod xattr;
 
 use crate::errno;
+use crate::errno::EResult;
 use crate::errno::Errno;
+use crate::errno::ResultContext;
+use crate::file::fs::options::MountOptions;
 use crate::file::fs::Filesystem;
 use crate::file::fs::FilesystemType;
 use crate::file::fs::Statfs;
 use crate::file::path::Path;
 use crate::file::perm::Gid;
 use crate::file::perm::Uid;
+use crate::file::quota;
+use crate::file::quota::Quota;
 use crate::file::DirEntry;
 use crate::file::File;
 use crate::file::FileContent;
@@ -45,6 +53,7 @@ use crate::file::Mode;
 use crate::memory::malloc;
 use crate::time::clock;
 use crate::time::clock::CLOCK_MONOTONIC;
+use crate::time::unit::Timestamp;
 use crate::time::unit::TimestampScale;
 use crate::util::container::hashmap::HashMap;
 use crate::util::container::string::String;
@@ -57,6 +66,7 @@ use crate::util::TryClone;
 use block_group_descriptor::BlockGroupDescriptor;
 use core::cmp::max;
 use core::cmp::min;
+use core::ffi::c_int;
 use core::intrinsics::unlikely;
 use core::mem::size_of;
 use core::mem::size_of_val;
@@ -64,6 +74,8 @@ use core::mem::MaybeUninit;
 use core::num::NonZeroUsize;
 use core::slice;
 use inode::Ext2INode;
+use journal::Journal;
+use journal::Transaction;
 
 // TODO Take into account user's UID/GID when allocating block/inode to handle
 // reserved blocks/inodes
@@ -125,6 +137,18 @@ const REQUIRED_FEATURE_DIRECTORY_TYPE: u32 = 0x2;
 const REQUIRED_FEATURE_JOURNAL_REPLAY: u32 = 0x4;
 /// Required feature: Filesystem uses a journal device
 const REQUIRED_FEATURE_JOURNAL_DEVIXE: u32 = 0x8;
+/// Required feature: Files may use extent trees
+const REQUIRED_FEATURE_EXTENTS: u32 = 0x40;
+/// Required feature: Block group descriptors may be larger, to store 64-bit block addresses
+const REQUIRED_FEATURE_64_BITS: u32 = 0x80;
+/// Required feature: The metadata of block groups may be stored in other groups
+const REQUIRED_FEATURE_FLEX_BG: u32 = 0x200;
+/// The required features implemented by the driver.
+const SUPPORTED_REQUIRED_FEATURES: u32 = REQUIRED_FEATURE_DIRECTORY_TYPE
+	| REQUIRED_FEATURE_JOURNAL_REPLAY
+	| REQUIRED_FEATURE_EXTENTS
+	| REQUIRED_FEATURE_64_BITS
+	| REQUIRED_FEATURE_FLEX_BG;
 
 /// Write-required feature: Sparse superblocks and group descriptor tables
 const WRITE_REQUIRED_SPARSE_SUPERBLOCKS: u32 = 0x1;
@@ -132,6 +156,20 @@ const WRITE_REQUIRED_SPARSE_SUPERBLOCKS: u32 = 0x1;
 const WRITE_REQUIRED_64_BITS: u32 = 0x2;
 /// Directory contents are stored in the form of a Binary Tree.
 const WRITE_REQUIRED_DIRECTORY_BINARY_TREE: u32 = 0x4;
+/// Write-required feature: The size of large files is counted in blocks instead of sectors
+const WRITE_REQUIRED_HUGE_FILE: u32 = 0x8;
+/// Write-required feature: Directories may have more than 65000 subdirectories
+const WRITE_REQUIRED_DIR_NLINK: u32 = 0x20;
+/// Write-required feature: Inodes have extra fields
+const WRITE_REQUIRED_EXTRA_ISIZE: u32 = 0x40;
+/// The write-required features implemented by the driver.
+///
+/// Notably, checksums of group descriptors and metadata are not implemented.
+const SUPPORTED_WRITE_REQUIRED_FEATURES: u32 = WRITE_REQUIRED_SPARSE_SUPERBLOCKS
+	| WRITE_REQUIRED_64_BITS
+	| WRITE_REQUIRED_HUGE_FILE
+	| WRITE_REQUIRED_DIR_NLINK
+	| WRITE_REQUIRED_EXTRA_ISIZE;
 
 /// The maximum length of a name in the filesystem.
 const MAX_NAME_LEN: usize = 255;
@@ -150,7 +188,7 @@ unsafe fn read<T>(offset: u64, io: &mut dyn IO) -> Result<T, Errno> {
 
 	let ptr = obj.as_mut_ptr() as *mut u8;
 	let buffer = slice::from_raw_parts_mut(ptr, size);
-	io.read(offset, buffer)?;
+	io.read(offset, buffer).context("ext2: metadata read")?;
 
 	Ok(obj.assume_init())
 }
@@ -165,7 +203,7 @@ fn write<T>(obj: &T, offset: u64, io: &mut dyn IO) -> Result<(), Errno> {
 	let size = size_of_val(obj);
 	let ptr = obj as *const T as *const u8;
 	let buffer = unsafe { slice::from_raw_parts(ptr, size) };
-	io.write(offset, buffer)?;
+	io.write(offset, buffer).context("ext2: metadata write")?;
 
 	Ok(())
 }
@@ -190,7 +228,8 @@ fn read_block<T>(
 	let blk_size = superblock.get_block_size() as u64;
 	let buffer =
 		unsafe { slice::from_raw_parts_mut(buff.as_mut_ptr() as *mut u8, size_of_val(buff)) };
-	io.read(off * blk_size, buffer)?;
+	io.read(off * blk_size, buffer)
+		.context("ext2: block read")?;
 
 	Ok(())
 }
@@ -214,7 +253,8 @@ fn write_block<T>(
 ) -> Result<(), Errno> {
 	let blk_size = superblock.get_block_size() as u64;
 	let buffer = unsafe { slice::from_raw_parts(buff.as_ptr() as *const u8, size_of_val(buff)) };
-	io.write(off * blk_size, buffer)?;
+	io.write(off * blk_size, buffer)
+		.context("ext2: block write")?;
 
 	Ok(())
 }
@@ -237,7 +277,8 @@ fn zero_blocks(
 	let blk_size = superblock.get_block_size() as u64;
 	let blk_buff = malloc::Alloc::<u8>::new_default(NonZeroUsize::new(blk_size as _).unwrap())?;
 	for i in off..(off + count) {
-		io.write(i * blk_size, blk_buff.as_slice())?;
+		io.write(i * blk_size, blk_buff.as_slice())
+			.context("ext2: block zeroing")?;
 	}
 
 	Ok(())
@@ -332,9 +373,17 @@ pub struct Superblock {
 	journal_device: u32,
 	/// The head of orphan inodes list.
 	orphan_inode_head: u32,
+	/// The seeds used by the hash algorithm of directory indexes.
+	hash_seed: [u32; 4],
+	/// The default hash algorithm of directory indexes.
+	default_hash_version: u8,
+	/// The type of the backup of the journal inode's block pointers.
+	journal_backup_type: u8,
+	/// The size of a block group descriptor, if the 64 bits feature is enabled.
+	group_descriptor_size: u16,
 
 	/// Structure padding.
-	_padding: [u8; 788],
+	_padding: [u8; 768],
 }
 
 impl Superblock {
@@ -358,6 +407,15 @@ impl Superblock {
 		(SUPERBLOCK_OFFSET / self.get_block_size() as u64) + 1
 	}
 
+	/// Returns the size of a block group descriptor in bytes.
+	pub fn get_group_descriptor_size(&self) -> u64 {
+		if self.required_features & REQUIRED_FEATURE_64_BITS != 0 {
+			max(self.group_descriptor_size, 32) as _
+		} else {
+			32
+		}
+	}
+
 	/// Returns the number of block groups.
 	fn get_block_groups_count(&self) -> u32 {
 		self.total_blocks / self.blocks_per_group
@@ -660,6 +718,13 @@ struct Ext2Fs {
 
 	/// Tells whether the filesystem is mounted in read-only.
 	readonly: bool,
+	/// The action to perform when corruption is detected.
+	error_action: u16,
+
+	/// The filesystem's journal, if any.
+	journal: Option<Journal>,
+	/// The disk quotas.
+	quota: Quota,
 }
 
 impl Ext2Fs {
@@ -672,11 +737,14 @@ impl Ext2Fs {
 	/// - `io` is the I/O interface.
 	/// - `mountpath` is the path on which the filesystem is mounted.
 	/// - `readonly` tells whether the filesystem is mounted in read-only.
+	/// - `error_action` is the action to perform when corruption is detected. If `None`, the
+	/// action specified by the superblock is used.
 	fn new(
 		mut superblock: Superblock,
 		io: &mut dyn IO,
 		mountpath: Path,
 		readonly: bool,
+		error_action: Option<u16>,
 	) -> Result<Self, Errno> {
 		if !superblock.is_valid() {
 			return Err(errno!(EINVAL));
@@ -685,18 +753,14 @@ impl Ext2Fs {
 		// Checking the filesystem doesn't require features that are not implemented by
 		// the driver
 		if superblock.major_version >= 1 {
-			// TODO Implement journal
-			let unsupported_required_features = REQUIRED_FEATURE_COMPRESSION
-				| REQUIRED_FEATURE_JOURNAL_REPLAY
-				| REQUIRED_FEATURE_JOURNAL_DEVIXE;
-
-			if superblock.required_features & unsupported_required_features != 0 {
+			// TODO Implement compression and external journal devices
+			if superblock.required_features & !SUPPORTED_REQUIRED_FEATURES != 0 {
 				// TODO Log?
 				return Err(errno!(EINVAL));
 			}
 
-			// TODO Implement
-			let unsupported_write_features = WRITE_REQUIRED_DIRECTORY_BINARY_TREE;
+			// TODO Implement binary tree directories and checksums
+			let unsupported_write_features = !SUPPORTED_WRITE_REQUIRED_FEATURES;
 
 			if !readonly && superblock.write_required_features & unsupported_write_features != 0 {
 				// TODO Log?
@@ -704,6 +768,27 @@ impl Ext2Fs {
 			}
 		}
 
+		// Replay the journal if the filesystem has not been unmounted properly
+		let mut journal = Journal::load(&superblock, io)?;
+		match &mut journal {
+			Some(journal) => {
+				if journal.recover(io)? {
+					// The superblock may have been modified by the replay
+					superblock = Superblock::read(io)?;
+				}
+			}
+			None if superblock.required_features & REQUIRED_FEATURE_JOURNAL_REPLAY != 0 => {
+				return Err(errno!(EINVAL));
+			}
+			None => {}
+		}
+		// While mounted in read-write, the journal may contain transactions to replay
+		if journal.is_some() && !readonly {
+			superblock.required_features |= REQUIRED_FEATURE_JOURNAL_REPLAY;
+		} else {
+			superblock.required_features &= !REQUIRED_FEATURE_JOURNAL_REPLAY;
+		}
+
 		let timestamp = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Second)?;
 		if superblock.mount_count_since_fsck >= superblock.mount_count_before_fsck {
 			return Err(errno!(EINVAL));
@@ -736,168 +821,81 @@ impl Ext2Fs {
 
 		superblock.write(io)?;
 
+		let error_action = error_action.unwrap_or(superblock.error_action);
 		Ok(Self {
 			mountpath,
 
 			superblock,
 
 			readonly,
-		})
-	}
-}
-
-// TODO Update the write timestamp when the fs is written (take mount flags into
-// account)
-impl Filesystem for Ext2Fs {
-	fn get_name(&self) -> &[u8] {
-		b"ext2"
-	}
-
-	fn is_readonly(&self) -> bool {
-		self.readonly
-	}
-
-	fn must_cache(&self) -> bool {
-		true
-	}
-
-	fn get_stat(&self, _io: &mut dyn IO) -> Result<Statfs, Errno> {
-		let fragment_size = math::pow2(self.superblock.fragment_size_log + 10);
+			error_action,
 
-		Ok(Statfs {
-			f_type: EXT2_SIGNATURE as _,
-			f_bsize: self.superblock.get_block_size(),
-			f_blocks: self.superblock.total_blocks as _,
-			f_bfree: self.superblock.total_unallocated_blocks as _,
-			// TODO Subtract blocks for superuser
-			f_bavail: self.superblock.total_unallocated_blocks as _,
-			f_files: self.superblock.total_inodes as _,
-			f_ffree: self.superblock.total_unallocated_inodes as _,
-			f_fsid: Default::default(),
-			f_namelen: MAX_NAME_LEN as _,
-			f_frsize: fragment_size,
-			f_flags: 0, // TODO
+			journal,
+			quota: Quota::default(),
 		})
 	}
 
-	fn get_root_inode(&self, _io: &mut dyn IO) -> Result<INode, Errno> {
-		Ok(inode::ROOT_DIRECTORY_INODE as _)
-	}
-
-	fn get_inode(
-		&mut self,
-		io: &mut dyn IO,
-		parent: Option<INode>,
-		name: &[u8],
-	) -> Result<INode, Errno> {
-		let parent_inode = parent.unwrap_or(inode::ROOT_DIRECTORY_INODE as _);
-
-		// Getting the parent inode
-		let parent = Ext2INode::read(parent_inode as _, &self.superblock, io)?;
-		if parent.get_type() != FileType::Directory {
-			return Err(errno!(ENOTDIR));
+	/// Performs the action set for errors if `res` tells that corruption has been detected, then
+	/// returns `res`.
+	fn check_error<T>(&mut self, io: &mut dyn IO, res: Result<T, Errno>) -> Result<T, Errno> {
+		let Err(e) = &res else {
+			return res;
+		};
+		if e.as_int() != errno::EUCLEAN {
+			return res;
 		}
-
-		// Getting the entry with the given name
-		if let Some((_, entry)) = parent.get_dirent(name, &self.superblock, io)? {
-			Ok(entry.get_inode() as _)
-		} else {
-			Err(errno!(ENOENT))
+		// Record the error so that the filesystem gets checked
+		if self.superblock.fs_state != FS_STATE_ERROR && !self.readonly {
+			self.superblock.fs_state = FS_STATE_ERROR;
+			let _ = self.superblock.write(io);
+		}
+		match self.error_action {
+			ERR_ACTION_KERNEL_PANIC => panic!("ext2: filesystem corrupted"),
+			ERR_ACTION_READ_ONLY => self.readonly = true,
+			_ => {}
 		}
+		res
 	}
 
-	fn load_file(&mut self, io: &mut dyn IO, inode: INode, name: String) -> Result<File, Errno> {
-		let inode_ = Ext2INode::read(inode as _, &self.superblock, io)?;
-		let file_type = inode_.get_type();
-
-		let file_content = match file_type {
-			FileType::Regular => FileContent::Regular,
-
-			FileType::Directory => {
-				let mut entries = Vec::new();
-
-				for res in inode_.iter_dirent(&self.superblock, io)?.unwrap() {
-					let (_, entry) = res?;
-					if entry.is_free() {
-						continue;
-					}
-
-					entries.push((
-						entry.get_inode(),
-						entry.get_type(&self.superblock),
-						String::try_from(entry.get_name(&self.superblock))?,
-					))?;
-				}
-
-				// Creating entries with types
-				let mut final_entries = HashMap::new();
-
-				for (inode, entry_type, name) in entries {
-					let entry_type = match entry_type {
-						Some(entry_type) => entry_type,
-						None => Ext2INode::read(inode, &self.superblock, io)?.get_type(),
-					};
-
-					final_entries.insert(
-						name.try_clone()?,
-						DirEntry {
-							inode: inode as _,
-							entry_type,
-						},
-					)?;
-				}
-
-				FileContent::Directory(final_entries)
-			}
-
-			FileType::Link => FileContent::Link(inode_.get_link(&self.superblock, io)?),
-
-			FileType::Fifo => FileContent::Fifo,
-
-			FileType::Socket => FileContent::Socket,
-
-			FileType::BlockDevice => {
-				let (major, minor) = inode_.get_device();
-
-				FileContent::BlockDevice {
-					major: major as _,
-					minor: minor as _,
-				}
-			}
-
-			FileType::CharDevice => {
-				let (major, minor) = inode_.get_device();
-
-				FileContent::CharDevice {
-					major: major as _,
-					minor: minor as _,
+	/// Runs `f` in a transaction, so that its changes on the device are applied atomically.
+	///
+	/// If the filesystem has no journal, changes are written directly to the device.
+	///
+	/// If `f` fails, its changes are discarded.
+	fn transaction<T, F: FnOnce(&mut Self, &mut dyn IO) -> Result<T, Errno>>(
+		&mut self,
+		io: &mut dyn IO,
+		f: F,
+	) -> Result<T, Errno> {
+		let Some(mut journal) = self.journal.take() else {
+			let res = f(self, io);
+			return self.check_error(io, res);
+		};
+		let res = {
+			let mut tx = Transaction::new(io, &mut journal);
+			let res = f(self, &mut tx).and_then(|val| tx.commit().map(|_| val));
+			if res.is_err() {
+				// The superblock in memory may not match the device anymore
+				if let Ok(superblock) = Superblock::read(tx.get_io()) {
+					self.superblock = superblock;
 				}
 			}
+			res
 		};
+		self.journal = Some(journal);
+		self.check_error(io, res)
+	}
 
-		let file_location = FileLocation::Filesystem {
-			mountpoint_id: 0, // dummy value to be replaced
-			inode,
-		};
-		let mut file = File::new(
-			name,
-			inode_.uid,
-			inode_.gid,
-			inode_.get_permissions(),
-			file_location,
-			file_content,
-		)?;
-		file.set_hard_links_count(inode_.hard_links_count as _);
-		file.blocks_count = inode_.used_sectors as _;
-		file.set_size(inode_.get_size(&self.superblock));
-		file.ctime = inode_.ctime as _;
-		file.mtime = inode_.mtime as _;
-		file.atime = inode_.atime as _;
-
-		Ok(file)
+	/// Charges the usage of `space` bytes and `inodes` inodes to the owner of `inode`. See
+	/// [`Quota::charge`].
+	fn charge(&mut self, inode: &Ext2INode, space: i64, inodes: i64, force: bool) -> EResult<()> {
+		self.quota
+			.charge(inode.uid, inode.gid, space, inodes, force)
 	}
 
-	fn add_file(
+	/// Creates a file. See [`Filesystem::add_file`].
+	#[allow(clippy::too_many_arguments)]
+	fn add_file_impl(
 		&mut self,
 		io: &mut dyn IO,
 		parent_inode: INode,
@@ -907,10 +905,6 @@ impl Filesystem for Ext2Fs {
 		mode: Mode,
 		content: FileContent,
 	) -> Result<File, Errno> {
-		if unlikely(self.readonly) {
-			return Err(errno!(EROFS));
-		}
-
 		let mut parent = Ext2INode::read(parent_inode as _, &self.superblock, io)?;
 
 		// Checking the parent file is a directory
@@ -923,6 +917,7 @@ impl Filesystem for Ext2Fs {
 			return Err(errno!(EEXIST));
 		}
 
+		self.quota.charge(uid, gid, 0, 1, false)?;
 		let inode_index = self.superblock.get_free_inode(io)?;
 		let location = FileLocation::Filesystem {
 			mountpoint_id: 0, // dummy value to be replaced
@@ -1002,6 +997,10 @@ impl Filesystem for Ext2Fs {
 		}
 
 		inode.write(inode_index, &self.superblock, io)?;
+		if Ext2INode::init_extra(inode_index, &self.superblock, io, file.ctime as _)? {
+			file.btime = Some(file.ctime);
+		}
+		self.charge(&inode, inode.used_sectors as i64 * 512, 0, true)?;
 		let dir = file.get_type() == FileType::Directory;
 		self.superblock.mark_inode_used(io, inode_index, dir)?;
 		self.superblock.write(io)?;
@@ -1018,17 +1017,14 @@ impl Filesystem for Ext2Fs {
 		Ok(file)
 	}
 
-	fn add_link(
+	/// Adds a hard link to a file. See [`Filesystem::add_link`].
+	fn add_link_impl(
 		&mut self,
 		io: &mut dyn IO,
 		parent_inode: INode,
 		name: &[u8],
 		inode: INode,
 	) -> Result<(), Errno> {
-		if unlikely(self.readonly) {
-			return Err(errno!(EROFS));
-		}
-
 		// Parent inode
 		let mut parent = Ext2INode::read(parent_inode as _, &self.superblock, io)?;
 
@@ -1099,18 +1095,26 @@ impl Filesystem for Ext2Fs {
 		Ok(())
 	}
 
-	fn update_inode(&mut self, io: &mut dyn IO, file: &File) -> Result<(), Errno> {
-		if unlikely(self.readonly) {
-			return Err(errno!(EROFS));
-		}
-
+	/// Writes the attributes of `file` to its inode. See [`Filesystem::update_inode`].
+	fn update_inode_impl(&mut self, io: &mut dyn IO, file: &File) -> Result<(), Errno> {
 		// The inode number
 		let inode = file.get_location().get_inode();
 		// The inode
 		let mut inode_ = Ext2INode::read(inode as _, &self.superblock, io)?;
 
 		// Changing file size if it has been truncated
+		let used_sectors = inode_.used_sectors;
 		inode_.truncate(&mut self.superblock, io, file.get_size())?;
+		let freed = (used_sectors as i64 - inode_.used_sectors as i64) * 512;
+		self.charge(&inode_, -freed, 0, true)?;
+
+		// Transferring the usage to the new owner
+		if inode_.uid != file.get_uid() || inode_.gid != file.get_gid() {
+			let space = inode_.used_sectors as i64 * 512;
+			self.quota
+				.charge(file.get_uid(), file.get_gid(), space, 1, false)?;
+			self.charge(&inode_, -space, -1, true)?;
+		}
 
 		// Updating file attributes
 		inode_.uid = file.get_uid();
@@ -1122,15 +1126,21 @@ impl Filesystem for Ext2Fs {
 		inode_.write(inode as _, &self.superblock, io)
 	}
 
-	fn remove_file(
+	/// Sets the timestamps of a file. See [`Filesystem::touch`].
+	fn touch_impl(&mut self, io: &mut dyn IO, inode: INode, ts: Timestamp) -> EResult<()> {
+		let mut inode_ = Ext2INode::read(inode as _, &self.superblock, io)?;
+		inode_.ctime = ts as _;
+		inode_.mtime = ts as _;
+		inode_.write(inode as _, &self.superblock, io)
+	}
+
+	/// Removes a link to a file. See [`Filesystem::remove_file`].
+	fn remove_file_impl(
 		&mut self,
 		io: &mut dyn IO,
 		parent_inode: INode,
 		name: &[u8],
 	) -> Result<u16, Errno> {
-		if unlikely(self.readonly) {
-			return Err(errno!(EROFS));
-		}
 		if parent_inode < 1 {
 			return Err(errno!(EINVAL));
 		}
@@ -1153,7 +1163,26 @@ impl Filesystem for Ext2Fs {
 			.map(|(_, ent)| ent)
 			.ok_or_else(|| errno!(ENOENT))?
 			.get_inode();
-		// The inode
+
+		// Removing the directory entry
+		parent.remove_dirent(&mut self.superblock, io, name)?;
+		parent.write(parent_inode as _, &self.superblock, io)?;
+
+		self.unlink_inode(io, parent_inode, inode)
+	}
+
+	/// Removes a link to the inode `inode`, whose entry has been removed from the directory
+	/// `parent_inode`.
+	///
+	/// If this is the last link, the inode is freed.
+	///
+	/// The function returns the number of hard links left on the inode.
+	fn unlink_inode(
+		&mut self,
+		io: &mut dyn IO,
+		parent_inode: INode,
+		inode: u32,
+	) -> Result<u16, Errno> {
 		let mut inode_ = Ext2INode::read(inode, &self.superblock, io)?;
 
 		// If directory, removing `.` and `..` entries
@@ -1166,17 +1195,15 @@ impl Filesystem for Ext2Fs {
 			}
 
 			// Removing `..`
-			if parent.hard_links_count > 0
-				&& inode_.get_dirent(b"..", &self.superblock, io)?.is_some()
-			{
-				parent.hard_links_count -= 1;
+			if inode_.get_dirent(b"..", &self.superblock, io)?.is_some() {
+				let mut parent = Ext2INode::read(parent_inode as _, &self.superblock, io)?;
+				if parent.hard_links_count > 0 {
+					parent.hard_links_count -= 1;
+					parent.write(parent_inode as _, &self.superblock, io)?;
+				}
 			}
 		}
 
-		// Removing the directory entry
-		parent.remove_dirent(&mut self.superblock, io, name)?;
-		parent.write(parent_inode as _, &self.superblock, io)?;
-
 		// Decrementing the hard links count
 		if inode_.hard_links_count > 0 {
 			inode_.hard_links_count -= 1;
@@ -1187,7 +1214,9 @@ impl Filesystem for Ext2Fs {
 			let timestamp = clock::current_time(clock::CLOCK_MONOTONIC, TimestampScale::Second)?;
 			inode_.dtime = timestamp as _;
 
+			self.charge(&inode_, -(inode_.used_sectors as i64 * 512), -1, true)?;
 			inode_.free_content(&mut self.superblock, io)?;
+			xattr::release(&mut inode_, &mut self.superblock, io)?;
 
 			// Freeing inode
 			self.superblock
@@ -1201,44 +1230,707 @@ impl Filesystem for Ext2Fs {
 		Ok(inode_.hard_links_count)
 	}
 
-	fn read_node(
+	/// Sets the inode and the type of the existing entry `name` of the directory `dir`.
+	///
+	/// If the entry doesn't exist, the function returns [`errno::ENOENT`].
+	fn set_dirent_inode(
 		&mut self,
 		io: &mut dyn IO,
-		inode: INode,
-		off: u64,
-		buf: &mut [u8],
-	) -> Result<u64, Errno> {
-		if inode < 1 {
-			return Err(errno!(EINVAL));
+		dir: INode,
+		name: &[u8],
+		inode: u32,
+		file_type: FileType,
+	) -> Result<(), Errno> {
+		let mut dir_ = Ext2INode::read(dir as _, &self.superblock, io)?;
+		let (off, mut entry) = dir_
+			.get_dirent(name, &self.superblock, io)?
+			.ok_or_else(|| errno!(ENOENT))?;
+		entry.set_inode(inode);
+		entry.set_type(&self.superblock, file_type);
+		dir_.write_dirent(&mut self.superblock, io, &entry, off)?;
+		dir_.write(dir as _, &self.superblock, io)
+	}
+
+	/// Updates the directory `inode` after it has been moved from the directory `old_parent` to
+	/// `new_parent`: its `..` entry and the number of hard links of both parents.
+	///
+	/// If the inode is not a directory, the function does nothing.
+	fn move_dir(
+		&mut self,
+		io: &mut dyn IO,
+		inode: u32,
+		old_parent: INode,
+		new_parent: INode,
+	) -> Result<(), Errno> {
+		if old_parent == new_parent {
+			return Ok(());
+		}
+		let mut inode_ = Ext2INode::read(inode, &self.superblock, io)?;
+		if inode_.get_type() != FileType::Directory {
+			return Ok(());
 		}
 
-		let inode_ = Ext2INode::read(inode as _, &self.superblock, io)?;
-		inode_.read_content(off, buf, &self.superblock, io)
+		// Updating the `..` entry
+		if let Some((off, mut entry)) = inode_.get_dirent(b"..", &self.superblock, io)? {
+			entry.set_inode(new_parent as _);
+			inode_.write_dirent(&mut self.superblock, io, &entry, off)?;
+			inode_.write(inode, &self.superblock, io)?;
+		}
+
+		// Updating links count of parents
+		let mut parent = Ext2INode::read(old_parent as _, &self.superblock, io)?;
+		parent.hard_links_count = parent.hard_links_count.saturating_sub(1);
+		parent.write(old_parent as _, &self.superblock, io)?;
+		let mut parent = Ext2INode::read(new_parent as _, &self.superblock, io)?;
+		parent.hard_links_count = parent.hard_links_count.saturating_add(1);
+		parent.write(new_parent as _, &self.superblock, io)
 	}
 
-	fn write_node(
+	/// Moves a directory entry. See [`Filesystem::rename`].
+	fn rename_impl(
 		&mut self,
 		io: &mut dyn IO,
-		inode: INode,
-		off: u64,
-		buf: &[u8],
+		old_parent: INode,
+		old_name: &[u8],
+		new_parent: INode,
+		new_name: &[u8],
+		exchange: bool,
 	) -> Result<(), Errno> {
-		if unlikely(self.readonly) {
-			return Err(errno!(EROFS));
+		if old_parent < 1 || new_parent < 1 {
+			return Err(errno!(EINVAL));
 		}
-		if inode < 1 {
+		if [old_name, new_name]
+			.iter()
+			.any(|name| *name == b"." || *name == b"..")
+		{
 			return Err(errno!(EINVAL));
 		}
 
-		let mut inode_ = Ext2INode::read(inode as _, &self.superblock, io)?;
-		inode_.write_content(off, buf, &mut self.superblock, io)?;
-		inode_.write(inode as _, &self.superblock, io)?;
+		// The inode number of each side
+		let old_parent_ = Ext2INode::read(old_parent as _, &self.superblock, io)?;
+		let new_parent_ = Ext2INode::read(new_parent as _, &self.superblock, io)?;
+		if old_parent_.get_type() != FileType::Directory
+			|| new_parent_.get_type() != FileType::Directory
+		{
+			return Err(errno!(ENOTDIR));
+		}
+		let old_inode = old_parent_
+			.get_dirent(old_name, &self.superblock, io)?
+			.ok_or_else(|| errno!(ENOENT))?
+			.1
+			.get_inode();
+		let new_inode = new_parent_
+			.get_dirent(new_name, &self.superblock, io)?
+			.map(|(_, ent)| ent.get_inode());
+		let old_type = Ext2INode::read(old_inode, &self.superblock, io)?.get_type();
+
+		match new_inode {
+			Some(new_inode) if exchange => {
+				let new_type = Ext2INode::read(new_inode, &self.superblock, io)?.get_type();
+				self.set_dirent_inode(io, new_parent, new_name, old_inode, old_type)?;
+				self.set_dirent_inode(io, old_parent, old_name, new_inode, new_type)?;
+				self.move_dir(io, old_inode, old_parent, new_parent)?;
+				self.move_dir(io, new_inode, new_parent, old_parent)?;
+			}
 
-		self.superblock.write(io)
-	}
-}
+			None if exchange => return Err(errno!(ENOENT)),
 
-/// Structure representing the ext2 filesystem type.
+			Some(new_inode) => {
+				// Replacing the entry in place, then removing the link it held
+				self.set_dirent_inode(io, new_parent, new_name, old_inode, old_type)?;
+				let mut old_parent_ = Ext2INode::read(old_parent as _, &self.superblock, io)?;
+				old_parent_.remove_dirent(&mut self.superblock, io, old_name)?;
+				old_parent_.write(old_parent as _, &self.superblock, io)?;
+				self.move_dir(io, old_inode, old_parent, new_parent)?;
+				self.unlink_inode(io, new_parent, new_inode)?;
+			}
+
+			None => {
+				let mut new_parent_ = new_parent_;
+				new_parent_.add_dirent(&mut self.superblock, io, old_inode, new_name, old_type)?;
+				new_parent_.write(new_parent as _, &self.superblock, io)?;
+				// Reading again since both parents may be the same directory
+				let mut old_parent_ = Ext2INode::read(old_parent as _, &self.superblock, io)?;
+				old_parent_.remove_dirent(&mut self.superblock, io, old_name)?;
+				old_parent_.write(old_parent as _, &self.superblock, io)?;
+				self.move_dir(io, old_inode, old_parent, new_parent)?;
+			}
+		}
+
+		self.superblock.write(io)
+	}
+
+	/// Returns the space in bytes of the blocks of `inode` covering `len` bytes at offset `off`
+	/// that are not allocated yet.
+	fn missing_space(
+		&self,
+		io: &mut dyn IO,
+		inode: &Ext2INode,
+		off: u64,
+		len: u64,
+	) -> EResult<i64> {
+		let blk_size = self.superblock.get_block_size() as u64;
+		let mut space = 0;
+		if len > 0 {
+			let end = (off + len).div_ceil(blk_size);
+			for blk in (off / blk_size)..end {
+				if inode
+					.get_content_block_off(blk as _, &self.superblock, io)?
+					.is_none()
+				{
+					space += blk_size as i64;
+				}
+			}
+		}
+		Ok(space)
+	}
+
+	/// Writes the content of a file. See [`Filesystem::write_node`].
+	fn write_node_impl(
+		&mut self,
+		io: &mut dyn IO,
+		inode: INode,
+		off: u64,
+		buf: &[u8],
+	) -> Result<(), Errno> {
+		if inode < 1 {
+			return Err(errno!(EINVAL));
+		}
+
+		let mut inode_ = Ext2INode::read(inode as _, &self.superblock, io)?;
+
+		// Checking the quota allows allocating the missing blocks beforehand since the write
+		// cannot be reverted without a journal
+		let space = self.missing_space(io, &inode_, off, buf.len() as _)?;
+		self.charge(&inode_, space, 0, false)?;
+
+		let used_sectors = inode_.used_sectors;
+		let res = inode_.write_content(off, buf, &mut self.superblock, io);
+		// Accounting for the blocks actually allocated, including indirection blocks
+		let allocated = (inode_.used_sectors - used_sectors) as i64 * 512;
+		self.charge(&inode_, allocated - space, 0, true)?;
+		res?;
+		inode_.write(inode as _, &self.superblock, io)?;
+
+		self.superblock.write(io)
+	}
+
+	/// Preallocates blocks of a file. See [`Filesystem::allocate_range`].
+	fn allocate_range_impl(
+		&mut self,
+		io: &mut dyn IO,
+		inode: INode,
+		off: u64,
+		len: u64,
+	) -> EResult<()> {
+		if inode < 1 {
+			return Err(errno!(EINVAL));
+		}
+
+		let mut inode_ = Ext2INode::read(inode as _, &self.superblock, io)?;
+		if inode_.get_type() != FileType::Regular {
+			return Err(errno!(ENODEV));
+		}
+
+		let space = self.missing_space(io, &inode_, off, len)?;
+		self.charge(&inode_, space, 0, false)?;
+
+		let used_sectors = inode_.used_sectors;
+		let res = inode_.allocate_content(off, len, &mut self.superblock, io);
+		// Accounting for the blocks actually allocated, including indirection blocks
+		let allocated = (inode_.used_sectors - used_sectors) as i64 * 512;
+		self.charge(&inode_, allocated - space, 0, true)?;
+		res?;
+		inode_.write(inode as _, &self.superblock, io)?;
+
+		self.superblock.write(io)
+	}
+
+	/// Deallocates blocks of a file. See [`Filesystem::punch_hole`].
+	fn punch_hole_impl(
+		&mut self,
+		io: &mut dyn IO,
+		inode: INode,
+		off: u64,
+		len: u64,
+	) -> EResult<()> {
+		if inode < 1 {
+			return Err(errno!(EINVAL));
+		}
+
+		let mut inode_ = Ext2INode::read(inode as _, &self.superblock, io)?;
+		if inode_.get_type() != FileType::Regular {
+			return Err(errno!(ENODEV));
+		}
+
+		let used_sectors = inode_.used_sectors;
+		let res = inode_.punch_hole(off, len, &mut self.superblock, io);
+		let freed = (used_sectors as i64 - inode_.used_sectors as i64) * 512;
+		self.charge(&inode_, -freed, 0, true)?;
+		res?;
+		inode_.write(inode as _, &self.superblock, io)?;
+
+		self.superblock.write(io)
+	}
+
+	/// Changes the size of a file. See [`Filesystem::truncate_node`].
+	fn truncate_node_impl(&mut self, io: &mut dyn IO, inode: INode, size: u64) -> EResult<()> {
+		if inode < 1 {
+			return Err(errno!(EINVAL));
+		}
+
+		let mut inode_ = Ext2INode::read(inode as _, &self.superblock, io)?;
+		if inode_.get_type() != FileType::Regular {
+			return Err(errno!(EINVAL));
+		}
+
+		if size > inode_.get_size(&self.superblock) {
+			// The new space is left unallocated, which reads as zeros
+			inode_.set_size(&self.superblock, size);
+		} else {
+			let used_sectors = inode_.used_sectors;
+			let res = inode_.truncate(&mut self.superblock, io, size);
+			let freed = (used_sectors as i64 - inode_.used_sectors as i64) * 512;
+			self.charge(&inode_, -freed, 0, true)?;
+			res?;
+		}
+		inode_.write(inode as _, &self.superblock, io)?;
+
+		self.superblock.write(io)
+	}
+
+	/// Modifies the extended attributes of a file with `f`, then writes the inode.
+	fn update_xattr_impl<
+		F: FnOnce(&mut Ext2INode, &mut Superblock, &mut dyn IO) -> EResult<()>,
+	>(
+		&mut self,
+		io: &mut dyn IO,
+		inode: INode,
+		f: F,
+	) -> EResult<()> {
+		if inode < 1 {
+			return Err(errno!(EINVAL));
+		}
+
+		let mut inode_ = Ext2INode::read(inode as _, &self.superblock, io)?;
+		let used_sectors = inode_.used_sectors as i64;
+		f(&mut inode_, &mut self.superblock, io)?;
+		let delta = (inode_.used_sectors as i64 - used_sectors) * 512;
+		self.charge(&inode_, delta, 0, true)?;
+		inode_.write(inode as _, &self.superblock, io)?;
+
+		self.superblock.write(io)
+	}
+}
+
+// TODO Update the write timestamp when the fs is written (take mount flags into
+// account)
+impl Filesystem for Ext2Fs {
+	fn get_name(&self) -> &[u8] {
+		b"ext2"
+	}
+
+	fn is_readonly(&self) -> bool {
+		self.readonly
+	}
+
+	fn must_cache(&self) -> bool {
+		true
+	}
+
+	fn get_stat(&self, _io: &mut dyn IO) -> Result<Statfs, Errno> {
+		let fragment_size = math::pow2(self.superblock.fragment_size_log + 10);
+
+		Ok(Statfs {
+			f_type: EXT2_SIGNATURE as _,
+			f_bsize: self.superblock.get_block_size(),
+			f_blocks: self.superblock.total_blocks as _,
+			f_bfree: self.superblock.total_unallocated_blocks as _,
+			f_bavail: self
+				.superblock
+				.total_unallocated_blocks
+				.saturating_sub(self.superblock.superuser_blocks) as _,
+			f_files: self.superblock.total_inodes as _,
+			f_ffree: self.superblock.total_unallocated_inodes as _,
+			f_fsid: Default::default(),
+			f_namelen: MAX_NAME_LEN as _,
+			f_frsize: fragment_size,
+			f_flags: 0,
+			f_spare: [0; 4],
+		})
+	}
+
+	fn get_root_inode(&self, _io: &mut dyn IO) -> Result<INode, Errno> {
+		Ok(inode::ROOT_DIRECTORY_INODE as _)
+	}
+
+	fn get_inode(
+		&mut self,
+		io: &mut dyn IO,
+		parent: Option<INode>,
+		name: &[u8],
+	) -> Result<INode, Errno> {
+		let parent_inode = parent.unwrap_or(inode::ROOT_DIRECTORY_INODE as _);
+
+		// Getting the parent inode
+		let parent = Ext2INode::read(parent_inode as _, &self.superblock, io)?;
+		if parent.get_type() != FileType::Directory {
+			return Err(errno!(ENOTDIR));
+		}
+
+		// Getting the entry with the given name
+		if let Some((_, entry)) = parent.get_dirent(name, &self.superblock, io)? {
+			Ok(entry.get_inode() as _)
+		} else {
+			Err(errno!(ENOENT))
+		}
+	}
+
+	fn load_file(&mut self, io: &mut dyn IO, inode: INode, name: String) -> Result<File, Errno> {
+		let inode_ = Ext2INode::read(inode as _, &self.superblock, io)?;
+		let file_type = inode_.get_type();
+
+		let file_content = match file_type {
+			FileType::Regular => FileContent::Regular,
+
+			FileType::Directory => {
+				let mut entries = Vec::new();
+
+				for res in inode_.iter_dirent(&self.superblock, io)?.unwrap() {
+					let (_, entry) = res?;
+					if entry.is_free() {
+						continue;
+					}
+
+					entries.push((
+						entry.get_inode(),
+						entry.get_type(&self.superblock),
+						String::try_from(entry.get_name(&self.superblock))?,
+					))?;
+				}
+
+				// Creating entries with types
+				let mut final_entries = HashMap::new();
+
+				for (inode, entry_type, name) in entries {
+					let entry_type = match entry_type {
+						Some(entry_type) => entry_type,
+						None => Ext2INode::read(inode, &self.superblock, io)?.get_type(),
+					};
+
+					final_entries.insert(
+						name.try_clone()?,
+						DirEntry {
+							inode: inode as _,
+							entry_type,
+						},
+					)?;
+				}
+
+				FileContent::Directory(final_entries)
+			}
+
+			FileType::Link => FileContent::Link(inode_.get_link(&self.superblock, io)?),
+
+			FileType::Fifo => FileContent::Fifo,
+
+			FileType::Socket => FileContent::Socket,
+
+			FileType::BlockDevice => {
+				let (major, minor) = inode_.get_device();
+
+				FileContent::BlockDevice {
+					major: major as _,
+					minor: minor as _,
+				}
+			}
+
+			FileType::CharDevice => {
+				let (major, minor) = inode_.get_device();
+
+				FileContent::CharDevice {
+					major: major as _,
+					minor: minor as _,
+				}
+			}
+		};
+
+		let file_location = FileLocation::Filesystem {
+			mountpoint_id: 0, // dummy value to be replaced
+			inode,
+		};
+		let mut file = File::new(
+			name,
+			inode_.uid,
+			inode_.gid,
+			inode_.get_permissions(),
+			file_location,
+			file_content,
+		)?;
+		file.set_hard_links_count(inode_.hard_links_count as _);
+		file.blocks_count = inode_.used_sectors as _;
+		file.set_size(inode_.get_size(&self.superblock));
+		file.ctime = inode_.ctime as _;
+		file.mtime = inode_.mtime as _;
+		file.atime = inode_.atime as _;
+		file.btime = Ext2INode::read_crtime(inode as _, &self.superblock, io)?.map(|t| t as _);
+
+		Ok(file)
+	}
+
+	fn add_file(
+		&mut self,
+		io: &mut dyn IO,
+		parent_inode: INode,
+		name: String,
+		uid: Uid,
+		gid: Gid,
+		mode: Mode,
+		content: FileContent,
+	) -> Result<File, Errno> {
+		if unlikely(self.readonly) {
+			return Err(errno!(EROFS));
+		}
+		self.transaction(io, |fs, io| {
+			fs.add_file_impl(io, parent_inode, name, uid, gid, mode, content)
+		})
+	}
+
+	fn add_link(
+		&mut self,
+		io: &mut dyn IO,
+		parent_inode: INode,
+		name: &[u8],
+		inode: INode,
+	) -> Result<(), Errno> {
+		if unlikely(self.readonly) {
+			return Err(errno!(EROFS));
+		}
+		self.transaction(io, |fs, io| fs.add_link_impl(io, parent_inode, name, inode))
+	}
+
+	fn update_inode(&mut self, io: &mut dyn IO, file: &File) -> Result<(), Errno> {
+		if unlikely(self.readonly) {
+			return Err(errno!(EROFS));
+		}
+		self.transaction(io, |fs, io| fs.update_inode_impl(io, file))
+	}
+
+	fn touch(&mut self, io: &mut dyn IO, inode: INode, ts: Timestamp) -> EResult<()> {
+		if unlikely(self.readonly) {
+			return Err(errno!(EROFS));
+		}
+		self.transaction(io, |fs, io| fs.touch_impl(io, inode, ts))
+	}
+
+	fn remove_file(
+		&mut self,
+		io: &mut dyn IO,
+		parent_inode: INode,
+		name: &[u8],
+	) -> Result<u16, Errno> {
+		if unlikely(self.readonly) {
+			return Err(errno!(EROFS));
+		}
+		self.transaction(io, |fs, io| fs.remove_file_impl(io, parent_inode, name))
+	}
+
+	fn rename(
+		&mut self,
+		io: &mut dyn IO,
+		old_parent: INode,
+		old_name: &[u8],
+		new_parent: INode,
+		new_name: &[u8],
+		exchange: bool,
+	) -> EResult<()> {
+		if unlikely(self.readonly) {
+			return Err(errno!(EROFS));
+		}
+		self.transaction(io, |fs, io| {
+			fs.rename_impl(io, old_parent, old_name, new_parent, new_name, exchange)
+		})
+	}
+
+	fn read_node(
+		&mut self,
+		io: &mut dyn IO,
+		inode: INode,
+		off: u64,
+		buf: &mut [u8],
+	) -> Result<u64, Errno> {
+		if inode < 1 {
+			return Err(errno!(EINVAL));
+		}
+
+		let res = Ext2INode::read(inode as _, &self.superblock, io)
+			.and_then(|inode_| inode_.read_content(off, buf, &self.superblock, io))
+			.context("ext2: file content read");
+		self.check_error(io, res)
+	}
+
+	fn write_node(
+		&mut self,
+		io: &mut dyn IO,
+		inode: INode,
+		off: u64,
+		buf: &[u8],
+	) -> Result<(), Errno> {
+		if unlikely(self.readonly) {
+			return Err(errno!(EROFS));
+		}
+		self.transaction(io, |fs, io| fs.write_node_impl(io, inode, off, buf))
+	}
+
+	fn allocate_range(
+		&mut self,
+		io: &mut dyn IO,
+		inode: INode,
+		off: u64,
+		len: u64,
+	) -> EResult<()> {
+		if unlikely(self.readonly) {
+			return Err(errno!(EROFS));
+		}
+		self.transaction(io, |fs, io| fs.allocate_range_impl(io, inode, off, len))
+	}
+
+	fn punch_hole(&mut self, io: &mut dyn IO, inode: INode, off: u64, len: u64) -> EResult<()> {
+		if unlikely(self.readonly) {
+			return Err(errno!(EROFS));
+		}
+		self.transaction(io, |fs, io| fs.punch_hole_impl(io, inode, off, len))
+	}
+
+	fn truncate_node(&mut self, io: &mut dyn IO, inode: INode, size: u64) -> EResult<()> {
+		if unlikely(self.readonly) {
+			return Err(errno!(EROFS));
+		}
+		self.transaction(io, |fs, io| fs.truncate_node_impl(io, inode, size))
+	}
+
+	fn get_xattr(&mut self, io: &mut dyn IO, inode: INode, name: &[u8]) -> EResult<Vec<u8>> {
+		if inode < 1 {
+			return Err(errno!(EINVAL));
+		}
+
+		let res = Ext2INode::read(inode as _, &self.superblock, io)
+			.and_then(|inode_| xattr::get(&inode_, name, &self.superblock, io));
+		self.check_error(io, res)
+	}
+
+	fn set_xattr(
+		&mut self,
+		io: &mut dyn IO,
+		inode: INode,
+		name: &[u8],
+		value: &[u8],
+		flags: c_int,
+	) -> EResult<()> {
+		if unlikely(self.readonly) {
+			return Err(errno!(EROFS));
+		}
+		self.transaction(io, |fs, io| {
+			fs.update_xattr_impl(io, inode, |inode_, superblock, io| {
+				xattr::set(inode_, name, value, flags, superblock, io)
+			})
+		})
+	}
+
+	fn list_xattr(&mut self, io: &mut dyn IO, inode: INode) -> EResult<Vec<u8>> {
+		if inode < 1 {
+			return Err(errno!(EINVAL));
+		}
+
+		let res = Ext2INode::read(inode as _, &self.superblock, io)
+			.and_then(|inode_| xattr::list(&inode_, &self.superblock, io));
+		self.check_error(io, res)
+	}
+
+	fn remove_xattr(&mut self, io: &mut dyn IO, inode: INode, name: &[u8]) -> EResult<()> {
+		if unlikely(self.readonly) {
+			return Err(errno!(EROFS));
+		}
+		self.transaction(io, |fs, io| {
+			fs.update_xattr_impl(io, inode, |inode_, superblock, io| {
+				xattr::remove(inode_, name, superblock, io)
+			})
+		})
+	}
+
+	fn get_quota(&mut self) -> Option<&mut Quota> {
+		Some(&mut self.quota)
+	}
+
+	fn unmount(&mut self, io: &mut dyn IO) -> EResult<()> {
+		if self.readonly {
+			return Ok(());
+		}
+		// Every transaction is checkpointed on commit, so the journal is empty
+		self.superblock.required_features &= !REQUIRED_FEATURE_JOURNAL_REPLAY;
+		if self.superblock.fs_state != FS_STATE_ERROR {
+			self.superblock.fs_state = FS_STATE_CLEAN;
+		}
+		let timestamp = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Second)?;
+		self.superblock.last_write_timestamp = timestamp as _;
+		self.superblock.write(io)?;
+		self.readonly = true;
+		Ok(())
+	}
+
+	fn remount(&mut self, io: &mut dyn IO, readonly: bool) -> EResult<()> {
+		if readonly {
+			return self.unmount(io);
+		}
+		if !self.readonly {
+			return Ok(());
+		}
+		if self.superblock.major_version >= 1
+			&& self.superblock.write_required_features & !SUPPORTED_WRITE_REQUIRED_FEATURES != 0
+		{
+			return Err(errno!(EROFS));
+		}
+		// While mounted in read-write, the journal may contain transactions to replay
+		if self.journal.is_some() {
+			self.superblock.required_features |= REQUIRED_FEATURE_JOURNAL_REPLAY;
+		}
+		self.superblock.write(io)?;
+		self.readonly = false;
+		Ok(())
+	}
+
+	fn quota_on(&mut self, io: &mut dyn IO, kind: usize) -> EResult<()> {
+		// Computing the current usage of each user or group
+		let mut usage = HashMap::<u32, (u64, u64)>::new();
+		let first_inode = self.superblock.get_first_available_inode();
+		for i in 1..=self.superblock.total_inodes {
+			if i < first_inode && i != inode::ROOT_DIRECTORY_INODE {
+				continue;
+			}
+			let inode_ = Ext2INode::read(i, &self.superblock, io)?;
+			if inode_.hard_links_count == 0 || inode_.mode == 0 {
+				continue;
+			}
+			let id = match kind {
+				quota::USRQUOTA => inode_.uid,
+				_ => inode_.gid,
+			} as u32;
+			let space = inode_.used_sectors as u64 * 512;
+			match usage.get_mut(&id) {
+				Some((s, n)) => {
+					*s += space;
+					*n += 1;
+				}
+				None => {
+					usage.insert(id, (space, 1))?;
+				}
+			}
+		}
+		self.quota.enable(kind, usage)
+	}
+}
+
+/// Structure representing the ext2 filesystem type.
 pub struct Ext2FsType {}
 
 impl FilesystemType for Ext2FsType {
@@ -1255,9 +1947,18 @@ impl FilesystemType for Ext2FsType {
 		io: &mut dyn IO,
 		mountpath: Path,
 		readonly: bool,
+		options: &MountOptions,
 	) -> Result<Arc<Mutex<dyn Filesystem>>, Errno> {
+		options.check(&[b"errors"])?;
+		let error_action = match options.get(b"errors")? {
+			Some(b"continue") => Some(ERR_ACTION_IGNORE),
+			Some(b"remount-ro") => Some(ERR_ACTION_READ_ONLY),
+			Some(b"panic") => Some(ERR_ACTION_KERNEL_PANIC),
+			Some(_) => return Err(errno!(EINVAL)),
+			None => None,
+		};
 		let superblock = Superblock::read(io)?;
-		let fs = Ext2Fs::new(superblock, io, mountpath, readonly)?;
+		let fs = Ext2Fs::new(superblock, io, mountpath, readonly, error_action)?;
 
 		Ok(Arc::new(Mutex::new(fs))? as _)
 	}
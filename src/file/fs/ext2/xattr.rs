@@ -0,0 +1,333 @@
+//! Extended attributes of an inode are stored in a separate block, pointed to by the inode.
+//!
+//! The block begins with a header, followed by the list of entries, terminated by four zero
+//! bytes. The values of the entries are stored at the end of the block, growing towards its
+//! beginning.
+//!
+//! Inodes having the same attributes may share the same block, which is then copied before being
+//! modified.
+
+use super::inode::Ext2INode;
+use super::read_block;
+use super::write_block;
+use super::Superblock;
+use crate::errno;
+use crate::errno::EResult;
+use crate::file::xattr::Namespace;
+use crate::file::xattr::XATTR_CREATE;
+use crate::file::xattr::XATTR_REPLACE;
+use crate::util::container::vec::Vec;
+use crate::util::io::IO;
+use core::ffi::c_int;
+
+/// The signature of attribute blocks.
+const XATTR_MAGIC: u32 = 0xea020000;
+/// The size of the header of a block, in bytes.
+const HEADER_SIZE: usize = 32;
+/// The size of the header of an entry, without the name, in bytes.
+const ENTRY_HEADER_SIZE: usize = 16;
+
+/// Name index: `user.` namespace.
+const INDEX_USER: u8 = 1;
+/// Name index: POSIX access ACL.
+const INDEX_POSIX_ACL_ACCESS: u8 = 2;
+/// Name index: POSIX default ACL.
+const INDEX_POSIX_ACL_DEFAULT: u8 = 3;
+/// Name index: `trusted.` namespace.
+const INDEX_TRUSTED: u8 = 4;
+/// Name index: `security.` namespace.
+const INDEX_SECURITY: u8 = 6;
+
+/// Reads the little-endian 16 bits value at offset `off` in `buf`.
+fn get_u16(buf: &[u8], off: usize) -> u16 {
+	u16::from_le_bytes([buf[off], buf[off + 1]])
+}
+
+/// Reads the little-endian 32 bits value at offset `off` in `buf`.
+fn get_u32(buf: &[u8], off: usize) -> u32 {
+	u32::from_le_bytes([buf[off], buf[off + 1], buf[off + 2], buf[off + 3]])
+}
+
+/// Rounds `len` up to a multiple of four bytes.
+fn pad(len: usize) -> usize {
+	(len + 3) & !3
+}
+
+/// Splits the attribute name `name` into its name index and the name stored in the entry.
+fn split_name(name: &[u8]) -> EResult<(u8, &[u8])> {
+	let (ns, suffix) = Namespace::parse(name).ok_or_else(|| errno!(EOPNOTSUPP))?;
+	let index = match ns {
+		Namespace::User => INDEX_USER,
+		Namespace::Trusted => INDEX_TRUSTED,
+		Namespace::Security => INDEX_SECURITY,
+		Namespace::System => return Err(errno!(EOPNOTSUPP)),
+	};
+	Ok((index, suffix))
+}
+
+/// Returns the prefix to prepend to the name of an entry with the given name index.
+///
+/// If the index is unknown, the function returns `None`.
+fn index_prefix(index: u8) -> Option<&'static [u8]> {
+	match index {
+		INDEX_USER => Some(Namespace::User.prefix()),
+		INDEX_POSIX_ACL_ACCESS => Some(b"system.posix_acl_access"),
+		INDEX_POSIX_ACL_DEFAULT => Some(b"system.posix_acl_default"),
+		INDEX_TRUSTED => Some(Namespace::Trusted.prefix()),
+		INDEX_SECURITY => Some(Namespace::Security.prefix()),
+		_ => None,
+	}
+}
+
+/// An attribute entry.
+struct Entry {
+	/// The name index, giving the namespace of the attribute.
+	index: u8,
+	/// The name of the attribute, without its namespace.
+	name: Vec<u8>,
+	/// The value of the attribute.
+	value: Vec<u8>,
+}
+
+/// Reads the attribute block `blk` and checks its header.
+fn read_xattr_block(blk: u32, superblock: &Superblock, io: &mut dyn IO) -> EResult<Vec<u8>> {
+	let mut buf = crate::vec![0u8; superblock.get_block_size() as _]?;
+	read_block(blk as _, superblock, io, &mut buf)?;
+	if get_u32(&buf, 0) != XATTR_MAGIC || get_u32(&buf, 8) != 1 {
+		return Err(errno!(EUCLEAN));
+	}
+	Ok(buf)
+}
+
+/// Reads the attribute entries of the given inode.
+fn read_entries(
+	inode: &Ext2INode,
+	superblock: &Superblock,
+	io: &mut dyn IO,
+) -> EResult<Vec<Entry>> {
+	let mut entries = Vec::new();
+	let blk = inode.extended_attributes_block;
+	if blk == 0 {
+		return Ok(entries);
+	}
+	let buf = read_xattr_block(blk, superblock, io)?;
+	let mut off = HEADER_SIZE;
+	loop {
+		if off + 4 > buf.len() {
+			return Err(errno!(EUCLEAN));
+		}
+		if get_u32(&buf, off) == 0 {
+			break;
+		}
+		if off + ENTRY_HEADER_SIZE > buf.len() {
+			return Err(errno!(EUCLEAN));
+		}
+		let name_len = buf[off] as usize;
+		let index = buf[off + 1];
+		let value_off = get_u16(&buf, off + 2) as usize;
+		let value_block = get_u32(&buf, off + 4);
+		let value_size = get_u32(&buf, off + 8) as usize;
+		let name_begin = off + ENTRY_HEADER_SIZE;
+		if name_begin + name_len > buf.len()
+			|| value_block != 0
+			|| value_off + value_size > buf.len()
+		{
+			return Err(errno!(EUCLEAN));
+		}
+		entries.push(Entry {
+			index,
+			name: Vec::from_slice(&buf[name_begin..(name_begin + name_len)])?,
+			value: Vec::from_slice(&buf[value_off..(value_off + value_size)])?,
+		})?;
+		off += pad(ENTRY_HEADER_SIZE + name_len);
+	}
+	Ok(entries)
+}
+
+/// Builds an attribute block containing the given entries.
+///
+/// If the entries do not fit in a block, the function returns [`errno::ENOSPC`].
+fn build_block(entries: &[Entry], superblock: &Superblock) -> EResult<Vec<u8>> {
+	let mut buf = crate::vec![0u8; superblock.get_block_size() as _]?;
+	buf[0..4].copy_from_slice(&XATTR_MAGIC.to_le_bytes());
+	// Reference count
+	buf[4..8].copy_from_slice(&1u32.to_le_bytes());
+	// Number of blocks
+	buf[8..12].copy_from_slice(&1u32.to_le_bytes());
+
+	let mut off = HEADER_SIZE;
+	let mut values_begin = buf.len();
+	let mut block_hash = 0u32;
+	for e in entries {
+		let entry_size = pad(ENTRY_HEADER_SIZE + e.name.len());
+		let value_size = pad(e.value.len());
+		// Keep room for the entries list terminator
+		if off + entry_size + 4 + value_size > values_begin {
+			return Err(errno!(ENOSPC));
+		}
+		values_begin -= value_size;
+		buf[values_begin..(values_begin + e.value.len())].copy_from_slice(&e.value);
+
+		let hash = e
+			.name
+			.iter()
+			.fold(0u32, |hash, c| hash.rotate_left(5) ^ (*c as i8 as u32));
+		let hash = buf[values_begin..(values_begin + value_size)]
+			.chunks(4)
+			.fold(hash, |hash, w| hash.rotate_left(16) ^ get_u32(w, 0));
+		block_hash = block_hash.rotate_left(16) ^ hash;
+
+		buf[off] = e.name.len() as _;
+		buf[off + 1] = e.index;
+		buf[(off + 2)..(off + 4)].copy_from_slice(&(values_begin as u16).to_le_bytes());
+		buf[(off + 8)..(off + 12)].copy_from_slice(&(e.value.len() as u32).to_le_bytes());
+		buf[(off + 12)..(off + 16)].copy_from_slice(&hash.to_le_bytes());
+		buf[(off + ENTRY_HEADER_SIZE)..(off + ENTRY_HEADER_SIZE + e.name.len())]
+			.copy_from_slice(&e.name);
+		off += entry_size;
+	}
+	buf[12..16].copy_from_slice(&block_hash.to_le_bytes());
+	Ok(buf)
+}
+
+/// Writes the given attribute entries for the inode.
+///
+/// If the block of the inode is shared, a new block is allocated for it. If `entries` is empty,
+/// the block is released.
+///
+/// The caller is responsible for writing the inode and the superblock.
+fn write_entries(
+	inode: &mut Ext2INode,
+	entries: &mut [Entry],
+	superblock: &mut Superblock,
+	io: &mut dyn IO,
+) -> EResult<()> {
+	if entries.is_empty() {
+		return release(inode, superblock, io);
+	}
+	entries.sort_unstable_by(|a, b| {
+		(a.index, a.name.len(), a.name.as_slice()).cmp(&(b.index, b.name.len(), b.name.as_slice()))
+	});
+	let buf = build_block(entries, superblock)?;
+
+	let old = inode.extended_attributes_block;
+	if old != 0 && get_u32(&read_xattr_block(old, superblock, io)?, 4) <= 1 {
+		write_block(old as _, superblock, io, &buf)?;
+		return Ok(());
+	}
+	// Copy on write
+	let blk = superblock.get_free_block(io)?;
+	superblock.mark_block_used(io, blk)?;
+	write_block(blk as _, superblock, io, &buf)?;
+	release(inode, superblock, io)?;
+	inode.extended_attributes_block = blk;
+	inode.increment_used_sectors(superblock.get_block_size());
+	Ok(())
+}
+
+/// Returns the value of the attribute `name` of the given inode.
+pub fn get(
+	inode: &Ext2INode,
+	name: &[u8],
+	superblock: &Superblock,
+	io: &mut dyn IO,
+) -> EResult<Vec<u8>> {
+	let (index, name) = split_name(name)?;
+	read_entries(inode, superblock, io)?
+		.into_iter()
+		.find(|e| e.index == index && e.name.as_slice() == name)
+		.map(|e| e.value)
+		.ok_or_else(|| errno!(ENODATA))
+}
+
+/// Returns the names of the attributes of the given inode, each followed by a null byte.
+pub fn list(inode: &Ext2INode, superblock: &Superblock, io: &mut dyn IO) -> EResult<Vec<u8>> {
+	let mut list = Vec::new();
+	for e in read_entries(inode, superblock, io)?.iter() {
+		let Some(prefix) = index_prefix(e.index) else {
+			continue;
+		};
+		list.extend_from_slice(prefix)?;
+		list.extend_from_slice(&e.name)?;
+		list.push(0)?;
+	}
+	Ok(list)
+}
+
+/// Sets the value of the attribute `name` of the given inode.
+///
+/// `flags` are `XATTR_*` flags restricting whether the attribute may be created or replaced.
+///
+/// The caller is responsible for writing the inode and the superblock.
+pub fn set(
+	inode: &mut Ext2INode,
+	name: &[u8],
+	value: &[u8],
+	flags: c_int,
+	superblock: &mut Superblock,
+	io: &mut dyn IO,
+) -> EResult<()> {
+	let (index, name) = split_name(name)?;
+	let mut entries = read_entries(inode, superblock, io)?;
+	let value = Vec::from_slice(value)?;
+	match entries
+		.iter_mut()
+		.find(|e| e.index == index && e.name.as_slice() == name)
+	{
+		Some(_) if flags & XATTR_CREATE != 0 => return Err(errno!(EEXIST)),
+		Some(e) => e.value = value,
+		None if flags & XATTR_REPLACE != 0 => return Err(errno!(ENODATA)),
+		None => entries.push(Entry {
+			index,
+			name: Vec::from_slice(name)?,
+			value,
+		})?,
+	}
+	write_entries(inode, &mut entries, superblock, io)
+}
+
+/// Removes the attribute `name` of the given inode.
+///
+/// The caller is responsible for writing the inode and the superblock.
+pub fn remove(
+	inode: &mut Ext2INode,
+	name: &[u8],
+	superblock: &mut Superblock,
+	io: &mut dyn IO,
+) -> EResult<()> {
+	let (index, name) = split_name(name)?;
+	let mut entries = read_entries(inode, superblock, io)?;
+	let i = entries
+		.iter()
+		.position(|e| e.index == index && e.name.as_slice() == name)
+		.ok_or_else(|| errno!(ENODATA))?;
+	entries.remove(i);
+	write_entries(inode, &mut entries, superblock, io)
+}
+
+/// Releases the attribute block of the given inode, if any.
+///
+/// The block is freed if the inode was its last user.
+///
+/// The caller is responsible for writing the inode and the superblock.
+pub fn release(
+	inode: &mut Ext2INode,
+	superblock: &mut Superblock,
+	io: &mut dyn IO,
+) -> EResult<()> {
+	let blk = inode.extended_attributes_block;
+	if blk == 0 {
+		return Ok(());
+	}
+	let mut buf = read_xattr_block(blk, superblock, io)?;
+	let refcount = get_u32(&buf, 4);
+	if refcount > 1 {
+		buf[4..8].copy_from_slice(&(refcount - 1).to_le_bytes());
+		write_block(blk as _, superblock, io, &buf)?;
+	} else {
+		superblock.free_block(io, blk)?;
+	}
+	inode.extended_attributes_block = 0;
+	inode.decrement_used_sectors(superblock.get_block_size());
+	Ok(())
+}
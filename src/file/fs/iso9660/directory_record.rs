@@ -0,0 +1,214 @@
+//! A directory record describes a file located in a directory. Records are stored one after the
+//! other in the directory's extent and never cross sector boundaries. The remaining space at the
+//! end of a sector is filled with zeros.
+
+use super::SECTOR_SIZE;
+use crate::errno;
+use crate::errno::AllocResult;
+use crate::errno::EResult;
+use crate::time::unit::Timestamp;
+use crate::util::container::string::String;
+use crate::util::container::vec::Vec;
+use crate::util::io::IO;
+
+/// The size of the fixed part of a directory record.
+const RECORD_HEADER_SIZE: usize = 33;
+
+/// Record flag: The file is a directory.
+const FLAG_DIRECTORY: u8 = 0x02;
+
+/// Converts the given date to a timestamp in seconds since the Unix epoch.
+///
+/// Arguments:
+/// - `year`, `month` (1 to 12), `day` (1 to 31), `hour`, `minute` and `second` are the date.
+/// - `gmt_offset` is the offset from GMT in 15 minutes intervals.
+pub fn to_timestamp(
+	year: i64,
+	month: i64,
+	day: i64,
+	hour: i64,
+	minute: i64,
+	second: i64,
+	gmt_offset: i8,
+) -> Timestamp {
+	// Days since the epoch, counting years from March so that leap days come last
+	let y = if month <= 2 { year - 1 } else { year };
+	let era = y.div_euclid(400);
+	let yoe = y - era * 400;
+	let mp = (month + 9) % 12;
+	let doy = (153 * mp + 2) / 5 + day - 1;
+	let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+	let days = era * 146097 + doe - 719468;
+
+	let ts = days * 86400 + hour * 3600 + minute * 60 + second - gmt_offset as i64 * 15 * 60;
+	ts.max(0) as _
+}
+
+/// Normalizes the ISO 9660 identifier `name` of a file: the version number and the trailing dot
+/// of names without extension are removed, and the name is converted to lowercase.
+pub fn normalize_name(name: &[u8]) -> AllocResult<String> {
+	let name = match name.iter().position(|c| *c == b';') {
+		Some(i) => &name[..i],
+		None => name,
+	};
+	let name = name.strip_suffix(b".").unwrap_or(name);
+	let mut s = String::try_from(name)?;
+	s.as_mut_bytes().make_ascii_lowercase();
+	Ok(s)
+}
+
+/// A directory record.
+#[derive(Debug)]
+pub struct DirectoryRecord {
+	/// The offset of the record on the device, in bytes.
+	pub offset: u64,
+
+	/// The block of the file's extent.
+	pub extent: u32,
+	/// The length of the file's data in bytes.
+	pub data_len: u32,
+	/// The recording date of the file.
+	pub date: [u8; 7],
+	/// The record's flags.
+	pub flags: u8,
+
+	/// The file's identifier.
+	pub name: Vec<u8>,
+	/// The System Use area, where extensions store their entries.
+	pub system_use: Vec<u8>,
+}
+
+impl DirectoryRecord {
+	/// Parses the record in `buf`, located at offset `offset` on the device.
+	///
+	/// If the record is invalid, the function returns `None`.
+	pub fn parse(buf: &[u8], offset: u64) -> AllocResult<Option<Self>> {
+		let len = buf.first().copied().unwrap_or(0) as usize;
+		if len < RECORD_HEADER_SIZE || len > buf.len() {
+			return Ok(None);
+		}
+		let buf = &buf[..len];
+		let name_len = buf[32] as usize;
+		let name_end = RECORD_HEADER_SIZE + name_len;
+		if name_end > len {
+			return Ok(None);
+		}
+		// The System Use area begins on an even offset
+		let system_use_begin = (name_end + 1) & !1;
+
+		Ok(Some(Self {
+			offset,
+
+			extent: u32::from_le_bytes(buf[2..6].try_into().unwrap()),
+			data_len: u32::from_le_bytes(buf[10..14].try_into().unwrap()),
+			date: buf[18..25].try_into().unwrap(),
+			flags: buf[25],
+
+			name: Vec::from_slice(&buf[RECORD_HEADER_SIZE..name_end])?,
+			system_use: Vec::from_slice(buf.get(system_use_begin..).unwrap_or(&[]))?,
+		}))
+	}
+
+	/// Reads the record located at offset `offset` on the device.
+	pub fn read(io: &mut dyn IO, offset: u64) -> EResult<Self> {
+		let mut len = [0u8];
+		io.read(offset, &mut len)?;
+		let mut buf = crate::vec![0u8; len[0] as usize]?;
+		io.read(offset, &mut buf)?;
+		Self::parse(&buf, offset)?.ok_or_else(|| errno!(EUCLEAN))
+	}
+
+	/// Tells whether the file is a directory.
+	pub fn is_directory(&self) -> bool {
+		self.flags & FLAG_DIRECTORY != 0
+	}
+
+	/// Tells whether the record is the directory's entry for itself.
+	pub fn is_current(&self) -> bool {
+		self.name.as_slice() == [0]
+	}
+
+	/// Tells whether the record is the directory's entry for its parent.
+	pub fn is_parent(&self) -> bool {
+		self.name.as_slice() == [1]
+	}
+
+	/// Returns the recording date as a timestamp.
+	pub fn get_timestamp(&self) -> Timestamp {
+		let d = &self.date;
+		to_timestamp(
+			1900 + d[0] as i64,
+			d[1] as _,
+			d[2] as _,
+			d[3] as _,
+			d[4] as _,
+			d[5] as _,
+			d[6] as i8,
+		)
+	}
+}
+
+/// Cursor over the records of a directory.
+///
+/// The cursor doesn't borrow the I/O interface, so that it can be used to read other structures
+/// between two records.
+pub struct Records {
+	/// The current offset on the device.
+	off: u64,
+	/// The offset of the end of the directory on the device.
+	end: u64,
+}
+
+impl Records {
+	/// Creates a cursor over the records of the directory described by `dir`.
+	///
+	/// `block_size` is the size of a logical block.
+	pub fn new(dir: &DirectoryRecord, block_size: u32) -> Self {
+		let off = dir.extent as u64 * block_size as u64;
+		Self {
+			off,
+			end: off + dir.data_len as u64,
+		}
+	}
+
+	/// Returns the next record, or `None` if the end of the directory is reached.
+	pub fn next(&mut self, io: &mut dyn IO) -> EResult<Option<DirectoryRecord>> {
+		while self.off < self.end {
+			let mut len = [0u8];
+			io.read(self.off, &mut len)?;
+			// The rest of the sector is padding
+			if len[0] == 0 {
+				self.off = (self.off / SECTOR_SIZE + 1) * SECTOR_SIZE;
+				continue;
+			}
+
+			let rec = DirectoryRecord::read(io, self.off)?;
+			self.off += len[0] as u64;
+			return Ok(Some(rec));
+		}
+		Ok(None)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test_case]
+	fn iso9660_normalize_name() {
+		assert_eq!(
+			normalize_name(b"README.TXT;1").unwrap().as_bytes(),
+			b"readme.txt"
+		);
+		assert_eq!(normalize_name(b"BOOT.;1").unwrap().as_bytes(), b"boot");
+		assert_eq!(normalize_name(b"ISOLINUX").unwrap().as_bytes(), b"isolinux");
+	}
+
+	#[test_case]
+	fn iso9660_timestamp() {
+		assert_eq!(to_timestamp(1970, 1, 1, 0, 0, 0, 0), 0);
+		assert_eq!(to_timestamp(2000, 3, 1, 12, 30, 15, 0), 951913815);
+		// UTC+1
+		assert_eq!(to_timestamp(2000, 3, 1, 13, 30, 15, 4), 951913815);
+	}
+}
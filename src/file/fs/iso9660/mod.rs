@@ -0,0 +1,436 @@
+//! ISO 9660 is the filesystem used on optical discs (CD, DVD), and on live images.
+//!
+//! The filesystem begins with a sequence of volume descriptors, starting at sector 16. The
+//! Primary Volume Descriptor holds the record of the root directory. Each directory's extent
+//! contains the records of the files it contains, starting with `.` and `..`.
+//!
+//! The filesystem is read-only. Rock Ridge extensions, if present, provide POSIX attributes,
+//! long names, symbolic links and device files.
+//!
+//! Since the filesystem has no inodes, the inode of a file is the offset of its directory record
+//! on the device. For directories, the `.` record is used so that the inode is the same whatever
+//! the path used to reach the directory.
+
+mod directory_record;
+mod rock_ridge;
+
+use crate::errno;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::fs::options::MountOptions;
+use crate::file::fs::Filesystem;
+use crate::file::fs::FilesystemType;
+use crate::file::fs::Statfs;
+use crate::file::path::Path;
+use crate::file::perm::Gid;
+use crate::file::perm::Uid;
+use crate::file::DirEntry;
+use crate::file::File;
+use crate::file::FileContent;
+use crate::file::FileLocation;
+use crate::file::FileType;
+use crate::file::INode;
+use crate::file::Mode;
+use crate::util::container::hashmap::HashMap;
+use crate::util::container::string::String;
+use crate::util::io::IO;
+use crate::util::lock::Mutex;
+use crate::util::math;
+use crate::util::ptr::arc::Arc;
+use core::cmp::min;
+use directory_record::DirectoryRecord;
+use directory_record::Records;
+use rock_ridge::RockRidge;
+
+/// The size of a sector in bytes.
+const SECTOR_SIZE: u64 = 2048;
+/// The sector of the first volume descriptor.
+const VOLUME_DESCRIPTORS_BEGIN: u64 = 16;
+/// The maximum number of volume descriptors to look at before giving up.
+const VOLUME_DESCRIPTORS_MAX: u64 = 32;
+
+/// The signature of volume descriptors.
+const SIGNATURE: &[u8] = b"CD001";
+/// The filesystem's magic number, as returned by `statfs`.
+const ISOFS_SUPER_MAGIC: u32 = 0x9660;
+
+/// Volume descriptor type: Primary Volume Descriptor.
+const VD_TYPE_PRIMARY: u8 = 1;
+/// Volume descriptor type: Terminator.
+const VD_TYPE_TERMINATOR: u8 = 255;
+
+/// The offset of the root directory record in the Primary Volume Descriptor.
+const ROOT_RECORD_OFFSET: usize = 156;
+
+/// The maximum length of a file name.
+const MAX_NAME_LEN: usize = 255;
+/// The permissions of files when Rock Ridge is not available.
+const DEFAULT_MODE: Mode = 0o555;
+
+/// Looks for the Primary Volume Descriptor, and returns it.
+///
+/// If not found, the function returns `None`.
+fn read_primary_descriptor(io: &mut dyn IO) -> EResult<Option<[u8; SECTOR_SIZE as usize]>> {
+	let end = min(
+		VOLUME_DESCRIPTORS_BEGIN + VOLUME_DESCRIPTORS_MAX,
+		io.get_size() / SECTOR_SIZE,
+	);
+	for sector in VOLUME_DESCRIPTORS_BEGIN..end {
+		let mut buf = [0u8; SECTOR_SIZE as usize];
+		io.read(sector * SECTOR_SIZE, &mut buf)?;
+		if &buf[1..6] != SIGNATURE {
+			break;
+		}
+		match buf[0] {
+			VD_TYPE_PRIMARY => return Ok(Some(buf)),
+			VD_TYPE_TERMINATOR => break,
+			_ => {}
+		}
+	}
+	Ok(None)
+}
+
+/// Structure representing an instance of the ISO 9660 filesystem.
+#[derive(Debug)]
+pub struct Iso9660Fs {
+	/// The size of a logical block in bytes.
+	block_size: u32,
+	/// The number of logical blocks in the volume.
+	blocks_count: u32,
+	/// The root directory's inode.
+	root: INode,
+
+	/// The number of bytes to skip at the beginning of System Use areas. If `None`, SUSP is not
+	/// used.
+	susp_skip: Option<u8>,
+}
+
+impl Iso9660Fs {
+	/// Creates a new instance from the Primary Volume Descriptor `pvd`.
+	fn new(io: &mut dyn IO, pvd: &[u8]) -> EResult<Self> {
+		let block_size = u16::from_le_bytes(pvd[128..130].try_into().unwrap()) as u32;
+		if !block_size.is_power_of_two() || block_size < 512 {
+			return Err(errno!(EINVAL));
+		}
+		let blocks_count = u32::from_le_bytes(pvd[80..84].try_into().unwrap());
+		let root_offset = VOLUME_DESCRIPTORS_BEGIN * SECTOR_SIZE + ROOT_RECORD_OFFSET as u64;
+		let root = DirectoryRecord::parse(&pvd[ROOT_RECORD_OFFSET..], root_offset)?
+			.ok_or_else(|| errno!(EINVAL))?;
+
+		let mut fs = Self {
+			block_size,
+			blocks_count,
+			root: 0,
+
+			susp_skip: None,
+		};
+		fs.root = fs.get_dir_inode(root.extent);
+		// SUSP is announced in the System Use area of the root directory's `.` record
+		let root_current = DirectoryRecord::read(io, fs.root)?;
+		fs.susp_skip = rock_ridge::detect(&root_current.system_use);
+
+		Ok(fs)
+	}
+
+	/// Returns the inode of the directory whose extent begins at block `extent`.
+	fn get_dir_inode(&self, extent: u32) -> INode {
+		extent as u64 * self.block_size as u64
+	}
+
+	/// Returns the Rock Ridge attributes of the given record.
+	fn get_rock_ridge(&self, io: &mut dyn IO, rec: &DirectoryRecord) -> EResult<RockRidge> {
+		match self.susp_skip {
+			Some(skip) => RockRidge::parse(io, &rec.system_use, skip, self.block_size),
+			None => Ok(RockRidge::default()),
+		}
+	}
+
+	/// Returns the name, inode and type of the file described by the record `rec`.
+	///
+	/// If the record must not be listed, the function returns `None`.
+	fn get_entry(
+		&self,
+		io: &mut dyn IO,
+		rec: &DirectoryRecord,
+	) -> EResult<Option<(String, INode, FileType)>> {
+		let rr = self.get_rock_ridge(io, rec)?;
+		if rr.relocated {
+			return Ok(None);
+		}
+
+		let inode = if rec.is_current() {
+			rec.offset
+		} else if let Some(block) = rr.child_link.or(rr.parent_link) {
+			self.get_dir_inode(block)
+		} else if rec.is_directory() {
+			self.get_dir_inode(rec.extent)
+		} else {
+			rec.offset
+		};
+
+		let entry_type = if rr.child_link.is_some() || rec.is_directory() {
+			FileType::Directory
+		} else {
+			rr.mode
+				.and_then(FileType::from_mode)
+				.unwrap_or(FileType::Regular)
+		};
+
+		let name = if rec.is_current() {
+			String::try_from(b".")?
+		} else if rec.is_parent() {
+			String::try_from(b"..")?
+		} else if let Some(name) = rr.name {
+			String::try_from(name.as_slice())?
+		} else {
+			directory_record::normalize_name(&rec.name)?
+		};
+
+		Ok(Some((name, inode, entry_type)))
+	}
+}
+
+impl Filesystem for Iso9660Fs {
+	fn get_name(&self) -> &[u8] {
+		b"iso9660"
+	}
+
+	fn is_readonly(&self) -> bool {
+		true
+	}
+
+	fn must_cache(&self) -> bool {
+		true
+	}
+
+	fn get_stat(&self, _io: &mut dyn IO) -> Result<Statfs, Errno> {
+		Ok(Statfs {
+			f_type: ISOFS_SUPER_MAGIC,
+			f_bsize: self.block_size,
+			f_blocks: self.blocks_count as _,
+			f_bfree: 0,
+			f_bavail: 0,
+			f_files: 0,
+			f_ffree: 0,
+			f_fsid: Default::default(),
+			f_namelen: MAX_NAME_LEN as _,
+			f_frsize: self.block_size,
+			f_flags: 0,
+			f_spare: [0; 4],
+		})
+	}
+
+	fn get_root_inode(&self, _io: &mut dyn IO) -> Result<INode, Errno> {
+		Ok(self.root)
+	}
+
+	fn get_inode(
+		&mut self,
+		io: &mut dyn IO,
+		parent: Option<INode>,
+		name: &[u8],
+	) -> Result<INode, Errno> {
+		let parent = DirectoryRecord::read(io, parent.unwrap_or(self.root))?;
+		if !parent.is_directory() {
+			return Err(errno!(ENOTDIR));
+		}
+
+		let mut records = Records::new(&parent, self.block_size);
+		while let Some(rec) = records.next(io)? {
+			// Without Rock Ridge, matching the ISO 9660 name first avoids building entries
+			if self.susp_skip.is_none()
+				&& !rec.is_current()
+				&& !rec.is_parent()
+				&& directory_record::normalize_name(&rec.name)?.as_bytes() != name
+			{
+				continue;
+			}
+			if let Some((entry_name, inode, _)) = self.get_entry(io, &rec)? {
+				if entry_name.as_bytes() == name {
+					return Ok(inode);
+				}
+			}
+		}
+
+		Err(errno!(ENOENT))
+	}
+
+	fn load_file(&mut self, io: &mut dyn IO, inode: INode, name: String) -> Result<File, Errno> {
+		let rec = DirectoryRecord::read(io, inode)?;
+		let rr = self.get_rock_ridge(io, &rec)?;
+
+		let file_type = if rec.is_directory() {
+			FileType::Directory
+		} else {
+			rr.mode
+				.and_then(FileType::from_mode)
+				.unwrap_or(FileType::Regular)
+		};
+		let file_content = match file_type {
+			FileType::Regular => FileContent::Regular,
+
+			FileType::Directory => {
+				let mut entries = HashMap::new();
+				let mut records = Records::new(&rec, self.block_size);
+				while let Some(rec) = records.next(io)? {
+					let Some((name, inode, entry_type)) = self.get_entry(io, &rec)? else {
+						continue;
+					};
+					entries.insert(
+						name,
+						DirEntry {
+							inode,
+							entry_type,
+						},
+					)?;
+				}
+				FileContent::Directory(entries)
+			}
+
+			FileType::Link => {
+				let target = rr.link.as_ref().map(|l| l.as_slice()).unwrap_or(&[]);
+				FileContent::Link(String::try_from(target)?)
+			}
+
+			FileType::Fifo => FileContent::Fifo,
+
+			FileType::Socket => FileContent::Socket,
+
+			FileType::BlockDevice => {
+				let (major, minor) = rr.dev.unwrap_or_default();
+				FileContent::BlockDevice {
+					major,
+					minor,
+				}
+			}
+
+			FileType::CharDevice => {
+				let (major, minor) = rr.dev.unwrap_or_default();
+				FileContent::CharDevice {
+					major,
+					minor,
+				}
+			}
+		};
+
+		let file_location = FileLocation::Filesystem {
+			mountpoint_id: 0, // dummy value to be replaced
+			inode,
+		};
+		let mode = rr.mode.map(|m| m & 0o7777).unwrap_or(DEFAULT_MODE);
+		let mut file = File::new(
+			name,
+			rr.uid.unwrap_or(0) as _,
+			rr.gid.unwrap_or(0) as _,
+			mode,
+			file_location,
+			file_content,
+		)?;
+		let size = match &rr.link {
+			Some(link) if file_type == FileType::Link => link.len() as u64,
+			_ => rec.data_len as u64,
+		};
+		file.set_hard_links_count(rr.nlink.unwrap_or(1) as _);
+		file.blocks_count = math::ceil_div(rec.data_len as u64, 512);
+		file.set_size(size);
+		let ts = rec.get_timestamp();
+		file.ctime = rr.ctime.unwrap_or(ts);
+		file.mtime = rr.mtime.unwrap_or(ts);
+		file.atime = rr.atime.unwrap_or(ts);
+
+		Ok(file)
+	}
+
+	fn add_file(
+		&mut self,
+		_io: &mut dyn IO,
+		_parent_inode: INode,
+		_name: String,
+		_uid: Uid,
+		_gid: Gid,
+		_mode: Mode,
+		_content: FileContent,
+	) -> Result<File, Errno> {
+		Err(errno!(EROFS))
+	}
+
+	fn add_link(
+		&mut self,
+		_io: &mut dyn IO,
+		_parent_inode: INode,
+		_name: &[u8],
+		_inode: INode,
+	) -> Result<(), Errno> {
+		Err(errno!(EROFS))
+	}
+
+	fn update_inode(&mut self, _io: &mut dyn IO, _file: &File) -> Result<(), Errno> {
+		Err(errno!(EROFS))
+	}
+
+	fn remove_file(
+		&mut self,
+		_io: &mut dyn IO,
+		_parent_inode: INode,
+		_name: &[u8],
+	) -> Result<u16, Errno> {
+		Err(errno!(EROFS))
+	}
+
+	fn read_node(
+		&mut self,
+		io: &mut dyn IO,
+		inode: INode,
+		off: u64,
+		buf: &mut [u8],
+	) -> Result<u64, Errno> {
+		let rec = DirectoryRecord::read(io, inode)?;
+		if rec.is_directory() {
+			return Err(errno!(EISDIR));
+		}
+		let len = rec.data_len as u64;
+		if off >= len {
+			return Ok(0);
+		}
+		let len = min(buf.len() as u64, len - off);
+		let begin = rec.extent as u64 * self.block_size as u64 + off;
+		io.read(begin, &mut buf[..(len as usize)])?;
+		Ok(len)
+	}
+
+	fn write_node(
+		&mut self,
+		_io: &mut dyn IO,
+		_inode: INode,
+		_off: u64,
+		_buf: &[u8],
+	) -> Result<(), Errno> {
+		Err(errno!(EROFS))
+	}
+}
+
+/// Structure representing the ISO 9660 filesystem type.
+pub struct Iso9660FsType {}
+
+impl FilesystemType for Iso9660FsType {
+	fn get_name(&self) -> &'static [u8] {
+		b"iso9660"
+	}
+
+	fn detect(&self, io: &mut dyn IO) -> Result<bool, Errno> {
+		Ok(read_primary_descriptor(io)?.is_some())
+	}
+
+	fn load_filesystem(
+		&self,
+		io: &mut dyn IO,
+		_mountpath: Path,
+		_readonly: bool,
+		options: &MountOptions,
+	) -> Result<Arc<Mutex<dyn Filesystem>>, Errno> {
+		options.check(&[])?;
+		let pvd = read_primary_descriptor(io)?.ok_or_else(|| errno!(EINVAL))?;
+		let fs = Iso9660Fs::new(io, &pvd)?;
+		Ok(Arc::new(Mutex::new(fs))? as _)
+	}
+}
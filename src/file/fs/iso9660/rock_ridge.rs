@@ -0,0 +1,321 @@
+//! The Rock Ridge extension stores POSIX file attributes in the System Use area of directory
+//! records, using the System Use Sharing Protocol (SUSP).
+//!
+//! Each SUSP entry begins with a two bytes signature, followed by its length and version.
+//! Entries that don't fit in the record are stored in a Continuation Area, pointed to by a `CE`
+//! entry.
+
+use super::directory_record::to_timestamp;
+use crate::device::id;
+use crate::errno::EResult;
+use crate::file::Mode;
+use crate::time::unit::Timestamp;
+use crate::util::container::vec::Vec;
+use crate::util::io::IO;
+
+/// The maximum number of Continuation Areas followed for a single record, to prevent loops on
+/// corrupted filesystems.
+const MAX_CONTINUATIONS: usize = 16;
+
+/// `NM` flag: The entry refers to the current directory.
+const NM_CURRENT: u8 = 0x02;
+/// `NM` flag: The entry refers to the parent directory.
+const NM_PARENT: u8 = 0x04;
+
+/// `SL` component flag: The component continues in the next component.
+const SL_CONTINUE: u8 = 0x01;
+/// `SL` component flag: The component refers to the current directory.
+const SL_CURRENT: u8 = 0x02;
+/// `SL` component flag: The component refers to the parent directory.
+const SL_PARENT: u8 = 0x04;
+/// `SL` component flag: The component refers to the root directory.
+const SL_ROOT: u8 = 0x08;
+
+/// `TF` flag: The creation time is recorded.
+const TF_CREATION: u8 = 0x01;
+/// `TF` flag: The modification time is recorded.
+const TF_MODIFY: u8 = 0x02;
+/// `TF` flag: The access time is recorded.
+const TF_ACCESS: u8 = 0x04;
+/// `TF` flag: The attributes change time is recorded.
+const TF_ATTRIBUTES: u8 = 0x08;
+/// `TF` flag: Times are recorded in the long form.
+const TF_LONG_FORM: u8 = 0x80;
+
+/// Returns the number of bytes to skip at the beginning of the System Use area of each record.
+///
+/// `system_use` is the System Use area of the root directory's `.` record.
+///
+/// If the filesystem doesn't use SUSP, the function returns `None`.
+pub fn detect(system_use: &[u8]) -> Option<u8> {
+	match system_use {
+		[b'S', b'P', 7, _, 0xbe, 0xef, skip, ..] => Some(*skip),
+		_ => None,
+	}
+}
+
+/// Returns the little-endian half of the both-endian 32 bits value at offset `off` in `buf`.
+fn read_u32(buf: &[u8], off: usize) -> Option<u32> {
+	let b = buf.get(off..(off + 4))?;
+	Some(u32::from_le_bytes(b.try_into().unwrap()))
+}
+
+/// Parses a timestamp of a `TF` entry.
+///
+/// If `long` is set, the timestamp is in the 17 bytes long form, else the 7 bytes short form.
+fn parse_time(buf: &[u8], long: bool) -> Timestamp {
+	if long {
+		let digits = |range: core::ops::Range<usize>| {
+			buf[range]
+				.iter()
+				.fold(0, |n, c| n * 10 + c.wrapping_sub(b'0') as i64)
+		};
+		to_timestamp(
+			digits(0..4),
+			digits(4..6),
+			digits(6..8),
+			digits(8..10),
+			digits(10..12),
+			digits(12..14),
+			buf[16] as i8,
+		)
+	} else {
+		to_timestamp(
+			1900 + buf[0] as i64,
+			buf[1] as _,
+			buf[2] as _,
+			buf[3] as _,
+			buf[4] as _,
+			buf[5] as _,
+			buf[6] as i8,
+		)
+	}
+}
+
+/// Attributes of a file, as stored by the Rock Ridge extension.
+#[derive(Debug, Default)]
+pub struct RockRidge {
+	/// The file's mode.
+	pub mode: Option<Mode>,
+	/// The number of hard links to the file.
+	pub nlink: Option<u32>,
+	/// The owner user's ID.
+	pub uid: Option<u32>,
+	/// The owner group's ID.
+	pub gid: Option<u32>,
+	/// The device's major and minor numbers.
+	pub dev: Option<(u32, u32)>,
+
+	/// The file's name.
+	pub name: Option<Vec<u8>>,
+	/// The target of the symbolic link.
+	pub link: Option<Vec<u8>>,
+
+	/// Timestamp of the last attributes change.
+	pub ctime: Option<Timestamp>,
+	/// Timestamp of the last modification.
+	pub mtime: Option<Timestamp>,
+	/// Timestamp of the last access.
+	pub atime: Option<Timestamp>,
+
+	/// For a relocated directory, the block of the directory's extent.
+	pub child_link: Option<u32>,
+	/// For the `..` record of a relocated directory, the block of the real parent's extent.
+	pub parent_link: Option<u32>,
+	/// Tells whether the record is a relocated directory, which must be hidden from listings.
+	pub relocated: bool,
+}
+
+impl RockRidge {
+	/// Parses the entries in the System Use area `system_use`.
+	///
+	/// Arguments:
+	/// - `io` is the I/O interface, used to read Continuation Areas.
+	/// - `skip` is the number of bytes to skip at the beginning of the area.
+	/// - `block_size` is the size of a logical block.
+	pub fn parse(io: &mut dyn IO, system_use: &[u8], skip: u8, block_size: u32) -> EResult<Self> {
+		let mut rr = Self::default();
+		let mut link_sep = false;
+
+		let mut continuation = rr.parse_area(
+			system_use.get(skip as usize..).unwrap_or(&[]),
+			&mut link_sep,
+		)?;
+		for _ in 0..MAX_CONTINUATIONS {
+			let Some((block, off, len)) = continuation else {
+				break;
+			};
+			let mut buf = crate::vec![0u8; len as usize]?;
+			io.read(block as u64 * block_size as u64 + off as u64, &mut buf)?;
+			continuation = rr.parse_area(&buf, &mut link_sep)?;
+		}
+
+		Ok(rr)
+	}
+
+	/// Parses the entries in the area `area`.
+	///
+	/// `link_sep` tells whether a separator is to be inserted before the next component of the
+	/// symbolic link's target.
+	///
+	/// If the area points to a Continuation Area, the function returns its block, offset and
+	/// length.
+	fn parse_area(
+		&mut self,
+		area: &[u8],
+		link_sep: &mut bool,
+	) -> EResult<Option<(u32, u32, u32)>> {
+		let mut continuation = None;
+		let mut off = 0;
+		while off + 4 <= area.len() {
+			let len = area[off + 2] as usize;
+			if len < 4 || off + len > area.len() {
+				break;
+			}
+			let entry = &area[off..(off + len)];
+			off += len;
+
+			match &entry[..2] {
+				b"PX" => {
+					self.mode = read_u32(entry, 4).map(|m| m as _);
+					self.nlink = read_u32(entry, 12);
+					self.uid = read_u32(entry, 20);
+					self.gid = read_u32(entry, 28);
+				}
+
+				b"PN" => {
+					if let (Some(high), Some(low)) = (read_u32(entry, 4), read_u32(entry, 12)) {
+						let dev = if high != 0 {
+							id::makedev(high, low)
+						} else {
+							low as u64
+						};
+						self.dev = Some((id::major(dev), id::minor(dev)));
+					}
+				}
+
+				b"NM" if len >= 5 => {
+					let flags = entry[4];
+					if flags & (NM_CURRENT | NM_PARENT) != 0 {
+						continue;
+					}
+					let name = match &mut self.name {
+						Some(name) => name,
+						None => self.name.insert(Vec::new()),
+					};
+					// A name split across several entries is concatenated
+					name.extend_from_slice(&entry[5..])?;
+				}
+
+				b"SL" if len >= 5 => {
+					let link = match &mut self.link {
+						Some(link) => link,
+						None => self.link.insert(Vec::new()),
+					};
+					let mut comps = &entry[5..];
+					while let [flags, comp_len, rest @ ..] = comps {
+						let comp_len = (*comp_len as usize).min(rest.len());
+						let content = &rest[..comp_len];
+						comps = &rest[comp_len..];
+
+						if *link_sep {
+							link.push(b'/')?;
+						}
+						if flags & SL_ROOT != 0 {
+							link.push(b'/')?;
+							*link_sep = false;
+							continue;
+						}
+						if flags & SL_CURRENT != 0 {
+							link.push(b'.')?;
+						} else if flags & SL_PARENT != 0 {
+							link.extend_from_slice(b"..")?;
+						} else {
+							link.extend_from_slice(content)?;
+						}
+						*link_sep = flags & SL_CONTINUE == 0;
+					}
+				}
+
+				b"TF" if len >= 5 => {
+					let flags = entry[4];
+					let long = flags & TF_LONG_FORM != 0;
+					let size = if long { 17 } else { 7 };
+					let mut times = &entry[5..];
+					for bit in 0..7 {
+						if flags & (1 << bit) == 0 {
+							continue;
+						}
+						if times.len() < size {
+							break;
+						}
+						let ts = parse_time(&times[..size], long);
+						times = &times[size..];
+						match 1 << bit {
+							TF_MODIFY => self.mtime = Some(ts),
+							TF_ACCESS => self.atime = Some(ts),
+							TF_ATTRIBUTES => self.ctime = Some(ts),
+							TF_CREATION if self.ctime.is_none() => self.ctime = Some(ts),
+							_ => {}
+						}
+					}
+				}
+
+				b"CL" => self.child_link = read_u32(entry, 4),
+				b"PL" => self.parent_link = read_u32(entry, 4),
+				b"RE" => self.relocated = true,
+
+				b"CE" => {
+					if let (Some(block), Some(off), Some(len)) =
+						(read_u32(entry, 4), read_u32(entry, 12), read_u32(entry, 20))
+					{
+						continuation = Some((block, off, len));
+					}
+				}
+
+				// Terminator
+				b"ST" => break,
+
+				_ => {}
+			}
+		}
+		Ok(continuation)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test_case]
+	fn rock_ridge_entries() {
+		let mut area = crate::vec![
+			b'N', b'M', 9, 1, 0, b'v', b'm', b'l', b'z', // name
+			b'S', b'L', 13, 1, 0, // symbolic link
+			8, 0, // root
+			0, 4, b'b', b'o', b'o', b't', // component
+			b'R', b'E', 4, 1, // relocated
+		]
+		.unwrap();
+		let mut px = crate::vec![0u8; 44].unwrap();
+		px[..4].copy_from_slice(b"PX\x2c\x01");
+		px[4..8].copy_from_slice(&0o100644u32.to_le_bytes());
+		px[12..16].copy_from_slice(&2u32.to_le_bytes());
+		area.extend_from_slice(&px).unwrap();
+
+		let mut rr = RockRidge::default();
+		let cont = rr.parse_area(&area, &mut false).unwrap();
+		assert!(cont.is_none());
+		assert_eq!(rr.name.unwrap().as_slice(), b"vmlz");
+		assert_eq!(rr.link.unwrap().as_slice(), b"/boot");
+		assert!(rr.relocated);
+		assert_eq!(rr.mode, Some(0o100644));
+		assert_eq!(rr.nlink, Some(2));
+	}
+
+	#[test_case]
+	fn rock_ridge_detect() {
+		assert_eq!(detect(b"SP\x07\x01\xbe\xef\x00"), Some(0));
+		assert_eq!(detect(b"PX\x07\x01\xbe\xef\x00"), None);
+	}
+}
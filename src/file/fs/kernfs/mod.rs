@@ -5,6 +5,7 @@ pub mod node;
 
 use crate::errno;
 use crate::errno::AllocError;
+use crate::errno::EResult;
 use crate::errno::Errno;
 use crate::file::fs::kernfs::node::DummyKernFSNode;
 use crate::file::fs::Filesystem;
@@ -20,10 +21,13 @@ use crate::file::INode;
 use crate::file::Mode;
 use crate::memory;
 use crate::process::oom;
+use crate::time::unit::Timestamp;
 use crate::util::boxed::Box;
+use crate::util::container::hashmap::HashMap;
 use crate::util::container::string::String;
 use crate::util::container::vec::Vec;
 use crate::util::io::IO;
+use crate::util::math;
 use crate::util::TryClone;
 use core::borrow::Borrow;
 use core::intrinsics::unlikely;
@@ -132,6 +136,11 @@ impl KernFS {
 			.ok_or_else(|| errno!(ENOENT))
 	}
 
+	/// Returns an iterator over the nodes of the filesystem.
+	pub fn iter_nodes(&self) -> impl Iterator<Item = &Box<dyn KernFSNode>> {
+		self.nodes.iter().filter_map(Option::as_ref)
+	}
+
 	/// Adds the given node `node` to the filesystem.
 	///
 	/// The function returns the allocated inode.
@@ -167,6 +176,81 @@ impl KernFS {
 		Ok(None)
 	}
 
+	/// Executes `f` with the entries of the directory `dir`.
+	///
+	/// If the node is not a directory, the function returns [`errno::ENOTDIR`].
+	fn with_entries<R, F: FnOnce(&mut HashMap<String, DirEntry>) -> R>(
+		&mut self,
+		dir: INode,
+		f: F,
+	) -> Result<R, Errno> {
+		let node = self.get_node_mut(dir)?;
+		let mut content = node.get_content()?;
+		let FileContent::Directory(entries) = &mut *content else {
+			return Err(errno!(ENOTDIR));
+		};
+		Ok(f(entries))
+	}
+
+	/// Removes a link to the node `inode`, which was an entry of the directory `parent_inode`.
+	///
+	/// If no link is left, the node is removed.
+	///
+	/// The function returns the number of hard links left on the node.
+	fn unlink_node(&mut self, parent_inode: INode, inode: INode) -> Result<u16, Errno> {
+		let node = self.get_node_mut(inode)?;
+		let is_dir = matches!(node.get_content()?.borrow(), FileContent::Directory(_));
+
+		// If the node is a directory, decrement the number of hard links in the parent
+		// (entry `..`)
+		if is_dir {
+			let parent = self.get_node_mut(parent_inode)?;
+			let links = parent.get_hard_links_count() - 1;
+			parent.set_hard_links_count(links);
+		}
+
+		// If no link is left, remove the node
+		let node = self.get_node_mut(inode)?;
+		let links = node.get_hard_links_count() - 1;
+		node.set_hard_links_count(links);
+		if node.get_hard_links_count() <= 0 {
+			oom::wrap(|| self.remove_node(inode).map_err(|_| AllocError));
+		}
+
+		Ok(links)
+	}
+
+	/// Updates the directory `inode` after it has been moved from the directory `old_parent` to
+	/// `new_parent`: its `..` entry and the number of hard links of both parents.
+	///
+	/// If the node is not a directory, the function does nothing.
+	fn move_dir(
+		&mut self,
+		inode: INode,
+		old_parent: INode,
+		new_parent: INode,
+	) -> Result<(), Errno> {
+		if old_parent == new_parent {
+			return Ok(());
+		}
+		let moved = self.with_entries(inode, |entries| {
+			if let Some(e) = entries.get_mut(b"..".as_slice()) {
+				e.inode = new_parent;
+			}
+		});
+		if moved.is_err() {
+			return Ok(());
+		}
+
+		let parent = self.get_node_mut(old_parent)?;
+		let links = parent.get_hard_links_count() - 1;
+		parent.set_hard_links_count(links);
+		let parent = self.get_node_mut(new_parent)?;
+		let links = parent.get_hard_links_count() + 1;
+		parent.set_hard_links_count(links);
+		Ok(())
+	}
+
 	// TODO Clean
 	/// Adds a file to the kernfs.
 	///
@@ -284,8 +368,9 @@ impl Filesystem for KernFS {
 			f_ffree: 0,
 			f_fsid: Default::default(),
 			f_namelen: MAX_NAME_LEN as _,
-			f_frsize: 0,
+			f_frsize: memory::PAGE_SIZE as _,
 			f_flags: 0,
+			f_spare: [0; 4],
 		})
 	}
 
@@ -331,9 +416,12 @@ impl Filesystem for KernFS {
 		)?;
 		file.set_hard_links_count(node.get_hard_links_count());
 		file.set_size(node.get_size());
+		// Nodes are stored in memory, the number of blocks is deduced from the size
+		file.blocks_count = math::ceil_div(node.get_size(), 512);
 		file.ctime = node.get_ctime();
 		file.mtime = node.get_mtime();
 		file.atime = node.get_atime();
+		file.btime = node.get_btime();
 
 		Ok(file)
 	}
@@ -411,6 +499,16 @@ impl Filesystem for KernFS {
 		Ok(())
 	}
 
+	fn touch(&mut self, _: &mut dyn IO, inode: INode, ts: Timestamp) -> EResult<()> {
+		if unlikely(self.readonly) {
+			return Err(errno!(EROFS));
+		}
+		let node = self.get_node_mut(inode)?;
+		node.set_ctime(ts);
+		node.set_mtime(ts);
+		Ok(())
+	}
+
 	fn remove_file(
 		&mut self,
 		_: &mut dyn IO,
@@ -440,33 +538,64 @@ impl Filesystem for KernFS {
 				return Err(errno!(ENOTEMPTY));
 			}
 		}
-		let is_dir = matches!(node.get_content()?.borrow(), FileContent::Directory(_));
 
 		// Removing directory entry
-		let parent = self.get_node_mut(parent_inode).unwrap();
-		let mut content = parent.get_content()?;
-		let FileContent::Directory(entries) = &mut *content else {
-			unreachable!();
-		};
-		entries.remove(name);
+		self.with_entries(parent_inode, |entries| entries.remove(name))?;
 
-		// If the node is a directory, decrement the number of hard links in the parent
-		// (entry `..`)
-		if is_dir {
-			let parent = self.get_node_mut(parent_inode).unwrap();
-			let links = parent.get_hard_links_count() - 1;
-			parent.set_hard_links_count(links);
+		self.unlink_node(parent_inode, inode)
+	}
+
+	fn rename(
+		&mut self,
+		_: &mut dyn IO,
+		old_parent: INode,
+		old_name: &[u8],
+		new_parent: INode,
+		new_name: &[u8],
+		exchange: bool,
+	) -> EResult<()> {
+		if unlikely(self.readonly) {
+			return Err(errno!(EROFS));
 		}
 
-		// If no link is left, remove the node
-		let node = self.get_node_mut(inode)?;
-		let links = node.get_hard_links_count() - 1;
-		node.set_hard_links_count(links);
-		if node.get_hard_links_count() <= 0 {
-			oom::wrap(|| self.remove_node(inode).map_err(|_| AllocError));
+		let old_entry = self
+			.with_entries(old_parent, |entries| entries.get(old_name).cloned())?
+			.ok_or_else(|| errno!(ENOENT))?;
+		let new_entry = self.with_entries(new_parent, |entries| entries.get(new_name).cloned())?;
+
+		match new_entry {
+			Some(new_entry) if exchange => {
+				// Overwriting existing entries cannot fail
+				self.with_entries(new_parent, |entries| {
+					if let Some(e) = entries.get_mut(new_name) {
+						*e = old_entry.clone();
+					}
+				})?;
+				self.with_entries(old_parent, |entries| {
+					if let Some(e) = entries.get_mut(old_name) {
+						*e = new_entry.clone();
+					}
+				})?;
+				self.move_dir(old_entry.inode, old_parent, new_parent)?;
+				self.move_dir(new_entry.inode, new_parent, old_parent)?;
+			}
+
+			None if exchange => return Err(errno!(ENOENT)),
+
+			new_entry => {
+				// Inserting the new entry first since it is the only operation that may fail
+				self.with_entries(new_parent, |entries| {
+					entries.insert(String::try_from(new_name)?, old_entry.clone())
+				})??;
+				self.with_entries(old_parent, |entries| entries.remove(old_name))?;
+				self.move_dir(old_entry.inode, old_parent, new_parent)?;
+				if let Some(new_entry) = new_entry {
+					self.unlink_node(new_parent, new_entry.inode)?;
+				}
+			}
 		}
 
-		Ok(links)
+		Ok(())
 	}
 
 	fn read_node(
@@ -495,4 +624,10 @@ impl Filesystem for KernFS {
 		node.write(off, buf)?;
 		Ok(())
 	}
+
+	fn remount(&mut self, _: &mut dyn IO, readonly: bool) -> Result<(), Errno> {
+		// Files are kept in memory, so there is nothing to flush
+		self.readonly = readonly;
+		Ok(())
+	}
 }
@@ -73,6 +73,11 @@ pub trait KernFSNode: Any + IO {
 	/// Sets the timestamp of the last modification of the file's content.
 	fn set_mtime(&mut self, _ts: Timestamp) {}
 
+	/// Returns the timestamp of the creation of the file, if known.
+	fn get_btime(&self) -> Option<Timestamp> {
+		None
+	}
+
 	/// Returns an immutable reference to the node's content.
 	fn get_content(&mut self) -> EResult<KernFSContent<'_>>;
 }
@@ -99,6 +104,8 @@ pub struct DummyKernFSNode {
 	mtime: Timestamp,
 	/// Timestamp of the last access to the file.
 	atime: Timestamp,
+	/// Timestamp of the creation of the file.
+	btime: Timestamp,
 
 	/// The node's content.
 	content: FileContent,
@@ -126,6 +133,7 @@ impl DummyKernFSNode {
 			ctime: ts,
 			mtime: ts,
 			atime: ts,
+			btime: ts,
 
 			content,
 		}
@@ -189,6 +197,10 @@ impl KernFSNode for DummyKernFSNode {
 		self.mtime = ts;
 	}
 
+	fn get_btime(&self) -> Option<Timestamp> {
+		Some(self.btime)
+	}
+
 	fn get_content(&mut self) -> EResult<KernFSContent<'_>> {
 		Ok(KernFSContent::Owned(&mut self.content))
 	}
@@ -1,40 +1,69 @@
 //! A filesystem is the representation of the file hierarchy on a storage
 //! device.
 
+pub mod devtmpfs;
 pub mod ext2;
 pub mod initramfs;
+pub mod iso9660;
 pub mod kernfs;
+pub mod options;
+pub mod overlay;
 pub mod procfs;
+pub mod pstore;
+pub mod sysfs;
 pub mod tmp;
 
 use super::path::Path;
 use super::File;
 use crate::errno;
+use crate::errno::EResult;
 use crate::errno::Errno;
+use crate::file::mountpoint;
+use crate::file::perm::AccessProfile;
 use crate::file::perm::Gid;
 use crate::file::perm::Uid;
+use crate::file::quota::Quota;
 use crate::file::FileContent;
 use crate::file::INode;
 use crate::file::Mode;
+use crate::limits;
+use crate::memory;
+use crate::time::unit::Timestamp;
 use crate::util::container::hashmap::HashMap;
 use crate::util::container::string::String;
+use crate::util::container::vec::Vec;
 use crate::util::io::IO;
 use crate::util::lock::Mutex;
 use crate::util::ptr::arc::Arc;
 use core::any::Any;
+use core::ffi::c_int;
+use options::MountOptions;
 
-/// This structure is used in the f_fsid field of statfs. It is currently
-/// unused.
+/// Flag of [`Statfs`]: The `f_flags` field is filled.
+const ST_VALID: u32 = 0x20;
+/// The mount flags reported in the `f_flags` field of [`Statfs`], which have the same values as
+/// the `ST_*` flags.
+const ST_MOUNT_FLAGS: u32 = mountpoint::FLAG_RDONLY
+	| mountpoint::FLAG_NOSUID
+	| mountpoint::FLAG_NODEV
+	| mountpoint::FLAG_NOEXEC
+	| mountpoint::FLAG_SYNCHRONOUS
+	| mountpoint::FLAG_MANDLOCK
+	| mountpoint::FLAG_NOATIME
+	| mountpoint::FLAG_NODIRATIME;
+
+/// This structure is used in the f_fsid field of statfs. It identifies the filesystem.
 #[repr(C)]
-#[derive(Debug, Default)]
+#[derive(Clone, Copy, Debug, Default)]
 struct Fsid {
-	/// Unused.
-	_val: [i32; 2],
+	/// The device number of the filesystem, split in two.
+	val: [i32; 2],
 }
 
-/// Structure storing statistics about a filesystem.
+/// Structure storing statistics about a filesystem, with the layout of the i386 `statfs64`
+/// structure.
 #[repr(C)]
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Statfs {
 	/// Type of filesystem.
 	f_type: u32,
@@ -58,6 +87,94 @@ pub struct Statfs {
 	f_frsize: u32,
 	/// Mount flags of filesystem.
 	f_flags: u32,
+	/// Padding.
+	f_spare: [u32; 4],
+}
+
+impl Statfs {
+	/// Returns the statistics of a file that is not located on a filesystem, such as a pipe.
+	pub fn anonymous() -> Self {
+		Self {
+			f_bsize: memory::PAGE_SIZE as _,
+			f_namelen: limits::NAME_MAX as _,
+			f_frsize: memory::PAGE_SIZE as _,
+			f_flags: ST_VALID,
+			..Default::default()
+		}
+	}
+
+	/// Returns the optimal transfer block size of the filesystem.
+	pub fn get_block_size(&self) -> u32 {
+		self.f_bsize
+	}
+
+	/// Fills the fields depending on the mountpoint the filesystem is mounted on.
+	///
+	/// Arguments:
+	/// - `dev` is the device number of the filesystem.
+	/// - `flags` are the mount flags.
+	pub fn set_mountpoint(&mut self, dev: u64, flags: u32) {
+		self.f_fsid = Fsid {
+			val: [dev as _, (dev >> 32) as _],
+		};
+		self.f_flags = (flags & ST_MOUNT_FLAGS) | ST_VALID;
+	}
+}
+
+/// Structure storing statistics about a filesystem, with the layout of the i386 `statfs`
+/// structure.
+#[repr(C)]
+#[derive(Debug)]
+pub struct Statfs32 {
+	/// Type of filesystem.
+	f_type: u32,
+	/// Optimal transfer block size.
+	f_bsize: u32,
+	/// Total data blocks in filesystem.
+	f_blocks: u32,
+	/// Free blocks in filesystem.
+	f_bfree: u32,
+	/// Free blocks available to unprivileged user.
+	f_bavail: u32,
+	/// Total inodes in filesystem.
+	f_files: u32,
+	/// Free inodes in filesystem.
+	f_ffree: u32,
+	/// Filesystem ID.
+	f_fsid: Fsid,
+	/// Maximum length of filenames.
+	f_namelen: u32,
+	/// Fragment size.
+	f_frsize: u32,
+	/// Mount flags of filesystem.
+	f_flags: u32,
+	/// Padding.
+	f_spare: [u32; 4],
+}
+
+impl TryFrom<&Statfs> for Statfs32 {
+	type Error = Errno;
+
+	/// Converts the statistics to the 32 bits layout.
+	///
+	/// If a value does not fit, the function returns [`errno::EOVERFLOW`].
+	fn try_from(stat: &Statfs) -> EResult<Self> {
+		let conv = |n: i64| u32::try_from(n).map_err(|_| errno!(EOVERFLOW));
+		Ok(Self {
+			f_type: stat.f_type,
+			f_bsize: stat.f_bsize,
+			f_blocks: conv(stat.f_blocks)?,
+			f_bfree: conv(stat.f_bfree)?,
+			f_bavail: conv(stat.f_bavail)?,
+			f_files: conv(stat.f_files)?,
+			f_ffree: conv(stat.f_ffree)?,
+			f_fsid: stat.f_fsid,
+			f_namelen: stat.f_namelen,
+			f_frsize: stat.f_frsize,
+			f_flags: stat.f_flags,
+			f_spare: [0; 4],
+		})
+	}
 }
 
 /// Trait representing a filesystem.
@@ -150,6 +267,19 @@ pub trait Filesystem: Any {
 	/// - `file` the file structure containing the new values for the inode.
 	fn update_inode(&mut self, io: &mut dyn IO, file: &File) -> Result<(), Errno>;
 
+	/// Sets the status change and modification timestamps of the file at inode `inode` to `ts`,
+	/// leaving its other attributes untouched.
+	///
+	/// By default, the file is loaded, then written back with [`Self::update_inode`].
+	/// Filesystems on which loading a file is expensive, such as a directory whose entries are
+	/// read from the disk, should override this.
+	fn touch(&mut self, io: &mut dyn IO, inode: INode, ts: Timestamp) -> EResult<()> {
+		let mut file = self.load_file(io, inode, String::new())?;
+		file.mtime = ts;
+		file.ctime = ts;
+		self.update_inode(io, &file)
+	}
+
 	/// Removes a file from the filesystem. If the links count of the inode
 	/// reaches zero, the inode is also removed.
 	///
@@ -166,6 +296,30 @@ pub trait Filesystem: Any {
 		name: &[u8],
 	) -> Result<u16, Errno>;
 
+	/// Atomically moves the entry `old_name` of the directory `old_parent` to the entry
+	/// `new_name` of the directory `new_parent`.
+	///
+	/// If `exchange` is `true`, both entries must exist and are swapped. Else, the entry
+	/// `new_name`, if it exists, is replaced and the link it held is removed.
+	///
+	/// When a directory is moved to another parent, its `..` entry is updated.
+	///
+	/// The caller is responsible for checking that the types of the files are compatible and
+	/// that a replaced directory is empty.
+	///
+	/// By default, renaming is not supported and the function returns [`errno::EPERM`].
+	fn rename(
+		&mut self,
+		_io: &mut dyn IO,
+		_old_parent: INode,
+		_old_name: &[u8],
+		_new_parent: INode,
+		_new_name: &[u8],
+		_exchange: bool,
+	) -> EResult<()> {
+		Err(errno!(EPERM))
+	}
+
 	/// Reads from the given inode `inode` into the buffer `buf`.
 	///
 	/// Arguments:
@@ -199,6 +353,163 @@ pub trait Filesystem: Any {
 		off: u64,
 		buf: &[u8],
 	) -> Result<(), Errno>;
+
+	/// Allocates the storage for `len` bytes at offset `off` in the file at inode `inode`, so that
+	/// writing in this range cannot fail for lack of space. The allocated space that has not been
+	/// written reads as zeros.
+	///
+	/// If the range ends past the end of the file, the size of the file is increased accordingly.
+	///
+	/// By default, preallocation is not supported and the function returns
+	/// [`errno::EOPNOTSUPP`].
+	fn allocate_range(
+		&mut self,
+		_io: &mut dyn IO,
+		_inode: INode,
+		_off: u64,
+		_len: u64,
+	) -> EResult<()> {
+		Err(errno!(EOPNOTSUPP))
+	}
+
+	/// Deallocates the storage for `len` bytes at offset `off` in the file at inode `inode`. The
+	/// range then reads as zeros. The size of the file is not changed.
+	///
+	/// By default, deallocation is not supported and the function returns [`errno::EOPNOTSUPP`].
+	fn punch_hole(
+		&mut self,
+		_io: &mut dyn IO,
+		_inode: INode,
+		_off: u64,
+		_len: u64,
+	) -> EResult<()> {
+		Err(errno!(EOPNOTSUPP))
+	}
+
+	/// Changes the size of the file at inode `inode` to `size`.
+	///
+	/// If the file shrinks, the storage past the new end is released. If it grows, the new space
+	/// reads as zeros.
+	///
+	/// If the file is not a regular file, the function returns [`errno::EINVAL`].
+	///
+	/// By default, truncation is not supported and the function returns [`errno::EOPNOTSUPP`].
+	fn truncate_node(&mut self, _io: &mut dyn IO, _inode: INode, _size: u64) -> EResult<()> {
+		Err(errno!(EOPNOTSUPP))
+	}
+
+	/// Checks whether the file at inode `inode`, entry of the directory `parent`, can be looked up
+	/// with the access profile `ap`.
+	///
+	/// This allows a filesystem to restrict the access to its files beyond their permissions. If
+	/// the file is hidden, the function returns [`errno::ENOENT`]. Files that cannot be looked up
+	/// are also omitted from directory listings.
+	///
+	/// By default, every file can be looked up.
+	fn check_lookup(&self, _parent: INode, _inode: INode, _ap: &AccessProfile) -> EResult<()> {
+		Ok(())
+	}
+
+	/// If the file at inode `inode` is a magic link, returns the file it resolves to.
+	///
+	/// Unlike a symbolic link, a magic link resolves directly to a file instead of a path, which
+	/// allows to reach files that have no path. `ap` is the access profile following the link.
+	///
+	/// By default, there is no magic link.
+	fn get_magic_link(
+		&self,
+		_inode: INode,
+		_ap: &AccessProfile,
+	) -> EResult<Option<Arc<Mutex<File>>>> {
+		Ok(None)
+	}
+
+	/// Returns the value of the extended attribute `name` of the file at inode `inode`.
+	///
+	/// Arguments:
+	/// - `io` is the IO interface.
+	/// - `inode` is the file's inode.
+	/// - `name` is the full name of the attribute, including its namespace.
+	///
+	/// If the attribute does not exist, the function returns [`errno::ENODATA`].
+	///
+	/// By default, extended attributes are not supported.
+	fn get_xattr(&mut self, _io: &mut dyn IO, _inode: INode, _name: &[u8]) -> EResult<Vec<u8>> {
+		Err(errno!(EOPNOTSUPP))
+	}
+
+	/// Sets the value of the extended attribute `name` of the file at inode `inode`.
+	///
+	/// Arguments:
+	/// - `io` is the IO interface.
+	/// - `inode` is the file's inode.
+	/// - `name` is the full name of the attribute, including its namespace.
+	/// - `value` is the new value of the attribute.
+	/// - `flags` are `XATTR_*` flags restricting whether the attribute may be created or replaced.
+	///
+	/// By default, extended attributes are not supported.
+	fn set_xattr(
+		&mut self,
+		_io: &mut dyn IO,
+		_inode: INode,
+		_name: &[u8],
+		_value: &[u8],
+		_flags: c_int,
+	) -> EResult<()> {
+		Err(errno!(EOPNOTSUPP))
+	}
+
+	/// Returns the names of the extended attributes of the file at inode `inode`, each followed by
+	/// a null byte.
+	///
+	/// By default, extended attributes are not supported.
+	fn list_xattr(&mut self, _io: &mut dyn IO, _inode: INode) -> EResult<Vec<u8>> {
+		Err(errno!(EOPNOTSUPP))
+	}
+
+	/// Removes the extended attribute `name` of the file at inode `inode`.
+	///
+	/// If the attribute does not exist, the function returns [`errno::ENODATA`].
+	///
+	/// By default, extended attributes are not supported.
+	fn remove_xattr(&mut self, _io: &mut dyn IO, _inode: INode, _name: &[u8]) -> EResult<()> {
+		Err(errno!(EOPNOTSUPP))
+	}
+
+	/// Returns the disk quotas of the filesystem.
+	///
+	/// By default, quotas are not supported and the function returns `None`.
+	fn get_quota(&mut self) -> Option<&mut Quota> {
+		None
+	}
+
+	/// Enables the quotas of type `kind`, computing the current usage of each user or group.
+	///
+	/// By default, quotas are not supported and the function returns [`errno::ENOSYS`].
+	fn quota_on(&mut self, _io: &mut dyn IO, _kind: usize) -> EResult<()> {
+		Err(errno!(ENOSYS))
+	}
+
+	/// Switches the filesystem between read-only and read-write, according to `readonly`.
+	///
+	/// When switching to read-only, pending changes are flushed to the device first.
+	///
+	/// By default, the mode cannot be changed and the function returns [`errno::EINVAL`] if it
+	/// differs from the current one.
+	fn remount(&mut self, _io: &mut dyn IO, readonly: bool) -> EResult<()> {
+		if readonly != self.is_readonly() {
+			return Err(errno!(EINVAL));
+		}
+		Ok(())
+	}
+
+	/// Flushes pending changes to the device and marks the filesystem as cleanly unmounted, so
+	/// that it can be detached from its device. Afterwards, the filesystem is read-only.
+	///
+	/// By default, the function does nothing.
+	fn unmount(&mut self, _io: &mut dyn IO) -> EResult<()> {
+		Ok(())
+	}
 }
 
 /// Trait representing a filesystem type.
@@ -217,11 +528,14 @@ pub trait FilesystemType {
 	/// - `io` is the IO interface.
 	/// - `mountpath` is the path on which the filesystem is mounted.
 	/// - `readonly` tells whether the filesystem is mounted in read-only.
+	/// - `options` is the set of filesystem-specific mount options. If an option is not supported
+	/// by the filesystem, the function returns [`errno::EINVAL`].
 	fn load_filesystem(
 		&self,
 		io: &mut dyn IO,
 		mountpath: Path,
 		readonly: bool,
+		options: &MountOptions,
 	) -> Result<Arc<Mutex<dyn Filesystem>>, Errno>;
 }
 
@@ -271,8 +585,12 @@ pub fn detect(io: &mut dyn IO) -> Result<Arc<dyn FilesystemType>, Errno> {
 pub fn register_defaults() -> Result<(), Errno> {
 	register(ext2::Ext2FsType {})?;
 	register(tmp::TmpFsType {})?;
+	register(devtmpfs::DevTmpFsType {})?;
+	register(iso9660::Iso9660FsType {})?;
+	register(overlay::OverlayFsType {})?;
 	register(procfs::ProcFsType {})?;
-	// TODO sysfs
+	register(pstore::PstoreFsType {})?;
+	register(sysfs::SysFsType {})?;
 
 	Ok(())
 }
@@ -0,0 +1,81 @@
+//! Mount options are filesystem-specific parameters given to the `mount` system call.
+//!
+//! They are given as a comma-separated list of entries, each being either a flag `key` or a
+//! pair `key=value`.
+
+use crate::errno;
+use crate::errno::EResult;
+use crate::util::container::hashmap::HashMap;
+use crate::util::container::string::String;
+
+/// A set of parsed mount options.
+#[derive(Default)]
+pub struct MountOptions(HashMap<String, Option<String>>);
+
+impl MountOptions {
+	/// Parses the string of mount options `s`.
+	///
+	/// Empty entries are ignored. If an option is given several times, the last value is kept.
+	pub fn parse(s: &[u8]) -> EResult<Self> {
+		let mut opts = HashMap::new();
+		for entry in s.split(|c| *c == b',').filter(|e| !e.is_empty()) {
+			let (key, value) = match entry.iter().position(|c| *c == b'=') {
+				Some(i) => (&entry[..i], Some(String::try_from(&entry[(i + 1)..])?)),
+				None => (entry, None),
+			};
+			if key.is_empty() {
+				return Err(errno!(EINVAL));
+			}
+			opts.insert(String::try_from(key)?, value)?;
+		}
+		Ok(Self(opts))
+	}
+
+	/// Checks that every option is in the list `known`.
+	///
+	/// If an unknown option is present, the function returns [`errno::EINVAL`].
+	pub fn check(&self, known: &[&[u8]]) -> EResult<()> {
+		let unknown = self
+			.0
+			.iter()
+			.any(|(key, _)| !known.contains(&key.as_bytes()));
+		if unknown {
+			return Err(errno!(EINVAL));
+		}
+		Ok(())
+	}
+
+	/// Tells whether the option `key` is present.
+	pub fn contains(&self, key: &[u8]) -> bool {
+		self.0.contains_key(key)
+	}
+
+	/// Returns the value of the option `key`.
+	///
+	/// If the option is not present, the function returns `None`. If the option is present
+	/// without a value, the function returns [`errno::EINVAL`].
+	pub fn get(&self, key: &[u8]) -> EResult<Option<&[u8]>> {
+		match self.0.get(key) {
+			Some(Some(value)) => Ok(Some(value.as_bytes())),
+			Some(None) => Err(errno!(EINVAL)),
+			None => Ok(None),
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test_case]
+	fn mount_options_parse() {
+		let opts = MountOptions::parse(b"ro,,size=16m,mode=0755,size=32m").unwrap();
+		assert!(opts.contains(b"ro"));
+		assert!(opts.get(b"ro").is_err());
+		assert_eq!(opts.get(b"size").unwrap(), Some(&b"32m"[..]));
+		assert_eq!(opts.get(b"uid").unwrap(), None);
+		assert!(opts.check(&[b"ro", b"size", b"mode"]).is_ok());
+		assert!(opts.check(&[b"ro", b"size"]).is_err());
+		assert!(MountOptions::parse(b"=foo").is_err());
+	}
+}
@@ -0,0 +1,100 @@
+//! A layer is a directory of another filesystem, used as a branch of the union.
+
+use crate::errno;
+use crate::errno::EResult;
+use crate::file::dcache;
+use crate::file::fs::Filesystem;
+use crate::file::path::Path;
+use crate::file::perm::AccessProfile;
+use crate::file::vfs;
+use crate::file::File;
+use crate::file::FileContent;
+use crate::file::FileType;
+use crate::file::INode;
+use crate::util::container::string::String;
+use crate::util::io::IO;
+use crate::util::lock::Mutex;
+use crate::util::ptr::arc::Arc;
+
+/// Tells whether the file `file` is a whiteout, which hides the file of the same name in the
+/// lower layer.
+///
+/// Like Linux, a whiteout is a character device with device numbers `0:0`.
+pub fn is_whiteout(file: &File) -> bool {
+	matches!(
+		file.get_content(),
+		FileContent::CharDevice {
+			major: 0,
+			minor: 0,
+		}
+	)
+}
+
+/// A directory of a filesystem, used as a layer.
+pub struct Layer {
+	/// The ID of the mountpoint of the filesystem.
+	mountpoint_id: u32,
+	/// The I/O interface of the filesystem.
+	io: Arc<Mutex<dyn IO>>,
+	/// The filesystem.
+	fs: Arc<Mutex<dyn Filesystem>>,
+	/// The inode of the directory on the filesystem.
+	pub root: INode,
+}
+
+impl Layer {
+	/// Returns the layer for the directory at path `path`.
+	///
+	/// Mountpoints located inside of the directory are not part of the layer.
+	pub fn new(path: &[u8]) -> EResult<Self> {
+		let path = Path::from_str(path, false)?;
+		let path = Path::root().concat(&path)?;
+		let dir_mutex = vfs::get_file_from_path(&path, &AccessProfile::KERNEL, true)?;
+		let dir = dir_mutex.lock();
+		if dir.get_type() != FileType::Directory {
+			return Err(errno!(ENOTDIR));
+		}
+
+		let location = dir.get_location();
+		let mountpoint_mutex = location.get_mountpoint().ok_or_else(|| errno!(ENOENT))?;
+		let mountpoint = mountpoint_mutex.lock();
+		Ok(Self {
+			mountpoint_id: mountpoint.get_id(),
+			io: mountpoint.get_source().get_io()?,
+			fs: mountpoint.get_filesystem(),
+			root: location.get_inode(),
+		})
+	}
+
+	/// Executes `f` with the filesystem and its I/O interface.
+	pub fn op<R, F: FnOnce(&mut dyn Filesystem, &mut dyn IO) -> EResult<R>>(
+		&self,
+		f: F,
+	) -> EResult<R> {
+		let mut io = self.io.lock();
+		let mut fs = self.fs.lock();
+		f(&mut *fs, &mut *io)
+	}
+
+	/// Returns the inode of the file with name `name` in the directory `parent`.
+	///
+	/// If the file doesn't exist, the function returns `None`.
+	pub fn lookup(&self, parent: INode, name: &[u8]) -> EResult<Option<INode>> {
+		self.op(|fs, io| match fs.get_inode(io, Some(parent), name) {
+			Ok(inode) => Ok(Some(inode)),
+			Err(e) if e.as_int() == errno::ENOENT => Ok(None),
+			Err(e) => Err(e),
+		})
+	}
+
+	/// Loads the file at inode `inode`.
+	pub fn load(&self, inode: INode, name: String) -> EResult<File> {
+		self.op(|fs, io| fs.load_file(io, inode, name))
+	}
+
+	/// Invalidates the entry `name` of the directory `parent` in the cache of the filesystem's
+	/// mountpoint, after it has been modified through the layer.
+	pub fn invalidate(&self, parent: INode, name: &[u8]) {
+		dcache::invalidate(self.mountpoint_id, parent, name);
+	}
+}
@@ -0,0 +1,781 @@
+//! The overlay filesystem merges a writable upper directory over a read-only lower directory,
+//! which allows to make a read-only filesystem writable, for example using a tmpfs.
+//!
+//! The layers are given by the `lowerdir` and `upperdir` mount options. Files are looked up in
+//! the upper layer first. Directories present in both layers are merged.
+//!
+//! The lower layer is never modified: a file is copied to the upper layer (*copy up*) before its
+//! first modification. A file removed while it exists in the lower layer is hidden by a
+//! *whiteout* in the upper layer. A directory created in place of a removed directory is made
+//! *opaque*, so that the entries of the lower directory are hidden. Since extended attributes are
+//! not supported, opacity is marked by a whiteout named [`OPAQUE_NAME`] inside the directory.
+//!
+//! The overlay has its own inodes, each associated with the corresponding files in the layers.
+
+mod layer;
+
+use super::options::MountOptions;
+use super::Filesystem;
+use super::FilesystemType;
+use super::Statfs;
+use crate::errno;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::path::Path;
+use crate::file::perm::Gid;
+use crate::file::perm::Uid;
+use crate::file::DirEntry;
+use crate::file::File;
+use crate::file::FileContent;
+use crate::file::FileLocation;
+use crate::file::FileType;
+use crate::file::INode;
+use crate::file::Mode;
+use crate::util::container::hashmap::HashMap;
+use crate::util::container::string::String;
+use crate::util::io::IO;
+use crate::util::lock::Mutex;
+use crate::util::ptr::arc::Arc;
+use crate::util::TryClone;
+use core::cmp::min;
+use layer::is_whiteout;
+use layer::Layer;
+
+/// The inode of the root directory.
+const ROOT_INODE: INode = 1;
+/// The name of the whiteout marking a directory of the upper layer as opaque.
+const OPAQUE_NAME: &[u8] = b".wh..wh..opq";
+/// The size of the buffer used to copy files' content.
+const COPY_BUFFER_SIZE: usize = 4096;
+
+/// Parses the mount options `options`.
+///
+/// The function returns the paths of the lower and upper layers. The work directory is accepted
+/// for compatibility but unused, since files are copied up in place.
+fn parse_options(options: &MountOptions) -> EResult<(&[u8], &[u8])> {
+	options.check(&[b"lowerdir", b"upperdir", b"workdir"])?;
+	let lower = options.get(b"lowerdir")?.ok_or_else(|| errno!(EINVAL))?;
+	let upper = options.get(b"upperdir")?.ok_or_else(|| errno!(EINVAL))?;
+	Ok((lower, upper))
+}
+
+/// A file of the overlay.
+struct Node {
+	/// The inode of the parent directory.
+	parent: INode,
+	/// The name of the file in its parent directory.
+	name: String,
+
+	/// The inode of the file in the upper layer, if present.
+	upper: Option<INode>,
+	/// The inode of the file in the lower layer, if visible.
+	lower: Option<INode>,
+}
+
+/// A file present in a layer, as found when listing a directory.
+struct LayerEntry {
+	/// The inode of the file in the layer.
+	inode: INode,
+	/// The type of the file.
+	entry_type: FileType,
+}
+
+/// Structure representing an overlay filesystem.
+pub struct OverlayFs {
+	/// The lower layer, which is never modified.
+	lower: Layer,
+	/// The upper layer, receiving modifications.
+	upper: Layer,
+
+	/// The files of the overlay, by inode.
+	nodes: HashMap<INode, Node>,
+	/// For each directory, the inodes of the files it contains, by name.
+	children: HashMap<INode, HashMap<String, INode>>,
+	/// The next inode to be allocated.
+	next_inode: INode,
+}
+
+impl OverlayFs {
+	/// Creates a new instance with the given layers.
+	fn new(lower: Layer, upper: Layer) -> EResult<Self> {
+		let mut fs = Self {
+			nodes: HashMap::new(),
+			children: HashMap::new(),
+			next_inode: ROOT_INODE + 1,
+
+			lower,
+			upper,
+		};
+		fs.nodes.insert(
+			ROOT_INODE,
+			Node {
+				parent: ROOT_INODE,
+				name: String::new(),
+
+				upper: Some(fs.upper.root),
+				lower: Some(fs.lower.root),
+			},
+		)?;
+		Ok(fs)
+	}
+
+	/// Returns the node with inode `inode`.
+	fn get_node(&self, inode: INode) -> EResult<&Node> {
+		self.nodes.get(&inode).ok_or_else(|| errno!(ENOENT))
+	}
+
+	/// Returns the inode of the file `name` in the directory `parent`, associating it with the
+	/// given files of the layers.
+	///
+	/// If the file has no inode yet, the function allocates one.
+	fn intern(
+		&mut self,
+		parent: INode,
+		name: &[u8],
+		upper: Option<INode>,
+		lower: Option<INode>,
+	) -> EResult<INode> {
+		let existing = self
+			.children
+			.get(&parent)
+			.and_then(|c| c.get(name))
+			.copied();
+		if let Some(inode) = existing {
+			if let Some(node) = self.nodes.get_mut(&inode) {
+				node.upper = upper;
+				node.lower = lower;
+			}
+			return Ok(inode);
+		}
+
+		let inode = self.next_inode;
+		self.nodes.insert(
+			inode,
+			Node {
+				parent,
+				name: String::try_from(name)?,
+
+				upper,
+				lower,
+			},
+		)?;
+		let children = match self.children.get_mut(&parent) {
+			Some(children) => children,
+			None => {
+				self.children.insert(parent, HashMap::new())?;
+				self.children.get_mut(&parent).unwrap()
+			}
+		};
+		if let Err(e) = children.insert(String::try_from(name)?, inode) {
+			self.nodes.remove(&inode);
+			return Err(e.into());
+		}
+		self.next_inode += 1;
+		Ok(inode)
+	}
+
+	/// Forgets the inode of the file `name` in the directory `parent`, after it has been removed.
+	fn forget(&mut self, parent: INode, name: &[u8]) {
+		let Some(inode) = self.children.get_mut(&parent).and_then(|c| c.remove(name)) else {
+			return;
+		};
+		self.nodes.remove(&inode);
+		self.children.remove(&inode);
+	}
+
+	/// Tells whether the directory `dir` of the upper layer is opaque.
+	fn is_opaque(&self, dir: INode) -> EResult<bool> {
+		Ok(self.upper.lookup(dir, OPAQUE_NAME)?.is_some())
+	}
+
+	/// Tells whether the directory `dir` of the upper layer contains a whiteout named `name`.
+	fn has_whiteout(&self, dir: INode, name: &[u8]) -> EResult<bool> {
+		let Some(inode) = self.upper.lookup(dir, name)? else {
+			return Ok(false);
+		};
+		Ok(is_whiteout(&self.upper.load(inode, String::new())?))
+	}
+
+	/// Tells whether the file `name` exists in the lower layer, in the directory of the node
+	/// `dir`.
+	fn in_lower(&self, dir: &Node, name: &[u8]) -> EResult<bool> {
+		match dir.lower {
+			Some(lower) => Ok(self.lower.lookup(lower, name)?.is_some()),
+			None => Ok(false),
+		}
+	}
+
+	/// Looks for the file `name` in the layers, in the directory of the node `dir`.
+	///
+	/// The function returns the inodes of the file in the upper and lower layers. If the file
+	/// doesn't exist or is hidden, the function returns `None`.
+	fn lookup_layers(
+		&self,
+		dir: &Node,
+		name: &[u8],
+	) -> EResult<Option<(Option<INode>, Option<INode>)>> {
+		if name == OPAQUE_NAME {
+			return Ok(None);
+		}
+
+		let mut upper = None;
+		if let Some(dir_upper) = dir.upper {
+			if let Some(inode) = self.upper.lookup(dir_upper, name)? {
+				let file = self.upper.load(inode, String::new())?;
+				if is_whiteout(&file) {
+					return Ok(None);
+				}
+				// A file that is not a directory hides the lower layer
+				if file.get_type() != FileType::Directory || self.is_opaque(inode)? {
+					return Ok(Some((Some(inode), None)));
+				}
+				upper = Some(inode);
+			}
+		}
+
+		let mut lower = None;
+		if let Some(dir_lower) = dir.lower {
+			if let Some(inode) = self.lower.lookup(dir_lower, name)? {
+				let file_type = self.lower.load(inode, String::new())?.get_type();
+				// Only directories are merged
+				if upper.is_none() || file_type == FileType::Directory {
+					lower = Some(inode);
+				}
+			}
+		}
+
+		if upper.is_none() && lower.is_none() {
+			return Ok(None);
+		}
+		Ok(Some((upper, lower)))
+	}
+
+	/// Lists the entries of the directory `dir` in the layer `layer`.
+	///
+	/// Whiteouts are not returned, but their names are inserted in `whiteouts`.
+	fn list_layer(
+		layer: &Layer,
+		dir: INode,
+		whiteouts: &mut HashMap<String, ()>,
+	) -> EResult<HashMap<String, LayerEntry>> {
+		let file = layer.load(dir, String::new())?;
+		let FileContent::Directory(entries) = file.get_content() else {
+			return Err(errno!(ENOTDIR));
+		};
+
+		let mut list = HashMap::new();
+		for (name, entry) in entries.iter() {
+			if name.as_bytes() == OPAQUE_NAME {
+				continue;
+			}
+			if entry.entry_type == FileType::CharDevice
+				&& is_whiteout(&layer.load(entry.inode, String::new())?)
+			{
+				whiteouts.insert(name.try_clone()?, ())?;
+				continue;
+			}
+			list.insert(
+				name.try_clone()?,
+				LayerEntry {
+					inode: entry.inode,
+					entry_type: entry.entry_type,
+				},
+			)?;
+		}
+		Ok(list)
+	}
+
+	/// Returns the merged entries of the directory with inode `inode`.
+	fn merge_entries(&mut self, inode: INode) -> EResult<HashMap<String, DirEntry>> {
+		let (parent, upper, lower) = {
+			let node = self.get_node(inode)?;
+			(node.parent, node.upper, node.lower)
+		};
+
+		let mut whiteouts = HashMap::new();
+		let upper_entries = match upper {
+			Some(upper) => Self::list_layer(&self.upper, upper, &mut whiteouts)?,
+			None => HashMap::new(),
+		};
+		let lower_entries = match lower {
+			Some(lower) => Self::list_layer(&self.lower, lower, &mut HashMap::new())?,
+			None => HashMap::new(),
+		};
+
+		let mut entries = HashMap::new();
+		for (name, entry) in upper_entries.iter() {
+			let inode = match name.as_bytes() {
+				b"." => inode,
+				b".." => parent,
+				_ => {
+					let lower = lower_entries
+						.get(name.as_bytes())
+						.filter(|l| {
+							entry.entry_type == FileType::Directory
+								&& l.entry_type == FileType::Directory
+						})
+						.map(|l| l.inode);
+					let lower = match lower {
+						Some(_) if self.is_opaque(entry.inode)? => None,
+						l => l,
+					};
+					self.intern(inode, name.as_bytes(), Some(entry.inode), lower)?
+				}
+			};
+			entries.insert(
+				name.try_clone()?,
+				DirEntry {
+					inode,
+					entry_type: entry.entry_type,
+				},
+			)?;
+		}
+		for (name, entry) in lower_entries.iter() {
+			if upper_entries.get(name.as_bytes()).is_some()
+				|| whiteouts.get(name.as_bytes()).is_some()
+			{
+				continue;
+			}
+			let inode = match name.as_bytes() {
+				b"." => inode,
+				b".." => parent,
+				_ => self.intern(inode, name.as_bytes(), None, Some(entry.inode))?,
+			};
+			entries.insert(
+				name.try_clone()?,
+				DirEntry {
+					inode,
+					entry_type: entry.entry_type,
+				},
+			)?;
+		}
+		Ok(entries)
+	}
+
+	/// Copies the file with inode `inode` to the upper layer, along with its parent directories,
+	/// if not already present.
+	///
+	/// The function returns the inode of the file in the upper layer.
+	fn copy_up(&mut self, inode: INode) -> EResult<INode> {
+		let (parent, name, lower) = {
+			let node = self.get_node(inode)?;
+			if let Some(upper) = node.upper {
+				return Ok(upper);
+			}
+			let lower = node.lower.ok_or_else(|| errno!(ENOENT))?;
+			(node.parent, node.name.try_clone()?, lower)
+		};
+		let parent_upper = self.copy_up(parent)?;
+
+		let src = self.lower.load(lower, String::new())?;
+		// The entries of a directory stay in the lower layer
+		let content = match src.get_content() {
+			FileContent::Directory(_) => FileContent::Directory(HashMap::new()),
+			content => content.try_clone()?,
+		};
+		let mut dst = self.upper.op(|fs, io| {
+			fs.add_file(
+				io,
+				parent_upper,
+				name.try_clone()?,
+				src.get_uid(),
+				src.get_gid(),
+				src.get_permissions(),
+				content,
+			)
+		})?;
+		let upper = dst.get_location().get_inode();
+		self.upper.invalidate(parent_upper, name.as_bytes());
+
+		if src.get_type() == FileType::Regular {
+			let size = src.get_size();
+			let mut buf = crate::vec![0u8; COPY_BUFFER_SIZE]?;
+			let mut off = 0;
+			while off < size {
+				let len = min(size - off, buf.len() as u64) as usize;
+				let len = self
+					.lower
+					.op(|fs, io| fs.read_node(io, lower, off, &mut buf[..len]))?;
+				if len == 0 {
+					break;
+				}
+				let data = &buf[..(len as usize)];
+				self.upper
+					.op(|fs, io| fs.write_node(io, upper, off, data))?;
+				off += len;
+			}
+			dst.set_size(size);
+		}
+		dst.ctime = src.ctime;
+		dst.mtime = src.mtime;
+		dst.atime = src.atime;
+		self.upper.op(|fs, io| fs.update_inode(io, &dst))?;
+
+		if let Some(node) = self.nodes.get_mut(&inode) {
+			node.upper = Some(upper);
+		}
+		Ok(upper)
+	}
+
+	/// Removes the whiteouts located in the directory `dir` of the upper layer, so that it can be
+	/// removed.
+	fn clear_whiteouts(&self, dir: INode) -> EResult<()> {
+		let mut whiteouts = HashMap::new();
+		Self::list_layer(&self.upper, dir, &mut whiteouts)?;
+		let opaque = self.is_opaque(dir)?;
+		let names = whiteouts
+			.iter()
+			.map(|(name, _)| name.as_bytes())
+			.chain(opaque.then_some(OPAQUE_NAME));
+		for name in names {
+			self.upper.op(|fs, io| fs.remove_file(io, dir, name))?;
+			self.upper.invalidate(dir, name);
+		}
+		Ok(())
+	}
+
+	/// Prepares the creation of the file `name` in the directory `parent`.
+	///
+	/// The function returns the inode of the directory in the upper layer and whether the file
+	/// hides a file of the lower layer.
+	fn prepare_create(&mut self, parent: INode, name: &[u8]) -> EResult<(INode, bool)> {
+		let parent_upper = self.copy_up(parent)?;
+		let hides_lower = self.in_lower(self.get_node(parent)?, name)?;
+		if self.has_whiteout(parent_upper, name)? {
+			self.upper
+				.op(|fs, io| fs.remove_file(io, parent_upper, name))?;
+			self.upper.invalidate(parent_upper, name);
+		}
+		Ok((parent_upper, hides_lower))
+	}
+
+	/// Returns a copy of the file `file` of the overlay, located at inode `inode` of the upper
+	/// layer.
+	fn relocate(file: &File, inode: INode) -> EResult<File> {
+		let content = match file.get_content() {
+			FileContent::Directory(_) => FileContent::Directory(HashMap::new()),
+			content => content.try_clone()?,
+		};
+		let mut upper = File::new(
+			file.get_name().try_clone()?,
+			file.get_uid(),
+			file.get_gid(),
+			file.get_permissions(),
+			FileLocation::Filesystem {
+				mountpoint_id: 0,
+				inode,
+			},
+			content,
+		)?;
+		upper.set_size(file.get_size());
+		upper.set_hard_links_count(file.get_hard_links_count());
+		upper.ctime = file.ctime;
+		upper.mtime = file.mtime;
+		upper.atime = file.atime;
+		upper.btime = file.btime;
+		Ok(upper)
+	}
+}
+
+impl Filesystem for OverlayFs {
+	fn get_name(&self) -> &[u8] {
+		b"overlay"
+	}
+
+	fn is_readonly(&self) -> bool {
+		false
+	}
+
+	fn must_cache(&self) -> bool {
+		// The layers may be modified directly
+		false
+	}
+
+	fn get_stat(&self, _io: &mut dyn IO) -> Result<Statfs, Errno> {
+		self.upper.op(|fs, io| fs.get_stat(io))
+	}
+
+	fn get_root_inode(&self, _io: &mut dyn IO) -> Result<INode, Errno> {
+		Ok(ROOT_INODE)
+	}
+
+	fn get_inode(
+		&mut self,
+		_io: &mut dyn IO,
+		parent: Option<INode>,
+		name: &[u8],
+	) -> Result<INode, Errno> {
+		let parent = parent.unwrap_or(ROOT_INODE);
+		let dir = self.get_node(parent)?;
+		match name {
+			b"." => return Ok(parent),
+			b".." => return Ok(dir.parent),
+			_ => {}
+		}
+		let (upper, lower) = self
+			.lookup_layers(dir, name)?
+			.ok_or_else(|| errno!(ENOENT))?;
+		self.intern(parent, name, upper, lower)
+	}
+
+	fn load_file(&mut self, _io: &mut dyn IO, inode: INode, name: String) -> Result<File, Errno> {
+		let node = self.get_node(inode)?;
+		let mut file = match (node.upper, node.lower) {
+			(Some(upper), _) => self.upper.load(upper, name)?,
+			(None, Some(lower)) => self.lower.load(lower, name)?,
+			(None, None) => return Err(errno!(ENOENT)),
+		};
+		file.location = FileLocation::Filesystem {
+			mountpoint_id: 0, // dummy value to be replaced
+			inode,
+		};
+		if let FileContent::Directory(_) = file.get_content() {
+			file.content = FileContent::Directory(self.merge_entries(inode)?);
+		}
+		Ok(file)
+	}
+
+	fn add_file(
+		&mut self,
+		_io: &mut dyn IO,
+		parent_inode: INode,
+		name: String,
+		uid: Uid,
+		gid: Gid,
+		mode: Mode,
+		content: FileContent,
+	) -> Result<File, Errno> {
+		let (parent_upper, hides_lower) = self.prepare_create(parent_inode, name.as_bytes())?;
+		let is_dir = matches!(content, FileContent::Directory(_));
+		let mut file = self.upper.op(|fs, io| {
+			fs.add_file(io, parent_upper, name.try_clone()?, uid, gid, mode, content)
+		})?;
+		let upper = file.get_location().get_inode();
+		self.upper.invalidate(parent_upper, name.as_bytes());
+		// The directory replaces the one of the lower layer instead of being merged with it
+		if is_dir && hides_lower {
+			self.upper.op(|fs, io| {
+				fs.add_file(
+					io,
+					upper,
+					String::try_from(OPAQUE_NAME)?,
+					0,
+					0,
+					0,
+					FileContent::CharDevice {
+						major: 0,
+						minor: 0,
+					},
+				)
+			})?;
+		}
+
+		let inode = self.intern(parent_inode, name.as_bytes(), Some(upper), None)?;
+		file.location = FileLocation::Filesystem {
+			mountpoint_id: 0, // dummy value to be replaced
+			inode,
+		};
+		if is_dir {
+			file.content = FileContent::Directory(self.merge_entries(inode)?);
+		}
+		Ok(file)
+	}
+
+	fn add_link(
+		&mut self,
+		_io: &mut dyn IO,
+		parent_inode: INode,
+		name: &[u8],
+		inode: INode,
+	) -> Result<(), Errno> {
+		let target = self.copy_up(inode)?;
+		let (parent_upper, _) = self.prepare_create(parent_inode, name)?;
+		self.upper
+			.op(|fs, io| fs.add_link(io, parent_upper, name, target))?;
+		self.upper.invalidate(parent_upper, name);
+		self.intern(parent_inode, name, Some(target), None)?;
+		Ok(())
+	}
+
+	fn update_inode(&mut self, _io: &mut dyn IO, file: &File) -> Result<(), Errno> {
+		let inode = file.get_location().get_inode();
+		let node = self.get_node(inode)?;
+		if let (None, Some(lower)) = (node.upper, node.lower) {
+			// Updating only timestamps does not require a copy up
+			let lower = self.lower.load(lower, String::new())?;
+			if lower.get_uid() == file.get_uid()
+				&& lower.get_gid() == file.get_gid()
+				&& lower.get_mode() == file.get_mode()
+				&& lower.get_size() == file.get_size()
+			{
+				return Ok(());
+			}
+		}
+		let upper = self.copy_up(inode)?;
+		let file = Self::relocate(file, upper)?;
+		self.upper.op(|fs, io| fs.update_inode(io, &file))
+	}
+
+	fn remove_file(
+		&mut self,
+		io: &mut dyn IO,
+		parent_inode: INode,
+		name: &[u8],
+	) -> Result<u16, Errno> {
+		let inode = self.get_inode(io, Some(parent_inode), name)?;
+		let (upper, is_dir) = {
+			let node = self.get_node(inode)?;
+			let file_type = match (node.upper, node.lower) {
+				(Some(upper), _) => self.upper.load(upper, String::new())?.get_type(),
+				(None, Some(lower)) => self.lower.load(lower, String::new())?.get_type(),
+				(None, None) => return Err(errno!(ENOENT)),
+			};
+			(node.upper, file_type == FileType::Directory)
+		};
+		if is_dir {
+			let entries = self.merge_entries(inode)?;
+			if entries.iter().any(|(e, _)| e != "." && e != "..") {
+				return Err(errno!(ENOTEMPTY));
+			}
+		}
+
+		let parent_upper = self.copy_up(parent_inode)?;
+		let hides_lower = self.in_lower(self.get_node(parent_inode)?, name)?;
+		let mut links = 0;
+		if let Some(upper) = upper {
+			if is_dir {
+				self.clear_whiteouts(upper)?;
+			}
+			links = self
+				.upper
+				.op(|fs, io| fs.remove_file(io, parent_upper, name))?;
+			self.upper.invalidate(parent_upper, name);
+		}
+		// Hide the file of the lower layer
+		if hides_lower {
+			self.upper.op(|fs, io| {
+				fs.add_file(
+					io,
+					parent_upper,
+					String::try_from(name)?,
+					0,
+					0,
+					0,
+					FileContent::CharDevice {
+						major: 0,
+						minor: 0,
+					},
+				)
+			})?;
+			self.upper.invalidate(parent_upper, name);
+			links = 0;
+		}
+
+		self.forget(parent_inode, name);
+		Ok(links)
+	}
+
+	fn rename(
+		&mut self,
+		io: &mut dyn IO,
+		old_parent: INode,
+		old_name: &[u8],
+		new_parent: INode,
+		new_name: &[u8],
+		exchange: bool,
+	) -> EResult<()> {
+		// Moving a directory would require redirecting the lookups of its lower part, which is
+		// not supported. Like for a move across filesystems, userspace is expected to copy it
+		// instead
+		let inode = self.get_inode(io, Some(old_parent), old_name)?;
+		let is_dir = matches!(
+			self.load_file(io, inode, String::new())?.get_content(),
+			FileContent::Directory(_)
+		);
+		if is_dir || exchange {
+			return Err(errno!(EXDEV));
+		}
+
+		if self.get_inode(io, Some(new_parent), new_name).is_ok() {
+			self.remove_file(io, new_parent, new_name)?;
+		}
+		self.add_link(io, new_parent, new_name, inode)?;
+		self.remove_file(io, old_parent, old_name)?;
+		Ok(())
+	}
+
+	fn read_node(
+		&mut self,
+		_io: &mut dyn IO,
+		inode: INode,
+		off: u64,
+		buf: &mut [u8],
+	) -> Result<u64, Errno> {
+		let node = self.get_node(inode)?;
+		match (node.upper, node.lower) {
+			(Some(upper), _) => self.upper.op(|fs, io| fs.read_node(io, upper, off, buf)),
+			(None, Some(lower)) => self.lower.op(|fs, io| fs.read_node(io, lower, off, buf)),
+			(None, None) => Err(errno!(ENOENT)),
+		}
+	}
+
+	fn write_node(
+		&mut self,
+		_io: &mut dyn IO,
+		inode: INode,
+		off: u64,
+		buf: &[u8],
+	) -> Result<(), Errno> {
+		let upper = self.copy_up(inode)?;
+		self.upper.op(|fs, io| fs.write_node(io, upper, off, buf))
+	}
+
+	fn truncate_node(&mut self, _io: &mut dyn IO, inode: INode, size: u64) -> EResult<()> {
+		let upper = self.copy_up(inode)?;
+		self.upper.op(|fs, io| fs.truncate_node(io, upper, size))
+	}
+}
+
+/// Structure representing the overlay filesystem type.
+pub struct OverlayFsType {}
+
+impl FilesystemType for OverlayFsType {
+	fn get_name(&self) -> &'static [u8] {
+		b"overlay"
+	}
+
+	fn detect(&self, _io: &mut dyn IO) -> Result<bool, Errno> {
+		Ok(false)
+	}
+
+	fn load_filesystem(
+		&self,
+		_io: &mut dyn IO,
+		_mountpath: Path,
+		_readonly: bool,
+		options: &MountOptions,
+	) -> Result<Arc<Mutex<dyn Filesystem>>, Errno> {
+		let (lower, upper) = parse_options(options)?;
+		let fs = OverlayFs::new(Layer::new(lower)?, Layer::new(upper)?)?;
+		Ok(Arc::new(Mutex::new(fs))?)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test_case]
+	fn overlay_options() {
+		let opts =
+			MountOptions::parse(b"lowerdir=/ro,upperdir=/rw/upper,workdir=/rw/work").unwrap();
+		assert_eq!(
+			parse_options(&opts).unwrap(),
+			(&b"/ro"[..], &b"/rw/upper"[..])
+		);
+		let opts = MountOptions::parse(b"lowerdir=/ro").unwrap();
+		assert!(parse_options(&opts).is_err());
+		let opts = MountOptions::parse(b"lowerdir=/ro,upperdir=/rw,index=on").unwrap();
+		assert!(parse_options(&opts).is_err());
+	}
+}
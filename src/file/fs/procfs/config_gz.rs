@@ -0,0 +1,50 @@
+//! The `/proc/config.gz` file contains the gzip-compressed configuration the kernel has been
+//! built with.
+
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::fs::kernfs::content::KernFSContent;
+use crate::file::fs::kernfs::node::KernFSNode;
+use crate::file::FileContent;
+use crate::file::Mode;
+use crate::util::io::IO;
+use core::cmp::min;
+
+/// The compressed configuration, embedded at build time.
+static CONFIG_GZ: &[u8] = include_bytes!(env!("MAESTRO_CONFIG_GZ"));
+
+/// Structure representing the config.gz node.
+pub struct ConfigGz {}
+
+impl KernFSNode for ConfigGz {
+	fn get_mode(&self) -> Mode {
+		0o444
+	}
+
+	fn get_content(&mut self) -> EResult<KernFSContent<'_>> {
+		Ok(KernFSContent::Dynamic(FileContent::Regular))
+	}
+}
+
+impl IO for ConfigGz {
+	fn get_size(&self) -> u64 {
+		CONFIG_GZ.len() as _
+	}
+
+	fn read(&mut self, offset: u64, buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+		let offset = min(offset, CONFIG_GZ.len() as u64) as usize;
+		let len = min(CONFIG_GZ.len() - offset, buff.len());
+		buff[..len].copy_from_slice(&CONFIG_GZ[offset..(offset + len)]);
+
+		let eof = offset + len >= CONFIG_GZ.len();
+		Ok((len as _, eof))
+	}
+
+	fn write(&mut self, _offset: u64, _buff: &[u8]) -> Result<u64, Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn poll(&mut self, _mask: u32) -> Result<u32, Errno> {
+		Ok(0)
+	}
+}
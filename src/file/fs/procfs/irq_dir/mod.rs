@@ -0,0 +1,106 @@
+//! The `irq` directory contains a directory for each ISA IRQ, allowing to read and set the CPUs
+//! the IRQ is delivered to.
+
+mod smp_affinity;
+
+use super::kernfs::KernFS;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::fs::kernfs::content::KernFSContent;
+use crate::file::fs::kernfs::node::DummyKernFSNode;
+use crate::file::fs::kernfs::node::KernFSNode;
+use crate::file::perm::Gid;
+use crate::file::perm::Uid;
+use crate::file::DirEntry;
+use crate::file::FileContent;
+use crate::file::FileType;
+use crate::file::Mode;
+use crate::idt::apic;
+use crate::util::boxed::Box;
+use crate::util::container::hashmap::HashMap;
+use crate::util::io::IO;
+use smp_affinity::SmpAffinity;
+
+// TODO Handle dropping
+/// Structure representing the `irq` directory.
+pub struct IrqDir {
+	/// The content of the directory. This will always be a Directory variant.
+	content: FileContent,
+}
+
+impl IrqDir {
+	/// Creates a new instance.
+	///
+	/// The function adds every nodes to the given kernfs `fs`.
+	pub fn new(fs: &mut KernFS) -> Result<Self, Errno> {
+		let mut entries = HashMap::new();
+
+		for irq in 0..(apic::ISA_IRQS_COUNT as u8) {
+			let mut irq_entries = HashMap::new();
+
+			// Create /proc/irq/<n>/smp_affinity
+			let node = SmpAffinity {
+				irq,
+			};
+			let inode = fs.add_node(Box::new(node)?)?;
+			irq_entries.insert(
+				b"smp_affinity".try_into()?,
+				DirEntry {
+					inode,
+					entry_type: FileType::Regular,
+				},
+			)?;
+
+			// Create /proc/irq/<n>
+			let node = DummyKernFSNode::new(0o555, 0, 0, FileContent::Directory(irq_entries));
+			let inode = fs.add_node(Box::new(node)?)?;
+			entries.insert(
+				crate::format!("{irq}")?,
+				DirEntry {
+					inode,
+					entry_type: FileType::Directory,
+				},
+			)?;
+		}
+
+		Ok(Self {
+			content: FileContent::Directory(entries),
+		})
+	}
+}
+
+impl KernFSNode for IrqDir {
+	fn get_mode(&self) -> Mode {
+		0o555
+	}
+
+	fn get_uid(&self) -> Uid {
+		0
+	}
+
+	fn get_gid(&self) -> Gid {
+		0
+	}
+
+	fn get_content(&mut self) -> EResult<KernFSContent<'_>> {
+		Ok(KernFSContent::Owned(&mut self.content))
+	}
+}
+
+impl IO for IrqDir {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, _offset: u64, _buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn write(&mut self, _offset: u64, _buff: &[u8]) -> Result<u64, Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn poll(&mut self, _mask: u32) -> Result<u32, Errno> {
+		Err(errno!(EINVAL))
+	}
+}
@@ -0,0 +1,75 @@
+//! The `smp_affinity` node allows to read and set the mask of CPUs an IRQ is delivered to, in
+//! hexadecimal.
+
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::fs::kernfs::content::KernFSContent;
+use crate::file::fs::kernfs::node::KernFSNode;
+use crate::file::perm::Gid;
+use crate::file::perm::Uid;
+use crate::file::FileContent;
+use crate::file::Mode;
+use crate::idt::apic;
+use crate::util::io::IO;
+use core::cmp::min;
+
+/// Structure representing the `smp_affinity` node of an IRQ.
+pub struct SmpAffinity {
+	/// The ISA IRQ.
+	pub irq: u8,
+}
+
+impl KernFSNode for SmpAffinity {
+	fn get_mode(&self) -> Mode {
+		0o644
+	}
+
+	fn get_uid(&self) -> Uid {
+		0
+	}
+
+	fn get_gid(&self) -> Gid {
+		0
+	}
+
+	fn get_content(&mut self) -> EResult<KernFSContent<'_>> {
+		Ok(FileContent::Regular.into())
+	}
+}
+
+impl IO for SmpAffinity {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, offset: u64, buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+		if buff.is_empty() {
+			return Ok((0, false));
+		}
+
+		// Generating content
+		let content = crate::format!("{:x}\n", apic::get_irq_affinity(self.irq))?;
+
+		// Copying content to userspace buffer
+		let content_bytes = content.as_bytes();
+		let offset = min(offset, content_bytes.len() as u64) as usize;
+		let len = min(content_bytes.len() - offset, buff.len());
+		buff[..len].copy_from_slice(&content_bytes[offset..(offset + len)]);
+
+		let eof = offset + len >= content_bytes.len();
+		Ok((len as _, eof))
+	}
+
+	fn write(&mut self, _offset: u64, buff: &[u8]) -> Result<u64, Errno> {
+		let mask = core::str::from_utf8(buff)
+			.ok()
+			.and_then(|s| u32::from_str_radix(s.trim(), 16).ok())
+			.ok_or_else(|| errno!(EINVAL))?;
+		apic::set_irq_affinity(self.irq, mask)?;
+		Ok(buff.len() as _)
+	}
+
+	fn poll(&mut self, _mask: u32) -> Result<u32, Errno> {
+		Ok(0)
+	}
+}
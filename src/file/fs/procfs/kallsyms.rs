@@ -0,0 +1,105 @@
+//! The kallsyms node lists the symbols of the kernel, which allows profilers and tracers to
+//! resolve kernel addresses.
+//!
+//! Each line has the format `<address> <type> <name>`. The type is `T` for functions and `D` for
+//! data, in lowercase for local symbols. Weak symbols are noted `W` and `V` respectively.
+//!
+//! Addresses are replaced by zeros when the reader is not allowed to see them, according to
+//! `/proc/sys/kernel/kptr_restrict`.
+
+use crate::debug;
+use crate::elf;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::fs::kernfs::content::KernFSContent;
+use crate::file::fs::kernfs::node::KernFSNode;
+use crate::file::FileContent;
+use crate::file::Mode;
+use crate::process::Process;
+use crate::util::container::string::String;
+use crate::util::io::IO;
+use crate::util::DisplayableStr;
+use core::cmp::min;
+
+/// Returns the character representing the type of the symbol with the given ELF type and binding.
+///
+/// If the symbol is not to be listed, the function returns `None`.
+fn get_type_char(type_: u8, bind: u8) -> Option<char> {
+	let c = match (type_, bind) {
+		(elf::STT_FUNC, elf::STB_WEAK) => 'W',
+		(elf::STT_OBJECT, elf::STB_WEAK) => 'V',
+		(elf::STT_FUNC, _) => 'T',
+		(elf::STT_OBJECT, _) => 'D',
+		_ => return None,
+	};
+	if bind == elf::STB_LOCAL {
+		Some(c.to_ascii_lowercase())
+	} else {
+		Some(c)
+	}
+}
+
+/// Structure representing the kallsyms node.
+pub struct KAllSyms {}
+
+impl KernFSNode for KAllSyms {
+	fn get_mode(&self) -> Mode {
+		0o444
+	}
+
+	fn get_content(&mut self) -> EResult<KernFSContent<'_>> {
+		Ok(KernFSContent::Dynamic(FileContent::Regular))
+	}
+}
+
+impl IO for KAllSyms {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, offset: u64, buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+		if buff.is_empty() {
+			return Ok((0, false));
+		}
+
+		let show_addr = {
+			let proc_mutex = Process::current_assert();
+			let proc = proc_mutex.lock();
+			debug::can_show_kernel_addr(&proc.access_profile)
+		};
+
+		// Generating content
+		let mut content = String::new();
+		let mut res = Ok(());
+		debug::foreach_symbol(|sym, name| {
+			if name.is_empty() || !sym.is_defined() {
+				return true;
+			}
+			let Some(c) = get_type_char(sym.get_type(), sym.get_bind()) else {
+				return true;
+			};
+			let addr = if show_addr { sym.st_value } else { 0 };
+			res = crate::format!("{addr:08x} {c} {}\n", DisplayableStr(name))
+				.and_then(|s| content.push_str(s));
+			res.is_ok()
+		});
+		res?;
+
+		// Copying content to userspace buffer
+		let content_bytes = content.as_bytes();
+		let offset = min(offset, content_bytes.len() as u64) as usize;
+		let len = min(content_bytes.len() - offset, buff.len());
+		buff[..len].copy_from_slice(&content_bytes[offset..(offset + len)]);
+
+		let eof = offset + len >= content_bytes.len();
+		Ok((len as _, eof))
+	}
+
+	fn write(&mut self, _offset: u64, _buff: &[u8]) -> Result<u64, Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn poll(&mut self, _mask: u32) -> Result<u32, Errno> {
+		Ok(0)
+	}
+}
@@ -6,7 +6,11 @@ use crate::errno::Errno;
 use crate::file::fs::kernfs::content::KernFSContent;
 use crate::file::fs::kernfs::node::KernFSNode;
 use crate::file::FileContent;
+use crate::file::Mode;
 use crate::memory;
+use crate::memory::buddy;
+use crate::memory::malloc;
+use crate::memory::memtest;
 use crate::util::io::IO;
 use core::cmp::min;
 
@@ -14,6 +18,10 @@ use core::cmp::min;
 pub struct MemInfo {}
 
 impl KernFSNode for MemInfo {
+	fn get_mode(&self) -> Mode {
+		0o444
+	}
+
 	fn get_content(&mut self) -> EResult<KernFSContent<'_>> {
 		Ok(KernFSContent::Dynamic(FileContent::Regular))
 	}
@@ -30,15 +38,53 @@ impl IO for MemInfo {
 		}
 
 		// Generating content
-		let mem_info = memory::stats::MEM_INFO.lock();
-		let content = mem_info.to_string()?;
+		let (mem_total, mem_free) = {
+			let mem_info = memory::stats::MEM_INFO.lock();
+			(mem_info.mem_total, mem_info.mem_free)
+		};
+		// Retired pages are counted as free by statistics
+		let corrupted = memtest::retired_pages() * 4;
+		let mem_free = mem_free.saturating_sub(corrupted);
+		// Pages of the kernel zone are always mapped, unlike pages of the user zone
+		let zones = buddy::get_zones_info();
+		let zone_kib = |name: &str| {
+			zones
+				.iter()
+				.find(|z| z.name == name)
+				.map(|z| (z.pages * 4, z.free * 4))
+				.unwrap_or((0, 0))
+		};
+		let (high_total, high_free) = zone_kib("User");
+		let (low_total, low_free) = zone_kib("Kernel");
+		let kernel_alloc = malloc::allocated_bytes() / 1024;
+		let content = crate::format!(
+			"MemTotal: {mem_total} kB
+MemFree: {mem_free} kB
+MemAvailable: {mem_free} kB
+Buffers: 0 kB
+Cached: 0 kB
+SwapCached: 0 kB
+HighTotal: {high_total} kB
+HighFree: {high_free} kB
+LowTotal: {low_total} kB
+LowFree: {low_free} kB
+SwapTotal: 0 kB
+SwapFree: 0 kB
+Shmem: 0 kB
+Slab: {kernel_alloc} kB
+SReclaimable: 0 kB
+SUnreclaim: {kernel_alloc} kB
+HardwareCorrupted: {corrupted} kB
+"
+		)?;
 
 		// Copying content to userspace buffer
 		let content_bytes = content.as_bytes();
-		let len = min((content_bytes.len() as u64 - offset) as usize, buff.len());
-		buff[..len].copy_from_slice(&content_bytes[(offset as usize)..(offset as usize + len)]);
+		let offset = min(offset, content_bytes.len() as u64) as usize;
+		let len = min(content_bytes.len() - offset, buff.len());
+		buff[..len].copy_from_slice(&content_bytes[offset..(offset + len)]);
 
-		let eof = (offset + len as u64) >= content_bytes.len() as u64;
+		let eof = offset + len >= content_bytes.len();
 		Ok((len as _, eof))
 	}
 
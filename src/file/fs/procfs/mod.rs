@@ -1,23 +1,35 @@
 //! The procfs is a virtual filesystem which provides informations about
 //! processes.
 
+mod config_gz;
+mod irq_dir;
+mod kallsyms;
 mod mem_info;
+mod modules;
+mod net_dir;
 mod proc_dir;
 mod self_link;
+mod stat;
 mod sys_dir;
+mod sysrq_trigger;
 mod uptime;
 mod version;
+mod zone_info;
 
 use super::kernfs;
 use super::kernfs::node::DummyKernFSNode;
 use super::kernfs::KernFS;
+use super::options::MountOptions;
 use super::Filesystem;
 use super::FilesystemType;
+use crate::errno;
 use crate::errno::AllocError;
 use crate::errno::AllocResult;
+use crate::errno::EResult;
 use crate::errno::Errno;
 use crate::file::fs::Statfs;
 use crate::file::path::Path;
+use crate::file::perm::AccessProfile;
 use crate::file::perm::Gid;
 use crate::file::perm::Uid;
 use crate::file::DirEntry;
@@ -36,12 +48,53 @@ use crate::util::io::IO;
 use crate::util::lock::Mutex;
 use crate::util::ptr::arc::Arc;
 use core::any::Any;
+use config_gz::ConfigGz;
+use irq_dir::IrqDir;
+use kallsyms::KAllSyms;
 use mem_info::MemInfo;
+use modules::Modules;
+use net_dir::NetDir;
+use proc_dir::fd;
 use proc_dir::ProcDir;
 use self_link::SelfNode;
+use stat::Stat;
 use sys_dir::SysDir;
+use sysrq_trigger::SysRqTrigger;
 use uptime::Uptime;
 use version::Version;
+use zone_info::ZoneInfo;
+
+/// The filesystem's magic number.
+const PROC_SUPER_MAGIC: u32 = 0x9fa0;
+
+/// The number of clock ticks per second in which times are expressed, as seen by userspace.
+const USER_HZ: u64 = 100;
+
+/// Restriction of the access to the directories of processes, set by the `hidepid` mount option.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HidePid {
+	/// Everyone can access every process directory.
+	Off,
+	/// Users can see every process directory, but can only access the content of their own
+	/// processes' directories.
+	NoAccess,
+	/// Users cannot see the directories of other users' processes.
+	Invisible,
+}
+
+impl HidePid {
+	/// Parses the value `s` of the `hidepid` mount option.
+	///
+	/// If the value is invalid, the function returns `None`.
+	fn parse(s: &[u8]) -> Option<Self> {
+		match s {
+			b"0" | b"off" => Some(Self::Off),
+			b"1" | b"noaccess" => Some(Self::NoAccess),
+			b"2" | b"invisible" => Some(Self::Invisible),
+			_ => None,
+		}
+	}
+}
 
 /// Structure representing the procfs.
 ///
@@ -51,20 +104,58 @@ pub struct ProcFS {
 	fs: KernFS,
 	/// The list of registered processes with their directory's inode.
 	procs: HashMap<Pid, INode>,
+	/// The restriction of the access to processes' directories.
+	hidepid: HidePid,
 }
 
 impl ProcFS {
 	/// Creates a new instance.
 	///
-	/// `readonly` tells whether the filesystem is readonly.
-	pub fn new(readonly: bool) -> Result<Self, Errno> {
+	/// Arguments:
+	/// - `readonly` tells whether the filesystem is readonly.
+	/// - `hidepid` is the restriction of the access to processes' directories.
+	pub fn new(readonly: bool, hidepid: HidePid) -> Result<Self, Errno> {
 		let mut fs = Self {
 			fs: KernFS::new(b"procfs".try_into()?, readonly)?,
 			procs: HashMap::new(),
+			hidepid,
 		};
 
 		let mut entries = HashMap::new();
 
+		// Create /proc/config.gz
+		let node = ConfigGz {};
+		let inode = fs.fs.add_node(Box::new(node)?)?;
+		entries.insert(
+			b"config.gz".try_into()?,
+			DirEntry {
+				inode,
+				entry_type: FileType::Regular,
+			},
+		)?;
+
+		// Create /proc/irq
+		let node = IrqDir::new(&mut fs.fs)?;
+		let inode = fs.fs.add_node(Box::new(node)?)?;
+		entries.insert(
+			b"irq".try_into()?,
+			DirEntry {
+				inode,
+				entry_type: FileType::Directory,
+			},
+		)?;
+
+		// Create /proc/kallsyms
+		let node = KAllSyms {};
+		let inode = fs.fs.add_node(Box::new(node)?)?;
+		entries.insert(
+			b"kallsyms".try_into()?,
+			DirEntry {
+				inode,
+				entry_type: FileType::Regular,
+			},
+		)?;
+
 		// Create /proc/meminfo
 		let node = MemInfo {};
 		let inode = fs.fs.add_node(Box::new(node)?)?;
@@ -76,6 +167,17 @@ impl ProcFS {
 			},
 		)?;
 
+		// Create /proc/modules
+		let node = Modules {};
+		let inode = fs.fs.add_node(Box::new(node)?)?;
+		entries.insert(
+			b"modules".try_into()?,
+			DirEntry {
+				inode,
+				entry_type: FileType::Regular,
+			},
+		)?;
+
 		// Create /proc/mounts
 		let node =
 			DummyKernFSNode::new(0o777, 0, 0, FileContent::Link(b"self/mounts".try_into()?));
@@ -88,6 +190,17 @@ impl ProcFS {
 			},
 		)?;
 
+		// Create /proc/net
+		let node = NetDir::new(&mut fs.fs)?;
+		let inode = fs.fs.add_node(Box::new(node)?)?;
+		entries.insert(
+			b"net".try_into()?,
+			DirEntry {
+				inode,
+				entry_type: FileType::Directory,
+			},
+		)?;
+
 		// Create /proc/self
 		let node = SelfNode {};
 		let inode = fs.fs.add_node(Box::new(node)?)?;
@@ -99,6 +212,17 @@ impl ProcFS {
 			},
 		)?;
 
+		// Create /proc/stat
+		let node = Stat {};
+		let inode = fs.fs.add_node(Box::new(node)?)?;
+		entries.insert(
+			b"stat".try_into()?,
+			DirEntry {
+				inode,
+				entry_type: FileType::Regular,
+			},
+		)?;
+
 		// Create /proc/sys
 		let node = SysDir::new(&mut fs.fs)?;
 		let inode = fs.fs.add_node(Box::new(node)?)?;
@@ -110,6 +234,17 @@ impl ProcFS {
 			},
 		)?;
 
+		// Create /proc/sysrq-trigger
+		let node = SysRqTrigger {};
+		let inode = fs.fs.add_node(Box::new(node)?)?;
+		entries.insert(
+			b"sysrq-trigger".try_into()?,
+			DirEntry {
+				inode,
+				entry_type: FileType::Regular,
+			},
+		)?;
+
 		// Create /proc/uptime
 		let node = Uptime {};
 		let inode = fs.fs.add_node(Box::new(node)?)?;
@@ -132,6 +267,17 @@ impl ProcFS {
 			},
 		)?;
 
+		// Create /proc/zoneinfo
+		let node = ZoneInfo {};
+		let inode = fs.fs.add_node(Box::new(node)?)?;
+		entries.insert(
+			b"zoneinfo".try_into()?,
+			DirEntry {
+				inode,
+				entry_type: FileType::Regular,
+			},
+		)?;
+
 		// Add the root node
 		let root_node = DummyKernFSNode::new(0o555, 0, 0, FileContent::Directory(entries));
 		fs.fs.set_root(Box::new(root_node)?)?;
@@ -218,7 +364,9 @@ impl Filesystem for ProcFS {
 	}
 
 	fn get_stat(&self, io: &mut dyn IO) -> Result<Statfs, Errno> {
-		self.fs.get_stat(io)
+		let mut stat = self.fs.get_stat(io)?;
+		stat.f_type = PROC_SUPER_MAGIC;
+		Ok(stat)
 	}
 
 	fn get_root_inode(&self, io: &mut dyn IO) -> Result<INode, Errno> {
@@ -235,6 +383,9 @@ impl Filesystem for ProcFS {
 	}
 
 	fn load_file(&mut self, io: &mut dyn IO, inode: INode, name: String) -> Result<File, Errno> {
+		if let Some((pid, fd)) = fd::parse_fd_inode(inode) {
+			return fd::load_link(pid, fd, inode);
+		}
 		self.fs.load_file(io, inode, name)
 	}
 
@@ -293,6 +444,40 @@ impl Filesystem for ProcFS {
 	) -> Result<(), Errno> {
 		self.fs.write_node(io, inode, off, buf)
 	}
+
+	fn get_magic_link(
+		&self,
+		inode: INode,
+		ap: &AccessProfile,
+	) -> EResult<Option<Arc<Mutex<File>>>> {
+		let Some((pid, fd)) = fd::parse_fd_inode(inode) else {
+			return Ok(None);
+		};
+		fd::resolve_link(pid, fd, ap).map(Some)
+	}
+
+	fn check_lookup(&self, parent: INode, inode: INode, ap: &AccessProfile) -> EResult<()> {
+		if self.hidepid == HidePid::Off || ap.is_privileged() {
+			return Ok(());
+		}
+		// The process directory the file is in, or is
+		let (dir, is_dir) = if parent == kernfs::ROOT_INODE {
+			(inode, true)
+		} else {
+			(parent, false)
+		};
+		if !self.procs.iter().any(|(_, i)| *i == dir) {
+			return Ok(());
+		}
+		if self.fs.get_node(dir)?.get_uid() == ap.get_euid() {
+			return Ok(());
+		}
+		match self.hidepid {
+			HidePid::Invisible => Err(errno!(ENOENT)),
+			_ if is_dir => Ok(()),
+			_ => Err(errno!(EPERM)),
+		}
+	}
 }
 
 /// Structure representing the procfs file system type.
@@ -312,7 +497,13 @@ impl FilesystemType for ProcFsType {
 		_io: &mut dyn IO,
 		_mountpath: Path,
 		readonly: bool,
+		options: &MountOptions,
 	) -> Result<Arc<Mutex<dyn Filesystem>>, Errno> {
-		Ok(Arc::new(Mutex::new(ProcFS::new(readonly)?))?)
+		options.check(&[b"hidepid"])?;
+		let hidepid = match options.get(b"hidepid")? {
+			Some(hidepid) => HidePid::parse(hidepid).ok_or_else(|| errno!(EINVAL))?,
+			None => HidePid::Off,
+		};
+		Ok(Arc::new(Mutex::new(ProcFS::new(readonly, hidepid)?))?)
 	}
 }
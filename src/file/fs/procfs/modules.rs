@@ -0,0 +1,125 @@
+//! The modules node lists the kernel modules that are loaded.
+//!
+//! Each line has the format `<name> <size> <refcount> <users> <state> <address> [(<taints>)]`,
+//! where `users` is the list of modules depending on the module, or `-` if none.
+//!
+//! Addresses are replaced by zeros when the reader is not allowed to see them, according to
+//! `/proc/sys/kernel/kptr_restrict`.
+
+use crate::debug;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::fs::kernfs::content::KernFSContent;
+use crate::file::fs::kernfs::node::KernFSNode;
+use crate::file::FileContent;
+use crate::file::Mode;
+use crate::module;
+use crate::module::Module;
+use crate::panic::DisplayTaint;
+use crate::process::Process;
+use crate::util::container::string::String;
+use crate::util::io::IO;
+use crate::util::DisplayableStr;
+use core::cmp::min;
+
+/// Appends the line describing `module` to `content`.
+///
+/// Arguments:
+/// - `users` is an iterator over the modules depending on `module`.
+/// - `show_addr` tells whether the address of the module is shown.
+fn push_module_line(
+	content: &mut String,
+	module: &Module,
+	users: &mut dyn Iterator<Item = &Module>,
+	show_addr: bool,
+) -> EResult<()> {
+	let s = crate::format!(
+		"{} {} {} ",
+		DisplayableStr(module.get_name()),
+		module.get_size(),
+		module.get_refcount()
+	)?;
+	content.push_str(s)?;
+
+	let mut no_user = true;
+	for m in users {
+		content.push_str(m.get_name())?;
+		content.push_str(b",")?;
+		no_user = false;
+	}
+	if no_user {
+		content.push_str(b"-")?;
+	}
+
+	let addr = if show_addr {
+		module.get_address() as usize
+	} else {
+		0
+	};
+	let s = crate::format!(" {} {addr:#010x}", module.get_state())?;
+	content.push_str(s)?;
+	if module.get_taints() != 0 {
+		let s = crate::format!(" ({})", DisplayTaint(module.get_taints()))?;
+		content.push_str(s)?;
+	}
+	content.push_str(b"\n")?;
+	Ok(())
+}
+
+/// Structure representing the modules node.
+pub struct Modules {}
+
+impl KernFSNode for Modules {
+	fn get_mode(&self) -> Mode {
+		0o444
+	}
+
+	fn get_content(&mut self) -> EResult<KernFSContent<'_>> {
+		Ok(KernFSContent::Dynamic(FileContent::Regular))
+	}
+}
+
+impl IO for Modules {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, offset: u64, buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+		if buff.is_empty() {
+			return Ok((0, false));
+		}
+
+		let show_addr = {
+			let proc_mutex = Process::current_assert();
+			let proc = proc_mutex.lock();
+			debug::can_show_kernel_addr(&proc.access_profile)
+		};
+
+		// Generating content
+		let mut content = String::new();
+		let mut res = Ok(());
+		module::foreach(|m, users| {
+			if res.is_ok() {
+				res = push_module_line(&mut content, m, users, show_addr);
+			}
+		});
+		res?;
+
+		// Copying content to userspace buffer
+		let content_bytes = content.as_bytes();
+		let offset = min(offset, content_bytes.len() as u64) as usize;
+		let len = min(content_bytes.len() - offset, buff.len());
+		buff[..len].copy_from_slice(&content_bytes[offset..(offset + len)]);
+
+		let eof = offset + len >= content_bytes.len();
+		Ok((len as _, eof))
+	}
+
+	fn write(&mut self, _offset: u64, _buff: &[u8]) -> Result<u64, Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn poll(&mut self, _mask: u32) -> Result<u32, Errno> {
+		Ok(0)
+	}
+}
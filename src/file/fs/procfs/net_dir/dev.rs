@@ -0,0 +1,70 @@
+//! The `dev` node lists the network interfaces of the system along with their statistics.
+
+use super::read_content;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::fs::kernfs::content::KernFSContent;
+use crate::file::fs::kernfs::node::KernFSNode;
+use crate::file::FileContent;
+use crate::file::Mode;
+use crate::net;
+use crate::util::container::string::String;
+use crate::util::io::IO;
+
+/// The `dev` node.
+pub struct Dev {}
+
+impl KernFSNode for Dev {
+	fn get_mode(&self) -> Mode {
+		0o444
+	}
+
+	fn get_content(&mut self) -> EResult<KernFSContent<'_>> {
+		Ok(KernFSContent::Dynamic(FileContent::Regular))
+	}
+}
+
+impl IO for Dev {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, offset: u64, buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+		let mut content = String::try_from(
+			b"Inter-|   Receive                                                |  Transmit\n \
+			  face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets \
+			  errs drop fifo colls carrier compressed\n",
+		)?;
+		let interfaces = net::INTERFACES.lock();
+		for (name, iface) in interfaces.iter() {
+			let stats = iface.lock().get_stats();
+			// Right-align the name
+			for _ in name.len()..6 {
+				content.push(b' ')?;
+			}
+			content.push_str(name)?;
+			let line = crate::format!(
+				": {:7} {:7} {:4} {:4}    0     0          0         0 {:8} {:7} {:4} {:4}    0     \
+				 0       0          0\n",
+				stats.rx_bytes,
+				stats.rx_packets,
+				stats.rx_errors,
+				stats.rx_dropped,
+				stats.tx_bytes,
+				stats.tx_packets,
+				stats.tx_errors,
+				stats.tx_dropped,
+			)?;
+			content.push_str(line)?;
+		}
+		Ok(read_content(content.as_bytes(), offset, buff))
+	}
+
+	fn write(&mut self, _offset: u64, _buff: &[u8]) -> Result<u64, Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn poll(&mut self, _mask: u32) -> Result<u32, Errno> {
+		Err(errno!(EINVAL))
+	}
+}
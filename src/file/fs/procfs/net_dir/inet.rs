@@ -0,0 +1,114 @@
+//! The `tcp` and `udp` nodes list the Internet sockets of the system.
+
+use super::foreach_socket;
+use super::hex_addr;
+use super::read_content;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::buffer::socket::SocketState;
+use crate::file::fs::kernfs::content::KernFSContent;
+use crate::file::fs::kernfs::node::KernFSNode;
+use crate::file::FileContent;
+use crate::file::Mode;
+use crate::net::sockaddr::SockAddr;
+use crate::net::Address;
+use crate::net::SocketDomain;
+use crate::net::SocketType;
+use crate::util::container::string::String;
+use crate::util::io::IO;
+
+/// Returns the address and port to be displayed for the sockaddr structure `buf`.
+fn addr_port(buf: &[u8]) -> (u32, u16) {
+	match SockAddr::from_bytes(buf) {
+		Some(SockAddr {
+			port,
+			addr: Address::IPv4(addr),
+		}) => (hex_addr(addr), port),
+		_ => (0, 0),
+	}
+}
+
+/// A node listing the IPv4 sockets of a given type.
+pub struct Inet {
+	/// The type of the listed sockets.
+	type_: SocketType,
+}
+
+impl Inet {
+	/// Creates the node listing TCP sockets.
+	pub fn tcp() -> Self {
+		Self {
+			type_: SocketType::SockStream,
+		}
+	}
+
+	/// Creates the node listing UDP sockets.
+	pub fn udp() -> Self {
+		Self {
+			type_: SocketType::SockDgram,
+		}
+	}
+
+	/// Returns the value of the `st` column for the given state.
+	fn state_id(&self, state: SocketState) -> u8 {
+		match (self.type_, state) {
+			(_, SocketState::Connected) => 0x01,
+			(SocketType::SockStream, SocketState::Connecting) => 0x02,
+			(SocketType::SockStream, SocketState::Closed) => 0x08,
+			(SocketType::SockStream, SocketState::Listening) => 0x0a,
+			_ => 0x07,
+		}
+	}
+}
+
+impl KernFSNode for Inet {
+	fn get_mode(&self) -> Mode {
+		0o444
+	}
+
+	fn get_content(&mut self) -> EResult<KernFSContent<'_>> {
+		Ok(KernFSContent::Dynamic(FileContent::Regular))
+	}
+}
+
+impl IO for Inet {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, offset: u64, buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+		let mut content = String::try_from(
+			b"  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  \
+			  timeout inode\n",
+		)?;
+		let mut sl = 0;
+		foreach_socket(|inode, sock| {
+			let desc = sock.desc();
+			if desc.domain != SocketDomain::AfInet || desc.type_ != self.type_ {
+				return Ok(());
+			}
+			let (local_addr, local_port) = addr_port(sock.sockname());
+			let (rem_addr, rem_port) = addr_port(sock.peername());
+			let line = crate::format!(
+				"{sl:4}: {local_addr:08X}:{local_port:04X} {rem_addr:08X}:{rem_port:04X} {st:02X} \
+				 {tx:08X}:{rx:08X} 00:00000000 00000000     0        0 {inode} 1 \
+				 0000000000000000\n",
+				st = self.state_id(sock.state()),
+				tx = sock.transmit_queue_len(),
+				rx = sock.receive_queue_len(),
+			)?;
+			content.push_str(line)?;
+			sl += 1;
+			Ok(())
+		})?;
+		Ok(read_content(content.as_bytes(), offset, buff))
+	}
+
+	fn write(&mut self, _offset: u64, _buff: &[u8]) -> Result<u64, Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn poll(&mut self, _mask: u32) -> Result<u32, Errno> {
+		Err(errno!(EINVAL))
+	}
+}
@@ -0,0 +1,138 @@
+//! The `net` directory contains informations about the network stack: sockets, interfaces and
+//! routes.
+
+mod dev;
+mod inet;
+mod route;
+mod unix;
+
+use super::kernfs::KernFS;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::buffer;
+use crate::file::buffer::socket::Socket;
+use crate::file::fs::kernfs::content::KernFSContent;
+use crate::file::fs::kernfs::node::KernFSNode;
+use crate::file::perm::Gid;
+use crate::file::perm::Uid;
+use crate::file::DirEntry;
+use crate::file::FileContent;
+use crate::file::FileType;
+use crate::file::INode;
+use crate::file::Mode;
+use crate::util::boxed::Box;
+use crate::util::container::hashmap::HashMap;
+use crate::util::io::IO;
+use core::any::Any;
+use core::cmp::min;
+use dev::Dev;
+use inet::Inet;
+use route::Route;
+use unix::Unix;
+
+/// Copies the part of `content` starting at `offset` into `buff`.
+///
+/// The function returns the number of bytes copied and whether the end of the content has been
+/// reached.
+fn read_content(content: &[u8], offset: u64, buff: &mut [u8]) -> (u64, bool) {
+	let offset = min(offset, content.len() as u64) as usize;
+	let len = min(content.len() - offset, buff.len());
+	buff[..len].copy_from_slice(&content[offset..(offset + len)]);
+
+	let eof = offset + len >= content.len();
+	(len as _, eof)
+}
+
+/// Calls `f` on each socket of the system, along with its inode.
+fn foreach_socket<F: FnMut(INode, &Socket) -> EResult<()>>(mut f: F) -> EResult<()> {
+	for loc in buffer::list()? {
+		// The buffer may have been released in the meantime
+		let Some(buff) = buffer::get(&loc) else {
+			continue;
+		};
+		let buff = buff.lock();
+		if let Some(sock) = (&*buff as &dyn Any).downcast_ref::<Socket>() {
+			f(loc.get_inode(), sock)?;
+		}
+	}
+	Ok(())
+}
+
+/// Returns the value of the IPv4 address `addr` as displayed in network files.
+fn hex_addr(addr: [u8; 4]) -> u32 {
+	// Addresses are displayed as a number read from memory in network byte order
+	u32::from_ne_bytes(addr)
+}
+
+/// Structure representing the `net` directory.
+pub struct NetDir {
+	/// The content of the directory. This will always be a Directory variant.
+	content: FileContent,
+}
+
+impl NetDir {
+	/// Creates a new instance.
+	///
+	/// The function adds every nodes to the given kernfs `fs`.
+	pub fn new(fs: &mut KernFS) -> Result<Self, Errno> {
+		let mut entries = HashMap::new();
+
+		let nodes: [(&[u8], Box<dyn KernFSNode>); 5] = [
+			(b"dev", Box::new(Dev {})?),
+			(b"route", Box::new(Route {})?),
+			(b"tcp", Box::new(Inet::tcp())?),
+			(b"udp", Box::new(Inet::udp())?),
+			(b"unix", Box::new(Unix {})?),
+		];
+		for (name, node) in nodes {
+			let inode = fs.add_node(node)?;
+			entries.insert(
+				name.try_into()?,
+				DirEntry {
+					inode,
+					entry_type: FileType::Regular,
+				},
+			)?;
+		}
+
+		Ok(Self {
+			content: FileContent::Directory(entries),
+		})
+	}
+}
+
+impl KernFSNode for NetDir {
+	fn get_mode(&self) -> Mode {
+		0o555
+	}
+
+	fn get_uid(&self) -> Uid {
+		0
+	}
+
+	fn get_gid(&self) -> Gid {
+		0
+	}
+
+	fn get_content(&mut self) -> EResult<KernFSContent<'_>> {
+		Ok(KernFSContent::Owned(&mut self.content))
+	}
+}
+
+impl IO for NetDir {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, _offset: u64, _buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn write(&mut self, _offset: u64, _buff: &[u8]) -> Result<u64, Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn poll(&mut self, _mask: u32) -> Result<u32, Errno> {
+		Err(errno!(EINVAL))
+	}
+}
@@ -0,0 +1,98 @@
+//! The `route` node lists the IPv4 routes of the routing table.
+
+use super::hex_addr;
+use super::read_content;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::fs::kernfs::content::KernFSContent;
+use crate::file::fs::kernfs::node::KernFSNode;
+use crate::file::FileContent;
+use crate::file::Mode;
+use crate::net;
+use crate::net::Address;
+use crate::util::container::string::String;
+use crate::util::io::IO;
+
+/// Route flag: the route is usable.
+const RTF_UP: u16 = 0x1;
+/// Route flag: the destination is reached through a gateway.
+const RTF_GATEWAY: u16 = 0x2;
+
+/// Returns the subnet mask for the given prefix length, as displayed in network files.
+fn mask(prefix_len: u8) -> u32 {
+	let mask = u32::MAX
+		.checked_shl(32u32.saturating_sub(prefix_len as _))
+		.unwrap_or(0);
+	hex_addr(mask.to_be_bytes())
+}
+
+/// The `route` node.
+pub struct Route {}
+
+impl KernFSNode for Route {
+	fn get_mode(&self) -> Mode {
+		0o444
+	}
+
+	fn get_content(&mut self) -> EResult<KernFSContent<'_>> {
+		Ok(KernFSContent::Dynamic(FileContent::Regular))
+	}
+}
+
+impl IO for Route {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, offset: u64, buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+		let mut content = String::try_from(
+			b"Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\t\tMTU\tWindow\tIRTT\n",
+		)?;
+		let routing_table = net::ROUTING_TABLE.lock();
+		for route in routing_table.iter() {
+			let Address::IPv4(gateway) = route.get_gateway() else {
+				continue;
+			};
+			// A route without destination is the default route
+			let (dst, mask) = match route.get_destination() {
+				Some(dst) => match dst.addr {
+					Address::IPv4(addr) => (hex_addr(addr), mask(dst.subnet_mask)),
+					Address::IPv6(_) => continue,
+				},
+				None => (0, 0),
+			};
+			let gw = hex_addr(*gateway);
+			let mut flags = RTF_UP;
+			if gw != 0 {
+				flags |= RTF_GATEWAY;
+			}
+			content.push_str(route.get_iface())?;
+			let line = crate::format!(
+				"\t{dst:08X}\t{gw:08X}\t{flags:04X}\t0\t0\t{metric}\t{mask:08X}\t0\t0\t0\n",
+				metric = route.get_metric(),
+			)?;
+			content.push_str(line)?;
+		}
+		Ok(read_content(content.as_bytes(), offset, buff))
+	}
+
+	fn write(&mut self, _offset: u64, _buff: &[u8]) -> Result<u64, Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn poll(&mut self, _mask: u32) -> Result<u32, Errno> {
+		Err(errno!(EINVAL))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test_case]
+	fn route_mask() {
+		assert_eq!(mask(0), 0);
+		assert_eq!(mask(24), u32::from_ne_bytes([255, 255, 255, 0]));
+		assert_eq!(mask(32), u32::MAX);
+	}
+}
@@ -0,0 +1,81 @@
+//! The `unix` node lists the Unix domain sockets of the system.
+
+use super::foreach_socket;
+use super::read_content;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::buffer::socket::SocketState;
+use crate::file::fs::kernfs::content::KernFSContent;
+use crate::file::fs::kernfs::node::KernFSNode;
+use crate::file::FileContent;
+use crate::file::Mode;
+use crate::net::SocketDomain;
+use crate::util::container::string::String;
+use crate::util::io::IO;
+
+/// Flag telling the socket is listening for connections.
+const SO_ACCEPTCON: u32 = 0x10000;
+
+/// The `unix` node.
+pub struct Unix {}
+
+impl KernFSNode for Unix {
+	fn get_mode(&self) -> Mode {
+		0o444
+	}
+
+	fn get_content(&mut self) -> EResult<KernFSContent<'_>> {
+		Ok(KernFSContent::Dynamic(FileContent::Regular))
+	}
+}
+
+impl IO for Unix {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, offset: u64, buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+		let mut content =
+			String::try_from(b"Num       RefCount Protocol Flags    Type St Inode Path\n")?;
+		foreach_socket(|inode, sock| {
+			let desc = sock.desc();
+			if desc.domain != SocketDomain::AfUnix {
+				return Ok(());
+			}
+			let state = sock.state();
+			let flags = if state == SocketState::Listening {
+				SO_ACCEPTCON
+			} else {
+				0
+			};
+			let st = match state {
+				SocketState::Connecting => 2,
+				SocketState::Connected => 3,
+				_ => 1,
+			};
+			let line = crate::format!(
+				"0000000000000000: 00000002 00000000 {flags:08X} {type_:04X} {st:02X} {inode:5}",
+				type_ = desc.type_.get_id(),
+			)?;
+			content.push_str(line)?;
+			// The path follows the address family
+			let path = sock.sockname().get(2..).unwrap_or_default();
+			let path_len = path.iter().position(|b| *b == 0).unwrap_or(path.len());
+			if path_len > 0 {
+				content.push(b' ')?;
+				content.push_str(&path[..path_len])?;
+			}
+			content.push(b'\n')?;
+			Ok(())
+		})?;
+		Ok(read_content(content.as_bytes(), offset, buff))
+	}
+
+	fn write(&mut self, _offset: u64, _buff: &[u8]) -> Result<u64, Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn poll(&mut self, _mask: u32) -> Result<u32, Errno> {
+		Err(errno!(EINVAL))
+	}
+}
@@ -9,9 +9,9 @@ use crate::file::perm::Gid;
 use crate::file::perm::Uid;
 use crate::file::FileContent;
 use crate::file::Mode;
+use crate::process::mem_space::MemSpace;
 use crate::process::pid::Pid;
 use crate::process::Process;
-use crate::util::container::string::String;
 use crate::util::io::IO;
 use core::cmp::min;
 
@@ -58,21 +58,17 @@ impl IO for Cmdline {
 		}
 
 		let proc_mutex = Process::get_by_pid(self.pid).ok_or_else(|| errno!(ENOENT))?;
-		let proc = proc_mutex.lock();
-
-		// Generating content
-		let mut content = String::new();
-		for a in proc.argv.iter() {
-			content.push_str(a)?;
-			content.push(b'\0')?;
-		}
+		let content = {
+			let proc = proc_mutex.lock();
+			super::read_proc_mem(&proc, MemSpace::get_args)?
+		};
 
 		// Copying content to userspace buffer
-		let content_bytes = content.as_bytes();
-		let len = min((content_bytes.len() as u64 - offset) as usize, buff.len());
-		buff[..len].copy_from_slice(&content_bytes[(offset as usize)..(offset as usize + len)]);
+		let offset = min(offset, content.len() as u64);
+		let len = min((content.len() as u64 - offset) as usize, buff.len());
+		buff[..len].copy_from_slice(&content[(offset as usize)..(offset as usize + len)]);
 
-		let eof = (offset + len as u64) >= content_bytes.len() as u64;
+		let eof = (offset + len as u64) >= content.len() as u64;
 		Ok((len as _, eof))
 	}
 
@@ -0,0 +1,96 @@
+//! The environ node allows to retrieve the environment variables of the process.
+//!
+//! Since environment variables may contain sensitive data, they can only be read by a process
+//! that is allowed to read the memory of the target process.
+
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::fs::kernfs::content::KernFSContent;
+use crate::file::fs::kernfs::node::KernFSNode;
+use crate::file::perm::Gid;
+use crate::file::perm::Uid;
+use crate::file::FileContent;
+use crate::file::Mode;
+use crate::process::mem_space::MemSpace;
+use crate::process::pid::Pid;
+use crate::process::Process;
+use crate::util::io::IO;
+use core::cmp::min;
+
+/// Structure representing the environ node of the procfs.
+pub struct Environ {
+	/// The PID of the process.
+	pub pid: Pid,
+}
+
+impl KernFSNode for Environ {
+	fn get_mode(&self) -> Mode {
+		0o400
+	}
+
+	fn get_uid(&self) -> Uid {
+		if let Some(proc_mutex) = Process::get_by_pid(self.pid) {
+			proc_mutex.lock().access_profile.get_euid()
+		} else {
+			0
+		}
+	}
+
+	fn get_gid(&self) -> Gid {
+		if let Some(proc_mutex) = Process::get_by_pid(self.pid) {
+			proc_mutex.lock().access_profile.get_egid()
+		} else {
+			0
+		}
+	}
+
+	fn get_content(&mut self) -> EResult<KernFSContent<'_>> {
+		Ok(KernFSContent::Dynamic(FileContent::Regular))
+	}
+}
+
+impl IO for Environ {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, offset: u64, buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+		if buff.is_empty() {
+			return Ok((0, false));
+		}
+
+		let cur_mutex = Process::current_assert();
+		let (cur_pid, ap) = {
+			let cur = cur_mutex.lock();
+			(cur.pid, cur.access_profile)
+		};
+		let content = if cur_pid == self.pid {
+			let proc = cur_mutex.lock();
+			super::read_proc_mem(&proc, MemSpace::get_env)?
+		} else {
+			let proc_mutex = Process::get_by_pid(self.pid).ok_or_else(|| errno!(ENOENT))?;
+			let proc = proc_mutex.lock();
+			if !ap.can_read_memory(&proc) {
+				return Err(errno!(EACCES));
+			}
+			super::read_proc_mem(&proc, MemSpace::get_env)?
+		};
+
+		// Copying content to userspace buffer
+		let offset = min(offset, content.len() as u64);
+		let len = min((content.len() as u64 - offset) as usize, buff.len());
+		buff[..len].copy_from_slice(&content[(offset as usize)..(offset as usize + len)]);
+
+		let eof = (offset + len as u64) >= content.len() as u64;
+		Ok((len as _, eof))
+	}
+
+	fn write(&mut self, _offset: u64, _buff: &[u8]) -> Result<u64, Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn poll(&mut self, _mask: u32) -> Result<u32, Errno> {
+		// TODO
+		todo!();
+	}
+}
@@ -0,0 +1,198 @@
+//! This module implements the `fd` directory, which contains a link to the file of each file
+//! descriptor of the process.
+//!
+//! The links are magic: besides their path, they resolve directly to the open file, which allows
+//! to reopen files that cannot be reached through a path, such as pipes or sockets.
+//!
+//! Since file descriptors come and go, links are not kernfs nodes. Instead, their inode encodes
+//! the PID of the process and the ID of the file descriptor.
+
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::fd::FileDescriptor;
+use crate::file::fs::kernfs::content::KernFSContent;
+use crate::file::fs::kernfs::node::KernFSNode;
+use crate::file::perm::AccessProfile;
+use crate::file::perm::Gid;
+use crate::file::perm::Uid;
+use crate::file::DirEntry;
+use crate::file::File;
+use crate::file::FileContent;
+use crate::file::FileLocation;
+use crate::file::FileType;
+use crate::file::INode;
+use crate::file::Mode;
+use crate::process::pid::Pid;
+use crate::process::Process;
+use crate::util::container::hashmap::HashMap;
+use crate::util::container::string::String;
+use crate::util::io::IO;
+use crate::util::lock::Mutex;
+use crate::util::ptr::arc::Arc;
+
+/// The bit set on the inodes of links.
+const FD_INODE_FLAG: INode = 1 << 63;
+
+/// Returns the inode of the link to the file descriptor `fd` of the process `pid`.
+fn fd_inode(pid: Pid, fd: u32) -> INode {
+	FD_INODE_FLAG | ((pid as INode) << 32) | fd as INode
+}
+
+/// Returns the PID of the process and the ID of the file descriptor from the inode of a link.
+///
+/// If the inode is not the one of a link, the function returns `None`.
+pub fn parse_fd_inode(inode: INode) -> Option<(Pid, u32)> {
+	if inode & FD_INODE_FLAG == 0 {
+		return None;
+	}
+	Some(((inode >> 32) as Pid, inode as u32))
+}
+
+/// Executes `f` with the file descriptor `fd` of the process `pid`.
+///
+/// If the process or the file descriptor doesn't exist, the function returns
+/// [`crate::errno::ENOENT`].
+fn with_fd<R, F: FnOnce(&Process, &FileDescriptor) -> EResult<R>>(
+	pid: Pid,
+	fd: u32,
+	f: F,
+) -> EResult<R> {
+	let proc_mutex = Process::get_by_pid(pid).ok_or_else(|| errno!(ENOENT))?;
+	let proc = proc_mutex.lock();
+	let fds_mutex = proc.get_fds().ok_or_else(|| errno!(ENOENT))?.clone();
+	let fds = fds_mutex.lock();
+	let fd = fds.get_fd(fd).ok_or_else(|| errno!(ENOENT))?;
+	f(&proc, fd)
+}
+
+/// Loads the link to the file descriptor `fd` of the process `pid`.
+///
+/// `inode` is the inode of the link.
+pub fn load_link(pid: Pid, fd: u32, inode: INode) -> EResult<File> {
+	let (uid, gid, mode, target) = with_fd(pid, fd, |proc, fd| {
+		let open_file = fd.get_open_file().lock();
+		// The permissions of the link reflect the access mode of the open file
+		let mut mode = 0o100;
+		if open_file.can_read() {
+			mode |= 0o400;
+		}
+		if open_file.can_write() {
+			mode |= 0o200;
+		}
+
+		let file = open_file.get_file().lock();
+		let target = match file.get_location() {
+			FileLocation::Virtual {
+				id,
+			} => match file.get_type() {
+				FileType::Fifo => crate::format!("pipe:[{id}]")?,
+				FileType::Socket => crate::format!("socket:[{id}]")?,
+				_ => crate::format!("anon_inode:[{id}]")?,
+			},
+			FileLocation::Filesystem {
+				..
+			} => crate::format!("{}", file.get_path()?)?,
+		};
+		Ok((
+			proc.access_profile.get_euid(),
+			proc.access_profile.get_egid(),
+			mode,
+			target,
+		))
+	})?;
+
+	let location = FileLocation::Filesystem {
+		mountpoint_id: 0, // dummy value to be replaced
+		inode,
+	};
+	File::new(
+		crate::format!("{fd}")?,
+		uid,
+		gid,
+		mode,
+		location,
+		FileContent::Link(target),
+	)
+}
+
+/// Returns the open file the link to the file descriptor `fd` of the process `pid` resolves to.
+///
+/// Following the link requires to be the owner of the process, or to be privileged. Otherwise,
+/// the function returns [`crate::errno::EACCES`].
+pub fn resolve_link(pid: Pid, fd: u32, ap: &AccessProfile) -> EResult<Arc<Mutex<File>>> {
+	with_fd(pid, fd, |proc, fd| {
+		if !ap.is_privileged() && proc.access_profile.get_euid() != ap.get_euid() {
+			return Err(errno!(EACCES));
+		}
+		let open_file = fd.get_open_file().lock();
+		Ok(open_file.get_file().clone())
+	})
+}
+
+/// Structure representing the `fd` directory.
+pub struct FdDir {
+	/// The PID of the process.
+	pub pid: Pid,
+}
+
+impl KernFSNode for FdDir {
+	fn get_mode(&self) -> Mode {
+		0o500
+	}
+
+	fn get_uid(&self) -> Uid {
+		if let Some(proc_mutex) = Process::get_by_pid(self.pid) {
+			proc_mutex.lock().access_profile.get_euid()
+		} else {
+			0
+		}
+	}
+
+	fn get_gid(&self) -> Gid {
+		if let Some(proc_mutex) = Process::get_by_pid(self.pid) {
+			proc_mutex.lock().access_profile.get_egid()
+		} else {
+			0
+		}
+	}
+
+	fn get_content(&mut self) -> EResult<KernFSContent<'_>> {
+		let mut entries = HashMap::new();
+		let fds_mutex = Process::get_by_pid(self.pid).and_then(|proc_mutex| {
+			let proc = proc_mutex.lock();
+			proc.get_fds().cloned()
+		});
+		if let Some(fds_mutex) = fds_mutex {
+			let fds = fds_mutex.lock();
+			for fd in fds.iter() {
+				let name: String = crate::format!("{}", fd.get_id())?;
+				entries.insert(
+					name,
+					DirEntry {
+						inode: fd_inode(self.pid, fd.get_id()),
+						entry_type: FileType::Link,
+					},
+				)?;
+			}
+		}
+		Ok(FileContent::Directory(entries).into())
+	}
+}
+
+impl IO for FdDir {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, _offset: u64, _buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn write(&mut self, _offset: u64, _buff: &[u8]) -> Result<u64, Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn poll(&mut self, _mask: u32) -> Result<u32, Errno> {
+		Err(errno!(EINVAL))
+	}
+}
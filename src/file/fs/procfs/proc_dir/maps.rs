@@ -0,0 +1,185 @@
+//! This module implements the `maps` node, which lists the memory mappings of a process.
+
+use crate::device::id;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::fs::kernfs::content::KernFSContent;
+use crate::file::fs::kernfs::node::KernFSNode;
+use crate::file::path::Path;
+use crate::file::perm::Gid;
+use crate::file::perm::Uid;
+use crate::file::FileContent;
+use crate::file::FileLocation;
+use crate::file::Mode;
+use crate::process::mem_space;
+use crate::process::mem_space::MapResidence;
+use crate::process::pid::Pid;
+use crate::process::Process;
+use crate::util::container::string::String;
+use crate::util::container::vec::Vec;
+use crate::util::io::IO;
+use crate::util::ptr::arc::Arc;
+use core::cmp::min;
+
+/// A mapping, as listed by the node.
+struct Entry {
+	/// The address of the beginning of the mapping.
+	begin: usize,
+	/// The address of the end of the mapping.
+	end: usize,
+	/// The mapping's flags.
+	flags: u8,
+	/// The file the mapping resides in, along with its path and the offset in the file.
+	file: Option<(FileLocation, Arc<Path>, u64)>,
+	/// Tells whether the mapping is the heap of the process.
+	heap: bool,
+}
+
+/// Returns the major and minor numbers of the device holding the file at location `loc`.
+fn device_numbers(loc: &FileLocation) -> (u32, u32) {
+	let Some(mountpoint) = loc.get_mountpoint() else {
+		return (0, 0);
+	};
+	let dev = mountpoint.lock().get_dev();
+	(id::major(dev), id::minor(dev))
+}
+
+/// Structure representing the maps node of the procfs.
+pub struct Maps {
+	/// The PID of the process.
+	pub pid: Pid,
+}
+
+impl KernFSNode for Maps {
+	fn get_mode(&self) -> Mode {
+		0o444
+	}
+
+	fn get_uid(&self) -> Uid {
+		if let Some(proc_mutex) = Process::get_by_pid(self.pid) {
+			proc_mutex.lock().access_profile.get_euid()
+		} else {
+			0
+		}
+	}
+
+	fn get_gid(&self) -> Gid {
+		if let Some(proc_mutex) = Process::get_by_pid(self.pid) {
+			proc_mutex.lock().access_profile.get_egid()
+		} else {
+			0
+		}
+	}
+
+	fn get_content(&mut self) -> EResult<KernFSContent<'_>> {
+		Ok(FileContent::Regular.into())
+	}
+}
+
+impl IO for Maps {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, offset: u64, buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+		if buff.is_empty() {
+			return Ok((0, false));
+		}
+
+		let Some(proc_mutex) = Process::get_by_pid(self.pid) else {
+			return Ok((0, true));
+		};
+		let Some(mem_space_mutex) = proc_mutex.lock().get_mem_space().cloned() else {
+			return Ok((0, true));
+		};
+
+		// Collect mappings
+		let mut entries = Vec::new();
+		{
+			let mem_space = mem_space_mutex.lock();
+			let brk_init = mem_space.get_brk_init();
+			let brk_ptr = mem_space.get_brk_ptr();
+			for mapping in mem_space.iter_mappings() {
+				let begin = mapping.get_begin();
+				let size = mapping.get_size().get();
+				let file = match mapping.get_residence() {
+					MapResidence::File {
+						location,
+						path,
+						off,
+						..
+					} => Some((location.clone(), path.clone(), *off)),
+					_ => None,
+				};
+				entries.push(Entry {
+					begin: begin as usize,
+					end: begin as usize + size,
+					flags: mapping.get_flags(),
+					file,
+					heap: !brk_init.is_null() && begin >= brk_init && begin < brk_ptr,
+				})?;
+			}
+		}
+
+		// Generating content
+		let mut content = String::new();
+		for e in entries {
+			let write = if e.flags & mem_space::MAPPING_FLAG_WRITE != 0 {
+				'w'
+			} else {
+				'-'
+			};
+			let exec = if e.flags & mem_space::MAPPING_FLAG_EXEC != 0 {
+				'x'
+			} else {
+				'-'
+			};
+			let shared = if e.flags & mem_space::MAPPING_FLAG_SHARED != 0 {
+				's'
+			} else {
+				'p'
+			};
+			let line = match &e.file {
+				Some((loc, path, off)) => {
+					let (major, minor) = device_numbers(loc);
+					crate::format!(
+						"{:08x}-{:08x} r{write}{exec}{shared} {off:08x} {major:02x}:{minor:02x} {} \
+{path}\n",
+						e.begin,
+						e.end,
+						loc.get_inode(),
+					)?
+				}
+				None if e.heap => crate::format!(
+					"{:08x}-{:08x} r{write}{exec}{shared} 00000000 00:00 0 [heap]\n",
+					e.begin,
+					e.end
+				)?,
+				None => crate::format!(
+					"{:08x}-{:08x} r{write}{exec}{shared} 00000000 00:00 0\n",
+					e.begin,
+					e.end
+				)?,
+			};
+			content.push_str(line)?;
+		}
+
+		// Copying content to userspace buffer
+		let content_bytes = content.as_bytes();
+		let offset = min(offset, content_bytes.len() as u64) as usize;
+		let len = min(content_bytes.len() - offset, buff.len());
+		buff[..len].copy_from_slice(&content_bytes[offset..(offset + len)]);
+
+		let eof = offset + len >= content_bytes.len();
+		Ok((len as _, eof))
+	}
+
+	fn write(&mut self, _offset: u64, _buff: &[u8]) -> Result<u64, Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn poll(&mut self, _mask: u32) -> Result<u32, Errno> {
+		// TODO
+		todo!();
+	}
+}
@@ -2,7 +2,10 @@
 
 mod cmdline;
 mod cwd;
+mod environ;
 mod exe;
+pub mod fd;
+mod maps;
 mod mounts;
 mod stat;
 mod status;
@@ -19,19 +22,40 @@ use crate::file::DirEntry;
 use crate::file::FileContent;
 use crate::file::FileType;
 use crate::file::Mode;
+use crate::process::mem_space::MemSpace;
 use crate::process::oom;
 use crate::process::pid::Pid;
 use crate::process::Process;
 use crate::util::boxed::Box;
 use crate::util::container::hashmap::HashMap;
+use crate::util::container::vec::Vec;
 use crate::util::io::IO;
 use cmdline::Cmdline;
+use core::ops::Range;
 use cwd::Cwd;
+use environ::Environ;
 use exe::Exe;
+use fd::FdDir;
+use maps::Maps;
 use mounts::Mounts;
 use stat::Stat;
 use status::Status;
 
+/// Reads the memory of the process `proc` in the range of addresses returned by `f`.
+///
+/// If the process has no memory space, the function returns an empty buffer.
+fn read_proc_mem<F: FnOnce(&MemSpace) -> Range<usize>>(proc: &Process, f: F) -> EResult<Vec<u8>> {
+	let Some(mem_space_mutex) = proc.get_mem_space() else {
+		return Ok(Vec::new());
+	};
+	let mem_space = mem_space_mutex.lock();
+	let range = f(&mem_space);
+	if range.is_empty() {
+		return Ok(Vec::new());
+	}
+	mem_space.read_remote(range.start as _, range.len())
+}
+
 /// Structure representing the directory of a process.
 pub struct ProcDir {
 	/// The PID of the process.
@@ -76,6 +100,19 @@ impl ProcDir {
 			},
 		)?;
 
+		// Create /proc/<pid>/environ
+		let node = Environ {
+			pid,
+		};
+		let inode = fs.add_node(Box::new(node)?)?;
+		entries.insert(
+			b"environ".try_into()?,
+			DirEntry {
+				inode,
+				entry_type: FileType::Regular,
+			},
+		)?;
+
 		// Create /proc/<pid>/exe
 		let node = Exe {
 			pid,
@@ -89,6 +126,32 @@ impl ProcDir {
 			},
 		)?;
 
+		// Create /proc/<pid>/fd
+		let node = FdDir {
+			pid,
+		};
+		let inode = fs.add_node(Box::new(node)?)?;
+		entries.insert(
+			b"fd".try_into()?,
+			DirEntry {
+				inode,
+				entry_type: FileType::Directory,
+			},
+		)?;
+
+		// Create /proc/<pid>/maps
+		let node = Maps {
+			pid,
+		};
+		let inode = fs.add_node(Box::new(node)?)?;
+		entries.insert(
+			b"maps".try_into()?,
+			DirEntry {
+				inode,
+				entry_type: FileType::Regular,
+			},
+		)?;
+
 		// Create /proc/<pid>/mounts
 		let node = Mounts {
 			pid,
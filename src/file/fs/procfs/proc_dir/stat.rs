@@ -2,6 +2,7 @@
 //! status of the process.
 
 use crate::errno::EResult;
+use crate::file::fs::procfs::USER_HZ;
 use crate::errno::Errno;
 use crate::file::fs::kernfs::content::KernFSContent;
 use crate::file::fs::kernfs::node::KernFSNode;
@@ -79,9 +80,13 @@ impl IO for Stat {
 
 		let priority = proc.priority;
 		let nice = proc.nice;
+		let rt_priority = proc.rt_priority;
+		let policy = proc.policy.get_id();
 
 		let num_threads = 1; // TODO
 
+		let start_time = proc.start_time / (1_000_000_000 / USER_HZ);
+
 		// TODO Fix deadlock
 		//let vmem_usage = proc.get_vmem_usage();
 		let vmem_usage = 0;
@@ -93,9 +98,9 @@ impl IO for Stat {
 		// Generating content
 		let content = crate::format!(
 			"{pid} ({name}) {state_char} {ppid} {pgid} {sid} TODO TODO 0 \
-0 0 0 0 {user_jiffies} {kernel_jiffies} TODO TODO {priority} {nice} {num_threads} 0 {vmem_usage} \
-TODO TODO TODO TODO {esp} {eip} TODO TODO TODO TODO 0 0 0 TODO TODO TODO TODO TODO TODO TODO TODO \
-TODO TODO TODO TODO TODO TODO TODO TODO TODO"
+0 0 0 0 {user_jiffies} {kernel_jiffies} TODO TODO {priority} {nice} {num_threads} 0 {start_time} \
+{vmem_usage} TODO TODO TODO TODO TODO {esp} {eip} TODO TODO TODO TODO 0 0 0 TODO TODO \
+{rt_priority} {policy} TODO TODO TODO TODO TODO TODO TODO TODO TODO TODO TODO TODO TODO"
 		)?;
 
 		// Copying content to userspace buffer
@@ -0,0 +1,121 @@
+//! This module implements the stat node, allowing to retrieve statistics about the activity of
+//! the system since boot.
+
+use super::USER_HZ;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::event;
+use crate::file::fs::kernfs::content::KernFSContent;
+use crate::file::fs::kernfs::node::KernFSNode;
+use crate::file::FileContent;
+use crate::file::Mode;
+use crate::process;
+use crate::process::scheduler::CpuStat;
+use crate::time::clock;
+use crate::time::clock::CLOCK_BOOTTIME;
+use crate::time::clock::CLOCK_REALTIME;
+use crate::time::unit::TimestampScale;
+use crate::util::container::string::String;
+use crate::util::container::vec::Vec;
+use crate::util::io::IO;
+use core::cmp::min;
+use core::fmt;
+
+/// Appends the line for the CPU named `name` with times `stat` to `content`.
+fn push_cpu_line(content: &mut String, name: impl fmt::Display, stat: &CpuStat) -> EResult<()> {
+	let to_ticks = |ns| ns / (1_000_000_000 / USER_HZ);
+	let s = crate::format!(
+		"{name} {} 0 {} {} 0 {} 0 0 0 0\n",
+		to_ticks(stat.user),
+		to_ticks(stat.system),
+		to_ticks(stat.idle),
+		to_ticks(stat.irq),
+	)?;
+	content.push_str(s)?;
+	Ok(())
+}
+
+/// Structure representing the stat node.
+pub struct Stat {}
+
+impl KernFSNode for Stat {
+	fn get_mode(&self) -> Mode {
+		0o444
+	}
+
+	fn get_content(&mut self) -> EResult<KernFSContent<'_>> {
+		Ok(KernFSContent::Dynamic(FileContent::Regular))
+	}
+}
+
+impl IO for Stat {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, offset: u64, buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+		if buff.is_empty() {
+			return Ok((0, false));
+		}
+
+		let (cpu_stats, context_switches, processes, running) = {
+			let sched = process::get_scheduler().lock();
+			let mut cpu_stats = Vec::new();
+			cpu_stats.extend_from_slice(sched.get_cpu_stats())?;
+			(
+				cpu_stats,
+				sched.get_context_switches(),
+				sched.get_processes_created(),
+				sched.get_running_count(),
+			)
+		};
+		let realtime = clock::current_time(CLOCK_REALTIME, TimestampScale::Second)?;
+		let uptime = clock::current_time(CLOCK_BOOTTIME, TimestampScale::Second)?;
+
+		// Generating content
+		let mut content = String::new();
+		let total = cpu_stats
+			.iter()
+			.fold(CpuStat::default(), |acc, stat| CpuStat {
+				user: acc.user + stat.user,
+				system: acc.system + stat.system,
+				idle: acc.idle + stat.idle,
+				irq: acc.irq + stat.irq,
+			});
+		push_cpu_line(&mut content, "cpu ", &total)?;
+		for (i, stat) in cpu_stats.iter().enumerate() {
+			let name = crate::format!("cpu{i}")?;
+			push_cpu_line(&mut content, name, stat)?;
+		}
+		let s = crate::format!(
+			"intr {}
+ctxt {context_switches}
+btime {}
+processes {processes}
+procs_running {running}
+procs_blocked 0
+",
+			event::get_interrupts_count(),
+			realtime.saturating_sub(uptime),
+		)?;
+		content.push_str(s)?;
+
+		// Copying content to userspace buffer
+		let content_bytes = content.as_bytes();
+		let offset = min(offset, content_bytes.len() as u64) as usize;
+		let len = min(content_bytes.len() - offset, buff.len());
+		buff[..len].copy_from_slice(&content_bytes[offset..(offset + len)]);
+
+		let eof = offset + len >= content_bytes.len();
+		Ok((len as _, eof))
+	}
+
+	fn write(&mut self, _offset: u64, _buff: &[u8]) -> Result<u64, Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn poll(&mut self, _mask: u32) -> Result<u32, Errno> {
+		// TODO
+		todo!();
+	}
+}
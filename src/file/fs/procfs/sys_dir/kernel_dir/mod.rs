@@ -1,6 +1,10 @@
 //! TODO doc
 
+mod kptr_restrict;
 mod osrelease;
+mod pid_max;
+mod random;
+mod uts_name;
 
 use super::kernfs::KernFS;
 use crate::errno::EResult;
@@ -16,7 +20,11 @@ use crate::file::Mode;
 use crate::util::boxed::Box;
 use crate::util::container::hashmap::HashMap;
 use crate::util::io::IO;
+use kptr_restrict::KptrRestrict;
 use osrelease::OsRelease;
+use pid_max::PidMax;
+use random::RandomDir;
+use uts_name::UtsName;
 
 // TODO Handle dropping
 /// Structure representing the `kernel` directory.
@@ -36,6 +44,40 @@ impl KernelDir {
 		// TODO On fail, remove previously inserted nodes
 
 		// Creating /proc/sys/kernel
+		let node = UtsName {
+			field: |uts| &mut uts.domainname,
+		};
+		let inode = fs.add_node(Box::new(node)?)?;
+		entries.insert(
+			b"domainname".try_into()?,
+			DirEntry {
+				inode,
+				entry_type: FileType::Regular,
+			},
+		)?;
+
+		let node = UtsName {
+			field: |uts| &mut uts.hostname,
+		};
+		let inode = fs.add_node(Box::new(node)?)?;
+		entries.insert(
+			b"hostname".try_into()?,
+			DirEntry {
+				inode,
+				entry_type: FileType::Regular,
+			},
+		)?;
+
+		let node = KptrRestrict {};
+		let inode = fs.add_node(Box::new(node)?)?;
+		entries.insert(
+			b"kptr_restrict".try_into()?,
+			DirEntry {
+				inode,
+				entry_type: FileType::Regular,
+			},
+		)?;
+
 		let node = OsRelease {};
 		let inode = fs.add_node(Box::new(node)?)?;
 		entries.insert(
@@ -46,6 +88,26 @@ impl KernelDir {
 			},
 		)?;
 
+		let node = PidMax {};
+		let inode = fs.add_node(Box::new(node)?)?;
+		entries.insert(
+			b"pid_max".try_into()?,
+			DirEntry {
+				inode,
+				entry_type: FileType::Regular,
+			},
+		)?;
+
+		let node = RandomDir::new(fs)?;
+		let inode = fs.add_node(Box::new(node)?)?;
+		entries.insert(
+			b"random".try_into()?,
+			DirEntry {
+				inode,
+				entry_type: FileType::Directory,
+			},
+		)?;
+
 		Ok(Self {
 			content: FileContent::Directory(entries),
 		})
@@ -0,0 +1,71 @@
+//! The `pid_max` node allows to read and set the maximum PID that can be allocated to a new
+//! process.
+
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::fs::kernfs::content::KernFSContent;
+use crate::file::fs::kernfs::node::KernFSNode;
+use crate::file::perm::Gid;
+use crate::file::perm::Uid;
+use crate::file::FileContent;
+use crate::file::Mode;
+use crate::process;
+use crate::util::io::IO;
+use core::cmp::min;
+
+/// Structure representing the `pid_max` node.
+pub struct PidMax {}
+
+impl KernFSNode for PidMax {
+	fn get_mode(&self) -> Mode {
+		0o644
+	}
+
+	fn get_uid(&self) -> Uid {
+		0
+	}
+
+	fn get_gid(&self) -> Gid {
+		0
+	}
+
+	fn get_content(&mut self) -> EResult<KernFSContent<'_>> {
+		Ok(FileContent::Regular.into())
+	}
+}
+
+impl IO for PidMax {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, offset: u64, buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+		if buff.is_empty() {
+			return Ok((0, false));
+		}
+
+		// Generating content
+		let content = crate::format!("{}\n", process::get_pid_max())?;
+
+		// Copying content to userspace buffer
+		let content_bytes = content.as_bytes();
+		let len = min((content_bytes.len() as u64 - offset) as usize, buff.len());
+		buff[..len].copy_from_slice(&content_bytes[(offset as usize)..(offset as usize + len)]);
+
+		let eof = (offset + len as u64) >= content_bytes.len() as u64;
+		Ok((len as _, eof))
+	}
+
+	fn write(&mut self, _offset: u64, buff: &[u8]) -> Result<u64, Errno> {
+		let max = core::str::from_utf8(buff)
+			.ok()
+			.and_then(|s| s.trim().parse().ok())
+			.ok_or_else(|| errno!(EINVAL))?;
+		process::set_pid_max(max)?;
+		Ok(buff.len() as _)
+	}
+
+	fn poll(&mut self, _mask: u32) -> Result<u32, Errno> {
+		Ok(0)
+	}
+}
@@ -0,0 +1,107 @@
+//! The `boot_id` node returns a random UUID identifying the current boot.
+//!
+//! Userspace uses it to tell whether the system has rebooted, for example to detect that a PID
+//! now refers to another process.
+
+use crate::crypto::rand;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::fs::kernfs::content::KernFSContent;
+use crate::file::fs::kernfs::node::KernFSNode;
+use crate::file::perm::Gid;
+use crate::file::perm::Uid;
+use crate::file::FileContent;
+use crate::file::Mode;
+use crate::util::io::IO;
+use crate::util::lock::Mutex;
+use core::cmp::min;
+
+/// The boot ID, generated on first use.
+static BOOT_ID: Mutex<Option<[u8; 16]>> = Mutex::new(None);
+
+/// Returns the boot ID, generating it if not done yet.
+fn get() -> [u8; 16] {
+	*BOOT_ID.lock().get_or_insert_with(|| {
+		let mut id = [0; 16];
+		if let Some(pool) = &mut *rand::ENTROPY_POOL.lock() {
+			pool.read(&mut id, true);
+		}
+		// Make it a version 4 (random) UUID
+		id[6] = (id[6] & 0x0f) | 0x40;
+		id[8] = (id[8] & 0x3f) | 0x80;
+		id
+	})
+}
+
+/// Structure representing the `boot_id` node.
+pub struct BootId {}
+
+impl KernFSNode for BootId {
+	fn get_mode(&self) -> Mode {
+		0o444
+	}
+
+	fn get_uid(&self) -> Uid {
+		0
+	}
+
+	fn get_gid(&self) -> Gid {
+		0
+	}
+
+	fn get_content(&mut self) -> EResult<KernFSContent<'_>> {
+		Ok(FileContent::Regular.into())
+	}
+}
+
+impl IO for BootId {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, offset: u64, buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+		if buff.is_empty() {
+			return Ok((0, false));
+		}
+
+		// Generating content
+		let id = get();
+		let content = crate::format!(
+			"{:02x}{:02x}{:02x}{:02x}-{:02x}{:02x}-{:02x}{:02x}-{:02x}{:02x}-\
+{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}\n",
+			id[0],
+			id[1],
+			id[2],
+			id[3],
+			id[4],
+			id[5],
+			id[6],
+			id[7],
+			id[8],
+			id[9],
+			id[10],
+			id[11],
+			id[12],
+			id[13],
+			id[14],
+			id[15]
+		)?;
+
+		// Copying content to userspace buffer
+		let content_bytes = content.as_bytes();
+		let len = min((content_bytes.len() as u64 - offset) as usize, buff.len());
+		buff[..len].copy_from_slice(&content_bytes[(offset as usize)..(offset as usize + len)]);
+
+		let eof = (offset + len as u64) >= content_bytes.len() as u64;
+		Ok((len as _, eof))
+	}
+
+	fn write(&mut self, _offset: u64, _buff: &[u8]) -> Result<u64, Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn poll(&mut self, _mask: u32) -> Result<u32, Errno> {
+		// TODO
+		todo!();
+	}
+}
@@ -0,0 +1,84 @@
+//! The `random` directory contains information about the kernel's random number generator.
+
+mod boot_id;
+
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::fs::kernfs::content::KernFSContent;
+use crate::file::fs::kernfs::node::KernFSNode;
+use crate::file::fs::kernfs::KernFS;
+use crate::file::perm::Gid;
+use crate::file::perm::Uid;
+use crate::file::DirEntry;
+use crate::file::FileContent;
+use crate::file::FileType;
+use crate::file::Mode;
+use crate::util::boxed::Box;
+use crate::util::container::hashmap::HashMap;
+use crate::util::io::IO;
+use boot_id::BootId;
+
+/// Structure representing the `random` directory.
+pub struct RandomDir {
+	/// The content of the directory. This will always be a Directory variant.
+	content: FileContent,
+}
+
+impl RandomDir {
+	/// Creates a new instance.
+	///
+	/// The function adds every nodes to the given kernfs `fs`.
+	pub fn new(fs: &mut KernFS) -> Result<Self, Errno> {
+		let mut entries = HashMap::new();
+
+		let node = BootId {};
+		let inode = fs.add_node(Box::new(node)?)?;
+		entries.insert(
+			b"boot_id".try_into()?,
+			DirEntry {
+				inode,
+				entry_type: FileType::Regular,
+			},
+		)?;
+
+		Ok(Self {
+			content: FileContent::Directory(entries),
+		})
+	}
+}
+
+impl KernFSNode for RandomDir {
+	fn get_mode(&self) -> Mode {
+		0o555
+	}
+
+	fn get_uid(&self) -> Uid {
+		0
+	}
+
+	fn get_gid(&self) -> Gid {
+		0
+	}
+
+	fn get_content(&mut self) -> EResult<KernFSContent<'_>> {
+		Ok(KernFSContent::Owned(&mut self.content))
+	}
+}
+
+impl IO for RandomDir {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, _offset: u64, _buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn write(&mut self, _offset: u64, _buff: &[u8]) -> Result<u64, Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn poll(&mut self, _mask: u32) -> Result<u32, Errno> {
+		Err(errno!(EINVAL))
+	}
+}
@@ -0,0 +1,88 @@
+//! The `hostname` and `domainname` nodes allow to read and set the names of the UTS namespace of
+//! the current process.
+
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::fs::kernfs::content::KernFSContent;
+use crate::file::fs::kernfs::node::KernFSNode;
+use crate::file::perm::Gid;
+use crate::file::perm::Uid;
+use crate::file::FileContent;
+use crate::file::Mode;
+use crate::limits;
+use crate::process::uts::UtsNamespace;
+use crate::process::Process;
+use crate::util::container::vec::Vec;
+use crate::util::io::IO;
+use core::cmp::min;
+
+/// Structure representing a node exposing one of the names of a UTS namespace.
+pub struct UtsName {
+	/// Returns the name exposed by the node in the given namespace.
+	pub field: fn(&mut UtsNamespace) -> &mut Vec<u8>,
+}
+
+impl KernFSNode for UtsName {
+	fn get_mode(&self) -> Mode {
+		0o644
+	}
+
+	fn get_uid(&self) -> Uid {
+		0
+	}
+
+	fn get_gid(&self) -> Gid {
+		0
+	}
+
+	fn get_content(&mut self) -> EResult<KernFSContent<'_>> {
+		Ok(FileContent::Regular.into())
+	}
+}
+
+impl IO for UtsName {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, offset: u64, buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+		if buff.is_empty() {
+			return Ok((0, false));
+		}
+
+		// Generating content
+		let mut content = {
+			let proc_mutex = Process::current_assert();
+			let proc = proc_mutex.lock();
+			let mut uts = proc.get_uts().lock();
+			Vec::from_slice((self.field)(&mut *uts))?
+		};
+		content.push(b'\n')?;
+
+		// Copying content to userspace buffer
+		let offset = min(offset, content.len() as u64) as usize;
+		let len = min(content.len() - offset, buff.len());
+		buff[..len].copy_from_slice(&content[offset..(offset + len)]);
+
+		let eof = offset + len >= content.len();
+		Ok((len as _, eof))
+	}
+
+	fn write(&mut self, _offset: u64, buff: &[u8]) -> Result<u64, Errno> {
+		let name = buff.strip_suffix(b"\n").unwrap_or(buff);
+		if name.len() > limits::HOST_NAME_MAX {
+			return Err(errno!(EINVAL));
+		}
+		let name = Vec::from_slice(name)?;
+
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+		let mut uts = proc.get_uts().lock();
+		*(self.field)(&mut *uts) = name;
+		Ok(buff.len() as _)
+	}
+
+	fn poll(&mut self, _mask: u32) -> Result<u32, Errno> {
+		Ok(0)
+	}
+}
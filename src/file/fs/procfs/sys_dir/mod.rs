@@ -1,5 +1,6 @@
 //! TODO doc
 
+mod fs_dir;
 mod kernel_dir;
 
 use super::kernfs;
@@ -17,6 +18,7 @@ use crate::file::Mode;
 use crate::util::boxed::Box;
 use crate::util::container::hashmap::HashMap;
 use crate::util::io::IO;
+use fs_dir::FsDir;
 use kernel_dir::KernelDir;
 
 // TODO Handle dropping
@@ -36,6 +38,17 @@ impl SysDir {
 		// TODO Add every nodes
 		// TODO On fail, remove previously inserted nodes
 
+		// Creating /proc/sys/fs
+		let node = FsDir::new(fs)?;
+		let inode = fs.add_node(Box::new(node)?)?;
+		entries.insert(
+			b"fs".try_into()?,
+			DirEntry {
+				inode,
+				entry_type: FileType::Directory,
+			},
+		)?;
+
 		// Creating /proc/sys/kernel
 		let node = KernelDir::new(fs)?;
 		let inode = fs.add_node(Box::new(node)?)?;
@@ -0,0 +1,45 @@
+//! The `/proc/sysrq-trigger` file allows to trigger a SysRq command by writing its key.
+
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::fs::kernfs::content::KernFSContent;
+use crate::file::fs::kernfs::node::KernFSNode;
+use crate::file::FileContent;
+use crate::file::Mode;
+use crate::sysrq;
+use crate::util::io::IO;
+
+/// Structure representing the sysrq-trigger node.
+pub struct SysRqTrigger {}
+
+impl KernFSNode for SysRqTrigger {
+	fn get_mode(&self) -> Mode {
+		0o200
+	}
+
+	fn get_content(&mut self) -> EResult<KernFSContent<'_>> {
+		Ok(KernFSContent::Dynamic(FileContent::Regular))
+	}
+}
+
+impl IO for SysRqTrigger {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, _offset: u64, _buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn write(&mut self, _offset: u64, buff: &[u8]) -> Result<u64, Errno> {
+		// Only the first character is used
+		if let Some(c) = buff.first() {
+			sysrq::handle(*c);
+		}
+		Ok(buff.len() as _)
+	}
+
+	fn poll(&mut self, _mask: u32) -> Result<u32, Errno> {
+		Ok(0)
+	}
+}
@@ -24,7 +24,12 @@ impl IO for Version {
 
 	fn read(&mut self, offset: u64, buff: &mut [u8]) -> Result<(u64, bool), Errno> {
 		// TODO const format
-		let version = crate::format!("{} version {}\n", crate::NAME, crate::VERSION)?;
+		let version = crate::format!(
+			"{} version {} {}\n",
+			crate::NAME,
+			crate::VERSION,
+			crate::BUILD_INFO
+		)?;
 		let version_bytes = version.as_bytes();
 
 		// Copy content to userspace buffer
@@ -0,0 +1,77 @@
+//! This module implements the zoneinfo node, allowing to retrieve informations about the memory
+//! zones of the buddy allocator.
+
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::fs::kernfs::content::KernFSContent;
+use crate::file::fs::kernfs::node::KernFSNode;
+use crate::file::FileContent;
+use crate::file::Mode;
+use crate::memory;
+use crate::memory::buddy;
+use crate::util::container::string::String;
+use crate::util::io::IO;
+use core::cmp::min;
+
+/// Structure representing the zoneinfo node.
+pub struct ZoneInfo {}
+
+impl KernFSNode for ZoneInfo {
+	fn get_mode(&self) -> Mode {
+		0o444
+	}
+
+	fn get_content(&mut self) -> EResult<KernFSContent<'_>> {
+		Ok(KernFSContent::Dynamic(FileContent::Regular))
+	}
+}
+
+impl IO for ZoneInfo {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, offset: u64, buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+		if buff.is_empty() {
+			return Ok((0, false));
+		}
+
+		// Generating content
+		let mut content = String::new();
+		for zone in buddy::get_zones_info().iter().filter(|z| z.pages > 0) {
+			let s = crate::format!(
+				"Node {}, zone {:>8}
+  pages free     {}
+        cached   {}
+        managed  {}
+  start_pfn:     {}
+",
+				zone.node.unwrap_or(0),
+				zone.name,
+				zone.free,
+				zone.cached,
+				zone.pages,
+				zone.begin / memory::PAGE_SIZE,
+			)?;
+			content.push_str(s)?;
+		}
+
+		// Copying content to userspace buffer
+		let content_bytes = content.as_bytes();
+		let offset = min(offset, content_bytes.len() as u64) as usize;
+		let len = min(content_bytes.len() - offset, buff.len());
+		buff[..len].copy_from_slice(&content_bytes[offset..(offset + len)]);
+
+		let eof = offset + len >= content_bytes.len();
+		Ok((len as _, eof))
+	}
+
+	fn write(&mut self, _offset: u64, _buff: &[u8]) -> Result<u64, Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn poll(&mut self, _mask: u32) -> Result<u32, Errno> {
+		// TODO
+		todo!();
+	}
+}
@@ -0,0 +1,234 @@
+//! The pstore is a virtual filesystem exposing records that persisted across reboots, such as the
+//! kernel logs at the time of a panic.
+//!
+//! Records are stored by a backend. The only backend is [`ram`], which uses reserved memory. A
+//! record is kept until its file is removed.
+//!
+//! Userspace is expected to mount the filesystem at `/sys/fs/pstore`, like on Linux. The directory
+//! is provided by the [`super::sysfs`].
+
+pub mod ram;
+
+use super::kernfs::content::KernFSContent;
+use super::kernfs::node::DummyKernFSNode;
+use super::kernfs::node::KernFSNode;
+use super::kernfs::KernFS;
+use super::options::MountOptions;
+use super::Filesystem;
+use super::FilesystemType;
+use crate::errno;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::fs::Statfs;
+use crate::file::path::Path;
+use crate::file::perm::Gid;
+use crate::file::perm::Uid;
+use crate::file::DirEntry;
+use crate::file::File;
+use crate::file::FileContent;
+use crate::file::FileType;
+use crate::file::INode;
+use crate::file::Mode;
+use crate::util::boxed::Box;
+use crate::util::container::hashmap::HashMap;
+use crate::util::container::string::String;
+use crate::util::io::IO;
+use crate::util::lock::Mutex;
+use crate::util::ptr::arc::Arc;
+
+/// The filesystem's magic number.
+const PSTOREFS_MAGIC: u32 = 0x6165676c;
+/// The name of the file of the panic record.
+const PANIC_RECORD_NAME: &[u8] = b"dmesg-ramoops-0";
+
+/// Node of the kernel logs recorded on panic in the previous boot.
+struct PanicRecord {}
+
+impl KernFSNode for PanicRecord {
+	fn get_mode(&self) -> Mode {
+		0o444
+	}
+
+	fn get_content(&mut self) -> EResult<KernFSContent<'_>> {
+		Ok(KernFSContent::Dynamic(FileContent::Regular))
+	}
+}
+
+impl IO for PanicRecord {
+	fn get_size(&self) -> u64 {
+		ram::get_record_len().unwrap_or(0) as _
+	}
+
+	fn read(&mut self, offset: u64, buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+		let (len, eof) = ram::read_record(offset, buff);
+		Ok((len as _, eof))
+	}
+
+	fn write(&mut self, _offset: u64, _buff: &[u8]) -> Result<u64, Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn poll(&mut self, _mask: u32) -> Result<u32, Errno> {
+		// TODO
+		todo!();
+	}
+}
+
+/// Structure representing the pstore.
+///
+/// On the inside, the pstore works using a kernfs.
+pub struct PstoreFS {
+	/// The kernfs.
+	fs: KernFS,
+}
+
+impl PstoreFS {
+	/// Creates a new instance.
+	///
+	/// `readonly` tells whether the filesystem is readonly.
+	pub fn new(readonly: bool) -> Result<Self, Errno> {
+		let mut fs = KernFS::new(b"pstore".try_into()?, readonly)?;
+
+		let mut entries = HashMap::new();
+		if ram::get_record_len().is_some() {
+			let inode = fs.add_node(Box::new(PanicRecord {})?)?;
+			entries.insert(
+				PANIC_RECORD_NAME.try_into()?,
+				DirEntry {
+					inode,
+					entry_type: FileType::Regular,
+				},
+			)?;
+		}
+
+		// Add the root node
+		let root_node = DummyKernFSNode::new(0o750, 0, 0, FileContent::Directory(entries));
+		fs.set_root(Box::new(root_node)?)?;
+
+		Ok(Self {
+			fs,
+		})
+	}
+}
+
+impl Filesystem for PstoreFS {
+	fn get_name(&self) -> &[u8] {
+		self.fs.get_name()
+	}
+
+	fn is_readonly(&self) -> bool {
+		self.fs.is_readonly()
+	}
+
+	fn must_cache(&self) -> bool {
+		self.fs.must_cache()
+	}
+
+	fn get_stat(&self, io: &mut dyn IO) -> Result<Statfs, Errno> {
+		let mut stat = self.fs.get_stat(io)?;
+		stat.f_type = PSTOREFS_MAGIC;
+		Ok(stat)
+	}
+
+	fn get_root_inode(&self, io: &mut dyn IO) -> Result<INode, Errno> {
+		self.fs.get_root_inode(io)
+	}
+
+	fn get_inode(
+		&mut self,
+		io: &mut dyn IO,
+		parent: Option<INode>,
+		name: &[u8],
+	) -> Result<INode, Errno> {
+		self.fs.get_inode(io, parent, name)
+	}
+
+	fn load_file(&mut self, io: &mut dyn IO, inode: INode, name: String) -> Result<File, Errno> {
+		self.fs.load_file(io, inode, name)
+	}
+
+	fn add_file(
+		&mut self,
+		_io: &mut dyn IO,
+		_parent_inode: INode,
+		_name: String,
+		_uid: Uid,
+		_gid: Gid,
+		_mode: Mode,
+		_content: FileContent,
+	) -> Result<File, Errno> {
+		Err(errno!(EACCES))
+	}
+
+	fn add_link(
+		&mut self,
+		_io: &mut dyn IO,
+		_parent_inode: INode,
+		_name: &[u8],
+		_inode: INode,
+	) -> Result<(), Errno> {
+		Err(errno!(EACCES))
+	}
+
+	fn update_inode(&mut self, _io: &mut dyn IO, _file: &File) -> Result<(), Errno> {
+		Ok(())
+	}
+
+	fn remove_file(
+		&mut self,
+		io: &mut dyn IO,
+		parent_inode: INode,
+		name: &[u8],
+	) -> Result<u16, Errno> {
+		let links = self.fs.remove_file(io, parent_inode, name)?;
+		// Removing the file frees space in the backend for the next record
+		if name == PANIC_RECORD_NAME {
+			ram::erase_record();
+		}
+		Ok(links)
+	}
+
+	fn read_node(
+		&mut self,
+		io: &mut dyn IO,
+		inode: INode,
+		off: u64,
+		buf: &mut [u8],
+	) -> Result<u64, Errno> {
+		self.fs.read_node(io, inode, off, buf)
+	}
+
+	fn write_node(
+		&mut self,
+		_io: &mut dyn IO,
+		_inode: INode,
+		_off: u64,
+		_buf: &[u8],
+	) -> Result<(), Errno> {
+		Err(errno!(EACCES))
+	}
+}
+
+/// Structure representing the pstore file system type.
+pub struct PstoreFsType {}
+
+impl FilesystemType for PstoreFsType {
+	fn get_name(&self) -> &'static [u8] {
+		b"pstore"
+	}
+
+	fn detect(&self, _io: &mut dyn IO) -> Result<bool, Errno> {
+		Ok(false)
+	}
+
+	fn load_filesystem(
+		&self,
+		_io: &mut dyn IO,
+		_mountpath: Path,
+		readonly: bool,
+		options: &MountOptions,
+	) -> Result<Arc<Mutex<dyn Filesystem>>, Errno> {
+		options.check(&[])?;
+		Ok(Arc::new(Mutex::new(PstoreFS::new(readonly)?))?)
+	}
+}
@@ -0,0 +1,139 @@
+//! The RAM backend stores records in the pstore region, reserved with the
+//! `pstore=<size>@<offset>` command line argument. Its content survives warm reboots since the
+//! region is never used by the kernel.
+//!
+//! The region must be given at the same place across boots for records to be found.
+
+use crate::logger::LOGGER;
+use crate::memory;
+use crate::memory::reserved;
+use crate::util::lock::IntMutex;
+use core::cmp::min;
+use core::mem::size_of;
+use core::slice;
+
+/// The magic number identifying a valid record.
+const MAGIC: u32 = 0x4f545350;
+
+/// The header of the record stored in RAM.
+#[repr(C)]
+struct Header {
+	/// The magic number, equal to [`MAGIC`] if a record is present.
+	magic: u32,
+	/// The length of the record in bytes.
+	len: u32,
+	/// The checksum of the record's content.
+	checksum: u32,
+}
+
+/// The area storing the record.
+struct Area {
+	/// The virtual address of the beginning of the area.
+	addr: usize,
+	/// The size of the area in bytes, including the header.
+	size: usize,
+	/// Tells whether the area contains a valid record from the previous boot.
+	has_record: bool,
+}
+
+impl Area {
+	/// Returns the header of the area.
+	fn header(&mut self) -> &mut Header {
+		unsafe { &mut *(self.addr as *mut Header) }
+	}
+
+	/// Returns the buffer storing the content of the record.
+	fn data(&mut self) -> &mut [u8] {
+		let ptr = (self.addr + size_of::<Header>()) as *mut u8;
+		unsafe { slice::from_raw_parts_mut(ptr, self.size - size_of::<Header>()) }
+	}
+}
+
+/// The area, if the backend is available.
+static AREA: IntMutex<Option<Area>> = IntMutex::new(None);
+
+/// Computes the checksum of `data` (FNV-1a).
+///
+/// A simple function is used since it must work in any state of the kernel.
+fn checksum(data: &[u8]) -> u32 {
+	data.iter()
+		.fold(0x811c9dc5, |h, b| (h ^ *b as u32).wrapping_mul(0x01000193))
+}
+
+/// Initializes the backend, looking for a record left by the previous boot.
+///
+/// If no pstore region is reserved, the backend is not available.
+pub fn init() {
+	let Some(region) = reserved::PSTORE.get() else {
+		return;
+	};
+	// The area must always be mapped in kernelspace
+	if region.begin + region.size > memory::get_kernelspace_size() {
+		crate::println!("pstore: region is not mapped in kernelspace");
+		return;
+	}
+	let addr = memory::kern_to_virt(region.begin as *const u8) as usize;
+	let mut area = Area {
+		addr,
+		size: region.size,
+		has_record: false,
+	};
+
+	let header = area.header();
+	let (magic, len, sum) = (header.magic, header.len as usize, header.checksum);
+	let data = area.data();
+	let valid = magic == MAGIC && len <= data.len() && checksum(&data[..len]) == sum;
+	area.has_record = valid;
+	if valid {
+		crate::println!("pstore: found a record from the previous boot");
+	}
+	*AREA.lock() = Some(area);
+}
+
+/// Returns the length of the record from the previous boot, if any.
+pub fn get_record_len() -> Option<usize> {
+	let mut area = AREA.lock();
+	let area = area.as_mut().filter(|a| a.has_record)?;
+	Some(area.header().len as _)
+}
+
+/// Reads the record from the previous boot at offset `off` into `buf`.
+///
+/// The function returns the number of bytes read and whether the end of the record is reached.
+pub fn read_record(off: u64, buf: &mut [u8]) -> (usize, bool) {
+	let mut area = AREA.lock();
+	let Some(area) = area.as_mut().filter(|a| a.has_record) else {
+		return (0, true);
+	};
+	let len = area.header().len as usize;
+	let data = &area.data()[..len];
+	let off = min(off, len as u64) as usize;
+	let n = min(buf.len(), len - off);
+	buf[..n].copy_from_slice(&data[off..(off + n)]);
+	(n, off + n >= len)
+}
+
+/// Erases the record from the previous boot.
+pub fn erase_record() {
+	let mut area = AREA.lock();
+	if let Some(area) = area.as_mut() {
+		area.header().magic = 0;
+		area.has_record = false;
+	}
+}
+
+/// Writes the end of the kernel logs as a record, to be retrieved on the next boot.
+///
+/// This function is called on kernel panic and must not allocate memory.
+pub fn write_panic_record() {
+	let mut area = AREA.lock();
+	let Some(area) = area.as_mut() else {
+		return;
+	};
+	let len = LOGGER.lock().copy_tail(area.data());
+	let sum = checksum(&area.data()[..len]);
+	let header = area.header();
+	header.len = len as _;
+	header.checksum = sum;
+	header.magic = MAGIC;
+}
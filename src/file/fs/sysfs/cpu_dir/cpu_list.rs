@@ -0,0 +1,97 @@
+//! The `online`, `possible` and `present` nodes list CPUs as ranges of numbers, such as `0-2,4`.
+
+use crate::cpu::hotplug;
+use crate::errno::AllocResult;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::fs::kernfs::content::KernFSContent;
+use crate::file::fs::kernfs::node::KernFSNode;
+use crate::file::FileContent;
+use crate::file::Mode;
+use crate::util::container::string::String;
+use crate::util::io::IO;
+use core::cmp::min;
+
+/// Formats the mask of CPUs `mask`, with one bit per CPU, as a list of ranges.
+fn format_list(mask: u32) -> AllocResult<String> {
+	let mut s = String::new();
+	let mut cpu = 0;
+	while cpu < hotplug::CPUS_MAX {
+		if mask & (1 << cpu) == 0 {
+			cpu += 1;
+			continue;
+		}
+		let begin = cpu;
+		while cpu + 1 < hotplug::CPUS_MAX && mask & (1 << (cpu + 1)) != 0 {
+			cpu += 1;
+		}
+		if !s.is_empty() {
+			s.push(b',')?;
+		}
+		if begin == cpu {
+			s.push_str(crate::format!("{begin}")?)?;
+		} else {
+			s.push_str(crate::format!("{begin}-{cpu}")?)?;
+		}
+		cpu += 1;
+	}
+	s.push(b'\n')?;
+	Ok(s)
+}
+
+/// A node listing CPUs.
+pub enum CpuList {
+	/// The CPUs that are running.
+	Online,
+	/// The CPUs that can be brought online. Since CPUs cannot be added while the system is
+	/// running, this is the same as [`Self::Present`].
+	Possible,
+	/// The CPUs of the system.
+	Present,
+}
+
+impl KernFSNode for CpuList {
+	fn get_mode(&self) -> Mode {
+		0o444
+	}
+
+	fn get_content(&mut self) -> EResult<KernFSContent<'_>> {
+		Ok(KernFSContent::Dynamic(FileContent::Regular))
+	}
+}
+
+impl IO for CpuList {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, offset: u64, buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+		if buff.is_empty() {
+			return Ok((0, false));
+		}
+
+		// Generating content
+		let mask = match self {
+			Self::Online => hotplug::get_online_mask(),
+			Self::Possible | Self::Present => ((1u64 << hotplug::get_count()) - 1) as u32,
+		};
+		let content = format_list(mask)?;
+
+		// Copying content to userspace buffer
+		let content_bytes = content.as_bytes();
+		let offset = min(offset, content_bytes.len() as u64) as usize;
+		let len = min(content_bytes.len() - offset, buff.len());
+		buff[..len].copy_from_slice(&content_bytes[offset..(offset + len)]);
+
+		let eof = offset + len >= content_bytes.len();
+		Ok((len as _, eof))
+	}
+
+	fn write(&mut self, _offset: u64, _buff: &[u8]) -> Result<u64, Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn poll(&mut self, _mask: u32) -> Result<u32, Errno> {
+		Ok(0)
+	}
+}
@@ -0,0 +1,127 @@
+//! The `cpu` directory describes the CPUs of the system. It contains a directory for each CPU,
+//! allowing to take it offline or to bring it back online.
+
+mod cpu_list;
+mod online;
+
+use crate::cpu::hotplug;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::fs::kernfs::content::KernFSContent;
+use crate::file::fs::kernfs::node::DummyKernFSNode;
+use crate::file::fs::kernfs::node::KernFSNode;
+use crate::file::fs::kernfs::KernFS;
+use crate::file::perm::Gid;
+use crate::file::perm::Uid;
+use crate::file::DirEntry;
+use crate::file::FileContent;
+use crate::file::FileType;
+use crate::file::Mode;
+use crate::util::boxed::Box;
+use crate::util::container::hashmap::HashMap;
+use crate::util::io::IO;
+use cpu_list::CpuList;
+use online::Online;
+
+// TODO Handle dropping
+/// Structure representing the `cpu` directory.
+pub struct CpuDir {
+	/// The content of the directory. This will always be a Directory variant.
+	content: FileContent,
+}
+
+impl CpuDir {
+	/// Creates a new instance.
+	///
+	/// The function adds every nodes to the given kernfs `fs`.
+	pub fn new(fs: &mut KernFS) -> Result<Self, Errno> {
+		let mut entries = HashMap::new();
+
+		// Create /sys/devices/system/cpu/{online,possible,present}
+		let lists: [(&[u8], CpuList); 3] = [
+			(b"online", CpuList::Online),
+			(b"possible", CpuList::Possible),
+			(b"present", CpuList::Present),
+		];
+		for (name, node) in lists {
+			let inode = fs.add_node(Box::new(node)?)?;
+			entries.insert(
+				name.try_into()?,
+				DirEntry {
+					inode,
+					entry_type: FileType::Regular,
+				},
+			)?;
+		}
+
+		for cpu in 0..hotplug::get_count() {
+			let mut cpu_entries = HashMap::new();
+
+			// Create /sys/devices/system/cpu/cpu<n>/online. The boot CPU cannot be taken offline
+			if cpu != 0 {
+				let node = Online {
+					cpu,
+				};
+				let inode = fs.add_node(Box::new(node)?)?;
+				cpu_entries.insert(
+					b"online".try_into()?,
+					DirEntry {
+						inode,
+						entry_type: FileType::Regular,
+					},
+				)?;
+			}
+
+			// Create /sys/devices/system/cpu/cpu<n>
+			let node = DummyKernFSNode::new(0o555, 0, 0, FileContent::Directory(cpu_entries));
+			let inode = fs.add_node(Box::new(node)?)?;
+			entries.insert(
+				crate::format!("cpu{cpu}")?,
+				DirEntry {
+					inode,
+					entry_type: FileType::Directory,
+				},
+			)?;
+		}
+
+		Ok(Self {
+			content: FileContent::Directory(entries),
+		})
+	}
+}
+
+impl KernFSNode for CpuDir {
+	fn get_mode(&self) -> Mode {
+		0o555
+	}
+
+	fn get_uid(&self) -> Uid {
+		0
+	}
+
+	fn get_gid(&self) -> Gid {
+		0
+	}
+
+	fn get_content(&mut self) -> EResult<KernFSContent<'_>> {
+		Ok(KernFSContent::Owned(&mut self.content))
+	}
+}
+
+impl IO for CpuDir {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, _offset: u64, _buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn write(&mut self, _offset: u64, _buff: &[u8]) -> Result<u64, Errno> {
+		Err(errno!(EINVAL))
+	}
+
+	fn poll(&mut self, _mask: u32) -> Result<u32, Errno> {
+		Err(errno!(EINVAL))
+	}
+}
@@ -0,0 +1,66 @@
+//! The `online` node of a CPU tells whether the CPU is online. Writing `0` takes the CPU offline
+//! and writing `1` brings it back online.
+
+use crate::cpu::hotplug;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::fs::kernfs::content::KernFSContent;
+use crate::file::fs::kernfs::node::KernFSNode;
+use crate::file::FileContent;
+use crate::file::Mode;
+use crate::util::io::IO;
+use core::cmp::min;
+
+/// Structure representing the `online` node of a CPU.
+pub struct Online {
+	/// The number of the CPU.
+	pub cpu: usize,
+}
+
+impl KernFSNode for Online {
+	fn get_mode(&self) -> Mode {
+		0o644
+	}
+
+	fn get_content(&mut self) -> EResult<KernFSContent<'_>> {
+		Ok(KernFSContent::Dynamic(FileContent::Regular))
+	}
+}
+
+impl IO for Online {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, offset: u64, buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+		if buff.is_empty() {
+			return Ok((0, false));
+		}
+
+		let content: &[u8] = if hotplug::is_online(self.cpu) {
+			b"1\n"
+		} else {
+			b"0\n"
+		};
+		let offset = min(offset, content.len() as u64) as usize;
+		let len = min(content.len() - offset, buff.len());
+		buff[..len].copy_from_slice(&content[offset..(offset + len)]);
+
+		let eof = offset + len >= content.len();
+		Ok((len as _, eof))
+	}
+
+	fn write(&mut self, _offset: u64, buff: &[u8]) -> Result<u64, Errno> {
+		let online = match core::str::from_utf8(buff).map(str::trim) {
+			Ok("0") => false,
+			Ok("1") => true,
+			_ => return Err(errno!(EINVAL)),
+		};
+		hotplug::set_online(self.cpu, online)?;
+		Ok(buff.len() as _)
+	}
+
+	fn poll(&mut self, _mask: u32) -> Result<u32, Errno> {
+		Ok(0)
+	}
+}
@@ -0,0 +1,237 @@
+//! The sysfs is a virtual filesystem which exposes the devices of the system and allows to
+//! configure them.
+//!
+//! The only devices exposed are CPUs, under `devices/system/cpu`. The `fs` directory holds the
+//! mount points of other virtual filesystems, such as `fs/pstore`.
+
+mod cpu_dir;
+
+use super::kernfs::node::DummyKernFSNode;
+use super::kernfs::KernFS;
+use super::options::MountOptions;
+use super::Filesystem;
+use super::FilesystemType;
+use crate::errno;
+use crate::errno::Errno;
+use crate::file::fs::Statfs;
+use crate::file::path::Path;
+use crate::file::perm::Gid;
+use crate::file::perm::Uid;
+use crate::file::DirEntry;
+use crate::file::File;
+use crate::file::FileContent;
+use crate::file::FileType;
+use crate::file::INode;
+use crate::file::Mode;
+use crate::util::boxed::Box;
+use crate::util::container::hashmap::HashMap;
+use crate::util::container::string::String;
+use crate::util::io::IO;
+use crate::util::lock::Mutex;
+use crate::util::ptr::arc::Arc;
+use cpu_dir::CpuDir;
+
+/// The filesystem's magic number.
+const SYSFS_MAGIC: u32 = 0x62656572;
+
+/// Structure representing the sysfs.
+///
+/// On the inside, the sysfs works using a kernfs.
+pub struct SysFS {
+	/// The kernfs.
+	fs: KernFS,
+}
+
+impl SysFS {
+	/// Creates a new instance.
+	///
+	/// `readonly` tells whether the filesystem is readonly.
+	pub fn new(readonly: bool) -> Result<Self, Errno> {
+		let mut fs = KernFS::new(b"sysfs".try_into()?, readonly)?;
+
+		// Create /sys/devices/system/cpu
+		let node = CpuDir::new(&mut fs)?;
+		let inode = fs.add_node(Box::new(node)?)?;
+		let mut system_entries = HashMap::new();
+		system_entries.insert(
+			b"cpu".try_into()?,
+			DirEntry {
+				inode,
+				entry_type: FileType::Directory,
+			},
+		)?;
+
+		// Create /sys/devices/system
+		let node = DummyKernFSNode::new(0o555, 0, 0, FileContent::Directory(system_entries));
+		let inode = fs.add_node(Box::new(node)?)?;
+		let mut devices_entries = HashMap::new();
+		devices_entries.insert(
+			b"system".try_into()?,
+			DirEntry {
+				inode,
+				entry_type: FileType::Directory,
+			},
+		)?;
+
+		let mut entries = HashMap::new();
+
+		// Create /sys/devices
+		let node = DummyKernFSNode::new(0o555, 0, 0, FileContent::Directory(devices_entries));
+		let inode = fs.add_node(Box::new(node)?)?;
+		entries.insert(
+			b"devices".try_into()?,
+			DirEntry {
+				inode,
+				entry_type: FileType::Directory,
+			},
+		)?;
+
+		// Create /sys/fs/pstore
+		let node = DummyKernFSNode::new(0o750, 0, 0, FileContent::Directory(HashMap::new()));
+		let inode = fs.add_node(Box::new(node)?)?;
+		let mut fs_entries = HashMap::new();
+		fs_entries.insert(
+			b"pstore".try_into()?,
+			DirEntry {
+				inode,
+				entry_type: FileType::Directory,
+			},
+		)?;
+
+		// Create /sys/fs
+		let node = DummyKernFSNode::new(0o555, 0, 0, FileContent::Directory(fs_entries));
+		let inode = fs.add_node(Box::new(node)?)?;
+		entries.insert(
+			b"fs".try_into()?,
+			DirEntry {
+				inode,
+				entry_type: FileType::Directory,
+			},
+		)?;
+
+		// Add the root node
+		let root_node = DummyKernFSNode::new(0o555, 0, 0, FileContent::Directory(entries));
+		fs.set_root(Box::new(root_node)?)?;
+
+		Ok(Self {
+			fs,
+		})
+	}
+}
+
+impl Filesystem for SysFS {
+	fn get_name(&self) -> &[u8] {
+		self.fs.get_name()
+	}
+
+	fn is_readonly(&self) -> bool {
+		self.fs.is_readonly()
+	}
+
+	fn must_cache(&self) -> bool {
+		self.fs.must_cache()
+	}
+
+	fn get_stat(&self, io: &mut dyn IO) -> Result<Statfs, Errno> {
+		let mut stat = self.fs.get_stat(io)?;
+		stat.f_type = SYSFS_MAGIC;
+		Ok(stat)
+	}
+
+	fn get_root_inode(&self, io: &mut dyn IO) -> Result<INode, Errno> {
+		self.fs.get_root_inode(io)
+	}
+
+	fn get_inode(
+		&mut self,
+		io: &mut dyn IO,
+		parent: Option<INode>,
+		name: &[u8],
+	) -> Result<INode, Errno> {
+		self.fs.get_inode(io, parent, name)
+	}
+
+	fn load_file(&mut self, io: &mut dyn IO, inode: INode, name: String) -> Result<File, Errno> {
+		self.fs.load_file(io, inode, name)
+	}
+
+	fn add_file(
+		&mut self,
+		_io: &mut dyn IO,
+		_parent_inode: INode,
+		_name: String,
+		_uid: Uid,
+		_gid: Gid,
+		_mode: Mode,
+		_content: FileContent,
+	) -> Result<File, Errno> {
+		Err(errno!(EACCES))
+	}
+
+	fn add_link(
+		&mut self,
+		_io: &mut dyn IO,
+		_parent_inode: INode,
+		_name: &[u8],
+		_inode: INode,
+	) -> Result<(), Errno> {
+		Err(errno!(EACCES))
+	}
+
+	fn update_inode(&mut self, _io: &mut dyn IO, _file: &File) -> Result<(), Errno> {
+		Ok(())
+	}
+
+	fn remove_file(
+		&mut self,
+		_io: &mut dyn IO,
+		_parent_inode: INode,
+		_name: &[u8],
+	) -> Result<u16, Errno> {
+		Err(errno!(EACCES))
+	}
+
+	fn read_node(
+		&mut self,
+		io: &mut dyn IO,
+		inode: INode,
+		off: u64,
+		buf: &mut [u8],
+	) -> Result<u64, Errno> {
+		self.fs.read_node(io, inode, off, buf)
+	}
+
+	fn write_node(
+		&mut self,
+		io: &mut dyn IO,
+		inode: INode,
+		off: u64,
+		buf: &[u8],
+	) -> Result<(), Errno> {
+		self.fs.write_node(io, inode, off, buf)
+	}
+}
+
+/// Structure representing the sysfs file system type.
+pub struct SysFsType {}
+
+impl FilesystemType for SysFsType {
+	fn get_name(&self) -> &'static [u8] {
+		b"sysfs"
+	}
+
+	fn detect(&self, _io: &mut dyn IO) -> Result<bool, Errno> {
+		Ok(false)
+	}
+
+	fn load_filesystem(
+		&self,
+		_io: &mut dyn IO,
+		_mountpath: Path,
+		readonly: bool,
+		options: &MountOptions,
+	) -> Result<Arc<Mutex<dyn Filesystem>>, Errno> {
+		options.check(&[])?;
+		Ok(Arc::new(Mutex::new(SysFS::new(readonly)?))?)
+	}
+}
@@ -7,36 +7,67 @@ mod node;
 
 use super::kernfs::node::KernFSNode;
 use super::kernfs::KernFS;
+use super::options::MountOptions;
 use super::Filesystem;
 use super::FilesystemType;
 use crate::errno;
+use crate::errno::EResult;
 use crate::file::fs::kernfs::node::DummyKernFSNode;
 use crate::file::fs::Statfs;
 use crate::file::path::Path;
 use crate::file::perm::Gid;
 use crate::file::perm::Uid;
+use crate::file::quota;
+use crate::file::quota::Quota;
+use crate::file::xattr::XattrSet;
 use crate::file::Errno;
 use crate::file::File;
 use crate::file::FileContent;
 use crate::file::INode;
 use crate::file::Mode;
+use crate::memory;
+use crate::time::unit::Timestamp;
 use crate::util::boxed::Box;
 use crate::util::container::hashmap::HashMap;
 use crate::util::container::string::String;
+use crate::util::container::vec::Vec;
 use crate::util::io::IO;
 use crate::util::lock::Mutex;
 use crate::util::ptr::arc::Arc;
+use core::any::Any;
+use core::cmp::min;
+use core::ffi::c_int;
 use core::mem::size_of;
 use node::TmpFSRegular;
 
+/// The filesystem's magic number.
+const TMPFS_MAGIC: u32 = 0x01021994;
+
 /// The default maximum amount of memory the filesystem can use in bytes.
-const DEFAULT_MAX_SIZE: usize = 512 * 1024 * 1024;
+pub const DEFAULT_MAX_SIZE: usize = 512 * 1024 * 1024;
 
 /// Returns the size in bytes used by the given node `node`.
 fn get_used_size<N: KernFSNode>(node: &N) -> usize {
 	size_of::<N>() + node.get_size() as usize
 }
 
+/// Parses the size `s` given in the `size` mount option.
+///
+/// The size is in bytes, optionally followed by the suffix `k`, `m` or `g` to multiply it by
+/// 1024, 1024² or 1024³.
+///
+/// If the size is invalid, the function returns `None`.
+fn parse_size(s: &[u8]) -> Option<usize> {
+	let (digits, unit) = match s.last()? {
+		b'k' | b'K' => (&s[..(s.len() - 1)], 1024),
+		b'm' | b'M' => (&s[..(s.len() - 1)], 1024 * 1024),
+		b'g' | b'G' => (&s[..(s.len() - 1)], 1024 * 1024 * 1024),
+		_ => (s, 1),
+	};
+	let n: usize = core::str::from_utf8(digits).ok()?.parse().ok()?;
+	n.checked_mul(unit)
+}
+
 /// Structure representing the temporary file system.
 ///
 /// On the inside, the tmpfs works using a kernfs.
@@ -48,20 +79,27 @@ pub struct TmpFS {
 
 	/// The kernfs.
 	fs: KernFS,
+	/// The extended attributes of each file, by inode.
+	xattrs: HashMap<INode, XattrSet>,
+	/// The disk quotas.
+	quota: Quota,
 }
 
 impl TmpFS {
 	/// Creates a new instance.
 	///
 	/// Arguments:
+	/// - `name` is the name of the filesystem.
 	/// - `max_size` is the maximum amount of memory the filesystem can use in bytes.
 	/// - `readonly` tells whether the filesystem is readonly.
-	pub fn new(max_size: usize, readonly: bool) -> Result<Self, Errno> {
+	pub fn new(name: &[u8], max_size: usize, readonly: bool) -> Result<Self, Errno> {
 		let mut fs = Self {
 			max_size,
 			size: 0,
 
-			fs: KernFS::new(b"tmpfs".try_into()?, readonly)?,
+			fs: KernFS::new(name.try_into()?, readonly)?,
+			xattrs: HashMap::new(),
+			quota: Quota::default(),
 		};
 
 		// Adding the root node
@@ -108,6 +146,14 @@ impl TmpFS {
 			Err(errno!(ENOSPC))
 		}
 	}
+
+	/// Charges the usage of `space` bytes and `inodes` inodes to the owner of the node `inode`.
+	/// See [`Quota::charge`].
+	fn charge(&mut self, inode: INode, space: i64, inodes: i64, force: bool) -> EResult<()> {
+		let node = self.fs.get_node(inode)?;
+		let (uid, gid) = (node.get_uid(), node.get_gid());
+		self.quota.charge(uid, gid, space, inodes, force)
+	}
 }
 
 impl Filesystem for TmpFS {
@@ -124,7 +170,13 @@ impl Filesystem for TmpFS {
 	}
 
 	fn get_stat(&self, io: &mut dyn IO) -> Result<Statfs, Errno> {
-		self.fs.get_stat(io)
+		let mut stat = self.fs.get_stat(io)?;
+		let free = (self.max_size.saturating_sub(self.size) / memory::PAGE_SIZE) as i64;
+		stat.f_type = TMPFS_MAGIC;
+		stat.f_blocks = (self.max_size / memory::PAGE_SIZE) as _;
+		stat.f_bfree = free;
+		stat.f_bavail = free;
+		Ok(stat)
 	}
 
 	fn get_root_inode(&self, io: &mut dyn IO) -> Result<INode, Errno> {
@@ -156,7 +208,8 @@ impl Filesystem for TmpFS {
 	) -> Result<File, Errno> {
 		// TODO Update fs's size
 
-		match content {
+		self.quota.charge(uid, gid, 0, 1, false)?;
+		let res = match content {
 			FileContent::Regular => {
 				let node = TmpFSRegular::new(mode, uid, gid);
 				self.fs.add_file_inner(parent_inode, node, name)
@@ -165,7 +218,11 @@ impl Filesystem for TmpFS {
 			_ => self
 				.fs
 				.add_file(io, parent_inode, name, uid, gid, mode, content),
+		};
+		if res.is_err() {
+			self.quota.charge(uid, gid, 0, -1, true)?;
 		}
+		res
 	}
 
 	fn add_link(
@@ -181,7 +238,30 @@ impl Filesystem for TmpFS {
 
 	fn update_inode(&mut self, io: &mut dyn IO, file: &File) -> Result<(), Errno> {
 		// TODO Update fs's size
-		self.fs.update_inode(io, file)
+		let inode = file.get_location().get_inode();
+		let node = self.fs.get_node(inode)?;
+		let (uid, gid, size) = (node.get_uid(), node.get_gid(), node.get_size());
+		// Transferring the usage to the new owner
+		let owner_changed = uid != file.get_uid() || gid != file.get_gid();
+		if owner_changed {
+			self.quota
+				.charge(file.get_uid(), file.get_gid(), size as _, 1, false)?;
+		}
+		match self.fs.update_inode(io, file) {
+			Ok(()) if owner_changed => self.quota.charge(uid, gid, -(size as i64), -1, true),
+			Ok(()) => Ok(()),
+			Err(e) => {
+				if owner_changed {
+					self.quota
+						.charge(file.get_uid(), file.get_gid(), -(size as i64), -1, true)?;
+				}
+				Err(e)
+			}
+		}
+	}
+
+	fn touch(&mut self, io: &mut dyn IO, inode: INode, ts: Timestamp) -> EResult<()> {
+		self.fs.touch(io, inode, ts)
 	}
 
 	fn remove_file(
@@ -191,7 +271,44 @@ impl Filesystem for TmpFS {
 		name: &[u8],
 	) -> Result<u16, Errno> {
 		// TODO Update fs's size
-		self.fs.remove_file(io, parent_inode, name)
+		let inode = self.fs.get_inode(io, Some(parent_inode), name)?;
+		let node = self.fs.get_node(inode)?;
+		let (uid, gid, size) = (node.get_uid(), node.get_gid(), node.get_size());
+		let links_left = self.fs.remove_file(io, parent_inode, name)?;
+		if links_left == 0 {
+			self.xattrs.remove(&inode);
+			self.quota.charge(uid, gid, -(size as i64), -1, true)?;
+		}
+		Ok(links_left)
+	}
+
+	fn rename(
+		&mut self,
+		io: &mut dyn IO,
+		old_parent: INode,
+		old_name: &[u8],
+		new_parent: INode,
+		new_name: &[u8],
+		exchange: bool,
+	) -> EResult<()> {
+		// The file that may be replaced, with its owner and size
+		let replaced = match self.fs.get_inode(io, Some(new_parent), new_name) {
+			Ok(inode) if !exchange => {
+				let node = self.fs.get_node(inode)?;
+				Some((inode, node.get_uid(), node.get_gid(), node.get_size()))
+			}
+			_ => None,
+		};
+		self.fs
+			.rename(io, old_parent, old_name, new_parent, new_name, exchange)?;
+		if let Some((inode, uid, gid, size)) = replaced {
+			// If no link is left, the node has been removed
+			if self.fs.get_node(inode).is_err() {
+				self.xattrs.remove(&inode);
+				self.quota.charge(uid, gid, -(size as i64), -1, true)?;
+			}
+		}
+		Ok(())
 	}
 
 	fn read_node(
@@ -212,7 +329,130 @@ impl Filesystem for TmpFS {
 		buf: &[u8],
 	) -> Result<(), Errno> {
 		// TODO Update fs's size
-		self.fs.write_node(io, inode, off, buf)
+		let size = self.fs.get_node(inode)?.get_size();
+		let growth = (off + buf.len() as u64).saturating_sub(size) as i64;
+		self.charge(inode, growth, 0, false)?;
+		let res = self.fs.write_node(io, inode, off, buf);
+		// Accounting for the actual size of the node, even on failure
+		let new_size = self.fs.get_node(inode)?.get_size();
+		self.charge(inode, new_size as i64 - size as i64 - growth, 0, true)?;
+		res
+	}
+
+	fn allocate_range(
+		&mut self,
+		io: &mut dyn IO,
+		inode: INode,
+		off: u64,
+		len: u64,
+	) -> EResult<()> {
+		// Files are kept in memory, so space is allocated by extending the file with zeros
+		let size = self.fs.get_node(inode)?.get_size();
+		let end = off.checked_add(len).ok_or_else(|| errno!(EFBIG))?;
+		if end > size {
+			let zeros = crate::vec![0; (end - size) as usize]?;
+			self.write_node(io, inode, size, &zeros)?;
+		}
+		Ok(())
+	}
+
+	fn punch_hole(&mut self, io: &mut dyn IO, inode: INode, off: u64, len: u64) -> EResult<()> {
+		// The content of a file is contiguous in memory, so the range is only filled with zeros
+		let size = self.fs.get_node(inode)?.get_size();
+		let end = min(off.saturating_add(len), size);
+		if off < end {
+			let zeros = crate::vec![0; (end - off) as usize]?;
+			self.fs.write_node(io, inode, off, &zeros)?;
+		}
+		Ok(())
+	}
+
+	fn truncate_node(&mut self, _io: &mut dyn IO, inode: INode, size: u64) -> EResult<()> {
+		if self.fs.is_readonly() {
+			return Err(errno!(EROFS));
+		}
+		let old_size = self.fs.get_node(inode)?.get_size();
+		let growth = size as i64 - old_size as i64;
+		self.charge(inode, growth, 0, growth <= 0)?;
+		let node = self.fs.get_node_mut(inode)?.as_mut() as &mut dyn Any;
+		let res = node
+			.downcast_mut::<TmpFSRegular>()
+			.ok_or_else(|| errno!(EINVAL))
+			.and_then(|node| node.truncate(size));
+		if res.is_err() {
+			self.charge(inode, -growth, 0, true)?;
+		}
+		res
+	}
+
+	fn get_xattr(&mut self, _io: &mut dyn IO, inode: INode, name: &[u8]) -> EResult<Vec<u8>> {
+		self.xattrs
+			.get(&inode)
+			.ok_or_else(|| errno!(ENODATA))?
+			.get(name)
+	}
+
+	fn set_xattr(
+		&mut self,
+		_io: &mut dyn IO,
+		inode: INode,
+		name: &[u8],
+		value: &[u8],
+		flags: c_int,
+	) -> EResult<()> {
+		if let Some(set) = self.xattrs.get_mut(&inode) {
+			return set.set(name, value, flags);
+		}
+		let mut set = XattrSet::default();
+		set.set(name, value, flags)?;
+		self.xattrs.insert(inode, set)?;
+		Ok(())
+	}
+
+	fn list_xattr(&mut self, _io: &mut dyn IO, inode: INode) -> EResult<Vec<u8>> {
+		match self.xattrs.get(&inode) {
+			Some(set) => set.list(),
+			None => Ok(Vec::new()),
+		}
+	}
+
+	fn remove_xattr(&mut self, _io: &mut dyn IO, inode: INode, name: &[u8]) -> EResult<()> {
+		let set = self.xattrs.get_mut(&inode).ok_or_else(|| errno!(ENODATA))?;
+		set.remove(name)?;
+		if set.is_empty() {
+			self.xattrs.remove(&inode);
+		}
+		Ok(())
+	}
+
+	fn get_quota(&mut self) -> Option<&mut Quota> {
+		Some(&mut self.quota)
+	}
+
+	fn quota_on(&mut self, _io: &mut dyn IO, kind: usize) -> EResult<()> {
+		// Computing the current usage of each user or group
+		let mut usage = HashMap::<u32, (u64, u64)>::new();
+		for node in self.fs.iter_nodes() {
+			let id = match kind {
+				quota::USRQUOTA => node.get_uid(),
+				_ => node.get_gid(),
+			} as u32;
+			let space = node.get_size();
+			match usage.get_mut(&id) {
+				Some((s, n)) => {
+					*s += space;
+					*n += 1;
+				}
+				None => {
+					usage.insert(id, (space, 1))?;
+				}
+			}
+		}
+		self.quota.enable(kind, usage)
+	}
+
+	fn remount(&mut self, io: &mut dyn IO, readonly: bool) -> EResult<()> {
+		self.fs.remount(io, readonly)
 	}
 }
 
@@ -233,10 +473,100 @@ impl FilesystemType for TmpFsType {
 		_io: &mut dyn IO,
 		_mountpath: Path,
 		readonly: bool,
+		options: &MountOptions,
 	) -> Result<Arc<Mutex<dyn Filesystem>>, Errno> {
+		options.check(&[b"size"])?;
+		let max_size = match options.get(b"size")? {
+			Some(size) => parse_size(size).ok_or_else(|| errno!(EINVAL))?,
+			None => DEFAULT_MAX_SIZE,
+		};
 		Ok(Arc::new(Mutex::new(TmpFS::new(
-			DEFAULT_MAX_SIZE,
-			readonly,
+			b"tmpfs", max_size, readonly,
 		)?))?)
 	}
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::util::io;
+	use crate::util::io::DummyIO;
+
+	#[test_case]
+	fn tmpfs_parse_size() {
+		assert_eq!(parse_size(b"4096"), Some(4096));
+		assert_eq!(parse_size(b"16k"), Some(16 * 1024));
+		assert_eq!(parse_size(b"2M"), Some(2 * 1024 * 1024));
+		assert_eq!(parse_size(b"g"), None);
+		assert_eq!(parse_size(b""), None);
+		assert_eq!(parse_size(b"12x"), None);
+	}
+
+	#[test_case]
+	fn tmpfs_rename() {
+		let mut io = DummyIO {};
+		let mut fs = TmpFS::new(b"tmpfs", DEFAULT_MAX_SIZE, false).unwrap();
+		let root = fs.get_root_inode(&mut io).unwrap();
+		let add = |fs: &mut TmpFS, parent, name: &[u8], content| {
+			let name = name.try_into().unwrap();
+			fs.add_file(&mut DummyIO {}, parent, name, 0, 0, 0o755, content)
+				.unwrap()
+				.get_location()
+				.get_inode()
+		};
+		let dir = add(&mut fs, root, b"dir", FileContent::Directory(HashMap::new()));
+		let a = add(&mut fs, root, b"a", FileContent::Regular);
+		let b = add(&mut fs, dir, b"b", FileContent::Regular);
+		let sub = add(&mut fs, dir, b"sub", FileContent::Directory(HashMap::new()));
+
+		// Moving to another directory
+		fs.rename(&mut io, root, b"a", dir, b"a", false).unwrap();
+		assert!(fs.get_inode(&mut io, Some(root), b"a").is_err());
+		assert_eq!(fs.get_inode(&mut io, Some(dir), b"a").unwrap(), a);
+
+		// Exchanging
+		fs.rename(&mut io, dir, b"a", dir, b"b", true).unwrap();
+		assert_eq!(fs.get_inode(&mut io, Some(dir), b"a").unwrap(), b);
+		assert_eq!(fs.get_inode(&mut io, Some(dir), b"b").unwrap(), a);
+		assert!(fs.rename(&mut io, dir, b"a", dir, b"c", true).is_err());
+
+		// Replacing, which removes the replaced file
+		fs.rename(&mut io, dir, b"a", dir, b"b", false).unwrap();
+		assert_eq!(fs.get_inode(&mut io, Some(dir), b"b").unwrap(), b);
+		assert!(fs.get_inode(&mut io, Some(dir), b"a").is_err());
+		assert!(fs.fs.get_node(a).is_err());
+
+		// Moving a directory updates its `..` entry
+		fs.rename(&mut io, dir, b"sub", root, b"sub", false).unwrap();
+		assert_eq!(fs.get_inode(&mut io, Some(sub), b"..").unwrap(), root);
+	}
+
+	#[test_case]
+	fn tmpfs_poll_regular() {
+		let mut io = DummyIO {};
+		let mut fs = TmpFS::new(b"tmpfs", DEFAULT_MAX_SIZE, false).unwrap();
+		let root = fs.get_root_inode(&mut io).unwrap();
+		let name = b"a".try_into().unwrap();
+		let mut file = fs
+			.add_file(&mut io, root, name, 0, 0, 0o644, FileContent::Regular)
+			.unwrap();
+
+		// A regular file is ready right away, so that a poll with a timeout of zero reports it
+		let mask = io::POLLIN | io::POLLOUT | io::POLLPRI;
+		assert_eq!(file.poll(mask).unwrap(), io::POLLIN | io::POLLOUT);
+		assert_eq!(file.poll(io::POLLRDNORM).unwrap(), io::POLLRDNORM);
+		assert_eq!(file.poll(io::POLLPRI).unwrap(), 0);
+	}
+
+	#[test_case]
+	fn tmpfs_stat() {
+		let mut io = DummyIO {};
+		let fs = TmpFS::new(b"tmpfs", 16 * memory::PAGE_SIZE, false).unwrap();
+		let stat = fs.get_stat(&mut io).unwrap();
+		assert_eq!(stat.f_type, TMPFS_MAGIC);
+		assert_eq!(stat.f_blocks, 16);
+		// The root directory uses a part of the first page
+		assert_eq!(stat.f_bfree, 15);
+		assert_eq!(stat.f_bavail, stat.f_bfree);
+	}
+}
@@ -35,6 +35,8 @@ pub struct TmpFSRegular {
 	mtime: Timestamp,
 	/// Timestamp of the last access to the file.
 	atime: Timestamp,
+	/// Timestamp of the creation of the file.
+	btime: Timestamp,
 
 	/// The content of the file.
 	content: Vec<u8>,
@@ -56,10 +58,21 @@ impl TmpFSRegular {
 			ctime: ts,
 			mtime: ts,
 			atime: ts,
+			btime: ts,
 
 			content: Vec::new(),
 		}
 	}
+
+	/// Changes the size of the file to `size`, filling new space with zeros.
+	///
+	/// If the file shrinks, the memory past the new end is released.
+	pub fn truncate(&mut self, size: u64) -> EResult<()> {
+		let size = usize::try_from(size).map_err(|_| errno!(EFBIG))?;
+		self.content.resize(size)?;
+		self.content.shrink_to_fit()?;
+		Ok(())
+	}
 }
 
 impl KernFSNode for TmpFSRegular {
@@ -119,6 +132,10 @@ impl KernFSNode for TmpFSRegular {
 		self.mtime = ts;
 	}
 
+	fn get_btime(&self) -> Option<Timestamp> {
+		Some(self.btime)
+	}
+
 	fn get_content(&mut self) -> EResult<KernFSContent<'_>> {
 		Ok(FileContent::Regular.into())
 	}
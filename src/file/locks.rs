@@ -0,0 +1,356 @@
+//! POSIX advisory record locks allow processes to lock ranges of bytes of a file.
+//!
+//! Locks are advisory: they do not prevent accessing the file, but only conflict with each other.
+//! Several processes may hold read locks on the same range, while a write lock excludes every
+//! other process's lock on the range.
+//!
+//! Record locks are owned by processes (thread groups) and are released when the process exits.
+//!
+//! Independently, whole-file locks set with `flock` are owned by open file descriptions. They
+//! are thus shared by duplicated file descriptors and across `fork`, and released when the open
+//! file description is closed.
+
+use crate::errno;
+use crate::errno::AllocResult;
+use crate::errno::EResult;
+use crate::file::FileLocation;
+use crate::process::pid::Pid;
+use crate::util::container::hashmap::HashMap;
+use crate::util::container::vec::Vec;
+use crate::util::lock::Mutex;
+use core::ops::Range;
+
+/// The maximum number of processes followed in a chain of waiters when looking for a deadlock.
+const MAX_DEADLOCK_ITERATIONS: usize = 10;
+
+/// The type of a record lock.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LockType {
+	/// Shared lock, for reading.
+	Read,
+	/// Exclusive lock, for writing.
+	Write,
+}
+
+/// A lock on a range of bytes of a file.
+#[derive(Clone, Debug)]
+pub struct RecordLock {
+	/// The type of the lock.
+	pub type_: LockType,
+	/// The locked range of bytes. If the end is `u64::MAX`, the lock extends to the end of the
+	/// file, however large it grows.
+	pub range: Range<u64>,
+	/// The PID of the process owning the lock.
+	pub pid: Pid,
+}
+
+impl RecordLock {
+	/// Tells whether the lock overlaps the range `range`.
+	fn overlaps(&self, range: &Range<u64>) -> bool {
+		self.range.start < range.end && range.start < self.range.end
+	}
+
+	/// Tells whether the lock prevents `other` from being acquired.
+	fn conflicts_with(&self, other: &Self) -> bool {
+		self.pid != other.pid
+			&& self.overlaps(&other.range)
+			&& (self.type_ == LockType::Write || other.type_ == LockType::Write)
+	}
+}
+
+/// The record locks of the system.
+struct Locks {
+	/// The locks held on each file.
+	files: HashMap<FileLocation, Vec<RecordLock>>,
+	/// For each process waiting for a lock, the location of the file and the requested lock.
+	waiting: HashMap<Pid, (FileLocation, RecordLock)>,
+}
+
+impl Locks {
+	/// Returns the first lock on the file at `loc` preventing `lock` from being acquired.
+	fn get_conflict(&self, loc: &FileLocation, lock: &RecordLock) -> Option<&RecordLock> {
+		self.files
+			.get(loc)?
+			.iter()
+			.find(|l| l.conflicts_with(lock))
+	}
+
+	/// Tells whether the process `pid` waiting for a lock held by `owner` would cause a deadlock,
+	/// which is the case if `owner` is itself waiting, directly or not, for a lock held by `pid`.
+	fn would_deadlock(&self, pid: Pid, mut owner: Pid) -> bool {
+		for _ in 0..MAX_DEADLOCK_ITERATIONS {
+			if owner == pid {
+				return true;
+			}
+			let Some((loc, lock)) = self.waiting.get(&owner) else {
+				return false;
+			};
+			let Some(blocker) = self.get_conflict(loc, lock) else {
+				return false;
+			};
+			owner = blocker.pid;
+		}
+		false
+	}
+
+	/// Replaces the locks of process `pid` on the range `range` of the file at `loc` by a lock
+	/// of type `type_`. If `type_` is `None`, the range is unlocked.
+	///
+	/// Locks of the process that partially overlap the range are split.
+	fn apply(
+		&mut self,
+		loc: &FileLocation,
+		pid: Pid,
+		type_: Option<LockType>,
+		range: Range<u64>,
+	) -> AllocResult<()> {
+		// Build the new list before modifying anything, so that a failure leaves locks untouched
+		let mut new = Vec::new();
+		for l in self.files.get(loc).into_iter().flat_map(|locks| locks.iter()) {
+			if l.pid != pid || !l.overlaps(&range) {
+				new.push(l.clone())?;
+				continue;
+			}
+			if l.range.start < range.start {
+				new.push(RecordLock {
+					type_: l.type_,
+					range: l.range.start..range.start,
+					pid,
+				})?;
+			}
+			if l.range.end > range.end {
+				new.push(RecordLock {
+					type_: l.type_,
+					range: range.end..l.range.end,
+					pid,
+				})?;
+			}
+		}
+		if let Some(type_) = type_ {
+			new.push(RecordLock {
+				type_,
+				range,
+				pid,
+			})?;
+		}
+
+		if new.is_empty() {
+			self.files.remove(loc);
+		} else {
+			self.files.insert(loc.clone(), new)?;
+		}
+		Ok(())
+	}
+}
+
+/// The record locks of the system.
+static LOCKS: Mutex<Locks> = Mutex::new(Locks {
+	files: HashMap::new(),
+	waiting: HashMap::new(),
+});
+
+/// The whole-file locks held on a file.
+#[derive(Default)]
+struct FileFlocks {
+	/// The number of shared locks.
+	shared: usize,
+	/// Tells whether an exclusive lock is held.
+	exclusive: bool,
+}
+
+/// The whole-file locks held on each file.
+static FLOCKS: Mutex<HashMap<FileLocation, FileFlocks>> = Mutex::new(HashMap::new());
+
+/// Returns the first lock on the file at `loc` preventing `lock` from being acquired.
+///
+/// If the lock can be acquired, the function returns `None`.
+pub fn get_conflict(loc: &FileLocation, lock: &RecordLock) -> Option<RecordLock> {
+	LOCKS.lock().get_conflict(loc, lock).cloned()
+}
+
+/// Tries to set a lock of type `type_` on the range `range` of the file at `loc`, for the
+/// process `pid`. If `type_` is `None`, the range is unlocked.
+///
+/// If the lock is acquired, the function returns `true`.
+///
+/// If another process holds a conflicting lock:
+/// - if `wait` is `false`, the function returns [`errno::EAGAIN`]
+/// - if waiting would cause a deadlock, the function returns [`errno::EDEADLK`]
+/// - else, the process is registered as waiting for the lock and the function returns `false`.
+///   The caller is then expected to retry later, or to call [`cancel_wait`]
+pub fn try_set(
+	loc: &FileLocation,
+	pid: Pid,
+	type_: Option<LockType>,
+	range: Range<u64>,
+	wait: bool,
+) -> EResult<bool> {
+	let mut locks = LOCKS.lock();
+
+	// Unlocking never conflicts
+	let conflict = type_.and_then(|type_| {
+		let lock = RecordLock {
+			type_,
+			range: range.clone(),
+			pid,
+		};
+		let owner = locks.get_conflict(loc, &lock)?.pid;
+		Some((owner, lock))
+	});
+	match conflict {
+		None => {
+			locks.waiting.remove(&pid);
+			locks.apply(loc, pid, type_, range)?;
+			Ok(true)
+		}
+
+		Some(_) if !wait => Err(errno!(EAGAIN)),
+
+		Some((owner, lock)) => {
+			if locks.would_deadlock(pid, owner) {
+				locks.waiting.remove(&pid);
+				return Err(errno!(EDEADLK));
+			}
+			locks.waiting.insert(pid, (loc.clone(), lock))?;
+			Ok(false)
+		}
+	}
+}
+
+/// Unregisters the process `pid` from waiting for a lock.
+pub fn cancel_wait(pid: Pid) {
+	LOCKS.lock().waiting.remove(&pid);
+}
+
+/// Releases every locks held by the process `pid`.
+pub fn release_all(pid: Pid) {
+	let mut locks = LOCKS.lock();
+	locks.waiting.remove(&pid);
+	locks.files.retain(|_, file_locks| {
+		file_locks.retain(|l| l.pid != pid);
+		!file_locks.is_empty()
+	});
+}
+
+/// Tries to acquire a whole-file lock of type `type_` on the file at `loc`.
+///
+/// The open file description acquiring the lock must not already hold one.
+///
+/// If a conflicting lock is held, the function returns `false`.
+pub fn flock_try_set(loc: &FileLocation, type_: LockType) -> AllocResult<bool> {
+	let mut flocks = FLOCKS.lock();
+	match flocks.get_mut(loc) {
+		Some(f) if f.exclusive => Ok(false),
+		Some(f) if type_ == LockType::Write && f.shared > 0 => Ok(false),
+		Some(f) => {
+			if type_ == LockType::Read {
+				f.shared += 1;
+			} else {
+				f.exclusive = true;
+			}
+			Ok(true)
+		}
+		None => {
+			let f = FileFlocks {
+				shared: (type_ == LockType::Read) as _,
+				exclusive: type_ == LockType::Write,
+			};
+			flocks.insert(loc.clone(), f)?;
+			Ok(true)
+		}
+	}
+}
+
+/// Releases a whole-file lock of type `type_` on the file at `loc`.
+pub fn flock_release(loc: &FileLocation, type_: LockType) {
+	let mut flocks = FLOCKS.lock();
+	let Some(f) = flocks.get_mut(loc) else {
+		return;
+	};
+	match type_ {
+		LockType::Read => f.shared = f.shared.saturating_sub(1),
+		LockType::Write => f.exclusive = false,
+	}
+	if f.shared == 0 && !f.exclusive {
+		flocks.remove(loc);
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	/// The location of the file used for testing purpose.
+	const LOC: FileLocation = FileLocation::Virtual {
+		id: 0,
+	};
+
+	/// Returns a lock of type `type_` on `range` for process `pid`.
+	fn lock(type_: LockType, range: Range<u64>, pid: Pid) -> RecordLock {
+		RecordLock {
+			type_,
+			range,
+			pid,
+		}
+	}
+
+	#[test_case]
+	fn locks_conflict() {
+		let mut locks = Locks {
+			files: HashMap::new(),
+			waiting: HashMap::new(),
+		};
+		locks.apply(&LOC, 1, Some(LockType::Read), 0..10).unwrap();
+		assert!(locks
+			.get_conflict(&LOC, &lock(LockType::Read, 5..15, 2))
+			.is_none());
+		assert!(locks
+			.get_conflict(&LOC, &lock(LockType::Write, 5..15, 2))
+			.is_some());
+		assert!(locks
+			.get_conflict(&LOC, &lock(LockType::Write, 10..15, 2))
+			.is_none());
+		assert!(locks
+			.get_conflict(&LOC, &lock(LockType::Write, 0..10, 1))
+			.is_none());
+	}
+
+	#[test_case]
+	fn locks_split() {
+		let mut locks = Locks {
+			files: HashMap::new(),
+			waiting: HashMap::new(),
+		};
+		locks.apply(&LOC, 1, Some(LockType::Write), 0..u64::MAX).unwrap();
+		locks.apply(&LOC, 1, None, 10..20).unwrap();
+		assert!(locks
+			.get_conflict(&LOC, &lock(LockType::Write, 10..20, 2))
+			.is_none());
+		assert!(locks
+			.get_conflict(&LOC, &lock(LockType::Read, 9..10, 2))
+			.is_some());
+		assert!(locks
+			.get_conflict(&LOC, &lock(LockType::Read, 1000..1001, 2))
+			.is_some());
+
+		locks.apply(&LOC, 1, None, 0..u64::MAX).unwrap();
+		assert!(locks.files.is_empty());
+	}
+
+	#[test_case]
+	fn locks_deadlock() {
+		let mut locks = Locks {
+			files: HashMap::new(),
+			waiting: HashMap::new(),
+		};
+		locks.apply(&LOC, 1, Some(LockType::Write), 0..10).unwrap();
+		locks.apply(&LOC, 2, Some(LockType::Write), 10..20).unwrap();
+		// Process 1 waits for process 2
+		locks
+			.waiting
+			.insert(1, (LOC, lock(LockType::Write, 10..20, 1)))
+			.unwrap();
+		assert!(locks.would_deadlock(2, 1));
+		assert!(!locks.would_deadlock(3, 1));
+	}
+}
@@ -8,27 +8,36 @@
 
 pub mod blocking;
 pub mod buffer;
+pub mod dcache;
 pub mod fd;
+pub mod locks;
 pub mod fs;
 pub mod mapping;
 pub mod mountpoint;
 pub mod open_file;
 pub mod path;
 pub mod perm;
+pub mod quota;
 pub mod util;
 pub mod vfs;
+pub mod xattr;
 
 use crate::device;
 use crate::device::DeviceID;
 use crate::device::DeviceType;
+use crate::device::storage;
 use crate::errno;
 use crate::errno::EResult;
 use crate::errno::Errno;
+use crate::file::buffer::memfd::MemfdBuffer;
 use crate::file::buffer::pipe::PipeBuffer;
 use crate::file::buffer::socket::Socket;
+use crate::file::fs::options::MountOptions;
 use crate::file::fs::Filesystem;
+use crate::file::fs::Statfs;
 use crate::file::perm::Gid;
 use crate::file::perm::Uid;
+use crate::process::mem_space;
 use crate::process::mem_space::MemSpace;
 use crate::syscall::ioctl;
 use crate::time::clock;
@@ -37,13 +46,17 @@ use crate::time::unit::Timestamp;
 use crate::time::unit::TimestampScale;
 use crate::util::container::hashmap::HashMap;
 use crate::util::container::string::String;
+use crate::util::io;
 use crate::util::io::IO;
 use crate::util::lock::IntMutex;
 use crate::util::lock::Mutex;
 use crate::util::ptr::arc::Arc;
 use crate::util::TryClone;
+use core::any::Any;
 use core::cmp::max;
+use core::cmp::min;
 use core::ffi::c_void;
+use mountpoint::DirtyTimes;
 use mountpoint::MountPoint;
 use mountpoint::MountSource;
 use path::Path;
@@ -314,6 +327,8 @@ pub struct File {
 	pub mtime: Timestamp,
 	/// Timestamp of the last access to the file.
 	pub atime: Timestamp,
+	/// Timestamp of the creation of the file. If `None`, the filesystem does not record it.
+	pub btime: Option<Timestamp>,
 
 	/// The location the file is stored on.
 	location: FileLocation,
@@ -364,6 +379,7 @@ impl File {
 			ctime: timestamp,
 			mtime: timestamp,
 			atime: timestamp,
+			btime: None,
 
 			location,
 			content,
@@ -424,6 +440,65 @@ impl File {
 		&self.location
 	}
 
+	/// Returns the device number of the filesystem the file is located on.
+	///
+	/// If the file is not located on a filesystem, the function returns zero.
+	pub fn get_dev(&self) -> u64 {
+		let Some(mountpoint_mutex) = self.location.get_mountpoint() else {
+			return 0;
+		};
+		// TODO Clean: This is a quick fix to avoid a deadlock because vfs is also using the
+		// mountpoint and locking vfs requires disabling interrupts
+		crate::idt::wrap_disable_interrupts(|| mountpoint_mutex.lock().get_dev())
+	}
+
+	/// Returns the device number of the device the file represents, or zero if the file is not
+	/// a device file.
+	pub fn get_rdev(&self) -> u64 {
+		match self.content {
+			FileContent::BlockDevice {
+				major,
+				minor,
+			}
+			| FileContent::CharDevice {
+				major,
+				minor,
+			} => device::id::makedev(major, minor),
+			_ => 0,
+		}
+	}
+
+	/// Returns the preferred block size for I/O on the file, which is the block size of the
+	/// filesystem it is located on.
+	///
+	/// If the file is not located on a filesystem, the function returns the size of a page.
+	pub fn get_block_size(&self) -> EResult<u64> {
+		Ok(self.get_statfs()?.get_block_size() as _)
+	}
+
+	/// Returns statistics about the filesystem the file is located on.
+	///
+	/// If the file is not located on a filesystem, the function returns placeholder statistics.
+	pub fn get_statfs(&self) -> EResult<Statfs> {
+		let Some(mountpoint_mutex) = self.location.get_mountpoint() else {
+			return Ok(Statfs::anonymous());
+		};
+		let (io_mutex, fs_mutex, dev, flags) =
+			crate::idt::wrap_disable_interrupts(|| -> EResult<_> {
+				let mountpoint = mountpoint_mutex.lock();
+				Ok((
+					mountpoint.get_source().get_io()?,
+					mountpoint.get_filesystem(),
+					mountpoint.get_dev(),
+					mountpoint.get_flags(),
+				))
+			})?;
+		let mut io = io_mutex.lock();
+		let mut stat = fs_mutex.lock().get_stat(&mut *io)?;
+		stat.set_mountpoint(dev, flags);
+		Ok(stat)
+	}
+
 	/// Returns the number of hard links.
 	pub fn get_hard_links_count(&self) -> u16 {
 		self.hard_links_count
@@ -438,7 +513,12 @@ impl File {
 	}
 
 	/// Sets the file's size.
+	///
+	/// The size is also updated for the memory mappings of the file.
 	pub fn set_size(&mut self, size: u64) {
+		if size != self.size {
+			mem_space::update_mapped_file_size(&self.location, size);
+		}
 		self.size = size;
 	}
 
@@ -587,10 +667,13 @@ impl File {
 
 	/// Synchronizes the file with the device.
 	///
+	/// The timestamps of the file are written too, so the ones pending on the mountpoint are
+	/// discarded. This allows setting timestamps older than the pending ones.
+	///
 	/// If no device is associated with the file, the function does nothing.
 	pub fn sync(&self) -> Result<(), Errno> {
 		if let Some(mountpoint_mutex) = self.location.get_mountpoint() {
-			let mountpoint = mountpoint_mutex.lock();
+			let mut mountpoint = mountpoint_mutex.lock();
 
 			let io_mutex = mountpoint.get_source().get_io()?;
 			let mut io = io_mutex.lock();
@@ -598,12 +681,124 @@ impl File {
 			let fs_mutex = mountpoint.get_filesystem();
 			let mut fs = fs_mutex.lock();
 
-			fs.update_inode(&mut *io, self)
+			fs.update_inode(&mut *io, self)?;
+			mountpoint.clear_dirty(self.location.get_inode());
+			Ok(())
 		} else {
 			Ok(())
 		}
 	}
 
+	/// Records the updated timestamps `times` of the file, to be written to the filesystem on the
+	/// next synchronization instead of immediately. See [`MountPoint::mark_dirty`].
+	///
+	/// If no device is associated with the file, the function does nothing.
+	pub fn mark_dirty(&self, times: DirtyTimes) -> EResult<()> {
+		let Some(mountpoint_mutex) = self.location.get_mountpoint() else {
+			return Ok(());
+		};
+		let mut mountpoint = mountpoint_mutex.lock();
+		mountpoint.mark_dirty(self.location.get_inode(), times)
+	}
+
+	/// Writes the pending changes of the file to the filesystem, then flushes the caches of
+	/// storage devices so that the file's content reaches the medium.
+	///
+	/// If `data_only` is set, the timestamps are not written since they are not required to
+	/// retrieve the content.
+	///
+	/// Filesystems write the content and size of files synchronously, so only the timestamps
+	/// may be pending.
+	pub fn fsync(&self, data_only: bool) -> EResult<()> {
+		if let Some(mountpoint_mutex) = self.location.get_mountpoint().filter(|_| !data_only) {
+			let mut mountpoint = mountpoint_mutex.lock();
+			mountpoint.sync_inode(self.location.get_inode())?;
+		}
+		storage::flush_all()
+	}
+
+	/// Allocates the storage for `len` bytes at offset `off` in the file. See
+	/// [`Filesystem::allocate_range`].
+	///
+	/// If `keep_size` is set, the size of the file is not changed. In this case, space is not
+	/// allocated past the end of the file since it would not be released on truncation.
+	///
+	/// If the file is not located on a filesystem, the function returns [`errno::EOPNOTSUPP`].
+	pub fn allocate(&mut self, off: u64, len: u64, keep_size: bool) -> EResult<()> {
+		let end = off.checked_add(len).ok_or_else(|| errno!(EFBIG))?;
+		let end = if keep_size { min(end, self.size) } else { end };
+		if off >= end {
+			return Ok(());
+		}
+		self.io_op(|io, fs| {
+			let (Some(io_mutex), Some((fs_mutex, inode))) = (io, fs) else {
+				return Err(errno!(EOPNOTSUPP));
+			};
+			let mut io = io_mutex.lock();
+			let mut fs = fs_mutex.lock();
+			fs.allocate_range(&mut *io, inode, off, end - off)
+		})?;
+		self.set_size(max(end, self.size));
+		Ok(())
+	}
+
+	/// Deallocates the storage for `len` bytes at offset `off` in the file. See
+	/// [`Filesystem::punch_hole`].
+	///
+	/// If the file is not located on a filesystem, the function returns [`errno::EOPNOTSUPP`].
+	pub fn punch_hole(&mut self, off: u64, len: u64) -> EResult<()> {
+		self.io_op(|io, fs| {
+			let (Some(io_mutex), Some((fs_mutex, inode))) = (io, fs) else {
+				return Err(errno!(EOPNOTSUPP));
+			};
+			let mut io = io_mutex.lock();
+			let mut fs = fs_mutex.lock();
+			fs.punch_hole(&mut *io, inode, off, len)
+		})
+	}
+
+	/// Changes the size of the file to `size`. See [`Filesystem::truncate_node`].
+	///
+	/// If the file is not a regular file, the function returns [`errno::EINVAL`].
+	///
+	/// The modification and status change timestamps are updated. The caller is responsible for
+	/// synchronizing them.
+	pub fn truncate(&mut self, size: u64) -> EResult<()> {
+		if self.get_type() != FileType::Regular {
+			return Err(errno!(EINVAL));
+		}
+		match self.location {
+			FileLocation::Filesystem {
+				..
+			} => self.io_op(|io, fs| {
+				let (Some(io_mutex), Some((fs_mutex, inode))) = (io, fs) else {
+					return Err(errno!(EINVAL));
+				};
+				let mut io = io_mutex.lock();
+				let mut fs = fs_mutex.lock();
+				fs.truncate_node(&mut *io, inode, size)
+			})?,
+
+			// Virtual regular files are memfds
+			FileLocation::Virtual {
+				..
+			} => {
+				let buf_mutex = buffer::get(&self.location).ok_or_else(|| errno!(EINVAL))?;
+				let mut buf = buf_mutex.lock();
+				(&mut *buf as &mut dyn Any)
+					.downcast_mut::<MemfdBuffer>()
+					.ok_or_else(|| errno!(EINVAL))?
+					.set_size(size)?;
+			}
+		}
+		self.set_size(size);
+
+		let timestamp = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Second).unwrap_or(0);
+		self.mtime = timestamp;
+		self.ctime = timestamp;
+		Ok(())
+	}
+
 	/// Wrapper for I/O operations on files.
 	///
 	/// For the current file, the function takes a closure which provides the following arguments:
@@ -882,11 +1077,18 @@ impl IO for File {
 			}
 		})?;
 		// Update file's size
-		self.size = max(off + len, self.size);
+		self.set_size(max(off + len, self.size));
 		Ok(len)
 	}
 
 	fn poll(&mut self, mask: u32) -> Result<u32, Errno> {
+		// A regular file on a filesystem never blocks
+		if matches!(self.content, FileContent::Regular)
+			&& matches!(self.location, FileLocation::Filesystem { .. })
+		{
+			return Ok(mask & (io::POLLIN | io::POLLRDNORM | io::POLLOUT | io::POLLWRNORM));
+		}
+
 		self.io_op(|io, _| {
 			let Some(io_mutex) = io else {
 				return Ok(0);
@@ -915,7 +1117,25 @@ pub fn init(root: Option<(u32, u32)>) -> Result<(), Errno> {
 
 		None => MountSource::NoDev(String::try_from(b"tmpfs")?),
 	};
-	mountpoint::create(mount_source, None, 0, Path::root())?;
+	mountpoint::create(
+		mount_source,
+		None,
+		0,
+		Path::root(),
+		&MountOptions::default(),
+	)?;
+
+	// Mount the devtmpfs, in which device files are created
+	let dev_path = Path::from_str(fs::devtmpfs::MOUNT_PATH, false)?;
+	util::create_dirs(&dev_path)?;
+	let devtmpfs = fs::get_type(b"devtmpfs").ok_or_else(|| errno!(ENODEV))?;
+	mountpoint::create(
+		MountSource::NoDev(String::try_from(b"devtmpfs")?),
+		Some(devtmpfs),
+		0,
+		dev_path,
+		&MountOptions::default(),
+	)?;
 
 	Ok(())
 }
@@ -1,52 +1,76 @@
 //! A mount point is a directory in which a filesystem is mounted.
 
 use super::fs;
+use super::fs::options::MountOptions;
 use super::fs::Filesystem;
 use super::fs::FilesystemType;
 use super::path::Path;
 use super::vfs;
+use super::File;
 use super::FileContent;
+use super::FileType;
+use super::INode;
 use crate::device;
+use crate::device::id;
 use crate::device::DeviceID;
+use crate::device::storage;
 use crate::device::DeviceType;
 use crate::errno::AllocResult;
+use crate::errno::CollectResult;
+use crate::errno::EResult;
 use crate::errno::Errno;
+use crate::file::dcache;
 use crate::file::perm::AccessProfile;
+use crate::time::unit::Timestamp;
+use crate::util::boxed::Box;
 use crate::util::container::hashmap::HashMap;
 use crate::util::container::string::String;
+use crate::util::container::vec::Vec;
 use crate::util::io::DummyIO;
 use crate::util::io::IO;
 use crate::util::lock::Mutex;
 use crate::util::ptr::arc::Arc;
+use crate::util::DisplayableStr;
 use crate::util::TryClone;
 use core::cmp::max;
 use core::fmt;
+use core::mem;
+use core::sync::atomic::AtomicU32;
+use core::sync::atomic::Ordering::Relaxed;
 
+/// Mounts the filesystem in read-only.
+pub const FLAG_RDONLY: u32 = 1;
+/// Ignore setuid and setgid flags on the filesystem.
+pub const FLAG_NOSUID: u32 = 2;
+/// Do not allows access to device files on the filesystem.
+pub const FLAG_NODEV: u32 = 4;
+/// Do not allow files on the filesystem to be executed.
+pub const FLAG_NOEXEC: u32 = 8;
+/// Makes writes on this filesystem synchronous.
+pub const FLAG_SYNCHRONOUS: u32 = 16;
+/// Changes the flags of an existing mountpoint instead of creating a new one.
+pub const FLAG_REMOUNT: u32 = 32;
 /// Permits mandatory locking on files.
-pub const FLAG_MANDLOCK: u32 = 0b000000000001;
+pub const FLAG_MANDLOCK: u32 = 64;
 /// Do not update file (all kinds) access timestamps on the filesystem.
-pub const FLAG_NOATIME: u32 = 0b000000000010;
-/// Do not allows access to device files on the filesystem.
-pub const FLAG_NODEV: u32 = 0b000000000100;
+pub const FLAG_NOATIME: u32 = 1024;
 /// Do not update directory access timestamps on the filesystem.
-pub const FLAG_NODIRATIME: u32 = 0b000000001000;
-/// Do not allow files on the filesystem to be executed.
-pub const FLAG_NOEXEC: u32 = 0b000000010000;
-/// Ignore setuid and setgid flags on the filesystem.
-pub const FLAG_NOSUID: u32 = 0b000000100000;
-/// Mounts the filesystem in read-only.
-pub const FLAG_RDONLY: u32 = 0b000001000000;
-/// TODO doc
-pub const FLAG_REC: u32 = 0b000010000000;
-/// Update atime only if less than or equal to mtime or ctime.
-pub const FLAG_RELATIME: u32 = 0b000100000000;
+pub const FLAG_NODIRATIME: u32 = 2048;
+/// Binds a directory to another path instead of mounting a filesystem.
+pub const FLAG_BIND: u32 = 4096;
+/// With [`FLAG_BIND`], also binds the mountpoints below the source directory.
+pub const FLAG_REC: u32 = 16384;
 /// Suppresses certain warning messages in the kernel logs.
-pub const FLAG_SILENT: u32 = 0b001000000000;
+pub const FLAG_SILENT: u32 = 32768;
+/// Update atime only if less than or equal to mtime or ctime.
+pub const FLAG_RELATIME: u32 = 1 << 21;
 /// Always update the last access time when files on this filesystem are
 /// accessed. Overrides NOATIME and RELATIME.
-pub const FLAG_STRICTATIME: u32 = 0b010000000000;
-/// Makes writes on this filesystem synchronous.
-pub const FLAG_SYNCHRONOUS: u32 = 0b100000000000;
+pub const FLAG_STRICTATIME: u32 = 1 << 24;
+
+/// The maximum number of files with pending changes on a mountpoint. Beyond this number, the
+/// changes are written to the filesystem.
+const DIRTY_MAX: usize = 1024;
 
 // TODO When removing a mountpoint, return an error if another mountpoint is
 // present in a subdir
@@ -70,6 +94,14 @@ pub enum MountSource {
 	///
 	/// The string value is the name of the source.
 	NoDev(String),
+
+	/// The mountpoint is bound to a directory of a filesystem that is already mounted.
+	Bind {
+		/// The source of the filesystem.
+		source: Box<MountSource>,
+		/// The inode of the directory on the filesystem.
+		inode: INode,
+	},
 }
 
 impl MountSource {
@@ -119,6 +151,37 @@ impl MountSource {
 		}
 	}
 
+	/// Creates a source binding the directory at path `path` to another mountpoint.
+	///
+	/// If the path is not a directory, the function returns [`errno::ENOTDIR`].
+	pub fn bind(path: &Path, ap: &AccessProfile) -> Result<Self, Errno> {
+		let file_mutex = vfs::get_file_from_path(path, ap, true)?;
+		let file = file_mutex.lock();
+		if file.get_type() != FileType::Directory {
+			return Err(errno!(ENOTDIR));
+		}
+		let mountpoint_mutex = file
+			.get_location()
+			.get_mountpoint()
+			.ok_or_else(|| errno!(EINVAL))?;
+		let mountpoint = mountpoint_mutex.lock();
+		Ok(Self::Bind {
+			source: Box::new(mountpoint.get_source().get_fs_source().try_clone()?)?,
+			inode: file.get_location().get_inode(),
+		})
+	}
+
+	/// Returns the source of the filesystem, which is the source itself unless it is a bind
+	/// mount.
+	pub fn get_fs_source(&self) -> &Self {
+		match self {
+			Self::Bind {
+				source, ..
+			} => source.get_fs_source(),
+			_ => self,
+		}
+	}
+
 	/// Returns the IO interface for the mount source.
 	pub fn get_io(&self) -> Result<Arc<Mutex<dyn IO>>, Errno> {
 		match self {
@@ -138,6 +201,10 @@ impl MountSource {
 			}
 
 			Self::NoDev(_) => Ok(Arc::new(Mutex::new(DummyIO {}))? as _),
+
+			Self::Bind {
+				source, ..
+			} => source.get_io(),
 		}
 	}
 }
@@ -156,6 +223,14 @@ impl TryClone for MountSource {
 			},
 
 			Self::NoDev(name) => Self::NoDev(name.try_clone()?),
+
+			Self::Bind {
+				source,
+				inode,
+			} => Self::Bind {
+				source: source.try_clone()?,
+				inode: *inode,
+			},
 		})
 	}
 }
@@ -171,6 +246,10 @@ impl fmt::Display for MountSource {
 			} => write!(fmt, "{}.{}.{}", dev_type, major, minor),
 
 			Self::NoDev(name) => write!(fmt, "{}", name),
+
+			Self::Bind {
+				source, ..
+			} => write!(fmt, "{}", source),
 		}
 	}
 }
@@ -182,10 +261,14 @@ struct LoadedFS {
 
 	/// The filesystem.
 	fs: Arc<Mutex<dyn Filesystem>>,
+	/// The device number identifying the filesystem.
+	dev: u64,
 }
 
 /// The list of loaded filesystems associated with their respective sources.
 static FILESYSTEMS: Mutex<HashMap<MountSource, LoadedFS>> = Mutex::new(HashMap::new());
+/// The next minor number to be given to a filesystem that is not backed by a device.
+static NEXT_ANON_MINOR: AtomicU32 = AtomicU32::new(1);
 
 /// Loads a filesystem.
 ///
@@ -195,14 +278,16 @@ static FILESYSTEMS: Mutex<HashMap<MountSource, LoadedFS>> = Mutex::new(HashMap::
 /// automaticaly.
 /// - `path` is the path to the directory on which the filesystem is mounted.
 /// - `readonly` tells whether the filesystem is mount in readonly.
+/// - `options` is the set of filesystem-specific mount options.
 ///
-/// On success, the function returns the loaded filesystem.
+/// On success, the function returns the loaded filesystem along with its device number.
 fn load_fs(
 	source: MountSource,
 	fs_type: Option<Arc<dyn FilesystemType>>,
 	path: Path,
 	readonly: bool,
-) -> Result<Arc<Mutex<dyn Filesystem>>, Errno> {
+	options: &MountOptions,
+) -> Result<(Arc<Mutex<dyn Filesystem>>, u64), Errno> {
 	// Getting the I/O interface
 	let io_mutex = source.get_io()?;
 	let mut io = io_mutex.lock();
@@ -216,7 +301,18 @@ fn load_fs(
 			_ => fs::detect(&mut *io)?,
 		},
 	};
-	let fs = fs_type.load_filesystem(&mut *io, path, readonly)?;
+	let fs = fs_type.load_filesystem(&mut *io, path, readonly, options)?;
+
+	// Filesystems that are not backed by a device get an anonymous device number, with major
+	// number zero
+	let dev = match source {
+		MountSource::Device {
+			major,
+			minor,
+			..
+		} => id::makedev(major, minor),
+		_ => id::makedev(0, NEXT_ANON_MINOR.fetch_add(1, Relaxed)),
+	};
 
 	// Inserting new filesystem into filesystems list
 	let mut container = FILESYSTEMS.lock();
@@ -226,33 +322,34 @@ fn load_fs(
 			ref_count: 1,
 
 			fs: fs.clone(),
+			dev,
 		},
 	)?;
 
-	Ok(fs)
+	Ok((fs, dev))
 }
 
-/// Returns the loaded filesystem with the given source `source`.
+/// Returns the loaded filesystem with the given source `source`, along with its device number.
 ///
 /// `take` tells whether the function increments the references count.
 ///
 /// If the filesystem isn't loaded, the function returns `None`.
-fn get_fs_(source: &MountSource, take: bool) -> Option<Arc<Mutex<dyn Filesystem>>> {
+fn get_fs_(source: &MountSource, take: bool) -> Option<(Arc<Mutex<dyn Filesystem>>, u64)> {
 	let mut container = FILESYSTEMS.lock();
 
-	let fs = container.get_mut(source)?;
+	let fs = container.get_mut(source.get_fs_source())?;
 	if take {
 		fs.ref_count += 1;
 	}
 
-	Some(fs.fs.clone())
+	Some((fs.fs.clone(), fs.dev))
 }
 
 /// Returns the loaded filesystem with the given source `source`.
 ///
 /// If the filesystem isn't loaded, the function returns `None`.
 pub fn get_fs(source: &MountSource) -> Option<Arc<Mutex<dyn Filesystem>>> {
-	get_fs_(source, false)
+	get_fs_(source, false).map(|(fs, _)| fs)
 }
 
 /// Drops a reference to the filesystem with the given source `source`.
@@ -263,6 +360,7 @@ pub fn get_fs(source: &MountSource) -> Option<Arc<Mutex<dyn Filesystem>>> {
 fn drop_fs(source: &MountSource) {
 	let mut container = FILESYSTEMS.lock();
 
+	let source = source.get_fs_source();
 	if let Some(fs) = container.get_mut(source) {
 		fs.ref_count -= 1;
 
@@ -273,6 +371,74 @@ fn drop_fs(source: &MountSource) {
 	}
 }
 
+/// Detaches all loaded filesystems from their devices. Afterwards, the filesystems are read-only.
+///
+/// This function is meant to be called before powering off the system. Errors are logged and do
+/// not prevent the other filesystems from being detached.
+pub fn unmount_all() {
+	if let Err(e) = sync_all() {
+		crate::println!("Cannot synchronize filesystems: {e}");
+	}
+	// The list is copied since loading a filesystem locks its I/O interface before the list
+	let filesystems = {
+		let container = FILESYSTEMS.lock();
+		container
+			.iter()
+			.map(|(source, fs)| (source.get_io(), fs.fs.clone()))
+			.collect::<CollectResult<Vec<_>>>()
+			.0
+	};
+	let filesystems = match filesystems {
+		Ok(filesystems) => filesystems,
+		Err(e) => {
+			crate::println!("Cannot detach filesystems: {}", Errno::from(e));
+			return;
+		}
+	};
+	for (io_mutex, fs_mutex) in filesystems {
+		let res = io_mutex.and_then(|io_mutex| {
+			let mut io = io_mutex.lock();
+			let mut fs = fs_mutex.lock();
+			fs.unmount(&mut *io)
+		});
+		if let Err(e) = res {
+			let fs = fs_mutex.lock();
+			let name = DisplayableStr(fs.get_name());
+			crate::println!("Cannot detach filesystem `{name}`: {e}");
+		}
+	}
+}
+
+/// The timestamps of a file that have been updated but not yet written to the filesystem.
+///
+/// Reading or writing a file updates its timestamps. Writing them to the filesystem on each
+/// operation would be expensive, so they are kept on the mountpoint until synchronization.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct DirtyTimes {
+	/// The timestamp of the last access, if updated.
+	pub atime: Option<Timestamp>,
+	/// The timestamp of the last modification of the content, if updated.
+	pub mtime: Option<Timestamp>,
+}
+
+impl DirtyTimes {
+	/// Merges the updates `other` into `self`, keeping the most recent timestamps.
+	fn merge(&mut self, other: &Self) {
+		self.atime = max(self.atime, other.atime);
+		self.mtime = max(self.mtime, other.mtime);
+	}
+
+	/// Applies the updated timestamps to the file `file`.
+	pub fn apply(&self, file: &mut File) {
+		if let Some(atime) = self.atime {
+			file.atime = max(file.atime, atime);
+		}
+		if let Some(mtime) = self.mtime {
+			file.mtime = max(file.mtime, mtime);
+		}
+	}
+}
+
 /// Structure representing a mount point.
 pub struct MountPoint {
 	/// The ID of the mountpoint.
@@ -287,8 +453,13 @@ pub struct MountPoint {
 	source: MountSource,
 	/// The filesystem associated with the mountpoint.
 	fs: Arc<Mutex<dyn Filesystem>>,
+	/// The device number identifying the filesystem.
+	dev: u64,
 	/// The name of the filesystem's type.
 	fs_type_name: String,
+
+	/// The files with changes that have not been written to the filesystem yet, by inode.
+	dirty: HashMap<INode, DirtyTimes>,
 }
 
 impl MountPoint {
@@ -301,22 +472,33 @@ impl MountPoint {
 	/// automaticaly.
 	/// - `flags` are the mount flags.
 	/// - `path` is the path on which the filesystem is to be mounted.
+	/// - `options` is the set of filesystem-specific mount options.
 	fn new(
 		id: u32,
 		source: MountSource,
 		fs_type: Option<Arc<dyn FilesystemType>>,
 		flags: u32,
 		path: Path,
+		options: &MountOptions,
 	) -> Result<Self, Errno> {
 		// Tells whether the filesystem will be mounted in read-only
 		let readonly = flags & FLAG_RDONLY != 0;
 
-		let fs_mutex = match get_fs_(&source, true) {
+		let (fs_mutex, dev) = match get_fs_(&source, true) {
 			// Filesystem exists, do nothing
 			Some(fs) => fs,
 
+			// The filesystem of a bind mount must already be mounted
+			None if matches!(source, MountSource::Bind { .. }) => return Err(errno!(ENOENT)),
+
 			// Filesystem doesn't exist, load it
-			None => load_fs(source.try_clone()?, fs_type, path.try_clone()?, readonly)?,
+			None => load_fs(
+				source.try_clone()?,
+				fs_type,
+				path.try_clone()?,
+				readonly,
+				options,
+			)?,
 		};
 
 		// TODO Increment number of references to the filesystem
@@ -334,7 +516,10 @@ impl MountPoint {
 
 			source,
 			fs: fs_mutex,
+			dev,
 			fs_type_name,
+
+			dirty: HashMap::new(),
 		})
 	}
 
@@ -363,16 +548,114 @@ impl MountPoint {
 		&self.source
 	}
 
+	/// Returns the inode of the root directory of the mountpoint.
+	///
+	/// Arguments:
+	/// - `fs` is the filesystem associated with the mountpoint.
+	/// - `io` is the I/O interface of the mountpoint.
+	pub fn get_root_inode(&self, fs: &dyn Filesystem, io: &mut dyn IO) -> Result<INode, Errno> {
+		match &self.source {
+			MountSource::Bind {
+				inode, ..
+			} => Ok(*inode),
+			_ => fs.get_root_inode(io),
+		}
+	}
+
 	/// Returns a mutable reference to the filesystem associated with the
 	/// mountpoint.
 	pub fn get_filesystem(&self) -> Arc<Mutex<dyn Filesystem>> {
 		self.fs.clone()
 	}
 
+	/// Returns the device number identifying the mountpoint's filesystem.
+	///
+	/// Mountpoints sharing the same filesystem have the same device number.
+	pub fn get_dev(&self) -> u64 {
+		self.dev
+	}
+
 	/// Returns the name of the filesystem's type.
 	pub fn get_filesystem_type(&self) -> &String {
 		&self.fs_type_name
 	}
+
+	/// Returns the timestamps of the file at inode `inode` that have not been written to the
+	/// filesystem yet.
+	pub fn get_dirty(&self, inode: INode) -> Option<&DirtyTimes> {
+		self.dirty.get(&inode)
+	}
+
+	/// Records the updated timestamps `times` of the file at inode `inode`, to be written to the
+	/// filesystem on the next synchronization.
+	///
+	/// If the mountpoint is read-only, the function does nothing.
+	pub fn mark_dirty(&mut self, inode: INode, times: DirtyTimes) -> EResult<()> {
+		if self.is_readonly() {
+			return Ok(());
+		}
+		if let Some(dirty) = self.dirty.get_mut(&inode) {
+			dirty.merge(&times);
+			return Ok(());
+		}
+		if self.dirty.len() >= DIRTY_MAX {
+			self.sync()?;
+		}
+		self.dirty.insert(inode, times)?;
+		Ok(())
+	}
+
+	/// Discards the pending changes of the file at inode `inode`.
+	pub fn clear_dirty(&mut self, inode: INode) {
+		self.dirty.remove(&inode);
+	}
+
+	/// Writes the pending changes of the file at inode `inode` to the filesystem.
+	///
+	/// If the file has no pending change, the function does nothing.
+	pub fn sync_inode(&mut self, inode: INode) -> EResult<()> {
+		let Some(times) = self.dirty.remove(&inode) else {
+			return Ok(());
+		};
+		let io_mutex = self.source.get_io()?;
+		let mut io = io_mutex.lock();
+		let mut fs = self.fs.lock();
+		write_times(&mut *fs, &mut *io, inode, &times)
+	}
+
+	/// Writes the pending changes of every file of the mountpoint to the filesystem.
+	///
+	/// Every file is written even if an error occurs. The first error is returned.
+	pub fn sync(&mut self) -> EResult<()> {
+		if self.dirty.is_empty() {
+			return Ok(());
+		}
+		let dirty = mem::take(&mut self.dirty);
+		let io_mutex = self.source.get_io()?;
+		let mut io = io_mutex.lock();
+		let mut fs = self.fs.lock();
+		let mut res = Ok(());
+		for (inode, times) in dirty.iter() {
+			let r = write_times(&mut *fs, &mut *io, *inode, times);
+			res = res.and(r);
+		}
+		res
+	}
+}
+
+/// Writes the updated timestamps `times` of the file at inode `inode` on the filesystem `fs`.
+///
+/// The file is reloaded from the filesystem so that attributes changed since the timestamps were
+/// updated are not overwritten with stale values.
+fn write_times(
+	fs: &mut dyn Filesystem,
+	io: &mut dyn IO,
+	inode: INode,
+	times: &DirtyTimes,
+) -> EResult<()> {
+	let mut file = fs.load_file(io, inode, String::new())?;
+	times.apply(&mut file);
+	fs.update_inode(io, &file)
 }
 
 impl Drop for MountPoint {
@@ -395,11 +678,13 @@ pub static PATH_TO_ID: Mutex<HashMap<Path, u32>> = Mutex::new(HashMap::new());
 /// - `fs_type` is the filesystem type. If `None`, the function tries to detect it automaticaly.
 /// - `flags` are the mount flags.
 /// - `path` is the path on which the filesystem is to be mounted.
+/// - `options` is the set of filesystem-specific mount options.
 pub fn create(
 	source: MountSource,
 	fs_type: Option<Arc<dyn FilesystemType>>,
 	flags: u32,
 	path: Path,
+	options: &MountOptions,
 ) -> Result<Arc<Mutex<MountPoint>>, Errno> {
 	// TODO clean
 	// PATH_TO_ID is locked first and during the whole function to prevent a race condition between
@@ -424,6 +709,7 @@ pub fn create(
 		fs_type,
 		flags,
 		path.try_clone()?,
+		options,
 	)?))?;
 
 	// Insertion
@@ -440,6 +726,87 @@ pub fn create(
 	Ok(mountpoint)
 }
 
+/// Binds the directory at `source` to `target`, making it accessible from both paths.
+///
+/// Arguments:
+/// - `source` is the absolute path to the directory to bind.
+/// - `target` is the absolute path on which the directory is to be mounted.
+/// - `flags` are the mount flags.
+/// - `recursive` tells whether mountpoints below `source` are also bound below `target`.
+/// - `ap` is the access profile used to resolve `source`.
+pub fn bind(
+	source: &Path,
+	target: Path,
+	flags: u32,
+	recursive: bool,
+	ap: &AccessProfile,
+) -> Result<(), Errno> {
+	let bind_source = MountSource::bind(source, ap)?;
+
+	// Collect mountpoints below the source before creating the new one, sorted so that parents
+	// are bound before their children
+	let mut submounts = Vec::new();
+	if recursive {
+		let mount_points = MOUNT_POINTS.lock();
+		for (_, mp) in mount_points.iter() {
+			let mp = mp.lock();
+			let path = mp.get_path();
+			if path.get_elements_count() <= source.get_elements_count()
+				|| !path.begins_with(source)
+			{
+				continue;
+			}
+			let sub_path = path.range_from(source.get_elements_count()..)?;
+			submounts.push((
+				target.concat(&sub_path)?,
+				mp.get_source().try_clone()?,
+				mp.get_flags(),
+			))?;
+		}
+	}
+	submounts.sort_unstable_by_key(|(path, ..)| path.get_elements_count());
+
+	create(bind_source, None, flags, target, &MountOptions::default())?;
+	for (path, source, flags) in submounts {
+		create(source, None, flags, path, &MountOptions::default())?;
+	}
+
+	Ok(())
+}
+
+/// Changes the flags of the mountpoint at the given path `path`.
+///
+/// Arguments:
+/// - `path` is the path of the mountpoint.
+/// - `flags` are the new mount flags.
+/// - `bind` tells whether only the flags of the mountpoint are changed. Otherwise, the filesystem
+/// is also switched between read-only and read-write, which affects every mountpoint using it.
+///
+/// If the mountpoint doesn't exist, the function returns `EINVAL`.
+pub fn remount(path: &Path, flags: u32, bind: bool) -> Result<(), Errno> {
+	let mountpoint_mutex = from_path(path).ok_or_else(|| errno!(EINVAL))?;
+	let mut mountpoint = mountpoint_mutex.lock();
+
+	let readonly = flags & FLAG_RDONLY != 0;
+	// Pending changes cannot be written once the filesystem is read-only
+	if readonly {
+		mountpoint.sync()?;
+	}
+	if !bind {
+		let io_mutex = mountpoint.source.get_io()?;
+		let mut io = io_mutex.lock();
+		let mut fs = mountpoint.fs.lock();
+		fs.remount(&mut *io, readonly)?;
+	}
+	// Make sure data written before switching to read-only reaches the medium
+	if readonly && !mountpoint.is_readonly() {
+		storage::flush_all()?;
+	}
+
+	mountpoint.flags = flags;
+	Ok(())
+}
+
 /// Removes the mountpoint at the given path `path`.
 ///
 /// Data is sychronized to the associated storage device, if any, before removing the mountpoint.
@@ -452,19 +819,41 @@ pub fn remove(path: &Path) -> Result<(), Errno> {
 	let mut mount_points = MOUNT_POINTS.lock();
 
 	let id = *path_to_id.get(path).ok_or(errno!(EINVAL))?;
-	let _mountpoint = mount_points.get(&id).ok_or(errno!(EINVAL))?;
+	let mountpoint = mount_points.get(&id).ok_or(errno!(EINVAL))?;
 
 	// TODO Check if busy (EBUSY)
 	// TODO Check if another mount point is present in a subdirectory (EBUSY)
 
-	// TODO sync fs
+	mountpoint.lock().sync()?;
+	storage::flush_all()?;
 
 	path_to_id.remove(path);
 	mount_points.remove(&id);
+	dcache::invalidate_mountpoint(id);
 
 	Ok(())
 }
 
+/// Writes the pending changes of every mountpoint to their filesystems, then flushes the caches
+/// of storage devices.
+///
+/// Every mountpoint is synchronized even if an error occurs. The first error is returned.
+pub fn sync_all() -> EResult<()> {
+	// The list is copied since synchronizing a mountpoint may require locking it again
+	let mount_points = MOUNT_POINTS
+		.lock()
+		.iter()
+		.map(|(_, mp)| mp.clone())
+		.collect::<CollectResult<Vec<_>>>()
+		.0?;
+	let mut res = Ok(());
+	for mp in mount_points {
+		let r = mp.lock().sync();
+		res = res.and(r);
+	}
+	res.and(storage::flush_all())
+}
+
 /// Returns the deepest mountpoint in the path `path`.
 ///
 /// If no mountpoint is in the path, the function returns `None`.
@@ -509,3 +898,27 @@ pub fn from_path(path: &Path) -> Option<Arc<Mutex<MountPoint>>> {
 	let id = container.get(path)?;
 	from_id(*id)
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test_case]
+	fn mountpoint_dirty_times_merge() {
+		let mut times = DirtyTimes {
+			atime: Some(10),
+			mtime: None,
+		};
+		times.merge(&DirtyTimes {
+			atime: Some(5),
+			mtime: Some(7),
+		});
+		assert_eq!(
+			times,
+			DirtyTimes {
+				atime: Some(10),
+				mtime: Some(7),
+			}
+		);
+	}
+}
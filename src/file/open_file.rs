@@ -6,24 +6,34 @@ use crate::device::DeviceType;
 use crate::errno;
 use crate::errno::EResult;
 use crate::errno::Errno;
+use crate::file::blocking::EventCounters;
+use crate::file::blocking::PollTable;
 use crate::file::buffer;
+use crate::file::buffer::pipe::PipeBuffer;
+use crate::file::buffer::Buffer;
+use crate::file::locks;
+use crate::file::locks::LockType;
 use crate::file::mountpoint;
+use crate::file::mountpoint::DirtyTimes;
 use crate::file::DeviceID;
 use crate::file::File;
 use crate::file::FileContent;
 use crate::file::FileLocation;
 use crate::process::mem_space::ptr::SyscallPtr;
 use crate::process::mem_space::MemSpace;
+use crate::process::scheduler;
 use crate::process::Process;
 use crate::syscall::ioctl;
 use crate::time::clock;
 use crate::time::clock::CLOCK_MONOTONIC;
 use crate::time::unit::TimestampScale;
 use crate::util::container::hashmap::HashMap;
+use crate::util::io;
 use crate::util::io::IO;
 use crate::util::lock::IntMutex;
 use crate::util::lock::Mutex;
 use crate::util::ptr::arc::Arc;
+use core::any::Any;
 use core::cmp::min;
 use core::ffi::c_int;
 use core::ffi::c_void;
@@ -86,6 +96,8 @@ pub struct OpenFile {
 	/// The current offset in the file.
 	/// If pointing to a directory, this is the offset in directory entries.
 	curr_off: u64,
+	/// The whole-file lock held by the open file description, set with `flock`.
+	flock: Option<LockType>,
 }
 
 impl OpenFile {
@@ -98,13 +110,18 @@ impl OpenFile {
 	/// If an open file already exists for this location, the function add the given flags to the
 	/// already existing instance and returns it.
 	pub fn new(file: Arc<Mutex<File>>, flags: i32) -> EResult<Self> {
-		let location = file.lock().get_location().clone();
+		let (location, is_fifo) = {
+			let file = file.lock();
+			let is_fifo = matches!(file.get_content(), FileContent::Fifo);
+			(file.get_location().clone(), is_fifo)
+		};
 		let s = Self {
 			file: Some(file),
 			location: location.clone(),
 			flags,
 
 			curr_off: 0,
+			flock: None,
 		};
 
 		// Update the open file counter
@@ -117,8 +134,14 @@ impl OpenFile {
 			}
 		}
 
-		// If the file points to a buffer, increment the number of open ends
-		if let Some(buff_mutex) = buffer::get(&location) {
+		// If the file points to a buffer, increment the number of open ends. The buffer of a named
+		// pipe is created at its first opening
+		let buff_mutex = if is_fifo {
+			Some(buffer::get_or_default::<PipeBuffer>(&location)?)
+		} else {
+			buffer::get(&location)
+		};
+		if let Some(buff_mutex) = buff_mutex {
 			let mut buff = buff_mutex.lock();
 			buff.increment_open(s.can_read(), s.can_write());
 		}
@@ -126,6 +149,56 @@ impl OpenFile {
 		Ok(s)
 	}
 
+	/// If the file is a FIFO, waits until its other end is open.
+	///
+	/// Opening for reading waits for a writer. Opening for writing waits for a reader, or fails
+	/// with [`errno::ENXIO`] if there is none and `O_NONBLOCK` is set. Opening for both reading
+	/// and writing does not wait.
+	///
+	/// The current process must not be locked.
+	pub fn wait_fifo_peer(&self) -> EResult<()> {
+		let (read, write) = (self.can_read(), self.can_write());
+		let nonblock = self.flags & O_NONBLOCK != 0;
+		if read == write || (read && nonblock) {
+			return Ok(());
+		}
+		if !matches!(self.get_file().lock().get_content(), FileContent::Fifo) {
+			return Ok(());
+		}
+		let Some(buff_mutex) = buffer::get(&self.location) else {
+			return Ok(());
+		};
+
+		let proc_mutex = Process::current_assert();
+		loop {
+			{
+				let mut buff = buff_mutex.lock();
+				let Some(pipe) = (&mut *buff as &mut dyn Any).downcast_mut::<PipeBuffer>() else {
+					return Ok(());
+				};
+				let has_peer = if read {
+					pipe.has_writers()
+				} else {
+					pipe.has_readers()
+				};
+				if has_peer {
+					return Ok(());
+				}
+				if nonblock {
+					return Err(errno!(ENXIO));
+				}
+
+				let mut proc = proc_mutex.lock();
+				if proc.get_next_signal().is_some() {
+					return Err(errno!(ERESTARTSYS));
+				}
+				pipe.add_waiting_process(&mut proc, io::POLLIN | io::POLLOUT)?;
+			}
+
+			scheduler::end_tick();
+		}
+	}
+
 	/// Tells whether the file at the given location is open.
 	pub fn is_open(loc: &FileLocation) -> bool {
 		OPEN_FILES.lock().contains_key(loc)
@@ -196,6 +269,18 @@ impl OpenFile {
 		mp_guard.get_flags() & mountpoint::FLAG_NOATIME != 0
 	}
 
+	/// Returns the whole-file lock held by the open file description.
+	pub fn get_flock(&self) -> Option<LockType> {
+		self.flock
+	}
+
+	/// Sets the whole-file lock held by the open file description.
+	///
+	/// The lock itself must be acquired or released with [`locks`] by the caller.
+	pub fn set_flock(&mut self, flock: Option<LockType>) {
+		self.flock = flock;
+	}
+
 	/// Returns the current offset in the file.
 	pub fn get_offset(&self) -> u64 {
 		self.curr_off
@@ -291,6 +376,84 @@ impl OpenFile {
 
 		Ok(())
 	}
+
+	/// Registers the given poll table on the file.
+	///
+	/// Arguments:
+	/// - `proc` is the process waiting on the table.
+	/// - `table` is the table to register.
+	/// - `mask` is the mask of poll event to wait for.
+	///
+	/// If the file cannot block, such as a regular file, the function does nothing since the
+	/// file is always ready.
+	pub fn register_poll(
+		&mut self,
+		proc: &Process,
+		table: &PollTable,
+		mask: u32,
+	) -> Result<(), Errno> {
+		let file = self.get_file().lock();
+		match file.get_content() {
+			FileContent::Fifo | FileContent::Socket => {
+				if let Some(buff_mutex) = buffer::get(self.get_location()) {
+					let mut buff = buff_mutex.lock();
+					return buff.register_poll(proc, table, mask);
+				}
+			}
+
+			FileContent::BlockDevice {
+				major,
+				minor,
+			} => {
+				let dev_mutex = device::get(&DeviceID {
+					type_: DeviceType::Block,
+					major: *major,
+					minor: *minor,
+				});
+
+				if let Some(dev_mutex) = dev_mutex {
+					let mut dev = dev_mutex.lock();
+					return dev.get_handle().register_poll(proc, table, mask);
+				}
+			}
+
+			FileContent::CharDevice {
+				major,
+				minor,
+			} => {
+				let dev_mutex = device::get(&DeviceID {
+					type_: DeviceType::Char,
+					major: *major,
+					minor: *minor,
+				});
+
+				if let Some(dev_mutex) = dev_mutex {
+					let mut dev = dev_mutex.lock();
+					return dev.get_handle().register_poll(proc, table, mask);
+				}
+			}
+
+			_ => {}
+		}
+
+		Ok(())
+	}
+
+	/// Returns the number of occurrences of each poll event on the file.
+	///
+	/// If the file doesn't track events, the function returns `None`.
+	pub fn get_event_counters(&self) -> Option<EventCounters> {
+		let file = self.get_file().lock();
+		match file.get_content() {
+			FileContent::Fifo | FileContent::Socket => {
+				let buff_mutex = buffer::get(self.get_location())?;
+				let buff = buff_mutex.lock();
+				buff.get_event_counters()
+			}
+
+			_ => None,
+		}
+	}
 }
 
 impl IO for OpenFile {
@@ -314,7 +477,10 @@ impl IO for OpenFile {
 		let timestamp = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Second).unwrap_or(0);
 		if self.is_atime_updated() {
 			file.atime = timestamp;
-			file.sync()?; // TODO Lazy
+			file.mark_dirty(DirtyTimes {
+				atime: Some(timestamp),
+				mtime: None,
+			})?;
 		}
 
 		let (len, eof) = file.read(self.curr_off, buf)?;
@@ -342,11 +508,15 @@ impl IO for OpenFile {
 
 		// Update access timestamps
 		let timestamp = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Second).unwrap_or(0);
-		if self.is_atime_updated() {
-			file.atime = timestamp;
+		let atime = self.is_atime_updated().then_some(timestamp);
+		if let Some(atime) = atime {
+			file.atime = atime;
 		}
 		file.mtime = timestamp;
-		file.sync()?; // TODO Lazy
+		file.mark_dirty(DirtyTimes {
+			atime,
+			mtime: Some(timestamp),
+		})?;
 
 		let len = file.write(self.curr_off, buf)?;
 
@@ -361,20 +531,32 @@ impl IO for OpenFile {
 
 impl Drop for OpenFile {
 	fn drop(&mut self) {
+		if let Some(flock) = self.flock {
+			locks::flock_release(&self.location, flock);
+		}
 		// If the file points to a buffer, decrement the number of open ends
 		if let Some(buff_mutex) = buffer::get(&self.location) {
 			let mut buff = buff_mutex.lock();
 			buff.decrement_open(self.can_read(), self.can_write());
 		}
 		// Update the open file counter
-		{
+		let closed = {
 			let mut open_files = OPEN_FILES.lock();
-			if let Some(count) = open_files.get_mut(&self.location) {
-				*count -= 1;
-				if *count == 0 {
-					open_files.remove(&self.location);
+			match open_files.get_mut(&self.location) {
+				Some(count) => {
+					*count -= 1;
+					let closed = *count == 0;
+					if closed {
+						open_files.remove(&self.location);
+					}
+					closed
 				}
+				None => false,
 			}
+		};
+		// A virtual location cannot be opened again once closed
+		if closed && matches!(self.location, FileLocation::Virtual { .. }) {
+			buffer::release(&self.location);
 		}
 	}
 }
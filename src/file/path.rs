@@ -129,6 +129,28 @@ impl Path {
 		true
 	}
 
+	/// Tells whether the current path is located beneath the directory at path `dir`, without
+	/// escaping it with `..` at any point.
+	///
+	/// Symbolic links are not taken into account.
+	pub fn is_beneath(&self, dir: &Self) -> bool {
+		if !self.begins_with(dir) {
+			return false;
+		}
+		let mut depth = 0usize;
+		for part in &self.parts.as_slice()[dir.parts.len()..] {
+			match part.as_bytes() {
+				b"." => {}
+				b".." => match depth.checked_sub(1) {
+					Some(d) => depth = d,
+					None => return false,
+				},
+				_ => depth += 1,
+			}
+		}
+		true
+	}
+
 	/// Returns a subpath in the given range `range`.
 	pub fn range(&self, range: Range<usize>) -> Result<Path, Errno> {
 		Ok(Self {
@@ -261,5 +283,17 @@ mod test {
 		assert!(!Path::from_str(b"./", false).unwrap().is_absolute());
 	}
 
+	#[test_case]
+	fn path_is_beneath() {
+		let dir = Path::from_str(b"/a/b", false).unwrap();
+		let beneath = |p: &[u8]| Path::from_str(p, false).unwrap().is_beneath(&dir);
+		assert!(beneath(b"/a/b"));
+		assert!(beneath(b"/a/b/c/./d"));
+		assert!(beneath(b"/a/b/c/.."));
+		assert!(!beneath(b"/a/b/.."));
+		assert!(!beneath(b"/a/b/c/../../d"));
+		assert!(!beneath(b"/a/c"));
+	}
+
 	// TODO test concat
 }
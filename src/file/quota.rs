@@ -0,0 +1,302 @@
+//! Disk quotas limit the amount of space and the number of inodes each user and group can use on
+//! a filesystem.
+//!
+//! Quotas are accounted by the filesystem itself, which charges every allocation to the owner
+//! user and group of the file. Once the hard limit is reached, or once the soft limit has been
+//! exceeded for longer than the grace period, allocations fail with [`errno::EDQUOT`].
+//!
+//! Quota limits are kept in memory and are lost when the filesystem is unmounted.
+
+use crate::errno;
+use crate::errno::EResult;
+use crate::file::perm::Gid;
+use crate::file::perm::Uid;
+use crate::time::clock;
+use crate::time::clock::CLOCK_REALTIME;
+use crate::time::unit::TimestampScale;
+use crate::util::container::hashmap::HashMap;
+
+/// Quota type: user quotas.
+pub const USRQUOTA: usize = 0;
+/// Quota type: group quotas.
+pub const GRPQUOTA: usize = 1;
+/// The number of quota types.
+pub const MAXQUOTAS: usize = 2;
+
+/// The size of the unit of space limits, in bytes.
+pub const QIF_DQBLKSIZE: u64 = 1024;
+/// The default grace period, in seconds.
+pub const DEFAULT_GRACE: u64 = 7 * 24 * 60 * 60;
+
+/// The limits and usage of a user or group.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DiskQuota {
+	/// The hard limit of space, in units of [`QIF_DQBLKSIZE`]. Zero means no limit.
+	pub block_hard_limit: u64,
+	/// The soft limit of space, in units of [`QIF_DQBLKSIZE`]. Zero means no limit.
+	pub block_soft_limit: u64,
+	/// The used space, in bytes.
+	pub space: u64,
+	/// The hard limit of inodes. Zero means no limit.
+	pub inode_hard_limit: u64,
+	/// The soft limit of inodes. Zero means no limit.
+	pub inode_soft_limit: u64,
+	/// The number of used inodes.
+	pub inodes: u64,
+	/// The timestamp at which the soft limit of space starts being enforced. Zero if the soft
+	/// limit is not exceeded.
+	pub block_time: u64,
+	/// The timestamp at which the soft limit of inodes starts being enforced. Zero if the soft
+	/// limit is not exceeded.
+	pub inode_time: u64,
+}
+
+impl DiskQuota {
+	/// Checks whether the usage can grow to `space` bytes and `inodes` inodes at the timestamp
+	/// `now`.
+	fn check(&self, space: u64, inodes: u64, now: u64) -> EResult<()> {
+		if inodes > self.inodes {
+			if self.inode_hard_limit != 0 && inodes > self.inode_hard_limit {
+				return Err(errno!(EDQUOT));
+			}
+			if self.inode_soft_limit != 0
+				&& inodes > self.inode_soft_limit
+				&& self.inode_time != 0
+				&& now >= self.inode_time
+			{
+				return Err(errno!(EDQUOT));
+			}
+		}
+		if space > self.space {
+			if self.block_hard_limit != 0 && space > self.block_hard_limit * QIF_DQBLKSIZE {
+				return Err(errno!(EDQUOT));
+			}
+			if self.block_soft_limit != 0
+				&& space > self.block_soft_limit * QIF_DQBLKSIZE
+				&& self.block_time != 0
+				&& now >= self.block_time
+			{
+				return Err(errno!(EDQUOT));
+			}
+		}
+		Ok(())
+	}
+
+	/// Updates the grace timers according to the current usage.
+	///
+	/// Arguments:
+	/// - `info` is the information of the quota type.
+	/// - `now` is the current timestamp.
+	fn update_timers(&mut self, info: &QuotaInfo, now: u64) {
+		if self.block_soft_limit != 0 && self.space > self.block_soft_limit * QIF_DQBLKSIZE {
+			if self.block_time == 0 {
+				self.block_time = now + info.block_grace;
+			}
+		} else {
+			self.block_time = 0;
+		}
+		if self.inode_soft_limit != 0 && self.inodes > self.inode_soft_limit {
+			if self.inode_time == 0 {
+				self.inode_time = now + info.inode_grace;
+			}
+		} else {
+			self.inode_time = 0;
+		}
+	}
+}
+
+/// Information about a quota type.
+#[derive(Clone, Copy, Debug)]
+pub struct QuotaInfo {
+	/// The grace period of space soft limits, in seconds.
+	pub block_grace: u64,
+	/// The grace period of inodes soft limits, in seconds.
+	pub inode_grace: u64,
+}
+
+impl Default for QuotaInfo {
+	fn default() -> Self {
+		Self {
+			block_grace: DEFAULT_GRACE,
+			inode_grace: DEFAULT_GRACE,
+		}
+	}
+}
+
+/// The quotas of a filesystem.
+#[derive(Default)]
+pub struct Quota {
+	/// Tells, for each quota type, whether quotas are enabled.
+	enabled: [bool; MAXQUOTAS],
+	/// For each quota type, the information of the type.
+	info: [QuotaInfo; MAXQUOTAS],
+	/// For each quota type, the quotas by user or group ID.
+	quotas: [HashMap<u32, DiskQuota>; MAXQUOTAS],
+}
+
+impl Quota {
+	/// Tells whether quotas of type `kind` are enabled.
+	pub fn is_enabled(&self, kind: usize) -> bool {
+		self.enabled[kind]
+	}
+
+	/// Enables quotas of type `kind`.
+	///
+	/// `usage` is the current usage of each user or group ID, as a number of bytes and a number
+	/// of inodes.
+	///
+	/// If quotas are already enabled, the function returns [`errno::EBUSY`].
+	pub fn enable(&mut self, kind: usize, usage: HashMap<u32, (u64, u64)>) -> EResult<()> {
+		if self.enabled[kind] {
+			return Err(errno!(EBUSY));
+		}
+		let now = clock::current_time(CLOCK_REALTIME, TimestampScale::Second)?;
+		// Limits set while quotas were disabled are kept
+		self.quotas[kind].retain(|_, dq| {
+			dq.space = 0;
+			dq.inodes = 0;
+			true
+		});
+		for (id, (space, inodes)) in usage.iter() {
+			let dq = self.get_or_insert(kind, *id)?;
+			dq.space = *space;
+			dq.inodes = *inodes;
+		}
+		let info = self.info[kind];
+		self.quotas[kind].retain(|_, dq| {
+			dq.update_timers(&info, now);
+			true
+		});
+		self.enabled[kind] = true;
+		Ok(())
+	}
+
+	/// Disables quotas of type `kind`.
+	///
+	/// If quotas are not enabled, the function returns [`errno::ESRCH`].
+	pub fn disable(&mut self, kind: usize) -> EResult<()> {
+		if !self.enabled[kind] {
+			return Err(errno!(ESRCH));
+		}
+		self.enabled[kind] = false;
+		Ok(())
+	}
+
+	/// Returns the entry of the ID `id` for quota type `kind`, inserting it if missing.
+	fn get_or_insert(&mut self, kind: usize, id: u32) -> EResult<&mut DiskQuota> {
+		if !self.quotas[kind].contains_key(&id) {
+			self.quotas[kind].insert(id, DiskQuota::default())?;
+		}
+		Ok(self.quotas[kind].get_mut(&id).unwrap())
+	}
+
+	/// Returns the quota of the ID `id` for quota type `kind`.
+	pub fn get(&self, kind: usize, id: u32) -> DiskQuota {
+		self.quotas[kind].get(&id).copied().unwrap_or_default()
+	}
+
+	/// Sets the quota of the ID `id` for quota type `kind`.
+	pub fn set(&mut self, kind: usize, id: u32, quota: DiskQuota) -> EResult<()> {
+		let now = clock::current_time(CLOCK_REALTIME, TimestampScale::Second)?;
+		let info = self.info[kind];
+		let dq = self.get_or_insert(kind, id)?;
+		*dq = quota;
+		dq.update_timers(&info, now);
+		Ok(())
+	}
+
+	/// Returns the information of quota type `kind`.
+	pub fn get_info(&self, kind: usize) -> QuotaInfo {
+		self.info[kind]
+	}
+
+	/// Sets the information of quota type `kind`.
+	pub fn set_info(&mut self, kind: usize, info: QuotaInfo) {
+		self.info[kind] = info;
+	}
+
+	/// Charges the usage of space and inodes to the user `uid` and the group `gid`.
+	///
+	/// Arguments:
+	/// - `space` is the number of bytes to add to the usage. It may be negative to release space.
+	/// - `inodes` is the number of inodes to add to the usage. It may be negative to release
+	/// inodes.
+	/// - `force` tells whether the limits are ignored. This is used to account allocations that
+	/// have already been performed.
+	///
+	/// Files owned by the superuser are not subject to limits.
+	///
+	/// If a limit is exceeded, the function returns [`errno::EDQUOT`] and the usage is left
+	/// unchanged.
+	pub fn charge(
+		&mut self,
+		uid: Uid,
+		gid: Gid,
+		space: i64,
+		inodes: i64,
+		force: bool,
+	) -> EResult<()> {
+		if !self.enabled.iter().any(|e| *e) || (space == 0 && inodes == 0) {
+			return Ok(());
+		}
+		let now = clock::current_time(CLOCK_REALTIME, TimestampScale::Second)?;
+		let ids = [uid as u32, gid as u32];
+		let apply = |dq: &DiskQuota| {
+			(
+				dq.space.saturating_add_signed(space),
+				dq.inodes.saturating_add_signed(inodes),
+			)
+		};
+		if !force && uid != 0 {
+			for kind in (0..MAXQUOTAS).filter(|k| self.enabled[*k]) {
+				let dq = self.get(kind, ids[kind]);
+				let (new_space, new_inodes) = apply(&dq);
+				dq.check(new_space, new_inodes, now)?;
+			}
+		}
+		for (kind, id) in ids.into_iter().enumerate() {
+			if !self.enabled[kind] {
+				continue;
+			}
+			let info = self.info[kind];
+			let dq = self.get_or_insert(kind, id)?;
+			(dq.space, dq.inodes) = apply(dq);
+			dq.update_timers(&info, now);
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test_case]
+	fn quota_limits() {
+		let mut quota = Quota::default();
+		quota.enable(USRQUOTA, HashMap::new()).unwrap();
+		quota
+			.set(
+				USRQUOTA,
+				1000,
+				DiskQuota {
+					block_hard_limit: 4,
+					inode_hard_limit: 2,
+					..Default::default()
+				},
+			)
+			.unwrap();
+
+		quota.charge(1000, 1000, 4096, 1, false).unwrap();
+		assert!(quota.charge(1000, 1000, 1, 0, false).is_err());
+		quota.charge(1000, 1000, 0, 1, false).unwrap();
+		assert!(quota.charge(1000, 1000, 0, 1, false).is_err());
+		// Releasing is always allowed
+		quota.charge(1000, 1000, -4096, -2, false).unwrap();
+		assert_eq!(quota.get(USRQUOTA, 1000).space, 0);
+		assert_eq!(quota.get(USRQUOTA, 1000).inodes, 0);
+		// Other users and the superuser are not limited
+		quota.charge(1001, 1000, 1 << 20, 10, false).unwrap();
+		quota.charge(0, 0, 1 << 20, 10, false).unwrap();
+	}
+}
@@ -7,40 +7,94 @@
 use crate::errno;
 use crate::errno::EResult;
 use crate::file::buffer;
+use crate::file::dcache;
+use crate::file::fs::Filesystem;
 use crate::file::mapping;
 use crate::file::mountpoint;
 use crate::file::open_file::OpenFile;
 use crate::file::path::Path;
 use crate::file::perm;
 use crate::file::perm::AccessProfile;
+use crate::file::xattr;
 use crate::file::File;
 use crate::file::FileContent;
 use crate::file::FileLocation;
 use crate::file::FileType;
+use crate::file::INode;
 use crate::file::Mode;
 use crate::file::MountPoint;
 use crate::limits;
+use crate::time::clock;
+use crate::time::clock::CLOCK_MONOTONIC;
+use crate::time::unit::Timestamp;
+use crate::time::unit::TimestampScale;
 use crate::util::container::string::String;
+use crate::util::container::vec::Vec;
+use crate::util::io::IO;
 use crate::util::lock::Mutex;
 use crate::util::ptr::arc::Arc;
 use crate::util::TryClone;
+use core::ffi::c_int;
+use core::ptr;
 use core::ptr::NonNull;
 
-// TODO implement and use cache
-
 /// Updates the location of the file `file` according to the given mountpoint
 /// `mountpoint`.
 ///
+/// The timestamps of the file that have not been written to the filesystem yet are also applied.
+///
 /// If the file in not located on a filesystem, the function does nothing.
 fn update_location(file: &mut File, mountpoint: &MountPoint) {
 	if let FileLocation::Filesystem {
-		mountpoint_id, ..
+		mountpoint_id,
+		inode,
 	} = &mut file.location
 	{
 		*mountpoint_id = mountpoint.get_id();
+		if let Some(times) = mountpoint.get_dirty(*inode).copied() {
+			times.apply(file);
+		}
 	}
 }
 
+/// Returns the current timestamp used for files.
+fn current_timestamp() -> Timestamp {
+	clock::current_time(CLOCK_MONOTONIC, TimestampScale::Second).unwrap_or(0)
+}
+
+/// Sets the modification and status change timestamps of the directory `inode` on the
+/// filesystem `fs` to `ts`, after its entries changed.
+///
+/// The change of the entries is committed at this point, so errors are ignored rather than
+/// reported for an operation that succeeded.
+fn touch_dir(fs: &mut dyn Filesystem, io: &mut dyn IO, inode: INode, ts: Timestamp) {
+	let _ = fs.touch(io, inode, ts);
+}
+
+/// Creates a regular file that is not located on any filesystem, whose content is provided by
+/// the buffer registered at location `location`.
+///
+/// Arguments:
+/// - `name` is the name of the file.
+/// - `ap` is the access profile of the agent creating the file, which becomes its owner.
+/// - `mode` is the permissions of the file.
+pub fn create_virtual_file(
+	name: String,
+	ap: &AccessProfile,
+	mode: Mode,
+	location: FileLocation,
+) -> EResult<Arc<Mutex<File>>> {
+	let file = File::new(
+		name,
+		ap.get_euid(),
+		ap.get_egid(),
+		mode,
+		location,
+		FileContent::Regular,
+	)?;
+	Ok(Arc::new(Mutex::new(file))?)
+}
+
 /// Returns the file corresponding to the given location `location`.
 ///
 /// This function doesn't set the name of the file since it cannot be known solely on its
@@ -89,18 +143,138 @@ pub fn get_file_by_location(location: &FileLocation) -> EResult<Arc<Mutex<File>>
 	}
 }
 
+/// Returns the inode of the file `name` located in the directory with inode `parent`, using the
+/// directory entry cache if the filesystem allows it.
+///
+/// Arguments:
+/// - `fs` is the filesystem.
+/// - `io` is the IO interface.
+/// - `mountpoint_id` is the ID of the mountpoint of the filesystem.
+///
+/// If the file doesn't exist, the function returns an error.
+fn lookup(
+	fs: &mut dyn Filesystem,
+	io: &mut dyn IO,
+	mountpoint_id: u32,
+	parent: INode,
+	name: &[u8],
+) -> EResult<INode> {
+	if !fs.must_cache() {
+		return fs.get_inode(io, Some(parent), name);
+	}
+	match dcache::lookup(mountpoint_id, parent, name) {
+		Some(Some(inode)) => return Ok(inode),
+		Some(None) => return Err(errno!(ENOENT)),
+		None => {}
+	}
+
+	match fs.get_inode(io, Some(parent), name) {
+		Ok(inode) => {
+			dcache::insert(mountpoint_id, parent, name, Some(inode));
+			Ok(inode)
+		}
+		Err(e) if e.as_int() == errno::ENOENT => {
+			dcache::insert(mountpoint_id, parent, name, None);
+			Err(e)
+		}
+		Err(e) => Err(e),
+	}
+}
+
+/// Resolve flag: the resolution fails with [`errno::EXDEV`] if it crosses a mountpoint.
+pub const RESOLVE_NO_XDEV: u64 = 0x01;
+/// Resolve flag: the resolution fails with [`errno::ELOOP`] if a symbolic link has to be
+/// followed.
+pub const RESOLVE_NO_SYMLINKS: u64 = 0x04;
+/// Resolve flag: the resolution fails with [`errno::EXDEV`] if it escapes the starting
+/// directory, either through `..` or through a symbolic link.
+pub const RESOLVE_BENEATH: u64 = 0x08;
+
+/// Settings for the resolution of a path.
+#[derive(Clone, Copy)]
+pub struct ResolutionSettings<'p> {
+	/// The directory the resolution starts from. [`RESOLVE_BENEATH`] and [`RESOLVE_NO_XDEV`] are
+	/// relative to it. If `None`, the root directory is used.
+	pub start: Option<&'p Path>,
+	/// Tells whether the symbolic link at the end of the path is followed.
+	pub follow_links: bool,
+	/// The set of `RESOLVE_*` flags restricting the resolution.
+	pub flags: u64,
+}
+
+impl<'p> ResolutionSettings<'p> {
+	/// Returns settings without any restriction.
+	///
+	/// `follow_links` tells whether the symbolic link at the end of the path is followed.
+	pub fn new(follow_links: bool) -> Self {
+		Self {
+			start: None,
+			follow_links,
+			flags: 0,
+		}
+	}
+}
+
+/// Returns the path to the target of the symbolic link whose content is `target`, located in the
+/// directory at `parent`. `suffix` is the remaining part of the path being resolved.
+///
+/// `follows_count` is the number of links that have been followed since the beginning of the path
+/// resolution. If it reaches [`limits::SYMLOOP_MAX`], the function returns [`errno::ELOOP`].
+fn follow_link(
+	parent: &Path,
+	target: &[u8],
+	suffix: &Path,
+	settings: &ResolutionSettings,
+	follows_count: usize,
+) -> EResult<Path> {
+	if settings.flags & RESOLVE_NO_SYMLINKS != 0 || follows_count >= limits::SYMLOOP_MAX {
+		return Err(errno!(ELOOP));
+	}
+	let target = Path::from_str(target, false)?;
+	if target.is_absolute() && settings.flags & RESOLVE_BENEATH != 0 {
+		return Err(errno!(EXDEV));
+	}
+	Ok(parent.concat(&target)?.concat(suffix)?)
+}
+
+/// Checks that the magic link being followed is allowed by the given settings.
+///
+/// Since magic links can point anywhere, they are rejected by every restricting flag.
+fn check_magic_link(settings: &ResolutionSettings) -> EResult<()> {
+	if settings.flags & RESOLVE_NO_SYMLINKS != 0 {
+		return Err(errno!(ELOOP));
+	}
+	if settings.flags & (RESOLVE_BENEATH | RESOLVE_NO_XDEV) != 0 {
+		return Err(errno!(EXDEV));
+	}
+	Ok(())
+}
+
+/// Returns the file at path `path`, resolved according to `settings`.
+///
 /// `follows_count` is the number of links that have been followed since the
 /// beginning of the path resolution.
 fn get_file_by_path_impl(
 	path: &Path,
 	ap: &AccessProfile,
-	follow_links: bool,
+	settings: &ResolutionSettings,
 	follows_count: usize,
 ) -> EResult<Arc<Mutex<File>>> {
 	let path = Path::root().concat(path)?;
+	let root = Path::root();
+	let start = settings.start.unwrap_or(&root);
+	if settings.flags & RESOLVE_BENEATH != 0 && !path.is_beneath(start) {
+		return Err(errno!(EXDEV));
+	}
 
 	// Get the path's deepest mountpoint
 	let mountpoint_mutex = mountpoint::get_deepest(&path).ok_or_else(|| errno!(ENOENT))?;
+	if settings.flags & RESOLVE_NO_XDEV != 0 {
+		let start_mountpoint = mountpoint::get_deepest(start).ok_or_else(|| errno!(ENOENT))?;
+		if !ptr::eq(start_mountpoint.as_ptr(), mountpoint_mutex.as_ptr()) {
+			return Err(errno!(EXDEV));
+		}
+	}
 	let mountpoint = mountpoint_mutex.lock();
 	let mountpath = mountpoint.get_path();
 
@@ -116,11 +290,19 @@ fn get_file_by_path_impl(
 	let mut fs = fs_mutex.lock();
 
 	// The root inode
-	let mut inode = fs.get_root_inode(&mut *io)?;
+	let mut inode = mountpoint.get_root_inode(&*fs, &mut *io)?;
 	let mut file = fs.load_file(&mut *io, inode, String::new())?;
 
 	for i in 0..inner_path.get_elements_count() {
-		inode = fs.get_inode(&mut *io, Some(inode), &inner_path[i])?;
+		let parent = inode;
+		inode = lookup(
+			&mut *fs,
+			&mut *io,
+			mountpoint.get_id(),
+			parent,
+			&inner_path[i],
+		)?;
+		fs.check_lookup(parent, inode, ap)?;
 
 		// Check permissions
 		if i < inner_path.get_elements_count() - 1 && !ap.can_search_directory(&file) {
@@ -129,30 +311,37 @@ fn get_file_by_path_impl(
 		// Get file
 		file = fs.load_file(&mut *io, inode, inner_path[i].try_clone()?)?;
 
+		// If this is the last element and links are followed, resolve magic links
+		if i == inner_path.get_elements_count() - 1 && settings.follow_links {
+			if let Some(target) = fs.get_magic_link(inode, ap)? {
+				check_magic_link(settings)?;
+				return Ok(target);
+			}
+		}
+
 		// If this is not the last element, or if links are followed
-		if i < inner_path.get_elements_count() - 1 || follow_links {
+		if i < inner_path.get_elements_count() - 1 || settings.follow_links {
 			// If symbolic link, resolve it
 			if let FileContent::Link(link_path) = file.get_content() {
-				if follows_count > limits::SYMLOOP_MAX {
-					return Err(errno!(ELOOP));
-				}
-
 				let mut prefix = inner_path.range_to(..i)?;
 				prefix.set_absolute(false);
 
-				let link_path = Path::from_str(link_path.as_bytes(), false)?;
-
 				let mut suffix = inner_path.range_from((i + 1)..)?;
 				suffix.set_absolute(false);
 
 				let parent_path = mountpath.concat(&prefix)?;
-				let new_path = parent_path.concat(&link_path)?;
-				let new_path = new_path.concat(&suffix)?;
+				let new_path = follow_link(
+					&parent_path,
+					link_path.as_bytes(),
+					&suffix,
+					settings,
+					follows_count,
+				)?;
 
 				drop(fs);
 				drop(io);
 				drop(mountpoint);
-				return get_file_by_path_impl(&new_path, ap, follow_links, follows_count + 1);
+				return get_file_by_path_impl(&new_path, ap, settings, follows_count + 1);
 			}
 		}
 	}
@@ -183,7 +372,22 @@ pub fn get_file_from_path(
 	ap: &AccessProfile,
 	follow_links: bool,
 ) -> EResult<Arc<Mutex<File>>> {
-	get_file_by_path_impl(path, ap, follow_links, 0)
+	get_file_by_path_impl(path, ap, &ResolutionSettings::new(follow_links), 0)
+}
+
+/// Returns a reference to the file at path `path`, resolved according to the given `settings`.
+///
+/// Symbolic links are followed at most [`limits::SYMLOOP_MAX`] times before the function returns
+/// [`errno::ELOOP`].
+///
+/// If the path is relative, the function starts from the root. The path must already include
+/// the starting directory of `settings`, if any.
+pub fn resolve_path(
+	path: &Path,
+	ap: &AccessProfile,
+	settings: &ResolutionSettings,
+) -> EResult<Arc<Mutex<File>>> {
+	get_file_by_path_impl(path, ap, settings, 0)
 }
 
 /// Returns a reference to the file `name` located in the directory `parent`.
@@ -224,18 +428,30 @@ pub fn get_file_from_parent(
 	let fs_mutex = mountpoint.get_filesystem();
 	let mut fs = fs_mutex.lock();
 
-	let inode = fs.get_inode(&mut *io, Some(parent.get_location().get_inode()), &name)?;
+	let inode = lookup(
+		&mut *fs,
+		&mut *io,
+		mountpoint.get_id(),
+		parent.get_location().get_inode(),
+		&name,
+	)?;
+	fs.check_lookup(parent.get_location().get_inode(), inode, ap)?;
 	let mut file = fs.load_file(&mut *io, inode, name)?;
 
 	if follow_links {
+		if let Some(target) = fs.get_magic_link(inode, ap)? {
+			return Ok(target);
+		}
 		if let FileContent::Link(link_path) = file.get_content() {
-			let link_path = Path::from_str(link_path.as_bytes(), false)?;
-			let new_path = parent.get_path()?.concat(&link_path)?;
+			let settings = ResolutionSettings::new(follow_links);
+			let suffix = Path::from_str(b"", false)?;
+			let new_path =
+				follow_link(&parent.get_path()?, link_path.as_bytes(), &suffix, &settings, 0)?;
 
 			drop(fs);
 			drop(io);
 			drop(mountpoint);
-			return get_file_by_path_impl(&new_path, ap, follow_links, 1);
+			return get_file_by_path_impl(&new_path, ap, &settings, 1);
 		}
 	}
 
@@ -310,6 +526,18 @@ pub fn create_file(
 	// Add the file to the filesystem
 	let parent_inode = parent.get_location().get_inode();
 	let mut file = fs.add_file(&mut *io, parent_inode, name, uid, gid, mode, content)?;
+	let ts = current_timestamp();
+	touch_dir(&mut *fs, &mut *io, parent_inode, ts);
+	parent.mtime = ts;
+	parent.ctime = ts;
+	if fs.must_cache() {
+		dcache::insert(
+			mountpoint.get_id(),
+			parent_inode,
+			file.get_name(),
+			Some(file.get_location().get_inode()),
+		);
+	}
 
 	// Add the file to the parent's entries
 	file.set_parent_path(parent.get_path()?);
@@ -366,13 +594,14 @@ pub fn create_link(
 		return Err(errno!(EROFS));
 	}
 
-	fs.add_link(
-		&mut *io,
-		parent.get_location().get_inode(),
-		name,
-		target.get_location().get_inode(),
-	)?;
+	let parent_inode = parent.get_location().get_inode();
+	fs.add_link(&mut *io, parent_inode, name, target.get_location().get_inode())?;
+	dcache::invalidate(mountpoint.get_id(), parent_inode, name);
 	target.set_hard_links_count(target.get_hard_links_count() + 1);
+	let ts = current_timestamp();
+	touch_dir(&mut *fs, &mut *io, parent_inode, ts);
+	parent.mtime = ts;
+	parent.ctime = ts;
 
 	Ok(())
 }
@@ -387,8 +616,8 @@ pub fn create_link(
 pub fn remove_file(file: &mut File, ap: &AccessProfile) -> EResult<()> {
 	// The parent directory
 	let parent_mutex = get_file_from_path(file.get_parent_path(), ap, true)?;
-	let parent = parent_mutex.lock();
-	let parent_location = parent.get_location();
+	let mut parent = parent_mutex.lock();
+	let parent_inode = parent.get_location().get_inode();
 
 	// Check permissions
 	if !ap.can_write_file(file) || !ap.can_write_directory(&*parent) {
@@ -426,15 +655,290 @@ pub fn remove_file(file: &mut File, ap: &AccessProfile) -> EResult<()> {
 	}
 
 	// Remove the file
-	let links_left = fs.remove_file(&mut *io, parent_location.get_inode(), name)?;
+	let links_left = fs.remove_file(&mut *io, parent_inode, name)?;
+	dcache::invalidate(mountpoint.get_id(), parent_inode, name);
+	if file.get_type() == FileType::Directory {
+		// The inode of the directory may be reused
+		dcache::invalidate_dir(mountpoint.get_id(), location.get_inode());
+	}
 	if links_left == 0 {
 		// If the file is a named pipe or socket, free its now unused buffer
 		buffer::release(location);
 	}
+	let ts = current_timestamp();
+	touch_dir(&mut *fs, &mut *io, parent_inode, ts);
+	parent.mtime = ts;
+	parent.ctime = ts;
 
 	Ok(())
 }
 
+/// Renames the file `old_name` located in the directory `old_parent` to `new_name` in the
+/// directory `new_parent`.
+///
+/// Arguments:
+/// - `ap` is the access profile to check permissions
+/// - `noreplace` tells whether the function fails with [`errno::EEXIST`] if the new file exists
+/// - `exchange` tells whether both files are exchanged. In this case, both must exist
+///
+/// If the new file exists and `exchange` is `false`, it is atomically replaced.
+///
+/// Both directories must be on the same mountpoint. Else, the function returns
+/// [`errno::EXDEV`].
+pub fn rename(
+	old_parent: &mut File,
+	old_name: &[u8],
+	new_parent: &mut File,
+	new_name: &[u8],
+	ap: &AccessProfile,
+	noreplace: bool,
+	exchange: bool,
+) -> EResult<()> {
+	// Check for errors
+	if old_parent.get_type() != FileType::Directory
+		|| new_parent.get_type() != FileType::Directory
+	{
+		return Err(errno!(ENOTDIR));
+	}
+	if !ap.can_write_directory(old_parent) || !ap.can_write_directory(new_parent) {
+		return Err(errno!(EACCES));
+	}
+	if [old_name, new_name]
+		.iter()
+		.any(|name| *name == b"." || *name == b"..")
+	{
+		return Err(errno!(EBUSY));
+	}
+	if old_parent.get_location().get_mountpoint_id()
+		!= new_parent.get_location().get_mountpoint_id()
+	{
+		return Err(errno!(EXDEV));
+	}
+
+	// Get the mountpoint
+	let mountpoint_mutex = old_parent
+		.get_location()
+		.get_mountpoint()
+		.ok_or_else(|| errno!(ENOENT))?;
+	let mountpoint = mountpoint_mutex.lock();
+	if mountpoint.is_readonly() {
+		return Err(errno!(EROFS));
+	}
+	let mountpoint_id = mountpoint.get_id();
+
+	// Get the IO interface
+	let io_mutex = mountpoint.get_source().get_io()?;
+	let mut io = io_mutex.lock();
+
+	// Get the filesystem
+	let fs_mutex = mountpoint.get_filesystem();
+	let mut fs = fs_mutex.lock();
+	if fs.is_readonly() {
+		return Err(errno!(EROFS));
+	}
+
+	// Get both files. The filesystem remains locked so that they cannot change
+	let old_parent_inode = old_parent.get_location().get_inode();
+	let new_parent_inode = new_parent.get_location().get_inode();
+	let old_inode = lookup(&mut *fs, &mut *io, mountpoint_id, old_parent_inode, old_name)?;
+	let old = fs.load_file(&mut *io, old_inode, String::new())?;
+	let new = match lookup(&mut *fs, &mut *io, mountpoint_id, new_parent_inode, new_name) {
+		Ok(inode) => {
+			let mut new = fs.load_file(&mut *io, inode, String::new())?;
+			update_location(&mut new, &mountpoint);
+			Some(new)
+		}
+		Err(e) if e.as_int() == errno::ENOENT => None,
+		Err(e) => return Err(e),
+	};
+	match &new {
+		Some(_) if noreplace => return Err(errno!(EEXIST)),
+		None if exchange => return Err(errno!(ENOENT)),
+		// Renaming a file to another link of itself does nothing
+		Some(new) if new.get_location().get_inode() == old_inode => return Ok(()),
+		_ => {}
+	}
+
+	// A directory cannot be moved inside of itself
+	let mut old_path = old_parent.get_path()?;
+	old_path.push(String::try_from(old_name)?)?;
+	if old.get_type() == FileType::Directory && new_parent.get_path()?.begins_with(&old_path) {
+		return Err(errno!(EINVAL));
+	}
+	let mut new_path = new_parent.get_path()?;
+	new_path.push(String::try_from(new_name)?)?;
+	let new_is_dir = new
+		.as_ref()
+		.is_some_and(|new| new.get_type() == FileType::Directory);
+	if exchange && new_is_dir && old_parent.get_path()?.begins_with(&new_path) {
+		return Err(errno!(EINVAL));
+	}
+
+	// The replaced file, if its last link is removed
+	let mut released = None;
+	if let (Some(new), false) = (&new, exchange) {
+		match (old.get_type(), new.get_content()) {
+			(FileType::Directory, FileContent::Directory(entries)) if entries.len() > 2 => {
+				return Err(errno!(ENOTEMPTY));
+			}
+			(FileType::Directory, FileContent::Directory(_)) => {}
+			(FileType::Directory, _) => return Err(errno!(ENOTDIR)),
+			(_, FileContent::Directory(_)) => return Err(errno!(EISDIR)),
+			_ => {}
+		}
+		if new_is_dir || new.get_hard_links_count() == 1 {
+			// Removal of a file in use cannot be deferred since the entry is replaced
+			let symlink = matches!(new.get_type(), FileType::Link);
+			if !symlink && OpenFile::is_open(new.get_location()) {
+				return Err(errno!(EBUSY));
+			}
+			released = Some(new.get_location());
+		}
+	}
+
+	// TODO Check permissions if sticky bit is set
+
+	fs.rename(
+		&mut *io,
+		old_parent_inode,
+		old_name,
+		new_parent_inode,
+		new_name,
+		exchange,
+	)?;
+	dcache::invalidate(mountpoint_id, old_parent_inode, old_name);
+	dcache::invalidate(mountpoint_id, new_parent_inode, new_name);
+	// The `..` entries of moved directories have changed
+	dcache::invalidate(mountpoint_id, old_inode, b"..");
+	if let Some(new) = &new {
+		dcache::invalidate(mountpoint_id, new.get_location().get_inode(), b"..");
+	}
+	if let Some(location) = released {
+		if new_is_dir {
+			// The inode of the directory may be reused
+			dcache::invalidate_dir(mountpoint_id, location.get_inode());
+		}
+		// If the file is a named pipe or socket, free its now unused buffer
+		buffer::release(location);
+	}
+	let ts = current_timestamp();
+	touch_dir(&mut *fs, &mut *io, old_parent_inode, ts);
+	if new_parent_inode != old_parent_inode {
+		touch_dir(&mut *fs, &mut *io, new_parent_inode, ts);
+	}
+	old_parent.mtime = ts;
+	old_parent.ctime = ts;
+	new_parent.mtime = ts;
+	new_parent.ctime = ts;
+
+	Ok(())
+}
+
+/// Executes `f` with the filesystem holding the file `file`, to operate on its extended
+/// attributes.
+///
+/// `write` tells whether `f` modifies the attributes, in which case the function fails if the
+/// filesystem is read-only.
+///
+/// Files that are not located on a filesystem do not support extended attributes.
+fn xattr_op<R, F: FnOnce(&mut dyn Filesystem, &mut dyn IO, INode) -> EResult<R>>(
+	file: &File,
+	write: bool,
+	f: F,
+) -> EResult<R> {
+	let FileLocation::Filesystem {
+		inode, ..
+	} = file.get_location()
+	else {
+		return Err(errno!(EOPNOTSUPP));
+	};
+
+	// Get the mountpoint
+	let mountpoint_mutex = file
+		.get_location()
+		.get_mountpoint()
+		.ok_or_else(|| errno!(ENOENT))?;
+	let mountpoint = mountpoint_mutex.lock();
+	if write && mountpoint.is_readonly() {
+		return Err(errno!(EROFS));
+	}
+
+	// Get the IO interface
+	let io_mutex = mountpoint.get_source().get_io()?;
+	let mut io = io_mutex.lock();
+
+	// Get the filesystem
+	let fs_mutex = mountpoint.get_filesystem();
+	let mut fs = fs_mutex.lock();
+	if write && fs.is_readonly() {
+		return Err(errno!(EROFS));
+	}
+
+	f(&mut *fs, &mut *io, *inode)
+}
+
+/// Returns the value of the extended attribute `name` of the file `file`.
+///
+/// `ap` is the access profile to check permissions.
+pub fn get_xattr(file: &File, name: &[u8], ap: &AccessProfile) -> EResult<Vec<u8>> {
+	let ns = xattr::check_name(name)?;
+	xattr::check_read(file, ns, ap)?;
+	xattr_op(file, false, |fs, io, inode| fs.get_xattr(io, inode, name))
+}
+
+/// Sets the value of the extended attribute `name` of the file `file`.
+///
+/// Arguments:
+/// - `value` is the new value of the attribute.
+/// - `flags` are `XATTR_*` flags restricting whether the attribute may be created or replaced.
+/// - `ap` is the access profile to check permissions.
+pub fn set_xattr(
+	file: &File,
+	name: &[u8],
+	value: &[u8],
+	flags: c_int,
+	ap: &AccessProfile,
+) -> EResult<()> {
+	if flags & !(xattr::XATTR_CREATE | xattr::XATTR_REPLACE) != 0 {
+		return Err(errno!(EINVAL));
+	}
+	if value.len() > xattr::XATTR_SIZE_MAX {
+		return Err(errno!(E2BIG));
+	}
+	let ns = xattr::check_name(name)?;
+	xattr::check_write(file, ns, ap)?;
+	xattr_op(file, true, |fs, io, inode| {
+		fs.set_xattr(io, inode, name, value, flags)
+	})
+}
+
+/// Returns the names of the extended attributes of the file `file` the access profile `ap` can
+/// read, each followed by a null byte.
+pub fn list_xattr(file: &File, ap: &AccessProfile) -> EResult<Vec<u8>> {
+	let list = xattr_op(file, false, |fs, io, inode| fs.list_xattr(io, inode))?;
+	// Hide the attributes that cannot be read
+	let mut res = Vec::new();
+	for name in list.split(|b| *b == 0).filter(|name| !name.is_empty()) {
+		let visible = xattr::Namespace::parse(name)
+			.map(|(ns, _)| ns != xattr::Namespace::Trusted || ap.is_privileged())
+			.unwrap_or(false);
+		if visible {
+			res.extend_from_slice(name)?;
+			res.push(0)?;
+		}
+	}
+	Ok(res)
+}
+
+/// Removes the extended attribute `name` of the file `file`.
+///
+/// `ap` is the access profile to check permissions.
+pub fn remove_xattr(file: &File, name: &[u8], ap: &AccessProfile) -> EResult<()> {
+	let ns = xattr::check_name(name)?;
+	xattr::check_write(file, ns, ap)?;
+	xattr_op(file, true, |fs, io, inode| fs.remove_xattr(io, inode, name))
+}
+
 /// Maps the page at offset `off` in the file at location `loc`.
 ///
 /// On success, the function returns a reference to the page.
@@ -454,3 +958,127 @@ pub fn unmap_file(loc: &FileLocation, off: usize) {
 	// TODO sync to disk if necessary
 	mapping::unmap(loc, off);
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::file::fs::options::MountOptions;
+	use crate::file::fs::tmp;
+	use crate::file::fs::tmp::TmpFS;
+	use crate::file::fs::FilesystemType;
+	use crate::file::mountpoint::MountSource;
+	use crate::util::container::hashmap::HashMap;
+	use crate::util::io::DummyIO;
+
+	/// A timestamp that is not the current time, set on directories before operating on them.
+	const OLD_TIMESTAMP: Timestamp = 1234;
+
+	#[test_case]
+	fn vfs_touch_dir() {
+		let mut io = DummyIO {};
+		let mut fs = TmpFS::new(b"tmpfs", tmp::DEFAULT_MAX_SIZE, false).unwrap();
+		let root = fs.get_root_inode(&mut io).unwrap();
+		let content = FileContent::Directory(HashMap::new());
+		let dir = fs
+			.add_file(&mut io, root, b"dir".try_into().unwrap(), 0, 0, 0o755, content)
+			.unwrap();
+		let dir = dir.get_location().get_inode();
+		fs.add_file(&mut io, dir, b"a".try_into().unwrap(), 0, 0, 0o644, FileContent::Regular)
+			.unwrap();
+
+		touch_dir(&mut fs, &mut io, dir, 1234);
+		let file = fs.load_file(&mut io, dir, String::new()).unwrap();
+		assert_eq!(file.mtime, 1234);
+		assert_eq!(file.ctime, 1234);
+		// The entries of the directory are left untouched
+		assert!(fs.get_inode(&mut io, Some(dir), b"a").is_ok());
+		assert_eq!(file.get_mode() & 0o7777, 0o755);
+	}
+
+	/// Sets the modification and status change timestamps of the directory `dir`, both in memory
+	/// and on its filesystem, to [`OLD_TIMESTAMP`].
+	fn set_old_times(dir: &mut File) {
+		let mountpoint_mutex = dir.get_location().get_mountpoint().unwrap();
+		let mountpoint = mountpoint_mutex.lock();
+		let fs_mutex = mountpoint.get_filesystem();
+		let mut fs = fs_mutex.lock();
+		let inode = dir.get_location().get_inode();
+		fs.touch(&mut DummyIO {}, inode, OLD_TIMESTAMP).unwrap();
+		dir.mtime = OLD_TIMESTAMP;
+		dir.ctime = OLD_TIMESTAMP;
+	}
+
+	/// Asserts that the modification and status change timestamps of the directory `dir` have
+	/// been updated, both in memory and on its filesystem.
+	fn assert_touched(dir: &File) {
+		assert_ne!(dir.mtime, OLD_TIMESTAMP);
+		assert_eq!(dir.ctime, dir.mtime);
+		let stored = get_file_from_path(&dir.get_path().unwrap(), &AccessProfile::KERNEL, true)
+			.unwrap();
+		let stored = stored.lock();
+		assert_eq!(stored.mtime, dir.mtime);
+		assert_eq!(stored.ctime, dir.ctime);
+	}
+
+	#[test_case]
+	fn vfs_parent_times() {
+		let ap = AccessProfile::KERNEL;
+		let path = Path::from_str(b"/vfs_test", false).unwrap();
+		let fs_type: Arc<dyn FilesystemType> = Arc::new(tmp::TmpFsType {}).unwrap();
+		mountpoint::create(
+			MountSource::NoDev(b"vfs_test".try_into().unwrap()),
+			Some(fs_type),
+			0,
+			path.try_clone().unwrap(),
+			&MountOptions::default(),
+		)
+		.unwrap();
+		let mkdir = |parent: &mut File, name: &[u8]| {
+			let content = FileContent::Directory(HashMap::new());
+			create_file(parent, name.try_into().unwrap(), &ap, 0o755, content).unwrap()
+		};
+		let root_mutex = get_file_from_path(&path, &ap, true).unwrap();
+		let a_mutex = mkdir(&mut root_mutex.lock(), b"a");
+		let b_mutex = mkdir(&mut root_mutex.lock(), b"b");
+		let mut a = a_mutex.lock();
+		let mut b = b_mutex.lock();
+
+		// Creating a file
+		set_old_times(&mut a);
+		let name = b"f".try_into().unwrap();
+		let file_mutex = create_file(&mut a, name, &ap, 0o644, FileContent::Regular).unwrap();
+		assert_touched(&a);
+
+		// Adding a hard link
+		set_old_times(&mut a);
+		create_link(&mut file_mutex.lock(), &mut a, b"g", &ap).unwrap();
+		assert_touched(&a);
+
+		// Renaming inside of the same directory, through two instances of it
+		let a_path = a.get_path().unwrap();
+		let a2_mutex = get_file_from_path(&a_path, &ap, true).unwrap();
+		let mut a2 = a2_mutex.lock();
+		set_old_times(&mut a);
+		rename(&mut a, b"g", &mut a2, b"h", &ap, false, false).unwrap();
+		assert_touched(&a);
+		assert_touched(&a2);
+		drop(a2);
+
+		// Moving to another directory updates both directories
+		set_old_times(&mut a);
+		set_old_times(&mut b);
+		rename(&mut a, b"h", &mut b, b"h", &ap, false, false).unwrap();
+		assert_touched(&a);
+		assert_touched(&b);
+
+		// Removing a file. The function loads the parent directory by itself
+		set_old_times(&mut a);
+		remove_file(&mut file_mutex.lock(), &ap).unwrap();
+		let a_mutex = get_file_from_path(&a_path, &ap, true).unwrap();
+		assert_touched(&a_mutex.lock());
+
+		drop(a);
+		drop(b);
+		mountpoint::remove(&path).unwrap();
+	}
+}
@@ -0,0 +1,227 @@
+//! Extended attributes are name-value pairs associated with files, besides their regular
+//! metadata.
+//!
+//! The name of an attribute begins with a namespace, which determines who can access it:
+//! - `user.`: accessible with the permissions of the file. Only regular files and directories can
+//! have such attributes
+//! - `trusted.`: accessible only by privileged users
+//! - `security.`: readable by everyone, writable only by privileged users
+//! - `system.`: reserved for attributes interpreted by the kernel, such as ACLs, which are not
+//! supported
+
+use crate::errno;
+use crate::errno::EResult;
+use crate::file::perm::AccessProfile;
+use crate::file::File;
+use crate::file::FileType;
+use crate::util::container::vec::Vec;
+use core::ffi::c_int;
+
+/// Flag for `setxattr`: fail if the attribute already exists.
+pub const XATTR_CREATE: c_int = 1;
+/// Flag for `setxattr`: fail if the attribute does not exist.
+pub const XATTR_REPLACE: c_int = 2;
+
+/// The maximum length of the name of an attribute.
+pub const XATTR_NAME_MAX: usize = 255;
+/// The maximum size of the value of an attribute.
+pub const XATTR_SIZE_MAX: usize = 65536;
+/// The maximum size of the list of attributes' names of a file.
+pub const XATTR_LIST_MAX: usize = 65536;
+
+/// An extended attribute namespace.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Namespace {
+	/// User attributes.
+	User,
+	/// Trusted attributes.
+	Trusted,
+	/// Security attributes.
+	Security,
+	/// System attributes.
+	System,
+}
+
+impl Namespace {
+	/// The list of namespaces along with their prefix.
+	const PREFIXES: [(Self, &'static [u8]); 4] = [
+		(Self::User, b"user."),
+		(Self::Trusted, b"trusted."),
+		(Self::Security, b"security."),
+		(Self::System, b"system."),
+	];
+
+	/// Splits the given attribute name into its namespace and the name inside of it.
+	///
+	/// If the name has no known namespace or if the name inside of it is empty, the function
+	/// returns `None`.
+	pub fn parse(name: &[u8]) -> Option<(Self, &[u8])> {
+		Self::PREFIXES.iter().find_map(|(ns, prefix)| {
+			let suffix = name.strip_prefix(*prefix)?;
+			(!suffix.is_empty()).then_some((*ns, suffix))
+		})
+	}
+
+	/// Returns the prefix of the namespace, including the trailing dot.
+	pub fn prefix(&self) -> &'static [u8] {
+		Self::PREFIXES
+			.iter()
+			.find(|(ns, _)| ns == self)
+			.map(|(_, prefix)| *prefix)
+			.unwrap()
+	}
+}
+
+/// Checks the given attribute name is valid and returns its namespace.
+///
+/// If the name is empty or too long, the function returns [`errno::ERANGE`]. If the namespace is
+/// not supported, the function returns [`errno::EOPNOTSUPP`].
+pub fn check_name(name: &[u8]) -> EResult<Namespace> {
+	if name.is_empty() || name.len() > XATTR_NAME_MAX {
+		return Err(errno!(ERANGE));
+	}
+	match Namespace::parse(name) {
+		Some((Namespace::System, _)) | None => Err(errno!(EOPNOTSUPP)),
+		Some((ns, _)) => Ok(ns),
+	}
+}
+
+/// Checks whether the access profile `ap` can read the attributes of namespace `ns` on `file`.
+///
+/// If an attribute cannot be read, it is hidden: the function returns [`errno::ENODATA`].
+pub fn check_read(file: &File, ns: Namespace, ap: &AccessProfile) -> EResult<()> {
+	match ns {
+		Namespace::User => {
+			if !matches!(file.get_type(), FileType::Regular | FileType::Directory) {
+				return Err(errno!(ENODATA));
+			}
+			if !ap.can_read_file(file) {
+				return Err(errno!(EACCES));
+			}
+		}
+		Namespace::Trusted if !ap.is_privileged() => return Err(errno!(ENODATA)),
+		_ => {}
+	}
+	Ok(())
+}
+
+/// Checks whether the access profile `ap` can write the attributes of namespace `ns` on `file`.
+pub fn check_write(file: &File, ns: Namespace, ap: &AccessProfile) -> EResult<()> {
+	match ns {
+		Namespace::User => {
+			if !matches!(file.get_type(), FileType::Regular | FileType::Directory) {
+				return Err(errno!(EPERM));
+			}
+			if !ap.can_write_file(file) {
+				return Err(errno!(EACCES));
+			}
+		}
+		Namespace::Trusted | Namespace::Security if !ap.is_privileged() => {
+			return Err(errno!(EPERM))
+		}
+		_ => {}
+	}
+	Ok(())
+}
+
+/// An in-memory set of extended attributes, for filesystems that do not store them on a device.
+#[derive(Debug, Default)]
+pub struct XattrSet {
+	/// The attributes, in order of creation.
+	attrs: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+impl XattrSet {
+	/// Returns the index of the attribute with the given name.
+	fn find(&self, name: &[u8]) -> Option<usize> {
+		self.attrs.iter().position(|(n, _)| n.as_slice() == name)
+	}
+
+	/// Tells whether the set is empty.
+	pub fn is_empty(&self) -> bool {
+		self.attrs.is_empty()
+	}
+
+	/// Returns the value of the attribute with the given name.
+	///
+	/// If the attribute does not exist, the function returns [`errno::ENODATA`].
+	pub fn get(&self, name: &[u8]) -> EResult<Vec<u8>> {
+		let i = self.find(name).ok_or_else(|| errno!(ENODATA))?;
+		Ok(Vec::from_slice(&self.attrs[i].1)?)
+	}
+
+	/// Sets the value of the attribute with the given name.
+	///
+	/// `flags` are `XATTR_*` flags restricting whether the attribute may be created or replaced.
+	pub fn set(&mut self, name: &[u8], value: &[u8], flags: c_int) -> EResult<()> {
+		let value = Vec::from_slice(value)?;
+		match self.find(name) {
+			Some(_) if flags & XATTR_CREATE != 0 => Err(errno!(EEXIST)),
+			Some(i) => {
+				self.attrs[i].1 = value;
+				Ok(())
+			}
+			None if flags & XATTR_REPLACE != 0 => Err(errno!(ENODATA)),
+			None => {
+				self.attrs.push((Vec::from_slice(name)?, value))?;
+				Ok(())
+			}
+		}
+	}
+
+	/// Returns the names of the attributes, each followed by a null byte.
+	pub fn list(&self) -> EResult<Vec<u8>> {
+		let mut list = Vec::new();
+		for (name, _) in self.attrs.iter() {
+			list.extend_from_slice(name)?;
+			list.push(0)?;
+		}
+		Ok(list)
+	}
+
+	/// Removes the attribute with the given name.
+	///
+	/// If the attribute does not exist, the function returns [`errno::ENODATA`].
+	pub fn remove(&mut self, name: &[u8]) -> EResult<()> {
+		let i = self.find(name).ok_or_else(|| errno!(ENODATA))?;
+		self.attrs.remove(i);
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test_case]
+	fn xattr_namespace() {
+		assert_eq!(
+			Namespace::parse(b"user.foo"),
+			Some((Namespace::User, &b"foo"[..]))
+		);
+		assert_eq!(
+			Namespace::parse(b"security.selinux"),
+			Some((Namespace::Security, &b"selinux"[..]))
+		);
+		assert_eq!(Namespace::parse(b"user."), None);
+		assert_eq!(Namespace::parse(b"foo.bar"), None);
+		assert!(check_name(b"system.posix_acl_access").is_err());
+		assert!(check_name(b"").is_err());
+	}
+
+	#[test_case]
+	fn xattr_set() {
+		let mut set = XattrSet::default();
+		set.set(b"user.a", b"1", 0).unwrap();
+		set.set(b"user.b", b"22", XATTR_CREATE).unwrap();
+		assert!(set.set(b"user.a", b"3", XATTR_CREATE).is_err());
+		assert!(set.set(b"user.c", b"3", XATTR_REPLACE).is_err());
+		set.set(b"user.a", b"333", XATTR_REPLACE).unwrap();
+		assert_eq!(set.get(b"user.a").unwrap().as_slice(), b"333");
+		assert_eq!(set.list().unwrap().as_slice(), b"user.a\0user.b\0");
+		set.remove(b"user.a").unwrap();
+		assert!(set.get(b"user.a").is_err());
+		assert!(set.remove(b"user.a").is_err());
+		assert_eq!(set.list().unwrap().as_slice(), b"user.b\0");
+	}
+}
@@ -0,0 +1,566 @@
+//! The APIC (Advanced Programmable Interrupt Controller) replaces the PIC on modern systems.
+//!
+//! Each CPU core has a local APIC, which receives interrupts and provides a timer. External
+//! interrupts are routed to local APICs by I/O APICs, which are described by ACPI's MADT.
+//!
+//! An ISA IRQ is mapped to a Global System Interrupt (GSI) of an I/O APIC, either directly or
+//! through an interrupt source override. Each IRQ keeps the interrupt vector it has with the PIC,
+//! so that interrupt handlers do not depend on the controller in use.
+//!
+//! If no APIC is available, the PIC is used instead.
+
+use crate::cpu::features;
+use crate::cpu::features::Feature;
+use crate::cpu::kvm;
+use crate::errno;
+use crate::errno::AllocResult;
+use crate::errno::EResult;
+use crate::idt::pic;
+use crate::io;
+use crate::memory;
+use crate::memory::mmio::MMIO;
+use crate::util;
+use crate::util::lock::IntMutex;
+use core::ffi::c_void;
+use core::hint;
+use core::mem::ManuallyDrop;
+use core::ptr;
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering::Relaxed;
+
+/// The maximum number of I/O APICs that can be registered.
+const IO_APICS_MAX: usize = 8;
+/// The number of ISA IRQs.
+pub const ISA_IRQS_COUNT: usize = 16;
+/// The interrupt vector of the first ISA IRQ.
+const IRQ_VECTOR_BASE: u32 = 0x20;
+/// The ISA IRQ used to cascade the secondary PIC, which is never raised.
+const CASCADE_IRQ: u8 = 2;
+
+/// The interrupt vector for spurious interrupts.
+///
+/// The lowest four bits are set since they are hardwired to ones on some processors.
+pub const SPURIOUS_VECTOR: u32 = 0x3f;
+/// The interrupt vector of the local APIC timer. This is the same as the PIT's so that the timer
+/// can replace it.
+pub const TIMER_VECTOR: u32 = IRQ_VECTOR_BASE;
+/// The interrupt vector of the IPI waking up an idle CPU.
+pub const WAKEUP_VECTOR: u32 = 0x30;
+
+/// Local APIC register: ID.
+const REG_ID: usize = 0x20;
+/// Local APIC register: Task Priority.
+const REG_TPR: usize = 0x80;
+/// Local APIC register: End Of Interrupt.
+const REG_EOI: usize = 0xb0;
+/// Local APIC register: Spurious Interrupt Vector.
+const REG_SVR: usize = 0xf0;
+/// Local APIC register: Interrupt Command, lower half.
+const REG_ICR_LOW: usize = 0x300;
+/// Local APIC register: Interrupt Command, upper half, with the destination.
+const REG_ICR_HIGH: usize = 0x310;
+/// Local APIC register: timer's Local Vector Table entry.
+const REG_LVT_TIMER: usize = 0x320;
+/// Local APIC register: timer's initial count.
+const REG_TIMER_INIT: usize = 0x380;
+/// Local APIC register: timer's current count.
+const REG_TIMER_CURRENT: usize = 0x390;
+/// Local APIC register: timer's divide configuration.
+const REG_TIMER_DIV: usize = 0x3e0;
+
+/// Spurious Interrupt Vector flag: the local APIC is enabled.
+const SVR_ENABLE: u32 = 1 << 8;
+/// Local Vector Table flag: the interrupt is masked.
+const LVT_MASKED: u32 = 1 << 16;
+/// Local Vector Table flag: the timer is periodic.
+const LVT_TIMER_PERIODIC: u32 = 1 << 17;
+/// Interrupt Command delivery mode: INIT.
+const ICR_INIT: u32 = 0b101 << 8;
+/// Interrupt Command delivery mode: Startup.
+const ICR_STARTUP: u32 = 0b110 << 8;
+/// Interrupt Command flag: the IPI has not been accepted yet.
+const ICR_PENDING: u32 = 1 << 12;
+/// Interrupt Command flag: the level is asserted.
+const ICR_ASSERT: u32 = 1 << 14;
+/// Timer divide configuration: the bus clock is divided by 16.
+pub const TIMER_DIV: u32 = 16;
+/// The value of the divide configuration register for [`TIMER_DIV`].
+const TIMER_DIV_VALUE: u32 = 0b0011;
+
+/// I/O APIC register selector.
+const IOREGSEL: usize = 0x00;
+/// I/O APIC register window.
+const IOWIN: usize = 0x10;
+/// I/O APIC register: version, with the number of redirection entries.
+const IOAPIC_REG_VER: u32 = 0x01;
+/// I/O APIC register: first redirection entry.
+const IOAPIC_REG_REDTBL: u32 = 0x10;
+
+/// Redirection entry flag: the interrupt is masked.
+const REDIR_MASKED: u32 = 1 << 16;
+/// Redirection entry flag: the interrupt is level-triggered.
+const REDIR_LEVEL: u32 = 1 << 15;
+/// Redirection entry flag: the interrupt is active low.
+const REDIR_ACTIVE_LOW: u32 = 1 << 13;
+
+/// MPS INTI flags: mask of the polarity.
+const INTI_POLARITY_MASK: u16 = 0b11;
+/// MPS INTI flags: the interrupt is active low.
+const INTI_POLARITY_LOW: u16 = 0b11;
+/// MPS INTI flags: mask of the trigger mode.
+const INTI_TRIGGER_MASK: u16 = 0b1100;
+/// MPS INTI flags: the interrupt is level-triggered.
+const INTI_TRIGGER_LEVEL: u16 = 0b1100;
+
+/// The port of the Edge/Level Control Register of the first PIC, telling which IRQs are
+/// level-triggered.
+const ELCR_MASTER: u16 = 0x4d0;
+/// The port of the Edge/Level Control Register of the second PIC.
+const ELCR_SLAVE: u16 = 0x4d1;
+
+/// An I/O APIC.
+#[derive(Clone, Copy, Debug)]
+struct IoApic {
+	/// The physical address of the registers.
+	phys_addr: u32,
+	/// The first GSI handled by the I/O APIC.
+	gsi_base: u32,
+
+	/// The virtual address of the registers. Zero until the APIC is initialized.
+	regs: usize,
+	/// The number of GSIs handled by the I/O APIC. Zero until the APIC is initialized.
+	gsi_count: u32,
+}
+
+impl IoApic {
+	/// Reads the register `reg`.
+	fn read(&self, reg: u32) -> u32 {
+		unsafe {
+			ptr::write_volatile((self.regs + IOREGSEL) as *mut u32, reg);
+			ptr::read_volatile((self.regs + IOWIN) as *const u32)
+		}
+	}
+
+	/// Writes `val` to the register `reg`.
+	fn write(&self, reg: u32, val: u32) {
+		unsafe {
+			ptr::write_volatile((self.regs + IOREGSEL) as *mut u32, reg);
+			ptr::write_volatile((self.regs + IOWIN) as *mut u32, val);
+		}
+	}
+
+	/// Tells whether the I/O APIC handles the GSI `gsi`.
+	fn handles(&self, gsi: u32) -> bool {
+		(self.gsi_base..self.gsi_base + self.gsi_count).contains(&gsi)
+	}
+
+	/// Sets the redirection entry for the GSI `gsi`, which must be handled by the I/O APIC.
+	///
+	/// `low` is the lower half of the entry, and `dest` is the ID of the destination local APIC.
+	fn set_redirection(&self, gsi: u32, low: u32, dest: u8) {
+		let reg = IOAPIC_REG_REDTBL + (gsi - self.gsi_base) * 2;
+		// Mask the entry while it is being modified
+		self.write(reg, REDIR_MASKED);
+		self.write(reg + 1, (dest as u32) << 24);
+		self.write(reg, low);
+	}
+
+	/// Sets the ID of the destination local APIC of the redirection entry for the GSI `gsi`,
+	/// keeping the rest of the entry.
+	fn set_destination(&self, gsi: u32, dest: u8) {
+		let reg = IOAPIC_REG_REDTBL + (gsi - self.gsi_base) * 2;
+		let low = self.read(reg);
+		self.set_redirection(gsi, low, dest);
+	}
+
+	/// Returns the ID of the destination local APIC of the redirection entry for the GSI `gsi`.
+	fn get_destination(&self, gsi: u32) -> u8 {
+		let reg = IOAPIC_REG_REDTBL + (gsi - self.gsi_base) * 2;
+		(self.read(reg + 1) >> 24) as u8
+	}
+
+	/// Sets whether the redirection entry for the GSI `gsi` is masked.
+	fn set_masked(&self, gsi: u32, masked: bool) {
+		let reg = IOAPIC_REG_REDTBL + (gsi - self.gsi_base) * 2;
+		let low = self.read(reg);
+		if masked {
+			self.write(reg, low | REDIR_MASKED);
+		} else {
+			self.write(reg, low & !REDIR_MASKED);
+		}
+	}
+}
+
+/// The configuration of the interrupt controllers, as described by the MADT.
+struct Config {
+	/// The physical address of the local APIC's registers. Zero if unknown.
+	local_apic_addr: u32,
+	/// Tells whether the system also has PICs, which have to be disabled.
+	has_pic: bool,
+	/// The registered I/O APICs.
+	io_apics: [Option<IoApic>; IO_APICS_MAX],
+	/// Interrupt source overrides for ISA IRQs, with the GSI and the MPS INTI flags.
+	overrides: [Option<(u32, u16)>; ISA_IRQS_COUNT],
+	/// The ID of the boot CPU's local APIC. Zero until the APIC is initialized.
+	boot_apic_id: u8,
+}
+
+impl Config {
+	/// Returns the GSI and the MPS INTI flags of the ISA IRQ `irq`.
+	fn route(&self, irq: u8) -> (u32, u16) {
+		self.overrides
+			.get(irq as usize)
+			.copied()
+			.flatten()
+			.unwrap_or((irq as _, 0))
+	}
+
+	/// Returns the I/O APIC handling the GSI `gsi`.
+	fn io_apic(&self, gsi: u32) -> Option<&IoApic> {
+		self.io_apics.iter().flatten().find(|a| a.handles(gsi))
+	}
+}
+
+/// The configuration of the interrupt controllers.
+static CONFIG: IntMutex<Config> = IntMutex::new(Config {
+	local_apic_addr: 0,
+	has_pic: true,
+	io_apics: [None; IO_APICS_MAX],
+	overrides: [None; ISA_IRQS_COUNT],
+	boot_apic_id: 0,
+});
+
+/// The virtual address of the local APIC's registers. Zero if the APIC is not in use.
+static LOCAL_APIC: AtomicUsize = AtomicUsize::new(0);
+
+/// Registers the physical address of the local APIC's registers.
+///
+/// `has_pic` tells whether the system also has PICs.
+pub fn set_local_apic(addr: u32, has_pic: bool) {
+	let mut config = CONFIG.lock();
+	config.local_apic_addr = addr;
+	config.has_pic = has_pic;
+}
+
+/// Registers the I/O APIC whose registers are at physical address `addr` and which handles GSIs
+/// starting at `gsi_base`.
+///
+/// If too many I/O APICs are registered, the I/O APIC is ignored.
+pub fn add_io_apic(addr: u32, gsi_base: u32) {
+	let mut config = CONFIG.lock();
+	if let Some(slot) = config.io_apics.iter_mut().find(|a| a.is_none()) {
+		*slot = Some(IoApic {
+			phys_addr: addr,
+			gsi_base,
+
+			regs: 0,
+			gsi_count: 0,
+		});
+	}
+}
+
+/// Registers an interrupt source override, mapping the ISA IRQ `irq` to the GSI `gsi`.
+///
+/// `flags` are the MPS INTI flags of the interrupt.
+pub fn add_override(irq: u8, gsi: u32, flags: u16) {
+	let mut config = CONFIG.lock();
+	if let Some(slot) = config.overrides.get_mut(irq as usize) {
+		*slot = Some((gsi, flags));
+	}
+}
+
+/// Maps the registers at physical address `phys_addr` and returns their virtual address.
+///
+/// The mapping is never removed.
+fn map_regs(phys_addr: u32) -> AllocResult<usize> {
+	let phys_addr = phys_addr as *mut c_void;
+	let begin = util::down_align(phys_addr, memory::PAGE_SIZE) as *mut c_void;
+	let mmio = ManuallyDrop::new(MMIO::new(begin, 1, false)?);
+	Ok(mmio.as_ptr() as usize + (phys_addr as usize - begin as usize))
+}
+
+/// Reads the local APIC register `reg`. `base` is the virtual address of the registers.
+fn read_reg(base: usize, reg: usize) -> u32 {
+	unsafe { ptr::read_volatile((base + reg) as *const u32) }
+}
+
+/// Writes `val` to the local APIC register `reg`. `base` is the virtual address of the registers.
+fn write_reg(base: usize, reg: usize, val: u32) {
+	unsafe {
+		ptr::write_volatile((base + reg) as *mut u32, val);
+	}
+}
+
+/// Tells whether the ISA IRQ `irq` is level-triggered according to the ELCR.
+///
+/// This is the case for IRQs assigned to PCI devices.
+fn is_level_triggered(irq: u8) -> bool {
+	let elcr = unsafe { io::inb(ELCR_MASTER) as u16 | (io::inb(ELCR_SLAVE) as u16) << 8 };
+	elcr & (1 << irq) != 0
+}
+
+/// Initializes the APIC with the configuration registered from the MADT.
+///
+/// If the APIC is not available, or if `disable` is `true`, the PIC is kept instead.
+///
+/// This function must be called only once at boot, with interrupts disabled.
+pub fn init(disable: bool) {
+	let mut config = CONFIG.lock();
+	let available = config.local_apic_addr != 0
+		&& config.io_apics.iter().any(Option::is_some)
+		&& features::has(Feature::Apic);
+	if disable || !available {
+		crate::println!("APIC not in use, falling back to the PIC");
+		return;
+	}
+
+	// Map registers before touching the hardware, so that the PIC is kept on failure
+	let mapped = map_regs(config.local_apic_addr).and_then(|local_apic| {
+		for io_apic in config.io_apics.iter_mut().flatten() {
+			io_apic.regs = map_regs(io_apic.phys_addr)?;
+		}
+		Ok(local_apic)
+	});
+	let local_apic = match mapped {
+		Ok(local_apic) => local_apic,
+		Err(e) => {
+			crate::println!("Cannot map APIC registers, falling back to the PIC: {e}");
+			return;
+		}
+	};
+
+	// Mask every interrupt of I/O APICs
+	for io_apic in config.io_apics.iter_mut().flatten() {
+		io_apic.gsi_count = ((io_apic.read(IOAPIC_REG_VER) >> 16) & 0xff) + 1;
+		for gsi in io_apic.gsi_base..(io_apic.gsi_base + io_apic.gsi_count) {
+			io_apic.set_redirection(gsi, REDIR_MASKED, 0);
+		}
+	}
+
+	// Enable the local APIC
+	write_reg(local_apic, REG_TPR, 0);
+	write_reg(local_apic, REG_LVT_TIMER, LVT_MASKED | TIMER_VECTOR);
+	write_reg(local_apic, REG_SVR, SVR_ENABLE | SPURIOUS_VECTOR);
+	kvm::pv_eoi_enable();
+	let dest = (read_reg(local_apic, REG_ID) >> 24) as u8;
+	config.boot_apic_id = dest;
+
+	// Route ISA IRQs. As with the PIC, every IRQ is enabled except the PIT's, which is replaced
+	// by the local APIC timer
+	for irq in 0..(ISA_IRQS_COUNT as u8) {
+		if irq == CASCADE_IRQ {
+			continue;
+		}
+		let (gsi, flags) = config.route(irq);
+		let Some(io_apic) = config.io_apic(gsi) else {
+			continue;
+		};
+
+		let mut low = IRQ_VECTOR_BASE + irq as u32;
+		let level = if flags & INTI_TRIGGER_MASK != 0 {
+			flags & INTI_TRIGGER_MASK == INTI_TRIGGER_LEVEL
+		} else {
+			is_level_triggered(irq)
+		};
+		if level {
+			low |= REDIR_LEVEL;
+		}
+		// Level-triggered ISA IRQs are shared PCI interrupts, which are active low
+		let active_low = if flags & INTI_POLARITY_MASK != 0 {
+			flags & INTI_POLARITY_MASK == INTI_POLARITY_LOW
+		} else {
+			level
+		};
+		if active_low {
+			low |= REDIR_ACTIVE_LOW;
+		}
+		if irq == 0 {
+			low |= REDIR_MASKED;
+		}
+		io_apic.set_redirection(gsi, low, dest);
+	}
+
+	if config.has_pic {
+		pic::disable();
+	}
+	LOCAL_APIC.store(local_apic, Relaxed);
+	crate::println!("Using the APIC for interrupts");
+}
+
+/// Tells whether the APIC is in use.
+pub fn is_enabled() -> bool {
+	LOCAL_APIC.load(Relaxed) != 0
+}
+
+/// Enables the local APIC of the current CPU, which must be an AP. Only IPIs are delivered to it,
+/// since I/O APICs route interrupts to the boot CPU and the timer is kept masked.
+///
+/// If the APIC is not in use, the function does nothing.
+pub fn init_ap() {
+	let local_apic = LOCAL_APIC.load(Relaxed);
+	if local_apic == 0 {
+		return;
+	}
+	write_reg(local_apic, REG_TPR, 0);
+	write_reg(local_apic, REG_LVT_TIMER, LVT_MASKED | TIMER_VECTOR);
+	write_reg(local_apic, REG_SVR, SVR_ENABLE | SPURIOUS_VECTOR);
+}
+
+/// Returns the ID of the boot CPU's local APIC. If the APIC is not in use, the function returns
+/// `None`.
+pub fn get_boot_apic_id() -> Option<u8> {
+	is_enabled().then(|| CONFIG.lock().boot_apic_id)
+}
+
+/// Sets whether the ISA IRQ `irq` is masked.
+fn set_irq_masked(irq: u8, masked: bool) {
+	let config = CONFIG.lock();
+	let (gsi, _) = config.route(irq);
+	if let Some(io_apic) = config.io_apic(gsi) {
+		io_apic.set_masked(gsi, masked);
+	}
+}
+
+/// Enables interruptions on the given ISA IRQ.
+pub fn enable_irq(irq: u8) {
+	set_irq_masked(irq, false);
+}
+
+/// Disables interruptions on the given ISA IRQ.
+pub fn disable_irq(irq: u8) {
+	set_irq_masked(irq, true);
+}
+
+// TODO Spread the IRQs of network and storage devices across CPUs once other CPUs handle
+// interrupts
+
+/// Returns the affinity of the ISA IRQ `irq`, which is the mask of CPUs the IRQ can be delivered
+/// to, with one bit per CPU.
+pub fn get_irq_affinity(irq: u8) -> u32 {
+	if !is_enabled() {
+		// The PIC delivers interrupts to the boot CPU only
+		return 1;
+	}
+	let config = CONFIG.lock();
+	let (gsi, _) = config.route(irq);
+	match config.io_apic(gsi) {
+		Some(io_apic) if io_apic.get_destination(gsi) != config.boot_apic_id => 0,
+		_ => 1,
+	}
+}
+
+/// Sets the affinity of the ISA IRQ `irq` to `mask`. See [`get_irq_affinity`].
+///
+/// Only the boot CPU (CPU `0`) handles interrupts, so the only valid mask is `1`. Otherwise, the
+/// function returns [`errno::EINVAL`].
+pub fn set_irq_affinity(irq: u8, mask: u32) -> EResult<()> {
+	if mask != 1 {
+		return Err(errno!(EINVAL));
+	}
+	if !is_enabled() {
+		return Ok(());
+	}
+	let config = CONFIG.lock();
+	let (gsi, _) = config.route(irq);
+	if let Some(io_apic) = config.io_apic(gsi) {
+		io_apic.set_destination(gsi, config.boot_apic_id);
+	}
+	Ok(())
+}
+
+/// Sends the Inter-Processor Interrupt described by the lower half of the Interrupt Command `cmd`
+/// to the CPU whose local APIC has ID `dest`, then waits until it is accepted.
+///
+/// If the APIC is not in use, the function returns [`errno::ENODEV`].
+fn send_ipi(dest: u8, cmd: u32) -> EResult<()> {
+	let local_apic = LOCAL_APIC.load(Relaxed);
+	if local_apic == 0 {
+		return Err(errno!(ENODEV));
+	}
+	write_reg(local_apic, REG_ICR_HIGH, (dest as u32) << 24);
+	write_reg(local_apic, REG_ICR_LOW, cmd);
+	while read_reg(local_apic, REG_ICR_LOW) & ICR_PENDING != 0 {
+		hint::spin_loop();
+	}
+	Ok(())
+}
+
+/// Sends an IPI to the CPU whose local APIC has ID `dest` to wake it up if it is halted. The
+/// interrupt has no other effect.
+pub fn send_wakeup(dest: u8) -> EResult<()> {
+	send_ipi(dest, ICR_ASSERT | WAKEUP_VECTOR)
+}
+
+/// Sends an INIT IPI to the CPU whose local APIC has ID `dest`, which resets it into a state where
+/// it waits for a Startup IPI.
+pub fn send_init(dest: u8) -> EResult<()> {
+	send_ipi(dest, ICR_INIT | ICR_ASSERT)
+}
+
+/// Sends a Startup IPI to the CPU whose local APIC has ID `dest`, which starts executing in real
+/// mode at the beginning of the physical page `page`.
+pub fn send_startup(dest: u8, page: u8) -> EResult<()> {
+	send_ipi(dest, ICR_STARTUP | ICR_ASSERT | page as u32)
+}
+
+/// Sends an End-Of-Interrupt message to the local APIC.
+pub fn end_of_interrupt() {
+	if kvm::pv_eoi_ack() {
+		return;
+	}
+	let local_apic = LOCAL_APIC.load(Relaxed);
+	if local_apic != 0 {
+		write_reg(local_apic, REG_EOI, 0);
+	}
+}
+
+/// Sends an End-Of-Interrupt message to the local APIC for the wakeup IPI.
+///
+/// Paravirtual End-Of-Interrupt is only enabled for the boot CPU, so the register of the local
+/// APIC is always written, regardless of the CPU running the handler.
+#[no_mangle]
+pub extern "C" fn wakeup_end_of_interrupt() {
+	let local_apic = LOCAL_APIC.load(Relaxed);
+	if local_apic != 0 {
+		write_reg(local_apic, REG_EOI, 0);
+	}
+}
+
+/// Starts the local APIC timer, triggering an interruption every `count` ticks of the timer if
+/// `periodic` is `true`, or once otherwise.
+///
+/// The timer ticks at the frequency of the bus divided by [`TIMER_DIV`].
+///
+/// If the APIC is not in use, the function does nothing.
+pub fn timer_start(count: u32, periodic: bool) {
+	let local_apic = LOCAL_APIC.load(Relaxed);
+	if local_apic == 0 {
+		return;
+	}
+	let mut lvt = TIMER_VECTOR;
+	if periodic {
+		lvt |= LVT_TIMER_PERIODIC;
+	}
+	write_reg(local_apic, REG_TIMER_DIV, TIMER_DIV_VALUE);
+	write_reg(local_apic, REG_LVT_TIMER, lvt);
+	write_reg(local_apic, REG_TIMER_INIT, count);
+}
+
+/// Stops the local APIC timer.
+pub fn timer_stop() {
+	let local_apic = LOCAL_APIC.load(Relaxed);
+	if local_apic == 0 {
+		return;
+	}
+	write_reg(local_apic, REG_LVT_TIMER, LVT_MASKED | TIMER_VECTOR);
+	write_reg(local_apic, REG_TIMER_INIT, 0);
+}
+
+/// Returns the current count of the local APIC timer.
+pub fn timer_current() -> u32 {
+	let local_apic = LOCAL_APIC.load(Relaxed);
+	if local_apic == 0 {
+		return 0;
+	}
+	read_reg(local_apic, REG_TIMER_CURRENT)
+}
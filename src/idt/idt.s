@@ -7,9 +7,14 @@
 .section .text
 
 .global idt_load
+.global spurious
+.global wakeup
 .type idt_load, @function
+.type spurious, @function
+.type wakeup, @function
 
 .extern end_of_interrupt
+.extern wakeup_end_of_interrupt
 
 /*
  * This macro creates a function to handle an error interrupt that does **not** pass an additional
@@ -40,6 +45,14 @@ GET_REGS \n
 	call event_handler
 	add $16, %esp
 
+	# Write back the registers restored by `iret`, which the kernel debugger may have modified
+	mov 0x0(%esp), %eax
+	mov %eax, (%ebp) # ebp
+	mov 0x8(%esp), %eax
+	mov %eax, 4(%ebp) # eip
+	mov 0xc(%esp), %eax
+	mov %eax, 12(%ebp) # eflags
+
 RESTORE_REGS
 
 	# Restore the context
@@ -200,6 +213,30 @@ IRQ 15
 
 
 
+/*
+ * Handler for spurious interrupts of the local APIC. Such interrupts must not be acknowledged.
+ */
+spurious:
+	iret
+
+
+
+/*
+ * Handler for the IPI waking up an idle CPU. The interrupt only has to be acknowledged.
+ */
+wakeup:
+	push %eax
+	push %ecx
+	push %edx
+	cld
+	call wakeup_end_of_interrupt
+	pop %edx
+	pop %ecx
+	pop %eax
+	iret
+
+
+
 /*
  * This function takes the IDT given as argument and loads it.
  */
@@ -2,6 +2,7 @@
 //! storing the list of interrupt handlers, allowing to catch and handle
 //! interruptions.
 
+pub mod apic;
 pub mod pic;
 
 use crate::util;
@@ -85,6 +86,8 @@ struct InterruptDescriptor {
 extern "C" {
 	fn idt_load(idt: *const c_void);
 	fn interrupt_is_enabled() -> i32;
+	fn spurious();
+	fn wakeup();
 }
 
 extern "C" {
@@ -219,6 +222,9 @@ pub fn init() {
 		id[0x2e] = create_id(irq14 as _, 0x8, 0x8e);
 		id[0x2f] = create_id(irq15 as _, 0x8, 0x8e);
 
+		id[apic::SPURIOUS_VECTOR as usize] = create_id(spurious as _, 0x8, 0x8e);
+		id[apic::WAKEUP_VECTOR as usize] = create_id(wakeup as _, 0x8, 0x8e);
+
 		id[SYSCALL_ENTRY] = create_id(syscall as _, 0x8, 0xee);
 	}
 
@@ -258,3 +264,32 @@ pub fn wrap_disable_interrupts<T, F: FnOnce() -> T>(f: F) -> T {
 
 	result
 }
+
+/// Enables interruptions on the given IRQ, on the interrupt controller in use.
+pub fn enable_irq(irq: u8) {
+	if apic::is_enabled() {
+		apic::enable_irq(irq);
+	} else {
+		pic::enable_irq(irq);
+	}
+}
+
+/// Disables interruptions on the given IRQ, on the interrupt controller in use.
+pub fn disable_irq(irq: u8) {
+	if apic::is_enabled() {
+		apic::disable_irq(irq);
+	} else {
+		pic::disable_irq(irq);
+	}
+}
+
+/// Sends an End-Of-Interrupt message to the interrupt controller in use for the given interrupt
+/// `irq`.
+#[no_mangle]
+pub extern "C" fn end_of_interrupt(irq: u8) {
+	if apic::is_enabled() {
+		apic::end_of_interrupt();
+	} else {
+		pic::end_of_interrupt(irq);
+	}
+}
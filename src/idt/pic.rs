@@ -94,9 +94,16 @@ pub fn disable_irq(mut n: u8) {
 	}
 }
 
+/// Masks every IRQ, leaving the PIC unused.
+pub fn disable() {
+	unsafe {
+		io::outb(MASTER_DATA, 0xff);
+		io::outb(SLAVE_DATA, 0xff);
+	}
+}
+
 /// Sends an End-Of-Interrupt message to the PIC for the given interrupt `irq`.
-#[no_mangle]
-pub extern "C" fn end_of_interrupt(irq: u8) {
+pub fn end_of_interrupt(irq: u8) {
 	if irq >= 0x8 {
 		unsafe {
 			io::outb(SLAVE_COMMAND, COMMAND_EOI);
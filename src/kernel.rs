@@ -71,6 +71,7 @@ pub mod print;
 pub mod process;
 pub mod selftest;
 pub mod syscall;
+pub mod sysrq;
 pub mod time;
 pub mod tty;
 #[macro_use]
@@ -96,11 +97,23 @@ use crate::util::lock::Mutex;
 use core::arch::asm;
 use core::ffi::c_void;
 use core::ptr::null;
+use core::sync::atomic::Ordering;
 
 /// The kernel's name.
 pub const NAME: &str = env!("CARGO_PKG_NAME");
 /// Current kernel version.
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// The git revision the kernel has been built from.
+pub const GIT_REV: &str = env!("MAESTRO_GIT_REV");
+/// Information about the build of the kernel, as returned in the `version` field of `uname`.
+pub const BUILD_INFO: &str = concat!(
+	"#",
+	env!("MAESTRO_GIT_REV"),
+	" ",
+	env!("MAESTRO_PROFILE"),
+	" ",
+	env!("MAESTRO_BUILD_DATE")
+);
 
 /// The name of the current architecture.
 pub const ARCH: &str = "x86";
@@ -108,9 +121,6 @@ pub const ARCH: &str = "x86";
 /// The path to the init process binary.
 const INIT_PATH: &[u8] = b"/sbin/init";
 
-/// The current hostname of the system.
-pub static HOSTNAME: Mutex<Vec<u8>> = Mutex::new(Vec::new());
-
 extern "C" {
 	fn kernel_loop_reset(stack: *mut c_void) -> !;
 }
@@ -253,25 +263,57 @@ pub extern "C" fn kernel_main(magic: u32, multiboot_ptr: *const c_void) -> ! {
 	// Initializing IDT
 	idt::init();
 
+	cpu::features::init();
+	cpu::kvm::init();
+
 	// Ensuring the CPU has SSE
 	if !cpu::sse::is_present() {
 		panic!("SSE support is required to run this kernel :(");
 	}
 	cpu::sse::enable();
+	cpu::fpu::init();
 
 	// Reading multiboot informations
 	multiboot::read_tags(multiboot_ptr);
 
+	let boot_info = multiboot::get_boot_info();
+
+	// Parsing bootloader command line arguments
+	let cmdline = boot_info.cmdline.unwrap_or(b"");
+	let args_parser = match cmdline::ArgsParser::parse(cmdline) {
+		Ok(p) => p,
+		Err(e) => {
+			println!("{e}");
+			power::halt();
+		}
+	};
+	LOGGER.lock().silent = args_parser.is_silent();
+	device::default::IOMEM_RELAXED.store(args_parser.is_iomem_relaxed(), Ordering::Relaxed);
+	if let Some(rate) = args_parser.get_hwrng_rate() {
+		device::hwrng::FILL_RATE.store(rate, Ordering::Relaxed);
+	}
+
 	// Initializing memory allocation
 	memory::memmap::init(multiboot_ptr);
 	if cfg!(config_debug_debug) {
 		memory::memmap::print_entries();
 	}
+	if let Some((size, offset)) = args_parser.get_crashkernel() {
+		memory::reserved::CRASHKERNEL.init(size, offset);
+	}
+	if let Some((size, offset)) = args_parser.get_pstore() {
+		memory::reserved::PSTORE.init(size, offset);
+	}
+	// Test memory before allocators use it
+	if let Some(passes) = args_parser.get_memtest_passes() {
+		memory::memtest::run(passes);
+	}
 	memory::alloc::init();
 
 	if init_vmem().is_err() {
 		panic!("Cannot initialize kernel virtual memory!");
 	}
+	file::fs::pstore::ram::init();
 
 	// From here, the kernel considers that memory management has been fully
 	// initialized
@@ -280,29 +322,24 @@ pub extern "C" fn kernel_main(magic: u32, multiboot_ptr: *const c_void) -> ! {
 	#[cfg(test)]
 	kernel_selftest();
 
-	let boot_info = multiboot::get_boot_info();
-
-	// Parsing bootloader command line arguments
-	let cmdline = boot_info.cmdline.unwrap_or(b"");
-	let args_parser = match cmdline::ArgsParser::parse(cmdline) {
-		Ok(p) => p,
-		Err(e) => {
-			println!("{e}");
-			power::halt();
-		}
-	};
-	LOGGER.lock().silent = args_parser.is_silent();
+	println!("Booting Maestro kernel version {VERSION} ({GIT_REV})");
+	if args_parser.is_kgdb_enabled() {
+		debug::gdbstub::enter();
+	}
 
-	println!("Booting Maestro kernel version {VERSION}");
-
-	// FIXME
-	//println!("Initializing ACPI...");
-	//acpi::init();
+	println!("Initializing ACPI...");
+	acpi::init();
+	idt::apic::init(args_parser.is_apic_disabled());
+	cpu::hotplug::init();
 
 	println!("Initializing time management...");
 	if time::init().is_err() {
 		panic!("failed to initialize time management");
 	}
+	if args_parser.get_memtest_passes().is_some() {
+		memory::memtest::init_scrubber()
+			.unwrap_or_else(|e| panic!("Failed to start memory scrubber! ({e})"));
+	}
 
 	// FIXME
 	/*println!("Initializing ramdisks...");
@@ -322,6 +359,12 @@ pub extern "C" fn kernel_main(magic: u32, multiboot_ptr: *const c_void) -> ! {
 			.unwrap_or_else(|e| panic!("Failed to initialize initramfs! ({e})"));
 	}
 	device::stage2().unwrap_or_else(|e| panic!("Failed to create device files! ({e})"));
+	if let Some(font_path) = args_parser.get_font_path() {
+		let res = Path::from_str(font_path, true).and_then(|path| vga::font::load_file(&path));
+		if let Err(e) = res {
+			println!("Failed to load console font: {e}");
+		}
+	}
 
 	println!("Initializing processes...");
 	process::init().unwrap_or_else(|e| panic!("Failed to init processes! ({e})"));
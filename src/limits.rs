@@ -12,7 +12,7 @@ pub const AIO_MAX: usize = 1;
 /// priority level from its own scheduling priority.
 pub const AIO_PRIO_DELTA_MAX: usize = 1024;
 /// Maximum length of argument to the exec functions including environment data.
-pub const ARG_MAX: usize = 4096;
+pub const ARG_MAX: usize = 131072;
 /// Maximum number of functions that may be registered with atexit().
 pub const ATEXIT_MAX: usize = 8;
 /// Maximum number of simultaneous processes per real user ID.
@@ -21,7 +21,7 @@ pub const CHILD_MAX: usize = 25;
 pub const DELAYTIMER_MAX: usize = 32;
 /// Maximum length of a host name (not including the terminating null) as
 /// returned from the gethostname() function.
-pub const HOST_NAME_MAX: usize = 255;
+pub const HOST_NAME_MAX: usize = 64;
 /// Maximum number of iovec structures that one process has available for use
 /// with readv() or writev().
 pub const IOV_MAX: usize = 16;
@@ -63,6 +63,19 @@ impl Logger {
 		&self.buff
 	}
 
+	/// Copies the most recent logs into `buf`, in order. If the logs do not fit, the oldest ones
+	/// are left out.
+	///
+	/// The function returns the number of bytes copied.
+	pub fn copy_tail(&self, buf: &mut [u8]) -> usize {
+		let len = min(self.get_size(), buf.len());
+		let begin = (self.write_head + self.buff.len() - len) % self.buff.len();
+		for (i, b) in buf[..len].iter_mut().enumerate() {
+			*b = self.buff[(begin + i) % self.buff.len()];
+		}
+		len
+	}
+
 	/// Pushes the given string onto the kernel logs buffer.
 	pub fn push(&mut self, s: &[u8]) {
 		if self.available_space() < s.len() {
@@ -16,6 +16,8 @@
 use crate::memory;
 use crate::memory::buddy;
 use crate::memory::memmap;
+use crate::memory::memtest;
+use crate::memory::reserved;
 use crate::util;
 use crate::util::math;
 use core::cmp::min;
@@ -74,4 +76,7 @@ pub fn init() {
 		unsafe { core::mem::zeroed() }, // TODO MMIO
 		kernel_zone,
 	]);
+
+	memtest::retire_boot_bad_pages();
+	reserved::reserve_all();
 }
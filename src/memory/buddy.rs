@@ -6,7 +6,12 @@
 //!
 //! The order of a frame is the `n` in the expression `pow(2, n)` that represents the
 //! size of a frame in pages.
+//!
+//! Single pages are allocated and freed through per-CPU caches, which avoids locking zones and
+//! splitting or coalescing frames on each operation. When a cache is refilled, frames on the NUMA
+//! node of the CPU are preferred.
 
+use super::numa;
 use super::stats;
 use crate::errno::AllocError;
 use crate::errno::AllocResult;
@@ -18,6 +23,8 @@ use core::ffi::c_void;
 use core::intrinsics::likely;
 use core::mem::size_of;
 use core::mem::MaybeUninit;
+use core::ptr::addr_of;
+use core::ptr::null_mut;
 use core::ptr::NonNull;
 
 /// Type representing the order of a memory frame.
@@ -46,6 +53,19 @@ pub const FLAG_ZONE_TYPE_KERNEL: Flags = 0b10;
 /// Value indicating that the frame is used.
 pub const FRAME_STATE_USED: FrameID = !0_u32;
 
+/// The names of zones, by index.
+const ZONES_NAME: [&str; ZONES_COUNT] = ["User", "MMIO", "Kernel"];
+
+/// The maximum number of CPU cores having a page cache.
+const CORES_MAX: usize = 1;
+/// The maximum number of pages held by a per-CPU cache, for each zone.
+const PCP_HIGH: usize = 64;
+/// The number of pages moved at once between a per-CPU cache and its zone.
+const PCP_BATCH: usize = 16;
+/// The maximum number of free frames examined in each free list to find a frame on the local
+/// NUMA node.
+const NODE_SCAN_MAX: usize = 8;
+
 /// Structure representing an allocatable zone of memory.
 #[derive(Debug)]
 pub(crate) struct Zone {
@@ -124,6 +144,122 @@ impl Zone {
 		(self.pages_count as usize) * memory::PAGE_SIZE
 	}
 
+	/// Returns an available frame owned by this zone, with an order of at least `order`, and
+	/// beginning on the NUMA node `node`.
+	///
+	/// Only the first frames of each free list are examined.
+	fn get_node_frame(&self, order: FrameOrder, node: u32) -> Option<&'static mut Frame> {
+		for first in self.free_list[(order as usize)..].iter().filter_map(|f| *f) {
+			let mut frame = unsafe { &mut *first };
+			for _ in 0..NODE_SCAN_MAX {
+				if numa::node_of(frame.get_ptr(self) as u64) == Some(node) {
+					return Some(frame);
+				}
+
+				let id = frame.get_id(self);
+				if frame.next == id {
+					break;
+				}
+				frame = unsafe { &mut *self.get_frame(frame.next) };
+			}
+		}
+		None
+	}
+
+	/// Allocates a frame of order `order`, preferably on the NUMA node `node`.
+	///
+	/// The function returns the physical address of the frame. If no frame is available, the
+	/// function returns `None`.
+	fn alloc_frame(&mut self, order: FrameOrder, node: Option<u32>) -> Option<*mut c_void> {
+		let frame = node
+			.and_then(|node| self.get_node_frame(order, node))
+			.or_else(|| self.get_available_frame(order))?;
+
+		debug_assert!(!frame.is_used());
+		frame.split(self, order);
+
+		let ptr = frame.get_ptr(self);
+		debug_assert!(ptr.is_aligned_to(memory::PAGE_SIZE));
+		debug_assert!(ptr >= self.begin && ptr < (self.begin as usize + self.get_size()) as _);
+
+		frame.mark_used();
+		self.allocated_pages += math::pow2(order as usize);
+		Some(ptr)
+	}
+
+	/// Frees the frame of order `order` at physical address `ptr`.
+	fn free_frame(&mut self, ptr: *const c_void, order: FrameOrder) {
+		let frame_id = self.get_frame_id_from_ptr(ptr);
+		debug_assert!(frame_id < self.pages_count);
+
+		let frame = self.get_frame(frame_id);
+		unsafe {
+			debug_assert!((*frame).is_used());
+			(*frame).mark_free(self);
+			(*frame).coalesce(self);
+		}
+
+		self.allocated_pages -= math::pow2(order as usize);
+	}
+
+	/// Tells whether the frame with identifier `id` is the beginning of a free frame of order
+	/// `order`, linked to the free list.
+	fn is_free_frame(&self, id: FrameID, order: FrameOrder) -> bool {
+		if id as usize + math::pow2(order as usize) > self.pages_count as usize {
+			return false;
+		}
+		let frame = unsafe { &*self.get_frame(id) };
+		if frame.is_used() || frame.order != order || frame.prev >= self.pages_count {
+			return false;
+		}
+		// The metadata of frames merged into larger frames is left as is, so checking the frame
+		// is actually linked is necessary
+		if frame.prev == id {
+			self.free_list[order as usize] == Some(frame as *const _ as *mut _)
+		} else {
+			unsafe { (*self.get_frame(frame.prev)).next == id }
+		}
+	}
+
+	/// Takes the free page with identifier `id` out of the free list, splitting the free frame
+	/// containing it.
+	///
+	/// If the page is not free, the function returns `false`.
+	fn take_page(&mut self, id: FrameID) -> bool {
+		let containing = (0..=MAX_ORDER)
+			.map(|order| (id & !(math::pow2(order as FrameID) - 1), order))
+			.find(|(begin, order)| self.is_free_frame(*begin, *order));
+		let Some((mut begin, mut order)) = containing else {
+			return false;
+		};
+
+		unsafe {
+			(*self.get_frame(begin)).unlink(self);
+		}
+		// Give back the halves that do not contain the page
+		while order > 0 {
+			order -= 1;
+			let half = math::pow2(order as FrameID);
+			let other = if id < begin + half {
+				begin + half
+			} else {
+				let lower = begin;
+				begin += half;
+				lower
+			};
+			let other = unsafe { &mut *self.get_frame(other) };
+			other.mark_free(self);
+			other.order = order;
+			other.link(self);
+		}
+
+		let frame = unsafe { &mut *self.get_frame(id) };
+		frame.order = 0;
+		frame.mark_used();
+		self.allocated_pages += 1;
+		true
+	}
+
 	/// Returns an available frame owned by this zone, with an order of at least
 	/// `order`.
 	fn get_available_frame(&self, order: FrameOrder) -> Option<&'static mut Frame> {
@@ -447,14 +583,102 @@ impl Frame {
 
 /// The array of buddy allocator zones.
 static ZONES: IntMutex<MaybeUninit<[Zone; ZONES_COUNT]>> = IntMutex::new(MaybeUninit::uninit());
+/// The physical memory range covered by each zone, as a beginning and end address.
+///
+/// This array allows to find the zone of a page without locking zones.
+static mut ZONES_RANGE: [(usize, usize); ZONES_COUNT] = [(0, 0); ZONES_COUNT];
+
+/// A cache of free single pages of a zone, for a CPU core.
+struct PageCache {
+	/// The physical addresses of the pages.
+	pages: [*mut c_void; PCP_HIGH],
+	/// The number of pages in the cache.
+	len: usize,
+}
+
+/// An empty page cache.
+const PAGE_CACHE_INIT: PageCache = PageCache {
+	pages: [null_mut(); PCP_HIGH],
+	len: 0,
+};
+/// The default value for `PAGE_CACHES`.
+#[allow(clippy::declare_interior_mutable_const)]
+const PAGE_CACHES_INIT: IntMutex<[PageCache; ZONES_COUNT]> =
+	IntMutex::new([PAGE_CACHE_INIT; ZONES_COUNT]);
+/// The page caches of each CPU core, for each zone.
+///
+/// If both a cache and `ZONES` are to be locked, the cache must be locked first.
+static PAGE_CACHES: [IntMutex<[PageCache; ZONES_COUNT]>; CORES_MAX] =
+	[PAGE_CACHES_INIT; CORES_MAX];
 
 /// Initializes the buddy allocator with the given list of zones.
 ///
 /// If this function is *not* called before using the buddy allocator, the behaviour is undefined.
 pub(crate) fn init(zones: [Zone; ZONES_COUNT]) {
+	for (i, z) in zones.iter().enumerate() {
+		// Safe because this function is called only once, at boot
+		unsafe {
+			ZONES_RANGE[i] = (z.begin as usize, z.begin as usize + z.get_size());
+		}
+	}
 	ZONES.lock().write(zones);
 }
 
+/// Returns the index of the zone containing the physical address `ptr`.
+fn get_zone_index(ptr: *const c_void) -> Option<usize> {
+	// Safe because the array is written only at initialization
+	let ranges = unsafe { &*addr_of!(ZONES_RANGE) };
+	ranges
+		.iter()
+		.position(|(begin, end)| (*begin..*end).contains(&(ptr as usize)))
+}
+
+/// Returns the page caches of the current CPU core.
+fn get_page_caches() -> &'static IntMutex<[PageCache; ZONES_COUNT]> {
+	let core_id = 0; // TODO
+	&PAGE_CACHES[core_id]
+}
+
+/// Allocates a page from the cache of the zone with index `zone_id`, refilling it if empty.
+///
+/// `caches` is the list of caches of the current CPU core.
+fn alloc_cached(caches: &mut [PageCache; ZONES_COUNT], zone_id: usize) -> Option<*mut c_void> {
+	let cache = &mut caches[zone_id];
+	if cache.len == 0 {
+		let node = numa::local_node();
+
+		let mut zones = ZONES.lock();
+		let zone = &mut unsafe { zones.assume_init_mut() }[zone_id];
+		while cache.len < PCP_BATCH {
+			let Some(ptr) = zone.alloc_frame(0, node) else {
+				break;
+			};
+			cache.pages[cache.len] = ptr;
+			cache.len += 1;
+		}
+	}
+
+	if cache.len == 0 {
+		return None;
+	}
+	cache.len -= 1;
+	Some(cache.pages[cache.len])
+}
+
+/// Returns the pages of every cache of the current CPU core to their zone.
+fn drain_caches() {
+	let mut caches = get_page_caches().lock();
+	let mut zones = ZONES.lock();
+	let zones = unsafe { zones.assume_init_mut() };
+
+	for (cache, zone) in caches.iter_mut().zip(zones.iter_mut()) {
+		for ptr in &cache.pages[..cache.len] {
+			zone.free_frame(*ptr, 0);
+		}
+		cache.len = 0;
+	}
+}
+
 /// The size in bytes of a frame with the given order `order`.
 #[inline]
 pub fn get_frame_size(order: FrameOrder) -> usize {
@@ -477,19 +701,6 @@ pub const fn get_frame_metadata_size() -> usize {
 	size_of::<Frame>()
 }
 
-/// Returns a mutable reference to the zone that contains the given pointer `ptr`.
-///
-/// `zones` is the list of zones.
-fn get_zone_for_pointer<'z>(
-	zones: &'z mut [Zone; ZONES_COUNT],
-	ptr: *const c_void,
-) -> Option<&'z mut Zone> {
-	zones
-		.iter_mut()
-		.filter(|z| ptr >= z.begin && (ptr as usize) < (z.begin as usize) + z.get_size())
-		.next()
-}
-
 /// Allocates a frame of memory using the buddy allocator.
 ///
 /// `order` is the order of the frame to be allocated.
@@ -500,30 +711,28 @@ fn get_zone_for_pointer<'z>(
 pub fn alloc(order: FrameOrder, flags: Flags) -> AllocResult<NonNull<c_void>> {
 	debug_assert!(order <= MAX_ORDER);
 
-	let mut zones = ZONES.lock();
-	let zones = unsafe { zones.assume_init_mut() };
-
 	let begin_zone = (flags & ZONE_TYPE_MASK) as usize;
-	for zone in &mut zones[begin_zone..] {
-		let Some(frame) = zone.get_available_frame(order) else {
-			continue;
+	let ptr = if order == 0 {
+		let mut caches = get_page_caches().lock();
+		(begin_zone..ZONES_COUNT).find_map(|i| alloc_cached(&mut caches, i))
+	} else {
+		let try_alloc = || {
+			let mut zones = ZONES.lock();
+			let zones = unsafe { zones.assume_init_mut() };
+			zones[begin_zone..]
+				.iter_mut()
+				.find_map(|zone| zone.alloc_frame(order, None))
 		};
-
-		debug_assert!(!frame.is_used());
-		frame.split(zone, order);
-
-		let ptr = frame.get_ptr(&zone);
-		debug_assert!(ptr.is_aligned_to(memory::PAGE_SIZE));
-		debug_assert!(ptr >= zone.begin && ptr < (zone.begin as usize + zone.get_size()) as _);
-
-		frame.mark_used();
-		zone.allocated_pages += math::pow2(order as usize);
-
-		update_stats(4 * math::pow2(order as usize) as isize);
-		return NonNull::new(ptr).ok_or(AllocError);
-	}
-
-	Err(AllocError)
+		// Pages held by caches may prevent forming a large enough frame
+		try_alloc().or_else(|| {
+			drain_caches();
+			try_alloc()
+		})
+	};
+	let ptr = ptr.ok_or(AllocError)?;
+
+	update_stats(4 * math::pow2(order as usize) as isize);
+	NonNull::new(ptr).ok_or(AllocError)
 }
 
 /// Calls `alloc` with order `order`.
@@ -546,25 +755,57 @@ pub fn free(ptr: *const c_void, order: FrameOrder) {
 	debug_assert!(ptr.is_aligned_to(memory::PAGE_SIZE));
 	debug_assert!(order <= MAX_ORDER);
 
-	let mut zones = ZONES.lock();
-	let zones = unsafe { zones.assume_init_mut() };
-
-	let zone = get_zone_for_pointer(zones, ptr).unwrap();
-
-	let frame_id = zone.get_frame_id_from_ptr(ptr);
-	debug_assert!(frame_id < zone.pages_count);
-
-	let frame = zone.get_frame(frame_id);
-	unsafe {
-		debug_assert!((*frame).is_used());
-		(*frame).mark_free(&zone);
-		(*frame).coalesce(zone);
+	let zone_id = get_zone_index(ptr).unwrap();
+	if order == 0 {
+		let mut caches = get_page_caches().lock();
+		let cache = &mut caches[zone_id];
+		if cache.len >= PCP_HIGH {
+			// The cache is full: give a batch of pages back to the zone
+			let mut zones = ZONES.lock();
+			let zone = &mut unsafe { zones.assume_init_mut() }[zone_id];
+			for ptr in &cache.pages[(cache.len - PCP_BATCH)..cache.len] {
+				zone.free_frame(*ptr, 0);
+			}
+			cache.len -= PCP_BATCH;
+		}
+		cache.pages[cache.len] = ptr as _;
+		cache.len += 1;
+	} else {
+		let mut zones = ZONES.lock();
+		let zone = &mut unsafe { zones.assume_init_mut() }[zone_id];
+		zone.free_frame(ptr, order);
 	}
 
-	zone.allocated_pages -= math::pow2(order as usize);
 	update_stats(-4 * math::pow2(order as usize) as isize);
 }
 
+/// Takes the free page at physical address `ptr` out of its zone, so that it cannot be allocated
+/// until it is given back with [`release_page`].
+///
+/// Pages held by the caches of CPU cores are not free from the point of view of zones, so they
+/// cannot be taken.
+///
+/// Unlike [`alloc`], this function does not update memory usage statistics.
+///
+/// If the page is not free, the function returns `false`.
+pub fn take_page(ptr: *const c_void) -> bool {
+	let Some(zone_id) = get_zone_index(ptr) else {
+		return false;
+	};
+	let mut zones = ZONES.lock();
+	let zone = &mut unsafe { zones.assume_init_mut() }[zone_id];
+	let id = zone.get_frame_id_from_ptr(ptr);
+	zone.take_page(id)
+}
+
+/// Gives back the page at physical address `ptr`, taken with [`take_page`].
+pub fn release_page(ptr: *const c_void) {
+	let zone_id = get_zone_index(ptr).unwrap();
+	let mut zones = ZONES.lock();
+	let zone = &mut unsafe { zones.assume_init_mut() }[zone_id];
+	zone.free_frame(ptr, 0);
+}
+
 /// Frees the given memory frame.
 ///
 /// `ptr` is the *virtual* address to the beginning of the frame and `order` is the order of the
@@ -588,12 +829,57 @@ pub fn update_stats(n: isize) {
 	}
 }
 
+/// Returns the number of pages held by the caches of CPU cores for the zone with index
+/// `zone_id`.
+fn cached_pages_count(zone_id: usize) -> usize {
+	PAGE_CACHES
+		.iter()
+		.map(|caches| caches.lock()[zone_id].len)
+		.sum()
+}
+
 /// Returns the total number of pages allocated by the buddy allocator.
 pub fn allocated_pages_count() -> usize {
+	let cached: usize = (0..ZONES_COUNT).map(cached_pages_count).sum();
+
 	let zones = ZONES.lock();
 	let zones = unsafe { zones.assume_init_ref() };
+	zones.iter().map(|z| z.allocated_pages).sum::<usize>() - cached
+}
+
+/// Informations about a zone.
+pub struct ZoneInfo {
+	/// The name of the zone.
+	pub name: &'static str,
+	/// The NUMA node of the beginning of the zone, if known.
+	pub node: Option<u32>,
+	/// The physical address of the beginning of the zone.
+	pub begin: usize,
+	/// The number of pages in the zone.
+	pub pages: usize,
+	/// The number of free pages in the zone, including pages held by caches.
+	pub free: usize,
+	/// The number of free pages held by the caches of CPU cores.
+	pub cached: usize,
+}
 
-	zones.iter().map(|z| z.allocated_pages).sum()
+/// Returns informations about each zone.
+pub fn get_zones_info() -> [ZoneInfo; ZONES_COUNT] {
+	let cached: [usize; ZONES_COUNT] = core::array::from_fn(cached_pages_count);
+
+	let zones = ZONES.lock();
+	let zones = unsafe { zones.assume_init_ref() };
+	core::array::from_fn(|i| {
+		let zone = &zones[i];
+		ZoneInfo {
+			name: ZONES_NAME[i],
+			node: numa::node_of(zone.begin as u64),
+			begin: zone.begin as usize,
+			pages: zone.pages_count as usize,
+			free: zone.pages_count as usize - zone.allocated_pages + cached[i],
+			cached: cached[i],
+		}
+	})
 }
 
 #[cfg(test)]
@@ -707,6 +993,23 @@ mod test {
 		debug_assert_eq!(allocated_pages_count(), alloc_pages);
 	}
 
+	#[test_case]
+	fn buddy_take_page() {
+		let alloc_pages = allocated_pages_count();
+
+		// Frames of order 1 are not cached, so they go back to the zone when freed
+		let p = alloc(1, FLAG_ZONE_TYPE_KERNEL).unwrap().as_ptr();
+		free(p, 1);
+		let page = (p as usize + memory::PAGE_SIZE) as *const c_void;
+		assert!(take_page(page));
+		assert!(!take_page(page));
+		release_page(page);
+		assert!(take_page(page));
+		release_page(page);
+
+		debug_assert_eq!(allocated_pages_count(), alloc_pages);
+	}
+
 	struct TestDupNode {
 		next: *mut TestDupNode,
 	}
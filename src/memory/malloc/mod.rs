@@ -26,9 +26,18 @@ use core::ops::IndexMut;
 use core::ptr;
 use core::ptr::drop_in_place;
 use core::slice;
+use core::sync::atomic;
+use core::sync::atomic::AtomicUsize;
 
 /// The allocator's mutex.
 static MUTEX: IntMutex<()> = IntMutex::new(());
+/// The number of bytes currently allocated.
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns the number of bytes currently allocated.
+pub fn allocated_bytes() -> usize {
+	ALLOCATED.load(atomic::Ordering::Relaxed)
+}
 
 /// Allocates `n` bytes of kernel memory and returns a pointer to the beginning
 /// of the allocated chunk.
@@ -55,6 +64,7 @@ pub unsafe fn alloc(n: NonZeroUsize) -> AllocResult<NonNull<c_void>> {
 
 	let chunk = &mut free_chunk.chunk;
 	chunk.used = true;
+	ALLOCATED.fetch_add(chunk.get_size(), atomic::Ordering::Relaxed);
 
 	let ptr = chunk.get_ptr_mut();
 	debug_assert!(ptr.is_aligned_to(chunk::ALIGNEMENT));
@@ -85,6 +95,7 @@ pub unsafe fn realloc(ptr: NonNull<c_void>, n: NonZeroUsize) -> AllocResult<NonN
 	match n.get().cmp(&chunk_size) {
 		Ordering::Less => {
 			chunk.shrink(chunk_size - n.get());
+			ALLOCATED.fetch_sub(chunk_size - chunk.get_size(), atomic::Ordering::Relaxed);
 			Ok(ptr)
 		}
 
@@ -99,6 +110,7 @@ pub unsafe fn realloc(ptr: NonNull<c_void>, n: NonZeroUsize) -> AllocResult<NonN
 
 				Ok(new_ptr)
 			} else {
+				ALLOCATED.fetch_add(chunk.get_size() - chunk_size, atomic::Ordering::Relaxed);
 				Ok(ptr)
 			}
 		}
@@ -125,6 +137,7 @@ pub unsafe fn free(mut ptr: NonNull<c_void>) {
 	chunk.check();
 
 	chunk.used = false;
+	ALLOCATED.fetch_sub(chunk.get_size(), atomic::Ordering::Relaxed);
 	let free_chunk = chunk.as_free_chunk().unwrap();
 	free_chunk.prev = None;
 	free_chunk.next = None;
@@ -60,6 +60,28 @@ pub fn print_entries() {
 	}
 }
 
+/// Tells whether the physical memory range starting at `begin` and ending at `end` (exclusive)
+/// overlaps a region of available RAM.
+pub fn is_ram(begin: u64, end: u64) -> bool {
+	let mem_info = get_info();
+
+	let mut ptr = mem_info.memory_maps;
+	while (ptr as usize) < (mem_info.memory_maps as usize) + (mem_info.memory_maps_size) {
+		let entry = unsafe { &*ptr };
+
+		if entry.type_ == multiboot::MEMORY_AVAILABLE
+			&& begin < entry.addr + entry.len
+			&& entry.addr < end
+		{
+			return true;
+		}
+
+		ptr = ((ptr as usize) + mem_info.memory_maps_entry_size) as *const _;
+	}
+
+	false
+}
+
 /// Returns the pointer to the beginning of the main physical allocatable memory
 /// and its size in number of pages.
 fn get_phys_main(multiboot_ptr: *const c_void) -> (*const c_void, usize) {
@@ -0,0 +1,249 @@
+//! Memory testing, to detect faulty RAM before it corrupts data.
+//!
+//! With the `-memtest <passes>` command line argument:
+//! - the free memory is tested at boot, before allocators are initialized
+//! - a scrubber periodically tests free pages at runtime
+//!
+//! Faulty pages are retired: they are taken out of the buddy allocator and never used again.
+//!
+//! Only memory that is always mapped in kernelspace is tested. The scrubber runs from the clock's
+//! interrupt handler, one test pass over a single page at a time.
+
+use crate::errno::EResult;
+use crate::event;
+use crate::event::CallbackResult;
+use crate::memory;
+use crate::memory::buddy;
+use crate::memory::memmap;
+use crate::memory::reserved;
+use crate::time::clock;
+use crate::time::clock::CLOCK_BOOTTIME;
+use crate::time::hw;
+use crate::time::unit::Timestamp;
+use crate::time::unit::TimestampScale;
+use crate::util::lock::IntMutex;
+use core::cmp::min;
+use core::ffi::c_void;
+use core::mem::size_of;
+use core::mem::ManuallyDrop;
+use core::ptr;
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering::Relaxed;
+
+/// The patterns written to memory to test it.
+const PATTERNS: [u32; 4] = [0x00000000, 0xffffffff, 0x55555555, 0xaaaaaaaa];
+/// The number of passes of the test of a page: one per pattern, then one writing the address of
+/// each word into itself.
+const TEST_PASSES: usize = PATTERNS.len() + 1;
+
+/// The maximum number of faulty pages found at boot that can be recorded.
+const BOOT_BAD_MAX: usize = 64;
+
+/// The number of pages tested per second by the scrubber.
+const SCRUB_RATE: u64 = 16;
+/// The maximum number of test passes run by the scrubber at once.
+const SCRUB_BATCH: u64 = 1;
+
+/// The number of pages that have been retired.
+static RETIRED_PAGES: AtomicUsize = AtomicUsize::new(0);
+
+/// The faulty pages found at boot, waiting for the buddy allocator to be initialized to be
+/// retired.
+struct BootBadPages {
+	/// The physical addresses of the pages.
+	pages: [usize; BOOT_BAD_MAX],
+	/// The number of pages in the list.
+	len: usize,
+}
+
+/// The faulty pages found at boot.
+static BOOT_BAD_PAGES: IntMutex<BootBadPages> = IntMutex::new(BootBadPages {
+	pages: [0; BOOT_BAD_MAX],
+	len: 0,
+});
+
+/// Runs the test pass `pass` on the page at physical address `phys`, which must be unused and
+/// mapped in kernelspace.
+///
+/// The content of the page is lost. If the page is faulty, the function returns `false`.
+fn test_pass(phys: usize, pass: usize) -> bool {
+	let page = memory::kern_to_virt(phys as *const c_void) as *mut u32;
+	let words = memory::PAGE_SIZE / size_of::<u32>();
+	// The last pass writes the address of each word into itself, to detect addressing faults
+	let pattern = PATTERNS.get(pass).copied();
+	let value = |i: usize| pattern.unwrap_or((phys + i * size_of::<u32>()) as _);
+	for i in 0..words {
+		unsafe {
+			ptr::write_volatile(page.add(i), value(i));
+		}
+	}
+	(0..words).all(|i| unsafe { ptr::read_volatile(page.add(i)) } == value(i))
+}
+
+/// Tests the page at physical address `phys`, running every pass.
+///
+/// Requirements and return value are the same as [`test_pass`].
+fn test_page(phys: usize) -> bool {
+	(0..TEST_PASSES).all(|pass| test_pass(phys, pass))
+}
+
+/// Returns the number of pages that have been retired.
+pub fn retired_pages() -> usize {
+	RETIRED_PAGES.load(Relaxed)
+}
+
+/// Tests the free memory with `passes` passes.
+///
+/// This function must be called before allocators are initialized. Faulty pages are retired
+/// later by [`retire_boot_bad_pages`].
+pub fn run(passes: u32) {
+	let mem_info = memmap::get_info();
+	let begin = mem_info.phys_main_begin as usize;
+	let end = min(
+		begin + mem_info.phys_main_pages * memory::PAGE_SIZE,
+		memory::get_kernelspace_size(),
+	);
+	crate::println!(
+		"memtest: testing {} kB with {passes} passes",
+		(end - begin) / 1024
+	);
+
+	let mut bad = BOOT_BAD_PAGES.lock();
+	for pass in 1..=passes {
+		crate::println!("memtest: pass {pass}/{passes}");
+		for page in (begin..end).step_by(memory::PAGE_SIZE) {
+			let page_end = (page + memory::PAGE_SIZE) as u64;
+			// The content of reserved regions must be preserved
+			if reserved::overlaps(page as _, page_end) {
+				continue;
+			}
+			if test_page(page) || bad.pages[..bad.len].contains(&page) {
+				continue;
+			}
+			crate::println!("memtest: faulty page at {page:#x}");
+			if bad.len < BOOT_BAD_MAX {
+				let len = bad.len;
+				bad.pages[len] = page;
+				bad.len += 1;
+			}
+		}
+	}
+	crate::println!("memtest: {} faulty pages found", bad.len);
+}
+
+/// Retires the faulty pages found by [`run`].
+///
+/// This function must be called once the buddy allocator is initialized.
+pub fn retire_boot_bad_pages() {
+	let bad = BOOT_BAD_PAGES.lock();
+	for page in &bad.pages[..bad.len] {
+		// Pages used for the allocator's metadata do not belong to any zone
+		if buddy::take_page(*page as _) {
+			RETIRED_PAGES.fetch_add(1, Relaxed);
+		} else {
+			crate::println!("memtest: cannot retire faulty page at {page:#x}");
+		}
+	}
+}
+
+/// The state of the scrubber.
+struct Scrubber {
+	/// The physical address of the beginning of the scrubbed memory.
+	begin: usize,
+	/// The number of pages of the scrubbed memory.
+	pages: usize,
+	/// The index of the next page to test.
+	cursor: usize,
+	/// The physical address of the page being tested, taken out of the buddy allocator, if any.
+	page: Option<usize>,
+	/// The next test pass to run on the page being tested.
+	pass: usize,
+
+	/// The timestamp of the last tick, in milliseconds.
+	last: Timestamp,
+	/// The number of test passes to run that could not be run yet.
+	due: u64,
+}
+
+impl Scrubber {
+	/// Tests free pages according to the time elapsed since the last tick.
+	///
+	/// Pages are taken out of the buddy allocator while being tested, which may span several
+	/// ticks. Faulty pages are never given back.
+	fn tick(&mut self) {
+		let Ok(now) = clock::current_time(CLOCK_BOOTTIME, TimestampScale::Millisecond) else {
+			return;
+		};
+		self.due += now.saturating_sub(self.last) * SCRUB_RATE * TEST_PASSES as u64 / 1000;
+		self.due = min(self.due, SCRUB_BATCH);
+		self.last = now;
+
+		while self.due > 0 {
+			self.due -= 1;
+			let page = match self.page {
+				Some(page) => page,
+				None => {
+					let page = self.begin + self.cursor * memory::PAGE_SIZE;
+					self.cursor = (self.cursor + 1) % self.pages;
+					if !buddy::take_page(page as _) {
+						continue;
+					}
+					self.page = Some(page);
+					self.pass = 0;
+					page
+				}
+			};
+
+			if !test_pass(page, self.pass) {
+				crate::println!("memtest: retired faulty page at {page:#x}");
+				RETIRED_PAGES.fetch_add(1, Relaxed);
+				self.page = None;
+				continue;
+			}
+			self.pass += 1;
+			if self.pass == TEST_PASSES {
+				buddy::release_page(page as _);
+				self.page = None;
+			}
+		}
+	}
+}
+
+/// Starts the scrubber.
+///
+/// The scrubber tests the kernel zone, which is always mapped. If no clock is available to drive
+/// the scrubber, the function does nothing.
+pub fn init_scrubber() -> EResult<()> {
+	let Some(zone) = buddy::get_zones_info()
+		.into_iter()
+		.find(|z| z.name == "Kernel" && z.pages > 0)
+	else {
+		return Ok(());
+	};
+	let vector = {
+		let hw_clocks = hw::CLOCKS.lock();
+		hw_clocks
+			.get(b"rtc".as_slice())
+			.map(|rtc| rtc.get_interrupt_vector())
+	};
+	let Some(vector) = vector else {
+		return Ok(());
+	};
+
+	let mut scrubber = Scrubber {
+		begin: zone.begin,
+		pages: zone.pages,
+		cursor: 0,
+		page: None,
+		pass: 0,
+
+		last: clock::current_time(CLOCK_BOOTTIME, TimestampScale::Millisecond)?,
+		due: 0,
+	};
+	let hook = event::register_callback(vector, move |_, _, _, _| {
+		scrubber.tick();
+		CallbackResult::Continue
+	})?;
+	let _ = ManuallyDrop::new(hook);
+	Ok(())
+}
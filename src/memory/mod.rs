@@ -12,8 +12,11 @@ pub mod alloc;
 pub mod buddy;
 pub mod malloc;
 pub mod memmap;
+pub mod memtest;
 pub mod mmio;
+pub mod numa;
 pub mod physical_ref_counter;
+pub mod reserved;
 pub mod stack;
 pub mod stats;
 pub mod vmem;
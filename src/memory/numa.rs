@@ -0,0 +1,90 @@
+//! NUMA (Non-Uniform Memory Access) topology.
+//!
+//! On NUMA systems, memory and CPU cores are grouped into nodes. Accessing memory from a core of
+//! the same node is faster than accessing memory of another node.
+//!
+//! The topology is provided by the ACPI's System Resource Affinity Table (SRAT). If it is not
+//! available, the system is considered as having a single node and every function of this module
+//! returns `None`.
+
+use crate::util::lock::IntMutex;
+use core::arch::x86::__cpuid;
+
+/// The maximum number of memory ranges that can be registered.
+const MEMORY_RANGES_MAX: usize = 32;
+/// The maximum number of CPU cores that can be registered.
+const CPUS_MAX: usize = 32;
+
+/// A range of physical memory belonging to a node.
+#[derive(Clone, Copy, Debug)]
+struct MemoryRange {
+	/// The ID of the node.
+	node: u32,
+	/// The beginning of the range.
+	begin: u64,
+	/// The end of the range (exclusive).
+	end: u64,
+}
+
+/// The NUMA topology of the system.
+struct Topology {
+	/// The registered memory ranges.
+	memory: [Option<MemoryRange>; MEMORY_RANGES_MAX],
+	/// The registered CPU cores, with their APIC ID and the ID of their node.
+	cpus: [Option<(u32, u32)>; CPUS_MAX],
+}
+
+/// The NUMA topology of the system.
+static TOPOLOGY: IntMutex<Topology> = IntMutex::new(Topology {
+	memory: [None; MEMORY_RANGES_MAX],
+	cpus: [None; CPUS_MAX],
+});
+
+/// Registers the memory range starting at `begin` of size `len` in bytes as belonging to the node
+/// `node`.
+///
+/// If too many ranges are registered, the range is ignored.
+pub fn add_memory(node: u32, begin: u64, len: u64) {
+	let mut topology = TOPOLOGY.lock();
+	if let Some(slot) = topology.memory.iter_mut().find(|r| r.is_none()) {
+		*slot = Some(MemoryRange {
+			node,
+			begin,
+			end: begin.saturating_add(len),
+		});
+	}
+}
+
+/// Registers the CPU core with APIC ID `apic_id` as belonging to the node `node`.
+///
+/// If too many cores are registered, the core is ignored.
+pub fn add_cpu(apic_id: u32, node: u32) {
+	let mut topology = TOPOLOGY.lock();
+	if let Some(slot) = topology.cpus.iter_mut().find(|c| c.is_none()) {
+		*slot = Some((apic_id, node));
+	}
+}
+
+/// Returns the ID of the node the physical address `addr` belongs to.
+pub fn node_of(addr: u64) -> Option<u32> {
+	TOPOLOGY
+		.lock()
+		.memory
+		.iter()
+		.flatten()
+		.find(|r| (r.begin..r.end).contains(&addr))
+		.map(|r| r.node)
+}
+
+/// Returns the ID of the node of the current CPU core.
+pub fn local_node() -> Option<u32> {
+	// Safe because `cpuid` is available on every supported CPU
+	let apic_id = unsafe { __cpuid(1) }.ebx >> 24;
+	TOPOLOGY
+		.lock()
+		.cpus
+		.iter()
+		.flatten()
+		.find(|(id, _)| *id == apic_id)
+		.map(|(_, node)| *node)
+}
@@ -0,0 +1,166 @@
+//! Reserved regions are ranges of physical memory set aside at boot with command line arguments:
+//! - `crashkernel=<size>@<offset>`: the crash kernel region, meant to hold a kernel to be started
+//! on crash
+//! - `pstore=<size>@<offset>`: the pstore region, holding records persisting across reboots. See
+//! [`crate::file::fs::pstore`]
+//!
+//! Reserved regions are never given to the buddy allocator and their content is left untouched by
+//! the kernel, including by the memory test.
+//!
+//! Privileged processes can access the crash kernel region through `/dev/mem`, even when access
+//! to RAM is otherwise restricted.
+
+use crate::memory;
+use crate::memory::buddy;
+use crate::memory::memmap;
+use crate::util::lock::IntMutex;
+use crate::util::math;
+
+/// A region of physical memory.
+#[derive(Clone, Copy, Debug)]
+pub struct Region {
+	/// The physical address of the beginning of the region, page-aligned.
+	pub begin: usize,
+	/// The size of the region in bytes, a multiple of the page size.
+	pub size: usize,
+}
+
+impl Region {
+	/// Tells whether the region contains the whole physical range starting at `begin` and ending
+	/// at `end` (exclusive).
+	pub fn contains(&self, begin: u64, end: u64) -> bool {
+		begin >= self.begin as u64 && end <= (self.begin + self.size) as u64
+	}
+
+	/// Tells whether the region has at least one byte in common with the physical range starting
+	/// at `begin` and ending at `end` (exclusive).
+	pub fn overlaps(&self, begin: u64, end: u64) -> bool {
+		begin < (self.begin + self.size) as u64 && end > self.begin as u64
+	}
+}
+
+/// A reserved region, which may be absent if not specified or invalid.
+pub struct ReservedRegion {
+	/// The name of the region, used in messages.
+	name: &'static str,
+	/// The region, if any.
+	region: IntMutex<Option<Region>>,
+}
+
+impl ReservedRegion {
+	/// Creates an absent region with the given name.
+	const fn new(name: &'static str) -> Self {
+		Self {
+			name,
+			region: IntMutex::new(None),
+		}
+	}
+
+	/// Returns the region, if any.
+	pub fn get(&self) -> Option<Region> {
+		*self.region.lock()
+	}
+
+	/// Sets the region to `size` bytes at physical offset `offset`. The size is rounded up to a
+	/// multiple of the page size.
+	///
+	/// This function must be called after the memory map is initialized and before the memory is
+	/// used, so that the content of the region is preserved. The pages are reserved later by
+	/// [`reserve_all`].
+	///
+	/// If the region is invalid, outside of the allocatable memory or overlaps another reserved
+	/// region, the function prints an error and the region is ignored.
+	pub fn init(&self, size: u64, offset: u64) {
+		let page_size = memory::PAGE_SIZE as u64;
+		let size = math::ceil_div(size, page_size) * page_size;
+		let end = offset.checked_add(size).unwrap_or(u64::MAX);
+		if size == 0 || offset % page_size != 0 {
+			crate::println!("{}: invalid region", self.name);
+			return;
+		}
+
+		let mem_info = memmap::get_info();
+		let main_begin = mem_info.phys_main_begin as u64;
+		let main_end = main_begin + (mem_info.phys_main_pages * memory::PAGE_SIZE) as u64;
+		if offset < main_begin || end > main_end {
+			crate::println!(
+				"{}: region {offset:#x}-{end:#x} is not in allocatable memory",
+				self.name
+			);
+			return;
+		}
+		let overlap = REGIONS
+			.iter()
+			.filter(|r| !core::ptr::eq(*r, self))
+			.any(|r| r.get().is_some_and(|r| r.overlaps(offset, end)));
+		if overlap {
+			crate::println!(
+				"{}: region {offset:#x}-{end:#x} overlaps another reserved region",
+				self.name
+			);
+			return;
+		}
+
+		*self.region.lock() = Some(Region {
+			begin: offset as _,
+			size: size as _,
+		});
+	}
+
+	/// Takes the pages of the region out of the buddy allocator.
+	///
+	/// If a page cannot be taken, the region is given back and ignored.
+	fn reserve(&self) {
+		let mut region = self.region.lock();
+		let Some(r) = *region else {
+			return;
+		};
+
+		let end = r.begin + r.size;
+		for page in (r.begin..end).step_by(memory::PAGE_SIZE) {
+			// Pages used for the allocator's metadata do not belong to any zone
+			if buddy::take_page(page as _) {
+				continue;
+			}
+			crate::println!("{}: cannot reserve page at {page:#x}", self.name);
+			for page in (r.begin..page).step_by(memory::PAGE_SIZE) {
+				buddy::release_page(page as _);
+			}
+			*region = None;
+			return;
+		}
+		buddy::update_stats((r.size / 1024) as _);
+
+		crate::println!(
+			"{}: reserved {} kB at {:#x}",
+			self.name,
+			r.size / 1024,
+			r.begin
+		);
+	}
+}
+
+/// The crash kernel region.
+pub static CRASHKERNEL: ReservedRegion = ReservedRegion::new("crashkernel");
+/// The region used by the RAM backend of the pstore.
+pub static PSTORE: ReservedRegion = ReservedRegion::new("pstore");
+
+/// The list of reserved regions.
+static REGIONS: [&ReservedRegion; 2] = [&CRASHKERNEL, &PSTORE];
+
+/// Tells whether the physical range starting at `begin` and ending at `end` (exclusive) has at
+/// least one byte in a reserved region.
+pub fn overlaps(begin: u64, end: u64) -> bool {
+	REGIONS
+		.iter()
+		.any(|r| r.get().is_some_and(|r| r.overlaps(begin, end)))
+}
+
+/// Takes the pages of the reserved regions out of the buddy allocator.
+///
+/// This function must be called once the buddy allocator is initialized.
+pub fn reserve_all() {
+	for region in REGIONS {
+		region.reserve();
+	}
+}
@@ -9,6 +9,10 @@
 //! - **Kernel Module**: A piece of software to be loaded at runtime in kernelspace.
 //!
 //! Thus, **Kernel Modules** contain **Modules**.
+//!
+//! Loading a module that was not built in the kernel's tree, or forcing the load or unload of a
+//! module, taints the kernel (see [`panic::add_taint`]). Loaded modules are listed in
+//! `/proc/modules` and in panic reports.
 
 pub mod version;
 
@@ -17,10 +21,12 @@ use crate::elf::parser::ELFParser;
 use crate::elf::relocation::Relocation;
 use crate::elf::ELF32Sym;
 use crate::errno;
+use crate::errno::EResult;
 use crate::errno::Errno;
 use crate::memory;
 use crate::memory::malloc;
 use crate::multiboot;
+use crate::panic;
 use crate::util::container::hashmap::HashMap;
 use crate::util::container::string::String;
 use crate::util::container::vec::Vec;
@@ -28,6 +34,7 @@ use crate::util::lock::Mutex;
 use crate::util::DisplayableStr;
 use crate::util::TryClone;
 use core::cmp::min;
+use core::fmt;
 use core::mem::size_of;
 use core::mem::transmute;
 use core::num::NonZeroUsize;
@@ -85,10 +92,35 @@ macro_rules! module {
 
 			#[no_mangle]
 			pub static MOD_DEPS: [Dependency; const_len(&$deps)] = $deps;
+
+			#[no_mangle]
+			pub static MOD_INTREE: bool = option_env!("MAESTRO_INTREE").is_some();
 		}
 	};
 }
 
+/// The state of a kernel module.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ModuleState {
+	/// The module is being initialized.
+	Loading,
+	/// The module is initialized and running.
+	Live,
+	/// The module is being unloaded.
+	Unloading,
+}
+
+impl fmt::Display for ModuleState {
+	fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let s = match self {
+			Self::Loading => "Loading",
+			Self::Live => "Live",
+			Self::Unloading => "Unloading",
+		};
+		write!(fmt, "{s}")
+	}
+}
+
 // TODO keep offsets of name, version and dependencies instead of allocating
 /// Structure representing a kernel module.
 pub struct Module {
@@ -105,6 +137,15 @@ pub struct Module {
 	/// The size of the module's memory.
 	mem_size: usize,
 
+	/// The state of the module.
+	state: ModuleState,
+	/// The taint flags caused by the module (see [`panic::add_taint`]).
+	taints: u32,
+	/// The number of loaded modules depending on this module.
+	refcount: usize,
+
+	/// Pointer to the module's initializer.
+	init: extern "C" fn() -> bool,
 	/// Pointer to the module's destructor.
 	fini: Option<extern "C" fn()>,
 }
@@ -189,7 +230,12 @@ impl Module {
 	}
 
 	/// Loads a kernel module from the given image.
-	pub fn load(image: &[u8]) -> Result<Self, Errno> {
+	///
+	/// If `force` is `true`, the magic number of the module is not checked and the module taints
+	/// the kernel.
+	///
+	/// The module is not initialized until it is added to the modules list with [`add`].
+	pub fn load(image: &[u8], force: bool) -> Result<Self, Errno> {
 		let parser = ELFParser::new(image).map_err(|e| {
 			crate::println!("Invalid ELF file as loaded module");
 			e
@@ -265,10 +311,20 @@ impl Module {
 				crate::println!("Missing `MOD_MAGIC` symbol in module image");
 				errno!(EINVAL)
 			})?;
-		if *magic != MOD_MAGIC {
+		if *magic != MOD_MAGIC && !force {
 			crate::println!("Module has an invalid magic number");
 			return Err(errno!(EINVAL));
 		}
+		let mut taints = 0;
+		if force {
+			taints |= panic::TAINT_FORCED_MODULE;
+		}
+		let in_tree = Self::get_attribute::<bool>(mem.as_slice(), &parser, "MOD_INTREE")
+			.copied()
+			.unwrap_or(false);
+		if !in_tree {
+			taints |= panic::TAINT_OOT_MODULE;
+		}
 
 		// Getting the module's name
 		let name = Self::get_attribute::<&'static str>(mem.as_slice(), &parser, "MOD_NAME")
@@ -293,25 +349,17 @@ impl Module {
 			})?;
 		let deps = Vec::from_slice(deps)?;
 
-		crate::println!("Loading module `{name}` version `{version}`");
-
-		// TODO Check that all dependencies are loaded
-
-		// Initializing module
+		// Retrieving initializer function
 		let init = parser.get_symbol_by_name("init").ok_or_else(|| {
 			crate::println!("Missing `init` symbol in module image");
 			errno!(EINVAL)
 		})?;
-		let ok = unsafe {
+		let init = unsafe {
 			let ptr = mem.as_ptr().add(init.st_value as usize);
 			let func: extern "C" fn() -> bool = transmute(ptr);
 
-			(func)()
+			func
 		};
-		if !ok {
-			crate::println!("Failed to load module `{name}`");
-			return Err(errno!(EINVAL));
-		}
 
 		// Retrieving destructor function
 		let fini = {
@@ -338,6 +386,11 @@ impl Module {
 			mem: mem as _,
 			mem_size: mem_size.get(),
 
+			state: ModuleState::Loading,
+			taints,
+			refcount: 0,
+
+			init,
 			fini,
 		})
 	}
@@ -351,6 +404,36 @@ impl Module {
 	pub fn get_version(&self) -> &Version {
 		&self.version
 	}
+
+	/// Returns the size of the module's memory in bytes.
+	pub fn get_size(&self) -> usize {
+		self.mem_size
+	}
+
+	/// Returns the address at which the module is loaded.
+	pub fn get_address(&self) -> *const u8 {
+		unsafe { self.mem.as_ptr() }
+	}
+
+	/// Returns the state of the module.
+	pub fn get_state(&self) -> ModuleState {
+		self.state
+	}
+
+	/// Returns the taint flags caused by the module.
+	pub fn get_taints(&self) -> u32 {
+		self.taints
+	}
+
+	/// Returns the number of loaded modules depending on this module.
+	pub fn get_refcount(&self) -> usize {
+		self.refcount
+	}
+
+	/// Tells whether the module depends on the module with name `name`.
+	fn depends_on(&self, name: &[u8]) -> bool {
+		self.deps.iter().any(|dep| dep.name.as_bytes() == name)
+	}
 }
 
 impl Drop for Module {
@@ -373,16 +456,131 @@ pub fn is_loaded(name: &[u8]) -> bool {
 	modules.get(name).is_some()
 }
 
-/// Adds the given module to the modules list.
-pub fn add(module: Module) -> Result<(), Errno> {
+/// Adds the given module to the modules list, then initializes it.
+///
+/// Every dependency of the module must be loaded. Else, the function returns
+/// [`errno::ENOENT`].
+///
+/// If a module with the same name is already loaded, the function returns [`errno::EEXIST`].
+pub fn add(module: Module) -> EResult<()> {
+	let name = module.name.try_clone()?;
+	let init = module.init;
+	{
+		let mut modules = MODULES.lock();
+		if modules.get(&name).is_some() {
+			return Err(errno!(EEXIST));
+		}
+		// TODO Check the version constraints of dependencies
+		let missing = module.deps.iter().find(|dep| {
+			let m = modules.get(dep.name.as_bytes());
+			!m.is_some_and(|m| m.state == ModuleState::Live)
+		});
+		if let Some(dep) = missing {
+			crate::println!("Missing dependency `{}` for module `{name}`", dep.name);
+			return Err(errno!(ENOENT));
+		}
+		crate::println!("Loading module `{name}` version `{}`", module.version);
+		panic::add_taint(module.taints);
+		for dep in module.deps.iter() {
+			if let Some(m) = modules.get_mut(dep.name.as_bytes()) {
+				m.refcount += 1;
+			}
+		}
+		modules.insert(name.try_clone()?, module)?;
+	}
+
+	// Initializing the module without holding the lock, since it may use modules
+	let ok = init();
+
 	let mut modules = MODULES.lock();
-	modules.insert(module.name.try_clone()?, module)?;
+	if ok {
+		if let Some(m) = modules.get_mut(&name) {
+			m.state = ModuleState::Live;
+		}
+		return Ok(());
+	}
+	crate::println!("Failed to load module `{name}`");
+	if let Some(mut module) = modules.remove(&name) {
+		// The module has not been initialized, so it must not be finalized either
+		module.fini = None;
+		release_deps(&mut modules, &module);
+	}
+	Err(errno!(EINVAL))
+}
 
-	Ok(())
+/// Decrements the reference counter of the dependencies of `module`.
+fn release_deps(modules: &mut HashMap<String, Module>, module: &Module) {
+	for dep in module.deps.iter() {
+		if let Some(m) = modules.get_mut(dep.name.as_bytes()) {
+			m.refcount = m.refcount.saturating_sub(1);
+		}
+	}
 }
 
-/// Removes the module with name `name`.
-pub fn remove(name: &[u8]) {
+/// Unloads the module with name `name`.
+///
+/// If the module is used by other modules, the function returns [`errno::EWOULDBLOCK`], unless
+/// `force` is `true`, in which case the kernel is tainted.
+///
+/// If the module is not loaded, the function returns [`errno::ENOENT`]. If it is being loaded or
+/// unloaded, the function returns [`errno::EBUSY`].
+pub fn remove(name: &[u8], force: bool) -> EResult<()> {
+	let fini = {
+		let mut modules = MODULES.lock();
+		let module = modules.get_mut(name).ok_or_else(|| errno!(ENOENT))?;
+		if module.state != ModuleState::Live {
+			return Err(errno!(EBUSY));
+		}
+		if module.refcount > 0 {
+			if !force {
+				return Err(errno!(EWOULDBLOCK));
+			}
+			panic::add_taint(panic::TAINT_FORCED_RMMOD);
+		}
+		module.state = ModuleState::Unloading;
+		module.fini.take()
+	};
+
+	// Finalizing the module without holding the lock, since it may use modules
+	if let Some(fini) = fini {
+		fini();
+	}
+
 	let mut modules = MODULES.lock();
-	modules.remove(name);
+	if let Some(module) = modules.remove(name) {
+		release_deps(&mut modules, &module);
+	}
+	Ok(())
+}
+
+/// Executes the given closure `f` for each loaded module. The closure also receives an iterator
+/// over the modules depending on the module.
+pub fn foreach<F>(mut f: F)
+where
+	F: FnMut(&Module, &mut dyn Iterator<Item = &Module>),
+{
+	let modules = MODULES.lock();
+	for (_, module) in modules.iter() {
+		let mut users = modules
+			.iter()
+			.map(|(_, m)| m)
+			.filter(|m| m.depends_on(&module.name));
+		f(module, &mut users);
+	}
+}
+
+/// Prints the list of loaded modules, for panic reports.
+///
+/// The list is accessed without locking since the lock may be held by the panicking code.
+pub fn print_list() {
+	// Safe because interruptions are disabled and the system is about to halt
+	let modules = unsafe { MODULES.get_payload() };
+	crate::print!("Modules linked in:");
+	for (name, module) in modules.iter() {
+		crate::print!(" {name}");
+		if module.taints != 0 {
+			crate::print!("({})", panic::DisplayTaint(module.taints));
+		}
+	}
+	crate::println!();
 }
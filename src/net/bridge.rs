@@ -0,0 +1,425 @@
+//! A software bridge forwards Ethernet frames between several network interfaces, its ports, the
+//! same way a switch does (IEEE 802.1D).
+//!
+//! The bridge learns on which port each MAC address is located from the source address of
+//! received frames. Frames to a known address are forwarded to its port only, others are flooded
+//! to every port except the one they came from.
+//!
+//! The bridge is also a network interface itself, through which the local host sends and
+//! receives frames on the bridged network.
+
+use super::buff::BuffList;
+use super::BindAddress;
+use super::Interface;
+use super::InterfaceStats;
+use super::MAC;
+use crate::errno::AllocResult;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::time::clock;
+use crate::time::clock::CLOCK_MONOTONIC;
+use crate::time::unit::TimestampScale;
+use crate::util::container::hashmap::HashMap;
+use crate::util::container::string::String;
+use crate::util::container::vec::Vec;
+use crate::util::lock::Mutex;
+use crate::util::ptr::arc::Arc;
+use crate::util::TryClone;
+
+/// The length of an Ethernet header.
+pub const ETH_HLEN: usize = 14;
+
+/// The time after which an entry of the forwarding database expires, in seconds.
+const FDB_AGEING_TIME: u64 = 300;
+/// The maximum number of entries in the forwarding database of a bridge.
+const FDB_MAX: usize = 1024;
+/// The maximum number of frames waiting to be received by the local host.
+const INCOMING_MAX: usize = 256;
+
+/// An entry of the forwarding database.
+struct FdbEntry {
+	/// The name of the port on which the address is located.
+	port: String,
+	/// The timestamp, in seconds, at which a frame from the address was last seen.
+	last_seen: u64,
+}
+
+/// The outcome of forwarding a frame.
+#[derive(Default)]
+pub struct Forward {
+	/// Tells whether the frame must be received by the local host.
+	pub local: bool,
+	/// The ports the frame must be transmitted on.
+	pub ports: Vec<String>,
+}
+
+/// A software bridge.
+pub struct Bridge {
+	/// The name of the bridge's interface.
+	name: String,
+	/// The MAC address of the bridge's interface.
+	mac: MAC,
+
+	/// The names of the bridged interfaces.
+	ports: Vec<String>,
+	/// The forwarding database, associating MAC addresses with the port they are located on.
+	fdb: HashMap<MAC, FdbEntry>,
+
+	/// Frames received by the local host, waiting to be read by the network stack.
+	incoming: Vec<Vec<u8>>,
+	/// The statistics of the bridge's interface.
+	stats: InterfaceStats,
+}
+
+impl Bridge {
+	/// Creates a new bridge with the given name and MAC address, without any port.
+	pub fn new(name: String, mac: MAC) -> Self {
+		Self {
+			name,
+			mac,
+
+			ports: Vec::new(),
+			fdb: HashMap::new(),
+
+			incoming: Vec::new(),
+			stats: InterfaceStats::default(),
+		}
+	}
+
+	/// Returns the names of the bridged interfaces.
+	pub fn get_ports(&self) -> &[String] {
+		&self.ports
+	}
+
+	/// Learns that the address `mac` is located on the port `port`.
+	fn learn(&mut self, mac: MAC, port: &[u8], now: u64) -> AllocResult<()> {
+		if let Some(entry) = self.fdb.get_mut(&mac) {
+			if entry.port.as_bytes() != port {
+				entry.port = String::try_from(port)?;
+			}
+			entry.last_seen = now;
+			return Ok(());
+		}
+		if self.fdb.len() >= FDB_MAX {
+			self.fdb
+				.retain(|_, e| now.saturating_sub(e.last_seen) < FDB_AGEING_TIME);
+			if self.fdb.len() >= FDB_MAX {
+				return Ok(());
+			}
+		}
+		self.fdb.insert(
+			mac,
+			FdbEntry {
+				port: String::try_from(port)?,
+				last_seen: now,
+			},
+		)?;
+		Ok(())
+	}
+
+	/// Returns the list of ports to flood a frame to, excluding `in_port`.
+	fn flood(&self, in_port: Option<&[u8]>) -> AllocResult<Vec<String>> {
+		let mut ports = Vec::new();
+		for p in self.ports.iter() {
+			if Some(p.as_bytes()) != in_port {
+				ports.push(p.try_clone()?)?;
+			}
+		}
+		Ok(ports)
+	}
+
+	/// Decides where the frame `frame` must be forwarded to.
+	///
+	/// Arguments:
+	/// - `in_port` is the port on which the frame has been received. If `None`, the frame is
+	/// transmitted by the local host.
+	/// - `now` is the current timestamp, in seconds.
+	pub fn forward(
+		&mut self,
+		in_port: Option<&[u8]>,
+		frame: &[u8],
+		now: u64,
+	) -> AllocResult<Forward> {
+		if frame.len() < ETH_HLEN {
+			return Ok(Forward::default());
+		}
+		let dst: MAC = frame[0..6].try_into().unwrap();
+		let src: MAC = frame[6..12].try_into().unwrap();
+
+		// Learn the location of the sender, unless the address is a group address
+		if let Some(in_port) = in_port {
+			if src[0] & 0x01 == 0 {
+				self.learn(src, in_port, now)?;
+			}
+		}
+
+		if in_port.is_some() && dst == self.mac {
+			return Ok(Forward {
+				local: true,
+				ports: Vec::new(),
+			});
+		}
+		// Broadcast and multicast
+		if dst[0] & 0x01 != 0 {
+			return Ok(Forward {
+				local: in_port.is_some(),
+				ports: self.flood(in_port)?,
+			});
+		}
+
+		let known = self
+			.fdb
+			.get(&dst)
+			.filter(|e| now.saturating_sub(e.last_seen) < FDB_AGEING_TIME);
+		let ports = match known {
+			// The destination is on the same segment as the sender
+			Some(e) if Some(e.port.as_bytes()) == in_port => Vec::new(),
+			Some(e) => crate::vec![e.port.try_clone()?]?,
+			None => self.flood(in_port)?,
+		};
+		Ok(Forward {
+			local: false,
+			ports,
+		})
+	}
+
+	/// Queues the frame `frame` to be received by the local host.
+	fn receive(&mut self, frame: &[u8]) -> AllocResult<()> {
+		if self.incoming.len() < INCOMING_MAX {
+			self.incoming.push(Vec::from_slice(frame)?)?;
+			self.stats.on_receive(frame.len());
+		} else {
+			self.stats.rx_dropped += 1;
+		}
+		Ok(())
+	}
+}
+
+impl Interface for Bridge {
+	fn get_name(&self) -> &[u8] {
+		&self.name
+	}
+
+	fn is_up(&self) -> bool {
+		true
+	}
+
+	fn get_mac(&self) -> &MAC {
+		&self.mac
+	}
+
+	fn get_addresses(&self) -> &[BindAddress] {
+		// TODO allow binding addresses
+		&[]
+	}
+
+	fn get_stats(&self) -> InterfaceStats {
+		self.stats
+	}
+
+	fn get_stats_mut(&mut self) -> Option<&mut InterfaceStats> {
+		Some(&mut self.stats)
+	}
+
+	fn read(&mut self, buff: &mut [u8]) -> Result<u64, Errno> {
+		if self.incoming.is_empty() {
+			return Ok(0);
+		}
+		let frame = self.incoming.remove(0);
+		let len = frame.len().min(buff.len());
+		buff[..len].copy_from_slice(&frame[..len]);
+		Ok(len as _)
+	}
+
+	fn write(&mut self, buff: &BuffList<'_>) -> Result<u64, Errno> {
+		let frame = buff.collect()?;
+		let fwd = self.forward(None, &frame, now())?;
+		transmit(&fwd.ports, &frame);
+		Ok(frame.len() as _)
+	}
+}
+
+/// The list of bridges, by name.
+static BRIDGES: Mutex<HashMap<String, Arc<Mutex<Bridge>>>> = Mutex::new(HashMap::new());
+/// The name of the bridge each bridged interface belongs to, by interface name.
+static PORTS: Mutex<HashMap<String, String>> = Mutex::new(HashMap::new());
+
+/// Returns the current timestamp, in seconds.
+fn now() -> u64 {
+	clock::current_time(CLOCK_MONOTONIC, TimestampScale::Second).unwrap_or(0)
+}
+
+/// Transmits the frame `frame` on each interface in `ports`.
+///
+/// Errors are ignored since a bridge does not guarantee delivery.
+fn transmit(ports: &[String], frame: &[u8]) {
+	for port in ports {
+		if let Some(iface) = super::get_iface(port) {
+			let _ = super::transmit(&mut *iface.lock(), &BuffList::from(frame));
+		}
+	}
+}
+
+/// Creates a bridge with the given name.
+///
+/// If an interface with the same name already exists, the function returns an error.
+pub fn create(name: &[u8]) -> EResult<()> {
+	if name.is_empty() || name.len() >= super::IFNAMSIZ {
+		return Err(errno!(EINVAL));
+	}
+	let name = String::try_from(name)?;
+	let bridge = Arc::new(Mutex::new(Bridge::new(
+		name.try_clone()?,
+		super::random_mac(),
+	)))?;
+
+	let mut bridges = BRIDGES.lock();
+	let key = name.try_clone()?;
+	super::register_iface(name.try_clone()?, bridge.clone())?;
+	if let Err(e) = bridges.insert(key, bridge) {
+		super::unregister_iface(&name);
+		return Err(e.into());
+	}
+	Ok(())
+}
+
+/// Deletes the bridge with the given name, releasing its ports.
+///
+/// If the bridge does not exist, the function returns an error.
+pub fn delete(name: &[u8]) -> EResult<()> {
+	let bridge = BRIDGES.lock().remove(name).ok_or_else(|| errno!(ENXIO))?;
+	PORTS.lock().retain(|_, b| b.as_bytes() != name);
+	bridge.lock().ports.clear();
+	super::unregister_iface(name);
+	Ok(())
+}
+
+/// Adds the interface `port` to the bridge `name`.
+///
+/// An interface can belong to only one bridge at a time and a bridge cannot be a port.
+pub fn add_port(name: &[u8], port: &[u8]) -> EResult<()> {
+	let bridge = BRIDGES
+		.lock()
+		.get(name)
+		.cloned()
+		.ok_or_else(|| errno!(ENXIO))?;
+	if super::get_iface(port).is_none() {
+		return Err(errno!(ENODEV));
+	}
+	if BRIDGES.lock().contains_key(port) {
+		return Err(errno!(ELOOP));
+	}
+
+	let mut ports = PORTS.lock();
+	if ports.contains_key(port) {
+		return Err(errno!(EBUSY));
+	}
+	let mut bridge = bridge.lock();
+	bridge.ports.push(String::try_from(port)?)?;
+	if let Err(e) = ports.insert(String::try_from(port)?, String::try_from(name)?) {
+		bridge.ports.pop();
+		return Err(e.into());
+	}
+	Ok(())
+}
+
+/// Removes the interface `port` from the bridge `name`.
+pub fn remove_port(name: &[u8], port: &[u8]) -> EResult<()> {
+	let bridge = BRIDGES
+		.lock()
+		.get(name)
+		.cloned()
+		.ok_or_else(|| errno!(ENXIO))?;
+
+	let mut ports = PORTS.lock();
+	match ports.get(port) {
+		Some(b) if b.as_bytes() == name => {}
+		_ => return Err(errno!(EINVAL)),
+	}
+	ports.remove(port);
+	let mut bridge = bridge.lock();
+	bridge.ports.retain(|p| p.as_bytes() != port);
+	bridge.fdb.retain(|_, e| e.port.as_bytes() != port);
+	Ok(())
+}
+
+/// Handles the frame `frame` received on the interface `port`.
+///
+/// If the interface belongs to a bridge, the frame is forwarded and the function returns `true`.
+/// Else, the frame must be handled by the interface itself and the function returns `false`.
+pub fn input(port: &[u8], frame: &[u8]) -> AllocResult<bool> {
+	let bridge = {
+		let ports = PORTS.lock();
+		let Some(name) = ports.get(port) else {
+			return Ok(false);
+		};
+		let Some(bridge) = BRIDGES.lock().get(name.as_bytes()).cloned() else {
+			return Ok(false);
+		};
+		bridge
+	};
+
+	let fwd = {
+		let mut bridge = bridge.lock();
+		let fwd = bridge.forward(Some(port), frame, now())?;
+		if fwd.local {
+			bridge.receive(frame)?;
+		}
+		fwd
+	};
+	// The bridge is not locked while transmitting, since a port may forward frames back to it
+	transmit(&fwd.ports, frame);
+	Ok(true)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	/// Builds a frame from `src` to `dst`.
+	fn frame(dst: MAC, src: MAC) -> [u8; ETH_HLEN] {
+		let mut f = [0; ETH_HLEN];
+		f[0..6].copy_from_slice(&dst);
+		f[6..12].copy_from_slice(&src);
+		f
+	}
+
+	#[test_case]
+	fn bridge_forward() {
+		let a = [0x02, 0, 0, 0, 0, 1];
+		let b = [0x02, 0, 0, 0, 0, 2];
+		let mut bridge = Bridge::new(String::try_from(b"br0").unwrap(), [0x02, 0, 0, 0, 0, 0xff]);
+		bridge
+			.ports
+			.push(String::try_from(b"tap0").unwrap())
+			.unwrap();
+		bridge
+			.ports
+			.push(String::try_from(b"tap1").unwrap())
+			.unwrap();
+		bridge
+			.ports
+			.push(String::try_from(b"tap2").unwrap())
+			.unwrap();
+
+		// Unknown destination: flooded
+		let fwd = bridge.forward(Some(b"tap0"), &frame(b, a), 0).unwrap();
+		assert!(!fwd.local);
+		assert_eq!(fwd.ports.len(), 2);
+		// The address of `a` has been learned
+		let fwd = bridge.forward(Some(b"tap1"), &frame(a, b), 0).unwrap();
+		assert_eq!(fwd.ports.len(), 1);
+		assert_eq!(fwd.ports[0].as_bytes(), b"tap0");
+		// Broadcast
+		let fwd = bridge
+			.forward(Some(b"tap2"), &frame([0xff; 6], b), 0)
+			.unwrap();
+		assert!(fwd.local);
+		assert_eq!(fwd.ports.len(), 2);
+		// Expired entry: flooded again
+		let fwd = bridge
+			.forward(Some(b"tap2"), &frame(a, b), FDB_AGEING_TIME)
+			.unwrap();
+		assert_eq!(fwd.ports.len(), 2);
+	}
+}
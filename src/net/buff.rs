@@ -1,5 +1,8 @@
 //! TODO doc
 
+use crate::errno::AllocResult;
+use crate::util::container::vec::Vec;
+use core::iter;
 use core::ptr::NonNull;
 
 /// A linked-list of buffers representing a packet being built.
@@ -44,4 +47,24 @@ impl<'b> BuffList<'b> {
 
 		front
 	}
+
+	/// Returns an iterator over the buffers of the list, in order.
+	pub fn iter(&self) -> impl Iterator<Item = &[u8]> {
+		let mut cur = Some(self);
+		iter::from_fn(move || {
+			let b = cur?;
+			// Safe because the following buffers live at least as long as the current one
+			cur = b.next.map(|n| unsafe { n.as_ref() });
+			Some(b.b)
+		})
+	}
+
+	/// Copies the content of the whole list into a new contiguous buffer.
+	pub fn collect(&self) -> AllocResult<Vec<u8>> {
+		let mut buf = Vec::with_capacity(self.len())?;
+		for b in self.iter() {
+			buf.extend_from_slice(b)?;
+		}
+		Ok(buf)
+	}
 }
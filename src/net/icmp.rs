@@ -4,6 +4,12 @@
 //! - With IPv4: RFC 792
 //! - With IPv6 (ICMPv6): RFC 4443
 
+use super::ip;
+use crate::crypto::checksum;
+use crate::errno::Errno;
+use crate::file::buffer::socket;
+use crate::file::buffer::socket::ConnectionKey;
+
 /// An enumeration of ICMP packet types.
 pub enum ICMPType {
 	/// Used by ping to reply to an echo request.
@@ -73,3 +79,69 @@ impl ICMPType {
 		}
 	}
 }
+
+/// Destination Unreachable code: the network is unreachable.
+const CODE_NET_UNREACHABLE: u8 = 0;
+/// Destination Unreachable code: the host is unreachable.
+const CODE_HOST_UNREACHABLE: u8 = 1;
+/// Destination Unreachable code: the protocol is not supported by the destination.
+const CODE_PROTO_UNREACHABLE: u8 = 2;
+/// Destination Unreachable code: no socket listens on the destination port.
+const CODE_PORT_UNREACHABLE: u8 = 3;
+/// Destination Unreachable code: fragmentation is needed but the Don't Fragment flag is set.
+const CODE_FRAG_NEEDED: u8 = 4;
+
+/// Returns the error corresponding to the Destination Unreachable code `code`.
+fn unreachable_errno(code: u8) -> Option<Errno> {
+	match code {
+		CODE_NET_UNREACHABLE => Some(errno!(ENETUNREACH)),
+		CODE_HOST_UNREACHABLE => Some(errno!(EHOSTUNREACH)),
+		CODE_PROTO_UNREACHABLE => Some(errno!(ENOPROTOOPT)),
+		CODE_PORT_UNREACHABLE => Some(errno!(ECONNREFUSED)),
+		_ => None,
+	}
+}
+
+/// Delivers the error `errno` to the socket which sent the packet `orig`, as embedded in an ICMP
+/// error message.
+fn deliver_error(orig: &[u8], errno: Errno) {
+	let Some((protocol, remote_addr, payload)) = ip::parse_embedded(orig) else {
+		return;
+	};
+	// The message contains at least the first 8 bytes of the transport header, which start with
+	// the source and destination ports for both TCP and UDP
+	if payload.len() < 4 {
+		return;
+	}
+	let key = ConnectionKey {
+		protocol,
+		remote_addr,
+		remote_port: u16::from_be_bytes([payload[2], payload[3]]),
+		local_port: u16::from_be_bytes([payload[0], payload[1]]),
+	};
+	socket::deliver_error(key, errno);
+}
+
+/// Handles a received ICMP packet `packet`, starting with the ICMP header.
+pub fn receive(packet: &[u8]) {
+	// The header is made of the type, the code, the checksum and 4 bytes depending on the type
+	if packet.len() < 8 || checksum::compute_rfc1071(packet) != 0 {
+		return;
+	}
+	let code = packet[1];
+
+	match ICMPType::from_type(packet[0]) {
+		Some(ICMPType::DestinationUnreachable) if code == CODE_FRAG_NEEDED => {
+			let next_hop_mtu = u16::from_be_bytes([packet[6], packet[7]]);
+			ip::handle_frag_needed(next_hop_mtu, &packet[8..]);
+		}
+		Some(ICMPType::DestinationUnreachable) => {
+			if let Some(errno) = unreachable_errno(code) {
+				deliver_error(&packet[8..], errno);
+			}
+		}
+
+		// TODO handle other types
+		_ => {}
+	}
+}
@@ -4,7 +4,12 @@ use super::buff::BuffList;
 use super::osi::Layer;
 use crate::crypto::checksum;
 use crate::errno::Errno;
+use crate::time::clock;
+use crate::time::clock::CLOCK_MONOTONIC;
+use crate::time::unit::TimestampScale;
 use crate::util::boxed::Box;
+use crate::util::container::hashmap::HashMap;
+use crate::util::lock::Mutex;
 use core::mem::size_of;
 use core::slice;
 
@@ -16,6 +21,21 @@ const FLAG_DF: u8 = 0b010;
 /// IPv4 flag: More fragments are to come after this one
 const FLAG_MF: u8 = 0b100;
 
+/// The MTU assumed for destinations for which no smaller path MTU has been discovered.
+pub const DEFAULT_MTU: u16 = 1500;
+/// The minimum path MTU. Smaller values reported by ICMP messages are ignored, to prevent an
+/// attacker from forcing tiny packets.
+pub const MIN_MTU: u16 = 552;
+/// The duration in seconds after which a discovered path MTU expires, so that an increase of the
+/// path MTU can be detected (RFC 1191).
+const PMTU_EXPIRY: u64 = 600;
+/// The maximum number of destinations in the path MTU cache.
+const PMTU_CACHE_MAX: usize = 256;
+
+/// Common MTU values, used to estimate the next-hop MTU when a router does not report it (RFC
+/// 1191, section 7).
+const MTU_PLATEAUS: [u16; 10] = [65535, 32000, 17914, 8166, 4352, 2002, 1492, 1006, 508, 296];
+
 /// Protocol: TCP
 pub const PROTO_TCP: u8 = 0x06;
 /// Protocol: UDP
@@ -70,6 +90,9 @@ impl IPv4Header {
 	}
 }
 
+/// The size of the IPv4 header without options.
+pub const IPV4_HEADER_SIZE: usize = size_of::<IPv4Header>();
+
 /// The IPv6 header (RFC 8200).
 #[repr(C, packed)]
 struct IPv6Header {
@@ -89,6 +112,108 @@ struct IPv6Header {
 	dst_addr: [u8; 16],
 }
 
+/// An entry of the path MTU cache.
+struct PathMtu {
+	/// The path MTU.
+	mtu: u16,
+	/// The timestamp in seconds at which the entry expires.
+	expiry: u64,
+}
+
+/// The path MTU cache, by destination address.
+static PMTU_CACHE: Mutex<HashMap<[u8; 4], PathMtu>> = Mutex::new(HashMap::new());
+
+/// Returns the current timestamp in seconds.
+fn now() -> u64 {
+	clock::current_time(CLOCK_MONOTONIC, TimestampScale::Second).unwrap_or(0)
+}
+
+/// Returns the path MTU to the destination `dst`.
+pub fn path_mtu(dst: &[u8; 4]) -> u16 {
+	let cache = PMTU_CACHE.lock();
+	match cache.get(dst) {
+		Some(e) if e.expiry > now() => e.mtu,
+		_ => DEFAULT_MTU,
+	}
+}
+
+/// Lowers the path MTU to the destination `dst` to `mtu`, as reported by an ICMP Fragmentation
+/// Needed message.
+///
+/// The path MTU cannot be increased this way, and is never lowered below [`MIN_MTU`].
+pub fn update_path_mtu(dst: [u8; 4], mtu: u16) {
+	let mtu = mtu.max(MIN_MTU);
+	if mtu >= path_mtu(&dst) {
+		return;
+	}
+
+	let now = now();
+	let mut cache = PMTU_CACHE.lock();
+	if cache.len() >= PMTU_CACHE_MAX {
+		cache.retain(|_, e| e.expiry > now);
+	}
+	let entry = PathMtu {
+		mtu,
+		expiry: now + PMTU_EXPIRY,
+	};
+	if cache.len() < PMTU_CACHE_MAX || cache.contains_key(&dst) {
+		// On allocation failure, the path MTU is discovered again later
+		let _ = cache.insert(dst, entry);
+	}
+}
+
+/// Estimates the next-hop MTU when a router reported none, given the `total_length` of the
+/// packet that could not be forwarded.
+pub fn next_plateau(total_length: u16) -> u16 {
+	MTU_PLATEAUS
+		.iter()
+		.copied()
+		.find(|p| *p < total_length)
+		.unwrap_or(MIN_MTU)
+}
+
+/// Handles an ICMP Fragmentation Needed message (Destination Unreachable, code 4).
+///
+/// Arguments:
+/// - `next_hop_mtu` is the MTU reported by the router, or zero if not reported.
+/// - `orig` is the beginning of the packet that could not be forwarded, starting with its IPv4
+/// header.
+pub fn handle_frag_needed(next_hop_mtu: u16, orig: &[u8]) {
+	if orig.len() < size_of::<IPv4Header>() {
+		return;
+	}
+	let hdr = unsafe { &*(orig.as_ptr() as *const IPv4Header) };
+	let dst = hdr.dst_addr;
+
+	let mtu = if next_hop_mtu != 0 {
+		next_hop_mtu
+	} else {
+		next_plateau(u16::from_be(hdr.total_length))
+	};
+	update_path_mtu(dst, mtu);
+}
+
+/// Parses the beginning of a packet `orig` sent by the local host, as embedded in an ICMP error
+/// message.
+///
+/// The function returns the protocol, the destination address and the beginning of the payload.
+/// If the packet is invalid, the function returns `None`.
+pub fn parse_embedded(orig: &[u8]) -> Option<(u8, [u8; 4], &[u8])> {
+	if orig.len() < size_of::<IPv4Header>() {
+		return None;
+	}
+	let hdr = unsafe { &*(orig.as_ptr() as *const IPv4Header) };
+	if hdr.version_ihl >> 4 != 4 {
+		return None;
+	}
+	let hdr_len = (hdr.version_ihl & 0xf) as usize * 4;
+	if hdr_len < size_of::<IPv4Header>() || hdr_len > orig.len() {
+		return None;
+	}
+
+	Some((hdr.protocol, hdr.dst_addr, &orig[hdr_len..]))
+}
+
 /// The network layer for the IPv4 protocol.
 pub struct IPv4Layer {
 	/// The protocol ID.
@@ -104,6 +229,13 @@ impl Layer for IPv4Layer {
 		F: Fn(BuffList<'c>) -> Result<(), Errno>,
 	{
 		let hdr_len = size_of::<IPv4Header>() as u16; // TODO add options support?
+		let total_length = hdr_len as usize + buff.len();
+
+		// Fragmentation is forbidden so that the path MTU can be discovered
+		let mtu = path_mtu(&self.dst_addr);
+		if total_length > mtu as usize {
+			return Err(errno!(EMSGSIZE));
+		}
 
 		let dscp = 0; // TODO
 		let ecn = 0; // TODO
@@ -112,10 +244,10 @@ impl Layer for IPv4Layer {
 		let mut hdr = IPv4Header {
 			version_ihl: 4 | (((hdr_len / 4) as u8) << 4),
 			type_of_service: (dscp << 2) | ecn,
-			total_length: hdr_len + buff.len() as u16,
+			total_length: (total_length as u16).to_be(),
 
-			identification: 0,        // TODO
-			flags_fragment_offset: 0, // TODO
+			identification: 0, // TODO
+			flags_fragment_offset: ((FLAG_DF as u16) << 13).to_be(),
 
 			// TODO allow setting a different value
 			ttl: DEFAULT_TTL,
@@ -149,3 +281,46 @@ pub fn inet6_build(_sockaddr: &[u8]) -> Result<Box<dyn Layer>, Errno> {
 	// TODO
 	todo!()
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test_case]
+	fn pmtu_update() {
+		let dst = [192, 0, 2, 1];
+		assert_eq!(path_mtu(&dst), DEFAULT_MTU);
+		update_path_mtu(dst, 1400);
+		assert_eq!(path_mtu(&dst), 1400);
+		// The path MTU cannot increase
+		update_path_mtu(dst, 1480);
+		assert_eq!(path_mtu(&dst), 1400);
+		// Nor go below the minimum
+		update_path_mtu(dst, 68);
+		assert_eq!(path_mtu(&dst), MIN_MTU);
+	}
+
+	#[test_case]
+	fn pmtu_plateau() {
+		assert_eq!(next_plateau(1500), 1492);
+		assert_eq!(next_plateau(1492), 1006);
+		assert_eq!(next_plateau(300), 296);
+		assert_eq!(next_plateau(296), MIN_MTU);
+	}
+
+	#[test_case]
+	fn embedded_packet() {
+		let mut orig = [0u8; 28];
+		orig[0] = 0x45;
+		orig[9] = PROTO_UDP;
+		orig[16..20].copy_from_slice(&[192, 0, 2, 53]);
+		orig[20..24].copy_from_slice(&[0x9c, 0x40, 0, 53]);
+		let (protocol, dst, payload) = parse_embedded(&orig).unwrap();
+		assert_eq!(protocol, PROTO_UDP);
+		assert_eq!(dst, [192, 0, 2, 53]);
+		assert_eq!(&payload[..4], &[0x9c, 0x40, 0, 53]);
+		// Header length larger than the packet
+		orig[0] = 0x4f;
+		assert!(parse_embedded(&orig).is_none());
+	}
+}
@@ -1,5 +1,6 @@
 //! Network stack implementation.
 
+pub mod bridge;
 pub mod buff;
 pub mod icmp;
 pub mod ip;
@@ -8,8 +9,14 @@ pub mod netlink;
 pub mod osi;
 pub mod sockaddr;
 pub mod tcp;
+pub mod tun;
+pub mod unix;
 
+use crate::crypto::rand::ENTROPY_POOL;
+use crate::errno;
+use crate::errno::AllocResult;
 use crate::errno::Errno;
+use crate::file::open_file;
 use crate::file::perm::AccessProfile;
 use crate::file::perm::ROOT_GID;
 use crate::file::perm::ROOT_UID;
@@ -20,13 +27,80 @@ use crate::util::container::string::String;
 use crate::util::container::vec::Vec;
 use crate::util::lock::Mutex;
 use crate::util::ptr::arc::Arc;
+use crate::util::TryClone;
 use buff::BuffList;
 use core::cmp::Ordering;
+use core::ffi::c_int;
+use core::ffi::c_short;
 use core::mem::size_of;
+use core::sync::atomic::AtomicU32;
+use core::sync::atomic::Ordering as AtomicOrdering;
 
 /// Type representing a Media Access Control (MAC) address.
 pub type MAC = [u8; 6];
 
+/// The maximum length of an interface name, including the terminating null byte.
+pub const IFNAMSIZ: usize = 16;
+
+/// Structure used by ioctl requests to configure network interfaces.
+#[repr(C)]
+#[derive(Clone)]
+pub struct IfReq {
+	/// The name of the interface, terminated by a null byte.
+	pub ifr_name: [u8; IFNAMSIZ],
+	/// The request's data, depending on the request.
+	pub ifr_data: [u8; 16],
+}
+
+impl IfReq {
+	/// Returns the name of the interface.
+	pub fn name(&self) -> &[u8] {
+		let len = self
+			.ifr_name
+			.iter()
+			.position(|b| *b == 0)
+			.unwrap_or(IFNAMSIZ);
+		&self.ifr_name[..len]
+	}
+
+	/// Sets the name of the interface.
+	///
+	/// If the name is too long, the function returns an error.
+	pub fn set_name(&mut self, name: &[u8]) -> Result<(), Errno> {
+		if name.len() >= IFNAMSIZ {
+			return Err(errno!(EINVAL));
+		}
+		self.ifr_name = [0; IFNAMSIZ];
+		self.ifr_name[..name.len()].copy_from_slice(name);
+		Ok(())
+	}
+
+	/// Returns the interface flags.
+	pub fn flags(&self) -> c_short {
+		c_short::from_ne_bytes([self.ifr_data[0], self.ifr_data[1]])
+	}
+
+	/// Returns the interface index.
+	pub fn ifindex(&self) -> c_int {
+		c_int::from_ne_bytes([
+			self.ifr_data[0],
+			self.ifr_data[1],
+			self.ifr_data[2],
+			self.ifr_data[3],
+		])
+	}
+}
+
+/// Returns a random, locally administered unicast MAC address.
+pub fn random_mac() -> MAC {
+	let mut mac = [0; 6];
+	if let Some(pool) = &mut *ENTROPY_POOL.lock() {
+		pool.read(&mut mac, true);
+	}
+	mac[0] = (mac[0] & !0x01) | 0x02;
+	mac
+}
+
 // TODO allow implementation of custom protocols
 
 /// An enumeration of network address types.
@@ -75,6 +149,41 @@ impl BindAddress {
 	}
 }
 
+/// Statistics of a network interface.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct InterfaceStats {
+	/// The number of bytes received.
+	pub rx_bytes: u64,
+	/// The number of packets received.
+	pub rx_packets: u64,
+	/// The number of received packets that have been dropped.
+	pub rx_dropped: u64,
+	/// The number of malformed packets received.
+	pub rx_errors: u64,
+	/// The number of bytes transmitted.
+	pub tx_bytes: u64,
+	/// The number of packets transmitted.
+	pub tx_packets: u64,
+	/// The number of packets that have been dropped instead of being transmitted.
+	pub tx_dropped: u64,
+	/// The number of packets that failed to be transmitted.
+	pub tx_errors: u64,
+}
+
+impl InterfaceStats {
+	/// Accounts for the reception of a packet of `len` bytes.
+	pub fn on_receive(&mut self, len: usize) {
+		self.rx_packets += 1;
+		self.rx_bytes += len as u64;
+	}
+
+	/// Accounts for the transmission of a packet of `len` bytes.
+	pub fn on_transmit(&mut self, len: usize) {
+		self.tx_packets += 1;
+		self.tx_bytes += len as u64;
+	}
+}
+
 /// Trait representing a network interface.
 pub trait Interface {
 	/// Returns the name of the interface.
@@ -89,6 +198,18 @@ pub trait Interface {
 	/// Returns the list of addresses bound to the interface.
 	fn get_addresses(&self) -> &[BindAddress];
 
+	/// Returns the statistics of the interface.
+	fn get_stats(&self) -> InterfaceStats {
+		InterfaceStats::default()
+	}
+
+	/// Returns a mutable reference to the statistics of the interface.
+	///
+	/// If the interface does not keep statistics, the function returns `None`.
+	fn get_stats_mut(&mut self) -> Option<&mut InterfaceStats> {
+		None
+	}
+
 	/// Reads data from the network interface and writes it into `buff`.
 	///
 	/// The function returns the number of bytes read.
@@ -115,6 +236,26 @@ pub struct Route {
 }
 
 impl Route {
+	/// Returns the destination of the route. If `None`, this is the default route.
+	pub fn get_destination(&self) -> Option<&BindAddress> {
+		self.dst.as_ref()
+	}
+
+	/// Returns the name of the network interface.
+	pub fn get_iface(&self) -> &[u8] {
+		&self.iface
+	}
+
+	/// Returns the gateway's address.
+	pub fn get_gateway(&self) -> &Address {
+		&self.gateway
+	}
+
+	/// Returns the route's metric.
+	pub fn get_metric(&self) -> u32 {
+		self.metric
+	}
+
 	/// Tells whether the route matches the given address.
 	pub fn is_matching(&self, addr: &Address) -> bool {
 		// Check gateway
@@ -170,6 +311,10 @@ impl Route {
 /// The list of network interfaces.
 pub static INTERFACES: Mutex<HashMap<String, Arc<Mutex<dyn Interface>>>> =
 	Mutex::new(HashMap::new());
+/// The names of network interfaces, by index.
+static IFACE_INDEXES: Mutex<HashMap<u32, String>> = Mutex::new(HashMap::new());
+/// The index to be assigned to the next registered interface.
+static NEXT_IFACE_INDEX: AtomicU32 = AtomicU32::new(1);
 /// The routing table.
 pub static ROUTING_TABLE: Mutex<Vec<Route>> = Mutex::new(Vec::new());
 
@@ -178,19 +323,28 @@ pub static ROUTING_TABLE: Mutex<Vec<Route>> = Mutex::new(Vec::new());
 /// Arguments:
 /// - `name` is the name of the interface.
 /// - `iface` is the interface to register.
-pub fn register_iface<I: 'static + Interface>(name: String, iface: I) -> Result<(), Errno> {
+///
+/// If an interface with the same name is already registered, the function returns an error.
+///
+/// On success, the function returns the index of the interface.
+pub fn register_iface(name: String, iface: Arc<Mutex<dyn Interface>>) -> Result<u32, Errno> {
 	let mut interfaces = INTERFACES.lock();
+	if interfaces.contains_key(name.as_bytes()) {
+		return Err(errno!(EEXIST));
+	}
 
-	let i = Arc::new(Mutex::new(iface))?;
-	interfaces.insert(name, i)?;
+	let index = NEXT_IFACE_INDEX.fetch_add(1, AtomicOrdering::Relaxed);
+	IFACE_INDEXES.lock().insert(index, name.try_clone()?)?;
+	interfaces.insert(name, iface)?;
 
-	Ok(())
+	Ok(index)
 }
 
 /// Unregisters the network interface with the given name.
 pub fn unregister_iface(name: &[u8]) {
 	let mut interfaces = INTERFACES.lock();
 	interfaces.remove(name);
+	IFACE_INDEXES.lock().retain(|_, n| n.as_bytes() != name);
 }
 
 /// Returns the network interface with the given name.
@@ -200,6 +354,17 @@ pub fn get_iface(name: &[u8]) -> Option<Arc<Mutex<dyn Interface>>> {
 	INTERFACES.lock().get(name).cloned()
 }
 
+/// Returns the name of the network interface with the given index.
+///
+/// If the interface doesn't exist, the function returns `None`.
+pub fn get_iface_name(index: u32) -> AllocResult<Option<String>> {
+	IFACE_INDEXES
+		.lock()
+		.get(&index)
+		.map(String::try_clone)
+		.transpose()
+}
+
 /// Returns the network interface to be used to transmit a packet to the given destination address.
 pub fn get_iface_for(addr: Address) -> Option<Arc<Mutex<dyn Interface>>> {
 	let routing_table = ROUTING_TABLE.lock();
@@ -211,6 +376,22 @@ pub fn get_iface_for(addr: Address) -> Option<Arc<Mutex<dyn Interface>>> {
 	get_iface(&route.iface)
 }
 
+/// Transmits `buff` on the interface `iface`, updating the interface's statistics.
+///
+/// A packet the interface has no room for is counted as dropped. Other failures are counted as
+/// errors.
+pub fn transmit(iface: &mut dyn Interface, buff: &BuffList<'_>) -> Result<u64, Errno> {
+	let res = iface.write(buff);
+	if let Some(stats) = iface.get_stats_mut() {
+		match &res {
+			Ok(_) => stats.on_transmit(buff.len()),
+			Err(e) if e.as_int() == errno::ENOBUFS => stats.tx_dropped += 1,
+			Err(_) => stats.tx_errors += 1,
+		}
+	}
+	res
+}
+
 /// Enumeration of socket domains.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub enum SocketDomain {
@@ -275,6 +456,11 @@ impl AccessProfile {
 	}
 }
 
+/// Socket type flag: the socket's open file is non-blocking.
+pub const SOCK_NONBLOCK: i32 = open_file::O_NONBLOCK;
+/// Socket type flag: the socket's file descriptor is closed on `execve`.
+pub const SOCK_CLOEXEC: i32 = open_file::O_CLOEXEC;
+
 /// Enumeration of socket types.
 #[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
 pub enum SocketType {
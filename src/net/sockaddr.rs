@@ -3,6 +3,8 @@
 
 use super::Address;
 use core::ffi::c_short;
+use core::mem::size_of;
+use core::ptr;
 
 /// Structure providing connection informations for sockets with IPv4.
 #[repr(C)]
@@ -52,18 +54,37 @@ pub struct SockAddr {
 	pub addr: Address,
 }
 
+impl SockAddr {
+	/// Parses the sockaddr structure `buf`, as given by userspace.
+	///
+	/// If the structure is invalid or its family is not supported, the function returns `None`.
+	pub fn from_bytes(buf: &[u8]) -> Option<Self> {
+		if buf.len() < size_of::<c_short>() {
+			return None;
+		}
+		let family = c_short::from_ne_bytes([buf[0], buf[1]]);
+		match family {
+			// AF_INET
+			2 if buf.len() >= size_of::<SockAddrIn>() => {
+				let val = unsafe { ptr::read_unaligned(buf.as_ptr() as *const SockAddrIn) };
+				Some(val.into())
+			}
+			// AF_INET6
+			10 if buf.len() >= size_of::<SockAddrIn6>() => {
+				let val = unsafe { ptr::read_unaligned(buf.as_ptr() as *const SockAddrIn6) };
+				Some(val.into())
+			}
+			_ => None,
+		}
+	}
+}
+
 impl From<SockAddrIn> for SockAddr {
 	fn from(val: SockAddrIn) -> Self {
-		let addr: [u8; 4] = [
-			((val.sin_addr >> 24) & 0xff) as u8,
-			((val.sin_addr >> 16) & 0xff) as u8,
-			((val.sin_addr >> 8) & 0xff) as u8,
-			(val.sin_addr & 0xff) as u8,
-		];
-
+		// Both the address and the port are in network byte order
 		Self {
-			port: val.sin_port as _,
-			addr: Address::IPv4(addr),
+			port: u16::from_be(val.sin_port as _),
+			addr: Address::IPv4(val.sin_addr.to_ne_bytes()),
 		}
 	}
 }
@@ -73,7 +94,7 @@ impl From<SockAddrIn6> for SockAddr {
 		let addr = unsafe { val.sin6_addr.__s6_addr };
 
 		Self {
-			port: val.sin6_port as _,
+			port: u16::from_be(val.sin6_port as _),
 			addr: Address::IPv6(addr),
 		}
 	}
@@ -2,9 +2,80 @@
 //! two-way, connection-based byte streams.
 
 use super::buff::BuffList;
+use super::ip;
 use super::osi::Layer;
+use crate::crypto::chacha20;
+use crate::crypto::checksum;
+use crate::crypto::rand::ENTROPY_POOL;
 use crate::errno::Errno;
 use crate::file::buffer::socket::Socket;
+use crate::time::clock;
+use crate::time::clock::CLOCK_MONOTONIC;
+use crate::time::unit::TimestampScale;
+use crate::util::lock::Mutex;
+use core::cmp::min;
+use core::mem::size_of;
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering::Relaxed;
+
+/// The MSS assumed when the peer does not advertise one (RFC 9293).
+pub const DEFAULT_MSS: u16 = 536;
+/// The size of the IPv4 and TCP headers without options.
+const HEADERS_SIZE: u16 = (ip::IPV4_HEADER_SIZE + size_of::<TCPHdr>()) as u16;
+
+/// Tells whether SYN cookies are sent when the SYN queue of a listening socket overflows.
+/// Otherwise, SYN segments are dropped.
+pub static SYN_COOKIES: AtomicBool = AtomicBool::new(true);
+
+/// The period in seconds after which the counter embedded in SYN cookies is incremented.
+const SYN_COOKIE_PERIOD: u64 = 64;
+/// The number of periods during which a SYN cookie remains valid.
+const SYN_COOKIE_VALIDITY: u32 = 2;
+/// The Maximum Segment Sizes that can be encoded in a SYN cookie.
+const SYN_COOKIE_MSS: [u16; 8] = [216, 536, 1200, 1300, 1400, 1440, 1452, 1460];
+
+/// The secret key used to compute SYN cookies. Generated on first use.
+static SYN_COOKIE_SECRET: Mutex<Option<[u8; 32]>> = Mutex::new(None);
+
+/// The maximum number of SYN segments per second accepted by the whole system before considering
+/// it is under a SYN flood.
+const SYN_RATE_GLOBAL_MAX: u32 = 1024;
+/// The maximum number of SYN segments per second accepted by a listening socket before
+/// considering it is under a SYN flood.
+const SYN_RATE_SOCKET_MAX: u32 = 256;
+
+/// The number of SYN segments received by the whole system during the current second.
+static SYN_RATE: Mutex<RateCounter> = Mutex::new(RateCounter::new());
+
+/// Counts events happening during the current second.
+pub struct RateCounter {
+	/// The timestamp in seconds of the current second.
+	second: u64,
+	/// The number of events during the current second.
+	count: u32,
+}
+
+impl RateCounter {
+	/// Creates a new instance.
+	pub const fn new() -> Self {
+		Self {
+			second: 0,
+			count: 0,
+		}
+	}
+
+	/// Records an event happening at `now`, in seconds.
+	///
+	/// The function returns the number of events during the current second, including this one.
+	pub fn hit(&mut self, now: u64) -> u32 {
+		if now != self.second {
+			self.second = now;
+			self.count = 0;
+		}
+		self.count = self.count.saturating_add(1);
+		self.count
+	}
+}
 
 /// The TCP segment header.
 #[repr(C, packed)]
@@ -48,8 +119,198 @@ impl Layer for TCPLayer {
 	}
 }
 
+/// Returns the MSS to advertise to the peer `peer`, which is derived from the path MTU so that
+/// segments are never fragmented.
+pub fn local_mss(peer: &[u8]) -> u16 {
+	match <[u8; 4]>::try_from(peer) {
+		Ok(addr) => ip::path_mtu(&addr) - HEADERS_SIZE,
+		// TODO IPv6
+		Err(_) => DEFAULT_MSS,
+	}
+}
+
+/// Negotiates the MSS with the peer `peer` during the handshake.
+///
+/// `peer_mss` is the MSS advertised by the peer, if any. It is clamped to the MSS allowed by the
+/// path MTU, so that connections through tunnels do not stall.
+pub fn negotiate_mss(peer: &[u8], peer_mss: Option<u16>) -> u16 {
+	min(peer_mss.unwrap_or(DEFAULT_MSS), local_mss(peer))
+}
+
+/// Identifies a TCP connection.
+pub struct ConnectionId<'a> {
+	/// The source address.
+	pub src_addr: &'a [u8],
+	/// The source port.
+	pub src_port: u16,
+	/// The destination address.
+	pub dst_addr: &'a [u8],
+	/// The destination port.
+	pub dst_port: u16,
+}
+
+/// The action to take when receiving a SYN segment on a listening socket.
+#[derive(Debug, Eq, PartialEq)]
+pub enum SynAction {
+	/// The half-open connection is kept in the SYN queue and a SYN-ACK is sent.
+	Queue,
+	/// A SYN-ACK is sent with the given SYN cookie as sequence number, without keeping any
+	/// state. The connection is created if the peer acknowledges the cookie.
+	Cookie(u32),
+	/// The segment is dropped. The peer retransmits it later.
+	Drop,
+}
+
+/// Returns the value of the counter embedded in SYN cookies.
+fn syn_cookie_counter() -> u32 {
+	let now = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Second).unwrap_or(0);
+	(now / SYN_COOKIE_PERIOD) as u32
+}
+
+/// Computes the 24 bits hash of a SYN cookie.
+///
+/// Arguments:
+/// - `conn` is the connection, from the point of view of the peer.
+/// - `isn` is the Initial Sequence Number of the peer.
+/// - `counter` is the counter embedded in the cookie, on 5 bits.
+/// - `mss_index` is the index of the MSS embedded in the cookie, on 3 bits.
+fn syn_cookie_hash(conn: &ConnectionId, isn: u32, counter: u32, mss_index: u32) -> u32 {
+	let secret = {
+		let mut secret = SYN_COOKIE_SECRET.lock();
+		*secret.get_or_insert_with(|| {
+			let mut key = [0; 32];
+			if let Some(pool) = &mut *ENTROPY_POOL.lock() {
+				pool.read(&mut key, true);
+			}
+			key
+		})
+	};
+
+	// The secret is used as the key of a ChaCha20 block, the connection as its input
+	let mut input = [0u8; 64];
+	input[..16].copy_from_slice(b"expand 32-byte k");
+	input[16..48].copy_from_slice(&secret);
+	let addrs = checksum::compute_crc32c(conn.src_addr) ^ checksum::compute_crc32c(conn.dst_addr);
+	input[48..52].copy_from_slice(&addrs.to_le_bytes());
+	input[52..54].copy_from_slice(&conn.src_port.to_le_bytes());
+	input[54..56].copy_from_slice(&conn.dst_port.to_le_bytes());
+	input[56..60].copy_from_slice(&isn.to_le_bytes());
+	input[60..64].copy_from_slice(&((counter << 3) | mss_index).to_le_bytes());
+	let mut output = [0u8; 64];
+	chacha20::block(&input, &mut output);
+
+	let out = u32::from_le_bytes(output[..4].try_into().unwrap());
+	let inp = u32::from_le_bytes(input[..4].try_into().unwrap());
+	out.wrapping_add(inp) & 0xffffff
+}
+
+/// Computes a SYN cookie for the connection `conn` with the peer's Initial Sequence Number
+/// `isn` and Maximum Segment Size `mss`.
+///
+/// The cookie is made of a counter on 5 bits, the index of the MSS on 3 bits, then a hash on 24
+/// bits.
+pub fn syn_cookie(conn: &ConnectionId, isn: u32, mss: u16) -> u32 {
+	let mss_index = SYN_COOKIE_MSS.iter().rposition(|m| *m <= mss).unwrap_or(0) as u32;
+	let counter = syn_cookie_counter() & 0x1f;
+	(counter << 27) | (mss_index << 24) | syn_cookie_hash(conn, isn, counter, mss_index)
+}
+
+/// Checks the SYN cookie acknowledged by the peer.
+///
+/// Arguments:
+/// - `conn` is the connection, from the point of view of the peer.
+/// - `seq` is the sequence number of the acknowledging segment.
+/// - `ack` is the acknowledgement number of the acknowledging segment.
+///
+/// If the cookie is valid, the function returns the MSS it encodes.
+pub fn check_syn_cookie(conn: &ConnectionId, seq: u32, ack: u32) -> Option<u16> {
+	let cookie = ack.wrapping_sub(1);
+	let isn = seq.wrapping_sub(1);
+	let counter = cookie >> 27;
+	let mss_index = (cookie >> 24) & 0x7;
+
+	let age = syn_cookie_counter().wrapping_sub(counter) & 0x1f;
+	if age > SYN_COOKIE_VALIDITY {
+		return None;
+	}
+	if syn_cookie_hash(conn, isn, counter, mss_index) != cookie & 0xffffff {
+		return None;
+	}
+	Some(SYN_COOKIE_MSS[mss_index as usize])
+}
+
+/// Decides what to do with a SYN segment received on the listening socket `listener`.
+///
+/// When the SYN queue overflows, or when SYN segments arrive faster than a listening socket or
+/// the system can reasonably handle, no state is kept for the connection: a SYN cookie is sent
+/// if enabled, or else the segment is dropped.
+///
+/// Arguments:
+/// - `half_open` is the number of connections of the listener in the SYN queue.
+/// - `conn` is the connection, from the point of view of the peer.
+/// - `isn` is the Initial Sequence Number of the peer.
+/// - `mss` is the Maximum Segment Size advertised by the peer.
+pub fn on_syn(
+	listener: &mut Socket,
+	half_open: usize,
+	conn: &ConnectionId,
+	isn: u32,
+	mss: u16,
+) -> SynAction {
+	// The connection could not be accepted anyway
+	if listener.is_accept_queue_full() {
+		return SynAction::Drop;
+	}
+
+	let now = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Second).unwrap_or(0);
+	let global_rate = SYN_RATE.lock().hit(now);
+	let socket_rate = listener.syn_rate().hit(now);
+	let flood = global_rate > SYN_RATE_GLOBAL_MAX || socket_rate > SYN_RATE_SOCKET_MAX;
+
+	if !flood && half_open < listener.backlog() {
+		return SynAction::Queue;
+	}
+	if SYN_COOKIES.load(Relaxed) {
+		let mss = negotiate_mss(conn.src_addr, Some(mss));
+		SynAction::Cookie(syn_cookie(conn, isn, mss))
+	} else {
+		SynAction::Drop
+	}
+}
+
 /// Initiates a TCP connection on the given socket `sock`.
 pub fn init_connection(_sock: &mut Socket) -> Result<(), Errno> {
 	// TODO
 	todo!();
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test_case]
+	fn syn_cookie_check() {
+		let conn = ConnectionId {
+			src_addr: &[10, 0, 0, 1],
+			src_port: 40000,
+			dst_addr: &[10, 0, 0, 2],
+			dst_port: 80,
+		};
+		let isn = 0x12345678;
+		let cookie = syn_cookie(&conn, isn, 1460);
+
+		let seq = isn.wrapping_add(1);
+		let ack = cookie.wrapping_add(1);
+		assert_eq!(check_syn_cookie(&conn, seq, ack), Some(1460));
+		assert_eq!(check_syn_cookie(&conn, seq, ack ^ 1), None);
+		assert_eq!(check_syn_cookie(&conn, seq.wrapping_add(1), ack), None);
+	}
+
+	#[test_case]
+	fn rate_counter() {
+		let mut rate = RateCounter::new();
+		assert_eq!(rate.hit(10), 1);
+		assert_eq!(rate.hit(10), 2);
+		assert_eq!(rate.hit(11), 1);
+	}
+}
@@ -0,0 +1,417 @@
+//! The tun/tap driver provides virtual network interfaces whose other end is a userspace program.
+//!
+//! Once a process attached an interface to `/dev/net/tun` using `TUNSETIFF`, every packet the
+//! kernel transmits on the interface can be read from the device, and every packet written to the
+//! device is received by the kernel as if it came from the network.
+//!
+//! Two modes are available:
+//! - tun: the interface carries IP packets (layer 3)
+//! - tap: the interface carries Ethernet frames (layer 2). A tap interface can be a port of a
+//! bridge
+
+use super::bridge;
+use super::bridge::ETH_HLEN;
+use super::buff::BuffList;
+use super::BindAddress;
+use super::IfReq;
+use super::Interface;
+use super::InterfaceStats;
+use super::IFNAMSIZ;
+use super::MAC;
+use crate::device;
+use crate::device::Device;
+use crate::device::DeviceHandle;
+use crate::device::DeviceID;
+use crate::device::DeviceType;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::blocking::BlockHandler;
+use crate::file::blocking::PollTable;
+use crate::file::path::Path;
+use crate::file::Mode;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::mem_space::MemSpace;
+use crate::process::pid::Pid;
+use crate::process::Process;
+use crate::syscall::ioctl;
+use crate::util::container::hashmap::HashMap;
+use crate::util::container::string::String;
+use crate::util::container::vec::Vec;
+use crate::util::io;
+use crate::util::io::IO;
+use crate::util::lock::IntMutex;
+use crate::util::lock::Mutex;
+use crate::util::ptr::arc::Arc;
+use crate::util::TryClone;
+use core::cmp::min;
+use core::ffi::c_short;
+use core::ffi::c_void;
+
+/// The major number of the `/dev/net/tun` device.
+const TUN_MAJOR: u32 = 10;
+/// The minor number of the `/dev/net/tun` device.
+const TUN_MINOR: u32 = 200;
+/// The mode of the `/dev/net/tun` device file.
+const TUN_MODE: Mode = 0o666;
+
+/// Interface flag: tun mode.
+const IFF_TUN: c_short = 0x0001;
+/// Interface flag: tap mode.
+const IFF_TAP: c_short = 0x0002;
+/// Interface flag: packets are not prefixed with packet information.
+const IFF_NO_PI: c_short = 0x1000;
+
+/// Packet information flag: the packet was truncated because the buffer was too small.
+const TUN_PKT_STRIP: u16 = 0x0001;
+/// The size of the packet information prefix.
+const PI_SIZE: usize = 4;
+
+/// Ethernet protocol: IPv4.
+const ETH_P_IP: u16 = 0x0800;
+/// Ethernet protocol: IPv6.
+const ETH_P_IPV6: u16 = 0x86dd;
+
+/// The maximum number of packets waiting in each direction. Further packets are dropped.
+const QUEUE_MAX: usize = 500;
+
+/// A tun/tap interface.
+pub struct Tun {
+	/// The name of the interface.
+	name: String,
+	/// If `true`, the interface is in tap mode. Else, it is in tun mode.
+	tap: bool,
+	/// Tells whether packets exchanged with userspace are prefixed with packet information.
+	packet_info: bool,
+	/// The MAC address of the interface. Meaningful only in tap mode.
+	mac: MAC,
+
+	/// Packets transmitted by the kernel, waiting to be read by userspace.
+	outgoing: Vec<Vec<u8>>,
+	/// Packets written by userspace, waiting to be received by the kernel.
+	incoming: Vec<Vec<u8>>,
+	/// The interface's statistics.
+	stats: InterfaceStats,
+
+	/// The handler for processes waiting on the interface's device.
+	block_handler: BlockHandler,
+}
+
+impl Tun {
+	/// Returns the protocol of the packet `packet`, to be given in packet information.
+	fn protocol(&self, packet: &[u8]) -> u16 {
+		if self.tap {
+			return packet
+				.get(12..14)
+				.map(|p| u16::from_be_bytes([p[0], p[1]]))
+				.unwrap_or(0);
+		}
+		match packet.first().map(|b| b >> 4) {
+			Some(4) => ETH_P_IP,
+			Some(6) => ETH_P_IPV6,
+			_ => 0,
+		}
+	}
+
+	/// Reads the next packet transmitted by the kernel into `buf`.
+	///
+	/// If the buffer is too small, the packet is truncated.
+	///
+	/// The function returns the number of bytes written to `buf`.
+	fn user_read(&mut self, buf: &mut [u8]) -> usize {
+		if self.outgoing.is_empty() {
+			return 0;
+		}
+		let packet = self.outgoing.remove(0);
+		let mut off = 0;
+		if self.packet_info {
+			if buf.len() < PI_SIZE {
+				return 0;
+			}
+			let flags = if buf.len() - PI_SIZE < packet.len() {
+				TUN_PKT_STRIP
+			} else {
+				0
+			};
+			buf[0..2].copy_from_slice(&flags.to_be_bytes());
+			buf[2..4].copy_from_slice(&self.protocol(&packet).to_be_bytes());
+			off = PI_SIZE;
+		}
+		let len = min(buf.len() - off, packet.len());
+		buf[off..(off + len)].copy_from_slice(&packet[..len]);
+		off + len
+	}
+
+	/// Validates the packet `buf` written by userspace and returns it without its packet
+	/// information.
+	fn user_packet<'b>(&self, buf: &'b [u8]) -> EResult<&'b [u8]> {
+		let packet = if self.packet_info {
+			buf.get(PI_SIZE..).ok_or_else(|| errno!(EINVAL))?
+		} else {
+			buf
+		};
+		let min_len = if self.tap { ETH_HLEN } else { 1 };
+		if packet.len() < min_len {
+			return Err(errno!(EINVAL));
+		}
+		Ok(packet)
+	}
+}
+
+impl Interface for Tun {
+	fn get_name(&self) -> &[u8] {
+		&self.name
+	}
+
+	fn is_up(&self) -> bool {
+		true
+	}
+
+	fn get_mac(&self) -> &MAC {
+		&self.mac
+	}
+
+	fn get_addresses(&self) -> &[BindAddress] {
+		// TODO allow binding addresses
+		&[]
+	}
+
+	fn get_stats(&self) -> InterfaceStats {
+		self.stats
+	}
+
+	fn get_stats_mut(&mut self) -> Option<&mut InterfaceStats> {
+		Some(&mut self.stats)
+	}
+
+	fn read(&mut self, buff: &mut [u8]) -> Result<u64, Errno> {
+		if self.incoming.is_empty() {
+			return Ok(0);
+		}
+		let packet = self.incoming.remove(0);
+		let len = min(buff.len(), packet.len());
+		buff[..len].copy_from_slice(&packet[..len]);
+		Ok(len as _)
+	}
+
+	fn write(&mut self, buff: &BuffList<'_>) -> Result<u64, Errno> {
+		// Like a physical interface, packets are dropped when the queue is full
+		if self.outgoing.len() >= QUEUE_MAX {
+			return Err(errno!(ENOBUFS));
+		}
+		self.outgoing.push(buff.collect()?)?;
+		self.block_handler.wake_processes(io::POLLIN);
+		Ok(buff.len() as _)
+	}
+}
+
+/// Returns the name of the first interface matching the pattern `pattern` that is not used.
+///
+/// The pattern may contain `%d`, which is replaced by a number.
+fn alloc_name(pattern: &[u8]) -> EResult<String> {
+	let Some(pos) = pattern.windows(2).position(|w| w == b"%d") else {
+		return Ok(String::try_from(pattern)?);
+	};
+	for i in 0.. {
+		let name = crate::format!(
+			"{}{i}{}",
+			core::str::from_utf8(&pattern[..pos]).map_err(|_| errno!(EINVAL))?,
+			core::str::from_utf8(&pattern[(pos + 2)..]).map_err(|_| errno!(EINVAL))?
+		)?;
+		if name.len() >= IFNAMSIZ {
+			break;
+		}
+		if super::get_iface(&name).is_none() {
+			return Ok(name);
+		}
+	}
+	Err(errno!(ENFILE))
+}
+
+/// The handle of the `/dev/net/tun` device.
+///
+/// TODO Devices cannot hold state per open file description yet, so interfaces are attached to
+/// the process that created them. A process can thus attach only one interface at a time.
+#[derive(Default)]
+pub struct TunDeviceHandle {
+	/// The interface attached to each process.
+	attached: HashMap<Pid, Arc<Mutex<Tun>>>,
+	/// The handler for processes waiting on the device while no interface is attached.
+	block_handler: BlockHandler,
+}
+
+impl TunDeviceHandle {
+	/// Returns the interface attached to the current process.
+	fn current(&self) -> EResult<Arc<Mutex<Tun>>> {
+		let pid = Process::current_assert().lock().pid;
+		self.attached
+			.get(&pid)
+			.cloned()
+			.ok_or_else(|| errno!(EBADFD))
+	}
+
+	/// Creates an interface as requested by `ifreq` and attaches it to the current process.
+	fn set_iff(&mut self, ifreq: &mut IfReq) -> EResult<()> {
+		let (pid, privileged) = {
+			let proc_mutex = Process::current_assert();
+			let proc = proc_mutex.lock();
+			(proc.pid, proc.access_profile.is_privileged())
+		};
+		if !privileged {
+			return Err(errno!(EPERM));
+		}
+		if self.attached.contains_key(&pid) {
+			return Err(errno!(EINVAL));
+		}
+		// Forget interfaces of processes that exited
+		self.attached.retain(|pid, tun| {
+			let alive = Process::get_by_pid(*pid).is_some();
+			if !alive {
+				super::unregister_iface(&tun.lock().name);
+			}
+			alive
+		});
+
+		let flags = ifreq.flags();
+		let tap = match (flags & IFF_TUN != 0, flags & IFF_TAP != 0) {
+			(true, false) => false,
+			(false, true) => true,
+			_ => return Err(errno!(EINVAL)),
+		};
+		let name = match ifreq.name() {
+			b"" if tap => alloc_name(b"tap%d")?,
+			b"" => alloc_name(b"tun%d")?,
+			pattern => alloc_name(pattern)?,
+		};
+
+		let tun = Arc::new(Mutex::new(Tun {
+			name: name.try_clone()?,
+			tap,
+			packet_info: flags & IFF_NO_PI == 0,
+			mac: if tap { super::random_mac() } else { [0; 6] },
+
+			outgoing: Vec::new(),
+			incoming: Vec::new(),
+			stats: InterfaceStats::default(),
+
+			block_handler: BlockHandler::new(),
+		}))?;
+		super::register_iface(name.try_clone()?, tun.clone())?;
+		if let Err(e) = self.attached.insert(pid, tun) {
+			super::unregister_iface(&name);
+			return Err(e.into());
+		}
+
+		ifreq.set_name(&name)?;
+		Ok(())
+	}
+}
+
+impl DeviceHandle for TunDeviceHandle {
+	fn ioctl(
+		&mut self,
+		mem_space: Arc<IntMutex<MemSpace>>,
+		request: ioctl::Request,
+		argp: *const c_void,
+	) -> Result<u32, Errno> {
+		match request.get_old_format() {
+			ioctl::TUNSETIFF => {
+				let mut mem_space_guard = mem_space.lock();
+				let ifreq_ptr: SyscallPtr<IfReq> = (argp as usize).into();
+				let ifreq = ifreq_ptr
+					.get_mut(&mut mem_space_guard)?
+					.ok_or_else(|| errno!(EFAULT))?;
+				self.set_iff(ifreq)?;
+				Ok(0)
+			}
+
+			_ => Err(errno!(EINVAL)),
+		}
+	}
+
+	fn add_waiting_process(&mut self, proc: &mut Process, mask: u32) -> Result<(), Errno> {
+		match self.attached.get(&proc.pid) {
+			Some(tun) => tun.lock().block_handler.add_waiting_process(proc, mask),
+			None => self.block_handler.add_waiting_process(proc, mask),
+		}
+	}
+
+	fn register_poll(
+		&mut self,
+		proc: &Process,
+		table: &PollTable,
+		mask: u32,
+	) -> Result<(), Errno> {
+		let res = match self.attached.get(&proc.pid) {
+			Some(tun) => tun.lock().block_handler.register(table, mask),
+			None => self.block_handler.register(table, mask),
+		};
+		Ok(res?)
+	}
+}
+
+impl IO for TunDeviceHandle {
+	fn get_size(&self) -> u64 {
+		0
+	}
+
+	fn read(&mut self, _: u64, buff: &mut [u8]) -> Result<(u64, bool), Errno> {
+		let tun = self.current()?;
+		let len = tun.lock().user_read(buff);
+		Ok((len as _, false))
+	}
+
+	fn write(&mut self, _: u64, buff: &[u8]) -> Result<u64, Errno> {
+		let tun_mutex = self.current()?;
+		let (name, tap, packet) = {
+			let mut tun = tun_mutex.lock();
+			let packet = match tun.user_packet(buff) {
+				Ok(packet) => packet,
+				Err(e) => {
+					tun.stats.rx_errors += 1;
+					return Err(e);
+				}
+			};
+			(tun.name.try_clone()?, tun.tap, packet)
+		};
+
+		// The interface is not locked while forwarding, since the bridge may transmit the frame
+		// back on it
+		let bridged = tap && bridge::input(&name, packet)?;
+		let mut tun = tun_mutex.lock();
+		if bridged || tun.incoming.len() < QUEUE_MAX {
+			if !bridged {
+				tun.incoming.push(Vec::from_slice(packet)?)?;
+			}
+			tun.stats.on_receive(packet.len());
+		} else {
+			tun.stats.rx_dropped += 1;
+		}
+		Ok(buff.len() as _)
+	}
+
+	fn poll(&mut self, mask: u32) -> Result<u32, Errno> {
+		let tun = self.current()?;
+		let tun = tun.lock();
+		let mut events = io::POLLOUT;
+		if !tun.outgoing.is_empty() {
+			events |= io::POLLIN;
+		}
+		Ok(events & mask)
+	}
+}
+
+/// Creates the `/dev/net/tun` device.
+pub fn init() -> EResult<()> {
+	let path = Path::from_str(b"/dev/net/tun", false)?;
+	let dev = Device::new(
+		DeviceID {
+			type_: DeviceType::Char,
+			major: TUN_MAJOR,
+			minor: TUN_MINOR,
+		},
+		path,
+		TUN_MODE,
+		TunDeviceHandle::default(),
+	)?;
+	device::register(dev)
+}
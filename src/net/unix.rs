@@ -0,0 +1,379 @@
+//! Unix domain sockets bookkeeping.
+//!
+//! Names in the abstract namespace are not files on a filesystem. They are registered here while
+//! the socket bound to them is alive.
+//!
+//! File descriptors passed with `SCM_RIGHTS` are *in flight* while they wait in the receive queue
+//! of a socket. If a socket is in flight in its own queue, or in the queue of another socket which
+//! is itself in flight in the first one, the sockets hold each other and are never closed, even
+//! once no process can access them anymore. The collector detects such cycles and breaks them.
+//!
+//! While in flight, files are charged to the user who sent them. A message cannot carry more than
+//! [`SCM_MAX_FD`] files, and a user cannot have more files in flight than a process can have open
+//! files, unless privileged.
+
+use crate::errno;
+use crate::errno::AllocResult;
+use crate::errno::EResult;
+use crate::file::buffer;
+use crate::file::buffer::socket::Socket;
+use crate::file::buffer::Buffer;
+use crate::file::open_file::OpenFile;
+use crate::file::perm::AccessProfile;
+use crate::file::perm::Uid;
+use crate::file::FileLocation;
+use crate::limits;
+use crate::util::container::hashmap::HashMap;
+use crate::util::container::vec::Vec;
+use crate::util::lock::Mutex;
+use crate::util::ptr::arc::Arc;
+use crate::util::ptr::arc::Weak;
+use core::any::Any;
+use core::mem;
+use core::sync::atomic;
+use core::sync::atomic::AtomicUsize;
+
+/// The `AF_UNIX` address family.
+const AF_UNIX: u16 = 1;
+
+/// The maximum number of files passed in a single message.
+pub const SCM_MAX_FD: usize = 253;
+
+/// A reference to a socket which doesn't keep it alive.
+type WeakSocket = Weak<Mutex<dyn Buffer>>;
+
+/// Sockets bound to a name in the abstract namespace.
+static ABSTRACT_NAMES: Mutex<HashMap<Vec<u8>, WeakSocket>> = Mutex::new(HashMap::new());
+
+/// The number of files in flight.
+static IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+/// Incremented each time a file enters or leaves a receive queue, so that the collector can
+/// tell whether queues changed while it was scanning them.
+static GENERATION: AtomicUsize = AtomicUsize::new(0);
+/// Files that left a receive queue while a socket was locked, waiting to be closed.
+static DEFERRED: Mutex<Vec<Arc<Mutex<OpenFile>>>> = Mutex::new(Vec::new());
+/// The number of files in flight sent by each user.
+static USER_IN_FLIGHT: Mutex<HashMap<Uid, usize>> = Mutex::new(HashMap::new());
+
+/// If `addr` is a Unix domain address in the abstract namespace, returns its name.
+///
+/// Such an address begins with a null byte, which is not part of the name.
+pub fn abstract_name(addr: &[u8]) -> Option<&[u8]> {
+	let family = u16::from_ne_bytes(addr.get(..2)?.try_into().unwrap());
+	match addr.get(2..)? {
+		[0, name @ ..] if family == AF_UNIX => Some(name),
+		_ => None,
+	}
+}
+
+/// Binds the socket `sock` to the name `name` in the abstract namespace.
+///
+/// If the name is already used by a socket that is still alive, the function returns an error.
+pub fn bind_abstract(name: &[u8], sock: &Arc<Mutex<dyn Buffer>>) -> EResult<()> {
+	let name = Vec::from_slice(name)?;
+	let mut names = ABSTRACT_NAMES.lock();
+	// The entry is not upgraded since dropping the last reference to the socket would unbind it
+	// while the registry is locked
+	if names.get(&name).is_some_and(|s| s.strong_count() > 0) {
+		return Err(errno!(EADDRINUSE));
+	}
+	names.insert(name, Arc::downgrade(sock))?;
+	Ok(())
+}
+
+/// Unbinds the name `name` in the abstract namespace, after the socket bound to it has been
+/// dropped.
+///
+/// If the name has been bound again in the meantime, the function does nothing. If the memory
+/// allocation fails, the entry is left in place and overwritten by the next bind on the name.
+pub fn unbind_abstract(name: &[u8]) {
+	let Ok(name) = Vec::from_slice(name) else {
+		return;
+	};
+	let mut names = ABSTRACT_NAMES.lock();
+	if names.get(&name).is_some_and(|s| s.strong_count() == 0) {
+		names.remove(&name);
+	}
+}
+
+/// Charges `count` files in flight to the user `uid`.
+///
+/// If the user would have too many files in flight and is not `privileged`, the function returns
+/// [`errno::ETOOMANYREFS`].
+fn charge(uid: Uid, count: usize, privileged: bool) -> EResult<()> {
+	let mut users = USER_IN_FLIGHT.lock();
+	let in_flight = users.get(&uid).copied().unwrap_or(0);
+	if !privileged && in_flight + count > limits::OPEN_MAX as usize {
+		return Err(errno!(ETOOMANYREFS));
+	}
+	users.insert(uid, in_flight + count)?;
+	IN_FLIGHT.fetch_add(count, atomic::Ordering::Relaxed);
+	GENERATION.fetch_add(1, atomic::Ordering::Relaxed);
+	Ok(())
+}
+
+/// Refunds `count` files in flight to the user `uid`.
+fn uncharge(uid: Uid, count: usize) {
+	let mut users = USER_IN_FLIGHT.lock();
+	if let Some(in_flight) = users.get_mut(&uid) {
+		*in_flight = in_flight.saturating_sub(count);
+		if *in_flight == 0 {
+			users.remove(&uid);
+		}
+	}
+	IN_FLIGHT.fetch_sub(count, atomic::Ordering::Relaxed);
+	GENERATION.fetch_add(1, atomic::Ordering::Relaxed);
+}
+
+/// Defers the release of the files `files`.
+///
+/// Dropping the last reference to an open file locks the buffer it points to, so files cannot be
+/// dropped while a socket is locked. They are released by the next call to [`collect`].
+pub fn release(mut files: Vec<Arc<Mutex<OpenFile>>>) {
+	if files.is_empty() {
+		return;
+	}
+	if DEFERRED.lock().append(&mut files).is_err() {
+		// Leaking the files is preferable to dropping them while a socket may be locked
+		mem::forget(files);
+	}
+}
+
+/// Files passed in a single message, in flight until they are received.
+///
+/// The files that are not taken out are released when the structure is dropped.
+#[derive(Default)]
+pub struct InFlight {
+	/// The user who sent the files, to whom they are charged.
+	uid: Uid,
+	/// The files.
+	files: Vec<Arc<Mutex<OpenFile>>>,
+}
+
+impl InFlight {
+	/// Puts the files `files` sent by the agent with access profile `ap` in flight.
+	///
+	/// If the message carries more than [`SCM_MAX_FD`] files, the function returns
+	/// [`errno::EINVAL`]. If the sender has too many files in flight, it returns
+	/// [`errno::ETOOMANYREFS`]. In both cases, the files are released.
+	pub fn new(ap: &AccessProfile, files: Vec<Arc<Mutex<OpenFile>>>) -> EResult<Self> {
+		let uid = ap.get_uid();
+		let res = if files.len() > SCM_MAX_FD {
+			Err(errno!(EINVAL))
+		} else {
+			charge(uid, files.len(), ap.is_privileged())
+		};
+		if let Err(e) = res {
+			release(files);
+			return Err(e);
+		}
+		Ok(Self {
+			uid,
+			files,
+		})
+	}
+
+	/// Returns the files.
+	pub fn files(&self) -> &[Arc<Mutex<OpenFile>>] {
+		&self.files
+	}
+
+	/// Takes the files out of flight and returns them.
+	pub fn take(&mut self) -> Vec<Arc<Mutex<OpenFile>>> {
+		let files = mem::take(&mut self.files);
+		if !files.is_empty() {
+			uncharge(self.uid, files.len());
+		}
+		files
+	}
+}
+
+impl Drop for InFlight {
+	fn drop(&mut self) {
+		release(self.take());
+	}
+}
+
+/// Files passed over a Unix domain socket.
+///
+/// The files that are not taken out are released when the structure is dropped.
+#[derive(Default)]
+pub struct PassedFiles(pub Vec<Arc<Mutex<OpenFile>>>);
+
+impl Drop for PassedFiles {
+	fn drop(&mut self) {
+		release(mem::take(&mut self.0));
+	}
+}
+
+/// Closes the files which release has been deferred.
+fn flush() {
+	let files = mem::take(&mut *DEFERRED.lock());
+	for file in files {
+		if let Some(file) = Arc::into_inner(file) {
+			let _ = file.into_inner().close();
+		}
+	}
+}
+
+/// Tells whether the collector has work to do.
+pub fn is_pending() -> bool {
+	IN_FLIGHT.load(atomic::Ordering::Relaxed) > 0 || !DEFERRED.lock().is_empty()
+}
+
+/// A socket, as seen by the collector.
+struct Node {
+	/// The location of the socket.
+	loc: FileLocation,
+	/// The socket.
+	sock: Arc<Mutex<dyn Buffer>>,
+	/// The number of open files pointing to the socket.
+	open_count: u32,
+	/// The files in flight in the socket's receive queue.
+	in_flight: Vec<Arc<Mutex<OpenFile>>>,
+}
+
+/// Takes a snapshot of every socket, along with the files in flight in its receive queue.
+fn snapshot() -> AllocResult<Vec<Node>> {
+	let mut nodes = Vec::new();
+	for loc in buffer::list()? {
+		let Some(sock_mutex) = buffer::get(&loc) else {
+			continue;
+		};
+		let (open_count, in_flight) = {
+			let mut sock = sock_mutex.lock();
+			let Some(sock) = (&mut *sock as &mut dyn Any).downcast_mut::<Socket>() else {
+				continue;
+			};
+			(sock.open_count(), sock.in_flight()?)
+		};
+		nodes.push(Node {
+			loc,
+			sock: sock_mutex,
+			open_count,
+			in_flight,
+		})?;
+	}
+	Ok(nodes)
+}
+
+/// Returns the sockets that can only be reached through files in flight in unreachable sockets.
+///
+/// A file is a candidate if every reference to it is in flight. A socket is a candidate if every
+/// open file pointing to it is a candidate. Candidates in flight in the queue of a socket that is
+/// not a candidate, directly or transitively, are still reachable.
+fn find_garbage(nodes: &[Node]) -> AllocResult<Vec<bool>> {
+	// The number of times each file is in flight
+	let mut occurrences: HashMap<*const Mutex<OpenFile>, usize> = HashMap::new();
+	for file in nodes.iter().flat_map(|n| n.in_flight.iter()) {
+		let ptr = file.as_ptr();
+		let count = occurrences.get(&ptr).copied().unwrap_or(0);
+		occurrences.insert(ptr, count + 1)?;
+	}
+	// Each occurrence is referenced once by the queue and once by the snapshot
+	let is_candidate_file = |file: &Arc<Mutex<OpenFile>>| {
+		let count = occurrences.get(&file.as_ptr()).copied().unwrap_or(0);
+		Arc::strong_count(file) == count * 2
+	};
+
+	// For each socket, the candidate files pointing to it. Files are counted only once
+	let mut candidate_files = crate::vec![0u32; nodes.len()]?;
+	let mut seen: HashMap<*const Mutex<OpenFile>, ()> = HashMap::new();
+	for file in nodes.iter().flat_map(|n| n.in_flight.iter()) {
+		if !is_candidate_file(file) || seen.get(&file.as_ptr()).is_some() {
+			continue;
+		}
+		seen.insert(file.as_ptr(), ())?;
+		let loc = file.lock().get_location().clone();
+		if let Some(i) = nodes.iter().position(|n| n.loc == loc) {
+			candidate_files[i] += 1;
+		}
+	}
+	let mut garbage = Vec::with_capacity(nodes.len())?;
+	for (node, count) in nodes.iter().zip(candidate_files.iter()) {
+		garbage.push(node.open_count > 0 && *count == node.open_count)?;
+	}
+
+	// Sockets in flight in reachable sockets are reachable
+	loop {
+		let mut changed = false;
+		for i in 0..nodes.len() {
+			if garbage[i] {
+				continue;
+			}
+			for file in nodes[i].in_flight.iter() {
+				let loc = file.lock().get_location().clone();
+				if let Some(j) = nodes.iter().position(|n| n.loc == loc) {
+					if garbage[j] {
+						garbage[j] = false;
+						changed = true;
+					}
+				}
+			}
+		}
+		if !changed {
+			break;
+		}
+	}
+	Ok(garbage)
+}
+
+/// Closes the files which release has been deferred, then breaks the cycles of unreachable
+/// sockets holding each other's files in flight, so that they are closed.
+pub fn collect() -> EResult<()> {
+	flush();
+	if IN_FLIGHT.load(atomic::Ordering::Relaxed) == 0 {
+		return Ok(());
+	}
+
+	let generation = GENERATION.load(atomic::Ordering::Relaxed);
+	let nodes = snapshot()?;
+	let garbage = find_garbage(&nodes)?;
+	// If queues changed during the scan, reference counts may be inconsistent. The collection is
+	// retried on the next close
+	if GENERATION.load(atomic::Ordering::Relaxed) != generation {
+		return Ok(());
+	}
+
+	// Unreachable sockets cannot be accessed by any process, so their queues cannot change anymore
+	for (node, _) in nodes.iter().zip(garbage.iter()).filter(|(_, g)| **g) {
+		let mut sock = node.sock.lock();
+		if let Some(sock) = (&mut *sock as &mut dyn Any).downcast_mut::<Socket>() {
+			sock.purge_in_flight();
+		}
+	}
+	drop(nodes);
+	flush();
+	Ok(())
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test_case]
+	fn unix_abstract_name() {
+		let mut addr = crate::vec![1u8, 0, 0, b'f', b'o', b'o'].unwrap();
+		addr[..2].copy_from_slice(&AF_UNIX.to_ne_bytes());
+		assert_eq!(abstract_name(&addr), Some(&b"foo"[..]));
+		// Pathname address
+		addr[2] = b'/';
+		assert_eq!(abstract_name(&addr), None);
+		// Unnamed address
+		assert_eq!(abstract_name(&addr[..2]), None);
+	}
+
+	#[test_case]
+	fn unix_in_flight_limit() {
+		let uid = 4242;
+		let max = limits::OPEN_MAX as usize;
+		charge(uid, max, false).unwrap();
+		assert!(charge(uid, 1, false).is_err());
+		// Privileged users are not limited
+		charge(uid, 1, true).unwrap();
+		uncharge(uid, 2);
+		charge(uid, 1, false).unwrap();
+		uncharge(uid, max);
+		assert!(USER_IN_FLIGHT.lock().get(&uid).is_none());
+	}
+}
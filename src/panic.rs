@@ -4,8 +4,55 @@
 //! from. This is an undesirable state which requires to reboot the host
 //! machine.
 
-use crate::{cpu, logger, power};
+use crate::{cpu, debug, file, logger, module, power};
+use core::fmt;
 use core::panic::PanicInfo;
+use core::sync::atomic::AtomicU32;
+use core::sync::atomic::Ordering::Relaxed;
+
+/// Taint flag: a module has been loaded by force, bypassing its checks.
+pub const TAINT_FORCED_MODULE: u32 = 1 << 1;
+/// Taint flag: a module has been unloaded by force while in use.
+pub const TAINT_FORCED_RMMOD: u32 = 1 << 7;
+/// Taint flag: a module that was not built in the kernel's tree has been loaded.
+pub const TAINT_OOT_MODULE: u32 = 1 << 12;
+
+/// Each taint flag with the letter representing it.
+const TAINT_LETTERS: [(u32, char); 3] = [
+	(TAINT_FORCED_MODULE, 'F'),
+	(TAINT_FORCED_RMMOD, 'R'),
+	(TAINT_OOT_MODULE, 'O'),
+];
+
+/// The taint flags of the kernel.
+///
+/// A tainted kernel has been in a state that is not supported, which is to be taken into account
+/// when triaging bugs.
+static TAINT: AtomicU32 = AtomicU32::new(0);
+
+/// Adds the given taint flags to the kernel. Flags cannot be removed.
+pub fn add_taint(flags: u32) {
+	TAINT.fetch_or(flags, Relaxed);
+}
+
+/// Returns the taint flags of the kernel.
+pub fn get_taint() -> u32 {
+	TAINT.load(Relaxed)
+}
+
+/// Displays taint flags as a list of letters.
+pub struct DisplayTaint(pub u32);
+
+impl fmt::Display for DisplayTaint {
+	fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+		for (flag, c) in TAINT_LETTERS {
+			if self.0 & flag != 0 {
+				write!(fmt, "{c}")?;
+			}
+		}
+		Ok(())
+	}
+}
 
 /// Called on Rust panic.
 #[panic_handler]
@@ -42,12 +89,19 @@ fn panic(panic_info: &PanicInfo) -> ! {
 		"If you believe this is a bug on the kernel side, please feel free to report it."
 	);
 
+	let taint = get_taint();
+	if taint != 0 {
+		crate::println!("Tainted: {}", DisplayTaint(taint));
+	} else {
+		crate::println!("Not tainted");
+	}
+	module::print_list();
+
 	let cr2 = unsafe { cpu::cr2_get() };
 	crate::println!("cr2: {cr2:p}\n");
 
 	#[cfg(config_debug_debug)]
 	{
-		use crate::debug;
 		use core::ffi::c_void;
 		use core::ptr::null_mut;
 
@@ -58,6 +112,11 @@ fn panic(panic_info: &PanicInfo) -> ! {
 		debug::print_callstack(&callstack);
 	}
 
+	// Keep the logs for the next boot
+	file::fs::pstore::ram::write_panic_record();
+
+	// Let the debugger inspect the state of the kernel, if enabled
+	debug::gdbstub::breakpoint();
 	power::halt();
 }
 
@@ -1,8 +1,101 @@
 //! This module handles system power.
+//!
+//! Before the system is powered off, rebooted or halted, [`prepare`] brings it to a state where no
+//! data can be lost: processes are terminated, filesystems are detached from their devices and
+//! devices are stopped.
+//!
+//! When the system is suspended, [`suspend`] quiesces devices instead, so that they can be resumed
+//! afterwards.
 
+use crate::device::manager;
+use crate::errno;
+use crate::errno::EResult;
+use crate::file::mountpoint;
 use crate::io;
+use crate::process;
+use crate::process::pid;
+use crate::process::scheduler;
+use crate::process::signal::Signal;
+use crate::process::Process;
+use crate::process::State;
+use crate::time::clock;
+use crate::time::clock::CLOCK_MONOTONIC;
+use crate::time::unit::TimestampScale;
 use core::arch::asm;
 
+/// The delay given to processes to terminate after `SIGTERM`, in milliseconds.
+const TERM_TIMEOUT: u64 = 5000;
+/// The delay given to processes to terminate after `SIGKILL`, in milliseconds.
+const KILL_TIMEOUT: u64 = 1000;
+
+/// Sends the signal `sig` to all processes except init and the current process, then waits for
+/// them to terminate for at most `timeout` milliseconds.
+///
+/// The function returns `true` if all processes have terminated.
+fn kill_all(sig: Signal, timeout: u64) -> bool {
+	let current_pid = Process::current_assert().lock().pid;
+	// Tells whether the process is to be terminated. Already terminated processes are ignored
+	let is_target = |pid, proc: &Process| {
+		pid != pid::INIT_PID && pid != current_pid && !matches!(proc.get_state(), State::Zombie)
+	};
+
+	process::kill_all(&sig, Some(current_pid));
+
+	let Ok(start) = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Millisecond) else {
+		return false;
+	};
+	loop {
+		let remaining = {
+			let mut sched = process::get_scheduler().lock();
+			sched
+				.iter_process()
+				.any(|(pid, proc_mutex)| is_target(*pid, &proc_mutex.lock()))
+		};
+		if !remaining {
+			return true;
+		}
+		let now = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Millisecond);
+		if now.map(|now| now >= start + timeout).unwrap_or(true) {
+			return false;
+		}
+		scheduler::end_tick();
+	}
+}
+
+/// Prepares the system to be powered off, rebooted or halted.
+///
+/// The function must be called from a process' context, which is the only process left running
+/// besides init afterwards.
+pub fn prepare() {
+	crate::println!("Sending SIGTERM to all processes...");
+	if !kill_all(Signal::SIGTERM, TERM_TIMEOUT) {
+		crate::println!("Sending SIGKILL to all processes...");
+		kill_all(Signal::SIGKILL, KILL_TIMEOUT);
+	}
+
+	crate::println!("Detaching filesystems...");
+	mountpoint::unmount_all();
+
+	crate::println!("Stopping devices...");
+	manager::shutdown();
+}
+
+/// Puts the system to sleep, then resumes it on wakeup.
+///
+/// Devices are suspended before sleeping and resumed afterwards, even if the system could not
+/// enter the sleep state.
+pub fn suspend() -> EResult<()> {
+	crate::println!("Suspending devices...");
+	manager::suspend()?;
+
+	// TODO Use ACPI to enter the S3 state
+	let res = Err(errno!(EOPNOTSUPP));
+
+	crate::println!("Resuming devices...");
+	manager::resume();
+	res
+}
+
 /// Halts the kernel until reboot.
 pub fn halt() -> ! {
 	// TODO Send a signal to all other cores to stop them
@@ -712,7 +712,17 @@ impl Executor for ELFExecutor {
 		let aux = build_auxilary(&self.info, &load_info, &vdso)?;
 
 		// The size in bytes of the initial data on the stack
-		let total_size = Self::get_init_stack_size(&self.info.argv, &self.info.envp, &aux).1;
+		let (info_size, total_size) =
+			Self::get_init_stack_size(&self.info.argv, &self.info.envp, &aux);
+		// Arguments then environment variables are stored at the beginning of the information
+		// block
+		let args_begin = user_stack as usize - info_size;
+		let args_len: usize = self.info.argv.iter().map(|a| a.len() + 1).sum();
+		let env_len: usize = self.info.envp.iter().map(|e| e.len() + 1).sum();
+		mem_space.set_exec_info(
+			args_begin..(args_begin + args_len),
+			(args_begin + args_len)..(args_begin + args_len + env_len),
+		);
 		// Pre-allocating pages on the user stack to write the initial data
 		{
 			// The number of pages to allocate on the user stack to write the initial data
@@ -81,21 +81,28 @@ pub fn exec(proc: &mut Process, image: ProgramImage) -> EResult<()> {
 	proc.argv = Arc::new(image.argv)?;
 	// TODO Set exec path
 
-	// Duplicate the file descriptor table
-	let fds = proc
-		.get_fds()
-		.map(|fds_mutex| -> EResult<_> {
-			let fds = fds_mutex.lock();
-			let new_fds = fds.duplicate(true)?;
-			Ok(Arc::new(Mutex::new(new_fds))?)
-		})
-		.transpose()?;
+	// A file descriptor table shared with other processes is left untouched. Instead, the
+	// process gets its own copy, without the file descriptors to be closed on exec
+	let unshared_fds = match proc.get_fds() {
+		Some(fds_mutex) if Arc::strong_count(fds_mutex) > 1 => {
+			let new_fds = fds_mutex.lock().duplicate(true)?;
+			Some(Arc::new(Mutex::new(new_fds))?)
+		}
+		_ => None,
+	};
 
 	// Set the new memory space to the process
 	proc.set_mem_space(Some(Arc::new(IntMutex::new(image.mem_space))?));
 
-	// Set new file descriptor table
-	proc.set_fds(fds);
+	// Now that the new image is committed, close file descriptors with the close-on-exec flag
+	match unshared_fds {
+		Some(fds) => proc.set_fds(Some(fds)),
+		None => {
+			if let Some(fds_mutex) = proc.get_fds() {
+				fds_mutex.lock().close_on_exec();
+			}
+		}
+	}
 
 	// Set the process's stacks
 	proc.user_stack = Some(image.user_stack);
@@ -113,6 +120,8 @@ pub fn exec(proc: &mut Process, image: ProgramImage) -> EResult<()> {
 
 	proc.reset_vfork();
 	proc.clear_tls_entries();
+	proc.restart_block = None;
+	proc.fpu_reset();
 
 	// Set the process's registers
 	let regs = Regs {
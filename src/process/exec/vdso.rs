@@ -10,6 +10,7 @@ use crate::process::mem_space;
 use crate::process::mem_space::MapConstraint;
 use crate::process::mem_space::MapResidence;
 use crate::process::mem_space::MemSpace;
+use crate::time::clock;
 use crate::util::container::vec::Vec;
 use crate::util::lock::Mutex;
 use crate::util::math;
@@ -25,7 +26,8 @@ static ELF_IMAGE: &[u8] = include_bytes_aligned!(usize, env!("VDSO_PATH"));
 
 /// Informations on the vDSO ELF image.
 struct Vdso {
-	/// The list of pages on which the image is loaded.
+	/// The list of pages to be mapped: the page containing the clocks' data, followed by the
+	/// pages on which the image is loaded.
 	pages: Arc<Vec<NonNull<[u8; memory::PAGE_SIZE]>>>,
 	/// The length of the ELF image in bytes.
 	len: usize,
@@ -50,9 +52,12 @@ fn load_image() -> Result<Vdso, Errno> {
 	let parser = ELFParser::new(ELF_IMAGE)?;
 	let entry_off = parser.get_header().e_entry as _;
 
+	// The vDSO reads clocks from the page right before the image
+	let mut pages = Vec::new();
+	pages.push(clock::data_page())?;
+
 	// Load image into pages
 	// TODO collect
-	let mut pages = Vec::new();
 	for i in 0..math::ceil_div(ELF_IMAGE.len(), memory::PAGE_SIZE) {
 		// Alloc page
 		let mut ptr = buddy::alloc(0, buddy::FLAG_ZONE_TYPE_KERNEL)?;
@@ -89,18 +94,20 @@ pub fn map(mem_space: &mut MemSpace) -> Result<MappedVDSO, Errno> {
 	let img = elf_image.as_ref().unwrap();
 
 	let vdso_pages = math::ceil_div(img.len, memory::PAGE_SIZE);
-	let Some(vdso_pages) = NonZeroUsize::new(vdso_pages) else {
+	if vdso_pages == 0 {
 		panic!("Invalid vDSO image");
-	};
+	}
 	// TODO ASLR
 	let ptr = mem_space.map(
 		MapConstraint::None,
-		vdso_pages,
+		NonZeroUsize::new(1 + vdso_pages).unwrap(),
 		mem_space::MAPPING_FLAG_USER,
 		MapResidence::Static {
 			pages: img.pages.clone(),
 		},
 	)?;
+	// Skip the clocks' data
+	let ptr = unsafe { ptr.add(memory::PAGE_SIZE) };
 
 	let entry = NonNull::new(unsafe { ptr.add(img.entry_off) }).unwrap();
 
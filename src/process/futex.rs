@@ -0,0 +1,726 @@
+//! A futex (fast userspace mutex) is a 32 bits integer in userspace memory on which processes can
+//! sleep until another process wakes them up.
+//!
+//! Userspace performs uncontended operations on the integer atomically, without entering the
+//! kernel. The kernel is called only to sleep when the futex is contended, or to wake up sleeping
+//! processes.
+//!
+//! Priority inheritance futexes are locks whose integer holds the TID of the owner. While a
+//! process waits on such a futex, the owner inherits its realtime priority, so that a lower
+//! priority process cannot delay a higher priority one indefinitely by holding the lock.
+
+use crate::errno;
+use crate::errno::AllocResult;
+use crate::errno::EResult;
+use crate::memory;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::mem_space::MemSpace;
+use crate::process::mem_space::MAPPING_FLAG_SHARED;
+use crate::process::pid::Pid;
+use crate::process::scheduler;
+use crate::process::Process;
+use crate::process::State;
+use crate::time::clock;
+use crate::time::clock::CLOCK_MONOTONIC;
+use crate::time::timer;
+use crate::time::unit::Timestamp;
+use crate::time::unit::TimestampScale;
+use crate::util::container::hashmap::HashMap;
+use crate::util::container::vec::Vec;
+use crate::util::lock::IntMutex;
+use crate::util::ptr::arc::Arc;
+use core::ffi::c_void;
+
+/// Bitset matching every waiter.
+pub const BITSET_MATCH_ANY: u32 = !0;
+
+/// Flag of a priority inheritance futex integer: processes are waiting on the futex.
+pub const FUTEX_WAITERS: u32 = 0x80000000;
+/// Flag of a priority inheritance futex integer: the previous owner exited without releasing the
+/// futex.
+pub const FUTEX_OWNER_DIED: u32 = 0x40000000;
+/// Mask of the TID of the owner in a priority inheritance futex integer.
+pub const FUTEX_TID_MASK: u32 = 0x3fffffff;
+
+/// The maximum length of a chain of processes waiting on priority inheritance futexes owned by
+/// each other. This bounds the time spent propagating priorities.
+const PI_CHAIN_MAX: usize = 1024;
+
+/// The identifier of a futex.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum FutexKey {
+	/// A futex that is not shared with other memory spaces.
+	Private {
+		/// The address of the memory space containing the futex.
+		mem_space: usize,
+		/// The virtual address of the futex.
+		addr: usize,
+	},
+	/// A futex in a shared memory mapping, which may be mapped at different addresses in
+	/// several memory spaces.
+	Shared {
+		/// The physical address of the futex.
+		addr: usize,
+	},
+}
+
+impl FutexKey {
+	/// Returns the key of the futex at address `addr` in the memory space `mem_space`.
+	///
+	/// If `private` is not set and the futex lies in a shared mapping, the futex is identified by
+	/// its physical address so that it matches across memory spaces. The page is allocated if it
+	/// was not yet.
+	///
+	/// If the address is not aligned, the function returns [`errno::EINVAL`].
+	pub fn new(mem_space: &Arc<IntMutex<MemSpace>>, addr: usize, private: bool) -> EResult<Self> {
+		if addr % 4 != 0 {
+			return Err(errno!(EINVAL));
+		}
+		if !private {
+			let mut mem_space_guard = mem_space.lock();
+			let ptr = addr as *const c_void;
+			let page = mem_space_guard
+				.get_mapping_mut_for(ptr)
+				.filter(|mapping| mapping.get_flags() & MAPPING_FLAG_SHARED != 0)
+				.map(|mapping| (addr - mapping.get_begin() as usize) / memory::PAGE_SIZE);
+			if let Some(page) = page {
+				mem_space_guard.alloc(ptr as *const u32, 1)?;
+				let phys_addr = mem_space_guard
+					.get_mapping_mut_for(ptr)
+					.and_then(|mapping| mapping.get_physical_page(page))
+					.ok_or_else(|| errno!(EFAULT))?;
+				return Ok(Self::Shared {
+					addr: phys_addr as usize + addr % memory::PAGE_SIZE,
+				});
+			}
+		}
+		Ok(Self::Private {
+			mem_space: mem_space.as_ptr() as usize,
+			addr,
+		})
+	}
+}
+
+/// A process waiting on a futex.
+#[derive(Clone, Copy, Debug)]
+struct Waiter {
+	/// The PID of the process.
+	pid: Pid,
+	/// The bitset given by the process. Only wake ups with a bitset matching it wake the process.
+	bitset: u32,
+}
+
+/// The state of a priority inheritance futex on which processes are waiting.
+struct PiState {
+	/// The PID of the process owning the futex.
+	owner: Pid,
+	/// The PIDs of the processes waiting to acquire the futex, in order of arrival.
+	waiters: Vec<Pid>,
+	/// Tells whether the futex has been handed over to the owner because the previous owner
+	/// exited. In this case, the owner has to update the integer itself.
+	owner_died: bool,
+}
+
+/// Returns the realtime priority of the process with PID `pid`. Zero if the process does not
+/// exist.
+fn get_priority(pid: Pid) -> u32 {
+	Process::get_by_pid(pid)
+		.map(|proc| proc.lock().get_rt_priority())
+		.unwrap_or(0)
+}
+
+/// The table of processes waiting on futexes.
+struct FutexTable {
+	/// The processes waiting on each futex, in order of arrival.
+	queues: HashMap<FutexKey, Vec<Waiter>>,
+	/// The futex each waiting process is waiting on.
+	waiting: HashMap<Pid, FutexKey>,
+
+	/// The state of each priority inheritance futex on which processes are waiting.
+	pi_states: HashMap<FutexKey, PiState>,
+	/// The priority inheritance futex each waiting process is waiting to acquire.
+	pi_waiting: HashMap<Pid, FutexKey>,
+}
+
+impl FutexTable {
+	/// Adds the waiter `waiter` to the queue of the futex `key`.
+	fn enqueue(&mut self, key: FutexKey, waiter: Waiter) -> AllocResult<()> {
+		let pid = waiter.pid;
+		match self.queues.get_mut(&key) {
+			Some(queue) => queue.push(waiter)?,
+			None => {
+				self.queues.insert(key, crate::vec![waiter]?)?;
+			}
+		}
+		if let Err(e) = self.waiting.insert(pid, key) {
+			self.remove(pid);
+			return Err(e);
+		}
+		Ok(())
+	}
+
+	/// Removes the process with PID `pid` from the queue it is waiting on.
+	///
+	/// If the process is not waiting, the function returns `false`.
+	fn remove(&mut self, pid: Pid) -> bool {
+		let Some(key) = self.waiting.remove(&pid) else {
+			return false;
+		};
+		if let Some(queue) = self.queues.get_mut(&key) {
+			queue.retain(|w| w.pid != pid);
+			if queue.is_empty() {
+				self.queues.remove(&key);
+			}
+		}
+		true
+	}
+
+	/// Removes at most `count` processes whose bitset matches `bitset` from the queue of the
+	/// futex `key`, in order of arrival.
+	///
+	/// The function returns the PIDs of the removed processes.
+	fn dequeue(&mut self, key: FutexKey, count: usize, bitset: u32) -> AllocResult<Vec<Pid>> {
+		let mut pids = Vec::new();
+		let Some(queue) = self.queues.get_mut(&key) else {
+			return Ok(pids);
+		};
+		let mut i = 0;
+		while i < queue.len() && pids.len() < count {
+			if queue[i].bitset & bitset == 0 {
+				i += 1;
+				continue;
+			}
+			pids.push(queue[i].pid)?;
+			queue.remove(i);
+		}
+		if queue.is_empty() {
+			self.queues.remove(&key);
+		}
+		for pid in pids.iter() {
+			self.waiting.remove(pid);
+		}
+		Ok(pids)
+	}
+
+	/// Adds the process with PID `pid` to the waiters of the priority inheritance futex `key`,
+	/// owned by the process with PID `owner`.
+	fn enqueue_pi(&mut self, key: FutexKey, pid: Pid, owner: Pid) -> AllocResult<()> {
+		match self.pi_states.get_mut(&key) {
+			Some(state) => state.waiters.push(pid)?,
+			None => {
+				let state = PiState {
+					owner,
+					waiters: crate::vec![pid]?,
+					owner_died: false,
+				};
+				self.pi_states.insert(key, state)?;
+			}
+		}
+		if let Err(e) = self.pi_waiting.insert(pid, key) {
+			self.remove_pi(pid);
+			return Err(e);
+		}
+		Ok(())
+	}
+
+	/// Removes the process with PID `pid` from the waiters of the priority inheritance futex it is
+	/// waiting on.
+	///
+	/// The function returns the PID of the owner of the futex, whose inherited priority has to be
+	/// updated. If the process is not waiting, the function returns `None`.
+	fn remove_pi(&mut self, pid: Pid) -> Option<Pid> {
+		let key = self.pi_waiting.remove(&pid)?;
+		let state = self.pi_states.get_mut(&key)?;
+		state.waiters.retain(|w| *w != pid);
+		let owner = state.owner;
+		if state.waiters.is_empty() && !state.owner_died {
+			self.pi_states.remove(&key);
+		}
+		Some(owner)
+	}
+
+	/// Hands the priority inheritance futex `key` over to the waiter with the highest priority.
+	/// Among waiters with the same priority, the first to arrive is chosen.
+	///
+	/// `owner_died` tells whether the previous owner exited without releasing the futex.
+	///
+	/// The function returns the PID of the new owner. If no process is waiting, the function
+	/// returns `None`.
+	fn grant_pi(&mut self, key: FutexKey, owner_died: bool) -> Option<Pid> {
+		let state = self.pi_states.get_mut(&key)?;
+		// `max_by_key` returns the last maximum, hence the reversal
+		let (i, _) = state
+			.waiters
+			.iter()
+			.enumerate()
+			.rev()
+			.max_by_key(|(_, pid)| get_priority(**pid))?;
+		let pid = state.waiters.remove(i);
+		state.owner = pid;
+		state.owner_died = owner_died;
+		if state.waiters.is_empty() && !owner_died {
+			self.pi_states.remove(&key);
+		}
+		self.pi_waiting.remove(&pid);
+		Some(pid)
+	}
+
+	/// Tells whether processes are waiting on the priority inheritance futex `key`.
+	fn has_pi_waiters(&self, key: &FutexKey) -> bool {
+		self.pi_states
+			.get(key)
+			.is_some_and(|state| !state.waiters.is_empty())
+	}
+
+	/// Returns the PID of the owner of the priority inheritance futex the process with PID `pid`
+	/// is waiting on, if any.
+	fn get_blocking_owner(&self, pid: Pid) -> Option<Pid> {
+		let key = self.pi_waiting.get(&pid)?;
+		Some(self.pi_states.get(key)?.owner)
+	}
+
+	/// Tells whether the process with PID `pid` waiting on a futex owned by the process with PID
+	/// `owner` would wait on itself through the chain of owners.
+	///
+	/// A chain longer than [`PI_CHAIN_MAX`] is considered a deadlock.
+	fn would_deadlock(&self, pid: Pid, mut owner: Pid) -> bool {
+		for _ in 0..PI_CHAIN_MAX {
+			if owner == pid {
+				return true;
+			}
+			match self.get_blocking_owner(owner) {
+				Some(next) => owner = next,
+				None => return false,
+			}
+		}
+		true
+	}
+
+	/// Updates the priority the process with PID `owner` inherits from the processes waiting on
+	/// the futexes it owns, then propagates the change along the chain of owners.
+	///
+	/// The function locks the processes of the chain. Thus, none of them may be locked by the
+	/// caller.
+	fn update_inherited(&self, mut owner: Pid) {
+		for _ in 0..PI_CHAIN_MAX {
+			let priority = self
+				.pi_states
+				.iter()
+				.filter(|(_, state)| state.owner == owner)
+				.flat_map(|(_, state)| state.waiters.iter())
+				.map(|pid| get_priority(*pid))
+				.max()
+				.unwrap_or(0);
+			let Some(proc_mutex) = Process::get_by_pid(owner) else {
+				return;
+			};
+			{
+				let mut proc = proc_mutex.lock();
+				// If the priority does not change, the rest of the chain is not affected
+				if proc.pi_priority == priority {
+					return;
+				}
+				proc.pi_priority = priority;
+			}
+			let Some(next) = self.get_blocking_owner(owner) else {
+				return;
+			};
+			owner = next;
+		}
+	}
+}
+
+/// The table of processes waiting on futexes.
+static FUTEXES: IntMutex<FutexTable> = IntMutex::new(FutexTable {
+	queues: HashMap::new(),
+	waiting: HashMap::new(),
+
+	pi_states: HashMap::new(),
+	pi_waiting: HashMap::new(),
+});
+
+/// Wakes up the processes with the given PIDs.
+fn wake_processes(pids: &[Pid]) {
+	for pid in pids {
+		if let Some(proc_mutex) = Process::get_by_pid(*pid) {
+			proc_mutex.lock().wake();
+		}
+	}
+}
+
+/// Makes the current process sleep on the futex `key` at address `uaddr`, if it has the value
+/// `val`. Otherwise, the function returns [`errno::EAGAIN`].
+///
+/// Arguments:
+/// - `bitset` is the mask of wake ups the process responds to. It must not be zero.
+/// - `deadline` is the timestamp on the monotonic clock, in nanoseconds, at which the function
+/// fails with [`errno::ETIMEDOUT`]. If `None`, the process sleeps until woken up.
+///
+/// If the process is interrupted by a signal, the function fails with [`errno::ERESTARTSYS`], or
+/// [`errno::EINTR`] if a deadline is set since restarting would extend the timeout.
+pub fn wait(
+	key: FutexKey,
+	uaddr: SyscallPtr<u32>,
+	val: u32,
+	bitset: u32,
+	deadline: Option<Timestamp>,
+) -> EResult<()> {
+	if bitset == 0 {
+		return Err(errno!(EINVAL));
+	}
+
+	let proc_mutex = Process::current_assert();
+	let pid = proc_mutex.lock().pid;
+	if let Some(deadline) = deadline {
+		timer::wake_at(pid, deadline)?;
+	}
+	let res = wait_impl(&proc_mutex, key, uaddr, val, bitset, deadline);
+	if let Some(deadline) = deadline {
+		timer::cancel_wake(pid, deadline);
+	}
+	res
+}
+
+/// Implementation of [`wait`], once the wake up at the deadline has been scheduled.
+fn wait_impl(
+	proc_mutex: &IntMutex<Process>,
+	key: FutexKey,
+	uaddr: SyscallPtr<u32>,
+	val: u32,
+	bitset: u32,
+	deadline: Option<Timestamp>,
+) -> EResult<()> {
+	{
+		let mut proc = proc_mutex.lock();
+		let pid = proc.pid;
+
+		let mem_space = proc.get_mem_space().unwrap().clone();
+		let mem_space_guard = mem_space.lock();
+		// The value is checked with the table locked so that a wake up cannot be missed
+		let mut futexes = FUTEXES.lock();
+		let curr = *uaddr.get(&mem_space_guard)?.ok_or_else(|| errno!(EFAULT))?;
+		if curr != val {
+			return Err(errno!(EAGAIN));
+		}
+		futexes.enqueue(
+			key,
+			Waiter {
+				pid,
+				bitset,
+			},
+		)?;
+		drop(futexes);
+		drop(mem_space_guard);
+
+		proc.set_state(State::Sleeping);
+	}
+
+	loop {
+		scheduler::end_tick();
+
+		let mut proc = proc_mutex.lock();
+		let mut futexes = FUTEXES.lock();
+		// If woken up by `wake`, the process is not waiting anymore
+		if futexes.waiting.get(&proc.pid).is_none() {
+			return Ok(());
+		}
+
+		let now = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Nanosecond)?;
+		if deadline.is_some_and(|deadline| now >= deadline) {
+			futexes.remove(proc.pid);
+			return Err(errno!(ETIMEDOUT));
+		}
+		if proc.get_next_signal().is_some() {
+			futexes.remove(proc.pid);
+			if deadline.is_some() {
+				return Err(errno!(EINTR));
+			}
+			return Err(errno!(ERESTARTSYS));
+		}
+
+		// Spurious wake up. Sleeping again, with the table locked so that a wake up cannot be
+		// missed
+		proc.set_state(State::Sleeping);
+	}
+}
+
+/// Releases the futexes held by the process with PID `pid`, which is exiting.
+///
+/// The process is removed from the queue of the futex it is waiting on, so that it is not counted
+/// by later wake ups. The priority inheritance futexes it owns are handed over to their waiters,
+/// which see [`FUTEX_OWNER_DIED`] in the integer.
+///
+/// The process itself may be locked by the caller.
+pub fn exit(pid: Pid) {
+	let mut futexes = FUTEXES.lock();
+	futexes.remove(pid);
+	if let Some(owner) = futexes.remove_pi(pid) {
+		futexes.update_inherited(owner);
+	}
+	loop {
+		let key = futexes
+			.pi_states
+			.iter()
+			.find(|(_, state)| state.owner == pid)
+			.map(|(key, _)| *key);
+		let Some(key) = key else {
+			break;
+		};
+		match futexes.grant_pi(key, true) {
+			Some(new_owner) => {
+				futexes.update_inherited(new_owner);
+				wake_processes(&[new_owner]);
+			}
+			// The process exited before updating the integer of a futex handed over to it
+			None => {
+				futexes.pi_states.remove(&key);
+			}
+		}
+	}
+}
+
+/// Acquires the priority inheritance futex `key` at address `uaddr` for the current process.
+///
+/// If the futex is owned by another process, the current process sleeps until the futex is handed
+/// over to it. Meanwhile, the owner inherits the priority of the current process.
+///
+/// Arguments:
+/// - `deadline` is the timestamp on the monotonic clock, in nanoseconds, at which the function
+/// fails with [`errno::ETIMEDOUT`]. If `None`, the process sleeps until the futex is acquired.
+/// - `try_lock` tells whether the function fails with [`errno::EAGAIN`] instead of sleeping.
+///
+/// If the futex is already owned by the current process, or if waiting would deadlock, the
+/// function fails with [`errno::EDEADLK`]. If the owner does not exist, the function fails with
+/// [`errno::ESRCH`].
+pub fn lock_pi(
+	key: FutexKey,
+	uaddr: SyscallPtr<u32>,
+	deadline: Option<Timestamp>,
+	try_lock: bool,
+) -> EResult<()> {
+	let proc_mutex = Process::current_assert();
+	let pid = proc_mutex.lock().pid;
+	if let Some(deadline) = deadline {
+		timer::wake_at(pid, deadline)?;
+	}
+	let res = lock_pi_impl(&proc_mutex, key, uaddr, deadline, try_lock);
+	if let Some(deadline) = deadline {
+		timer::cancel_wake(pid, deadline);
+	}
+	res
+}
+
+/// Implementation of [`lock_pi`], once the wake up at the deadline has been scheduled.
+fn lock_pi_impl(
+	proc_mutex: &IntMutex<Process>,
+	key: FutexKey,
+	uaddr: SyscallPtr<u32>,
+	deadline: Option<Timestamp>,
+	try_lock: bool,
+) -> EResult<()> {
+	let owner = {
+		let mut proc = proc_mutex.lock();
+		let pid = proc.pid;
+
+		let mem_space = proc.get_mem_space().unwrap().clone();
+		let mut mem_space_guard = mem_space.lock();
+		// The integer is checked with the table locked so that a release cannot be missed
+		let mut futexes = FUTEXES.lock();
+		let word = uaddr
+			.get_mut(&mut mem_space_guard)?
+			.ok_or_else(|| errno!(EFAULT))?;
+		let owner = *word & FUTEX_TID_MASK;
+		if owner == 0 {
+			*word = pid as u32;
+			return Ok(());
+		}
+		if owner == pid as u32 {
+			return Err(errno!(EDEADLK));
+		}
+		if try_lock {
+			return Err(errno!(EAGAIN));
+		}
+		let owner = Pid::try_from(owner)
+			.ok()
+			.filter(|owner| Process::get_by_pid(*owner).is_some())
+			.ok_or_else(|| errno!(ESRCH))?;
+		if futexes.would_deadlock(pid, owner) {
+			return Err(errno!(EDEADLK));
+		}
+		futexes.enqueue_pi(key, pid, owner)?;
+		*word |= FUTEX_WAITERS;
+		drop(futexes);
+		drop(mem_space_guard);
+
+		proc.set_state(State::Sleeping);
+		owner
+	};
+	// The owner inherits the priority of the current process
+	FUTEXES.lock().update_inherited(owner);
+
+	loop {
+		scheduler::end_tick();
+
+		let mut proc = proc_mutex.lock();
+		let pid = proc.pid;
+		let mut futexes = FUTEXES.lock();
+		// If the futex has been handed over, the process is not waiting anymore
+		if futexes.pi_waiting.get(&pid).is_none() {
+			let Some(state) = futexes.pi_states.get_mut(&key) else {
+				return Ok(());
+			};
+			if state.owner != pid || !state.owner_died {
+				return Ok(());
+			}
+			// The previous owner exited and could not update the integer
+			state.owner_died = false;
+			let waiters = !state.waiters.is_empty();
+			if !waiters {
+				futexes.pi_states.remove(&key);
+			}
+			drop(futexes);
+			let mem_space = proc.get_mem_space().unwrap().clone();
+			let mut mem_space_guard = mem_space.lock();
+			let word = uaddr
+				.get_mut(&mut mem_space_guard)?
+				.ok_or_else(|| errno!(EFAULT))?;
+			*word = pid as u32 | FUTEX_OWNER_DIED;
+			if waiters {
+				*word |= FUTEX_WAITERS;
+			}
+			return Ok(());
+		}
+
+		let now = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Nanosecond)?;
+		let timeout = deadline.is_some_and(|deadline| now >= deadline);
+		if timeout || proc.get_next_signal().is_some() {
+			let owner = futexes.remove_pi(pid);
+			drop(proc);
+			// The owner does not inherit the priority of the current process anymore
+			if let Some(owner) = owner {
+				futexes.update_inherited(owner);
+			}
+			if timeout {
+				return Err(errno!(ETIMEDOUT));
+			}
+			return Err(errno!(ERESTARTNOINTR));
+		}
+
+		// Spurious wake up. Sleeping again, with the table locked so that a hand over cannot be
+		// missed
+		proc.set_state(State::Sleeping);
+	}
+}
+
+/// Releases the priority inheritance futex `key` at address `uaddr`, owned by the current
+/// process.
+///
+/// The futex is handed over to the waiter with the highest priority, if any.
+///
+/// If the futex is not owned by the current process, the function fails with [`errno::EPERM`].
+pub fn unlock_pi(key: FutexKey, uaddr: SyscallPtr<u32>) -> EResult<()> {
+	let proc_mutex = Process::current_assert();
+	let (pid, new_owner) = {
+		let proc = proc_mutex.lock();
+		let pid = proc.pid;
+
+		let mem_space = proc.get_mem_space().unwrap().clone();
+		let mut mem_space_guard = mem_space.lock();
+		let mut futexes = FUTEXES.lock();
+		let word = uaddr
+			.get_mut(&mut mem_space_guard)?
+			.ok_or_else(|| errno!(EFAULT))?;
+		if *word & FUTEX_TID_MASK != pid as u32 {
+			return Err(errno!(EPERM));
+		}
+		let new_owner = futexes.grant_pi(key, false);
+		*word = match new_owner {
+			Some(new_owner) if futexes.has_pi_waiters(&key) => new_owner as u32 | FUTEX_WAITERS,
+			Some(new_owner) => new_owner as u32,
+			None => 0,
+		};
+		(pid, new_owner)
+	};
+
+	let futexes = FUTEXES.lock();
+	// The current process does not inherit the priority of the waiters of the futex anymore
+	futexes.update_inherited(pid);
+	if let Some(new_owner) = new_owner {
+		futexes.update_inherited(new_owner);
+		drop(futexes);
+		wake_processes(&[new_owner]);
+	}
+	Ok(())
+}
+
+/// Propagates a change of priority of the process with PID `pid` to the owner of the priority
+/// inheritance futex it is waiting on, if any.
+///
+/// The process must not be locked by the caller.
+pub fn priority_changed(pid: Pid) {
+	let futexes = FUTEXES.lock();
+	if let Some(owner) = futexes.get_blocking_owner(pid) {
+		futexes.update_inherited(owner);
+	}
+}
+
+/// Wakes up at most `count` processes waiting on the futex `key` whose bitset matches `bitset`.
+///
+/// The function returns the number of processes woken up.
+pub fn wake(key: FutexKey, count: usize, bitset: u32) -> EResult<usize> {
+	if bitset == 0 {
+		return Err(errno!(EINVAL));
+	}
+	let pids = FUTEXES.lock().dequeue(key, count, bitset)?;
+	wake_processes(&pids);
+	Ok(pids.len())
+}
+
+/// Wakes up at most `wake_count` processes waiting on the futex `key`, then moves at most
+/// `requeue_count` of the remaining processes to the queue of the futex `key2`.
+///
+/// If `cmp` is set, the operation is performed only if the futex at address `uaddr` has this
+/// value. Otherwise, the function returns [`errno::EAGAIN`].
+///
+/// The function returns the number of processes woken up or moved.
+pub fn requeue(
+	key: FutexKey,
+	uaddr: SyscallPtr<u32>,
+	key2: FutexKey,
+	wake_count: usize,
+	requeue_count: usize,
+	cmp: Option<u32>,
+) -> EResult<usize> {
+	let (woken, moved) = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+		let mem_space = proc.get_mem_space().unwrap().clone();
+		let mem_space_guard = mem_space.lock();
+
+		let mut futexes = FUTEXES.lock();
+		if let Some(cmp) = cmp {
+			let curr = *uaddr.get(&mem_space_guard)?.ok_or_else(|| errno!(EFAULT))?;
+			if curr != cmp {
+				return Err(errno!(EAGAIN));
+			}
+		}
+		let woken = futexes.dequeue(key, wake_count, BITSET_MATCH_ANY)?;
+		let moved = futexes.dequeue(key, requeue_count, BITSET_MATCH_ANY)?;
+		for (i, pid) in moved.iter().enumerate() {
+			let waiter = Waiter {
+				pid: *pid,
+				bitset: BITSET_MATCH_ANY,
+			};
+			if let Err(e) = futexes.enqueue(key2, waiter) {
+				// Wake up the processes that cannot be moved
+				drop(futexes);
+				wake_processes(&woken);
+				wake_processes(&moved[i..]);
+				return Err(e.into());
+			}
+		}
+		(woken, moved.len())
+	};
+	wake_processes(&woken);
+	Ok(woken.len() + moved)
+}
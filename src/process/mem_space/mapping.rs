@@ -122,6 +122,11 @@ impl MemMapping {
 		self.flags
 	}
 
+	/// Returns the residence of the mapping.
+	pub fn get_residence(&self) -> &MapResidence {
+		&self.residence
+	}
+
 	/// Returns a reference to the virtual memory context handler associated
 	/// with the mapping.
 	pub fn get_vmem(&self) -> &Arc<dyn VMem> {
@@ -485,6 +490,7 @@ impl MemMapping {
 		let MapResidence::File {
 			location,
 			off,
+			..
 		} = &self.residence
 		else {
 			return Ok(());
@@ -9,8 +9,11 @@ mod gap;
 mod mapping;
 pub mod ptr;
 
+use crate::errno;
 use crate::errno::AllocError;
+use crate::errno::EResult;
 use crate::errno::Errno;
+use crate::file::path::Path;
 use crate::file::perm::AccessProfile;
 use crate::file::FileLocation;
 use crate::idt;
@@ -24,11 +27,15 @@ use crate::process::oom;
 use crate::process::open_file::OpenFile;
 use crate::process::AllocResult;
 use crate::util;
+use crate::util::container::hashmap::HashMap;
 use crate::util::container::map::Map;
 use crate::util::container::vec::Vec;
+use crate::util::io::IO;
+use crate::util::lock::IntMutex;
 use crate::util::lock::Mutex;
 use crate::util::math;
 use crate::util::ptr::arc::Arc;
+use crate::util::ptr::arc::Weak;
 use crate::util::TryClone;
 use core::cmp::min;
 use core::cmp::Ordering;
@@ -36,6 +43,8 @@ use core::ffi::c_void;
 use core::fmt;
 use core::mem::size_of;
 use core::num::NonZeroUsize;
+use core::ops::Range;
+use core::ptr::copy_nonoverlapping;
 use core::ptr::null_mut;
 use core::ptr::NonNull;
 use gap::MemGap;
@@ -62,6 +71,56 @@ pub static PHYSICAL_REF_COUNTER: Mutex<PhysRefCounter> = Mutex::new(PhysRefCount
 
 // TODO when reaching the last reference to the open file, close it on unmap
 
+/// The sizes of the files mapped in memory, by location.
+///
+/// Mappings share the size of their file so that page faults can check it without locking the
+/// file, which the kernel may be holding while faulting on a userspace buffer. The file updates
+/// the size each time it changes.
+static MAPPED_FILE_SIZES: Mutex<HashMap<FileLocation, Weak<IntMutex<u64>>>> =
+	Mutex::new(HashMap::new());
+
+/// Returns the size shared by the mappings of the file at `location`.
+///
+/// If the file is not mapped yet, the size is initialized with `size`.
+pub fn get_mapped_file_size(
+	location: &FileLocation,
+	size: u64,
+) -> AllocResult<Arc<IntMutex<u64>>> {
+	let mut sizes = MAPPED_FILE_SIZES.lock();
+	if let Some(shared) = sizes.get(location).and_then(Weak::upgrade) {
+		return Ok(shared);
+	}
+	let shared = Arc::new(IntMutex::new(size))?;
+	sizes.insert(location.clone(), Arc::downgrade(&shared))?;
+	Ok(shared)
+}
+
+/// Sets the size of the file at `location` for its mappings, if any.
+pub fn update_mapped_file_size(location: &FileLocation, size: u64) {
+	let mut sizes = MAPPED_FILE_SIZES.lock();
+	let Some(shared) = sizes.get(location) else {
+		return;
+	};
+	match shared.upgrade() {
+		Some(shared) => *shared.lock() = size,
+		// The file is not mapped anymore
+		None => {
+			sizes.remove(location);
+		}
+	}
+}
+
+/// The reason why a page fault cannot be resolved.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PageFaultError {
+	/// No mapping contains the address.
+	Unmapped,
+	/// The access is not permitted by the mapping.
+	Access,
+	/// The address is beyond the end of the file the mapping points to.
+	BeyondEof,
+}
+
 // TODO Disallow clone and use a special function + Drop to increment/decrement reference counters
 /// Enumeration of map residences.
 ///
@@ -82,8 +141,13 @@ pub enum MapResidence {
 	File {
 		/// The location of the file.
 		location: FileLocation,
+		/// The path of the file at the time it was mapped.
+		path: Arc<Path>,
 		/// The offset of the mapping in the file.
 		off: u64,
+		/// The size of the file, shared with the other mappings of the file. See
+		/// [`get_mapped_file_size`].
+		size: Arc<IntMutex<u64>>,
 	},
 
 	/// The mapping resides in swap space.
@@ -103,6 +167,20 @@ impl MapResidence {
 		matches!(self, MapResidence::Normal)
 	}
 
+	/// Tells whether the page at offset `off` is entirely beyond the end of the file the
+	/// residence points to, if applicable.
+	pub fn is_beyond_eof(&self, off: usize) -> bool {
+		match self {
+			MapResidence::File {
+				off: file_off,
+				size,
+				..
+			} => file_off + (off * memory::PAGE_SIZE) as u64 >= *size.lock(),
+
+			_ => false,
+		}
+	}
+
 	/// Adds a value of `pages` pages to the offset of the residence, if applicable.
 	pub fn offset_add(&mut self, pages: usize) {
 		match self {
@@ -160,8 +238,7 @@ impl MapResidence {
 			}
 
 			MapResidence::File {
-				location: _,
-				off: _,
+				..
 			} => {
 				// TODO get physical page for this offset
 				todo!();
@@ -190,8 +267,7 @@ impl MapResidence {
 			}
 
 			MapResidence::File {
-				location: _,
-				off: _,
+				..
 			} => {
 				// TODO
 				todo!();
@@ -248,6 +324,11 @@ pub struct MemSpace {
 	/// The current pointer of the `brk` system call.
 	brk_ptr: *mut c_void,
 
+	/// The range of addresses storing the command line arguments of the program, set at exec.
+	args: Range<usize>,
+	/// The range of addresses storing the environment variables of the program, set at exec.
+	env: Range<usize>,
+
 	/// The virtual memory context handler.
 	vmem: Arc<dyn VMem>,
 }
@@ -346,6 +427,9 @@ impl MemSpace {
 			brk_init: null_mut::<_>(),
 			brk_ptr: null_mut::<_>(),
 
+			args: 0..0,
+			env: 0..0,
+
 			vmem: Arc::try_from(vmem::new()?)?,
 		};
 
@@ -755,6 +839,9 @@ impl MemSpace {
 			brk_init: self.brk_init,
 			brk_ptr: self.brk_ptr,
 
+			args: self.args.clone(),
+			env: self.env.clone(),
+
 			vmem: Arc::try_from(vmem::try_clone(&*self.vmem)?)?,
 		};
 		for (_, m) in self.mappings.iter_mut() {
@@ -769,6 +856,55 @@ impl MemSpace {
 		Ok(mem_space)
 	}
 
+	/// Copies `len` bytes of memory at `ptr` into a new buffer, reading through the virtual memory
+	/// context of the memory space, which does not need to be the current one.
+	///
+	/// Only pages that are already present are read, so that no page fault may occur while the
+	/// memory space is not the current one. If the memory cannot be read, the function returns
+	/// [`errno::EFAULT`].
+	pub fn read_remote(&self, ptr: *const u8, len: usize) -> EResult<Vec<u8>> {
+		if !self.can_access(ptr, len, true, false) {
+			return Err(errno!(EFAULT));
+		}
+		let begin = util::down_align(ptr as _, memory::PAGE_SIZE) as usize;
+		let end = ptr as usize + len;
+		let present = (begin..end)
+			.step_by(memory::PAGE_SIZE)
+			.all(|page| self.vmem.translate(page as _).is_some());
+		if !present {
+			return Err(errno!(EFAULT));
+		}
+
+		let mut buf = crate::vec![0u8; len]?;
+		let dst = buf.as_mut_slice().as_mut_ptr();
+		let vmem = &*self.vmem;
+		// The kernel stack is not reachable from another memory space, so an alternate stack is
+		// used. Values are moved to avoid referring to the previous stack
+		idt::wrap_disable_interrupts(|| unsafe {
+			stack::switch(None, move || {
+				vmem::switch(vmem, move || copy_nonoverlapping(ptr, dst, len));
+			})
+		})?;
+		Ok(buf)
+	}
+
+	/// Sets the ranges of addresses storing the command line arguments `args` and the environment
+	/// variables `env` of the program.
+	pub fn set_exec_info(&mut self, args: Range<usize>, env: Range<usize>) {
+		self.args = args;
+		self.env = env;
+	}
+
+	/// Returns the range of addresses storing the command line arguments of the program.
+	pub fn get_args(&self) -> Range<usize> {
+		self.args.clone()
+	}
+
+	/// Returns the range of addresses storing the environment variables of the program.
+	pub fn get_env(&self) -> Range<usize> {
+		self.env.clone()
+	}
+
 	/// Clones the current memory space for process forking.
 	pub fn fork(&mut self) -> AllocResult<MemSpace> {
 		idt::wrap_disable_interrupts(|| unsafe { stack::switch(None, || self.do_fork()) })?
@@ -828,6 +964,16 @@ impl MemSpace {
 		Ok(())
 	}
 
+	/// Returns an iterator over the memory mappings, sorted by address.
+	pub fn iter_mappings(&self) -> impl Iterator<Item = &MemMapping> {
+		self.mappings.iter().map(|(_, m)| m)
+	}
+
+	/// Returns the initial pointer of the `brk` syscall.
+	pub fn get_brk_init(&self) -> *mut c_void {
+		self.brk_init
+	}
+
 	/// Returns the pointer for the `brk` syscall.
 	pub fn get_brk_ptr(&self) -> *mut c_void {
 		self.brk_ptr
@@ -902,33 +1048,39 @@ impl MemSpace {
 	/// - `virt_addr` is the virtual address of the wrong memory access that caused the fault.
 	/// - `code` is the error code given along with the error.
 	///
-	/// If the process should continue, the function returns `true`, else `false`.
-	pub fn handle_page_fault(&mut self, virt_addr: *const c_void, code: u32) -> bool {
-		if code & vmem::x86::PAGE_FAULT_PRESENT == 0 {
-			return false;
-		}
-
+	/// If the process should not continue, the function returns the reason of the fault.
+	pub fn handle_page_fault(
+		&mut self,
+		virt_addr: *const c_void,
+		code: u32,
+	) -> Result<(), PageFaultError> {
 		let Some(mapping) = Self::get_mapping_mut_for_(&mut self.mappings, virt_addr) else {
-			return false;
+			return Err(PageFaultError::Unmapped);
 		};
+		if code & vmem::x86::PAGE_FAULT_PRESENT == 0 {
+			return Err(PageFaultError::Access);
+		}
 
 		let can_write_mapping = mapping.get_flags() & MAPPING_FLAG_WRITE != 0;
 		if code & vmem::x86::PAGE_FAULT_WRITE != 0 && !can_write_mapping {
-			return false;
+			return Err(PageFaultError::Access);
 		}
 
 		// TODO check exec
 
 		let userspace_mapping = mapping.get_flags() & MAPPING_FLAG_USER != 0;
 		if code & vmem::x86::PAGE_FAULT_USER != 0 && !userspace_mapping {
-			return false;
+			return Err(PageFaultError::Access);
 		}
 
 		let page_offset = (virt_addr as usize - mapping.get_begin() as usize) / memory::PAGE_SIZE;
+		if mapping.get_residence().is_beyond_eof(page_offset) {
+			return Err(PageFaultError::BeyondEof);
+		}
 		oom::wrap(|| mapping.map(page_offset));
 
 		mapping.update_vmem(page_offset);
-		true
+		Ok(())
 	}
 }
 
@@ -293,12 +293,7 @@ impl fmt::Debug for SyscallString {
 		let mem_space = mem_space_mutex.lock();
 
 		match self.get(&mem_space) {
-			Ok(Some(s)) => {
-				// TODO Add backslashes to escape `"` and `\`
-
-				let s = DisplayableStr(s);
-				write!(fmt, "{:p} = \"{}\"", self.as_ptr(), s)
-			}
+			Ok(Some(s)) => write!(fmt, "{:p} = {:?}", self.as_ptr(), DisplayableStr(s)),
 
 			Ok(None) => write!(fmt, "NULL"),
 
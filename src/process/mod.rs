@@ -8,6 +8,7 @@
 // TODO When a process receives a signal, log it if the `strace` feature is enabled
 
 pub mod exec;
+pub mod futex;
 pub mod iovec;
 pub mod mem_space;
 pub mod oom;
@@ -18,9 +19,14 @@ pub mod scheduler;
 pub mod signal;
 #[cfg(target_arch = "x86")]
 pub mod tss;
+pub mod ucounts;
 pub mod user_desc;
+pub mod uts;
 
 use crate::cpu;
+use crate::cpu::debug::DebugRegs;
+use crate::cpu::fpu;
+use crate::cpu::fpu::FpuState;
 use crate::errno;
 use crate::errno::AllocResult;
 use crate::errno::EResult;
@@ -31,17 +37,23 @@ use crate::file;
 use crate::file::fd::FileDescriptorTable;
 use crate::file::fd::NewFDConstraint;
 use crate::file::fs::procfs::ProcFS;
+use crate::file::locks;
 use crate::file::mountpoint;
 use crate::file::open_file;
 use crate::file::path::Path;
 use crate::file::perm::AccessProfile;
 use crate::file::perm::ROOT_UID;
+use crate::file::perm::Uid;
 use crate::file::vfs;
 use crate::gdt;
 use crate::memory;
 use crate::process::mountpoint::MountSource;
 use crate::process::open_file::OpenFile;
+use crate::time::clock;
+use crate::time::clock::CLOCK_BOOTTIME;
 use crate::time::timer::TimerManager;
+use crate::time::unit::Timestamp;
+use crate::time::unit::TimestampScale;
 use crate::tty;
 use crate::tty::TTYHandle;
 use crate::util::container::bitfield::Bitfield;
@@ -52,20 +64,28 @@ use crate::util::ptr::arc::Arc;
 use crate::util::ptr::arc::Weak;
 use crate::util::TryClone;
 use core::any::Any;
+use core::cmp::max;
 use core::ffi::c_void;
 use core::mem::size_of;
 use core::mem::ManuallyDrop;
 use core::mem::MaybeUninit;
+use core::ptr::null;
 use core::ptr::NonNull;
 use mem_space::MemSpace;
+use mem_space::PageFaultError;
 use pid::PIDManager;
 use pid::Pid;
 use regs::Regs;
 use rusage::RUsage;
+use scheduler::SchedPolicy;
 use scheduler::Scheduler;
+use signal::PendingSignals;
+use signal::SigInfo;
 use signal::Signal;
 use signal::SignalAction;
 use signal::SignalHandler;
+use ucounts::UCount;
+use uts::UtsNamespace;
 #[cfg(target_arch = "x86")]
 use tss::TSS;
 
@@ -100,6 +120,18 @@ pub const TLS_ENTRIES_COUNT: usize = 3;
 /// The size of the redzone in userspace, in bytes.
 const REDZONE_SIZE: usize = 128;
 
+/// The state of a system call interrupted by a signal, used to resume it with `restart_syscall`.
+#[derive(Clone, Debug)]
+pub enum RestartBlock {
+	/// `nanosleep`.
+	Nanosleep {
+		/// The timestamp of `CLOCK_MONOTONIC` at which sleeping ends, in nanoseconds.
+		end: Timestamp,
+		/// The address at which the remaining time is written if interrupted again.
+		rem: usize,
+	},
+}
+
 /// An enumeration containing possible states for a process.
 #[derive(Eq, Debug, PartialEq)]
 pub enum State {
@@ -150,6 +182,12 @@ pub struct ForkOptions {
 	/// If `true`, the parent and child processes both share the same signal
 	/// handlers table.
 	pub share_sighand: bool,
+	/// If `true`, the child process is a thread in the same thread group as the parent. This
+	/// requires `share_sighand`.
+	pub thread: bool,
+	/// If `true`, the child process is placed in a copy of the parent's UTS namespace instead of
+	/// sharing it.
+	pub new_uts: bool,
 
 	/// If `true`, the parent is paused until the child process exits or executes
 	/// a program.
@@ -167,12 +205,24 @@ impl Default for ForkOptions {
 			share_memory: false,
 			share_fd: false,
 			share_sighand: false,
+			thread: false,
+			new_uts: false,
 
 			vfork: false,
 		}
 	}
 }
 
+/// The state of a thread group, shared between all its threads.
+#[derive(Debug, Default)]
+pub struct ThreadGroup {
+	/// The TIDs of the threads of the group, including the leader, that have not been removed
+	/// from the scheduler yet.
+	threads: Vec<Pid>,
+	/// The number of threads of the group that have not exited yet.
+	live: usize,
+}
+
 /// The vfork operation is similar to the fork operation except the parent
 /// process isn't executed until the child process exits or executes a program.
 ///
@@ -201,6 +251,9 @@ pub struct Process {
 	pub pgid: Pid,
 	/// The thread ID of the process.
 	pub tid: Pid,
+	/// The ID of the thread group, which is the PID seen by userspace. For the leader of the
+	/// group, it is equal to `pid`.
+	pub tgid: Pid,
 
 	/// The argv of the process.
 	pub argv: Arc<Vec<String>>,
@@ -227,6 +280,14 @@ pub struct Process {
 	pub nice: usize,
 	/// The number of quantum run during the cycle.
 	quantum_count: usize,
+	/// The scheduling policy of the process.
+	pub policy: SchedPolicy,
+	/// The realtime priority of the process, from [`scheduler::RT_PRIORITY_MIN`] to
+	/// [`scheduler::RT_PRIORITY_MAX`] for realtime policies. Zero otherwise.
+	pub rt_priority: u32,
+	/// The realtime priority inherited from the processes waiting on the priority inheritance
+	/// futexes owned by the process. Zero if none.
+	pi_priority: u32,
 
 	/// A pointer to the parent process.
 	parent: Option<Weak<IntMutex<Process>>>,
@@ -239,11 +300,28 @@ pub struct Process {
 	pub regs: Regs,
 	/// Tells whether the process was syscalling or not.
 	pub syscalling: bool,
-
+	/// The FPU state of the process.
+	///
+	/// If the process owns the FPU and the FPU is not trapping, the state is the one in the FPU
+	/// instead. See [`Process::fpu_sync`].
+	fpu: FpuState,
+	/// The state of the hardware debug registers of the process.
+	pub debug_regs: DebugRegs,
+	/// The PID of the process tracing this one, if any.
+	pub tracer: Option<Pid>,
+
+	/// The state needed to resume the last system call interrupted by a signal, if any.
+	pub restart_block: Option<RestartBlock>,
+
+	/// Informations about the last fault raised by the process, passed to the handler of the
+	/// signal it triggered.
+	fault_info: Option<SigInfo>,
 	/// Tells whether the process is handling a signal.
 	handled_signal: Option<Signal>,
 	/// The saved state of registers, used when handling a signal.
 	saved_regs: Regs,
+	/// The saved FPU state, used when handling a signal.
+	saved_fpu: FpuState,
 	/// Tells whether the process has information that can be retrieved by
 	/// wait/waitpid.
 	waitable: bool,
@@ -265,11 +343,19 @@ pub struct Process {
 	pub chroot: Arc<Path>,
 	/// The list of open file descriptors with their respective ID.
 	file_descriptors: Option<Arc<Mutex<FileDescriptorTable>>>,
+	/// The UTS namespace of the process.
+	uts: Arc<Mutex<UtsNamespace>>,
 
 	/// A bitfield storing the set of blocked signals.
 	pub sigmask: Bitfield,
-	/// A bitfield storing the set of pending signals.
-	sigpending: Bitfield,
+	/// A bitfield storing the set of pending signals directed to the thread.
+	sigpending: PendingSignals,
+	/// A bitfield storing the set of pending signals directed to the thread group. It is shared
+	/// between all the threads of the group and locked from the scheduler tick, hence disabling
+	/// interrupts.
+	shared_sigpending: Arc<IntMutex<PendingSignals>>,
+	/// The state of the thread group the process belongs to.
+	thread_group: Arc<IntMutex<ThreadGroup>>,
 	/// The list of signal handlers.
 	signal_handlers: Arc<Mutex<[SignalHandler; signal::SIGNALS_COUNT]>>,
 
@@ -285,6 +371,8 @@ pub struct Process {
 
 	/// The process's resources usage.
 	rusage: RUsage,
+	/// The time at which the process was created, on the boottime clock, in nanoseconds.
+	pub start_time: Timestamp,
 
 	/// The exit status of the process after exiting.
 	exit_status: ExitStatus,
@@ -296,6 +384,8 @@ pub struct Process {
 static mut PID_MANAGER: MaybeUninit<Mutex<PIDManager>> = MaybeUninit::uninit();
 /// The processes scheduler.
 static mut SCHEDULER: MaybeUninit<Arc<IntMutex<Scheduler>>> = MaybeUninit::uninit();
+/// The PID of the process whose FPU state is loaded in the FPU, if any.
+static FPU_OWNER: IntMutex<Option<Pid>> = IntMutex::new(None);
 
 /// Initializes processes system. This function must be called only once, at
 /// kernel initialization.
@@ -353,15 +443,17 @@ pub fn init() -> Result<(), Errno> {
 				if inst_prefix == HLT_INSTRUCTION {
 					curr_proc.exit(regs.eax, false);
 				} else {
-					curr_proc.kill(&Signal::SIGSEGV, true);
-					curr_proc.signal_next();
+					curr_proc.kill_fault(SigInfo::fault(
+						&Signal::SIGSEGV,
+						signal::SI_KERNEL,
+						null(),
+					));
 				}
 			}
 
 			// Alignment Check
 			0x11 => {
-				curr_proc.kill(&Signal::SIGBUS, true);
-				curr_proc.signal_next();
+				curr_proc.kill_fault(SigInfo::fault(&Signal::SIGBUS, signal::BUS_ADRALN, null()));
 			}
 
 			_ => {}
@@ -389,20 +481,29 @@ pub fn init() -> Result<(), Errno> {
 		let mut curr_proc = curr_proc.lock();
 
 		// Handle page fault
-		let success = {
+		let result = {
 			let mem_space_mutex = curr_proc.get_mem_space().unwrap();
 			let mut mem_space = mem_space_mutex.lock();
 
 			mem_space.handle_page_fault(accessed_ptr, code)
 		};
 
-		if !success {
+		if let Err(e) = result {
 			if ring < 3 {
 				return CallbackResult::Panic;
-			} else {
-				curr_proc.kill(&Signal::SIGSEGV, true);
-				curr_proc.signal_next();
 			}
+			let info = match e {
+				PageFaultError::Unmapped => {
+					SigInfo::fault(&Signal::SIGSEGV, signal::SEGV_MAPERR, accessed_ptr)
+				}
+				PageFaultError::Access => {
+					SigInfo::fault(&Signal::SIGSEGV, signal::SEGV_ACCERR, accessed_ptr)
+				}
+				PageFaultError::BeyondEof => {
+					SigInfo::fault(&Signal::SIGBUS, signal::BUS_ADRERR, accessed_ptr)
+				}
+			};
+			curr_proc.kill_fault(info);
 		}
 
 		if matches!(curr_proc.get_state(), State::Running) {
@@ -412,9 +513,69 @@ pub fn init() -> Result<(), Errno> {
 		}
 	};
 
+	let debug_callback = |_id: u32, _code: u32, _regs: &Regs, ring: u32| {
+		// The kernel hit a watchpoint while accessing userspace memory on behalf of the process.
+		// The process may be locked, so the trap is ignored
+		if ring < 3 {
+			cpu::debug::clear_status();
+			return CallbackResult::Continue;
+		}
+
+		// Get process
+		let curr_proc = {
+			let sched_mutex = unsafe { SCHEDULER.assume_init_mut() };
+			let mut sched = sched_mutex.lock();
+
+			sched.get_current_process()
+		};
+		let Some(curr_proc) = curr_proc else {
+			return CallbackResult::Panic;
+		};
+		let mut curr_proc = curr_proc.lock();
+
+		curr_proc.debug_regs.save_status();
+		curr_proc.kill_fault(SigInfo::fault(
+			&Signal::SIGTRAP,
+			signal::TRAP_HWBKPT,
+			null(),
+		));
+
+		if matches!(curr_proc.get_state(), State::Running) {
+			CallbackResult::Continue
+		} else {
+			CallbackResult::Idle
+		}
+	};
+
+	let device_not_available_callback = |_id: u32, _code: u32, _regs: &Regs, ring: u32| {
+		// The kernel does not use the FPU
+		if ring < 3 {
+			return CallbackResult::Panic;
+		}
+
+		// Get process
+		let curr_proc = {
+			let sched_mutex = unsafe { SCHEDULER.assume_init_mut() };
+			let mut sched = sched_mutex.lock();
+
+			sched.get_current_process()
+		};
+		let Some(curr_proc) = curr_proc else {
+			return CallbackResult::Panic;
+		};
+		curr_proc.lock().fpu_load();
+
+		CallbackResult::Continue
+	};
+
 	let _ = ManuallyDrop::new(event::register_callback(0x00, callback)?);
+	let _ = ManuallyDrop::new(event::register_callback(0x01, debug_callback)?);
 	let _ = ManuallyDrop::new(event::register_callback(0x03, callback)?);
 	let _ = ManuallyDrop::new(event::register_callback(0x06, callback)?);
+	let _ = ManuallyDrop::new(event::register_callback(
+		0x07,
+		device_not_available_callback,
+	)?);
 	let _ = ManuallyDrop::new(event::register_callback(0x0d, callback)?);
 	let _ = ManuallyDrop::new(event::register_callback(0x0e, page_fault_callback)?);
 	let _ = ManuallyDrop::new(event::register_callback(0x10, callback)?);
@@ -432,6 +593,38 @@ pub fn get_scheduler() -> &'static IntMutex<Scheduler> {
 	}
 }
 
+/// Returns the maximum PID that can be allocated to a new process.
+pub fn get_pid_max() -> Pid {
+	unsafe { PID_MANAGER.assume_init_ref() }.lock().get_max()
+}
+
+/// Sets the maximum PID that can be allocated to a new process.
+///
+/// See [`PIDManager::set_max`].
+pub fn set_pid_max(max: Pid) -> EResult<()> {
+	unsafe { PID_MANAGER.assume_init_ref() }.lock().set_max(max)
+}
+
+/// Sends the signal `sig` to all processes except init, zombies and the process with PID
+/// `except`, if any.
+///
+/// The function returns the number of processes the signal has been sent to.
+pub fn kill_all(sig: &Signal, except: Option<Pid>) -> usize {
+	let mut sched = get_scheduler().lock();
+	let mut count = 0;
+	for (pid, proc_mutex) in sched.iter_process() {
+		if *pid == pid::INIT_PID || Some(*pid) == except {
+			continue;
+		}
+		let mut proc = proc_mutex.lock();
+		if !matches!(proc.get_state(), State::Zombie) {
+			proc.kill(sig, false);
+			count += 1;
+		}
+	}
+	count
+}
+
 impl Process {
 	/// Returns the process with PID `pid`.
 	///
@@ -520,11 +713,13 @@ impl Process {
 
 			fds_table
 		};
+		ucounts::charge(access_profile.get_uid(), UCount::Processes, true)?;
 
 		let process = Self {
 			pid: pid::INIT_PID,
 			pgid: pid::INIT_PID,
 			tid: pid::INIT_PID,
+			tgid: pid::INIT_PID,
 
 			argv: Arc::new(Vec::new())?,
 			exec_path: Arc::new(Path::root())?,
@@ -540,6 +735,9 @@ impl Process {
 			priority: 0,
 			nice: 0,
 			quantum_count: 0,
+			policy: SchedPolicy::Other,
+			rt_priority: 0,
+			pi_priority: 0,
 
 			parent: None,
 			children: Vec::new(),
@@ -547,9 +745,16 @@ impl Process {
 
 			regs: Regs::default(),
 			syscalling: false,
+			fpu: FpuState::new()?,
+			debug_regs: DebugRegs::default(),
+			tracer: None,
+
+			restart_block: None,
 
+			fault_info: None,
 			handled_signal: None,
 			saved_regs: Regs::default(),
+			saved_fpu: FpuState::new()?,
 			waitable: false,
 
 			timer_manager: Arc::new(Mutex::new(TimerManager::new(pid::INIT_PID)?))?,
@@ -561,9 +766,15 @@ impl Process {
 			cwd: Arc::new(Path::root())?,
 			chroot: Arc::new(Path::root())?,
 			file_descriptors: Some(Arc::new(Mutex::new(file_descriptors))?),
+			uts: Arc::new(Mutex::new(UtsNamespace::new()?))?,
 
 			sigmask: Bitfield::new(signal::SIGNALS_COUNT)?,
-			sigpending: Bitfield::new(signal::SIGNALS_COUNT)?,
+			sigpending: PendingSignals::new()?,
+			shared_sigpending: Arc::new(IntMutex::new(PendingSignals::new()?))?,
+			thread_group: Arc::new(IntMutex::new(ThreadGroup {
+				threads: Vec::from_slice(&[pid::INIT_PID])?,
+				live: 1,
+			}))?,
 			signal_handlers: Arc::new(Mutex::new(
 				[SignalHandler::Default; signal::SIGNALS_COUNT],
 			))?,
@@ -574,6 +785,7 @@ impl Process {
 			clear_child_tid: None,
 
 			rusage: RUsage::default(),
+			start_time: clock::current_time(CLOCK_BOOTTIME, TimestampScale::Nanosecond)?,
 
 			exit_status: 0,
 			termsig: 0,
@@ -692,7 +904,8 @@ impl Process {
 				panic!("Terminated init process!");
 			}
 
-			// Removing the memory space and file descriptors table to save memory
+			// Removing the memory space and file descriptors table to save memory. Since those
+			// may be shared with other threads, only the reference of the process is dropped
 			//self.mem_space = None; // TODO Handle the case where the memory space is bound
 			self.file_descriptors = None;
 
@@ -720,6 +933,17 @@ impl Process {
 		matches!(self.get_state(), State::Running) && self.vfork_state != VForkState::Waiting
 	}
 
+	/// Returns the realtime priority the process is scheduled with, including the priority it
+	/// inherits through futexes. Zero if the process is not realtime.
+	pub fn get_rt_priority(&self) -> u32 {
+		max(self.rt_priority, self.pi_priority)
+	}
+
+	/// Tells whether the process is scheduled as a realtime process.
+	pub fn is_realtime(&self) -> bool {
+		self.get_rt_priority() > 0
+	}
+
 	/// Wakes the process if sleeping.
 	pub fn wake(&mut self) {
 		if self.state == State::Sleeping {
@@ -792,6 +1016,21 @@ impl Process {
 		}
 	}
 
+	/// Returns the TID of the `i`th thread of the process's thread group, including the leader.
+	///
+	/// Threads that have been removed from the scheduler are not listed.
+	pub fn get_thread(&self, i: usize) -> Option<Pid> {
+		self.thread_group.lock().threads.get(i).copied()
+	}
+
+	/// Takes the list of the threads of the process's thread group, leaving it empty.
+	///
+	/// This function is meant to be called when the group is reaped, to remove its threads from
+	/// the scheduler without having to look for them.
+	pub fn take_threads(&self) -> Vec<Pid> {
+		core::mem::take(&mut self.thread_group.lock().threads)
+	}
+
 	/// Returns a reference to the process's memory space.
 	///
 	/// If the process is terminated, the function returns `None`.
@@ -826,6 +1065,28 @@ impl Process {
 		self.file_descriptors = fds;
 	}
 
+	/// Sets the user ID of the process in the same way the `setuid` system call does.
+	///
+	/// If the real user ID changes, the process is accounted to the new user. The new user's
+	/// process limit is not enforced since the process already exists.
+	pub fn set_uid(&mut self, uid: Uid) -> EResult<()> {
+		let mut access_profile = self.access_profile;
+		access_profile.set_uid(uid)?;
+		let old_uid = self.access_profile.get_uid();
+		let new_uid = access_profile.get_uid();
+		if new_uid != old_uid {
+			ucounts::charge(new_uid, UCount::Processes, true)?;
+			ucounts::uncharge(old_uid, UCount::Processes);
+		}
+		self.access_profile = access_profile;
+		Ok(())
+	}
+
+	/// Returns the UTS namespace of the process.
+	pub fn get_uts(&self) -> &Arc<Mutex<UtsNamespace>> {
+		&self.uts
+	}
+
 	/// Updates the TSS on the current core for the process.
 	pub fn update_tss(&self) {
 		// Compute the kernel stack pointer
@@ -869,6 +1130,8 @@ impl Process {
 			self.update_tls(i);
 		}
 		gdt::flush();
+		// Load breakpoints
+		self.debug_regs.load();
 
 		// Bind the memory space
 		self.get_mem_space().unwrap().lock().bind();
@@ -920,6 +1183,9 @@ impl Process {
 			VForkState::None
 		};
 
+		// The child inherits the FPU state, which may be only in the FPU
+		self.fpu_sync();
+
 		// Clone memory space
 		let (mem_space, kernel_stack) = {
 			let curr_mem_space = self.get_mem_space().unwrap();
@@ -961,16 +1227,41 @@ impl Process {
 			Arc::new(Mutex::new(self.signal_handlers.lock().clone()))?
 		};
 
+		let uts = if fork_options.new_uts {
+			Arc::new(Mutex::new(self.uts.lock().duplicate()?))?
+		} else {
+			self.uts.clone()
+		};
+
+		// Threads share the signals directed to their group
+		let (shared_sigpending, thread_group) = if fork_options.thread {
+			(self.shared_sigpending.clone(), self.thread_group.clone())
+		} else {
+			(
+				Arc::new(IntMutex::new(PendingSignals::new()?))?,
+				Arc::new(IntMutex::new(ThreadGroup::default()))?,
+			)
+		};
+
+		// The child is accounted to the real user ID it inherits. The charge is refunded if the
+		// process cannot be created
+		let charge = ucounts::Charge::new(
+			self.access_profile.get_uid(),
+			UCount::Processes,
+			self.access_profile.is_privileged(),
+		)?;
 		// FIXME PID is leaked if the following code fails
 		let pid = {
 			let mutex = unsafe { PID_MANAGER.assume_init_mut() };
 			mutex.lock().get_unique_pid()
 		}?;
+		let tgid = if fork_options.thread { self.tgid } else { pid };
 
 		let process = Self {
 			pid,
 			pgid: self.pgid,
 			tid: pid,
+			tgid,
 
 			argv: self.argv.clone(),
 			exec_path: self.exec_path.clone(),
@@ -986,6 +1277,9 @@ impl Process {
 			priority: self.priority,
 			nice: self.nice,
 			quantum_count: 0,
+			policy: self.policy,
+			rt_priority: self.rt_priority,
+			pi_priority: 0,
 
 			parent: Some(parent),
 			children: Vec::new(),
@@ -993,9 +1287,16 @@ impl Process {
 
 			regs: self.regs.clone(),
 			syscalling: false,
+			fpu: self.fpu.try_clone()?,
+			debug_regs: DebugRegs::default(),
+			tracer: None,
 
+			restart_block: None,
+
+			fault_info: None,
 			handled_signal: self.handled_signal.clone(),
 			saved_regs: self.saved_regs.clone(),
+			saved_fpu: self.saved_fpu.try_clone()?,
 			waitable: false,
 
 			// TODO if creating a thread: timer_manager: self.timer_manager.clone(),
@@ -1008,25 +1309,41 @@ impl Process {
 			cwd: self.cwd.clone(),
 			chroot: self.chroot.clone(),
 			file_descriptors,
+			uts,
 
 			sigmask: self.sigmask.try_clone()?,
-			sigpending: Bitfield::new(signal::SIGNALS_COUNT)?,
+			sigpending: PendingSignals::new()?,
+			shared_sigpending,
+			thread_group,
 			signal_handlers,
 
 			tls_entries: self.tls_entries,
 
 			set_child_tid: self.set_child_tid,
-			clear_child_tid: self.clear_child_tid,
+			// Set by `clone` with `CLONE_CHILD_CLEARTID`
+			clear_child_tid: None,
 
 			rusage: RUsage::default(),
+			start_time: clock::current_time(CLOCK_BOOTTIME, TimestampScale::Nanosecond)?,
 
 			exit_status: self.exit_status,
 			termsig: 0,
 		};
+		// From now on, the charge is refunded when the process is dropped
+		charge.keep();
+		{
+			// If registering the thread fails, dropping the process undoes the count
+			let mut thread_group = process.thread_group.lock();
+			thread_group.live += 1;
+			thread_group.threads.push(pid)?;
+		}
 
 		process.register_procfs()?;
 
-		self.add_child(pid)?;
+		// Threads are not reported to the parent on their own
+		if !fork_options.thread {
+			self.add_child(pid)?;
+		}
 
 		let sched_mutex = unsafe { SCHEDULER.assume_init_mut() };
 		Ok(sched_mutex.lock().add_process(process)?)
@@ -1076,10 +1393,64 @@ impl Process {
 		if !sig.can_catch() || no_handler {
 			sig.execute_action(self, no_handler);
 		} else {
-			self.sigpending.set(sig.get_id() as _);
+			// If the user has too many pending signals, the signal is discarded
+			let _ = self.sigpending.add(
+				sig,
+				self.access_profile.get_uid(),
+				self.access_profile.is_privileged(),
+			);
 		}
 	}
 
+	/// Kills the thread group of the process with the given signal `sig`.
+	///
+	/// Unlike [`Process::kill`], which targets the thread itself, the signal is queued in the set
+	/// shared by all the threads of the group. It is then handled by the first thread that does
+	/// not block it.
+	///
+	/// Signals that cannot be caught are executed immediately on the thread.
+	pub fn kill_process(&mut self, sig: &Signal) {
+		if !sig.can_catch() {
+			self.kill(sig, false);
+			return;
+		}
+
+		self.rusage.ru_nsignals += 1;
+
+		if matches!(self.get_state(), State::Stopped)
+			&& sig.get_default_action() == SignalAction::Continue
+		{
+			self.set_state(State::Running);
+		}
+
+		// If the user has too many pending signals, the signal is discarded
+		let _ = self.shared_sigpending.lock().add(
+			sig,
+			self.access_profile.get_uid(),
+			self.access_profile.is_privileged(),
+		);
+	}
+
+	/// Kills the process with the signal described by `info`, raised by a fault.
+	///
+	/// Since execution cannot resume at the faulting instruction, the default action is executed
+	/// if the signal is blocked or ignored. Then, the signal is handled immediately.
+	pub fn kill_fault(&mut self, info: SigInfo) {
+		let Ok(sig) = Signal::try_from(info.si_signo as u32) else {
+			return;
+		};
+		if self.is_signal_blocked(&sig)
+			|| matches!(self.get_signal_handler(&sig), SignalHandler::Ignore)
+		{
+			self.sigmask.clear(sig.get_id() as _);
+			self.set_signal_handler(&sig, SignalHandler::Default);
+		}
+
+		self.fault_info = Some(info);
+		self.kill(&sig, true);
+		self.signal_next();
+	}
+
 	/// Kills every processes in the process group.
 	///
 	/// Arguments are the same as `kill`.
@@ -1107,10 +1478,11 @@ impl Process {
 		self.sigpending.as_slice()
 	}
 
-	/// Tells whether the process has a signal pending.
+	/// Tells whether the process has a signal pending, either directed to the thread or to its
+	/// group.
 	#[inline(always)]
 	pub fn has_signal_pending(&self) -> bool {
-		self.sigpending.find_set().is_some()
+		self.sigpending.find_set().is_some() || self.shared_sigpending.lock().find_set().is_some()
 	}
 
 	/// Returns the ID of the next signal to be executed.
@@ -1122,9 +1494,13 @@ impl Process {
 			return None;
 		}
 
-		self.sigpending
+		// Signals directed to the thread have priority over the ones directed to its group
+		let shared = self.shared_sigpending.lock();
+		let sig = self
+			.sigpending
 			.iter()
 			.enumerate()
+			.chain(shared.iter().enumerate())
 			.filter_map(|(i, b)| {
 				if !b {
 					return None;
@@ -1137,7 +1513,8 @@ impl Process {
 					None
 				}
 			})
-			.next()
+			.next();
+		sig
 	}
 
 	/// Makes the process handle the next signal.
@@ -1160,7 +1537,9 @@ impl Process {
 	///
 	/// If the signal is already cleared, the function does nothing.
 	pub fn signal_clear(&mut self, sig: Signal) {
-		self.sigpending.clear(sig.get_id() as _);
+		if !self.sigpending.remove(&sig) {
+			self.shared_sigpending.lock().remove(&sig);
+		}
 	}
 
 	/// Saves the process's state to handle a signal.
@@ -1172,6 +1551,8 @@ impl Process {
 		debug_assert!(!self.is_handling_signal());
 
 		self.saved_regs = self.regs.clone();
+		self.fpu_sync();
+		self.saved_fpu.copy_from(&self.fpu);
 		self.handled_signal = Some(sig);
 	}
 
@@ -1180,9 +1561,59 @@ impl Process {
 		if self.handled_signal.is_some() {
 			self.handled_signal = None;
 			self.regs = self.saved_regs.clone();
+			self.fpu.copy_from(&self.saved_fpu);
+			self.fpu_invalidate();
 		}
 	}
 
+	/// If the FPU holds the state of the process and the process may have modified it, saves it
+	/// into the process's structure.
+	pub fn fpu_sync(&mut self) {
+		let owner = FPU_OWNER.lock();
+		// The FPU is trapping whenever the owner is not running
+		if *owner == Some(self.pid) && !fpu::is_trapping() {
+			self.fpu.save();
+		}
+	}
+
+	/// Makes the FPU reload the process's state on next use, after it has been modified in the
+	/// process's structure.
+	///
+	/// If the process is running, the state currently in the FPU is discarded.
+	pub fn fpu_invalidate(&mut self) {
+		let mut owner = FPU_OWNER.lock();
+		if *owner == Some(self.pid) {
+			*owner = None;
+			fpu::set_trap();
+		}
+	}
+
+	/// Saves the FPU state of the process, which is being switched out, and makes the next use of
+	/// the FPU trap.
+	pub fn fpu_switch_out(&mut self) {
+		self.fpu_sync();
+		fpu::set_trap();
+	}
+
+	/// Loads the FPU state of the process, which is running, into the FPU.
+	///
+	/// This function is called when the process uses the FPU while it is trapping.
+	pub fn fpu_load(&mut self) {
+		fpu::clear_trap();
+		let mut owner = FPU_OWNER.lock();
+		// If the FPU still holds the process's state, there is nothing to do
+		if *owner != Some(self.pid) {
+			self.fpu.restore();
+			*owner = Some(self.pid);
+		}
+	}
+
+	/// Resets the FPU state of the process to its initial state.
+	pub fn fpu_reset(&mut self) {
+		self.fpu.reset();
+		self.fpu_invalidate();
+	}
+
 	/// Returns the list of TLS entries for the process.
 	pub fn get_tls_entries(&mut self) -> &mut [gdt::Entry] {
 		&mut self.tls_entries
@@ -1209,6 +1640,11 @@ impl Process {
 		}
 	}
 
+	/// Returns the `clear_child_tid` attribute of the process.
+	pub fn get_clear_child_tid(&self) -> Option<NonNull<i32>> {
+		self.clear_child_tid
+	}
+
 	/// Sets the `clear_child_tid` attribute of the process.
 	pub fn set_clear_child_tid(&mut self, ptr: Option<NonNull<i32>>) {
 		self.clear_child_tid = ptr;
@@ -1254,9 +1690,37 @@ impl Process {
 			0
 		};
 
+		let was_live = !matches!(self.state, State::Zombie);
 		self.set_state(State::Zombie);
 		self.reset_vfork();
-		self.set_waitable(sig);
+		futex::exit(self.pid);
+
+		// The thread group is reported to the parent only once every thread has exited. Until
+		// then, the exited threads keep their references to the resources they share with the
+		// rest of the group
+		let last = {
+			let mut thread_group = self.thread_group.lock();
+			if was_live {
+				thread_group.live -= 1;
+			}
+			thread_group.live == 0
+		};
+		if !last {
+			self.waitable = false;
+			return;
+		}
+		// Record locks belong to the thread group
+		locks::release_all(self.tgid);
+		if self.pid == self.tgid {
+			self.set_waitable(sig);
+		} else {
+			self.waitable = false;
+			if let Some(leader_mutex) = Process::get_by_pid(self.tgid) {
+				let mut leader = leader_mutex.lock();
+				let sig = leader.termsig;
+				leader.set_waitable(sig);
+			}
+		}
 	}
 
 	/// Returns the number of virtual memory pages used by the process.
@@ -1305,6 +1769,25 @@ impl AccessProfile {
 			|| euid == proc.access_profile.get_uid()
 			|| euid == proc.access_profile.get_suid()
 	}
+
+	/// Tells whether the agent can read the memory of the process, such as its environment
+	/// variables.
+	pub fn can_read_memory(&self, proc: &Process) -> bool {
+		if self.is_privileged() {
+			return true;
+		}
+
+		// The agent must own every identity of the process
+		let euid = self.get_euid();
+		let egid = self.get_egid();
+		let target = &proc.access_profile;
+		euid == target.get_uid()
+			&& euid == target.get_euid()
+			&& euid == target.get_suid()
+			&& egid == target.get_gid()
+			&& egid == target.get_egid()
+			&& egid == target.get_sgid()
+	}
 }
 
 impl Drop for Process {
@@ -1329,6 +1812,25 @@ impl Drop for Process {
 			Ok(())
 		});
 
+		// The PID may be reused, so the FPU must not be considered as holding the process's state
+		{
+			let mut owner = FPU_OWNER.lock();
+			if *owner == Some(self.pid) {
+				*owner = None;
+			}
+		}
+
+		// A process dropped without exiting (when `fork` fails) is still counted in its group
+		{
+			let mut thread_group = self.thread_group.lock();
+			thread_group.threads.retain(|tid| *tid != self.pid);
+			if !matches!(self.state, State::Zombie) {
+				thread_group.live -= 1;
+			}
+		}
+
+		ucounts::uncharge(self.access_profile.get_uid(), UCount::Processes);
+
 		// Freeing the PID
 		let mut pid_manager = unsafe { PID_MANAGER.assume_init_mut() }.lock();
 		pid_manager.release_pid(self.pid);
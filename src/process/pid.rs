@@ -2,23 +2,39 @@
 //!
 //! Each process must have an unique PID, thus they have to be allocated.
 //! A bitfield is used to store the used PIDs.
+//!
+//! PIDs are allocated cyclically up to the limit `pid_max`, which is configurable at runtime
+//! through `/proc/sys/kernel/pid_max`.
 
+use crate::errno;
 use crate::errno::AllocResult;
+use crate::errno::EResult;
+use crate::util::boxed::Box;
 use crate::util::container::id_allocator::IDAllocator;
+use core::array;
 
 /// Type representing a Process ID. This ID is unique for every running
 /// processes.
 pub type Pid = u16;
 
 /// The maximum possible PID.
-const MAX_PID: Pid = 32768;
+pub const MAX_PID: Pid = 32768;
 /// The PID of the init process.
 pub const INIT_PID: Pid = 1;
+/// When wrapping around, PIDs up to this value are skipped since they are likely to be held by
+/// long-running system processes.
+const RESERVED_PIDS: Pid = 300;
+/// The minimum value of `pid_max`.
+pub const MIN_PID_MAX: Pid = RESERVED_PIDS + 1;
 
 /// A structure handling PID allocations.
 pub struct PIDManager {
 	/// The PID allocator.
 	allocator: IDAllocator,
+	/// The last allocated PID.
+	last: Pid,
+	/// The maximum PID to allocate.
+	max: Pid,
 }
 
 impl PIDManager {
@@ -26,22 +42,47 @@ impl PIDManager {
 	pub fn new() -> AllocResult<Self> {
 		let mut s = Self {
 			allocator: IDAllocator::new(MAX_PID as _)?,
+			last: INIT_PID,
+			max: MAX_PID,
 		};
 		s.allocator.set_used((INIT_PID - 1) as _);
 		Ok(s)
 	}
 
+	/// Returns the maximum PID to allocate.
+	pub fn get_max(&self) -> Pid {
+		self.max
+	}
+
+	/// Sets the maximum PID to allocate.
+	///
+	/// Already allocated PIDs above the new maximum remain valid.
+	///
+	/// If the value is not in the range `MIN_PID_MAX..=MAX_PID`, the function returns
+	/// [`errno::EINVAL`].
+	pub fn set_max(&mut self, max: Pid) -> EResult<()> {
+		if !(MIN_PID_MAX..=MAX_PID).contains(&max) {
+			return Err(errno!(EINVAL));
+		}
+		self.max = max;
+		Ok(())
+	}
+
 	/// Returns a unused PID and marks it as used.
+	///
+	/// PIDs are allocated in increasing order, wrapping around after the maximum, so that a
+	/// released PID is not reused right away.
 	#[must_use = "not freeing a PID shall cause a leak"]
 	pub fn get_unique_pid(&mut self) -> AllocResult<Pid> {
-		match self.allocator.alloc(None) {
-			Ok(i) => {
-				debug_assert!(i <= MAX_PID as _);
+		// Since IDs start at zero, the ID of PID `n` is `n - 1`
+		let next = self.last as u32;
+		let i = self
+			.allocator
+			.alloc_in(next..(self.max as u32))
+			.or_else(|_| self.allocator.alloc_in((RESERVED_PIDS as u32)..next))?;
 
-				Ok((i + 1) as _)
-			}
-			Err(e) => Err(e),
-		}
+		self.last = (i + 1) as _;
+		Ok(self.last)
 	}
 
 	/// Releases the given PID `pid` to make it available for other processes.
@@ -54,3 +95,106 @@ impl PIDManager {
 		self.allocator.free((pid - 1) as _)
 	}
 }
+
+/// The number of bits of a PID used as index in a leaf of a [`PidMap`].
+const LEAF_BITS: usize = 8;
+/// The number of entries in a leaf of a [`PidMap`].
+const LEAF_SIZE: usize = 1 << LEAF_BITS;
+/// The number of leaves of a [`PidMap`].
+const LEAVES_COUNT: usize = (MAX_PID as usize >> LEAF_BITS) + 1;
+
+/// A leaf of a [`PidMap`].
+type Leaf<T> = [Option<T>; LEAF_SIZE];
+
+/// A radix tree associating PIDs with values, allowing lookups in constant time.
+///
+/// Leaves are allocated only when a value is inserted in their range.
+pub struct PidMap<T> {
+	/// The leaves of the tree.
+	leaves: [Option<Box<Leaf<T>>>; LEAVES_COUNT],
+}
+
+impl<T> PidMap<T> {
+	/// Creates a new empty instance.
+	pub fn new() -> Self {
+		Self {
+			leaves: array::from_fn(|_| None),
+		}
+	}
+
+	/// Returns the value associated with the PID `pid`.
+	pub fn get(&self, pid: Pid) -> Option<&T> {
+		let pid = pid as usize;
+		let leaf = self.leaves.get(pid >> LEAF_BITS)?.as_ref()?;
+		leaf[pid % LEAF_SIZE].as_ref()
+	}
+
+	/// Associates the value `val` with the PID `pid`.
+	///
+	/// The function returns the previous value, if any.
+	pub fn insert(&mut self, pid: Pid, val: T) -> AllocResult<Option<T>> {
+		let pid = pid as usize;
+		let leaf = &mut self.leaves[pid >> LEAF_BITS];
+		if leaf.is_none() {
+			*leaf = Some(Box::new(array::from_fn(|_| None))?);
+		}
+		let leaf = leaf.as_mut().unwrap();
+		Ok(leaf[pid % LEAF_SIZE].replace(val))
+	}
+
+	/// Removes the value associated with the PID `pid`, then returns it.
+	///
+	/// If the leaf containing the PID becomes empty, it is freed.
+	pub fn remove(&mut self, pid: Pid) -> Option<T> {
+		let pid = pid as usize;
+		let entry = self.leaves.get_mut(pid >> LEAF_BITS)?;
+		let leaf = entry.as_mut()?;
+		let val = leaf[pid % LEAF_SIZE].take();
+		if leaf.iter().all(Option::is_none) {
+			*entry = None;
+		}
+		val
+	}
+}
+
+impl<T> Default for PidMap<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test_case]
+	fn pid_alloc_cyclic() {
+		let mut manager = PIDManager::new().unwrap();
+		let a = manager.get_unique_pid().unwrap();
+		assert_eq!(a, INIT_PID + 1);
+		manager.release_pid(a);
+		// The released PID is not reused right away
+		assert_eq!(manager.get_unique_pid().unwrap(), a + 1);
+	}
+
+	#[test_case]
+	fn pid_alloc_wrap() {
+		let mut manager = PIDManager::new().unwrap();
+		manager.set_max(MIN_PID_MAX).unwrap();
+		while manager.get_unique_pid().unwrap() < MIN_PID_MAX {}
+		// Reserved PIDs are skipped when wrapping around
+		assert!(manager.get_unique_pid().is_err());
+	}
+
+	#[test_case]
+	fn pid_map() {
+		let mut map = PidMap::new();
+		assert_eq!(map.get(42), None);
+		map.insert(42, 1).unwrap();
+		map.insert(MAX_PID, 2).unwrap();
+		assert_eq!(map.get(42), Some(&1));
+		assert_eq!(map.get(MAX_PID), Some(&2));
+		assert_eq!(map.remove(42), Some(1));
+		assert_eq!(map.get(42), None);
+	}
+}
@@ -23,13 +23,6 @@ context_switch:
 	mov %ax, %ds
 	mov %ax, %es
 
-	# Restore the fx state
-	mov 4(%esp), %eax
-	add $0x30, %eax
-	push %eax
-	call restore_fxstate
-	add $4, %esp
-
 	# Set registers, except %eax
 	mov 4(%esp), %eax
 	mov 0x0(%eax), %ebp
@@ -60,13 +53,6 @@ context_switch:
 context_switch_kernel:
 	cli
 
-	# Restore the fx state
-	mov 4(%esp), %eax
-	add $0x30, %eax
-	push %eax
-	call restore_fxstate
-	add $4, %esp
-
 	mov 4(%esp), %eax
 
 	# Set eflags without the interrupt flag
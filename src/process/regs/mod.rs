@@ -2,15 +2,10 @@
 
 use crate::errno::Errno;
 use crate::gdt;
-use core::arch::asm;
 use core::fmt;
 
 /// The default value of the eflags register.
 const DEFAULT_EFLAGS: u32 = 0x1202;
-/// The default value of the FCW.
-const DEFAULT_FCW: u32 = 0b1100111111;
-/// The default value of the MXCSR.
-const DEFAULT_MXCSR: u32 = 0b1111111000000;
 
 extern "C" {
 	/// This function switches to a userspace context.
@@ -26,34 +21,6 @@ extern "C" {
 	fn context_switch_kernel(regs: &Regs) -> !;
 }
 
-/// Wrapper allowing to align the fxstate buffer.
-#[repr(align(16))]
-struct FXStateWrapper([u8; 512]);
-
-/// Saves the current x87 FPU, MMX and SSE state to the given buffer.
-#[no_mangle]
-pub extern "C" fn save_fxstate(fxstate: &mut [u8; 512]) {
-	let mut buff = FXStateWrapper([0; 512]);
-	unsafe {
-		asm!("fxsave [{}]", in(reg) buff.0.as_mut_ptr());
-	}
-
-	// TODO avoid copy (slow). `fxstate` itself should be aligned
-	fxstate.copy_from_slice(&buff.0);
-}
-
-/// Restores the x87 FPU, MMX and SSE state from the given buffer.
-#[no_mangle]
-pub extern "C" fn restore_fxstate(fxstate: &[u8; 512]) {
-	let mut buff = FXStateWrapper([0; 512]);
-	// TODO avoid copy (slow). `fxstate` itself should be aligned
-	buff.0.copy_from_slice(fxstate);
-
-	unsafe {
-		asm!("fxrstor [{}]", in(reg) buff.0.as_ptr());
-	}
-}
-
 /// Structure representing the list of registers for a context.
 ///
 /// The content of this structure depends on the architecture for which the kernel is compiled.
@@ -74,9 +41,6 @@ pub struct Regs {
 
 	pub gs: u32,
 	pub fs: u32,
-
-	/// x87 FPU, MMX and SSE state.
-	pub fxstate: [u8; 512],
 }
 
 impl Regs {
@@ -113,7 +77,7 @@ impl Regs {
 
 impl Default for Regs {
 	fn default() -> Self {
-		let mut s = Self {
+		Self {
 			ebp: 0x0,
 			esp: 0x0,
 			eip: 0x0,
@@ -127,21 +91,7 @@ impl Default for Regs {
 
 			gs: 0x0,
 			fs: 0x0,
-
-			fxstate: [0; 512],
-		};
-
-		// Setting the default FPU control word
-		s.fxstate[0] = (DEFAULT_FCW & 0xff) as _;
-		s.fxstate[1] = ((DEFAULT_FCW >> 8) & 0xff) as _;
-
-		// Setting the default MXCSR
-		s.fxstate[24] = (DEFAULT_MXCSR & 0xff) as _;
-		s.fxstate[25] = ((DEFAULT_MXCSR >> 8) & 0xff) as _;
-		s.fxstate[26] = ((DEFAULT_MXCSR >> 16) & 0xff) as _;
-		s.fxstate[27] = ((DEFAULT_MXCSR >> 24) & 0xff) as _;
-
-		s
+		}
 	}
 }
 
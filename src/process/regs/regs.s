@@ -3,7 +3,7 @@
  */
 
 // The size in bytes of the structure storing the registers' states
-.set REGS_SIZE, 560
+.set REGS_SIZE, 48
 
 /*
  * This macro stores the values of every registers after an interruption was triggered.
@@ -30,13 +30,6 @@
 	mov %ebx, 0x14(%esp)
 	mov %eax, 0x10(%esp)
 
-	# Save the fx state
-	mov %esp, %eax
-	add $0x30, %eax
-	push %eax
-	call save_fxstate
-	add $4, %esp
-
 	mov 12(%ebp), %eax
 	mov %eax, 0xc(%esp) # eflags
 	mov 4(%ebp), %eax
@@ -69,13 +62,6 @@ esp_end_\n:
  * This macro restores the registers' states and frees the space allocated by the function GET_REGS.
  */
 .macro RESTORE_REGS
-	# Restore the fx state
-	mov %esp, %eax
-	add $0x30, %eax
-	push %eax
-	call restore_fxstate
-	add $4, %esp
-
 	# Restore segments
 	mov 0x2c(%esp), %fs
 	mov 0x28(%esp), %gs
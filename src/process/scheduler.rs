@@ -8,20 +8,30 @@
 //! each process, based on the number of running processes and their priority.
 //! This number represents the number of ticks during which the process keeps
 //! running until switching to the next process.
+//!
+//! Realtime processes (`SCHED_FIFO` and `SCHED_RR`) always run before other processes, the one
+//! with the highest priority first. To prevent a runaway realtime process from starving the
+//! system, realtime processes are throttled once they have run for [`RT_RUNTIME`] during a period
+//! of [`RT_PERIOD`]. Until the end of the period, other processes run first.
 
 use crate::errno::AllocResult;
 use crate::event;
 use crate::event::CallbackHook;
-use crate::idt::pic;
+use crate::idt;
 use crate::memory;
 use crate::memory::malloc;
 use crate::memory::stack;
 use crate::process;
 use crate::process::pid::Pid;
+use crate::process::pid::PidMap;
 use crate::process::regs::Regs;
 use crate::process::Process;
 use crate::process::State;
 use crate::time;
+use crate::time::clock;
+use crate::time::clock::CLOCK_MONOTONIC;
+use crate::time::unit::Timestamp;
+use crate::time::unit::TimestampScale;
 use crate::util::container::map::Map;
 use crate::util::container::map::MapIterator;
 use crate::util::container::vec::Vec;
@@ -31,6 +41,8 @@ use crate::util::math::rational::Rational;
 use crate::util::ptr::arc::Arc;
 use core::arch::asm;
 use core::cmp::max;
+use core::cmp::min;
+use core::ffi::c_int;
 use core::ffi::c_void;
 
 /// The size of the temporary stack for context switching.
@@ -39,6 +51,103 @@ const TMP_STACK_SIZE: usize = 16 * memory::PAGE_SIZE;
 const AVERAGE_PRIORITY_QUANTA: usize = 10;
 /// The number of quanta for the process with the maximum priority.
 const MAX_PRIORITY_QUANTA: usize = 30;
+/// The number of quanta a `SCHED_RR` process runs before letting other processes with the same
+/// priority run.
+const RR_QUANTA: usize = 2;
+
+/// The period over which the time spent running realtime processes is limited, in nanoseconds.
+pub const RT_PERIOD: Timestamp = 1_000_000_000;
+/// The maximum time realtime processes can run during [`RT_PERIOD`], in nanoseconds.
+pub const RT_RUNTIME: Timestamp = 950_000_000;
+
+/// Scheduling policy: default time-sharing policy.
+pub const SCHED_OTHER: c_int = 0;
+/// Scheduling policy: realtime first-in first-out policy.
+pub const SCHED_FIFO: c_int = 1;
+/// Scheduling policy: realtime round-robin policy.
+pub const SCHED_RR: c_int = 2;
+
+/// The minimum priority of a realtime process.
+pub const RT_PRIORITY_MIN: u32 = 1;
+/// The maximum priority of a realtime process.
+pub const RT_PRIORITY_MAX: u32 = 99;
+
+/// Scheduling parameters of a process, as used by `sched_setparam` and `sched_getparam`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct SchedParam {
+	/// The realtime priority of the process.
+	pub sched_priority: c_int,
+}
+
+/// A scheduling policy.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SchedPolicy {
+	/// Time-sharing between processes (`SCHED_OTHER`).
+	Other,
+	/// The process runs until it blocks or a process with a higher priority is runnable
+	/// (`SCHED_FIFO`).
+	Fifo,
+	/// Same as [`Self::Fifo`], except that processes with the same priority take turns
+	/// (`SCHED_RR`).
+	RoundRobin,
+}
+
+impl SchedPolicy {
+	/// Returns the policy with the given ID.
+	///
+	/// If the ID is invalid, the function returns `None`.
+	pub fn from_id(id: c_int) -> Option<Self> {
+		match id {
+			SCHED_OTHER => Some(Self::Other),
+			SCHED_FIFO => Some(Self::Fifo),
+			SCHED_RR => Some(Self::RoundRobin),
+			_ => None,
+		}
+	}
+
+	/// Returns the ID of the policy.
+	pub fn get_id(self) -> c_int {
+		match self {
+			Self::Other => SCHED_OTHER,
+			Self::Fifo => SCHED_FIFO,
+			Self::RoundRobin => SCHED_RR,
+		}
+	}
+
+	/// Tells whether the policy is a realtime policy.
+	pub fn is_realtime(self) -> bool {
+		self != Self::Other
+	}
+
+	/// Returns the range of valid priorities for the policy.
+	pub fn get_priority_range(self) -> (u32, u32) {
+		if self.is_realtime() {
+			(RT_PRIORITY_MIN, RT_PRIORITY_MAX)
+		} else {
+			(0, 0)
+		}
+	}
+}
+
+/// Time spent by a CPU core in each state, in nanoseconds.
+///
+/// Times are sampled at each tick of the scheduler: the time elapsed since the previous tick is
+/// accounted to the state of the context the tick interrupted.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CpuStat {
+	/// Time spent running processes in userspace.
+	pub user: Timestamp,
+	/// Time spent running processes in kernelspace.
+	pub system: Timestamp,
+	/// Time spent with no process to run.
+	pub idle: Timestamp,
+	/// Time spent handling interrupts.
+	///
+	/// Since interrupt handlers run with interrupts disabled, ticks never sample them and this
+	/// value currently remains zero.
+	pub irq: Timestamp,
+}
 
 /// The structure representing the process scheduler.
 pub struct Scheduler {
@@ -50,10 +159,20 @@ pub struct Scheduler {
 	tick_callback_hook: CallbackHook,
 	/// The total number of ticks since the instanciation of the scheduler.
 	total_ticks: u64,
+	/// The timestamp of the previous tick, in nanoseconds.
+	last_tick: Timestamp,
+	/// Time accounting for each CPU cores.
+	cpu_stats: Vec<CpuStat>,
+	/// The total number of context switches since the instanciation of the scheduler.
+	context_switches: u64,
+	/// The total number of processes created since the instanciation of the scheduler.
+	processes_created: u64,
 
 	/// A binary tree containing all processes registered to the current
 	/// scheduler.
 	processes: Map<Pid, Arc<IntMutex<Process>>>,
+	/// The same processes, indexed for lookups by PID in constant time.
+	pids: PidMap<Arc<IntMutex<Process>>>,
 	/// The currently running process with its PID.
 	curr_proc: Option<(Pid, Arc<IntMutex<Process>>)>,
 
@@ -64,6 +183,11 @@ pub struct Scheduler {
 	priority_sum: usize,
 	/// The priority of the processs which has the current highest priority.
 	priority_max: usize,
+
+	/// The timestamp of the beginning of the current realtime period, in nanoseconds.
+	rt_period_begin: Timestamp,
+	/// The time spent running realtime processes during the current period, in nanoseconds.
+	rt_time: Timestamp,
 }
 
 impl Scheduler {
@@ -75,31 +199,41 @@ impl Scheduler {
 				TMP_STACK_SIZE.try_into().unwrap(),
 			)?)?;
 		}
+		let cpu_stats = crate::vec![CpuStat::default(); cores_count]?;
 
 		// Register tick handler
 		let mut clocks = time::hw::CLOCKS.lock();
-		let pit = clocks.get_mut(b"pit".as_slice()).unwrap();
+		let clock = clocks.get_mut(time::hw::get_tick_clock()).unwrap();
 		let tick_callback_hook = event::register_callback(
-			pit.get_interrupt_vector(),
+			clock.get_interrupt_vector(),
 			|_: u32, _: u32, regs: &Regs, ring: u32| {
 				Scheduler::tick(process::get_scheduler(), regs, ring);
 			},
 		)?
 		.unwrap();
 
+		let now = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Nanosecond).unwrap_or(0);
 		Arc::new(IntMutex::new(Self {
 			tmp_stacks,
 
 			tick_callback_hook,
 			total_ticks: 0,
+			last_tick: now,
+			cpu_stats,
+			context_switches: 0,
+			processes_created: 0,
 
 			processes: Map::new(),
+			pids: PidMap::new(),
 			curr_proc: None,
 
 			running_procs: 0,
 
 			priority_sum: 0,
 			priority_max: 0,
+
+			rt_period_begin: now,
+			rt_time: 0,
 		}))
 	}
 
@@ -118,6 +252,26 @@ impl Scheduler {
 		self.total_ticks
 	}
 
+	/// Returns the time accounting of each CPU cores.
+	pub fn get_cpu_stats(&self) -> &[CpuStat] {
+		&self.cpu_stats
+	}
+
+	/// Returns the total number of context switches since the instanciation of the scheduler.
+	pub fn get_context_switches(&self) -> u64 {
+		self.context_switches
+	}
+
+	/// Returns the total number of processes created since the instanciation of the scheduler.
+	pub fn get_processes_created(&self) -> u64 {
+		self.processes_created
+	}
+
+	/// Returns the current number of running processes.
+	pub fn get_running_count(&self) -> usize {
+		self.running_procs
+	}
+
 	/// Returns an iterator on the scheduler's processes.
 	pub fn iter_process(&mut self) -> MapIterator<'_, Pid, Arc<IntMutex<Process>>> {
 		self.processes.iter()
@@ -127,15 +281,15 @@ impl Scheduler {
 	///
 	/// If the process doesn't exist, the function returns `None`.
 	pub fn get_by_pid(&self, pid: Pid) -> Option<Arc<IntMutex<Process>>> {
-		Some(self.processes.get(pid)?.clone())
+		self.pids.get(pid).cloned()
 	}
 
 	/// Returns the process with TID `tid`.
 	///
 	/// If the process doesn't exist, the function returns `None`.
-	pub fn get_by_tid(&self, _tid: Pid) -> Option<Arc<IntMutex<Process>>> {
-		// TODO
-		todo!();
+	pub fn get_by_tid(&self, tid: Pid) -> Option<Arc<IntMutex<Process>>> {
+		// Each thread has its own entry, with its TID as PID
+		self.get_by_pid(tid)
 	}
 
 	/// Returns the current running process.
@@ -173,8 +327,13 @@ impl Scheduler {
 		}
 
 		let ptr = Arc::new(IntMutex::new(process))?;
-		self.processes.insert(pid, ptr.clone())?;
+		self.pids.insert(pid, ptr.clone())?;
+		if let Err(e) = self.processes.insert(pid, ptr.clone()) {
+			self.pids.remove(pid);
+			return Err(e);
+		}
 		self.update_priority(0, priority);
+		self.processes_created += 1;
 
 		Ok(ptr)
 	}
@@ -190,6 +349,7 @@ impl Scheduler {
 
 			let priority = proc.priority;
 			self.processes.remove(&pid);
+			self.pids.remove(pid);
 			self.update_priority(priority, 0);
 		}
 	}
@@ -204,11 +364,11 @@ impl Scheduler {
 		self.running_procs += 1;
 
 		let mut clocks = time::hw::CLOCKS.lock();
-		let pit = clocks.get_mut(b"pit".as_slice()).unwrap();
+		let clock = clocks.get_mut(time::hw::get_tick_clock()).unwrap();
 
 		if self.running_procs > 1 {
-			pit.set_frequency(self.get_ticking_frequency());
-			pit.set_enabled(true);
+			clock.set_frequency(self.get_ticking_frequency());
+			clock.set_enabled(true);
 		}
 	}
 
@@ -217,12 +377,12 @@ impl Scheduler {
 		self.running_procs -= 1;
 
 		let mut clocks = time::hw::CLOCKS.lock();
-		let pit = clocks.get_mut(b"pit".as_slice()).unwrap();
+		let clock = clocks.get_mut(time::hw::get_tick_clock()).unwrap();
 
 		if self.running_procs <= 1 {
-			pit.set_enabled(false);
+			clock.set_enabled(false);
 		} else {
-			pit.set_frequency(self.get_ticking_frequency());
+			clock.set_frequency(self.get_ticking_frequency());
 		}
 	}
 
@@ -270,9 +430,80 @@ impl Scheduler {
 		}
 	}
 
+	/// Accounts `elapsed` nanoseconds spent running realtime processes, at timestamp `now`.
+	fn account_rt(&mut self, now: Timestamp, elapsed: Timestamp) {
+		if now.saturating_sub(self.rt_period_begin) >= RT_PERIOD {
+			self.rt_period_begin = now;
+			self.rt_time = 0;
+		}
+		// Time elapsed before the beginning of the period is not accounted to it
+		self.rt_time += min(elapsed, now.saturating_sub(self.rt_period_begin));
+	}
+
+	/// Tells whether realtime processes are throttled for the rest of the current period.
+	fn is_rt_throttled(&self) -> bool {
+		self.rt_time >= RT_RUNTIME
+	}
+
+	/// Returns the next process with PID greater than `curr_pid` for which `filter` returns
+	/// `true`, looping back to the beginning of the list if none is found.
+	fn find_next<F: Fn(&Process) -> bool>(
+		&self,
+		curr_pid: Pid,
+		filter: F,
+	) -> Option<(Pid, Arc<IntMutex<Process>>)> {
+		let process_filter = |(_, proc): &(&Pid, &Arc<IntMutex<Process>>)| filter(&proc.lock());
+		self.processes
+			.range((curr_pid + 1)..)
+			.find(process_filter)
+			.or_else(|| {
+				// If no suitable process is found, go back to the beginning to check processes
+				// located before the previous process (looping)
+
+				self.processes.iter().find(process_filter)
+			})
+			.map(|(pid, proc)| (*pid, proc.clone()))
+	}
+
+	/// Returns the next realtime process to run with its PID.
+	///
+	/// The process with the highest priority is chosen. If the current process `curr_proc` has
+	/// this priority, it keeps running, unless it uses the round-robin policy and has run all its
+	/// quanta.
+	fn get_next_rt_process(
+		&self,
+		curr_pid: Pid,
+		curr_proc: &Arc<IntMutex<Process>>,
+	) -> Option<(Pid, Arc<IntMutex<Process>>)> {
+		let priority = self
+			.processes
+			.iter()
+			.filter_map(|(_, proc)| {
+				let proc = proc.lock();
+				(proc.can_run() && proc.is_realtime()).then(|| proc.get_rt_priority())
+			})
+			.max()?;
+		let filter = |proc: &Process| {
+			proc.can_run() && proc.is_realtime() && proc.get_rt_priority() == priority
+		};
+
+		{
+			let proc = curr_proc.lock();
+			let quanta_left =
+				proc.policy != SchedPolicy::RoundRobin || proc.quantum_count < RR_QUANTA;
+			if filter(&proc) && quanta_left {
+				return Some((curr_pid, curr_proc.clone()));
+			}
+		}
+		self.find_next(curr_pid, filter)
+	}
+
 	// TODO Clean
 	/// Returns the next process to run with its PID.
 	///
+	/// Realtime processes run first, unless they are throttled. In which case, they run only if
+	/// no other process can run.
+	///
 	/// If the process is changed, the quantum count of the previous process is reset.
 	fn get_next_process(&self) -> Option<(Pid, Arc<IntMutex<Process>>)> {
 		let priority_sum = self.priority_sum;
@@ -288,28 +519,24 @@ impl Scheduler {
 				.map(|(pid, proc)| (*pid, proc.clone()))
 		})?;
 
-		let process_filter = |(_, proc): &(&Pid, &Arc<IntMutex<Process>>)| {
-			let guard = proc.lock();
-			Self::can_run(&guard, priority_sum, priority_max, processes_count)
-		};
-
-		let next_proc = self
-			.processes
-			.range((curr_pid + 1)..)
-			.find(process_filter)
-			.or_else(|| {
-				// If no suitable process is found, go back to the beginning to check processes
-				// located before the previous process (looping)
-
-				self.processes.iter().find(process_filter)
+		let get_next_other = || {
+			self.find_next(curr_pid, |proc| {
+				!proc.is_realtime()
+					&& Self::can_run(proc, priority_sum, priority_max, processes_count)
 			})
-			.map(|(pid, proc)| (*pid, proc));
+		};
+		let next_proc = if self.is_rt_throttled() {
+			get_next_other().or_else(|| self.get_next_rt_process(curr_pid, &curr_proc))
+		} else {
+			self.get_next_rt_process(curr_pid, &curr_proc)
+				.or_else(get_next_other)
+		};
 
 		let (next_pid, next_proc) = next_proc?;
 		if next_pid != curr_pid || processes_count == 1 {
 			curr_proc.lock().quantum_count = 0;
 		}
-		Some((next_pid, next_proc.clone()))
+		Some((next_pid, next_proc))
 	}
 
 	/// Ticking the scheduler.
@@ -328,19 +555,39 @@ impl Scheduler {
 		cli!();
 
 		let tmp_stack = {
-			let mut sched = sched_mutex.lock();
+			let mut sched_guard = sched_mutex.lock();
+			let sched = &mut *sched_guard;
 			sched.total_ticks += 1;
 
-			// If a process is running, save its registers
-			if let Some(curr_proc) = sched.get_current_process() {
-				let mut curr_proc = curr_proc.lock();
+			// The current core ID
+			let core_id = 0; // TODO
+
+			let now = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Nanosecond)
+				.unwrap_or(sched.last_tick);
+			let elapsed = now.saturating_sub(sched.last_tick);
+			sched.last_tick = now;
+
+			// Account the elapsed time to the interrupted context. If a process is running, save
+			// its registers
+			let stat = &mut sched.cpu_stats[core_id as usize];
+			let mut realtime = false;
+			if let Some((_, curr_proc)) = &sched.curr_proc {
+				if ring < 3 {
+					stat.system += elapsed;
+				} else {
+					stat.user += elapsed;
+				}
 
+				let mut curr_proc = curr_proc.lock();
 				curr_proc.regs = regs.clone();
 				curr_proc.syscalling = ring < 3;
+				curr_proc.fpu_switch_out();
+				realtime = curr_proc.is_realtime();
+			} else {
+				stat.idle += elapsed;
 			}
+			sched.account_rt(now, if realtime { elapsed } else { 0 });
 
-			// The current core ID
-			let core_id = 0; // TODO
 			sched.get_tmp_stack(core_id)
 		};
 
@@ -348,6 +595,10 @@ impl Scheduler {
 			let mut sched = sched_mutex.lock();
 
 			if let Some(next_proc) = sched.get_next_process() {
+				let prev_pid = sched.curr_proc.as_ref().map(|(pid, _)| *pid);
+				if prev_pid != Some(next_proc.0) {
+					sched.context_switches += 1;
+				}
 				// Set the process as current
 				sched.curr_proc = Some(next_proc.clone());
 
@@ -371,7 +622,7 @@ impl Scheduler {
 
 						// Resume execution
 						event::unlock_callbacks(0x20);
-						pic::end_of_interrupt(0x0);
+						idt::end_of_interrupt(0x0);
 						regs.switch(!syscalling);
 					})
 					.unwrap();
@@ -388,7 +639,7 @@ impl Scheduler {
 
 		unsafe {
 			event::unlock_callbacks(0x20);
-			pic::end_of_interrupt(0x0);
+			idt::end_of_interrupt(0x0);
 			crate::loop_reset(tmp_stack);
 		}
 	}
@@ -2,19 +2,26 @@
 
 mod signal_trampoline;
 
+use super::ucounts;
+use super::ucounts::UCount;
 use super::Process;
 use super::State;
+use crate::errno;
+use crate::errno::AllocResult;
+use crate::errno::EResult;
 use crate::errno::Errno;
 use crate::file::perm::Uid;
 use crate::process::oom;
 use crate::process::pid::Pid;
-use crate::time::unit::ClockIdT;
+use crate::util::container::bitfield::Bitfield;
 use core::ffi::c_int;
 use core::ffi::c_void;
 use core::fmt;
 use core::fmt::Debug;
 use core::mem::size_of;
 use core::mem::transmute;
+use core::ops::Deref;
+use core::ptr;
 use core::slice;
 use signal_trampoline::signal_trampoline;
 
@@ -26,6 +33,12 @@ pub const SIG_IGN: *const c_void = 0x0 as _;
 /// The default action for the signal.
 pub const SIG_DFL: *const c_void = 0x1 as _;
 
+/// Signal action flag: the handler takes informations about the signal as arguments.
+pub const SA_SIGINFO: c_int = 0x00000004;
+/// Signal action flag: restart system calls interrupted by the signal instead of failing with
+/// `EINTR`.
+pub const SA_RESTART: c_int = 0x10000000;
+
 /// Notify method: generate a signal
 pub const SIGEV_SIGNAL: c_int = 0;
 /// Notify method: do nothing
@@ -69,57 +82,77 @@ impl Debug for SigVal {
 	}
 }
 
-/// Structure storing signal informations.
+/// Signal code: the signal was sent by a process.
+pub const SI_USER: c_int = 0;
+/// Signal code: the signal was sent by the kernel.
+pub const SI_KERNEL: c_int = 0x80;
+/// `SIGSEGV` code: the address is not mapped.
+pub const SEGV_MAPERR: c_int = 1;
+/// `SIGSEGV` code: the address is mapped, but the access is not permitted.
+pub const SEGV_ACCERR: c_int = 2;
+/// `SIGBUS` code: invalid address alignment.
+pub const BUS_ADRALN: c_int = 1;
+/// `SIGBUS` code: the address does not correspond to an existing object, such as a page of a file
+/// mapping beyond the end of the file.
+pub const BUS_ADRERR: c_int = 2;
+/// `SIGTRAP` code: hardware breakpoint or watchpoint.
+pub const TRAP_HWBKPT: c_int = 4;
+
+/// Fields of [`SigInfo`] depending on the signal.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union SigInfoFields {
+	/// For `SIGSEGV`, `SIGBUS`, `SIGILL`, `SIGFPE` and `SIGTRAP`: memory location which caused
+	/// fault.
+	pub si_addr: *mut c_void,
+	/// Padding to the size of the structure.
+	_pad: [u32; 29],
+}
+
+/// Structure storing signal informations, with the layout of the kernel's ABI.
 #[repr(C)]
+#[derive(Clone, Copy)]
 pub struct SigInfo {
 	/// Signal number.
-	si_signo: i32,
+	pub si_signo: i32,
 	/// An errno value.
-	si_errno: i32,
+	pub si_errno: i32,
 	/// Signal code.
-	si_code: i32,
-	/// Trap number that caused hardware-generated signal.
-	si_trapno: i32,
-	/// Sending process ID.
-	si_pid: Pid,
-	/// Real user ID of sending process.
-	si_uid: Uid,
-	/// Exit value or signal.
-	si_status: i32,
-	/// User time consumed.
-	si_utime: ClockIdT,
-	/// System time consumed.
-	si_stime: ClockIdT,
-	/// Signal value
-	si_value: SigVal,
-	/// POSIX.1b signal.
-	si_int: i32,
-	/// POSIX.1b signal.
-	si_ptr: *mut c_void,
-	/// Timer overrun count.
-	si_overrun: i32,
-	/// Timer ID.
-	si_timerid: i32,
-	/// Memory location which caused fault.
-	si_addr: *mut c_void,
-	/// Band event.
-	si_band: i32, // FIXME long (64bits?)
-	/// File descriptor.
-	si_fd: i32,
-	/// Least significant bit of address.
-	si_addr_lsb: i16,
-	/// Lower bound when address violation.
-	si_lower: *mut c_void,
-	/// Upper bound when address violation.
-	si_upper: *mut c_void,
-	/// Protection key on PTE that caused fault.
-	si_pkey: i32,
-	/// Address of system call instruction.
-	si_call_addr: *mut c_void,
-	/// Number of attempted system call.
-	si_syscall: i32,
-	/// Architecture of attempted system call.
-	si_arch: u32,
+	pub si_code: i32,
+	/// Fields depending on the signal.
+	pub fields: SigInfoFields,
+}
+
+impl SigInfo {
+	/// Creates an instance for a signal without any specific information.
+	pub fn new(sig: &Signal, code: c_int) -> Self {
+		Self {
+			si_signo: sig.get_id() as _,
+			si_errno: 0,
+			si_code: code,
+			fields: SigInfoFields {
+				_pad: [0; 29],
+			},
+		}
+	}
+
+	/// Creates an instance for a signal raised by a fault at address `addr`.
+	pub fn fault(sig: &Signal, code: c_int, addr: *const c_void) -> Self {
+		let mut info = Self::new(sig, code);
+		info.fields.si_addr = addr as _;
+		info
+	}
+}
+
+impl Debug for SigInfo {
+	fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let addr = unsafe { self.fields.si_addr };
+		write!(
+			fmt,
+			"SigInfo {{ si_signo: {}, si_errno: {}, si_code: {}, si_addr: {:p} }}",
+			self.si_signo, self.si_errno, self.si_code, addr
+		)
+	}
 }
 
 // TODO Check the type is correct
@@ -127,20 +160,24 @@ pub struct SigInfo {
 pub type SigSet = u32;
 
 /// Structure storing an action to be executed when a signal is received.
+///
+/// The layout is the one of the kernel's ABI.
 #[repr(C)]
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub struct SigAction {
 	/// The action associated with the signal.
+	///
+	/// If `SA_SIGINFO` is specified in `sa_flags`, the function takes the same arguments as
+	/// `sa_sigaction` in userspace: the signal number, a pointer to a [`SigInfo`] structure and a
+	/// pointer to the context.
 	pub sa_handler: Option<SigHandler>,
-	/// Used instead of `sa_handler` if SA_SIGINFO is specified in `sa_flags`.
-	pub sa_sigaction: Option<extern "C" fn(i32, *mut SigInfo, *mut c_void)>,
-	/// A mask of signals that should be masked while executing the signal
-	/// handler.
-	pub sa_mask: SigSet,
 	/// A set of flags which modifies the behaviour of the signal.
 	pub sa_flags: i32,
 	/// Unused.
 	pub sa_restorer: Option<extern "C" fn()>,
+	/// A mask of signals that should be masked while executing the signal
+	/// handler.
+	pub sa_mask: SigSet,
 }
 
 /// Structure for notification from asynchronous routines.
@@ -193,10 +230,6 @@ impl SignalHandler {
 		match self {
 			Self::Ignore => SigAction {
 				sa_handler: unsafe { transmute::<_, _>(SIG_IGN) },
-				sa_sigaction: #[allow(invalid_value)]
-				unsafe {
-					core::mem::zeroed()
-				},
 				sa_mask: 0,
 				sa_flags: 0,
 				sa_restorer: #[allow(invalid_value)]
@@ -207,10 +240,6 @@ impl SignalHandler {
 
 			Self::Default => SigAction {
 				sa_handler: unsafe { transmute::<_, _>(SIG_DFL) },
-				sa_sigaction: #[allow(invalid_value)]
-				unsafe {
-					core::mem::zeroed()
-				},
 				sa_mask: 0,
 				sa_flags: 0,
 				sa_restorer: #[allow(invalid_value)]
@@ -401,10 +430,7 @@ impl Signal {
 
 	/// Tells whether the signal can be caught.
 	pub fn can_catch(&self) -> bool {
-		!matches!(
-			self,
-			Self::SIGKILL | Self::SIGSEGV | Self::SIGSTOP | Self::SIGSYS
-		)
+		!matches!(self, Self::SIGKILL | Self::SIGSTOP | Self::SIGSYS)
 	}
 
 	/// Executes the action associated with the signal for process `process`.
@@ -464,15 +490,23 @@ impl Signal {
 				}
 			}
 
-			// TODO Handle sa_sigaction, sa_flags and sa_mask
+			// TODO Handle sa_mask
 			SignalHandler::Handler(action) if !process.is_handling_signal() => {
 				// TODO Handle the case where an alternate stack is specified (only if the
 				// action has the flag)
 				// The signal handler stack
 				let stack = process.get_signal_stack();
 
-				let signal_data_size = size_of::<[u32; 3]>();
-				let signal_esp = (stack as usize) - signal_data_size;
+				// Informations about the signal, for handlers using `SA_SIGINFO`
+				let info = process
+					.fault_info
+					.take()
+					.filter(|info| info.si_signo == self.get_id() as i32)
+					.unwrap_or_else(|| SigInfo::new(self, SI_USER));
+				let info_addr = ((stack as usize) - size_of::<SigInfo>()) & !0xf;
+
+				let signal_data_size = size_of::<[u32; 5]>();
+				let signal_esp = info_addr - signal_data_size;
 
 				// FIXME Don't write data out of the stack
 				oom::wrap(|| {
@@ -480,10 +514,17 @@ impl Signal {
 					let mut mem_space = mem_space.lock();
 
 					mem_space.bind();
-					mem_space.alloc(signal_esp as *mut u32, 3)
+					mem_space.alloc(signal_esp as *mut u8, stack as usize - signal_esp)
 				});
-				let signal_data = unsafe { slice::from_raw_parts_mut(signal_esp as *mut u32, 3) };
+				unsafe {
+					ptr::write_unaligned(info_addr as *mut SigInfo, info);
+				}
+				let signal_data = unsafe { slice::from_raw_parts_mut(signal_esp as *mut u32, 5) };
 
+				// The pointer to the context (unsupported)
+				signal_data[4] = 0;
+				// The pointer to the signal's informations
+				signal_data[3] = info_addr as _;
 				// The signal number
 				signal_data[2] = self.get_id() as _;
 				// The pointer to the signal handler
@@ -492,9 +533,10 @@ impl Signal {
 				signal_data[0] = 0;
 
 				let signal_trampoline = unsafe {
-					transmute::<extern "C" fn(*const c_void, i32) -> !, *const c_void>(
-						signal_trampoline,
-					)
+					transmute::<
+						extern "C" fn(*const c_void, i32, *mut SigInfo, *mut c_void) -> !,
+						*const c_void,
+					>(signal_trampoline)
 				};
 
 				let mut regs = process.regs.clone();
@@ -514,3 +556,72 @@ impl Signal {
 		}
 	}
 }
+
+/// A set of pending signals.
+///
+/// Each pending signal is accounted to the real user ID of the process it has been sent to,
+/// until it is delivered or the set is dropped.
+pub struct PendingSignals {
+	/// The set of pending signals.
+	set: Bitfield,
+	/// For each pending signal, the user it is accounted to.
+	uids: [Uid; SIGNALS_COUNT],
+}
+
+impl PendingSignals {
+	/// Creates an empty set.
+	pub fn new() -> AllocResult<Self> {
+		Ok(Self {
+			set: Bitfield::new(SIGNALS_COUNT)?,
+			uids: [0; SIGNALS_COUNT],
+		})
+	}
+
+	/// Makes the signal `sig` pending, accounting it to the user `uid`.
+	///
+	/// `privileged` tells whether the user is exempt from the limit on pending signals.
+	///
+	/// If the signal is already pending, the function does nothing. If the user has too many
+	/// pending signals, the function returns [`errno::EAGAIN`].
+	pub fn add(&mut self, sig: &Signal, uid: Uid, privileged: bool) -> EResult<()> {
+		let id = sig.get_id() as usize;
+		if self.set.is_set(id) {
+			return Ok(());
+		}
+		ucounts::charge(uid, UCount::SigPending, privileged)?;
+		self.set.set(id);
+		self.uids[id] = uid;
+		Ok(())
+	}
+
+	/// Removes the signal `sig` from the set.
+	///
+	/// The function returns `true` if the signal was pending.
+	pub fn remove(&mut self, sig: &Signal) -> bool {
+		let id = sig.get_id() as usize;
+		if !self.set.is_set(id) {
+			return false;
+		}
+		self.set.clear(id);
+		ucounts::uncharge(self.uids[id], UCount::SigPending);
+		true
+	}
+}
+
+impl Deref for PendingSignals {
+	type Target = Bitfield;
+
+	fn deref(&self) -> &Self::Target {
+		&self.set
+	}
+}
+
+impl Drop for PendingSignals {
+	fn drop(&mut self) {
+		for (id, pending) in self.set.iter().enumerate() {
+			if pending {
+				ucounts::uncharge(self.uids[id], UCount::SigPending);
+			}
+		}
+	}
+}
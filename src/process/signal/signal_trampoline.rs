@@ -9,6 +9,7 @@
 //!
 //! When the signal handler returns, the process returns directly to execution.
 
+use super::SigInfo;
 use core::arch::asm;
 use core::ffi::c_void;
 use core::mem::transmute;
@@ -24,12 +25,25 @@ use core::mem::transmute;
 /// Arguments:
 /// - `handler` is a pointer to the handler function for the signal.
 /// - `sig` is the signal number.
+/// - `info` is a pointer to informations about the signal.
+/// - `ctx` is a pointer to the context of the process before the signal.
+///
+/// The handler is always called with the three arguments of a `SA_SIGINFO` handler. Under the C
+/// calling convention, handlers taking only the signal number ignore the other arguments.
 #[no_mangle]
-pub extern "C" fn signal_trampoline(handler: *const c_void, sig: i32) -> ! {
+pub extern "C" fn signal_trampoline(
+	handler: *const c_void,
+	sig: i32,
+	info: *mut SigInfo,
+	ctx: *mut c_void,
+) -> ! {
 	// Calling the signal handler
 	unsafe {
-		let handler = transmute::<*const c_void, unsafe extern "C" fn(i32)>(handler);
-		handler(sig);
+		let handler = transmute::<
+			*const c_void,
+			unsafe extern "C" fn(i32, *mut SigInfo, *mut c_void),
+		>(handler);
+		handler(sig, info, ctx);
 	}
 
 	// Calling `sigreturn` to end signal handling.
@@ -0,0 +1,142 @@
+//! Per-user accounting of kernel resources.
+//!
+//! Some kernel tables are shared by the whole system. To prevent a single user from exhausting
+//! them, the amount of resources held by each real user ID is counted and limited. Privileged
+//! users are counted but not limited.
+
+use crate::errno;
+use crate::errno::EResult;
+use crate::file::perm::Uid;
+use crate::util::container::hashmap::HashMap;
+use crate::util::lock::IntMutex;
+
+/// The maximum number of processes an unprivileged user can have (`RLIMIT_NPROC`).
+pub const NPROC_MAX: usize = 4096;
+/// The maximum number of signals pending for an unprivileged user (`RLIMIT_SIGPENDING`).
+pub const SIGPENDING_MAX: usize = 4096;
+
+/// The number of kinds of accounted resources.
+const UCOUNTS_COUNT: usize = 2;
+
+/// A kind of resource accounted per user.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum UCount {
+	/// Processes, including threads, whose real user ID is the user.
+	Processes,
+	/// Signals pending for processes of the user.
+	SigPending,
+}
+
+impl UCount {
+	/// Returns the maximum amount of the resource an unprivileged user can hold.
+	pub fn get_max(self) -> usize {
+		match self {
+			Self::Processes => NPROC_MAX,
+			Self::SigPending => SIGPENDING_MAX,
+		}
+	}
+}
+
+/// The amount of each resource held by each user.
+///
+/// Users holding no resource are absent.
+static UCOUNTS: IntMutex<HashMap<Uid, [usize; UCOUNTS_COUNT]>> = IntMutex::new(HashMap::new());
+
+/// Returns the amount of the resource `kind` held by the user `uid`.
+pub fn get(uid: Uid, kind: UCount) -> usize {
+	UCOUNTS
+		.lock()
+		.get(&uid)
+		.map(|counts| counts[kind as usize])
+		.unwrap_or(0)
+}
+
+/// Charges one unit of the resource `kind` to the user `uid`.
+///
+/// `privileged` tells whether the user is exempt from the limit.
+///
+/// If the user already holds the maximum amount of the resource, the function returns
+/// [`errno::EAGAIN`].
+pub fn charge(uid: Uid, kind: UCount, privileged: bool) -> EResult<()> {
+	let mut ucounts = UCOUNTS.lock();
+	let mut counts = ucounts.get(&uid).copied().unwrap_or_default();
+	let count = &mut counts[kind as usize];
+	if !privileged && *count >= kind.get_max() {
+		return Err(errno!(EAGAIN));
+	}
+	*count += 1;
+	ucounts.insert(uid, counts)?;
+	Ok(())
+}
+
+/// Refunds one unit of the resource `kind` to the user `uid`.
+pub fn uncharge(uid: Uid, kind: UCount) {
+	let mut ucounts = UCOUNTS.lock();
+	let Some(counts) = ucounts.get_mut(&uid) else {
+		return;
+	};
+	let count = &mut counts[kind as usize];
+	*count = count.saturating_sub(1);
+	if counts.iter().all(|c| *c == 0) {
+		ucounts.remove(&uid);
+	}
+}
+
+/// One unit of a resource charged to a user, refunded when dropped.
+///
+/// This allows refunding the resource if the operation it was charged for fails.
+pub struct Charge {
+	/// The user the resource is charged to.
+	uid: Uid,
+	/// The kind of resource.
+	kind: UCount,
+}
+
+impl Charge {
+	/// Charges one unit of the resource `kind` to the user `uid`.
+	///
+	/// Arguments and errors are the same as [`charge`].
+	pub fn new(uid: Uid, kind: UCount, privileged: bool) -> EResult<Self> {
+		charge(uid, kind, privileged)?;
+		Ok(Self {
+			uid,
+			kind,
+		})
+	}
+
+	/// Keeps the resource charged. Refunding it becomes the responsibility of the caller.
+	pub fn keep(self) {
+		core::mem::forget(self);
+	}
+}
+
+impl Drop for Charge {
+	fn drop(&mut self) {
+		uncharge(self.uid, self.kind);
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test_case]
+	fn ucounts_limit() {
+		let uid = 4243;
+		let max = UCount::SigPending.get_max();
+		for _ in 0..max {
+			charge(uid, UCount::SigPending, false).unwrap();
+		}
+		assert_eq!(charge(uid, UCount::SigPending, false), Err(errno!(EAGAIN)));
+		// Other resources are accounted separately
+		charge(uid, UCount::Processes, false).unwrap();
+		// Privileged users are not limited
+		charge(uid, UCount::SigPending, true).unwrap();
+		assert_eq!(get(uid, UCount::SigPending), max + 1);
+		for _ in 0..=max {
+			uncharge(uid, UCount::SigPending);
+		}
+		uncharge(uid, UCount::Processes);
+		assert!(UCOUNTS.lock().get(&uid).is_none());
+	}
+}
@@ -53,6 +53,12 @@ impl UserDesc {
 		(self.val[12] & 0b1) != 0
 	}
 
+	/// Returns the type of the segment's contents: data, expand-down data or code.
+	#[inline(always)]
+	pub fn get_contents(&self) -> u8 {
+		((self.val[12] >> 1) & 0b11) as _
+	}
+
 	/// Tells whether the segment is writable.
 	#[inline(always)]
 	pub fn is_read_exec_only(&self) -> bool {
@@ -77,17 +83,35 @@ impl UserDesc {
 		(self.val[12] & 0b1000000) != 0
 	}
 
+	/// Tells whether the descriptor is empty, meaning the entry has to be cleared.
+	pub fn is_empty(&self) -> bool {
+		if !self.get_base_addr().is_null() || self.get_limit() != 0 {
+			return false;
+		}
+		// Either every fields are zero, or only `read_exec_only` and `seg_not_present` are set
+		let flags = self.val[12] & 0b1111111;
+		flags == 0 || flags == 0b0101000
+	}
+
 	/// Converts the current descriptor to a GDT entry.
 	pub fn to_descriptor(&self) -> gdt::Entry {
-		let mut access_byte = 0b01110010;
-		if self.is_present() && self.is_usable() {
+		if self.is_empty() {
+			return gdt::Entry::default();
+		}
+
+		// Ring 3, non-system segment, accessed
+		let mut access_byte = 0b01110001 | (self.get_contents() << 2);
+		if self.is_present() {
 			access_byte |= 1 << 7;
 		}
-		if self.is_read_exec_only() {
-			access_byte |= 1 << 3;
+		if !self.is_read_exec_only() {
+			access_byte |= 1 << 1;
 		}
 
 		let mut flags = 0b0000;
+		if self.is_usable() {
+			flags |= 1;
+		}
 		if self.is_32bits() {
 			flags |= 1 << 2;
 		}
@@ -102,6 +126,39 @@ impl UserDesc {
 		entry.set_flags(flags);
 		entry
 	}
+
+	/// Creates a descriptor from the given GDT entry, with the given entry number.
+	pub fn from_descriptor(entry_number: i32, entry: &gdt::Entry) -> Self {
+		let mut val = [0; USER_DESC_SIZE];
+		val[0..4].copy_from_slice(&entry_number.to_ne_bytes());
+		val[4..8].copy_from_slice(&entry.get_base().to_ne_bytes());
+		val[8..12].copy_from_slice(&entry.get_limit().to_ne_bytes());
+
+		let access_byte = entry.get_access_byte();
+		let flags = entry.get_flags();
+		let mut desc_flags = 0;
+		if flags & (1 << 2) != 0 {
+			desc_flags |= 1;
+		}
+		desc_flags |= ((access_byte >> 2) & 0b11) << 1;
+		if access_byte & (1 << 1) == 0 {
+			desc_flags |= 1 << 3;
+		}
+		if flags & (1 << 3) != 0 {
+			desc_flags |= 1 << 4;
+		}
+		if !entry.is_present() {
+			desc_flags |= 1 << 5;
+		}
+		if flags & 1 != 0 {
+			desc_flags |= 1 << 6;
+		}
+		val[12..16].copy_from_slice(&(desc_flags as u32).to_ne_bytes());
+
+		Self {
+			val: val.map(|b| b as _),
+		}
+	}
 }
 
 impl fmt::Debug for UserDesc {
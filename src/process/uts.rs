@@ -0,0 +1,38 @@
+//! A UTS namespace holds the hostname and the NIS domain name of the system, as seen by the
+//! processes belonging to it.
+//!
+//! Child processes share the namespace of their parent, unless created with `CLONE_NEWUTS`, in
+//! which case they get a copy of it.
+
+use crate::errno::AllocResult;
+use crate::util::container::vec::Vec;
+
+/// The maximum length of the names in a UTS namespace, not including the terminating null byte.
+pub const UTS_NAME_LEN: usize = 64;
+
+/// A UTS namespace.
+#[derive(Debug)]
+pub struct UtsNamespace {
+	/// The hostname.
+	pub hostname: Vec<u8>,
+	/// The NIS domain name.
+	pub domainname: Vec<u8>,
+}
+
+impl UtsNamespace {
+	/// Creates the initial namespace, in which names are not set yet.
+	pub fn new() -> AllocResult<Self> {
+		Ok(Self {
+			hostname: Vec::from_slice(b"(none)")?,
+			domainname: Vec::from_slice(b"(none)")?,
+		})
+	}
+
+	/// Returns a copy of the namespace.
+	pub fn duplicate(&self) -> AllocResult<Self> {
+		Ok(Self {
+			hostname: Vec::from_slice(&self.hostname)?,
+			domainname: Vec::from_slice(&self.domainname)?,
+		})
+	}
+}
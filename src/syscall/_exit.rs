@@ -2,29 +2,81 @@
 //! status code.
 
 use crate::errno::Errno;
+use crate::process::futex;
+use crate::process::futex::FutexKey;
+use crate::process::mem_space::ptr::SyscallPtr;
 use crate::process::scheduler;
 use crate::process::Process;
 use core::ffi::c_int;
 use macros::syscall;
 
+/// Clears the TID at the `clear_child_tid` address of the process `proc`, then wakes up a process
+/// waiting on it, such as a thread joining `proc`.
+///
+/// Errors are ignored since the process is exiting.
+fn clear_child_tid(proc: &Process) {
+	let Some(ptr) = proc.get_clear_child_tid() else {
+		return;
+	};
+	let Some(mem_space) = proc.get_mem_space().cloned() else {
+		return;
+	};
+	let addr = ptr.as_ptr() as usize;
+
+	{
+		let mut mem_space_guard = mem_space.lock();
+		let tid: SyscallPtr<i32> = addr.into();
+		let Ok(Some(tid)) = tid.get_mut(&mut mem_space_guard) else {
+			return;
+		};
+		*tid = 0;
+	}
+	if let Ok(key) = FutexKey::new(&mem_space, addr, false) {
+		let _ = futex::wake(key, 1, futex::BITSET_MATCH_ANY);
+	}
+}
+
 /// Exits the current process.
 ///
 /// Arguments:
 /// - `status` is the exit status.
-/// - `thread_group`: if `true`, the function exits the whole process group.
+/// - `thread_group`: if `true`, the function exits the whole thread group.
+///
+/// Resources shared between threads of the group (memory space, file descriptors table, ...)
+/// are released only when the last thread referencing them is removed.
 pub fn do_exit(status: u32, thread_group: bool) -> ! {
-	let (_pid, _tid) = {
+	// No other thread of the group may run while the group is exiting
+	cli!();
+
+	if thread_group {
 		let proc_mutex = Process::current_assert();
-		let mut proc = proc_mutex.lock();
+		let pid = proc_mutex.lock().pid;
 
-		proc.exit(status, false);
+		// Exit every other thread first so that the last one to exit reports the group to the
+		// parent. The current thread is not locked while doing so since exiting a thread may
+		// lock the group's leader
+		let mut i = 0;
+		loop {
+			let Some(tid) = proc_mutex.lock().get_thread(i) else {
+				break;
+			};
+			i += 1;
+			if tid == pid {
+				continue;
+			}
+			if let Some(thread_mutex) = Process::get_by_pid(tid) {
+				thread_mutex.lock().exit(status, false);
+			}
+		}
+	}
 
-		(proc.pid, proc.tid)
-	};
+	// TODO Robust futex lists and System V semaphore undo lists are not handled
 
-	if thread_group {
-		// TODO Iterate on every process of thread group `tid`, except the
-		// process with pid `pid`
+	{
+		let proc_mutex = Process::current_assert();
+		let mut proc = proc_mutex.lock();
+		clear_child_tid(&proc);
+		proc.exit(status, false);
 	}
 
 	scheduler::end_tick();
@@ -32,7 +84,7 @@ pub fn do_exit(status: u32, thread_group: bool) -> ! {
 	unreachable!();
 }
 
-#[syscall]
+#[syscall(0x001)]
 pub fn _exit(status: c_int) -> Result<i32, Errno> {
 	do_exit(status as _, false);
 }
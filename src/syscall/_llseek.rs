@@ -9,13 +9,13 @@ use core::ffi::c_ulong;
 use macros::syscall;
 
 /// Sets the offset from the given value.
-const SEEK_SET: u32 = 0;
+pub const SEEK_SET: u32 = 0;
 /// Sets the offset relative to the current offset.
-const SEEK_CUR: u32 = 1;
+pub const SEEK_CUR: u32 = 1;
 /// Sets the offset relative to the end of the file.
-const SEEK_END: u32 = 2;
+pub const SEEK_END: u32 = 2;
 
-#[syscall]
+#[syscall(0x08c)]
 pub fn _llseek(
 	fd: c_uint,
 	offset_high: c_ulong,
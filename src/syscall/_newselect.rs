@@ -8,7 +8,7 @@ use crate::time::unit::Timeval;
 use core::ffi::c_int;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x08e)]
 pub fn _newselect(
 	nfds: c_int,
 	readfds: SyscallPtr<FDSet>,
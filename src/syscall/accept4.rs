@@ -0,0 +1,132 @@
+//! The `accept4` system call accepts a connection on a listening socket.
+
+use crate::errno::Errno;
+use crate::file::buffer;
+use crate::file::buffer::socket::Socket;
+use crate::file::buffer::socket::SocketState;
+use crate::file::buffer::Buffer;
+use crate::file::fd::FD_CLOEXEC;
+use crate::file::open_file;
+use crate::file::open_file::OpenFile;
+use crate::file::open_file::O_NONBLOCK;
+use crate::file::vfs;
+use crate::net::SOCK_CLOEXEC;
+use crate::net::SOCK_NONBLOCK;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::scheduler;
+use crate::process::Process;
+use crate::util::io;
+use core::any::Any;
+use core::ffi::c_int;
+use macros::syscall;
+
+#[syscall(0x16c)]
+pub fn accept4(
+	sockfd: c_int,
+	addr: SyscallSlice<u8>,
+	addrlen: SyscallPtr<isize>,
+	flags: c_int,
+) -> Result<i32, Errno> {
+	if sockfd < 0 {
+		return Err(errno!(EBADF));
+	}
+	if flags & !(SOCK_NONBLOCK | SOCK_CLOEXEC) != 0 {
+		return Err(errno!(EINVAL));
+	}
+
+	let (proc, mem_space, fds_mutex, open_file) = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+
+		let mem_space = proc.get_mem_space().unwrap().clone();
+
+		let fds_mutex = proc.get_fds().unwrap().clone();
+		let open_file_mutex = fds_mutex
+			.lock()
+			.get_fd(sockfd as _)
+			.ok_or_else(|| errno!(EBADF))?
+			.get_open_file()
+			.clone();
+
+		drop(proc);
+		(proc_mutex, mem_space, fds_mutex, open_file_mutex)
+	};
+
+	// Get socket
+	let (sock_mutex, nonblock) = {
+		let open_file = open_file.lock();
+		let sock_mutex = buffer::get(open_file.get_location()).ok_or_else(|| errno!(ENOENT))?;
+		(sock_mutex, open_file.get_flags() & O_NONBLOCK != 0)
+	};
+
+	// Wait for a connection
+	let conn = loop {
+		super::util::signal_check()?;
+
+		{
+			let mut sock = sock_mutex.lock();
+			let sock = (&mut *sock as &mut dyn Any)
+				.downcast_mut::<Socket>()
+				.ok_or_else(|| errno!(ENOTSOCK))?;
+			if sock.state() != SocketState::Listening {
+				return Err(errno!(EINVAL));
+			}
+
+			if let Some(conn) = sock.accept() {
+				break conn;
+			}
+			if nonblock {
+				return Err(errno!(EAGAIN));
+			}
+
+			let mut proc = proc.lock();
+			sock.add_waiting_process(&mut proc, io::POLLIN | io::POLLERR)?;
+		}
+
+		// Make current process sleep
+		scheduler::end_tick();
+	};
+
+	// Write the peer's address
+	{
+		let mut mem_space_guard = mem_space.lock();
+		if let Some(addrlen_val) = addrlen.get_mut(&mut mem_space_guard)? {
+			if *addrlen_val < 0 {
+				return Err(errno!(EINVAL));
+			}
+			let addrlen_val = *addrlen_val as usize;
+
+			let addr_slice = addr
+				.get_mut(&mut mem_space_guard, addrlen_val)?
+				.ok_or_else(|| errno!(EFAULT))?;
+			let len = conn.lock().read_peername(addr_slice) as _;
+
+			// Update actual length of the address
+			let addrlen_val = addrlen
+				.get_mut(&mut mem_space_guard)?
+				.ok_or_else(|| errno!(EFAULT))?;
+			*addrlen_val = len;
+		}
+	}
+
+	// Create the file descriptor for the connection
+	let loc = buffer::register(None, conn)?;
+	let file = vfs::get_file_by_location(&loc)?;
+
+	let mut open_flags = open_file::O_RDWR;
+	if flags & SOCK_NONBLOCK != 0 {
+		open_flags |= O_NONBLOCK;
+	}
+	let open_file = OpenFile::new(file, open_flags)?;
+
+	let fd_flags = if flags & SOCK_CLOEXEC != 0 {
+		FD_CLOEXEC
+	} else {
+		0
+	};
+	let mut fds = fds_mutex.lock();
+	let fd = fds.create_fd(fd_flags, open_file)?;
+
+	Ok(fd.get_id() as _)
+}
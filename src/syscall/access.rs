@@ -106,7 +106,7 @@ pub fn do_access(
 	Ok(0)
 }
 
-#[syscall]
+#[syscall(0x021)]
 pub fn access(pathname: SyscallString, mode: c_int) -> Result<i32, Errno> {
 	do_access(None, pathname, mode, None)
 }
@@ -1,11 +1,78 @@
 //! The `arch_prctl` system call sets architecture-specific thread state.
+//!
+//! Under x86, the base addresses of the `fs` and `gs` segments are set by allocating a TLS entry
+//! in the GDT, then loading its selector in the corresponding segment register.
 
+use super::set_thread_area;
+use crate::errno;
 use crate::errno::Errno;
+use crate::gdt;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::Process;
 use core::ffi::c_int;
 use macros::syscall;
 
-#[syscall]
-pub fn arch_prctl(_code: c_int, _addr: usize) -> Result<i32, Errno> {
-	// TODO
-	Err(errno!(EINVAL))
+/// Code: set the base address of the `gs` segment.
+const ARCH_SET_GS: c_int = 0x1001;
+/// Code: set the base address of the `fs` segment.
+const ARCH_SET_FS: c_int = 0x1002;
+/// Code: get the base address of the `fs` segment.
+const ARCH_GET_FS: c_int = 0x1003;
+/// Code: get the base address of the `gs` segment.
+const ARCH_GET_GS: c_int = 0x1004;
+
+/// Returns a 32 bits, flat, writable data segment for userspace, starting at `base`.
+fn make_entry(base: u32) -> gdt::Entry {
+	let mut entry = gdt::Entry::default();
+	entry.set_base(base);
+	entry.set_limit(0xfffff);
+	entry.set_access_byte(0b11110011);
+	entry.set_flags(0b1101);
+	entry
+}
+
+#[syscall(0x180)]
+pub fn arch_prctl(code: c_int, addr: usize) -> Result<i32, Errno> {
+	let proc_mutex = Process::current_assert();
+	let mut proc = proc_mutex.lock();
+
+	let selector = match code {
+		ARCH_SET_FS | ARCH_GET_FS => regs.fs,
+		ARCH_SET_GS | ARCH_GET_GS => regs.gs,
+		_ => return Err(errno!(EINVAL)),
+	};
+	let id = set_thread_area::selector_to_entry(selector);
+
+	if matches!(code, ARCH_GET_FS | ARCH_GET_GS) {
+		let base = id
+			.map(|id| proc.get_tls_entries()[id].get_base())
+			.unwrap_or(0);
+
+		let mem_space = proc.get_mem_space().unwrap().clone();
+		let mut mem_space_guard = mem_space.lock();
+		let addr_ptr: SyscallPtr<u32> = addr.into();
+		let addr_ref = addr_ptr
+			.get_mut(&mut mem_space_guard)?
+			.ok_or_else(|| errno!(EFAULT))?;
+		*addr_ref = base;
+		return Ok(0);
+	}
+
+	// Reuse the entry already designated by the segment register, if any
+	let id = match id {
+		Some(id) => id,
+		None => set_thread_area::get_free_entry(&mut proc)?,
+	};
+	proc.get_tls_entries()[id] = make_entry(addr as _);
+	proc.update_tls(id);
+	gdt::flush();
+
+	// Load the entry's selector in the segment register when returning to userspace
+	let selector = set_thread_area::entry_to_selector(id);
+	if code == ARCH_SET_FS {
+		regs.fs = selector;
+	} else {
+		regs.gs = selector;
+	}
+	Ok(0)
 }
@@ -3,13 +3,14 @@
 use crate::errno::Errno;
 use crate::file::buffer;
 use crate::file::buffer::socket::Socket;
+use crate::net::unix;
 use crate::process::mem_space::ptr::SyscallSlice;
 use crate::process::Process;
 use core::any::Any;
 use core::ffi::c_int;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x169)]
 pub fn bind(sockfd: c_int, addr: SyscallSlice<u8>, addrlen: isize) -> Result<i32, Errno> {
 	if sockfd < 0 {
 		return Err(errno!(EBADF));
@@ -41,6 +42,12 @@ pub fn bind(sockfd: c_int, addr: SyscallSlice<u8>, addrlen: isize) -> Result<i32
 		.get(&mut mem_space_guard, addrlen as _)?
 		.ok_or(errno!(EFAULT))?;
 
+	if let Some(name) = unix::abstract_name(addr_slice) {
+		if sock.is_bound() {
+			return Err(errno!(EINVAL));
+		}
+		unix::bind_abstract(name, &sock_mutex)?;
+	}
 	sock.bind(addr_slice)?;
 	Ok(0)
 }
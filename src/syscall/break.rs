@@ -4,7 +4,7 @@ use crate::errno;
 use crate::errno::Errno;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x011)]
 pub fn r#break() -> Result<i32, Errno> {
 	Err(errno!(ENOSYS))
 }
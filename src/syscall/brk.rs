@@ -2,13 +2,12 @@
 //! process, thus allowing memory allocations.
 
 use crate::errno::Errno;
-use crate::process::regs::Regs;
 use crate::process::Process;
 use core::ffi::c_void;
+use macros::syscall;
 
-pub fn brk(regs: &Regs) -> Result<i32, Errno> {
-	let addr = regs.ebx as *mut c_void;
-
+#[syscall(0x02d)]
+pub fn brk(addr: *mut c_void) -> Result<i32, Errno> {
 	let proc_mutex = Process::current_assert();
 	let proc = proc_mutex.lock();
 
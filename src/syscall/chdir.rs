@@ -11,7 +11,7 @@ use crate::process::Process;
 use crate::util::ptr::arc::Arc;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x00c)]
 pub fn chdir(path: SyscallString) -> Result<i32, Errno> {
 	let (new_cwd, ap) = {
 		let proc_mutex = Process::current_assert();
@@ -8,7 +8,7 @@ use crate::process::Process;
 use core::ffi::c_int;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x00f)]
 pub fn chmod(pathname: SyscallString, mode: c_int) -> Result<i32, Errno> {
 	let (path, ap) = {
 		let proc_mutex = Process::current_assert();
@@ -49,7 +49,7 @@ pub fn do_chown(
 	Ok(0)
 }
 
-#[syscall]
+#[syscall(0x0b6)]
 pub fn chown(pathname: SyscallString, owner: c_int, group: c_int) -> EResult<i32> {
 	do_chown(pathname, owner, group, true)
 }
@@ -5,7 +5,7 @@ use crate::process::mem_space::ptr::SyscallString;
 use core::ffi::c_int;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x0d4)]
 pub fn chown32(pathname: SyscallString, owner: c_int, group: c_int) -> EResult<i32> {
 	super::chown::do_chown(pathname, owner, group, true)
 }
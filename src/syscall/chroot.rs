@@ -9,7 +9,7 @@ use crate::util::ptr::arc::Arc;
 use crate::vfs;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x03d)]
 pub fn chroot(path: SyscallString) -> Result<i32, Errno> {
 	let proc_mutex = Process::current_assert();
 	let mut proc = proc_mutex.lock();
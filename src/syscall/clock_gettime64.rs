@@ -8,7 +8,7 @@ use crate::time::unit::ClockIdT;
 use crate::time::unit::Timespec;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x193)]
 pub fn clock_gettime64(clockid: ClockIdT, tp: SyscallPtr<Timespec>) -> Result<i32, Errno> {
 	let curr_time = clock::current_time_struct::<Timespec>(clockid)?;
 
@@ -1,5 +1,7 @@
 //! The `clone` system call creates a child process.
 
+use super::set_thread_area;
+use crate::errno;
 use crate::errno::Errno;
 use crate::process::mem_space::ptr::SyscallPtr;
 use crate::process::scheduler;
@@ -8,6 +10,7 @@ use crate::process::ForkOptions;
 use crate::process::Process;
 use crate::util::ptr::arc::Arc;
 use core::ffi::c_void;
+use core::ptr::NonNull;
 use macros::syscall;
 
 /// TODO doc
@@ -30,7 +33,7 @@ const CLONE_PTRACE: i32 = 0x2000;
 const CLONE_VFORK: i32 = 0x4000;
 /// TODO doc
 const CLONE_PARENT: i32 = 0x8000;
-/// TODO doc
+/// If specified, the child process is a thread in the same thread group as the parent.
 const CLONE_THREAD: i32 = 0x10000;
 /// TODO doc
 const CLONE_NEWNS: i32 = 0x20000;
@@ -50,7 +53,7 @@ const CLONE_UNTRACED: i32 = 0x800000;
 const CLONE_CHILD_SETTID: i32 = 0x1000000;
 /// TODO doc
 const CLONE_NEWCGROUP: i32 = 0x2000000;
-/// TODO doc
+/// Creates the child process in a new UTS namespace.
 const CLONE_NEWUTS: i32 = 0x4000000;
 /// TODO doc
 const CLONE_NEWIPC: i32 = 0x8000000;
@@ -62,13 +65,13 @@ const CLONE_NEWPID: i32 = 0x20000000;
 const CLONE_NEWNET: i32 = 0x40000000;
 
 // TODO Check args types
-#[syscall]
+#[syscall(0x078)]
 pub fn clone(
 	flags: i32,
 	stack: *mut c_void,
 	_parent_tid: SyscallPtr<i32>,
 	tls: i32,
-	_child_tid: SyscallPtr<i32>,
+	child_tid: SyscallPtr<i32>,
 ) -> Result<i32, Errno> {
 	let new_tid = {
 		// The current process
@@ -83,10 +86,24 @@ pub fn clone(
 			todo!();
 		}
 
+		// Threads share signal handlers, which requires sharing the memory space
+		if flags & CLONE_THREAD != 0 && flags & CLONE_SIGHAND == 0 {
+			return Err(errno!(EINVAL));
+		}
+		if flags & CLONE_SIGHAND != 0 && flags & CLONE_VM == 0 {
+			return Err(errno!(EINVAL));
+		}
+		// Creating namespaces is a privileged operation
+		if flags & CLONE_NEWUTS != 0 && !curr_proc.access_profile.is_privileged() {
+			return Err(errno!(EPERM));
+		}
+
 		let fork_options = ForkOptions {
 			share_memory: flags & CLONE_VM != 0,
 			share_fd: flags & CLONE_FILES != 0,
 			share_sighand: flags & CLONE_SIGHAND != 0,
+			thread: flags & CLONE_THREAD != 0,
+			new_uts: flags & CLONE_NEWUTS != 0,
 
 			vfork: flags & CLONE_VFORK != 0,
 		};
@@ -105,16 +122,17 @@ pub fn clone(
 		};
 		// Setting TLS
 		if flags & CLONE_SETTLS != 0 {
-			let _tls: SyscallPtr<UserDesc> = (tls as usize).into();
+			let tls: SyscallPtr<UserDesc> = (tls as usize).into();
 
-			// TODO
-			todo!();
+			let mem_space = curr_proc.get_mem_space().unwrap().clone();
+			let mut mem_space_guard = mem_space.lock();
+			let info = tls.get_mut(&mut mem_space_guard)?.ok_or(errno!(EFAULT))?;
+			set_thread_area::do_set_thread_area(&mut new_proc, info, false)?;
 		}
 		new_proc.regs = new_regs;
 
 		if flags & CLONE_CHILD_CLEARTID != 0 {
-			// TODO new_proc.set_clear_child_tid(child_tid);
-			todo!();
+			new_proc.set_clear_child_tid(NonNull::new(child_tid.as_ptr_mut()));
 		}
 		if flags & CLONE_CHILD_SETTID != 0 {
 			// TODO
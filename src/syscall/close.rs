@@ -2,22 +2,30 @@
 
 use crate::errno;
 use crate::errno::Errno;
+use crate::net::unix;
 use crate::process::Process;
 use core::ffi::c_int;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x006)]
 pub fn close(fd: c_int) -> Result<i32, Errno> {
 	if fd < 0 {
 		return Err(errno!(EBADF));
 	}
 
-	let proc_mutex = Process::current_assert();
-	let proc = proc_mutex.lock();
+	{
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
 
-	let fds_mutex = proc.get_fds().unwrap();
-	let mut fds = fds_mutex.lock();
+		let fds_mutex = proc.get_fds().unwrap();
+		let mut fds = fds_mutex.lock();
 
-	fds.close_fd(fd as _)?;
+		fds.close_fd(fd as _)?;
+	}
+
+	// Closing the file may have made sockets unreachable
+	if unix::is_pending() {
+		unix::collect()?;
+	}
 	Ok(0)
 }
@@ -2,15 +2,22 @@
 
 use crate::errno::Errno;
 use crate::file::buffer;
+use crate::file::buffer::socket;
 use crate::file::buffer::socket::Socket;
+use crate::file::buffer::socket::SocketState;
+use crate::file::buffer::socket::UCred;
+use crate::file::buffer::Buffer;
+use crate::file::open_file::O_NONBLOCK;
 use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::scheduler;
 use crate::process::Process;
+use crate::util::io;
 use core::any::Any;
 use core::ffi::c_int;
 use macros::syscall;
 
 /// The implementation of the `connect` syscall.
-#[syscall]
+#[syscall(0x16a)]
 pub fn connect(sockfd: c_int, addr: SyscallSlice<u8>, addrlen: isize) -> Result<i32, Errno> {
 	if sockfd < 0 {
 		return Err(errno!(EBADF));
@@ -19,27 +26,80 @@ pub fn connect(sockfd: c_int, addr: SyscallSlice<u8>, addrlen: isize) -> Result<
 		return Err(errno!(EINVAL));
 	}
 
-	let proc_mutex = Process::current_assert();
-	let proc = proc_mutex.lock();
+	let (proc, mem_space, open_file) = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+
+		let mem_space = proc.get_mem_space().unwrap().clone();
+
+		let fds_mutex = proc.get_fds().unwrap().clone();
+		let fds = fds_mutex.lock();
+		let open_file_mutex = fds
+			.get_fd(sockfd as _)
+			.ok_or_else(|| errno!(EBADF))?
+			.get_open_file()
+			.clone();
+
+		drop(proc);
+		(proc_mutex, mem_space, open_file_mutex)
+	};
 
 	// Get socket
-	let fds_mutex = proc.get_fds().unwrap();
-	let fds = fds_mutex.lock();
-	let fd = fds.get_fd(sockfd as _).ok_or_else(|| errno!(EBADF))?;
-	let open_file_mutex = fd.get_open_file();
-	let open_file = open_file_mutex.lock();
-	let sock_mutex = buffer::get(open_file.get_location()).ok_or_else(|| errno!(ENOENT))?;
-	let mut sock = sock_mutex.lock();
-	let _sock = (&mut *sock as &mut dyn Any)
-		.downcast_mut::<Socket>()
-		.ok_or_else(|| errno!(ENOTSOCK))?;
-
-	let mem_space_mutex = proc.get_mem_space().unwrap();
-	let mem_space = mem_space_mutex.lock();
-	let _addr_slice = addr
-		.get(&mem_space, addrlen as _)?
-		.ok_or_else(|| errno!(EFAULT))?;
-
-	// TODO connect socket
-	todo!();
+	let (sock_mutex, nonblock) = {
+		let open_file = open_file.lock();
+		let sock_mutex = buffer::get(open_file.get_location()).ok_or_else(|| errno!(ENOENT))?;
+		(sock_mutex, open_file.get_flags() & O_NONBLOCK != 0)
+	};
+
+	{
+		let mut sock = sock_mutex.lock();
+		let sock = (&mut *sock as &mut dyn Any)
+			.downcast_mut::<Socket>()
+			.ok_or_else(|| errno!(ENOTSOCK))?;
+
+		let mem_space = mem_space.lock();
+		let addr_slice = addr
+			.get(&mem_space, addrlen as _)?
+			.ok_or_else(|| errno!(EFAULT))?;
+		sock.connect(addr_slice)?;
+		sock.set_cred(UCred::from_process(&proc.lock()));
+	}
+	socket::register_connected(&sock_mutex)?;
+
+	{
+		let mut sock = sock_mutex.lock();
+		let sock = (&mut *sock as &mut dyn Any)
+			.downcast_mut::<Socket>()
+			.ok_or_else(|| errno!(ENOTSOCK))?;
+		if nonblock && sock.state() == SocketState::Connecting {
+			return Err(errno!(EINPROGRESS));
+		}
+	}
+
+	// Wait for the connection to be established
+	loop {
+		super::util::signal_check()?;
+
+		{
+			let mut sock = sock_mutex.lock();
+			let sock = (&mut *sock as &mut dyn Any)
+				.downcast_mut::<Socket>()
+				.ok_or_else(|| errno!(ENOTSOCK))?;
+
+			if let Some(errno) = sock.take_error() {
+				return Err(errno);
+			}
+			match sock.state() {
+				SocketState::Connected => return Ok(0),
+				SocketState::Connecting => {}
+				_ => return Err(errno!(ECONNREFUSED)),
+			}
+
+			let mut proc = proc.lock();
+			sock.add_waiting_process(&mut proc, io::POLLOUT | io::POLLERR | io::POLLHUP)?;
+		}
+
+		// Make current process sleep
+		scheduler::end_tick();
+	}
 }
@@ -8,7 +8,7 @@ use core::ffi::c_int;
 use macros::syscall;
 
 // TODO Check args type
-#[syscall]
+#[syscall(0x008)]
 pub fn creat(pathname: SyscallString, mode: c_int) -> Result<i32, Errno> {
 	let flags = open_file::O_CREAT | open_file::O_WRONLY | open_file::O_TRUNC;
 	open::open_(pathname, flags, mode as _)
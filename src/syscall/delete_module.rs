@@ -2,6 +2,8 @@
 
 use crate::errno;
 use crate::errno::Errno;
+use crate::file::open_file::O_NONBLOCK;
+use crate::file::open_file::O_TRUNC;
 use crate::module;
 use crate::process::mem_space::ptr::SyscallString;
 use crate::process::Process;
@@ -9,10 +11,14 @@ use crate::util::container::string::String;
 use core::ffi::c_uint;
 use macros::syscall;
 
-// TODO handle flags
+#[syscall(0x081)]
+pub fn delete_module(name: SyscallString, flags: c_uint) -> Result<i32, Errno> {
+	if flags & !((O_NONBLOCK | O_TRUNC) as c_uint) != 0 {
+		return Err(errno!(EINVAL));
+	}
+	// Unloading a module in use is forced with `O_TRUNC`
+	let force = flags & O_TRUNC as c_uint != 0;
 
-#[syscall]
-pub fn delete_module(name: SyscallString, _flags: c_uint) -> Result<i32, Errno> {
 	let name = {
 		let proc_mutex = Process::current_assert();
 		let proc = proc_mutex.lock();
@@ -28,8 +34,7 @@ pub fn delete_module(name: SyscallString, _flags: c_uint) -> Result<i32, Errno>
 		String::try_from(name)?
 	};
 
-	// TODO handle dependency (don't unload a module that is required by another)
-	module::remove(&name);
+	module::remove(&name, force)?;
 
 	Ok(0)
 }
@@ -6,7 +6,7 @@ use crate::process::Process;
 use core::ffi::c_int;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x029)]
 pub fn dup(oldfd: c_int) -> Result<i32, Errno> {
 	if oldfd < 0 {
 		return Err(errno!(EBADF));
@@ -3,13 +3,14 @@
 
 use crate::errno::Errno;
 use crate::file::fd::NewFDConstraint;
+use crate::limits;
 use crate::process::Process;
 use core::ffi::c_int;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x03f)]
 pub fn dup2(oldfd: c_int, newfd: c_int) -> Result<i32, Errno> {
-	if oldfd < 0 || newfd < 0 {
+	if oldfd < 0 || newfd < 0 || newfd as u32 >= limits::OPEN_MAX {
 		return Err(errno!(EBADF));
 	}
 
@@ -19,6 +20,12 @@ pub fn dup2(oldfd: c_int, newfd: c_int) -> Result<i32, Errno> {
 	let fds_mutex = proc.get_fds().unwrap();
 	let mut fds = fds_mutex.lock();
 
+	// Duplicating a valid file descriptor onto itself does nothing
+	if oldfd == newfd {
+		fds.get_fd(oldfd as _).ok_or_else(|| errno!(EBADF))?;
+		return Ok(newfd);
+	}
+
 	let newfd = fds.duplicate_fd(oldfd as _, NewFDConstraint::Fixed(newfd as _), false)?;
 	Ok(newfd.get_id() as _)
 }
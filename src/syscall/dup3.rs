@@ -0,0 +1,32 @@
+//! The `dup3` syscall allows to duplicate a file descriptor, specifying the id of the newly
+//! created file descriptor and its flags.
+
+use crate::errno::Errno;
+use crate::file::fd::NewFDConstraint;
+use crate::file::open_file::O_CLOEXEC;
+use crate::limits;
+use crate::process::Process;
+use core::ffi::c_int;
+use macros::syscall;
+
+#[syscall(0x14a)]
+pub fn dup3(oldfd: c_int, newfd: c_int, flags: c_int) -> Result<i32, Errno> {
+	if oldfd < 0 || newfd < 0 || newfd as u32 >= limits::OPEN_MAX {
+		return Err(errno!(EBADF));
+	}
+	// Unlike `dup2`, duplicating a file descriptor onto itself is an error
+	if flags & !O_CLOEXEC != 0 || oldfd == newfd {
+		return Err(errno!(EINVAL));
+	}
+
+	let proc_mutex = Process::current_assert();
+	let proc = proc_mutex.lock();
+
+	let fds_mutex = proc.get_fds().unwrap();
+	let mut fds = fds_mutex.lock();
+
+	// The close-on-exec flag is set atomically with the creation of the file descriptor
+	let cloexec = flags & O_CLOEXEC != 0;
+	let newfd = fds.duplicate_fd(oldfd as _, NewFDConstraint::Fixed(newfd as _), cloexec)?;
+	Ok(newfd.get_id() as _)
+}
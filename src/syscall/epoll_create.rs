@@ -0,0 +1,50 @@
+//! The `epoll_create` system call creates an epoll instance.
+
+use crate::errno;
+use crate::errno::Errno;
+use crate::file::buffer;
+use crate::file::buffer::epoll::EpollBuffer;
+use crate::file::fd::FD_CLOEXEC;
+use crate::file::open_file;
+use crate::file::open_file::OpenFile;
+use crate::file::vfs;
+use crate::process::Process;
+use crate::util::lock::Mutex;
+use crate::util::ptr::arc::Arc;
+use crate::util::TryDefault;
+use core::ffi::c_int;
+use macros::syscall;
+
+/// Creates an epoll instance and returns its file descriptor.
+///
+/// `flags` are the flags of the instance. The only accepted flag is `EPOLL_CLOEXEC`.
+pub fn do_epoll_create(flags: c_int) -> Result<i32, Errno> {
+	if flags & !open_file::O_CLOEXEC != 0 {
+		return Err(errno!(EINVAL));
+	}
+
+	let proc_mutex = Process::current_assert();
+	let fds_mutex = proc_mutex.lock().get_fds().unwrap().clone();
+
+	let loc = buffer::register(None, Arc::new(Mutex::new(EpollBuffer::try_default()?))?)?;
+	let file = vfs::get_file_by_location(&loc)?;
+	let open_file = OpenFile::new(file, open_file::O_RDWR)?;
+
+	let fd_flags = if flags & open_file::O_CLOEXEC != 0 {
+		FD_CLOEXEC
+	} else {
+		0
+	};
+	let mut fds = fds_mutex.lock();
+	let fd = fds.create_fd(fd_flags, open_file)?;
+
+	Ok(fd.get_id() as _)
+}
+
+#[syscall(0x0fe)]
+pub fn epoll_create(size: c_int) -> Result<i32, Errno> {
+	if size <= 0 {
+		return Err(errno!(EINVAL));
+	}
+	do_epoll_create(0)
+}
@@ -0,0 +1,11 @@
+//! The `epoll_create1` system call creates an epoll instance with the given flags.
+
+use super::epoll_create::do_epoll_create;
+use crate::errno::Errno;
+use core::ffi::c_int;
+use macros::syscall;
+
+#[syscall(0x149)]
+pub fn epoll_create1(flags: c_int) -> Result<i32, Errno> {
+	do_epoll_create(flags)
+}
@@ -0,0 +1,95 @@
+//! The `epoll_ctl` system call registers, modifies or unregisters a file descriptor on an epoll
+//! instance.
+
+use crate::errno;
+use crate::errno::Errno;
+use crate::file::buffer;
+use crate::file::buffer::epoll::EpollBuffer;
+use crate::file::buffer::epoll::EpollEvent;
+use crate::file::buffer::epoll::EPOLL_CTL_ADD;
+use crate::file::buffer::epoll::EPOLL_CTL_DEL;
+use crate::file::buffer::epoll::EPOLL_CTL_MOD;
+use crate::file::FileContent;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::Process;
+use core::any::Any;
+use core::ffi::c_int;
+use macros::syscall;
+
+#[syscall(0x0ff)]
+pub fn epoll_ctl(
+	epfd: c_int,
+	op: c_int,
+	fd: c_int,
+	event: SyscallPtr<EpollEvent>,
+) -> Result<i32, Errno> {
+	if epfd < 0 || fd < 0 {
+		return Err(errno!(EBADF));
+	}
+
+	let proc_mutex = Process::current_assert();
+	let (mem_space, fds_mutex) = {
+		let proc = proc_mutex.lock();
+		let mem_space = proc.get_mem_space().unwrap().clone();
+		let fds_mutex = proc.get_fds().unwrap().clone();
+		(mem_space, fds_mutex)
+	};
+
+	let event = if op != EPOLL_CTL_DEL {
+		let mem_space_guard = mem_space.lock();
+		event
+			.get(&mem_space_guard)?
+			.cloned()
+			.ok_or_else(|| errno!(EFAULT))?
+	} else {
+		EpollEvent::default()
+	};
+
+	let (epoll_mutex, file_mutex) = {
+		let fds = fds_mutex.lock();
+		let epoll_file = fds
+			.get_fd(epfd as _)
+			.ok_or_else(|| errno!(EBADF))?
+			.get_open_file()
+			.clone();
+		let file_mutex = fds
+			.get_fd(fd as _)
+			.ok_or_else(|| errno!(EBADF))?
+			.get_open_file()
+			.clone();
+		let epoll_mutex =
+			buffer::get(epoll_file.lock().get_location()).ok_or_else(|| errno!(EINVAL))?;
+		(epoll_mutex, file_mutex)
+	};
+
+	{
+		let file = file_mutex.lock();
+		// Regular files and directories are always ready, thus cannot be monitored
+		if matches!(
+			file.get_file().lock().get_content(),
+			FileContent::Regular | FileContent::Directory(_)
+		) {
+			return Err(errno!(EPERM));
+		}
+		// Nested epoll instances are not supported
+		if let Some(buff_mutex) = buffer::get(file.get_location()) {
+			let buff = buff_mutex.lock();
+			if (&*buff as &dyn Any).is::<EpollBuffer>() {
+				return Err(errno!(EINVAL));
+			}
+		}
+	}
+
+	let mut epoll = epoll_mutex.lock();
+	let epoll = (&mut *epoll as &mut dyn Any)
+		.downcast_mut::<EpollBuffer>()
+		.ok_or_else(|| errno!(EINVAL))?;
+	match op {
+		EPOLL_CTL_ADD => epoll.add(fd as _, &file_mutex, &event)?,
+		EPOLL_CTL_MOD => epoll.modify(fd as _, &event)?,
+		EPOLL_CTL_DEL => epoll.remove(fd as _)?,
+		_ => return Err(errno!(EINVAL)),
+	}
+
+	Ok(0)
+}
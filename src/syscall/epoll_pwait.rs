@@ -0,0 +1,19 @@
+//! `epoll_pwait` is similar to `epoll_wait`.
+
+use super::epoll_wait::do_epoll_wait;
+use crate::errno::Errno;
+use crate::file::buffer::epoll::EpollEvent;
+use crate::process::mem_space::ptr::SyscallSlice;
+use core::ffi::c_int;
+use macros::syscall;
+
+#[syscall(0x13f)]
+pub fn epoll_pwait(
+	epfd: c_int,
+	events: SyscallSlice<EpollEvent>,
+	maxevents: c_int,
+	timeout: c_int,
+	sigmask: SyscallSlice<u8>,
+) -> Result<i32, Errno> {
+	do_epoll_wait(epfd, events, maxevents, timeout, Some(sigmask))
+}
@@ -0,0 +1,108 @@
+//! The `epoll_wait` system call waits for events on the file descriptors registered on an epoll
+//! instance.
+
+use crate::errno;
+use crate::errno::Errno;
+use crate::file::buffer;
+use crate::file::buffer::epoll::EpollBuffer;
+use crate::file::buffer::epoll::EpollEvent;
+use crate::file::buffer::Buffer;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::scheduler;
+use crate::process::Process;
+use crate::time::clock;
+use crate::time::clock::CLOCK_MONOTONIC;
+use crate::time::unit::Timestamp;
+use crate::time::unit::TimestampScale;
+use core::any::Any;
+use core::ffi::c_int;
+use macros::syscall;
+
+/// Performs the `epoll_wait` system call.
+///
+/// Arguments:
+/// - `epfd` is the file descriptor of the epoll instance.
+/// - `events` is the array in which events are written.
+/// - `maxevents` is the size of the array.
+/// - `timeout` is the timeout in milliseconds. If negative, the function waits indefinitely.
+/// - `sigmask` TODO
+pub fn do_epoll_wait(
+	epfd: c_int,
+	events: SyscallSlice<EpollEvent>,
+	maxevents: c_int,
+	timeout: c_int,
+	_sigmask: Option<SyscallSlice<u8>>,
+) -> Result<i32, Errno> {
+	if epfd < 0 {
+		return Err(errno!(EBADF));
+	}
+	if maxevents <= 0 {
+		return Err(errno!(EINVAL));
+	}
+
+	// The timeout. None means no timeout
+	let to: Option<Timestamp> = if timeout >= 0 {
+		Some(timeout as _)
+	} else {
+		None
+	};
+	// The start timestamp
+	let start_ts = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Millisecond)?;
+
+	let proc_mutex = Process::current_assert();
+	let (mem_space, epoll_mutex) = {
+		let proc = proc_mutex.lock();
+		let mem_space = proc.get_mem_space().unwrap().clone();
+		let fds_mutex = proc.get_fds().unwrap();
+		let fds = fds_mutex.lock();
+		let open_file_mutex = fds
+			.get_fd(epfd as _)
+			.ok_or_else(|| errno!(EBADF))?
+			.get_open_file();
+		let open_file = open_file_mutex.lock();
+		let epoll_mutex = buffer::get(open_file.get_location()).ok_or_else(|| errno!(EINVAL))?;
+		(mem_space, epoll_mutex)
+	};
+
+	loop {
+		{
+			let mut mem_space_guard = mem_space.lock();
+			let events = events
+				.get_mut(&mut mem_space_guard, maxevents as _)?
+				.ok_or_else(|| errno!(EFAULT))?;
+			let mut epoll = epoll_mutex.lock();
+			let epoll = (&mut *epoll as &mut dyn Any)
+				.downcast_mut::<EpollBuffer>()
+				.ok_or_else(|| errno!(EINVAL))?;
+
+			let count = epoll.collect(events)?;
+			if count > 0 {
+				return Ok(count as _);
+			}
+
+			// Checking whether the system call timed out
+			if let Some(timeout) = to {
+				let now = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Millisecond)?;
+				if now >= start_ts + timeout {
+					return Ok(0);
+				}
+			} else {
+				// Sleep until an event occurs on a file descriptor
+				let mut proc = proc_mutex.lock();
+				epoll.add_waiting_process(&mut proc, 0)?;
+			}
+		}
+
+		scheduler::end_tick();
+	}
+}
+
+#[syscall(0x100)]
+pub fn epoll_wait(
+	epfd: c_int,
+	events: SyscallSlice<EpollEvent>,
+	maxevents: c_int,
+	timeout: c_int,
+) -> Result<i32, Errno> {
+	do_epoll_wait(epfd, events, maxevents, timeout, None)
+}
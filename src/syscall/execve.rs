@@ -29,8 +29,6 @@ const SHEBANG_MAX: usize = 257;
 /// execution.
 const INTERP_MAX: usize = 4;
 
-// TODO Use ARG_MAX
-
 /// Structure representing a shebang.
 struct Shebang {
 	/// The shebang's string.
@@ -139,7 +137,7 @@ fn build_image(
 	exec::build_image(&mut file, exec_info)
 }
 
-#[syscall]
+#[syscall(0x00b)]
 pub fn execve(
 	pathname: SyscallString,
 	argv: *const *const u8,
@@ -162,8 +160,10 @@ pub fn execve(
 		};
 		let path = super::util::get_absolute_path(&proc, path)?;
 
-		let argv = unsafe { super::util::get_str_array(&proc, argv)? };
-		let envp = unsafe { super::util::get_str_array(&proc, envp)? };
+		// The total size of arguments and environment, limited to `ARG_MAX`
+		let mut args_size = 0;
+		let argv = unsafe { super::util::get_str_array(&proc, argv, &mut args_size)? };
+		let envp = unsafe { super::util::get_str_array(&proc, envp, &mut args_size)? };
 
 		(path, argv, envp, proc.access_profile)
 	};
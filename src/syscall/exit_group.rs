@@ -5,7 +5,7 @@ use crate::errno::Errno;
 use core::ffi::c_int;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x0fc)]
 pub fn exit_group(status: c_int) -> Result<i32, Errno> {
 	super::_exit::do_exit(status as _, true);
 }
@@ -5,7 +5,7 @@ use crate::process::mem_space::ptr::SyscallString;
 use core::ffi::c_int;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x133)]
 pub fn faccessat(dir_fd: c_int, pathname: SyscallString, mode: c_int) -> Result<i32, Errno> {
 	super::access::do_access(Some(dir_fd), pathname, mode, None)
 }
@@ -5,7 +5,7 @@ use crate::process::mem_space::ptr::SyscallString;
 use core::ffi::c_int;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x1b7)]
 pub fn faccessat2(
 	dir_fd: c_int,
 	pathname: SyscallString,
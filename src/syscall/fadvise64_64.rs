@@ -5,7 +5,7 @@ use core::ffi::c_int;
 use macros::syscall;
 
 // TODO Check args type
-#[syscall]
+#[syscall(0x110)]
 pub fn fadvise64_64(_fd: c_int, _offset: u64, _len: u64, _advice: c_int) -> Result<i32, Errno> {
 	// TODO
 	Ok(0)
@@ -0,0 +1,74 @@
+//! The `fallocate` system call allows to preallocate or deallocate the storage of a file.
+
+use crate::errno;
+use crate::errno::Errno;
+use crate::file::FileType;
+use crate::process::Process;
+use core::ffi::c_int;
+use macros::syscall;
+
+/// Mode flag: the size of the file is not changed.
+const FALLOC_FL_KEEP_SIZE: c_int = 0x01;
+/// Mode flag: deallocates the range instead of allocating it. Must be used with
+/// [`FALLOC_FL_KEEP_SIZE`].
+const FALLOC_FL_PUNCH_HOLE: c_int = 0x02;
+
+#[syscall(0x144)]
+pub fn fallocate(
+	fd: c_int,
+	mode: c_int,
+	offset_low: u32,
+	offset_high: u32,
+	len_low: u32,
+	len_high: u32,
+) -> Result<i32, Errno> {
+	let off = ((offset_high as u64) << 32) | (offset_low as u64);
+	let len = ((len_high as u64) << 32) | (len_low as u64);
+	if (off as i64) < 0 || (len as i64) <= 0 {
+		return Err(errno!(EINVAL));
+	}
+	if mode & !(FALLOC_FL_KEEP_SIZE | FALLOC_FL_PUNCH_HOLE) != 0 {
+		return Err(errno!(EOPNOTSUPP));
+	}
+	let punch_hole = mode & FALLOC_FL_PUNCH_HOLE != 0;
+	let keep_size = mode & FALLOC_FL_KEEP_SIZE != 0;
+	if punch_hole && !keep_size {
+		return Err(errno!(EOPNOTSUPP));
+	}
+	if fd < 0 {
+		return Err(errno!(EBADF));
+	}
+
+	let file_mutex = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+
+		let fds_mutex = proc.get_fds().unwrap();
+		let fds = fds_mutex.lock();
+
+		let fd = fds.get_fd(fd as _).ok_or_else(|| errno!(EBADF))?;
+
+		let open_file_mutex = fd.get_open_file();
+		let open_file = open_file_mutex.lock();
+		if !open_file.can_write() {
+			return Err(errno!(EBADF));
+		}
+
+		open_file.get_file().clone()
+	};
+
+	let mut file = file_mutex.lock();
+	match file.get_type() {
+		FileType::Regular => {}
+		FileType::Directory => return Err(errno!(EISDIR)),
+		FileType::Fifo => return Err(errno!(ESPIPE)),
+		_ => return Err(errno!(ENODEV)),
+	}
+	if punch_hole {
+		file.punch_hole(off, len)?;
+	} else {
+		file.allocate(off, len, keep_size)?;
+	}
+
+	Ok(0)
+}
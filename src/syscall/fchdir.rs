@@ -9,7 +9,7 @@ use crate::util::ptr::arc::Arc;
 use core::ffi::c_int;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x085)]
 pub fn fchdir(fd: c_int) -> Result<i32, Errno> {
 	if fd < 0 {
 		return Err(errno!(EBADF));
@@ -6,7 +6,7 @@ use core::ffi::c_int;
 use macros::syscall;
 
 // TODO Check args type
-#[syscall]
+#[syscall(0x05e)]
 pub fn fchmod(fd: c_int, mode: i32) -> Result<i32, Errno> {
 	if fd < 0 {
 		return Err(errno!(EBADF));
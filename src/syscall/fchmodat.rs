@@ -8,7 +8,7 @@ use core::ffi::c_int;
 use macros::syscall;
 
 // TODO Check args type
-#[syscall]
+#[syscall(0x132)]
 pub fn fchmodat(
 	dirfd: c_int,
 	pathname: SyscallString,
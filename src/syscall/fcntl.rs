@@ -1,13 +1,35 @@
 //! The `fcntl` syscall call allows to manipulate a file descriptor.
 
+use super::_llseek::SEEK_CUR;
+use super::_llseek::SEEK_END;
+use super::_llseek::SEEK_SET;
+use crate::errno;
+use crate::errno::EResult;
 use crate::errno::Errno;
 use crate::file::buffer;
+use crate::file::buffer::memfd::MemfdBuffer;
+use crate::file::buffer::pipe;
 use crate::file::buffer::pipe::PipeBuffer;
+use crate::file::fd::FileDescriptorTable;
+use crate::file::fd::FD_CLOEXEC;
 use crate::file::fd::NewFDConstraint;
+use crate::file::locks;
+use crate::file::locks::LockType;
+use crate::file::locks::RecordLock;
+use crate::file::open_file::OpenFile;
 use crate::file::FileContent;
+use crate::limits;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::scheduler;
 use crate::process::Process;
+use crate::util::io::IO;
+use crate::util::lock::Mutex;
+use crate::util::ptr::arc::Arc;
+use core::any::Any;
 use core::ffi::c_int;
+use core::ffi::c_short;
 use core::ffi::c_void;
+use core::ops::Range;
 use macros::syscall;
 
 /// Duplicate the file descriptor using the lowest numbered available file descriptor greater than
@@ -21,11 +43,11 @@ const F_SETFD: i32 = 2;
 const F_GETFL: i32 = 3;
 /// Set the file status flag.
 const F_SETFL: i32 = 4;
-/// TODO doc
+/// Return the first lock preventing the given lock from being acquired.
 const F_GETLK: i32 = 5;
-/// TODO doc
+/// Acquire or release a record lock, failing if a conflicting lock is held.
 const F_SETLK: i32 = 6;
-/// TODO doc
+/// Like `F_SETLK`, but wait for conflicting locks to be released.
 const F_SETLKW: i32 = 7;
 /// Set the process ID or process group ID that will receive `SIGIO` and `SIGURG` signals for
 /// events on the file descriptor.
@@ -37,11 +59,11 @@ const F_GETOWN: i32 = 9;
 const F_SETSIG: i32 = 10;
 /// Return the signal sent when input or output becomes possible.
 const F_GETSIG: i32 = 11;
-/// TODO doc
+/// Like `F_GETLK`, with 64 bits offsets.
 const F_GETLK64: i32 = 12;
-/// TODO doc
+/// Like `F_SETLK`, with 64 bits offsets.
 const F_SETLK64: i32 = 13;
-/// TODO doc
+/// Like `F_SETLKW`, with 64 bits offsets.
 const F_SETLKW64: i32 = 14;
 /// Similar to `F_SETOWN`, except it allows to specifiy a thread ID using the `f_owner_ex`
 /// structure.
@@ -86,9 +108,6 @@ const F_GET_FILE_RW_HINT: i32 = 1037;
 /// descriptor.
 const F_SET_FILE_RW_HINT: i32 = 1038;
 
-/// TODO doc
-const F_SEAL_FUTURE_WRITE: i32 = 16;
-
 /// Take out a read lease.
 const F_RDLCK: i32 = 0;
 /// Take out a write lease.
@@ -96,6 +115,209 @@ const F_WRLCK: i32 = 1;
 /// Remove our lease from the file.
 const F_UNLCK: i32 = 2;
 
+/// Description of a record lock, with 32 bits offsets.
+#[repr(C)]
+#[derive(Clone, Debug)]
+struct Flock {
+	/// The type of the lock.
+	l_type: c_short,
+	/// The position `l_start` is relative to, with the same values as `lseek`.
+	l_whence: c_short,
+	/// The offset of the beginning of the locked range.
+	l_start: i32,
+	/// The length of the locked range. If zero, the range extends to the end of the file.
+	l_len: i32,
+	/// The PID of the process holding the lock, returned by `F_GETLK`.
+	l_pid: c_int,
+}
+
+/// Description of a record lock, with 64 bits offsets.
+#[repr(C)]
+#[derive(Clone, Debug)]
+struct Flock64 {
+	/// The type of the lock.
+	l_type: c_short,
+	/// The position `l_start` is relative to, with the same values as `lseek`.
+	l_whence: c_short,
+	/// The offset of the beginning of the locked range.
+	l_start: i64,
+	/// The length of the locked range. If zero, the range extends to the end of the file.
+	l_len: i64,
+	/// The PID of the process holding the lock, returned by `F_GETLK`.
+	l_pid: c_int,
+}
+
+/// A structure describing a record lock in userspace.
+trait LockDescription: Clone {
+	/// Converts the description to its 64 bits version.
+	fn to_flock64(self) -> Flock64;
+
+	/// Converts the 64 bits description `lock` back.
+	///
+	/// If a value does not fit, the function returns [`errno::EOVERFLOW`].
+	fn from_flock64(lock: Flock64) -> EResult<Self>;
+}
+
+impl LockDescription for Flock {
+	fn to_flock64(self) -> Flock64 {
+		Flock64 {
+			l_type: self.l_type,
+			l_whence: self.l_whence,
+			l_start: self.l_start as _,
+			l_len: self.l_len as _,
+			l_pid: self.l_pid,
+		}
+	}
+
+	fn from_flock64(lock: Flock64) -> EResult<Self> {
+		Ok(Self {
+			l_type: lock.l_type,
+			l_whence: lock.l_whence,
+			l_start: lock.l_start.try_into().map_err(|_| errno!(EOVERFLOW))?,
+			l_len: lock.l_len.try_into().map_err(|_| errno!(EOVERFLOW))?,
+			l_pid: lock.l_pid,
+		})
+	}
+}
+
+impl LockDescription for Flock64 {
+	fn to_flock64(self) -> Flock64 {
+		self
+	}
+
+	fn from_flock64(lock: Flock64) -> EResult<Self> {
+		Ok(lock)
+	}
+}
+
+/// Returns the range of bytes described by `lock` on the open file `open_file`.
+fn get_lock_range(open_file: &OpenFile, lock: &Flock64) -> EResult<Range<u64>> {
+	let base = match lock.l_whence as u32 {
+		SEEK_SET => 0,
+		SEEK_CUR => open_file.get_offset() as i64,
+		SEEK_END => open_file.get_size() as i64,
+		_ => return Err(errno!(EINVAL)),
+	};
+	let start = base
+		.checked_add(lock.l_start)
+		.ok_or_else(|| errno!(EOVERFLOW))?;
+	// A negative length designates the bytes before `start`. A zero length extends the range
+	// to the end of the file
+	let (start, end) = match lock.l_len {
+		0 => (start, None),
+		len if len > 0 => (start, start.checked_add(len)),
+		len => (start.checked_add(len).ok_or_else(|| errno!(EINVAL))?, Some(start)),
+	};
+	if start < 0 {
+		return Err(errno!(EINVAL));
+	}
+	let end = end.map(|end| end as u64).unwrap_or(u64::MAX);
+	Ok((start as u64)..end)
+}
+
+/// Performs the record lock operation `cmd` (either `F_GETLK`, `F_SETLK` or `F_SETLKW`) with
+/// the lock description `lock` on the open file `open_file_mutex`.
+///
+/// With `F_GETLK`, `lock` is updated with the conflicting lock, if any.
+fn do_record_lock(
+	open_file_mutex: &Arc<Mutex<OpenFile>>,
+	cmd: i32,
+	lock: &mut Flock64,
+) -> EResult<()> {
+	let pid = Process::current_assert().lock().tgid;
+	let (loc, range, readable, writable) = {
+		let open_file = open_file_mutex.lock();
+		let range = get_lock_range(&open_file, lock)?;
+		let loc = open_file.get_file().lock().get_location().clone();
+		(loc, range, open_file.can_read(), open_file.can_write())
+	};
+	let type_ = match lock.l_type as i32 {
+		F_RDLCK => Some(LockType::Read),
+		F_WRLCK => Some(LockType::Write),
+		F_UNLCK => None,
+		_ => return Err(errno!(EINVAL)),
+	};
+
+	if cmd == F_GETLK {
+		let type_ = type_.ok_or_else(|| errno!(EINVAL))?;
+		let conflict = locks::get_conflict(
+			&loc,
+			&RecordLock {
+				type_,
+				range,
+				pid,
+			},
+		);
+		match conflict {
+			Some(conflict) => {
+				lock.l_type = match conflict.type_ {
+					LockType::Read => F_RDLCK,
+					LockType::Write => F_WRLCK,
+				} as _;
+				lock.l_whence = SEEK_SET as _;
+				lock.l_start = conflict.range.start as _;
+				lock.l_len = if conflict.range.end == u64::MAX {
+					0
+				} else {
+					(conflict.range.end - conflict.range.start) as _
+				};
+				lock.l_pid = conflict.pid as _;
+			}
+			None => lock.l_type = F_UNLCK as _,
+		}
+		return Ok(());
+	}
+
+	// The file must be open with the access corresponding to the type of lock
+	match type_ {
+		Some(LockType::Read) if !readable => return Err(errno!(EBADF)),
+		Some(LockType::Write) if !writable => return Err(errno!(EBADF)),
+		_ => {}
+	}
+	loop {
+		if locks::try_set(&loc, pid, type_, range.clone(), cmd == F_SETLKW)? {
+			return Ok(());
+		}
+		if let Err(e) = super::util::signal_check() {
+			locks::cancel_wait(pid);
+			return Err(e);
+		}
+		scheduler::end_tick();
+	}
+}
+
+/// Performs the record lock operation `cmd` on the open file `open_file`, with the lock
+/// description at `arg` in userspace.
+///
+/// `T` is the structure used to describe the lock.
+fn record_lock<T: LockDescription>(
+	open_file: Arc<Mutex<OpenFile>>,
+	cmd: i32,
+	arg: *mut c_void,
+) -> EResult<()> {
+	let proc_mutex = Process::current_assert();
+	let mem_space = proc_mutex.lock().get_mem_space().unwrap().clone();
+	let ptr: SyscallPtr<T> = (arg as usize).into();
+
+	let mut lock = {
+		let mem_space_guard = mem_space.lock();
+		ptr.get(&mem_space_guard)?
+			.ok_or_else(|| errno!(EFAULT))?
+			.clone()
+			.to_flock64()
+	};
+	do_record_lock(&open_file, cmd, &mut lock)?;
+	if cmd == F_GETLK {
+		let lock = T::from_flock64(lock)?;
+		let mut mem_space_guard = mem_space.lock();
+		let lock_ref = ptr
+			.get_mut(&mut mem_space_guard)?
+			.ok_or_else(|| errno!(EFAULT))?;
+		*lock_ref = lock;
+	}
+	Ok(())
+}
+
 /// Send the signal to the process group whose ID is specified.
 const F_OWNER_PGRP: i32 = 2;
 /// Send the signal to the process whose ID is specified.
@@ -103,14 +325,31 @@ const F_OWNER_PID: i32 = 1;
 /// Send the signal to the thread whose thread ID is specified.
 const F_OWNER_TID: i32 = 0;
 
-/// If this seal is set, the size of the file in question cannot be increased.
-const F_SEAL_GROW: i32 = 4;
-/// If this seal is set, any further call to `fcntl` with `F_ADD_SEALS` fails.
-const F_SEAL_SEAL: i32 = 1;
-/// If this seal is set, the size of the file in question cannot be reduced.
-const F_SEAL_SHRINK: i32 = 2;
-/// If this seal is set, you cannot modify the contents of the file.
-const F_SEAL_WRITE: i32 = 8;
+/// Calls `f` with the buffer of the memfd `open_file`.
+///
+/// If the file is not a memfd, the function returns [`errno::EINVAL`].
+fn memfd_op<R, F>(open_file: &OpenFile, f: F) -> EResult<R>
+where
+	F: FnOnce(&mut MemfdBuffer) -> EResult<R>,
+{
+	let buf_mutex = buffer::get(open_file.get_location()).ok_or_else(|| errno!(EINVAL))?;
+	let mut buf = buf_mutex.lock();
+	let memfd = (&mut *buf as &mut dyn Any)
+		.downcast_mut::<MemfdBuffer>()
+		.ok_or_else(|| errno!(EINVAL))?;
+	f(memfd)
+}
+
+/// Duplicates the file descriptor `fd` to the lowest available ID greater than or equal to `min`.
+///
+/// `cloexec` tells whether the close-on-exec flag is set on the new file descriptor.
+fn dup_fd(fds: &mut FileDescriptorTable, fd: i32, min: c_int, cloexec: bool) -> EResult<i32> {
+	if min < 0 || min as u32 >= limits::OPEN_MAX {
+		return Err(errno!(EINVAL));
+	}
+	let new_fd = fds.duplicate_fd(fd as _, NewFDConstraint::Min(min as _), cloexec)?;
+	Ok(new_fd.get_id() as _)
+}
 
 /// Performs the fcntl system call.
 ///
@@ -120,18 +359,16 @@ pub fn do_fcntl(fd: i32, cmd: i32, arg: *mut c_void, _fcntl64: bool) -> Result<i
 		return Err(errno!(EBADF));
 	}
 
-	let fds_mutex = {
+	let (fds_mutex, ap) = {
 		let proc_mutex = Process::current_assert();
 		let proc = proc_mutex.lock();
 
-		proc.get_fds().unwrap().clone()
+		(proc.get_fds().unwrap().clone(), proc.access_profile)
 	};
 	let mut fds = fds_mutex.lock();
 
 	match cmd {
-		F_DUPFD => Ok(fds
-			.duplicate_fd(fd as _, NewFDConstraint::Min(arg as _), false)?
-			.get_id() as _),
+		F_DUPFD => dup_fd(&mut fds, fd, arg as _, false),
 
 		F_GETFD => {
 			let fd = fds.get_fd(fd as _).ok_or_else(|| errno!(EBADF))?;
@@ -140,7 +377,8 @@ pub fn do_fcntl(fd: i32, cmd: i32, arg: *mut c_void, _fcntl64: bool) -> Result<i
 
 		F_SETFD => {
 			let fd = fds.get_fd_mut(fd as _).ok_or_else(|| errno!(EBADF))?;
-			fd.set_flags(arg as _);
+			// Only the close-on-exec flag is defined
+			fd.set_flags(arg as i32 & FD_CLOEXEC);
 			Ok(0)
 		}
 
@@ -161,19 +399,13 @@ pub fn do_fcntl(fd: i32, cmd: i32, arg: *mut c_void, _fcntl64: bool) -> Result<i
 			Ok(0)
 		}
 
-		F_GETLK => {
-			// TODO
-			todo!();
-		}
-
-		F_SETLK => {
-			// TODO
-			todo!();
-		}
-
-		F_SETLKW => {
-			// TODO
-			todo!();
+		F_GETLK | F_SETLK | F_SETLKW => {
+			let fd = fds.get_fd(fd as _).ok_or_else(|| errno!(EBADF))?;
+			let open_file = fd.get_open_file().clone();
+			// Do not keep the table locked while waiting for the lock
+			drop(fds);
+			record_lock::<Flock>(open_file, cmd, arg)?;
+			Ok(0)
 		}
 
 		F_SETOWN => {
@@ -196,19 +428,15 @@ pub fn do_fcntl(fd: i32, cmd: i32, arg: *mut c_void, _fcntl64: bool) -> Result<i
 			todo!();
 		}
 
-		F_GETLK64 => {
-			// TODO
-			todo!();
-		}
-
-		F_SETLK64 => {
-			// TODO
-			todo!();
-		}
-
-		F_SETLKW64 => {
-			// TODO
-			todo!();
+		F_GETLK64 | F_SETLK64 | F_SETLKW64 => {
+			let fd = fds.get_fd(fd as _).ok_or_else(|| errno!(EBADF))?;
+			let open_file = fd.get_open_file().clone();
+			// Do not keep the table locked while waiting for the lock
+			drop(fds);
+			// The 64 bits commands have the same semantics
+			let cmd = cmd - F_GETLK64 + F_GETLK;
+			record_lock::<Flock64>(open_file, cmd, arg)?;
+			Ok(0)
 		}
 
 		F_SETOWN_EX => {
@@ -251,13 +479,33 @@ pub fn do_fcntl(fd: i32, cmd: i32, arg: *mut c_void, _fcntl64: bool) -> Result<i
 			todo!();
 		}
 
-		F_DUPFD_CLOEXEC => Ok(fds
-			.duplicate_fd(fd as _, NewFDConstraint::Min(arg as _), true)?
-			.get_id() as _),
+		F_DUPFD_CLOEXEC => dup_fd(&mut fds, fd, arg as _, true),
 
 		F_SETPIPE_SZ => {
-			// TODO
-			todo!();
+			let size = arg as usize;
+			if size > pipe::get_max_capacity() && !ap.is_privileged() {
+				return Err(errno!(EPERM));
+			}
+
+			let fd = fds.get_fd(fd as _).ok_or_else(|| errno!(EBADF))?;
+
+			let open_file_mutex = fd.get_open_file();
+			let open_file = open_file_mutex.lock();
+
+			let file_mutex = open_file.get_file();
+			let file = file_mutex.lock();
+
+			match file.get_content() {
+				FileContent::Fifo => {
+					let buf_mutex = buffer::get_or_default::<PipeBuffer>(file.get_location())?;
+					let mut buf = buf_mutex.lock();
+					let pipe = (&mut *buf as &mut dyn Any)
+						.downcast_mut::<PipeBuffer>()
+						.unwrap();
+					Ok(pipe.set_capacity(size)? as _)
+				}
+				_ => Err(errno!(EBADF)),
+			}
 		}
 
 		F_GETPIPE_SZ => {
@@ -275,18 +523,27 @@ pub fn do_fcntl(fd: i32, cmd: i32, arg: *mut c_void, _fcntl64: bool) -> Result<i
 					let cap = buf.lock().get_capacity();
 					Ok(cap as _)
 				}
-				_ => Ok(0),
+				_ => Err(errno!(EBADF)),
 			}
 		}
 
 		F_ADD_SEALS => {
-			// TODO
-			todo!();
+			let fd = fds.get_fd(fd as _).ok_or_else(|| errno!(EBADF))?;
+			let open_file_mutex = fd.get_open_file();
+			let open_file = open_file_mutex.lock();
+			// Seals can only be added through a file descriptor open for writing
+			if !open_file.can_write() {
+				return Err(errno!(EPERM));
+			}
+			memfd_op(&open_file, |memfd| memfd.add_seals(arg as _))?;
+			Ok(0)
 		}
 
 		F_GET_SEALS => {
-			// TODO
-			todo!();
+			let fd = fds.get_fd(fd as _).ok_or_else(|| errno!(EBADF))?;
+			let open_file_mutex = fd.get_open_file();
+			let open_file = open_file_mutex.lock();
+			memfd_op(&open_file, |memfd| Ok(memfd.get_seals() as _))
 		}
 
 		F_GET_RW_HINT => {
@@ -313,7 +570,7 @@ pub fn do_fcntl(fd: i32, cmd: i32, arg: *mut c_void, _fcntl64: bool) -> Result<i
 	}
 }
 
-#[syscall]
+#[syscall(0x037)]
 pub fn fcntl(fd: c_int, cmd: c_int, arg: *mut c_void) -> Result<i32, Errno> {
 	do_fcntl(fd, cmd, arg, false)
 }
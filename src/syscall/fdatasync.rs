@@ -0,0 +1,12 @@
+//! The `fdatasync` system call synchronizes the content of a file to storage, without the
+//! metadata that is not required to retrieve it.
+
+use super::fsync::do_fsync;
+use crate::errno::Errno;
+use core::ffi::c_int;
+use macros::syscall;
+
+#[syscall(0x094)]
+pub fn fdatasync(fd: c_int) -> Result<i32, Errno> {
+	do_fsync(fd, true)
+}
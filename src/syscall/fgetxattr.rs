@@ -0,0 +1,17 @@
+//! The `fgetxattr` system call returns the value of an extended attribute of an open file.
+
+use crate::errno::Errno;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::mem_space::ptr::SyscallString;
+use core::ffi::c_int;
+use macros::syscall;
+
+#[syscall(0x0e7)]
+pub fn fgetxattr(
+	fd: c_int,
+	name: SyscallString,
+	value: SyscallSlice<u8>,
+	size: usize,
+) -> EResult<i32> {
+	super::getxattr::do_getxattr(None, fd, true, name, value, size)
+}
@@ -11,11 +11,21 @@ use crate::util::io::IO;
 use core::ffi::c_int;
 use macros::syscall;
 
-#[syscall]
-pub fn finit_module(fd: c_int, _param_values: SyscallString, _flags: c_int) -> Result<i32, Errno> {
+/// Flag: Ignore symbol version hashes.
+const MODULE_INIT_IGNORE_MODVERSIONS: c_int = 1;
+/// Flag: Ignore the kernel version magic.
+const MODULE_INIT_IGNORE_VERMAGIC: c_int = 2;
+
+#[syscall(0x15e)]
+pub fn finit_module(fd: c_int, _param_values: SyscallString, flags: c_int) -> Result<i32, Errno> {
 	if fd < 0 {
 		return Err(errno!(EBADF));
 	}
+	if flags & !(MODULE_INIT_IGNORE_MODVERSIONS | MODULE_INIT_IGNORE_VERMAGIC) != 0 {
+		return Err(errno!(EINVAL));
+	}
+	// Symbols are not versioned, so only ignoring the version magic forces the load
+	let force = flags & MODULE_INIT_IGNORE_VERMAGIC != 0;
 
 	let open_file_mutex = {
 		let proc_mutex = Process::current_assert();
@@ -41,11 +51,7 @@ pub fn finit_module(fd: c_int, _param_values: SyscallString, _flags: c_int) -> R
 		image
 	};
 
-	let module = Module::load(image.as_slice())?;
-	if !module::is_loaded(module.get_name()) {
-		module::add(module)?;
-		Ok(0)
-	} else {
-		Err(errno!(EEXIST))
-	}
+	let module = Module::load(image.as_slice(), force)?;
+	module::add(module)?;
+	Ok(0)
 }
@@ -0,0 +1,11 @@
+//! The `flistxattr` system call returns the names of the extended attributes of an open file.
+
+use crate::errno::Errno;
+use crate::process::mem_space::ptr::SyscallSlice;
+use core::ffi::c_int;
+use macros::syscall;
+
+#[syscall(0x0ea)]
+pub fn flistxattr(fd: c_int, list: SyscallSlice<u8>, size: usize) -> EResult<i32> {
+	super::listxattr::do_listxattr(None, fd, true, list, size)
+}
@@ -0,0 +1,77 @@
+//! The `flock` system call allows to acquire or release a whole-file advisory lock.
+//!
+//! The lock belongs to the open file description, so it is shared by file descriptors duplicated
+//! with `dup` or inherited through `fork`. It is independent of the record locks set with
+//! `fcntl`.
+
+use crate::errno::Errno;
+use crate::file::locks;
+use crate::file::locks::LockType;
+use crate::process::scheduler;
+use crate::process::Process;
+use core::ffi::c_int;
+use macros::syscall;
+
+/// Acquire a shared lock.
+const LOCK_SH: c_int = 1;
+/// Acquire an exclusive lock.
+const LOCK_EX: c_int = 2;
+/// Do not block if the lock cannot be acquired.
+const LOCK_NB: c_int = 4;
+/// Release the lock.
+const LOCK_UN: c_int = 8;
+
+#[syscall(0x08f)]
+pub fn flock(fd: c_int, operation: c_int) -> Result<i32, Errno> {
+	if fd < 0 {
+		return Err(errno!(EBADF));
+	}
+	let type_ = match operation & !LOCK_NB {
+		LOCK_SH => Some(LockType::Read),
+		LOCK_EX => Some(LockType::Write),
+		LOCK_UN => None,
+		_ => return Err(errno!(EINVAL)),
+	};
+	let nonblock = operation & LOCK_NB != 0;
+
+	let open_file_mutex = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+
+		let fds_mutex = proc.get_fds().unwrap();
+		let fds = fds_mutex.lock();
+		fds.get_fd(fd as _)
+			.ok_or_else(|| errno!(EBADF))?
+			.get_open_file()
+			.clone()
+	};
+
+	loop {
+		{
+			let mut open_file = open_file_mutex.lock();
+			let held = open_file.get_flock();
+			if held == type_ {
+				return Ok(0);
+			}
+			// Converting a lock is not atomic: the previous lock is released first
+			let loc = open_file.get_location().clone();
+			if let Some(held) = held {
+				locks::flock_release(&loc, held);
+				open_file.set_flock(None);
+			}
+			let Some(type_) = type_ else {
+				return Ok(0);
+			};
+			if locks::flock_try_set(&loc, type_)? {
+				open_file.set_flock(Some(type_));
+				return Ok(0);
+			}
+		}
+
+		if nonblock {
+			return Err(errno!(EWOULDBLOCK));
+		}
+		super::util::signal_check()?;
+		scheduler::end_tick();
+	}
+}
@@ -8,7 +8,7 @@ use crate::process::Process;
 use crate::util::ptr::arc::Arc;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x002)]
 pub fn fork() -> Result<i32, Errno> {
 	// The current process
 	let curr_mutex = Process::current_assert();
@@ -0,0 +1,11 @@
+//! The `fremovexattr` system call removes an extended attribute of an open file.
+
+use crate::errno::Errno;
+use crate::process::mem_space::ptr::SyscallString;
+use core::ffi::c_int;
+use macros::syscall;
+
+#[syscall(0x0ed)]
+pub fn fremovexattr(fd: c_int, name: SyscallString) -> EResult<i32> {
+	super::removexattr::do_removexattr(None, fd, true, name)
+}
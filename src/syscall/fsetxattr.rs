@@ -0,0 +1,18 @@
+//! The `fsetxattr` system call sets the value of an extended attribute of an open file.
+
+use crate::errno::Errno;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::mem_space::ptr::SyscallString;
+use core::ffi::c_int;
+use macros::syscall;
+
+#[syscall(0x0e4)]
+pub fn fsetxattr(
+	fd: c_int,
+	name: SyscallString,
+	value: SyscallSlice<u8>,
+	size: usize,
+	flags: c_int,
+) -> EResult<i32> {
+	super::setxattr::do_setxattr(None, fd, true, name, value, size, flags)
+}
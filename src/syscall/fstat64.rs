@@ -1,22 +1,20 @@
 //! The `fstat64` system call allows get the status of a file.
 
 use crate::errno::Errno;
-use crate::file::perm::Gid;
-use crate::file::perm::Uid;
 use crate::file::INode;
 use crate::file::Mode;
 use crate::process::mem_space::ptr::SyscallPtr;
 use crate::process::Process;
 use crate::time::unit::TimeUnit;
-use crate::time::unit::Timespec;
+use crate::time::unit::Timespec32;
 use crate::time::unit::TimestampScale;
 use crate::util::io::IO;
 use core::ffi::c_int;
 use core::ffi::c_long;
 use macros::syscall;
 
-// TODO Check types
-/// Structure containing the informations of a file.
+/// Structure containing the informations of a file, with the layout of the i386 `stat64`
+/// structure.
 #[repr(C)]
 #[derive(Debug)]
 struct Stat {
@@ -26,16 +24,16 @@ struct Stat {
 	/// Padding.
 	__st_dev_padding: c_int,
 
-	/// The inode number.
-	st_ino: INode,
+	/// The inode number, truncated to 32 bits.
+	__st_ino_truncated: u32,
 	/// File's mode.
 	st_mode: Mode,
 	/// Number of hard links to the file.
 	st_nlink: u32,
 	/// File's owner UID.
-	st_uid: Uid,
+	st_uid: u32,
 	/// File's owner GID.
-	st_gid: Gid,
+	st_gid: u32,
 	/// Device ID (if device file).
 	st_rdev: u64,
 
@@ -43,21 +41,24 @@ struct Stat {
 	__st_rdev_padding: c_int,
 
 	/// Size of the file in bytes.
-	st_size: u32,
+	st_size: i64,
 	/// Size of a block on the file's storage medium.
 	st_blksize: c_long,
-	/// Size of the file in blocks.
+	/// Size of the file in blocks of 512 bytes.
 	st_blocks: u64,
 
 	/// Timestamp of last access.
-	st_atim: Timespec,
+	st_atim: Timespec32,
 	/// Timestamp of last modification of the content.
-	st_mtim: Timespec,
+	st_mtim: Timespec32,
 	/// Timestamp of last modification of the metadata.
-	st_ctim: Timespec,
+	st_ctim: Timespec32,
+
+	/// The inode number.
+	st_ino: INode,
 }
 
-#[syscall]
+#[syscall(0x0c5)]
 pub fn fstat64(fd: c_int, statbuf: SyscallPtr<Stat>) -> Result<i32, Errno> {
 	if fd < 0 {
 		return Err(errno!(EBADF));
@@ -81,40 +82,36 @@ pub fn fstat64(fd: c_int, statbuf: SyscallPtr<Stat>) -> Result<i32, Errno> {
 	let file = file_mutex.lock();
 
 	let inode = file.get_location().get_inode();
-
+	let timespec = |ts| {
+		Timespec32::from_nano(TimestampScale::convert(
+			ts,
+			TimestampScale::Second,
+			TimestampScale::Nanosecond,
+		))
+	};
 	let stat = Stat {
-		st_dev: 0, // TODO
+		st_dev: file.get_dev(),
 
 		__st_dev_padding: 0,
 
-		st_ino: inode,
+		__st_ino_truncated: inode as _,
 		st_mode: file.get_mode(),
 		st_nlink: file.get_hard_links_count() as _,
-		st_uid: file.get_uid(),
-		st_gid: file.get_gid(),
-		st_rdev: 0, // TODO
+		st_uid: file.get_uid() as _,
+		st_gid: file.get_gid() as _,
+		st_rdev: file.get_rdev(),
 
 		__st_rdev_padding: 0,
 
 		st_size: file.get_size() as _,
-		st_blksize: 512, // TODO
+		st_blksize: file.get_block_size()? as _,
 		st_blocks: file.blocks_count,
 
-		st_atim: Timespec::from_nano(TimestampScale::convert(
-			file.atime,
-			TimestampScale::Second,
-			TimestampScale::Nanosecond,
-		)),
-		st_mtim: Timespec::from_nano(TimestampScale::convert(
-			file.mtime,
-			TimestampScale::Second,
-			TimestampScale::Nanosecond,
-		)),
-		st_ctim: Timespec::from_nano(TimestampScale::convert(
-			file.ctime,
-			TimestampScale::Second,
-			TimestampScale::Nanosecond,
-		)),
+		st_atim: timespec(file.atime),
+		st_mtim: timespec(file.mtime),
+		st_ctim: timespec(file.ctime),
+
+		st_ino: inode,
 	};
 
 	{
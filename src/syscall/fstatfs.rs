@@ -1,15 +1,18 @@
 //! The `fstatfs` system call returns information about a mounted file system.
 
+use super::statfs::write_statfs;
 use crate::errno;
+use crate::errno::EResult;
 use crate::errno::Errno;
 use crate::file::fs::Statfs;
+use crate::file::fs::Statfs32;
 use crate::process::mem_space::ptr::SyscallPtr;
 use crate::process::Process;
 use core::ffi::c_int;
 use macros::syscall;
 
-#[syscall]
-pub fn fstatfs(fd: c_int, buf: SyscallPtr<Statfs>) -> Result<i32, Errno> {
+/// Returns the statistics of the filesystem on which the file open at `fd` is located.
+pub fn do_fstatfs(fd: c_int) -> EResult<Statfs> {
 	if fd < 0 {
 		return Err(errno!(EBADF));
 	}
@@ -30,31 +33,12 @@ pub fn fstatfs(fd: c_int, buf: SyscallPtr<Statfs>) -> Result<i32, Errno> {
 	};
 
 	let file = file_mutex.lock();
+	file.get_statfs()
+}
 
-	let mountpoint_mutex = file.get_location().get_mountpoint().unwrap();
-	let mountpoint = mountpoint_mutex.lock();
-
-	let io_mutex = mountpoint.get_source().get_io()?;
-	let mut io = io_mutex.lock();
-
-	let fs_mutex = mountpoint.get_filesystem();
-	let fs = fs_mutex.lock();
-
-	let stat = fs.get_stat(&mut *io)?;
-
-	// Writing the statfs structure to userspace
-	{
-		let proc_mutex = Process::current_assert();
-		let proc = proc_mutex.lock();
-
-		let mem_space = proc.get_mem_space().unwrap();
-		let mut mem_space_guard = mem_space.lock();
-
-		let buf = buf
-			.get_mut(&mut mem_space_guard)?
-			.ok_or_else(|| errno!(EFAULT))?;
-		*buf = stat;
-	}
-
+#[syscall(0x064)]
+pub fn fstatfs(fd: c_int, buf: SyscallPtr<Statfs32>) -> Result<i32, Errno> {
+	let stat = do_fstatfs(fd)?;
+	write_statfs(buf, Statfs32::try_from(&stat)?)?;
 	Ok(0)
 }
@@ -1,13 +1,16 @@
 //! The `fsync` system call synchronizes the state of a file to storage.
 
 use crate::errno;
+use crate::errno::EResult;
 use crate::errno::Errno;
 use crate::process::Process;
 use core::ffi::c_int;
 use macros::syscall;
 
-#[syscall]
-pub fn fsync(fd: c_int) -> Result<i32, Errno> {
+/// Synchronizes the file open at `fd` to storage.
+///
+/// If `data_only` is set, only what is required to retrieve the file's content is synchronized.
+pub fn do_fsync(fd: c_int, data_only: bool) -> EResult<i32> {
 	if fd < 0 {
 		return Err(errno!(EBADF));
 	}
@@ -28,7 +31,12 @@ pub fn fsync(fd: c_int) -> Result<i32, Errno> {
 	};
 
 	let file = file_mutex.lock();
-	file.sync()?;
+	file.fsync(data_only)?;
 
 	Ok(0)
 }
+
+#[syscall(0x076)]
+pub fn fsync(fd: c_int) -> Result<i32, Errno> {
+	do_fsync(fd, false)
+}
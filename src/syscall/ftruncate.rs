@@ -0,0 +1,53 @@
+//! The `ftruncate` system call allows to truncate a file from a file descriptor.
+
+use crate::errno;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::FileType;
+use crate::process::Process;
+use core::ffi::c_int;
+use core::ffi::c_long;
+use macros::syscall;
+
+/// Performs the ftruncate system call, with `length` the new size of the file.
+pub fn do_ftruncate(fd: c_int, length: i64) -> EResult<i32> {
+	if length < 0 {
+		return Err(errno!(EINVAL));
+	}
+	if fd < 0 {
+		return Err(errno!(EBADF));
+	}
+
+	let file_mutex = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+
+		let fds_mutex = proc.get_fds().unwrap();
+		let fds = fds_mutex.lock();
+
+		let fd = fds.get_fd(fd as _).ok_or_else(|| errno!(EBADF))?;
+
+		let open_file_mutex = fd.get_open_file();
+		let open_file = open_file_mutex.lock();
+		if !open_file.can_write() {
+			return Err(errno!(EINVAL));
+		}
+
+		open_file.get_file().clone()
+	};
+
+	let mut file = file_mutex.lock();
+	if file.get_type() != FileType::Regular {
+		return Err(errno!(EINVAL));
+	}
+
+	file.truncate(length as _)?;
+	file.sync()?;
+
+	Ok(0)
+}
+
+#[syscall(0x05d)]
+pub fn ftruncate(fd: c_int, length: c_long) -> Result<i32, Errno> {
+	do_ftruncate(fd, length as _)
+}
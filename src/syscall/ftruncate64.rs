@@ -0,0 +1,11 @@
+//! The `ftruncate64` system call is like `ftruncate`, but with a 64 bits length.
+
+use crate::errno::Errno;
+use core::ffi::c_int;
+use macros::syscall;
+
+#[syscall(0x0c2)]
+pub fn ftruncate64(fd: c_int, length_low: u32, length_high: u32) -> Result<i32, Errno> {
+	let length = ((length_high as u64) << 32) | (length_low as u64);
+	super::ftruncate::do_ftruncate(fd, length as _)
+}
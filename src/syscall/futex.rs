@@ -0,0 +1,171 @@
+//! The `futex` system call allows to sleep on a futex and to wake up processes sleeping on it.
+//!
+//! See [`crate::process::futex`].
+
+use crate::errno;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::process::futex;
+use crate::process::futex::FutexKey;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::Process;
+use crate::time::clock;
+use crate::time::clock::CLOCK_MONOTONIC;
+use crate::time::clock::CLOCK_REALTIME;
+use crate::time::unit::TimeUnit;
+use crate::time::unit::Timespec32;
+use crate::time::unit::Timestamp;
+use crate::time::unit::TimestampScale;
+use core::ffi::c_int;
+use macros::syscall;
+
+/// Operation: sleep if the futex has the given value.
+const FUTEX_WAIT: c_int = 0;
+/// Operation: wake up processes sleeping on the futex.
+const FUTEX_WAKE: c_int = 1;
+/// Operation: wake up processes sleeping on the futex and move the others to another futex.
+const FUTEX_REQUEUE: c_int = 3;
+/// Operation: same as [`FUTEX_REQUEUE`], if the futex has the given value.
+const FUTEX_CMP_REQUEUE: c_int = 4;
+/// Operation: acquire the priority inheritance futex, sleeping until it is released.
+const FUTEX_LOCK_PI: c_int = 6;
+/// Operation: release the priority inheritance futex.
+const FUTEX_UNLOCK_PI: c_int = 7;
+/// Operation: acquire the priority inheritance futex if it is free.
+const FUTEX_TRYLOCK_PI: c_int = 8;
+/// Operation: same as [`FUTEX_WAIT`], with a bitset and an absolute timeout.
+const FUTEX_WAIT_BITSET: c_int = 9;
+/// Operation: same as [`FUTEX_WAKE`], waking only processes whose bitset matches.
+const FUTEX_WAKE_BITSET: c_int = 10;
+
+/// Flag: the futex is not shared with other processes, so that it does not need to be looked up
+/// in shared mappings.
+const FUTEX_PRIVATE_FLAG: c_int = 128;
+/// Flag: the timeout is measured on `CLOCK_REALTIME` instead of `CLOCK_MONOTONIC`.
+const FUTEX_CLOCK_REALTIME: c_int = 256;
+
+/// Returns the deadline on the monotonic clock, in nanoseconds, for the timeout `timeout`.
+///
+/// If `absolute` is set, the timeout is a timestamp on the monotonic clock, or on the realtime
+/// clock if `realtime` is set. Otherwise, the timeout is relative to the current time.
+///
+/// If `timeout` is null, the function returns `None`.
+fn get_deadline<T: TimeUnit>(
+	timeout: SyscallPtr<T>,
+	absolute: bool,
+	realtime: bool,
+) -> EResult<Option<Timestamp>> {
+	let timeout = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+
+		let mem_space = proc.get_mem_space().unwrap();
+		let mem_space_guard = mem_space.lock();
+		match timeout.get(&mem_space_guard)? {
+			Some(timeout) => timeout.to_nano(),
+			None => return Ok(None),
+		}
+	};
+
+	let now = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Nanosecond)?;
+	let deadline = if !absolute {
+		now + timeout
+	} else if realtime {
+		let real_now = clock::current_time(CLOCK_REALTIME, TimestampScale::Nanosecond)?;
+		(timeout + now).saturating_sub(real_now)
+	} else {
+		timeout
+	};
+	Ok(Some(deadline))
+}
+
+/// Performs the futex operation `futex_op` on the futex at address `uaddr`.
+///
+/// `T` is the type of the structure `timeout` points to. For operations that take no timeout,
+/// `timeout` is an integer argument instead.
+///
+/// The other arguments depend on the operation. See `man 2 futex`.
+pub fn do_futex<T: TimeUnit>(
+	uaddr: SyscallPtr<u32>,
+	futex_op: c_int,
+	val: u32,
+	timeout: usize,
+	uaddr2: SyscallPtr<u32>,
+	val3: u32,
+) -> EResult<i32> {
+	let cmd = futex_op & !(FUTEX_PRIVATE_FLAG | FUTEX_CLOCK_REALTIME);
+	let private = futex_op & FUTEX_PRIVATE_FLAG != 0;
+	let realtime = futex_op & FUTEX_CLOCK_REALTIME != 0;
+	if realtime && cmd != FUTEX_WAIT_BITSET {
+		return Err(errno!(ENOSYS));
+	}
+
+	let mem_space = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+		proc.get_mem_space().unwrap().clone()
+	};
+	let key = FutexKey::new(&mem_space, uaddr.as_ptr() as _, private)?;
+
+	match cmd {
+		FUTEX_WAIT | FUTEX_WAIT_BITSET => {
+			let (bitset, absolute) = if cmd == FUTEX_WAIT_BITSET {
+				(val3, true)
+			} else {
+				(futex::BITSET_MATCH_ANY, false)
+			};
+			let deadline = get_deadline::<T>(timeout.into(), absolute, realtime)?;
+			futex::wait(key, uaddr, val, bitset, deadline)?;
+			Ok(0)
+		}
+
+		FUTEX_WAKE | FUTEX_WAKE_BITSET => {
+			let bitset = if cmd == FUTEX_WAKE_BITSET {
+				val3
+			} else {
+				futex::BITSET_MATCH_ANY
+			};
+			let count = futex::wake(key, val as _, bitset)?;
+			Ok(count as _)
+		}
+
+		FUTEX_REQUEUE | FUTEX_CMP_REQUEUE => {
+			let key2 = FutexKey::new(&mem_space, uaddr2.as_ptr() as _, private)?;
+			let cmp = (cmd == FUTEX_CMP_REQUEUE).then_some(val3);
+			// The timeout argument is the maximum number of processes to move
+			let count = futex::requeue(key, uaddr, key2, val as _, timeout, cmp)?;
+			Ok(count as _)
+		}
+
+		FUTEX_LOCK_PI | FUTEX_TRYLOCK_PI => {
+			let try_lock = cmd == FUTEX_TRYLOCK_PI;
+			// The timeout is always a timestamp on the realtime clock
+			let deadline = if try_lock {
+				None
+			} else {
+				get_deadline::<T>(timeout.into(), true, true)?
+			};
+			futex::lock_pi(key, uaddr, deadline, try_lock)?;
+			Ok(0)
+		}
+
+		FUTEX_UNLOCK_PI => {
+			futex::unlock_pi(key, uaddr)?;
+			Ok(0)
+		}
+
+		_ => Err(errno!(ENOSYS)),
+	}
+}
+
+#[syscall(0x0f0)]
+pub fn futex(
+	uaddr: SyscallPtr<u32>,
+	futex_op: c_int,
+	val: u32,
+	timeout: usize,
+	uaddr2: SyscallPtr<u32>,
+	val3: u32,
+) -> Result<i32, Errno> {
+	do_futex::<Timespec32>(uaddr, futex_op, val, timeout, uaddr2, val3)
+}
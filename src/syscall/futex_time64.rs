@@ -0,0 +1,21 @@
+//! The `futex_time64` system call is the same as `futex`, except that the timeout has 64 bits
+//! fields.
+
+use super::futex;
+use crate::errno::Errno;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::time::unit::Timespec;
+use core::ffi::c_int;
+use macros::syscall;
+
+#[syscall(0x1a6)]
+pub fn futex_time64(
+	uaddr: SyscallPtr<u32>,
+	futex_op: c_int,
+	val: u32,
+	timeout: usize,
+	uaddr2: SyscallPtr<u32>,
+	val3: u32,
+) -> Result<i32, Errno> {
+	futex::do_futex::<Timespec>(uaddr, futex_op, val, timeout, uaddr2, val3)
+}
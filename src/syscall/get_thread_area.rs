@@ -0,0 +1,33 @@
+//! This module implements the `get_thread_area` system call, which allows to
+//! get a TLS area.
+
+use super::set_thread_area;
+use crate::errno;
+use crate::errno::Errno;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::user_desc::UserDesc;
+use crate::process::Process;
+use macros::syscall;
+
+#[syscall(0x0f4)]
+pub fn get_thread_area(u_info: SyscallPtr<UserDesc>) -> Result<i32, Errno> {
+	let proc_mutex = Process::current_assert();
+	let mut proc = proc_mutex.lock();
+
+	let mem_space = proc.get_mem_space().unwrap().clone();
+	let mut mem_space_guard = mem_space.lock();
+
+	// A reference to the user_desc structure
+	let info = u_info
+		.get_mut(&mut mem_space_guard)?
+		.ok_or(errno!(EFAULT))?;
+
+	let entry_number = info.get_entry_number();
+	if entry_number == -1 {
+		return Err(errno!(EINVAL));
+	}
+	let (_, entry) = set_thread_area::get_entry(&mut proc, entry_number)?;
+	*info = UserDesc::from_descriptor(entry_number, entry);
+
+	Ok(0)
+}
@@ -8,7 +8,7 @@ use crate::process::Process;
 use crate::util;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x0b7)]
 pub fn getcwd(buf: SyscallSlice<u8>, size: usize) -> Result<i32, Errno> {
 	if size == 0 {
 		return Err(errno!(EINVAL));
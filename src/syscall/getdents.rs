@@ -32,7 +32,7 @@ pub trait Dirent: Sized {
 
 /// Performs the getdents system call.
 pub fn do_getdents<E: Dirent>(fd: c_uint, dirp: SyscallSlice<u8>, count: usize) -> EResult<i32> {
-	let (mem_space, open_file_mutex) = {
+	let (mem_space, open_file_mutex, ap) = {
 		let proc_mutex = Process::current_assert();
 		let proc = proc_mutex.lock();
 
@@ -47,7 +47,7 @@ pub fn do_getdents<E: Dirent>(fd: c_uint, dirp: SyscallSlice<u8>, count: usize)
 			.get_open_file()
 			.clone();
 
-		(mem_space, open_file_mutex)
+		(mem_space, open_file_mutex, proc.access_profile)
 	};
 
 	let mut mem_space_guard = mem_space.lock();
@@ -68,11 +68,23 @@ pub fn do_getdents<E: Dirent>(fd: c_uint, dirp: SyscallSlice<u8>, count: usize)
 		let FileContent::Directory(entries) = file.get_content() else {
 			return Err(errno!(ENOTDIR));
 		};
+		let location = file.get_location();
+		let mountpoint_mutex = location.get_mountpoint().ok_or_else(|| errno!(ENOENT))?;
+		let fs_mutex = mountpoint_mutex.lock().get_filesystem();
+		let fs = fs_mutex.lock();
 		// TODO skip entries whose inode cannot fit in struct
 		let entries = entries.iter().skip(start as _);
 
 		// Iterate over entries and fill the buffer
 		for (name, entry) in entries {
+			// Entries that cannot be looked up are skipped
+			if fs
+				.check_lookup(location.get_inode(), entry.inode, &ap)
+				.is_err()
+			{
+				entries_count += 1;
+				continue;
+			}
 			let len = E::required_length(name);
 			// If the buffer is not large enough, return an error
 			if off == 0 && len > count {
@@ -139,7 +151,7 @@ impl Dirent for LinuxDirent {
 	}
 }
 
-#[syscall]
+#[syscall(0x08d)]
 pub fn getdents(fd: c_uint, dirp: SyscallSlice<u8>, count: c_uint) -> Result<i32, Errno> {
 	do_getdents::<LinuxDirent>(fd, dirp, count as usize)
 }
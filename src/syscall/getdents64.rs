@@ -53,7 +53,7 @@ impl Dirent for LinuxDirent64 {
 	}
 }
 
-#[syscall]
+#[syscall(0x0dc)]
 pub fn getdents64(fd: c_int, dirp: SyscallSlice<u8>, count: usize) -> Result<i32, Errno> {
 	if fd < 0 {
 		return Err(errno!(EBADF));
@@ -1,10 +1,11 @@
 //! The `getegid` syscall returns the effective GID of the process's owner.
 
 use crate::errno::Errno;
-use crate::process::regs::Regs;
 use crate::process::Process;
+use macros::syscall;
 
-pub fn getegid(_: &Regs) -> Result<i32, Errno> {
+#[syscall(0x032)]
+pub fn getegid() -> Result<i32, Errno> {
 	let proc_mutex = Process::current_assert();
 	let proc = proc_mutex.lock();
 	Ok(proc.access_profile.get_egid() as _)
@@ -4,7 +4,7 @@ use crate::errno::Errno;
 use crate::process::Process;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x0c8)]
 pub fn getgid32() -> Result<i32, Errno> {
 	let proc_mutex = Process::current_assert();
 	let proc = proc_mutex.lock();
@@ -7,7 +7,7 @@ use crate::process::pid::Pid;
 use crate::process::Process;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x084)]
 pub fn getpgid(pid: Pid) -> Result<i32, Errno> {
 	let proc_mutex = Process::current_assert();
 	let proc = proc_mutex.lock();
@@ -1,13 +1,14 @@
-//! The `getpid` system call returns the PID of the current process.
+//! The `getpid` system call returns the PID of the current process, which is the ID of its thread
+//! group.
 
 use crate::errno::Errno;
 use crate::process::Process;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x014)]
 pub fn getpid() -> Result<i32, Errno> {
 	let proc_mutex = Process::current_assert();
 	let proc = proc_mutex.lock();
 
-	Ok(proc.pid as _)
+	Ok(proc.tgid as _)
 }
@@ -13,7 +13,7 @@ const GRND_RANDOM: u32 = 2;
 /// returns EAGAIN.
 const GRND_NONBLOCK: u32 = 1;
 
-#[syscall]
+#[syscall(0x163)]
 pub fn getrandom(buf: SyscallSlice<u8>, buflen: usize, flags: c_uint) -> Result<i32, Errno> {
 	let bypass_threshold = flags & GRND_RANDOM == 0;
 	let nonblock = flags & GRND_NONBLOCK != 0;
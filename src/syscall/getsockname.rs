@@ -10,7 +10,7 @@ use core::any::Any;
 use core::ffi::c_int;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x16f)]
 pub fn getsockname(
 	sockfd: c_int,
 	addr: SyscallSlice<u8>,
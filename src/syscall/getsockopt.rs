@@ -9,7 +9,7 @@ use core::any::Any;
 use core::ffi::c_int;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x16d)]
 pub fn getsockopt(
 	sockfd: c_int,
 	level: c_int,
@@ -0,0 +1,85 @@
+//! The `getxattr` system call returns the value of an extended attribute of a file.
+
+use super::util;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::vfs;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::mem_space::ptr::SyscallString;
+use crate::process::Process;
+use crate::util::container::vec::Vec;
+use core::ffi::c_int;
+use macros::syscall;
+
+/// Performs the `*getxattr` system calls.
+///
+/// Arguments:
+/// - `pathname` is the path to the file. If `None`, the file descriptor `fd` is used instead.
+/// - `fd` is the file descriptor of the file.
+/// - `follow_links` tells whether symbolic links are followed.
+/// - `name` is the name of the attribute.
+/// - `value` is the buffer to write the value to, of `size` bytes. If `size` is zero, only the
+/// size of the value is returned.
+pub fn do_getxattr(
+	pathname: Option<SyscallString>,
+	fd: c_int,
+	follow_links: bool,
+	name: SyscallString,
+	value: SyscallSlice<u8>,
+	size: usize,
+) -> EResult<i32> {
+	let (file_mutex, mem_space, name, ap) = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+
+		let ap = proc.access_profile;
+
+		let mem_space = proc.get_mem_space().unwrap().clone();
+		let mem_space_guard = mem_space.lock();
+
+		let name = name.get(&mem_space_guard)?.ok_or_else(|| errno!(EFAULT))?;
+		let name = Vec::from_slice(name)?;
+		let pathname = pathname
+			.map(|pathname| {
+				let pathname = pathname
+					.get(&mem_space_guard)?
+					.ok_or_else(|| errno!(EFAULT))?;
+				Ok::<_, Errno>(Vec::from_slice(pathname)?)
+			})
+			.transpose()?;
+		// Unlock to avoid deadlock with procfs
+		drop(mem_space_guard);
+
+		let file_mutex =
+			util::get_file_by_path_or_fd(proc, pathname.as_deref(), fd, follow_links)?;
+		(file_mutex, mem_space, name, ap)
+	};
+
+	let val = {
+		let file = file_mutex.lock();
+		vfs::get_xattr(&file, &name, &ap)?
+	};
+	if size == 0 {
+		return Ok(val.len() as _);
+	}
+	if val.len() > size {
+		return Err(errno!(ERANGE));
+	}
+
+	let mut mem_space_guard = mem_space.lock();
+	let buf = value
+		.get_mut(&mut mem_space_guard, val.len())?
+		.ok_or_else(|| errno!(EFAULT))?;
+	buf.copy_from_slice(&val);
+	Ok(val.len() as _)
+}
+
+#[syscall(0x0e5)]
+pub fn getxattr(
+	pathname: SyscallString,
+	name: SyscallString,
+	value: SyscallSlice<u8>,
+	size: usize,
+) -> EResult<i32> {
+	do_getxattr(Some(pathname), -1, true, name, value, size)
+}
@@ -10,7 +10,7 @@ use crate::process::Process;
 use core::ffi::c_ulong;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x080)]
 pub fn init_module(
 	module_image: SyscallSlice<u8>,
 	len: c_ulong,
@@ -30,13 +30,9 @@ pub fn init_module(
 			.get(&mem_space_guard, len as usize)?
 			.ok_or_else(|| errno!(EFAULT))?;
 
-		Module::load(image)?
+		Module::load(image, false)?
 	};
 
-	if !module::is_loaded(module.get_name()) {
-		module::add(module)?;
-		Ok(0)
-	} else {
-		Err(errno!(EEXIST))
-	}
+	module::add(module)?;
+	Ok(0)
 }
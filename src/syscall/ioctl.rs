@@ -13,16 +13,92 @@ use macros::syscall;
 
 /// ioctl request: get device geometry.
 pub const HDIO_GETGEO: u32 = 0x00000301;
+/// ioctl request: send a raw ATA command without data transfer.
+pub const HDIO_DRIVE_TASK: u32 = 0x0000031e;
+/// ioctl request: send a raw ATA command, reading the data it returns.
+pub const HDIO_DRIVE_CMD: u32 = 0x0000031f;
 
 // ioctl requests: storage
 
 /// ioctl request: re-read partition table.
 pub const BLKRRPART: u32 = 0x0000125f;
+/// ioctl request: flush buffers of the device.
+pub const BLKFLSBUF: u32 = 0x00001261;
 /// ioctl request: get block size.
 pub const BLKSSZGET: u32 = 0x00001268;
 /// ioctl request: get storage size in bytes.
 pub const BLKGETSIZE64: u32 = 0x00001272;
 
+// ioctl requests: device-mapper
+
+/// ioctl request: get the version of the device-mapper interface.
+pub const DM_VERSION: u32 = 0x0000fd00;
+/// ioctl request: remove every mapped devices.
+pub const DM_REMOVE_ALL: u32 = 0x0000fd01;
+/// ioctl request: list mapped devices.
+pub const DM_LIST_DEVICES: u32 = 0x0000fd02;
+/// ioctl request: create a mapped device.
+pub const DM_DEV_CREATE: u32 = 0x0000fd03;
+/// ioctl request: remove a mapped device.
+pub const DM_DEV_REMOVE: u32 = 0x0000fd04;
+/// ioctl request: suspend or resume a mapped device.
+pub const DM_DEV_SUSPEND: u32 = 0x0000fd06;
+/// ioctl request: get the status of a mapped device.
+pub const DM_DEV_STATUS: u32 = 0x0000fd07;
+/// ioctl request: load a table in the inactive slot of a mapped device.
+pub const DM_TABLE_LOAD: u32 = 0x0000fd09;
+/// ioctl request: clear the inactive table of a mapped device.
+pub const DM_TABLE_CLEAR: u32 = 0x0000fd0a;
+
+// ioctl requests: loop devices
+
+/// ioctl request: attach a file to a loop device.
+pub const LOOP_SET_FD: u32 = 0x00004c00;
+/// ioctl request: detach the file from a loop device.
+pub const LOOP_CLR_FD: u32 = 0x00004c01;
+/// ioctl request: get the index of an unused loop device.
+pub const LOOP_CTL_GET_FREE: u32 = 0x00004c82;
+
+// ioctl requests: software RAID
+
+/// ioctl request: get the description of a RAID array.
+pub const GET_ARRAY_INFO: u32 = 0x00000911;
+/// ioctl request: add a disk to a RAID array that is not running yet.
+pub const ADD_NEW_DISK: u32 = 0x00000921;
+/// ioctl request: set the parameters of a RAID array to be created.
+pub const SET_ARRAY_INFO: u32 = 0x00000923;
+/// ioctl request: add a disk to a running RAID array.
+pub const HOT_ADD_DISK: u32 = 0x00000928;
+/// ioctl request: start a RAID array.
+pub const RUN_ARRAY: u32 = 0x00000930;
+/// ioctl request: stop a RAID array.
+pub const STOP_ARRAY: u32 = 0x00000932;
+
+// ioctl requests: sound
+
+/// ioctl request: stop playback and discard pending samples.
+pub const SNDCTL_DSP_RESET: u32 = 0x00005000;
+/// ioctl request: wait until every pending samples have been played.
+pub const SNDCTL_DSP_SYNC: u32 = 0x00005001;
+/// ioctl request: set the sample rate.
+pub const SNDCTL_DSP_SPEED: u32 = 0x00005002;
+/// ioctl request: select mono or stereo.
+pub const SNDCTL_DSP_STEREO: u32 = 0x00005003;
+/// ioctl request: get the size of a fragment in bytes.
+pub const SNDCTL_DSP_GETBLKSIZE: u32 = 0x00005004;
+/// ioctl request: set the format of samples.
+pub const SNDCTL_DSP_SETFMT: u32 = 0x00005005;
+/// ioctl request: set the number of channels.
+pub const SNDCTL_DSP_CHANNELS: u32 = 0x00005006;
+/// ioctl request: start playing samples that have been written so far.
+pub const SNDCTL_DSP_POST: u32 = 0x00005008;
+/// ioctl request: get the mask of supported sample formats.
+pub const SNDCTL_DSP_GETFMTS: u32 = 0x0000500b;
+/// ioctl request: get the amount of space available for playback.
+pub const SNDCTL_DSP_GETOSPACE: u32 = 0x0000500c;
+/// ioctl request: get the number of bytes that have been written but not played yet.
+pub const SNDCTL_DSP_GETODELAY: u32 = 0x00005017;
+
 // ioctl requests: TTY
 
 /// ioctl request: Returns the current serial port settings.
@@ -47,6 +123,47 @@ pub const TIOCSWINSZ: u32 = 0x00005414;
 /// ioctl request: Returns the number of bytes available on the file descriptor.
 pub const FIONREAD: u32 = 0x0000541b;
 
+// ioctl requests: virtual terminals
+
+/// ioctl request: Returns the state of the virtual terminals.
+pub const VT_GETSTATE: u32 = 0x00005603;
+/// ioctl request: Switches to the given virtual terminal.
+pub const VT_ACTIVATE: u32 = 0x00005606;
+/// ioctl request: Returns an entry of the keymap.
+pub const KDGKBENT: u32 = 0x00004b46;
+/// ioctl request: Sets an entry of the keymap.
+pub const KDSKBENT: u32 = 0x00004b47;
+/// ioctl request: Sets the display mode of the terminal.
+pub const KDSETMODE: u32 = 0x00004b3a;
+/// ioctl request: Returns the display mode of the terminal.
+pub const KDGETMODE: u32 = 0x00004b3b;
+/// ioctl request: Adds entries to the Unicode map of the console font.
+pub const PIO_UNIMAP: u32 = 0x00004b67;
+/// ioctl request: Clears the Unicode map of the console font.
+pub const PIO_UNIMAPCLR: u32 = 0x00004b68;
+/// ioctl request: Performs an operation on the console font.
+pub const KDFONTOP: u32 = 0x00004b72;
+
+// ioctl requests: socket
+
+/// ioctl request: Tells whether the socket is at the out-of-band mark.
+pub const SIOCATMARK: u32 = 0x00008905;
+/// ioctl request: Returns the time at which the last packet returned to userspace was received.
+pub const SIOCGSTAMP: u32 = 0x00008906;
+/// ioctl request: Creates a bridge.
+pub const SIOCBRADDBR: u32 = 0x000089a0;
+/// ioctl request: Deletes a bridge.
+pub const SIOCBRDELBR: u32 = 0x000089a1;
+/// ioctl request: Adds an interface to a bridge.
+pub const SIOCBRADDIF: u32 = 0x000089a2;
+/// ioctl request: Removes an interface from a bridge.
+pub const SIOCBRDELIF: u32 = 0x000089a3;
+
+// ioctl requests: tun/tap
+
+/// ioctl request: Creates a tun/tap interface and attaches it to the device.
+pub const TUNSETIFF: u32 = 0x000054ca;
+
 /// Enumeration of IO directions for ioctl requests.
 #[derive(Eq, PartialEq)]
 pub enum Direction {
@@ -56,6 +173,8 @@ pub enum Direction {
 	Read,
 	/// The userspace transmits information.
 	Write,
+	/// The userspace transmits information and requires information in return.
+	ReadWrite,
 }
 
 impl TryFrom<c_ulong> for Direction {
@@ -66,6 +185,7 @@ impl TryFrom<c_ulong> for Direction {
 			0 => Ok(Self::None),
 			2 => Ok(Self::Read),
 			1 => Ok(Self::Write),
+			3 => Ok(Self::ReadWrite),
 
 			_ => Err(()),
 		}
@@ -104,7 +224,7 @@ impl Request {
 	}
 }
 
-#[syscall]
+#[syscall(0x036)]
 pub fn ioctl(fd: c_int, request: c_ulong, argp: *const c_void) -> Result<i32, Errno> {
 	let request = Request::from(request);
 
@@ -32,7 +32,7 @@ fn try_kill(pid: Pid, sig: &Option<Signal>) -> Result<(), Errno> {
 		}
 
 		if let Some(sig) = sig {
-			target.kill(sig, false);
+			target.kill_process(sig);
 		}
 
 		Ok(())
@@ -125,7 +125,7 @@ fn send_signal(pid: i32, sig: Option<Signal>) -> Result<(), Errno> {
 	}
 }
 
-#[syscall]
+#[syscall(0x025)]
 pub fn kill(pid: c_int, sig: c_int) -> Result<i32, Errno> {
 	if sig < 0 {
 		return Err(errno!(EINVAL));
@@ -0,0 +1,17 @@
+//! The `lgetxattr` system call returns the value of an extended attribute of a file, without
+//! following symbolic links.
+
+use crate::errno::Errno;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::mem_space::ptr::SyscallString;
+use macros::syscall;
+
+#[syscall(0x0e6)]
+pub fn lgetxattr(
+	pathname: SyscallString,
+	name: SyscallString,
+	value: SyscallSlice<u8>,
+	size: usize,
+) -> EResult<i32> {
+	super::getxattr::do_getxattr(Some(pathname), -1, false, name, value, size)
+}
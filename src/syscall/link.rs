@@ -6,7 +6,7 @@ use crate::process::mem_space::ptr::SyscallString;
 use crate::process::Process;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x009)]
 pub fn link(oldpath: SyscallString, newpath: SyscallString) -> Result<i32, Errno> {
 	let proc_mutex = Process::current_assert();
 	let proc = proc_mutex.lock();
@@ -8,7 +8,7 @@ use crate::process::Process;
 use core::ffi::c_int;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x12f)]
 pub fn linkat(
 	olddirfd: c_int,
 	oldpath: SyscallString,
@@ -0,0 +1,40 @@
+//! The `listen` system call marks a socket as accepting incoming connections.
+
+use crate::errno::Errno;
+use crate::file::buffer;
+use crate::file::buffer::socket::Socket;
+use crate::file::buffer::socket::UCred;
+use crate::process::Process;
+use core::any::Any;
+use core::ffi::c_int;
+use macros::syscall;
+
+#[syscall(0x16b)]
+pub fn listen(sockfd: c_int, backlog: c_int) -> Result<i32, Errno> {
+	if sockfd < 0 {
+		return Err(errno!(EBADF));
+	}
+
+	let proc_mutex = Process::current_assert();
+	let proc = proc_mutex.lock();
+
+	// Get socket
+	let fds_mutex = proc.get_fds().unwrap();
+	let fds = fds_mutex.lock();
+	let fd = fds.get_fd(sockfd as _).ok_or_else(|| errno!(EBADF))?;
+	let open_file_mutex = fd.get_open_file();
+	let open_file = open_file_mutex.lock();
+	let sock_mutex = buffer::get(open_file.get_location()).ok_or_else(|| errno!(ENOENT))?;
+	let mut sock = sock_mutex.lock();
+	let sock = (&mut *sock as &mut dyn Any)
+		.downcast_mut::<Socket>()
+		.ok_or_else(|| errno!(ENOTSOCK))?;
+
+	if !sock.desc().type_.is_stream() {
+		return Err(errno!(EOPNOTSUPP));
+	}
+	sock.listen(backlog)?;
+	sock.set_cred(UCred::from_process(&proc));
+
+	Ok(0)
+}
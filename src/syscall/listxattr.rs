@@ -0,0 +1,80 @@
+//! The `listxattr` system call returns the names of the extended attributes of a file.
+
+use super::util;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::vfs;
+use crate::file::xattr::XATTR_LIST_MAX;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::mem_space::ptr::SyscallString;
+use crate::process::Process;
+use crate::util::container::vec::Vec;
+use core::ffi::c_int;
+use macros::syscall;
+
+/// Performs the `*listxattr` system calls.
+///
+/// Arguments:
+/// - `pathname` is the path to the file. If `None`, the file descriptor `fd` is used instead.
+/// - `fd` is the file descriptor of the file.
+/// - `follow_links` tells whether symbolic links are followed.
+/// - `list` is the buffer to write the null-separated list of names to, of `size` bytes. If
+/// `size` is zero, only the size of the list is returned.
+pub fn do_listxattr(
+	pathname: Option<SyscallString>,
+	fd: c_int,
+	follow_links: bool,
+	list: SyscallSlice<u8>,
+	size: usize,
+) -> EResult<i32> {
+	let (file_mutex, mem_space, ap) = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+
+		let ap = proc.access_profile;
+
+		let mem_space = proc.get_mem_space().unwrap().clone();
+		let mem_space_guard = mem_space.lock();
+
+		let pathname = pathname
+			.map(|pathname| {
+				let pathname = pathname
+					.get(&mem_space_guard)?
+					.ok_or_else(|| errno!(EFAULT))?;
+				Ok::<_, Errno>(Vec::from_slice(pathname)?)
+			})
+			.transpose()?;
+		// Unlock to avoid deadlock with procfs
+		drop(mem_space_guard);
+
+		let file_mutex =
+			util::get_file_by_path_or_fd(proc, pathname.as_deref(), fd, follow_links)?;
+		(file_mutex, mem_space, ap)
+	};
+
+	let names = {
+		let file = file_mutex.lock();
+		vfs::list_xattr(&file, &ap)?
+	};
+	if names.len() > XATTR_LIST_MAX {
+		return Err(errno!(E2BIG));
+	}
+	if size == 0 {
+		return Ok(names.len() as _);
+	}
+	if names.len() > size {
+		return Err(errno!(ERANGE));
+	}
+
+	let mut mem_space_guard = mem_space.lock();
+	let buf = list
+		.get_mut(&mut mem_space_guard, names.len())?
+		.ok_or_else(|| errno!(EFAULT))?;
+	buf.copy_from_slice(&names);
+	Ok(names.len() as _)
+}
+
+#[syscall(0x0e8)]
+pub fn listxattr(pathname: SyscallString, list: SyscallSlice<u8>, size: usize) -> EResult<i32> {
+	do_listxattr(Some(pathname), -1, true, list, size)
+}
@@ -0,0 +1,12 @@
+//! The `llistxattr` system call returns the names of the extended attributes of a file, without
+//! following symbolic links.
+
+use crate::errno::Errno;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::mem_space::ptr::SyscallString;
+use macros::syscall;
+
+#[syscall(0x0e9)]
+pub fn llistxattr(pathname: SyscallString, list: SyscallSlice<u8>, size: usize) -> EResult<i32> {
+	super::listxattr::do_listxattr(Some(pathname), -1, false, list, size)
+}
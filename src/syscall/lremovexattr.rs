@@ -0,0 +1,11 @@
+//! The `lremovexattr` system call removes an extended attribute of a file, without following
+//! symbolic links.
+
+use crate::errno::Errno;
+use crate::process::mem_space::ptr::SyscallString;
+use macros::syscall;
+
+#[syscall(0x0ec)]
+pub fn lremovexattr(pathname: SyscallString, name: SyscallString) -> EResult<i32> {
+	super::removexattr::do_removexattr(Some(pathname), -1, false, name)
+}
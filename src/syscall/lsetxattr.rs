@@ -0,0 +1,19 @@
+//! The `lsetxattr` system call sets the value of an extended attribute of a file, without
+//! following symbolic links.
+
+use crate::errno::Errno;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::mem_space::ptr::SyscallString;
+use core::ffi::c_int;
+use macros::syscall;
+
+#[syscall(0x0e3)]
+pub fn lsetxattr(
+	pathname: SyscallString,
+	name: SyscallString,
+	value: SyscallSlice<u8>,
+	size: usize,
+	flags: c_int,
+) -> EResult<i32> {
+	super::setxattr::do_setxattr(Some(pathname), -1, false, name, value, size, flags)
+}
@@ -6,7 +6,7 @@ use core::ffi::c_int;
 use core::ffi::c_void;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x0db)]
 pub fn madvise(_addr: *mut c_void, _length: usize, _advice: c_int) -> Result<i32, Errno> {
 	// TODO
 	Ok(0)
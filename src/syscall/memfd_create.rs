@@ -0,0 +1,66 @@
+//! The `memfd_create` system call creates an anonymous file whose content resides in memory.
+
+use crate::errno;
+use crate::errno::Errno;
+use crate::file::buffer;
+use crate::file::buffer::memfd::MemfdBuffer;
+use crate::file::fd::FD_CLOEXEC;
+use crate::file::open_file;
+use crate::file::open_file::OpenFile;
+use crate::file::vfs;
+use crate::process::mem_space::ptr::SyscallString;
+use crate::process::Process;
+use crate::util::container::string::String;
+use crate::util::lock::Mutex;
+use crate::util::ptr::arc::Arc;
+use core::ffi::c_uint;
+use macros::syscall;
+
+/// Flag: sets the close-on-exec flag on the file descriptor.
+const MFD_CLOEXEC: c_uint = 1;
+/// Flag: allows adding seals to the file.
+const MFD_ALLOW_SEALING: c_uint = 2;
+
+/// The prefix of the names of memfds.
+const NAME_PREFIX: &[u8] = b"memfd:";
+/// The maximum length of the name given by userspace, excluding the terminating nul byte.
+const NAME_MAX: usize = 249;
+
+#[syscall(0x164)]
+pub fn memfd_create(name: SyscallString, flags: c_uint) -> Result<i32, Errno> {
+	if flags & !(MFD_CLOEXEC | MFD_ALLOW_SEALING) != 0 {
+		return Err(errno!(EINVAL));
+	}
+
+	let (fds_mutex, ap, name) = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+
+		let mem_space_mutex = proc.get_mem_space().unwrap();
+		let mem_space = mem_space_mutex.lock();
+		let name = name.get(&mem_space)?.ok_or_else(|| errno!(EFAULT))?;
+		if name.len() > NAME_MAX {
+			return Err(errno!(EINVAL));
+		}
+		// The name is only used for debugging purposes, it doesn't have to be unique
+		let mut full_name = String::try_from(NAME_PREFIX)?;
+		full_name.push_str(name)?;
+
+		(proc.get_fds().unwrap().clone(), proc.access_profile, full_name)
+	};
+
+	let buff = MemfdBuffer::new(flags & MFD_ALLOW_SEALING != 0);
+	let loc = buffer::register(None, Arc::new(Mutex::new(buff))?)?;
+	let file = vfs::create_virtual_file(name, &ap, 0o777, loc)?;
+	let open_file = OpenFile::new(file, open_file::O_RDWR | open_file::O_LARGEFILE)?;
+
+	let fd_flags = if flags & MFD_CLOEXEC != 0 {
+		FD_CLOEXEC
+	} else {
+		0
+	};
+	let mut fds = fds_mutex.lock();
+	let fd = fds.create_fd(fd_flags, open_file)?;
+
+	Ok(fd.get_id() as _)
+}
@@ -10,7 +10,7 @@ use crate::process::Process;
 use crate::util::container::hashmap::HashMap;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x027)]
 pub fn mkdir(pathname: SyscallString, mode: file::Mode) -> Result<i32, Errno> {
 	let (path, mode, ap) = {
 		let proc_mutex = Process::current_assert();
@@ -13,7 +13,7 @@ use crate::process::Process;
 use macros::syscall;
 
 // TODO Check args type
-#[syscall]
+#[syscall(0x00e)]
 pub fn mknod(pathname: SyscallString, mode: file::Mode, dev: u64) -> Result<i32, Errno> {
 	let (path, umask, ap) = {
 		let proc_mutex = Process::current_assert();
@@ -2,6 +2,7 @@
 
 use crate::errno;
 use crate::errno::Errno;
+use crate::file::FileContent;
 use crate::file::FileType;
 use crate::memory;
 use crate::process::mem_space;
@@ -9,6 +10,7 @@ use crate::process::mem_space::MapResidence;
 use crate::process::Process;
 use crate::syscall::mmap::mem_space::MapConstraint;
 use crate::util::math;
+use crate::util::ptr::arc::Arc;
 use core::ffi::c_int;
 use core::ffi::c_void;
 use core::num::NonZeroUsize;
@@ -105,6 +107,17 @@ pub fn do_mmap(
 		None
 	};
 
+	// Mapping `/dev/zero` is equivalent to an anonymous mapping
+	let file_mutex = file_mutex.filter(|file_mutex| {
+		!matches!(
+			file_mutex.lock().get_content(),
+			FileContent::CharDevice {
+				major: 1,
+				minor: 5
+			}
+		)
+	});
+
 	// TODO anon flag
 
 	// Get residence
@@ -127,7 +140,9 @@ pub fn do_mmap(
 
 			MapResidence::File {
 				location: file.get_location().clone(),
+				path: Arc::new(file.get_path()?)?,
 				off: offset,
+				size: mem_space::get_mapped_file_size(file.get_location(), file.get_size())?,
 			}
 		}
 		None => {
@@ -158,7 +173,7 @@ pub fn do_mmap(
 }
 
 // TODO Check last arg type
-#[syscall]
+#[syscall(0x05a)]
 pub fn mmap(
 	addr: *mut c_void,
 	length: usize,
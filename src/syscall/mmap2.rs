@@ -8,7 +8,7 @@ use core::ffi::c_void;
 use macros::syscall;
 
 // TODO Check last argument type
-#[syscall]
+#[syscall(0x0c0)]
 pub fn mmap2(
 	addr: *mut c_void,
 	length: usize,
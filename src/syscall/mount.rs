@@ -3,28 +3,27 @@
 use crate::errno;
 use crate::errno::Errno;
 use crate::file::fs;
+use crate::file::fs::options::MountOptions;
 use crate::file::mountpoint;
 use crate::file::mountpoint::MountSource;
 use crate::file::path::Path;
 use crate::file::vfs;
 use crate::file::FileType;
-use crate::process::mem_space::ptr::SyscallPtr;
 use crate::process::mem_space::ptr::SyscallString;
 use crate::process::Process;
 use crate::util::TryClone;
 use core::ffi::c_ulong;
-use core::ffi::c_void;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x015)]
 pub fn mount(
 	source: SyscallString,
 	target: SyscallString,
 	filesystemtype: SyscallString,
 	mountflags: c_ulong,
-	_data: SyscallPtr<c_void>,
+	data: SyscallString,
 ) -> Result<i32, Errno> {
-	let (mount_source, fs_type, target_path) = {
+	let (mount_source, fs_type, target_path, options) = {
 		let proc_mutex = Process::current_assert();
 		let proc = proc_mutex.lock();
 
@@ -34,14 +33,7 @@ pub fn mount(
 		let cwd = proc.chroot.try_clone()?.concat(&proc.cwd)?;
 
 		// Get strings
-		let source_slice = source.get(&mem_space_guard)?.ok_or(errno!(EFAULT))?;
 		let target_slice = target.get(&mem_space_guard)?.ok_or(errno!(EFAULT))?;
-		let filesystemtype_slice = filesystemtype
-			.get(&mem_space_guard)?
-			.ok_or(errno!(EFAULT))?;
-
-		// Get the mount source
-		let mount_source = MountSource::from_str(source_slice, cwd)?;
 
 		// Get the target file
 		let target_path = Path::from_str(target_slice, true)?;
@@ -56,14 +48,57 @@ pub fn mount(
 
 		// TODO Check for loop between source and target
 
+		if mountflags & mountpoint::FLAG_REMOUNT != 0 {
+			drop(target_file);
+
+			mountpoint::remount(
+				&target_path,
+				mountflags & !(mountpoint::FLAG_REMOUNT | mountpoint::FLAG_BIND),
+				mountflags & mountpoint::FLAG_BIND != 0,
+			)?;
+			return Ok(0);
+		}
+
+		let source_slice = source.get(&mem_space_guard)?.ok_or(errno!(EFAULT))?;
+
+		// Bind mounts reuse the filesystem of the source, so the type and options are ignored
+		if mountflags & mountpoint::FLAG_BIND != 0 {
+			let source_path = Path::from_str(source_slice, true)?;
+			let source_path = super::util::get_absolute_path(&proc, source_path)?;
+			drop(target_file);
+
+			mountpoint::bind(
+				&source_path,
+				target_path,
+				mountflags & !(mountpoint::FLAG_BIND | mountpoint::FLAG_REC),
+				mountflags & mountpoint::FLAG_REC != 0,
+				&proc.access_profile,
+			)?;
+			return Ok(0);
+		}
+
+		// Get the mount source
+		let mount_source = MountSource::from_str(source_slice, cwd)?;
+
+		let filesystemtype_slice = filesystemtype
+			.get(&mem_space_guard)?
+			.ok_or(errno!(EFAULT))?;
 		let fs_type = fs::get_type(filesystemtype_slice).ok_or(errno!(ENODEV))?;
 
-		(mount_source, fs_type, target_path)
+		// Get filesystem-specific options
+		let options = MountOptions::parse(data.get(&mem_space_guard)?.unwrap_or(&[]))?;
+
+		(mount_source, fs_type, target_path, options)
 	};
 
-	// TODO Use `data`
 	// Create mountpoint
-	mountpoint::create(mount_source, Some(fs_type), mountflags, target_path)?;
+	mountpoint::create(
+		mount_source,
+		Some(fs_type),
+		mountflags,
+		target_path,
+		&options,
+	)?;
 
 	Ok(0)
 }
@@ -23,7 +23,7 @@ fn prot_to_flags(prot: i32) -> u8 {
 	mem_flags
 }
 
-#[syscall]
+#[syscall(0x07d)]
 pub fn mprotect(addr: *mut c_void, len: usize, prot: c_int) -> Result<i32, Errno> {
 	// Checking alignment of `addr` and `length`
 	if !addr.is_aligned_to(memory::PAGE_SIZE) || len == 0 {
@@ -16,7 +16,7 @@ const MS_SYNC: i32 = 0b010;
 /// Invalides other mappings of the same file so they can be updated.
 const MS_INVALIDATE: i32 = 0b100;
 
-#[syscall]
+#[syscall(0x090)]
 pub fn msync(addr: *mut c_void, length: usize, flags: c_int) -> Result<i32, Errno> {
 	// Checking address alignment
 	if !addr.is_aligned_to(memory::PAGE_SIZE) {
@@ -10,7 +10,7 @@ use core::ffi::c_void;
 use core::num::NonZeroUsize;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x05b)]
 pub fn munmap(addr: *mut c_void, length: usize) -> Result<i32, Errno> {
 	if !addr.is_aligned_to(memory::PAGE_SIZE) || length == 0 {
 		return Err(errno!(EINVAL));
@@ -1,19 +1,62 @@
 //! The `nanosleep` system call allows to make the current process sleep for a
 //! given delay.
 
+use crate::errno;
+use crate::errno::EResult;
 use crate::errno::Errno;
 use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::scheduler;
 use crate::process::Process;
+use crate::process::RestartBlock;
 use crate::time::clock;
 use crate::time::clock::CLOCK_MONOTONIC;
+use crate::time::unit::TimeUnit;
 use crate::time::unit::Timespec32;
+use crate::time::unit::Timestamp;
+use crate::time::unit::TimestampScale;
 use macros::syscall;
 
-// TODO Handle signal interruption (EINTR)
+/// Sleeps until the timestamp `end` of `CLOCK_MONOTONIC` is reached, in nanoseconds.
+///
+/// If interrupted by a signal, the remaining time is written to `rem` and the state needed to
+/// resume sleeping with `restart_syscall` is saved.
+pub fn do_nanosleep(end: Timestamp, rem: SyscallPtr<Timespec32>) -> EResult<i32> {
+	// Looping until time is elapsed or the process is interrupted by a signal
+	loop {
+		let curr_time = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Nanosecond)?;
+		if curr_time >= end {
+			break;
+		}
 
-#[syscall]
+		if super::util::signal_check().is_err() {
+			let proc_mutex = Process::current_assert();
+			let mut proc = proc_mutex.lock();
+
+			{
+				let mem_space = proc.get_mem_space().unwrap();
+				let mut mem_space_guard = mem_space.lock();
+
+				if let Some(remaining) = rem.get_mut(&mut mem_space_guard)? {
+					*remaining = Timespec32::from_nano(end - curr_time);
+				}
+			}
+
+			proc.restart_block = Some(RestartBlock::Nanosleep {
+				end,
+				rem: rem.as_ptr() as _,
+			});
+			return Err(errno!(ERESTART_RESTARTBLOCK));
+		}
+
+		scheduler::end_tick();
+	}
+
+	Ok(0)
+}
+
+#[syscall(0x0a2)]
 pub fn nanosleep(req: SyscallPtr<Timespec32>, rem: SyscallPtr<Timespec32>) -> Result<i32, Errno> {
-	let start_time = clock::current_time_struct::<Timespec32>(CLOCK_MONOTONIC)?;
+	let start_time = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Nanosecond)?;
 
 	let delay = {
 		let proc_mutex = Process::current_assert();
@@ -27,30 +70,5 @@ pub fn nanosleep(req: SyscallPtr<Timespec32>, rem: SyscallPtr<Timespec32>) -> Re
 			.clone()
 	};
 
-	// Looping until time is elapsed or the process is interrupted by a signal
-	loop {
-		let curr_time = clock::current_time_struct::<Timespec32>(CLOCK_MONOTONIC)?;
-		if curr_time >= start_time + delay {
-			break;
-		}
-
-		// TODO Allow interruption by signal
-		// TODO Make the current process sleep
-	}
-
-	// Setting remaining time to zero
-	{
-		let proc_mutex = Process::current_assert();
-		let proc = proc_mutex.lock();
-
-		let mem_space = proc.get_mem_space().unwrap();
-		let mut mem_space_guard = mem_space.lock();
-
-		let remaining = rem
-			.get_mut(&mut mem_space_guard)?
-			.ok_or_else(|| errno!(EFAULT))?;
-		*remaining = Timespec32::default();
-	}
-
-	Ok(0)
+	do_nanosleep(start_time + delay.to_nano(), rem)
 }
@@ -123,9 +123,12 @@ pub fn handle_flags(file: &mut File, flags: i32, access_profile: &AccessProfile)
 	if flags & open_file::O_DIRECTORY != 0 && file.get_type() != FileType::Directory {
 		return Err(errno!(ENOTDIR));
 	}
-	// Truncate the file if necessary
-	if flags & open_file::O_TRUNC != 0 {
-		file.set_size(0);
+	// Truncate the file if necessary. The flag is ignored on other types of files
+	if flags & open_file::O_TRUNC != 0 && file.get_type() == FileType::Regular {
+		if !access_profile.can_write_file(file) {
+			return Err(errno!(EACCES));
+		}
+		file.truncate(0)?;
 	}
 
 	Ok(())
@@ -158,6 +161,7 @@ pub fn open_(pathname: SyscallString, flags: i32, mode: file::Mode) -> EResult<i
 
 	// Create open file description
 	let open_file = OpenFile::new(file_mutex.clone(), flags)?;
+	open_file.wait_fifo_peer()?;
 
 	// Create FD
 	let mut fd_flags = 0;
@@ -179,7 +183,7 @@ pub fn open_(pathname: SyscallString, flags: i32, mode: file::Mode) -> EResult<i
 	Ok(fd_id as _)
 }
 
-#[syscall]
+#[syscall(0x005)]
 pub fn open(pathname: SyscallString, flags: c_int, mode: file::Mode) -> Result<i32, Errno> {
 	open_(pathname, flags, mode)
 }
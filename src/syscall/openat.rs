@@ -67,7 +67,7 @@ fn get_file(
 	}
 }
 
-#[syscall]
+#[syscall(0x127)]
 pub fn openat(
 	dirfd: c_int,
 	pathname: SyscallString,
@@ -86,6 +86,7 @@ pub fn openat(
 	drop(file);
 
 	let open_file = OpenFile::new(file_mutex, flags)?;
+	open_file.wait_fifo_peer()?;
 
 	let mut fd_flags = 0;
 	if flags & open_file::O_CLOEXEC != 0 {
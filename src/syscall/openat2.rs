@@ -0,0 +1,216 @@
+//! The `openat2` system call is an extension of `openat`, allowing to restrict the resolution of
+//! the path.
+
+use super::util;
+use crate::errno;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::fd::FD_CLOEXEC;
+use crate::file::open_file;
+use crate::file::open_file::OpenFile;
+use crate::file::path::Path;
+use crate::file::perm::AccessProfile;
+use crate::file::vfs;
+use crate::file::vfs::ResolutionSettings;
+use crate::file::File;
+use crate::file::FileContent;
+use crate::file::FileType;
+use crate::file::Mode;
+use crate::memory;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::mem_space::ptr::SyscallString;
+use crate::process::Process;
+use crate::util::lock::Mutex;
+use crate::util::ptr::arc::Arc;
+use crate::util::TryClone;
+use core::ffi::c_int;
+use core::mem::size_of;
+use macros::syscall;
+
+/// The set of flags accepted by `openat2`. Unlike `openat`, unknown flags are rejected.
+const VALID_FLAGS: i32 = open_file::O_WRONLY
+	| open_file::O_RDWR
+	| open_file::O_APPEND
+	| open_file::O_ASYNC
+	| open_file::O_CLOEXEC
+	| open_file::O_CREAT
+	| open_file::O_DIRECT
+	| open_file::O_DIRECTORY
+	| open_file::O_EXCL
+	| open_file::O_LARGEFILE
+	| open_file::O_NOATIME
+	| open_file::O_NOCTTY
+	| open_file::O_NOFOLLOW
+	| open_file::O_NONBLOCK
+	| open_file::O_SYNC
+	| open_file::O_TRUNC;
+/// The set of supported resolve flags.
+const VALID_RESOLVE: u64 = vfs::RESOLVE_NO_XDEV | vfs::RESOLVE_NO_SYMLINKS | vfs::RESOLVE_BENEATH;
+
+/// Userspace structure describing how to open a file.
+#[repr(C)]
+#[derive(Debug)]
+struct OpenHow {
+	/// The open file flags.
+	flags: u64,
+	/// The permissions of the file, if created.
+	mode: u64,
+	/// The set of `RESOLVE_*` flags.
+	resolve: u64,
+}
+
+impl OpenHow {
+	/// Parses the structure from the given buffer `buf`, provided by userspace.
+	///
+	/// Newer versions of the structure may be larger. Fields that are unknown to the kernel are
+	/// accepted only if they are zero, otherwise the function returns [`errno::E2BIG`].
+	fn parse(buf: &[u8]) -> EResult<Self> {
+		if buf.len() < size_of::<Self>() {
+			return Err(errno!(EINVAL));
+		}
+		if buf[size_of::<Self>()..].iter().any(|b| *b != 0) {
+			return Err(errno!(E2BIG));
+		}
+		let field = |i: usize| u64::from_ne_bytes(buf[(i * 8)..((i + 1) * 8)].try_into().unwrap());
+		Ok(Self {
+			flags: field(0),
+			mode: field(1),
+			resolve: field(2),
+		})
+	}
+}
+
+/// Returns the file at the given path `path`, resolved with `settings`.
+///
+/// If the file doesn't exist and the `O_CREAT` flag is set, the file is created with the
+/// permissions `mode`, then the function returns it.
+///
+/// The access profile `ap` is used to check permissions and to set the owner of a created file.
+fn get_file(
+	path: Path,
+	flags: i32,
+	mode: Mode,
+	ap: &AccessProfile,
+	settings: &ResolutionSettings,
+) -> EResult<Arc<Mutex<File>>> {
+	if flags & open_file::O_CREAT == 0 {
+		return vfs::resolve_path(&path, ap, settings);
+	}
+
+	// The name of the file is not checked when resolving its parent
+	let root = Path::root();
+	let start = settings.start.unwrap_or(&root);
+	if settings.flags & vfs::RESOLVE_BENEATH != 0 && !path.is_beneath(start) {
+		return Err(errno!(EXDEV));
+	}
+
+	let mut parent_path = path.try_clone()?;
+	let name = parent_path.pop().ok_or_else(|| errno!(ENOENT))?;
+	let parent_settings = ResolutionSettings {
+		follow_links: true,
+		..*settings
+	};
+	let parent_mutex = vfs::resolve_path(&parent_path, ap, &parent_settings)?;
+	let mut parent = parent_mutex.lock();
+
+	match vfs::get_file_from_parent(&parent, name.try_clone()?, ap, false) {
+		Ok(_) if flags & open_file::O_EXCL != 0 => Err(errno!(EEXIST)),
+		Ok(file) => {
+			// Symbolic links are followed with the same restrictions as the rest of the path
+			let file_type = file.lock().get_type();
+			if file_type != FileType::Link {
+				return Ok(file);
+			}
+			drop(parent);
+			if !settings.follow_links {
+				return Err(errno!(ELOOP));
+			}
+			vfs::resolve_path(&path, ap, settings)
+		}
+		Err(e) if e.as_int() == errno::ENOENT => {
+			vfs::create_file(&mut parent, name, ap, mode, FileContent::Regular)
+		}
+		Err(e) => Err(e),
+	}
+}
+
+#[syscall(0x1b5)]
+pub fn openat2(
+	dirfd: c_int,
+	pathname: SyscallString,
+	how: SyscallSlice<u8>,
+	size: usize,
+) -> Result<i32, Errno> {
+	if size > memory::PAGE_SIZE {
+		return Err(errno!(E2BIG));
+	}
+
+	let proc_mutex = Process::current_assert();
+	let proc = proc_mutex.lock();
+	let ap = proc.access_profile;
+	let umask = proc.umask;
+
+	let (path, how) = {
+		let mem_space = proc.get_mem_space().unwrap().clone();
+		let mem_space_guard = mem_space.lock();
+
+		let how = how.get(&mem_space_guard, size)?.ok_or_else(|| errno!(EFAULT))?;
+		let how = OpenHow::parse(how)?;
+		let pathname = pathname
+			.get(&mem_space_guard)?
+			.ok_or_else(|| errno!(EFAULT))?;
+		(Path::from_str(pathname, true)?, how)
+	};
+
+	// Check arguments
+	if how.flags & !(VALID_FLAGS as u64) != 0 || how.resolve & !VALID_RESOLVE != 0 {
+		return Err(errno!(EINVAL));
+	}
+	let flags = how.flags as i32;
+	if how.mode & !0o7777 != 0 || (flags & open_file::O_CREAT == 0 && how.mode != 0) {
+		return Err(errno!(EINVAL));
+	}
+	let mode = how.mode as Mode & !umask;
+	if path.is_absolute() && how.resolve & vfs::RESOLVE_BENEATH != 0 {
+		return Err(errno!(EXDEV));
+	}
+
+	// Absolute paths are resolved from the root directory
+	let dir = if path.is_absolute() {
+		drop(proc);
+		None
+	} else {
+		Some(util::get_dir_path(proc, dirfd)?)
+	};
+	let settings = ResolutionSettings {
+		start: dir.as_ref(),
+		follow_links: flags & open_file::O_NOFOLLOW == 0,
+		flags: how.resolve,
+	};
+	let path = match &dir {
+		Some(dir) => dir.concat(&path)?,
+		None => path,
+	};
+
+	// Get the file
+	let file_mutex = get_file(path, flags, mode, &ap, &settings)?;
+	let mut file = file_mutex.lock();
+
+	// Handle flags
+	super::open::handle_flags(&mut file, flags, &ap)?;
+	drop(file);
+
+	let open_file = OpenFile::new(file_mutex, flags)?;
+	open_file.wait_fifo_peer()?;
+
+	let mut fd_flags = 0;
+	if flags & open_file::O_CLOEXEC != 0 {
+		fd_flags |= FD_CLOEXEC;
+	}
+	let proc = proc_mutex.lock();
+	let fds_mutex = proc.get_fds().unwrap();
+	let mut fds = fds_mutex.lock();
+	let fd = fds.create_fd(fd_flags, open_file)?;
+
+	Ok(fd.get_id() as _)
+}
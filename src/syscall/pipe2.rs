@@ -1,9 +1,11 @@
 //! The pipe2 system call allows to create a pipe with given flags.
 
 use crate::errno;
+use crate::errno::EResult;
 use crate::errno::Errno;
 use crate::file::buffer;
 use crate::file::buffer::pipe::PipeBuffer;
+use crate::file::fd::FD_CLOEXEC;
 use crate::file::open_file;
 use crate::file::open_file::OpenFile;
 use crate::file::vfs;
@@ -15,12 +17,23 @@ use crate::util::TryDefault;
 use core::ffi::c_int;
 use macros::syscall;
 
-#[syscall]
-pub fn pipe2(pipefd: SyscallPtr<[c_int; 2]>, flags: c_int) -> Result<i32, Errno> {
+/// Creates a pipe and writes the file descriptors of its reading and writing ends to `pipefd`.
+///
+/// `flags` is a set of flags among `O_CLOEXEC`, `O_DIRECT` and `O_NONBLOCK`:
+/// - `O_CLOEXEC` sets the `FD_CLOEXEC` flag on both file descriptors.
+/// - `O_NONBLOCK` makes operations on both ends fail with [`errno::EAGAIN`] instead of blocking.
+pub fn do_pipe2(pipefd: SyscallPtr<[c_int; 2]>, flags: c_int) -> EResult<i32> {
 	let accepted_flags = open_file::O_CLOEXEC | open_file::O_DIRECT | open_file::O_NONBLOCK;
 	if flags & !accepted_flags != 0 {
 		return Err(errno!(EINVAL));
 	}
+	// TODO Handle `O_DIRECT` (packet mode)
+	let file_flags = flags & open_file::O_NONBLOCK;
+	let fd_flags = if flags & open_file::O_CLOEXEC != 0 {
+		FD_CLOEXEC
+	} else {
+		0
+	};
 
 	let proc_mutex = Process::current_assert();
 	let (mem_space, fds_mutex) = {
@@ -34,8 +47,8 @@ pub fn pipe2(pipefd: SyscallPtr<[c_int; 2]>, flags: c_int) -> Result<i32, Errno>
 	let loc = buffer::register(None, Arc::new(Mutex::new(PipeBuffer::try_default()?))?)?;
 	let file = vfs::get_file_by_location(&loc)?;
 
-	let open_file0 = OpenFile::new(file.clone(), open_file::O_RDONLY)?;
-	let open_file1 = OpenFile::new(file, open_file::O_WRONLY)?;
+	let open_file0 = OpenFile::new(file.clone(), open_file::O_RDONLY | file_flags)?;
+	let open_file1 = OpenFile::new(file, open_file::O_WRONLY | file_flags)?;
 
 	let mut fds = fds_mutex.lock();
 	let mut mem_space_guard = mem_space.lock();
@@ -43,10 +56,15 @@ pub fn pipe2(pipefd: SyscallPtr<[c_int; 2]>, flags: c_int) -> Result<i32, Errno>
 	let pipefd_slice = pipefd
 		.get_mut(&mut mem_space_guard)?
 		.ok_or(errno!(EFAULT))?;
-	let fd0 = fds.create_fd(0, open_file0)?;
+	let fd0 = fds.create_fd(fd_flags, open_file0)?;
 	pipefd_slice[0] = fd0.get_id() as _;
-	let fd1 = fds.create_fd(0, open_file1)?;
+	let fd1 = fds.create_fd(fd_flags, open_file1)?;
 	pipefd_slice[1] = fd1.get_id() as _;
 
 	Ok(0)
 }
+
+#[syscall(0x14b)]
+pub fn pipe2(pipefd: SyscallPtr<[c_int; 2]>, flags: c_int) -> Result<i32, Errno> {
+	do_pipe2(pipefd, flags)
+}
@@ -2,14 +2,15 @@
 //! descriptors.
 
 use crate::errno::Errno;
+use crate::file::blocking::PollTable;
 use crate::process::mem_space::ptr::SyscallSlice;
-use crate::process::scheduler;
 use crate::process::Process;
 use crate::time::clock;
 use crate::time::clock::CLOCK_MONOTONIC;
 use crate::time::unit::Timestamp;
 use crate::time::unit::TimestampScale;
 use crate::util::io;
+use crate::util::io::IO;
 use core::ffi::c_int;
 use macros::syscall;
 
@@ -26,78 +27,76 @@ struct PollFD {
 }
 
 // TODO Check second arg type
-#[syscall]
+#[syscall(0x0a8)]
 pub fn poll(fds: SyscallSlice<PollFD>, nfds: usize, timeout: c_int) -> Result<i32, Errno> {
-	// The timeout. None means no timeout
-	let to: Option<Timestamp> = if timeout >= 0 {
-		Some(timeout as _)
+	// The timestamp at which the system call times out. None means no timeout
+	let deadline: Option<Timestamp> = if timeout >= 0 {
+		let now = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Nanosecond)?;
+		let timeout = TimestampScale::convert(
+			timeout as _,
+			TimestampScale::Millisecond,
+			TimestampScale::Nanosecond,
+		);
+		Some(now + timeout)
 	} else {
 		None
 	};
 
-	// The start timestamp
-	let start_ts = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Millisecond)?;
+	let proc_mutex = Process::current_assert();
+	let (mem_space, fds_mutex, table) = {
+		let proc = proc_mutex.lock();
+		let mem_space = proc.get_mem_space().unwrap().clone();
+		let fds_mutex = proc.get_fds().unwrap().clone();
+		(mem_space, fds_mutex, PollTable::new(proc.pid)?)
+	};
 
 	loop {
-		// Checking whether the system call timed out
-		if let Some(timeout) = to {
-			let now = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Millisecond)?;
-			if now >= start_ts + timeout {
-				return Ok(0);
-			}
-		}
+		super::util::signal_check()?;
 
 		{
-			let proc_mutex = Process::current_assert();
-			let proc = proc_mutex.lock();
-
-			let mem_space = proc.get_mem_space().unwrap();
-			let mem_space_guard = mem_space.lock();
-
-			let fds = fds
-				.get(&mem_space_guard, nfds)?
+			let mut mem_space_guard = mem_space.lock();
+			let poll_fds = fds
+				.get_mut(&mut mem_space_guard, nfds)?
 				.ok_or_else(|| errno!(EFAULT))?;
-
-			// Checking the file descriptors list
-			for fd in fds {
-				if fd.events as u32 & io::POLLIN != 0 {
-					// TODO
-					todo!();
-				}
-
-				if fd.events as u32 & io::POLLPRI != 0 {
-					// TODO
-					todo!();
-				}
-
-				if fd.events as u32 & io::POLLOUT != 0 {
-					// TODO
-					todo!();
-				}
-
-				if fd.events as u32 & io::POLLRDNORM != 0 {
-					// TODO
-					todo!();
-				}
-
-				if fd.events as u32 & io::POLLRDBAND != 0 {
-					// TODO
-					todo!();
-				}
-
-				if fd.events as u32 & io::POLLWRNORM != 0 {
-					// TODO
-					todo!();
+			let fds = fds_mutex.lock();
+
+			// Registering on the file descriptors before checking them, so that no event can be
+			// missed in between
+			{
+				let proc = proc_mutex.lock();
+				for poll_fd in poll_fds.iter() {
+					if poll_fd.fd < 0 {
+						continue;
+					}
+					let Some(fd) = fds.get_fd(poll_fd.fd as _) else {
+						continue;
+					};
+					let mask = poll_fd.events as u16 as u32 | io::POLLERR | io::POLLHUP;
+					fd.get_open_file()
+						.lock()
+						.register_poll(&proc, &table, mask)?;
 				}
+			}
 
-				if fd.events as u32 & io::POLLWRBAND != 0 {
-					// TODO
-					todo!();
+			// Checking the file descriptors list
+			for poll_fd in poll_fds.iter_mut() {
+				poll_fd.revents = 0;
+				if poll_fd.fd < 0 {
+					continue;
 				}
+				let Some(fd) = fds.get_fd(poll_fd.fd as _) else {
+					poll_fd.revents = io::POLLNVAL as _;
+					continue;
+				};
+
+				// Errors and hang ups are always reported
+				let mask = poll_fd.events as u16 as u32 | io::POLLERR | io::POLLHUP;
+				let mut open_file = fd.get_open_file().lock();
+				poll_fd.revents = (open_file.poll(mask)? & mask) as _;
 			}
 
 			// The number of file descriptor with at least one event
-			let fd_event_count = fds.iter().filter(|fd| fd.revents != 0).count();
+			let fd_event_count = poll_fds.iter().filter(|fd| fd.revents != 0).count();
 			// If at least on event happened, return the number of file descriptors
 			// concerned
 			if fd_event_count > 0 {
@@ -105,8 +104,15 @@ pub fn poll(fds: SyscallSlice<PollFD>, nfds: usize, timeout: c_int) -> Result<i3
 			}
 		}
 
-		// TODO Make process sleep until an event occurs on a file descriptor in
-		// `fds`
-		scheduler::end_tick();
+		// Checking whether the system call timed out
+		if let Some(deadline) = deadline {
+			let now = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Nanosecond)?;
+			if now >= deadline {
+				return Ok(0);
+			}
+		}
+
+		// Sleep until an event occurs on a file descriptor, or until timeout
+		table.wait(&proc_mutex, deadline)?;
 	}
 }
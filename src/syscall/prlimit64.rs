@@ -63,7 +63,7 @@ struct RLimit {
 }
 
 // TODO Check args types
-#[syscall]
+#[syscall(0x154)]
 pub fn prlimit64(
 	pid: Pid,
 	resource: c_int,
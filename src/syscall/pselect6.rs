@@ -9,7 +9,7 @@ use crate::time::unit::Timespec;
 use core::ffi::c_int;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x134)]
 pub fn pselect6(
 	nfds: c_int,
 	readfds: SyscallPtr<FDSet>,
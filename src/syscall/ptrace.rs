@@ -0,0 +1,195 @@
+//! The `ptrace` system call allows a process to observe and control the execution of another
+//! process.
+//!
+//! Only the requests needed to manage hardware breakpoints and watchpoints are supported for now.
+
+use crate::cpu::debug;
+use crate::cpu::debug::DebugRegs;
+use crate::errno;
+use crate::errno::Errno;
+use crate::process::iovec::IOVec;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::mem_space::MemSpace;
+use crate::process::pid::Pid;
+use crate::process::signal::Signal;
+use crate::process::Process;
+use crate::process::State;
+use core::cmp::min;
+use core::ffi::c_long;
+use core::mem::size_of;
+use macros::syscall;
+
+/// Request: the process is traced by its parent.
+const PTRACE_TRACEME: c_long = 0;
+/// Request: reads a word in the `struct user` of the tracee.
+const PTRACE_PEEKUSER: c_long = 3;
+/// Request: writes a word in the `struct user` of the tracee.
+const PTRACE_POKEUSER: c_long = 6;
+/// Request: attaches to the given process, which is then stopped.
+const PTRACE_ATTACH: c_long = 16;
+/// Request: detaches from the given process.
+const PTRACE_DETACH: c_long = 17;
+/// Request: reads a set of registers of the tracee.
+const PTRACE_GETREGSET: c_long = 0x4204;
+/// Request: writes a set of registers of the tracee.
+const PTRACE_SETREGSET: c_long = 0x4205;
+
+/// Register set: hardware debug registers.
+const NT_X86_DEBUGREG: usize = 0x203;
+
+/// The offset of the debug registers (`u_debugreg`) in `struct user`.
+const USER_DEBUGREG_OFF: usize = 252;
+
+/// Returns the index of the debug register at offset `off` in `struct user`.
+///
+/// If the offset does not point to a debug register, the function returns [`errno::EIO`].
+fn user_debugreg(off: usize) -> Result<usize, Errno> {
+	let size = size_of::<u32>();
+	let n = off
+		.checked_sub(USER_DEBUGREG_OFF)
+		.filter(|off| off % size == 0)
+		.map(|off| off / size)
+		.filter(|n| *n < debug::REGS_COUNT)
+		.ok_or_else(|| errno!(EIO))?;
+	Ok(n)
+}
+
+/// Executes `f` on the process with PID `pid`, which must be traced by the current process.
+///
+/// `f` also receives the memory space of the current process.
+fn with_tracee<F: FnOnce(&mut Process, &mut MemSpace) -> Result<i32, Errno>>(
+	pid: Pid,
+	f: F,
+) -> Result<i32, Errno> {
+	let proc_mutex = Process::current_assert();
+	let proc = proc_mutex.lock();
+	if pid == proc.pid {
+		return Err(errno!(ESRCH));
+	}
+
+	let tracee_mutex = Process::get_by_pid(pid).ok_or_else(|| errno!(ESRCH))?;
+	let mut tracee = tracee_mutex.lock();
+	if tracee.tracer != Some(proc.pid) || matches!(tracee.get_state(), State::Zombie) {
+		return Err(errno!(ESRCH));
+	}
+
+	let mem_space = proc.get_mem_space().unwrap();
+	let mut mem_space_guard = mem_space.lock();
+	f(&mut tracee, &mut mem_space_guard)
+}
+
+/// Transfers the debug registers of `regs` from or to the buffer described by the I/O vector
+/// at `iov`.
+///
+/// Arguments:
+/// - `mem_space` is the memory space of the tracer.
+/// - `set` tells whether the registers are written from the buffer, instead of read to it.
+///
+/// On success, `iov_len` is updated to the number of bytes transferred.
+fn transfer_regset(
+	regs: &mut DebugRegs,
+	mem_space: &mut MemSpace,
+	iov: SyscallPtr<IOVec>,
+	set: bool,
+) -> Result<i32, Errno> {
+	let iov = iov.get_mut(mem_space)?.ok_or_else(|| errno!(EFAULT))?;
+	let count = min(iov.iov_len / size_of::<u32>(), debug::REGS_COUNT);
+	let buf_ptr = iov.iov_base as usize;
+	iov.iov_len = count * size_of::<u32>();
+
+	let buf = SyscallSlice::<u32>::from(buf_ptr);
+	if set {
+		let buf = buf.get(mem_space, count)?.ok_or_else(|| errno!(EFAULT))?;
+		let mut new = *regs;
+		for (n, val) in buf.iter().enumerate() {
+			// Aliases are ignored
+			if matches!(n, 4 | 5) {
+				continue;
+			}
+			if !new.set(n, *val) {
+				return Err(errno!(EINVAL));
+			}
+		}
+		*regs = new;
+	} else {
+		let buf = buf.get_mut(mem_space, count)?.ok_or_else(|| errno!(EFAULT))?;
+		for (n, val) in buf.iter_mut().enumerate() {
+			*val = regs.get(n).unwrap_or(0);
+		}
+	}
+	Ok(0)
+}
+
+#[syscall(0x01a)]
+pub fn ptrace(request: c_long, pid: Pid, addr: usize, data: usize) -> Result<i32, Errno> {
+	match request {
+		PTRACE_TRACEME => {
+			let proc_mutex = Process::current_assert();
+			let mut proc = proc_mutex.lock();
+			if proc.tracer.is_some() {
+				return Err(errno!(EPERM));
+			}
+			proc.tracer = Some(proc.get_parent_pid());
+			Ok(0)
+		}
+
+		PTRACE_ATTACH => {
+			let proc_mutex = Process::current_assert();
+			let proc = proc_mutex.lock();
+			if pid == proc.pid {
+				return Err(errno!(EPERM));
+			}
+
+			let tracee_mutex = Process::get_by_pid(pid).ok_or_else(|| errno!(ESRCH))?;
+			let mut tracee = tracee_mutex.lock();
+			if matches!(tracee.get_state(), State::Zombie) {
+				return Err(errno!(ESRCH));
+			}
+			if tracee.tracer.is_some() || !proc.access_profile.can_kill(&tracee) {
+				return Err(errno!(EPERM));
+			}
+			tracee.tracer = Some(proc.pid);
+			tracee.kill(&Signal::SIGSTOP, false);
+			Ok(0)
+		}
+
+		PTRACE_DETACH => with_tracee(pid, |tracee, _| {
+			tracee.tracer = None;
+			tracee.debug_regs = DebugRegs::default();
+			Ok(0)
+		}),
+
+		PTRACE_PEEKUSER => with_tracee(pid, |tracee, mem_space| {
+			let val = tracee.debug_regs.get(user_debugreg(addr)?).unwrap_or(0);
+			let data = SyscallPtr::<u32>::from(data);
+			*data.get_mut(mem_space)?.ok_or_else(|| errno!(EFAULT))? = val;
+			Ok(0)
+		}),
+
+		PTRACE_POKEUSER => with_tracee(pid, |tracee, _| {
+			let n = user_debugreg(addr)?;
+			if matches!(n, 4 | 5) {
+				return Err(errno!(EIO));
+			}
+			if !tracee.debug_regs.set(n, data as _) {
+				return Err(errno!(EINVAL));
+			}
+			Ok(0)
+		}),
+
+		PTRACE_GETREGSET | PTRACE_SETREGSET => with_tracee(pid, |tracee, mem_space| {
+			if addr != NT_X86_DEBUGREG {
+				return Err(errno!(EINVAL));
+			}
+			transfer_regset(
+				&mut tracee.debug_regs,
+				mem_space,
+				SyscallPtr::from(data),
+				request == PTRACE_SETREGSET,
+			)
+		}),
+
+		_ => Err(errno!(EIO)),
+	}
+}
@@ -6,7 +6,7 @@ use crate::process::mem_space::ptr::SyscallSlice;
 use core::ffi::c_int;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x17b)]
 pub fn pwritev2(
 	fd: c_int,
 	iov: SyscallSlice<IOVec>,
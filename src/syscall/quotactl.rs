@@ -0,0 +1,317 @@
+//! The `quotactl` system call manipulates the disk quotas of a filesystem.
+
+use crate::device::DeviceType;
+use crate::errno;
+use crate::errno::Errno;
+use crate::file::fs::Filesystem;
+use crate::file::mountpoint;
+use crate::file::mountpoint::MountSource;
+use crate::file::path::Path;
+use crate::file::perm::AccessProfile;
+use crate::file::quota;
+use crate::file::vfs;
+use crate::file::FileContent;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::mem_space::ptr::SyscallString;
+use crate::process::Process;
+use crate::util::io::IO;
+use crate::util::lock::Mutex;
+use crate::util::ptr::arc::Arc;
+use core::ffi::c_int;
+use macros::syscall;
+
+/// Command: write the quotas to the disk.
+const Q_SYNC: c_int = 0x800001;
+/// Command: enable quotas.
+const Q_QUOTAON: c_int = 0x800002;
+/// Command: disable quotas.
+const Q_QUOTAOFF: c_int = 0x800003;
+/// Command: get the format of quotas.
+const Q_GETFMT: c_int = 0x800004;
+/// Command: get the information of a quota type.
+const Q_GETINFO: c_int = 0x800005;
+/// Command: set the information of a quota type.
+const Q_SETINFO: c_int = 0x800006;
+/// Command: get the quota of a user or group.
+const Q_GETQUOTA: c_int = 0x800007;
+/// Command: set the quota of a user or group.
+const Q_SETQUOTA: c_int = 0x800008;
+
+/// The quota format returned by `Q_GETFMT`.
+const QFMT_VFS_V1: u32 = 4;
+
+/// `dqb_valid` flag: the block limits are valid.
+const QIF_BLIMITS: u32 = 1;
+/// `dqb_valid` flag: the used space is valid.
+const QIF_SPACE: u32 = 2;
+/// `dqb_valid` flag: the inode limits are valid.
+const QIF_ILIMITS: u32 = 4;
+/// `dqb_valid` flag: the number of used inodes is valid.
+const QIF_INODES: u32 = 8;
+/// `dqb_valid` flag: the block grace time is valid.
+const QIF_BTIME: u32 = 16;
+/// `dqb_valid` flag: the inode grace time is valid.
+const QIF_ITIME: u32 = 32;
+/// `dqb_valid` flags: all fields are valid.
+const QIF_ALL: u32 = 63;
+
+/// `dqi_valid` flag: the block grace period is valid.
+const IIF_BGRACE: u32 = 1;
+/// `dqi_valid` flag: the inode grace period is valid.
+const IIF_IGRACE: u32 = 2;
+/// `dqi_valid` flag: the flags are valid.
+const IIF_FLAGS: u32 = 4;
+
+/// The quota of a user or group, as exchanged with userspace.
+#[repr(C)]
+#[derive(Debug)]
+struct IfDqblk {
+	/// The hard limit of space, in units of [`quota::QIF_DQBLKSIZE`].
+	dqb_bhardlimit: u64,
+	/// The soft limit of space, in units of [`quota::QIF_DQBLKSIZE`].
+	dqb_bsoftlimit: u64,
+	/// The used space, in bytes.
+	dqb_curspace: u64,
+	/// The hard limit of inodes.
+	dqb_ihardlimit: u64,
+	/// The soft limit of inodes.
+	dqb_isoftlimit: u64,
+	/// The number of used inodes.
+	dqb_curinodes: u64,
+	/// The time at which the space soft limit starts being enforced.
+	dqb_btime: u64,
+	/// The time at which the inode soft limit starts being enforced.
+	dqb_itime: u64,
+	/// Flags telling which fields are valid.
+	dqb_valid: u32,
+}
+
+/// The information of a quota type, as exchanged with userspace.
+#[repr(C)]
+#[derive(Debug)]
+struct IfDqinfo {
+	/// The grace period of space soft limits, in seconds.
+	dqi_bgrace: u64,
+	/// The grace period of inode soft limits, in seconds.
+	dqi_igrace: u64,
+	/// Quota flags.
+	dqi_flags: u32,
+	/// Flags telling which fields are valid.
+	dqi_valid: u32,
+}
+
+/// A filesystem along with the I/O interface of its source.
+type FilesystemIO = (Arc<Mutex<dyn Filesystem>>, Arc<Mutex<dyn IO>>);
+
+/// Returns the filesystem designated by `special`, along with its I/O interface.
+///
+/// `special` is either the path to the block device on which the filesystem is mounted, or the
+/// path to any file on the filesystem.
+fn get_filesystem(special: &Path, ap: &AccessProfile) -> Result<FilesystemIO, Errno> {
+	let file_mutex = vfs::get_file_from_path(special, ap, true)?;
+	let file = file_mutex.lock();
+	match file.get_content() {
+		FileContent::BlockDevice {
+			major,
+			minor,
+		} => {
+			let source = MountSource::Device {
+				dev_type: DeviceType::Block,
+				major: *major,
+				minor: *minor,
+			};
+			let fs = mountpoint::get_fs(&source).ok_or_else(|| errno!(ENODEV))?;
+			Ok((fs, source.get_io()?))
+		}
+		_ => {
+			let mountpoint_mutex = file
+				.get_location()
+				.get_mountpoint()
+				.ok_or_else(|| errno!(ENOTBLK))?;
+			let mountpoint = mountpoint_mutex.lock();
+			Ok((
+				mountpoint.get_filesystem(),
+				mountpoint.get_source().get_io()?,
+			))
+		}
+	}
+}
+
+#[syscall(0x083)]
+pub fn quotactl(cmd: c_int, special: SyscallString, id: c_int, addr: usize) -> Result<i32, Errno> {
+	let subcmd = cmd >> 8;
+	let kind = (cmd & 0xff) as usize;
+	if kind >= quota::MAXQUOTAS {
+		return Err(errno!(EINVAL));
+	}
+
+	let proc_mutex = Process::current_assert();
+	let (special, ap) = {
+		let proc = proc_mutex.lock();
+
+		let mem_space = proc.get_mem_space().unwrap();
+		let mem_space_guard = mem_space.lock();
+
+		let special = special
+			.get(&mem_space_guard)?
+			.ok_or_else(|| errno!(EFAULT))?;
+		let special = Path::from_str(special, true)?;
+		let special = super::util::get_absolute_path(&proc, special)?;
+
+		(special, proc.access_profile)
+	};
+
+	// Users can only get their own quotas
+	let own_id = match kind {
+		quota::USRQUOTA => ap.get_euid(),
+		_ => ap.get_egid(),
+	} as c_int;
+	if !ap.is_privileged() && (subcmd != Q_GETQUOTA || id != own_id) {
+		return Err(errno!(EPERM));
+	}
+
+	let (fs_mutex, io_mutex) = get_filesystem(&special, &ap)?;
+	let mut io = io_mutex.lock();
+	let mut fs = fs_mutex.lock();
+
+	match subcmd {
+		Q_SYNC => {}
+
+		Q_QUOTAON => {
+			if fs.is_readonly() {
+				return Err(errno!(EROFS));
+			}
+			// Only the in-memory format is supported, the quota file is ignored
+			fs.quota_on(&mut *io, kind)?;
+		}
+
+		Q_QUOTAOFF => {
+			let quota = fs.get_quota().ok_or_else(|| errno!(ENOSYS))?;
+			quota.disable(kind)?;
+		}
+
+		Q_GETFMT => {
+			let quota = fs.get_quota().ok_or_else(|| errno!(ENOSYS))?;
+			if !quota.is_enabled(kind) {
+				return Err(errno!(ESRCH));
+			}
+
+			let proc = proc_mutex.lock();
+			let mem_space = proc.get_mem_space().unwrap();
+			let mut mem_space_guard = mem_space.lock();
+			let fmt = SyscallPtr::<u32>::from(addr)
+				.get_mut(&mut mem_space_guard)?
+				.ok_or_else(|| errno!(EFAULT))?;
+			*fmt = QFMT_VFS_V1;
+		}
+
+		Q_GETINFO => {
+			let quota = fs.get_quota().ok_or_else(|| errno!(ENOSYS))?;
+			if !quota.is_enabled(kind) {
+				return Err(errno!(ESRCH));
+			}
+			let info = quota.get_info(kind);
+
+			let proc = proc_mutex.lock();
+			let mem_space = proc.get_mem_space().unwrap();
+			let mut mem_space_guard = mem_space.lock();
+			let dqinfo = SyscallPtr::<IfDqinfo>::from(addr)
+				.get_mut(&mut mem_space_guard)?
+				.ok_or_else(|| errno!(EFAULT))?;
+			*dqinfo = IfDqinfo {
+				dqi_bgrace: info.block_grace,
+				dqi_igrace: info.inode_grace,
+				dqi_flags: 0,
+				dqi_valid: IIF_BGRACE | IIF_IGRACE | IIF_FLAGS,
+			};
+		}
+
+		Q_SETINFO => {
+			let quota = fs.get_quota().ok_or_else(|| errno!(ENOSYS))?;
+			if !quota.is_enabled(kind) {
+				return Err(errno!(ESRCH));
+			}
+
+			let proc = proc_mutex.lock();
+			let mem_space = proc.get_mem_space().unwrap();
+			let mem_space_guard = mem_space.lock();
+			let dqinfo = SyscallPtr::<IfDqinfo>::from(addr)
+				.get(&mem_space_guard)?
+				.ok_or_else(|| errno!(EFAULT))?;
+			let mut info = quota.get_info(kind);
+			if dqinfo.dqi_valid & IIF_BGRACE != 0 {
+				info.block_grace = dqinfo.dqi_bgrace;
+			}
+			if dqinfo.dqi_valid & IIF_IGRACE != 0 {
+				info.inode_grace = dqinfo.dqi_igrace;
+			}
+			quota.set_info(kind, info);
+		}
+
+		Q_GETQUOTA => {
+			let quota = fs.get_quota().ok_or_else(|| errno!(ENOSYS))?;
+			if !quota.is_enabled(kind) {
+				return Err(errno!(ESRCH));
+			}
+			let dq = quota.get(kind, id as _);
+
+			let proc = proc_mutex.lock();
+			let mem_space = proc.get_mem_space().unwrap();
+			let mut mem_space_guard = mem_space.lock();
+			let dqblk = SyscallPtr::<IfDqblk>::from(addr)
+				.get_mut(&mut mem_space_guard)?
+				.ok_or_else(|| errno!(EFAULT))?;
+			*dqblk = IfDqblk {
+				dqb_bhardlimit: dq.block_hard_limit,
+				dqb_bsoftlimit: dq.block_soft_limit,
+				dqb_curspace: dq.space,
+				dqb_ihardlimit: dq.inode_hard_limit,
+				dqb_isoftlimit: dq.inode_soft_limit,
+				dqb_curinodes: dq.inodes,
+				dqb_btime: dq.block_time,
+				dqb_itime: dq.inode_time,
+				dqb_valid: QIF_ALL,
+			};
+		}
+
+		Q_SETQUOTA => {
+			let quota = fs.get_quota().ok_or_else(|| errno!(ENOSYS))?;
+			if !quota.is_enabled(kind) {
+				return Err(errno!(ESRCH));
+			}
+
+			let proc = proc_mutex.lock();
+			let mem_space = proc.get_mem_space().unwrap();
+			let mem_space_guard = mem_space.lock();
+			let dqblk = SyscallPtr::<IfDqblk>::from(addr)
+				.get(&mem_space_guard)?
+				.ok_or_else(|| errno!(EFAULT))?;
+			let mut dq = quota.get(kind, id as _);
+			if dqblk.dqb_valid & QIF_BLIMITS != 0 {
+				dq.block_hard_limit = dqblk.dqb_bhardlimit;
+				dq.block_soft_limit = dqblk.dqb_bsoftlimit;
+			}
+			if dqblk.dqb_valid & QIF_SPACE != 0 {
+				dq.space = dqblk.dqb_curspace;
+			}
+			if dqblk.dqb_valid & QIF_ILIMITS != 0 {
+				dq.inode_hard_limit = dqblk.dqb_ihardlimit;
+				dq.inode_soft_limit = dqblk.dqb_isoftlimit;
+			}
+			if dqblk.dqb_valid & QIF_INODES != 0 {
+				dq.inodes = dqblk.dqb_curinodes;
+			}
+			if dqblk.dqb_valid & QIF_BTIME != 0 {
+				dq.block_time = dqblk.dqb_btime;
+			}
+			if dqblk.dqb_valid & QIF_ITIME != 0 {
+				dq.inode_time = dqblk.dqb_itime;
+			}
+			quota.set(kind, id as _, dq)?;
+		}
+
+		_ => return Err(errno!(EINVAL)),
+	}
+
+	Ok(0)
+}
@@ -14,7 +14,7 @@ use macros::syscall;
 
 // TODO O_ASYNC
 
-#[syscall]
+#[syscall(0x003)]
 pub fn read(fd: c_int, buf: SyscallSlice<u8>, count: usize) -> Result<i32, Errno> {
 	if fd < 0 {
 		return Err(errno!(EBADF));
@@ -44,7 +44,7 @@ pub fn read(fd: c_int, buf: SyscallSlice<u8>, count: usize) -> Result<i32, Errno
 	};
 
 	loop {
-		super::util::signal_check(regs);
+		super::util::signal_check()?;
 
 		{
 			let mut mem_space_guard = mem_space.lock();
@@ -60,10 +60,13 @@ pub fn read(fd: c_int, buf: SyscallSlice<u8>, count: usize) -> Result<i32, Errno
 			if len == 0 && eof {
 				return Ok(0);
 			}
-			if len > 0 || flags & O_NONBLOCK != 0 {
-				// The file descriptor is non blocking
+			if len > 0 {
 				return Ok(len as _);
 			}
+			if flags & O_NONBLOCK != 0 {
+				// The file descriptor is non blocking
+				return Err(errno!(EAGAIN));
+			}
 
 			// Block on file
 			let mut proc = proc.lock();
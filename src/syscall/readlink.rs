@@ -11,7 +11,7 @@ use crate::util;
 use core::cmp::min;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x055)]
 pub fn readlink(
 	pathname: SyscallString,
 	buf: SyscallSlice<u8>,
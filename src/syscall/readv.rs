@@ -121,7 +121,7 @@ pub fn do_readv(
 	};
 
 	loop {
-		// TODO super::util::signal_check(regs);
+		// TODO super::util::signal_check()?;
 
 		{
 			let mut open_file = open_file_mutex.lock();
@@ -157,7 +157,7 @@ pub fn do_readv(
 	}
 }
 
-#[syscall]
+#[syscall(0x091)]
 pub fn readv(fd: c_int, iov: SyscallSlice<IOVec>, iovcnt: c_int) -> Result<i32, Errno> {
 	do_readv(fd, iov, iovcnt, None, None)
 }
@@ -22,7 +22,7 @@ const CMD_HALT: u32 = 2;
 /// Command to suspend the system.
 const CMD_SUSPEND: u32 = 3;
 
-#[syscall]
+#[syscall(0x058)]
 pub fn reboot(magic: c_int, magic2: c_int, cmd: c_int, _arg: *const c_void) -> Result<i32, Errno> {
 	if (magic as u32) != MAGIC || (magic2 as u32) != MAGIC2 {
 		return Err(errno!(EINVAL));
@@ -38,20 +38,23 @@ pub fn reboot(magic: c_int, magic2: c_int, cmd: c_int, _arg: *const c_void) -> R
 
 	match cmd as u32 {
 		CMD_POWEROFF => {
+			power::prepare();
 			crate::println!("Power down...");
 			power::shutdown();
 		}
 		CMD_REBOOT => {
+			power::prepare();
 			crate::println!("Rebooting...");
 			power::reboot();
 		}
 		CMD_HALT => {
+			power::prepare();
 			crate::println!("Halting...");
 			power::halt();
 		}
 		CMD_SUSPEND => {
-			// TODO Use ACPI to suspend the system
-			todo!()
+			power::suspend()?;
+			Ok(0)
 		}
 		_ => Err(errno!(EINVAL)),
 	}
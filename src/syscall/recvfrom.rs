@@ -0,0 +1,146 @@
+//! The `recvfrom` system call receives a message from a socket.
+
+use crate::errno::Errno;
+use crate::file::buffer;
+use crate::file::buffer::socket::Socket;
+use crate::file::buffer::socket::MSG_DONTWAIT;
+use crate::file::buffer::socket::MSG_OOB;
+use crate::file::buffer::socket::MSG_PEEK;
+use crate::file::buffer::socket::MSG_TRUNC;
+use crate::file::buffer::socket::MSG_WAITALL;
+use crate::file::buffer::Buffer;
+use crate::file::open_file::O_NONBLOCK;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::scheduler;
+use crate::process::Process;
+use crate::util::io;
+use core::any::Any;
+use core::cmp::min;
+use core::ffi::c_int;
+use macros::syscall;
+
+#[syscall(0x173)]
+pub fn recvfrom(
+	sockfd: c_int,
+	buf: SyscallSlice<u8>,
+	len: usize,
+	flags: c_int,
+	src_addr: SyscallSlice<u8>,
+	addrlen: SyscallPtr<isize>,
+) -> Result<i32, Errno> {
+	if sockfd < 0 {
+		return Err(errno!(EBADF));
+	}
+	// TODO support out-of-band data
+	if flags & MSG_OOB != 0 {
+		return Err(errno!(EOPNOTSUPP));
+	}
+	let len = min(len, i32::MAX as usize);
+
+	let (proc, mem_space, open_file) = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+
+		let mem_space = proc.get_mem_space().unwrap().clone();
+
+		let fds_mutex = proc.get_fds().unwrap().clone();
+		let fds = fds_mutex.lock();
+		let open_file_mutex = fds
+			.get_fd(sockfd as _)
+			.ok_or_else(|| errno!(EBADF))?
+			.get_open_file()
+			.clone();
+
+		drop(proc);
+		(proc_mutex, mem_space, open_file_mutex)
+	};
+
+	// Get socket
+	let (sock_mutex, nonblock) = {
+		let open_file = open_file.lock();
+		let sock_mutex = buffer::get(open_file.get_location()).ok_or_else(|| errno!(ENOENT))?;
+		let nonblock = open_file.get_flags() & O_NONBLOCK != 0 || flags & MSG_DONTWAIT != 0;
+		(sock_mutex, nonblock)
+	};
+
+	let mut total = 0;
+	loop {
+		// If interrupted after receiving data, return it
+		if let Err(e) = super::util::signal_check() {
+			if total > 0 {
+				return Ok(total as _);
+			}
+			return Err(e);
+		}
+
+		{
+			let mut sock = sock_mutex.lock();
+			let sock = (&mut *sock as &mut dyn Any)
+				.downcast_mut::<Socket>()
+				.ok_or_else(|| errno!(ENOTSOCK))?;
+			let stream = sock.desc().type_.is_stream();
+
+			let mut mem_space_guard = mem_space.lock();
+			let buf_slice = buf
+				.get_mut(&mut mem_space_guard, len)?
+				.ok_or_else(|| errno!(EFAULT))?;
+			let res = sock.recv(&mut buf_slice[total..], flags)?;
+			total += res.len;
+
+			let done = if stream {
+				// Unless waiting for all data, return as soon as some data is available
+				let wait_all = flags & (MSG_WAITALL | MSG_PEEK) == MSG_WAITALL;
+				total == len || (total > 0 && !wait_all) || sock.is_eof()
+			} else {
+				res.full_len > 0 || sock.is_eof()
+			};
+			if done {
+				// Write the sender's address
+				if let Some(addrlen_val) = addrlen.get_mut(&mut mem_space_guard)? {
+					if *addrlen_val < 0 {
+						return Err(errno!(EINVAL));
+					}
+					let addrlen_val = *addrlen_val as usize;
+
+					let addr_slice = src_addr
+						.get_mut(&mut mem_space_guard, addrlen_val)?
+						.ok_or_else(|| errno!(EFAULT))?;
+					let addr_len = match &res.src {
+						Some(src) => {
+							let l = min(addr_slice.len(), src.len());
+							addr_slice[..l].copy_from_slice(&src[..l]);
+							src.len()
+						}
+						None => sock.read_peername(addr_slice),
+					};
+
+					let addrlen_val = addrlen
+						.get_mut(&mut mem_space_guard)?
+						.ok_or_else(|| errno!(EFAULT))?;
+					*addrlen_val = addr_len as _;
+				}
+
+				// For datagrams, the real length is returned if requested
+				if !stream && flags & MSG_TRUNC != 0 {
+					return Ok(res.full_len as _);
+				}
+				return Ok(total as _);
+			}
+
+			if nonblock {
+				if total > 0 {
+					return Ok(total as _);
+				}
+				return Err(errno!(EAGAIN));
+			}
+
+			// Block on socket
+			let mut proc = proc.lock();
+			sock.add_waiting_process(&mut proc, io::POLLIN | io::POLLERR | io::POLLHUP)?;
+		}
+
+		// Make current process sleep
+		scheduler::end_tick();
+	}
+}
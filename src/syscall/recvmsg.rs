@@ -0,0 +1,383 @@
+//! The `recvmsg` system call receives a message from a socket, along with ancillary data.
+
+use crate::errno::Errno;
+use crate::file::buffer;
+use crate::file::buffer::socket::RecvResult;
+use crate::file::buffer::socket::Socket;
+use crate::file::buffer::socket::UCred;
+use crate::file::buffer::socket::BUFFER_SIZE;
+use crate::file::buffer::socket::MSG_CTRUNC;
+use crate::file::buffer::socket::MSG_DONTWAIT;
+use crate::file::buffer::socket::MSG_OOB;
+use crate::file::buffer::socket::MSG_PEEK;
+use crate::file::buffer::socket::MSG_TRUNC;
+use crate::file::buffer::socket::MSG_WAITALL;
+use crate::file::buffer::socket::SO_TIMESTAMP;
+use crate::file::buffer::Buffer;
+use crate::file::fd::FileDescriptorTable;
+use crate::file::fd::FD_CLOEXEC;
+use crate::file::open_file::O_NONBLOCK;
+use crate::limits;
+use crate::net::unix::PassedFiles;
+use crate::process::iovec::IOVec;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::mem_space::MemSpace;
+use crate::process::scheduler;
+use crate::process::Process;
+use crate::time::unit::TimeUnit;
+use crate::time::unit::Timeval;
+use crate::util::container::vec::Vec;
+use crate::util::io;
+use core::any::Any;
+use core::cmp::min;
+use core::ffi::c_int;
+use core::ffi::c_void;
+use core::mem::size_of;
+use core::slice;
+use macros::syscall;
+
+/// Control message level: socket.
+const SOL_SOCKET: c_int = 1;
+/// Control message type: file descriptors passed by the sender, as an array of `c_int`.
+const SCM_RIGHTS: c_int = 1;
+/// Control message type: the credentials of the sender, as a `UCred`.
+const SCM_CREDENTIALS: c_int = 2;
+/// Control message type: the timestamp of the received data, as a `Timeval`.
+const SCM_TIMESTAMP: c_int = SO_TIMESTAMP;
+
+/// Receive flag: set the `FD_CLOEXEC` flag on file descriptors received with [`SCM_RIGHTS`].
+const MSG_CMSG_CLOEXEC: c_int = 0x40000000;
+
+/// Structure describing a message sent or received on a socket.
+#[repr(C)]
+#[derive(Clone, Debug)]
+pub struct MsgHdr {
+	/// Buffer for the address of the peer.
+	msg_name: *mut c_void,
+	/// The size of the buffer for the address of the peer.
+	msg_namelen: u32,
+	/// The I/O vector containing the data.
+	msg_iov: *mut IOVec,
+	/// The number of entries in `msg_iov`.
+	msg_iovlen: usize,
+	/// Buffer for ancillary data.
+	msg_control: *mut c_void,
+	/// The size of the buffer for ancillary data.
+	msg_controllen: usize,
+	/// Flags on the received message.
+	msg_flags: c_int,
+}
+
+/// The header of an ancillary data object.
+#[repr(C)]
+struct CMsgHdr {
+	/// The length of the object, including the header.
+	cmsg_len: usize,
+	/// The originating protocol.
+	cmsg_level: c_int,
+	/// The protocol-specific type.
+	cmsg_type: c_int,
+}
+
+/// Rounds `len` up to the alignment of ancillary data objects.
+fn cmsg_align(len: usize) -> usize {
+	let align = size_of::<usize>();
+	(len + align - 1) & !(align - 1)
+}
+
+/// Writes `data` into the I/O vector `iov`, starting at offset `off`.
+fn scatter(
+	mem_space: &mut MemSpace,
+	iov: &[IOVec],
+	mut off: usize,
+	data: &[u8],
+) -> Result<(), Errno> {
+	let mut data_off = 0;
+	for i in iov {
+		if data_off >= data.len() {
+			break;
+		}
+		if off >= i.iov_len {
+			off -= i.iov_len;
+			continue;
+		}
+		let len = min(i.iov_len - off, data.len() - data_off);
+		let ptr = SyscallSlice::<u8>::from(i.iov_base as usize + off);
+		let slice = ptr.get_mut(mem_space, len)?.ok_or_else(|| errno!(EFAULT))?;
+		slice.copy_from_slice(&data[data_off..(data_off + len)]);
+		data_off += len;
+		off = 0;
+	}
+	Ok(())
+}
+
+/// Appends an ancillary data object to the buffer of `msg`, at offset `off`.
+///
+/// If the buffer is too small, the object is dropped and `msg` is flagged with [`MSG_CTRUNC`].
+///
+/// The function returns the offset following the object.
+fn push_control(
+	mem_space: &mut MemSpace,
+	msg: &mut MsgHdr,
+	off: usize,
+	r#type: c_int,
+	data: &[u8],
+) -> Result<usize, Errno> {
+	let hdr_len = cmsg_align(size_of::<CMsgHdr>());
+	let len = hdr_len + data.len();
+	if msg.msg_controllen < off + cmsg_align(len) {
+		msg.msg_flags |= MSG_CTRUNC;
+		return Ok(off);
+	}
+	let ptr = SyscallSlice::<u8>::from(msg.msg_control as usize + off);
+	let buf = ptr
+		.get_mut(mem_space, cmsg_align(len))?
+		.ok_or_else(|| errno!(EFAULT))?;
+	let hdr = CMsgHdr {
+		cmsg_len: len,
+		cmsg_level: SOL_SOCKET,
+		cmsg_type: r#type,
+	};
+	let hdr =
+		unsafe { slice::from_raw_parts(&hdr as *const _ as *const u8, size_of::<CMsgHdr>()) };
+	buf.fill(0);
+	buf[..hdr.len()].copy_from_slice(hdr);
+	buf[hdr_len..len].copy_from_slice(data);
+	Ok(off + cmsg_align(len))
+}
+
+/// Installs the files `files` passed by the sender in the file descriptors table `fds`, then
+/// appends the `SCM_RIGHTS` object listing them to the buffer of `msg`, at offset `off`.
+///
+/// Files that do not fit in the buffer or that cannot be installed are left in `files`, and `msg`
+/// is flagged with [`MSG_CTRUNC`].
+///
+/// The function returns the offset following the object.
+fn push_rights(
+	mem_space: &mut MemSpace,
+	msg: &mut MsgHdr,
+	off: usize,
+	fds: &mut FileDescriptorTable,
+	files: &mut PassedFiles,
+	flags: c_int,
+) -> Result<usize, Errno> {
+	if files.0.is_empty() {
+		return Ok(off);
+	}
+	let hdr_len = cmsg_align(size_of::<CMsgHdr>());
+	let max = msg.msg_controllen.saturating_sub(off + hdr_len) / size_of::<c_int>();
+	let fd_flags = if flags & MSG_CMSG_CLOEXEC != 0 {
+		FD_CLOEXEC
+	} else {
+		0
+	};
+
+	let mut ids = Vec::new();
+	for file in files.0.iter().take(max) {
+		// Files are charged to the receiving process, which cannot exceed its limit of open files
+		let Ok(fd) = fds.install_fd(fd_flags, file.clone()) else {
+			break;
+		};
+		ids.push(fd.get_id() as c_int)?;
+	}
+	if ids.len() < files.0.len() {
+		msg.msg_flags |= MSG_CTRUNC;
+	}
+	// Installed files are still referenced by the table
+	let mut i = 0;
+	files.0.retain(|_| {
+		i += 1;
+		i > ids.len()
+	});
+	if ids.is_empty() {
+		return Ok(off);
+	}
+
+	let data = unsafe {
+		slice::from_raw_parts(ids.as_ptr() as *const u8, ids.len() * size_of::<c_int>())
+	};
+	push_control(mem_space, msg, off, SCM_RIGHTS, data)
+}
+
+/// Writes the ancillary data of the received message.
+///
+/// Arguments:
+/// - `fds` is the file descriptors table in which passed files are installed.
+/// - `flags` is the set of flags given to the syscall.
+///
+/// The function updates the length and flags of `msg` accordingly.
+fn write_control(
+	mem_space: &mut MemSpace,
+	msg: &mut MsgHdr,
+	sock: &Socket,
+	res: &mut RecvResult,
+	fds: &mut FileDescriptorTable,
+	flags: c_int,
+) -> Result<(), Errno> {
+	let mut off = 0;
+	if let (true, Some(stamp)) = (sock.is_timestamping(), res.stamp) {
+		let tv = Timeval::from_nano(stamp);
+		let tv =
+			unsafe { slice::from_raw_parts(&tv as *const _ as *const u8, size_of::<Timeval>()) };
+		off = push_control(mem_space, msg, off, SCM_TIMESTAMP, tv)?;
+	}
+	if sock.is_passcred() {
+		let cred = res.cred.unwrap_or_else(UCred::unknown);
+		off = push_control(mem_space, msg, off, SCM_CREDENTIALS, cred.as_bytes())?;
+	}
+	off = push_rights(mem_space, msg, off, fds, &mut res.files, flags)?;
+	msg.msg_controllen = off;
+	Ok(())
+}
+
+#[syscall(0x174)]
+pub fn recvmsg(sockfd: c_int, msg: SyscallPtr<MsgHdr>, flags: c_int) -> Result<i32, Errno> {
+	if sockfd < 0 {
+		return Err(errno!(EBADF));
+	}
+	// TODO support out-of-band data
+	if flags & MSG_OOB != 0 {
+		return Err(errno!(EOPNOTSUPP));
+	}
+
+	let (proc, mem_space, fds_mutex, open_file) = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+
+		let mem_space = proc.get_mem_space().unwrap().clone();
+
+		let fds_mutex = proc.get_fds().unwrap().clone();
+		let fds = fds_mutex.lock();
+		let open_file_mutex = fds
+			.get_fd(sockfd as _)
+			.ok_or_else(|| errno!(EBADF))?
+			.get_open_file()
+			.clone();
+
+		drop(fds);
+		drop(proc);
+		(proc_mutex, mem_space, fds_mutex, open_file_mutex)
+	};
+
+	// Get the I/O vector
+	let iov = {
+		let mem_space_guard = mem_space.lock();
+		let msg = msg.get(&mem_space_guard)?.ok_or_else(|| errno!(EFAULT))?;
+		if msg.msg_iovlen > limits::IOV_MAX {
+			return Err(errno!(EMSGSIZE));
+		}
+		let iov_ptr = SyscallSlice::<IOVec>::from(msg.msg_iov as usize);
+		let iov_slice = iov_ptr
+			.get(&mem_space_guard, msg.msg_iovlen)?
+			.ok_or_else(|| errno!(EFAULT))?;
+		let mut iov = Vec::new();
+		iov.extend_from_slice(iov_slice)?;
+		iov
+	};
+	let len = iov
+		.iter()
+		.fold(0usize, |total, i| total.saturating_add(i.iov_len));
+	let len = min(len, i32::MAX as usize);
+
+	// Get socket
+	let (sock_mutex, nonblock) = {
+		let open_file = open_file.lock();
+		let sock_mutex = buffer::get(open_file.get_location()).ok_or_else(|| errno!(ENOENT))?;
+		let nonblock = open_file.get_flags() & O_NONBLOCK != 0 || flags & MSG_DONTWAIT != 0;
+		(sock_mutex, nonblock)
+	};
+
+	// The data is received into a kernel buffer, then written to the I/O vector
+	let mut buf = crate::vec![0u8; min(len, BUFFER_SIZE)]?;
+	let mut total = 0;
+	loop {
+		// If interrupted after receiving data, return it
+		if let Err(e) = super::util::signal_check() {
+			if total > 0 {
+				return Ok(total as _);
+			}
+			return Err(e);
+		}
+
+		{
+			let mut sock = sock_mutex.lock();
+			let sock = (&mut *sock as &mut dyn Any)
+				.downcast_mut::<Socket>()
+				.ok_or_else(|| errno!(ENOTSOCK))?;
+			let stream = sock.desc().type_.is_stream();
+
+			let mut mem_space_guard = mem_space.lock();
+			let l = min(buf.len(), len - total);
+			let mut res = sock.recv(&mut buf[..l], flags)?;
+			scatter(&mut mem_space_guard, &iov, total, &buf[..res.len])?;
+			total += res.len;
+
+			let done = if stream {
+				// Unless waiting for all data, return as soon as some data is available
+				let wait_all = flags & (MSG_WAITALL | MSG_PEEK) == MSG_WAITALL;
+				total == len || (total > 0 && !wait_all) || sock.is_eof()
+			} else {
+				res.full_len > 0 || sock.is_eof()
+			};
+			if done {
+				let mut hdr = msg
+					.get(&mem_space_guard)?
+					.ok_or_else(|| errno!(EFAULT))?
+					.clone();
+				hdr.msg_flags = 0;
+
+				// Write the sender's address
+				if !hdr.msg_name.is_null() {
+					let name_ptr = SyscallSlice::<u8>::from(hdr.msg_name as usize);
+					let name = name_ptr
+						.get_mut(&mut mem_space_guard, hdr.msg_namelen as _)?
+						.ok_or_else(|| errno!(EFAULT))?;
+					let name_len = match &res.src {
+						Some(src) => {
+							let l = min(name.len(), src.len());
+							name[..l].copy_from_slice(&src[..l]);
+							src.len()
+						}
+						None => sock.read_peername(name),
+					};
+					hdr.msg_namelen = name_len as _;
+				}
+
+				write_control(
+					&mut mem_space_guard,
+					&mut hdr,
+					sock,
+					&mut res,
+					&mut fds_mutex.lock(),
+					flags,
+				)?;
+				if !stream && res.full_len > res.len {
+					hdr.msg_flags |= MSG_TRUNC;
+				}
+				*msg.get_mut(&mut mem_space_guard)?
+					.ok_or_else(|| errno!(EFAULT))? = hdr;
+
+				// For datagrams, the real length is returned if requested
+				if !stream && flags & MSG_TRUNC != 0 {
+					return Ok(res.full_len as _);
+				}
+				return Ok(total as _);
+			}
+
+			if nonblock {
+				if total > 0 {
+					return Ok(total as _);
+				}
+				return Err(errno!(EAGAIN));
+			}
+
+			// Block on socket
+			let mut proc = proc.lock();
+			sock.add_waiting_process(&mut proc, io::POLLIN | io::POLLERR | io::POLLHUP)?;
+		}
+
+		// Make current process sleep
+		scheduler::end_tick();
+	}
+}
@@ -0,0 +1,61 @@
+//! The `removexattr` system call removes an extended attribute of a file.
+
+use super::util;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::vfs;
+use crate::process::mem_space::ptr::SyscallString;
+use crate::process::Process;
+use crate::util::container::vec::Vec;
+use core::ffi::c_int;
+use macros::syscall;
+
+/// Performs the `*removexattr` system calls.
+///
+/// Arguments:
+/// - `pathname` is the path to the file. If `None`, the file descriptor `fd` is used instead.
+/// - `fd` is the file descriptor of the file.
+/// - `follow_links` tells whether symbolic links are followed.
+/// - `name` is the name of the attribute.
+pub fn do_removexattr(
+	pathname: Option<SyscallString>,
+	fd: c_int,
+	follow_links: bool,
+	name: SyscallString,
+) -> EResult<i32> {
+	let (file_mutex, name, ap) = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+
+		let ap = proc.access_profile;
+
+		let mem_space = proc.get_mem_space().unwrap().clone();
+		let mem_space_guard = mem_space.lock();
+
+		let name = name.get(&mem_space_guard)?.ok_or_else(|| errno!(EFAULT))?;
+		let name = Vec::from_slice(name)?;
+		let pathname = pathname
+			.map(|pathname| {
+				let pathname = pathname
+					.get(&mem_space_guard)?
+					.ok_or_else(|| errno!(EFAULT))?;
+				Ok::<_, Errno>(Vec::from_slice(pathname)?)
+			})
+			.transpose()?;
+		// Unlock to avoid deadlock with procfs
+		drop(mem_space_guard);
+
+		let file_mutex =
+			util::get_file_by_path_or_fd(proc, pathname.as_deref(), fd, follow_links)?;
+		(file_mutex, name, ap)
+	};
+
+	let file = file_mutex.lock();
+	vfs::remove_xattr(&file, &name, &ap)?;
+	Ok(0)
+}
+
+#[syscall(0x0eb)]
+pub fn removexattr(pathname: SyscallString, name: SyscallString) -> EResult<i32> {
+	do_removexattr(Some(pathname), -1, true, name)
+}
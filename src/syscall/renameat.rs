@@ -0,0 +1,16 @@
+//! The `renameat` system call renames a file, with paths relative to directory file descriptors.
+
+use crate::errno::Errno;
+use crate::process::mem_space::ptr::SyscallString;
+use core::ffi::c_int;
+use macros::syscall;
+
+#[syscall(0x12e)]
+pub fn renameat(
+	olddirfd: c_int,
+	oldpath: SyscallString,
+	newdirfd: c_int,
+	newpath: SyscallString,
+) -> Result<i32, Errno> {
+	super::renameat2::do_renameat2(olddirfd, oldpath, newdirfd, newpath, 0)
+}
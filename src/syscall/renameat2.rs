@@ -1,11 +1,12 @@
 //! The `renameat2` allows to rename a file.
 
+use super::util;
+use crate::errno::EResult;
 use crate::errno::Errno;
-use crate::file;
 use crate::file::vfs;
-use crate::file::FileType;
 use crate::process::mem_space::ptr::SyscallString;
 use crate::process::Process;
+use crate::util::container::vec::Vec;
 use core::ffi::c_int;
 use macros::syscall;
 
@@ -14,19 +15,32 @@ const RENAME_NOREPLACE: c_int = 1;
 /// Flag: Exchanges old and new paths atomically.
 const RENAME_EXCHANGE: c_int = 2;
 
-#[syscall]
-pub fn renameat2(
+/// Performs the `rename*` system calls.
+///
+/// Arguments:
+/// - `olddirfd` and `oldpath` are the directory file descriptor and the path of the file to
+/// rename.
+/// - `newdirfd` and `newpath` are the directory file descriptor and the new path of the file.
+/// - `flags` is a combination of `RENAME_*` flags.
+pub fn do_renameat2(
 	olddirfd: c_int,
 	oldpath: SyscallString,
 	newdirfd: c_int,
 	newpath: SyscallString,
-	_flags: c_int,
-) -> Result<i32, Errno> {
-	let (old_mutex, new_parent_mutex, new_name, ap) = {
-		let proc_mutex = Process::current_assert();
-		let proc = proc_mutex.lock();
+	flags: c_int,
+) -> EResult<i32> {
+	if flags & !(RENAME_NOREPLACE | RENAME_EXCHANGE) != 0 {
+		return Err(errno!(EINVAL));
+	}
+	let noreplace = flags & RENAME_NOREPLACE != 0;
+	let exchange = flags & RENAME_EXCHANGE != 0;
+	if noreplace && exchange {
+		return Err(errno!(EINVAL));
+	}
 
-		let ap = proc.access_profile;
+	let proc_mutex = Process::current_assert();
+	let (oldpath, newpath, ap) = {
+		let proc = proc_mutex.lock();
 
 		let mem_space = proc.get_mem_space().unwrap().clone();
 		let mem_space_guard = mem_space.lock();
@@ -34,44 +48,42 @@ pub fn renameat2(
 		let oldpath = oldpath
 			.get(&mem_space_guard)?
 			.ok_or_else(|| errno!(EFAULT))?;
-		let old = super::util::get_file_at(proc, olddirfd, oldpath, false, 0)?;
-
-		let proc = proc_mutex.lock();
 		let newpath = newpath
 			.get(&mem_space_guard)?
 			.ok_or_else(|| errno!(EFAULT))?;
-		let (new_parent, new_name) =
-			super::util::get_parent_at_with_name(proc, newdirfd, newpath, false, 0)?;
-
-		(old, new_parent, new_name, ap)
+		(
+			Vec::from_slice(oldpath)?,
+			Vec::from_slice(newpath)?,
+			proc.access_profile,
+		)
 	};
+	let (old_parent_mutex, old_name) =
+		util::get_parent_at_with_name(proc_mutex.lock(), olddirfd, &oldpath, true, 0)?;
+	let (new_parent_mutex, new_name) =
+		util::get_parent_at_with_name(proc_mutex.lock(), newdirfd, &newpath, true, 0)?;
 
-	let mut old = old_mutex.lock();
+	let mut old_parent = old_parent_mutex.lock();
 	let mut new_parent = new_parent_mutex.lock();
-
-	// TODO Check permissions if sticky bit is set
-
-	if new_parent.get_location().get_mountpoint_id() == old.get_location().get_mountpoint_id() {
-		// Old and new are both on the same filesystem
-
-		// TODO On fail, undo
-
-		// Create link at new location
-		// The `..` entry is already updated by the file system since having the same
-		// directory in several locations is not allowed
-		vfs::create_link(&mut old, &mut new_parent, &new_name, &ap)?;
-
-		if old.get_type() != FileType::Directory {
-			vfs::remove_file(&mut old, &ap)?;
-		}
-	} else {
-		// Old and new are on different filesystems.
-
-		// TODO On fail, undo
-
-		file::util::copy_file(&mut old, &mut new_parent, new_name)?;
-		file::util::remove_recursive(&mut old, &ap)?;
-	}
+	vfs::rename(
+		&mut old_parent,
+		&old_name,
+		&mut new_parent,
+		&new_name,
+		&ap,
+		noreplace,
+		exchange,
+	)?;
 
 	Ok(0)
 }
+
+#[syscall(0x161)]
+pub fn renameat2(
+	olddirfd: c_int,
+	oldpath: SyscallString,
+	newdirfd: c_int,
+	newpath: SyscallString,
+	flags: c_int,
+) -> Result<i32, Errno> {
+	do_renameat2(olddirfd, oldpath, newdirfd, newpath, flags)
+}
@@ -0,0 +1,30 @@
+//! The `restart_syscall` system call resumes a system call that has been interrupted by a signal.
+//!
+//! It is not meant to be called by userspace directly: when a system call is interrupted and
+//! cannot be restarted with the same arguments, the kernel makes the process call it after the
+//! signal has been handled.
+
+use super::nanosleep;
+use crate::errno;
+use crate::errno::Errno;
+use crate::process::Process;
+use crate::process::RestartBlock;
+use macros::syscall;
+
+#[syscall(0x000)]
+pub fn restart_syscall() -> Result<i32, Errno> {
+	let restart_block = {
+		let proc_mutex = Process::current_assert();
+		let mut proc = proc_mutex.lock();
+		proc.restart_block.take()
+	};
+
+	match restart_block {
+		Some(RestartBlock::Nanosleep {
+			end,
+			rem,
+		}) => nanosleep::do_nanosleep(end, rem.into()),
+
+		None => Err(errno!(EINTR)),
+	}
+}
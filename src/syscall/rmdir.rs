@@ -10,7 +10,7 @@ use crate::process::mem_space::ptr::SyscallString;
 use crate::process::Process;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x028)]
 pub fn rmdir(pathname: SyscallString) -> Result<i32, Errno> {
 	let (path, ap) = {
 		let proc_mutex = Process::current_assert();
@@ -9,7 +9,7 @@ use crate::syscall::Signal;
 use core::ffi::c_int;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x0ae)]
 pub fn rt_sigaction(
 	signum: c_int,
 	act: SyscallPtr<SigAction>,
@@ -16,7 +16,7 @@ const SIG_UNBLOCK: i32 = 1;
 const SIG_SETMASK: i32 = 2;
 
 // TODO Use SigSet in crate::process::signal
-#[syscall]
+#[syscall(0x0af)]
 pub fn rt_sigprocmask(
 	how: c_int,
 	set: SyscallSlice<u8>,
@@ -0,0 +1,13 @@
+//! The `sched_get_priority_max` system call returns the maximum priority of a scheduling policy.
+
+use crate::errno;
+use crate::errno::Errno;
+use crate::process::scheduler::SchedPolicy;
+use core::ffi::c_int;
+use macros::syscall;
+
+#[syscall(0x09f)]
+pub fn sched_get_priority_max(policy: c_int) -> Result<i32, Errno> {
+	let policy = SchedPolicy::from_id(policy).ok_or_else(|| errno!(EINVAL))?;
+	Ok(policy.get_priority_range().1 as _)
+}
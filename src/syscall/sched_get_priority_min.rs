@@ -0,0 +1,13 @@
+//! The `sched_get_priority_min` system call returns the minimum priority of a scheduling policy.
+
+use crate::errno;
+use crate::errno::Errno;
+use crate::process::scheduler::SchedPolicy;
+use core::ffi::c_int;
+use macros::syscall;
+
+#[syscall(0x0a0)]
+pub fn sched_get_priority_min(policy: c_int) -> Result<i32, Errno> {
+	let policy = SchedPolicy::from_id(policy).ok_or_else(|| errno!(EINVAL))?;
+	Ok(policy.get_priority_range().0 as _)
+}
@@ -0,0 +1,33 @@
+//! The `sched_getparam` system call returns the realtime priority of a process.
+
+use super::sched_setscheduler;
+use crate::errno;
+use crate::errno::Errno;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::scheduler::SchedParam;
+use crate::process::Process;
+use core::ffi::c_int;
+use macros::syscall;
+
+#[syscall(0x09b)]
+pub fn sched_getparam(pid: c_int, param: SyscallPtr<SchedParam>) -> Result<i32, Errno> {
+	let priority = {
+		let proc_mutex = sched_setscheduler::get_target(pid)?;
+		let proc = proc_mutex.lock();
+		proc.rt_priority
+	};
+
+	let proc_mutex = Process::current_assert();
+	let proc = proc_mutex.lock();
+
+	let mem_space = proc.get_mem_space().unwrap();
+	let mut mem_space_guard = mem_space.lock();
+	let param = param
+		.get_mut(&mut mem_space_guard)?
+		.ok_or_else(|| errno!(EINVAL))?;
+	*param = SchedParam {
+		sched_priority: priority as _,
+	};
+
+	Ok(0)
+}
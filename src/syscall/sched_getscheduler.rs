@@ -0,0 +1,13 @@
+//! The `sched_getscheduler` system call returns the scheduling policy of a process.
+
+use super::sched_setscheduler;
+use crate::errno::Errno;
+use core::ffi::c_int;
+use macros::syscall;
+
+#[syscall(0x09d)]
+pub fn sched_getscheduler(pid: c_int) -> Result<i32, Errno> {
+	let proc_mutex = sched_setscheduler::get_target(pid)?;
+	let proc = proc_mutex.lock();
+	Ok(proc.policy.get_id())
+}
@@ -0,0 +1,14 @@
+//! The `sched_setparam` system call sets the realtime priority of a process, keeping its
+//! scheduling policy.
+
+use super::sched_setscheduler;
+use crate::errno::Errno;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::scheduler::SchedParam;
+use core::ffi::c_int;
+use macros::syscall;
+
+#[syscall(0x09a)]
+pub fn sched_setparam(pid: c_int, param: SyscallPtr<SchedParam>) -> Result<i32, Errno> {
+	sched_setscheduler::do_setscheduler(pid, None, param)
+}
@@ -0,0 +1,87 @@
+//! The `sched_setscheduler` system call sets the scheduling policy and the realtime priority of a
+//! process.
+
+use crate::errno;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::process::futex;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::pid::Pid;
+use crate::process::scheduler::SchedParam;
+use crate::process::scheduler::SchedPolicy;
+use crate::process::Process;
+use crate::util::lock::IntMutex;
+use crate::util::ptr::arc::Arc;
+use core::ffi::c_int;
+use macros::syscall;
+
+/// Returns the process targeted by a scheduling system call with the PID `pid`.
+///
+/// If `pid` is zero, the current process is returned.
+pub fn get_target(pid: c_int) -> EResult<Arc<IntMutex<Process>>> {
+	if pid < 0 {
+		return Err(errno!(EINVAL));
+	}
+	if pid == 0 {
+		return Ok(Process::current_assert());
+	}
+	Pid::try_from(pid)
+		.ok()
+		.and_then(Process::get_by_pid)
+		.ok_or_else(|| errno!(ESRCH))
+}
+
+/// Sets the scheduling policy and the parameters `param` of the process with PID `pid`.
+///
+/// If `policy` is `None`, the policy of the process is kept.
+pub fn do_setscheduler(
+	pid: c_int,
+	policy: Option<SchedPolicy>,
+	param: SyscallPtr<SchedParam>,
+) -> EResult<i32> {
+	let (ap, priority) = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+
+		let mem_space = proc.get_mem_space().unwrap();
+		let mem_space_guard = mem_space.lock();
+		let param = param.get(&mem_space_guard)?.ok_or_else(|| errno!(EINVAL))?;
+
+		(proc.access_profile, param.sched_priority)
+	};
+
+	let proc_mutex = get_target(pid)?;
+	let mut proc = proc_mutex.lock();
+
+	let policy = policy.unwrap_or(proc.policy);
+	let (min, max) = policy.get_priority_range();
+	let priority = u32::try_from(priority)
+		.ok()
+		.filter(|p| (min..=max).contains(p))
+		.ok_or_else(|| errno!(EINVAL))?;
+	if !ap.can_kill(&proc) {
+		return Err(errno!(EPERM));
+	}
+	// Only privileged processes can raise the realtime priority
+	if !ap.is_privileged() && priority > proc.rt_priority {
+		return Err(errno!(EPERM));
+	}
+
+	proc.policy = policy;
+	proc.rt_priority = priority;
+	let pid = proc.pid;
+	drop(proc);
+	// The owners of the futexes the process is waiting on inherit the new priority
+	futex::priority_changed(pid);
+	Ok(0)
+}
+
+#[syscall(0x09c)]
+pub fn sched_setscheduler(
+	pid: c_int,
+	policy: c_int,
+	param: SyscallPtr<SchedParam>,
+) -> Result<i32, Errno> {
+	let policy = SchedPolicy::from_id(policy).ok_or_else(|| errno!(EINVAL))?;
+	do_setscheduler(pid, Some(policy), param)
+}
@@ -5,7 +5,7 @@ use crate::errno::Errno;
 use crate::process::scheduler;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x09e)]
 pub fn sched_yield() -> Result<i32, Errno> {
 	scheduler::end_tick();
 	Ok(0)
@@ -1,17 +1,22 @@
 //! `select` waits for a file descriptor in the given sets to be readable,
 //! writable or for an exception to occur.
 
+use crate::errno::EResult;
 use crate::errno::Errno;
+use crate::file::blocking::PollTable;
 use crate::process::mem_space::ptr::SyscallPtr;
 use crate::process::mem_space::ptr::SyscallSlice;
-use crate::process::scheduler;
+use crate::process::mem_space::MemSpace;
 use crate::process::Process;
 use crate::time::clock;
 use crate::time::clock::CLOCK_MONOTONIC;
 use crate::time::unit::TimeUnit;
+use crate::time::unit::Timestamp;
+use crate::time::unit::TimestampScale;
 use crate::time::unit::Timeval;
 use crate::util::io;
 use crate::util::io::IO;
+use crate::util::lock::IntMutex;
 use core::cmp::min;
 use core::ffi::c_int;
 use core::ffi::c_long;
@@ -22,7 +27,7 @@ pub const FD_SETSIZE: usize = 1024;
 
 /// Structure representing `fd_set`.
 #[repr(C)]
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct FDSet {
 	/// The set's bitfield.
 	fds_bits: [c_long; FD_SETSIZE / c_long::BITS as usize],
@@ -35,8 +40,8 @@ impl FDSet {
 			return false;
 		}
 
-		// TODO Check correctness
-		self.fds_bits[(fd as usize) / c_long::BITS as usize] >> (fd % (c_long::BITS as u32)) != 0
+		let bits = self.fds_bits[(fd as usize) / c_long::BITS as usize];
+		bits & (1 << (fd % (c_long::BITS as u32))) != 0
 	}
 
 	/// Sets the bit for file descriptor `fd`.
@@ -53,6 +58,29 @@ impl FDSet {
 	}
 }
 
+/// Copies the set pointed to by `ptr` from userspace.
+///
+/// If the pointer is null, the function returns `None`.
+fn read_set(mem_space: &IntMutex<MemSpace>, ptr: &SyscallPtr<FDSet>) -> EResult<Option<FDSet>> {
+	let mem_space_guard = mem_space.lock();
+	Ok(ptr.get(&mem_space_guard)?.cloned())
+}
+
+/// Writes `set` back to the userspace set pointed to by `ptr`.
+///
+/// If the pointer is null, the function does nothing.
+fn write_set(
+	mem_space: &IntMutex<MemSpace>,
+	ptr: &SyscallPtr<FDSet>,
+	set: &Option<FDSet>,
+) -> EResult<()> {
+	let mut mem_space_guard = mem_space.lock();
+	if let (Some(dst), Some(set)) = (ptr.get_mut(&mut mem_space_guard)?, set) {
+		*dst = set.clone();
+	}
+	Ok(())
+}
+
 /// Performs the select operation.
 ///
 /// Arguments:
@@ -60,7 +88,8 @@ impl FDSet {
 /// - `readfds` is the bitfield of fds to check for read operations.
 /// - `writefds` is the bitfield of fds to check for write operations.
 /// - `exceptfds` is the bitfield of fds to check for exceptional conditions.
-/// - `timeout` is the timeout after which the syscall returns.
+/// - `timeout` is the timeout after which the syscall returns. If null, the syscall waits
+/// indefinitely.
 /// - `sigmask` TODO
 pub fn do_select<T: TimeUnit>(
 	nfds: u32,
@@ -71,146 +100,130 @@ pub fn do_select<T: TimeUnit>(
 	_sigmask: Option<SyscallSlice<u8>>,
 ) -> Result<i32, Errno> {
 	// Getting start timestamp
-	let start = clock::current_time_struct::<T>(CLOCK_MONOTONIC)?;
+	let start = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Nanosecond)?;
 
-	// Getting timeout
-	let timeout = {
-		let proc_mutex = Process::current_assert();
+	let proc_mutex = Process::current_assert();
+	let (mem_space, fds_mutex, table) = {
 		let proc = proc_mutex.lock();
+		let mem_space = proc.get_mem_space().unwrap().clone();
+		let fds_mutex = proc.get_fds().unwrap().clone();
+		(mem_space, fds_mutex, PollTable::new(proc.pid)?)
+	};
 
-		let mem_space = proc.get_mem_space().unwrap();
+	// Getting timeout
+	let timeout = {
 		let mem_space_guard = mem_space.lock();
-		timeout.get(&mem_space_guard)?.cloned().unwrap_or_default()
+		timeout.get(&mem_space_guard)?.cloned()
 	};
-
 	// Tells whether the syscall immediately returns
-	let polling = timeout.is_zero();
-	// The end timestamp
-	let end = start + timeout;
+	let polling = timeout.as_ref().is_some_and(TimeUnit::is_zero);
+	// The end timestamp. If `None`, the syscall doesn't time out
+	let end: Option<Timestamp> = timeout.map(|timeout| start + timeout.to_nano());
+
+	// The sets are read once since they are overwritten with the results
+	let read_in = read_set(&mem_space, &readfds)?;
+	let write_in = read_set(&mem_space, &writefds)?;
+	let except_in = read_set(&mem_space, &exceptfds)?;
+	// Returns the mask of events to check on the file descriptor `fd_id`
+	let events_mask = |fd_id: u32| {
+		let is_set = |set: &Option<FDSet>| set.as_ref().is_some_and(|set| set.is_set(fd_id));
+		let mut mask = 0;
+		if is_set(&read_in) {
+			mask |= io::POLLIN;
+		}
+		if is_set(&write_in) {
+			mask |= io::POLLOUT;
+		}
+		if is_set(&except_in) {
+			mask |= io::POLLPRI;
+		}
+		mask
+	};
 
+	let nfds = min(nfds, FD_SETSIZE as u32);
 	loop {
-		let mut events_count = 0;
-		// Set if every bitfields are set to zero
-		let mut all_zeros = true;
-
-		for fd_id in 0..min(nfds, FD_SETSIZE as u32) {
-			let (mem_space, fds_mutex) = {
-				let proc_mutex = Process::current_assert();
-				let proc = proc_mutex.lock();
-
-				let mem_space = proc.get_mem_space().unwrap().clone();
-				let fds_mutex = proc.get_fds().unwrap().clone();
-
-				(mem_space, fds_mutex)
-			};
-
-			let (read, write, except) = {
-				let mem_space_guard = mem_space.lock();
-
-				let read = readfds
-					.get(&mem_space_guard)?
-					.map(|fds| fds.is_set(fd_id))
-					.unwrap_or(false);
-				let write = writefds
-					.get(&mem_space_guard)?
-					.map(|fds| fds.is_set(fd_id))
-					.unwrap_or(false);
-				let except = exceptfds
-					.get(&mem_space_guard)?
-					.map(|fds| fds.is_set(fd_id))
-					.unwrap_or(false);
-
-				(read, write, except)
-			};
-
-			if read || write || except {
-				all_zeros = false;
-			}
+		super::util::signal_check()?;
 
+		let mut read_out = read_in.clone();
+		let mut write_out = write_in.clone();
+		let mut except_out = except_in.clone();
+		let mut events_count = 0;
+		{
 			let fds = fds_mutex.lock();
-			let fd = fds.get_fd(fd_id);
 
-			// Checking the file descriptor exists
-			let fd = match fd {
-				Some(fd) => fd,
-
-				None => {
-					if read || write || except {
-						return Err(errno!(EBADF));
+			// Registering on the file descriptors before checking them, so that no event can be
+			// missed in between
+			{
+				let proc = proc_mutex.lock();
+				for fd_id in 0..nfds {
+					let mask = events_mask(fd_id);
+					if mask == 0 {
+						continue;
 					}
-
-					continue;
+					let fd = fds.get_fd(fd_id).ok_or_else(|| errno!(EBADF))?;
+					fd.get_open_file().lock().register_poll(
+						&proc,
+						&table,
+						mask | io::POLLERR | io::POLLHUP,
+					)?;
 				}
-			};
-
-			// Building event mask
-			let mut mask = 0;
-			if read {
-				mask |= io::POLLIN;
-			}
-			if write {
-				mask |= io::POLLOUT;
-			}
-			if except {
-				mask |= io::POLLPRI;
 			}
 
-			let open_file_mutex = fd.get_open_file();
-			let mut open_file = open_file_mutex.lock();
-
-			let result = open_file.poll(mask)?;
-
-			// Setting results
-			let mut mem_space_guard = mem_space.lock();
-			if read && result & io::POLLIN != 0 {
-				readfds
-					.get_mut(&mut mem_space_guard)?
-					.map(|fds| fds.set(fd_id));
-				events_count += 1;
-			} else {
-				readfds
-					.get_mut(&mut mem_space_guard)?
-					.map(|fds| fds.clear(fd_id));
-			}
-			if write && result & io::POLLOUT != 0 {
-				writefds
-					.get_mut(&mut mem_space_guard)?
-					.map(|fds| fds.set(fd_id));
-				events_count += 1;
-			} else {
-				writefds
-					.get_mut(&mut mem_space_guard)?
-					.map(|fds| fds.clear(fd_id));
-			}
-			if except && result & io::POLLPRI != 0 {
-				exceptfds
-					.get_mut(&mut mem_space_guard)?
-					.map(|fds| fds.set(fd_id));
-				events_count += 1;
-			} else {
-				exceptfds
-					.get_mut(&mut mem_space_guard)?
-					.map(|fds| fds.clear(fd_id));
+			for fd_id in 0..nfds {
+				let mask = events_mask(fd_id);
+				if mask == 0 {
+					continue;
+				}
+				let fd = fds.get_fd(fd_id).ok_or_else(|| errno!(EBADF))?;
+
+				let open_file_mutex = fd.get_open_file();
+				let mut open_file = open_file_mutex.lock();
+
+				// Errors and hang ups make the file descriptor ready, since the next operation
+				// would not block
+				let result = open_file.poll(mask | io::POLLERR | io::POLLHUP)?;
+
+				// Setting results
+				let readable = result & (io::POLLIN | io::POLLERR | io::POLLHUP) != 0;
+				let writable = result & (io::POLLOUT | io::POLLERR) != 0;
+				if mask & io::POLLIN != 0 && readable {
+					events_count += 1;
+				} else if let Some(set) = &mut read_out {
+					set.clear(fd_id);
+				}
+				if mask & io::POLLOUT != 0 && writable {
+					events_count += 1;
+				} else if let Some(set) = &mut write_out {
+					set.clear(fd_id);
+				}
+				if mask & io::POLLPRI != 0 && result & io::POLLPRI != 0 {
+					events_count += 1;
+				} else if let Some(set) = &mut except_out {
+					set.clear(fd_id);
+				}
 			}
 		}
 
-		// If one or more events occured, return
-		if all_zeros || polling || events_count > 0 {
+		// Returns if one or more events occurred, or on timeout
+		let timed_out = match end {
+			Some(end) => {
+				polling || clock::current_time(CLOCK_MONOTONIC, TimestampScale::Nanosecond)? >= end
+			}
+			None => false,
+		};
+		if events_count > 0 || timed_out {
+			write_set(&mem_space, &readfds, &read_out)?;
+			write_set(&mem_space, &writefds, &write_out)?;
+			write_set(&mem_space, &exceptfds, &except_out)?;
 			return Ok(events_count);
 		}
 
-		let curr = clock::current_time_struct::<T>(CLOCK_MONOTONIC)?;
-		// On timeout, return 0
-		if curr >= end {
-			return Ok(0);
-		}
-
-		// TODO Make the process sleep?
-		scheduler::end_tick();
+		// Sleep until an event occurs on a file descriptor, or until timeout
+		table.wait(&proc_mutex, end)?;
 	}
 }
 
-#[syscall]
+#[syscall(0x052)]
 pub fn select(
 	nfds: c_int,
 	readfds: SyscallPtr<FDSet>,
@@ -0,0 +1,191 @@
+//! The `sendfile` system call copies data from one file descriptor to another without going
+//! through userspace.
+
+use crate::errno;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::open_file::OpenFile;
+use crate::file::open_file::O_APPEND;
+use crate::file::open_file::O_NONBLOCK;
+use crate::file::FileType;
+use crate::memory;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::scheduler;
+use crate::process::signal::Signal;
+use crate::process::Process;
+use crate::util::io;
+use crate::util::io::IO;
+use crate::util::lock::IntMutex;
+use crate::util::lock::Mutex;
+use core::cmp::min;
+use core::ffi::c_int;
+use macros::syscall;
+
+/// Writes `buf` to `output`, blocking until at least one byte can be written.
+///
+/// Arguments:
+/// - `proc` is the current process.
+/// - `nonblock` tells whether the function returns [`errno::EAGAIN`] instead of blocking.
+///
+/// The function returns the number of bytes written.
+fn write_chunk(
+	proc: &IntMutex<Process>,
+	output: &Mutex<OpenFile>,
+	buf: &[u8],
+	nonblock: bool,
+) -> EResult<usize> {
+	loop {
+		super::util::signal_check()?;
+		{
+			let mut output = output.lock();
+			let len = match output.write(0, buf) {
+				Ok(len) => len as usize,
+				Err(e) => {
+					// If writing to a broken pipe, kill with SIGPIPE
+					if e.as_int() == errno::EPIPE {
+						proc.lock().kill(&Signal::SIGPIPE, false);
+					}
+					return Err(e);
+				}
+			};
+			if len > 0 {
+				return Ok(len);
+			}
+			if nonblock {
+				return Err(errno!(EAGAIN));
+			}
+			output.add_waiting_process(&mut proc.lock(), io::POLLOUT | io::POLLERR)?;
+		}
+		scheduler::end_tick();
+	}
+}
+
+/// Performs the `sendfile` system call.
+///
+/// Arguments:
+/// - `out_fd` is the file descriptor to write to.
+/// - `in_fd` is the file descriptor to read from.
+/// - `offset` is a pointer to the offset to read from in `in_fd`. If not null, the offset of
+/// `in_fd` is left untouched and the pointed value is updated instead.
+/// - `count` is the number of bytes to copy.
+/// - `max` is the largest offset representable by `T`.
+pub fn do_sendfile<T: Copy + Into<i64> + TryFrom<u64>>(
+	out_fd: c_int,
+	in_fd: c_int,
+	offset: SyscallPtr<T>,
+	count: usize,
+	max: u64,
+) -> EResult<i32> {
+	if out_fd < 0 || in_fd < 0 {
+		return Err(errno!(EBADF));
+	}
+
+	let proc_mutex = Process::current_assert();
+	let (mem_space, input_mutex, output_mutex) = {
+		let proc = proc_mutex.lock();
+		let mem_space = proc.get_mem_space().unwrap().clone();
+
+		let fds_mutex = proc.get_fds().unwrap();
+		let fds = fds_mutex.lock();
+		let input = fds
+			.get_fd(in_fd as _)
+			.ok_or_else(|| errno!(EBADF))?
+			.get_open_file()
+			.clone();
+		let output = fds
+			.get_fd(out_fd as _)
+			.ok_or_else(|| errno!(EBADF))?
+			.get_open_file()
+			.clone();
+
+		(mem_space, input, output)
+	};
+	let off = offset.get(&mem_space.lock())?.copied();
+
+	let (start_off, nonblock) = {
+		let input = input_mutex.lock();
+		let output = output_mutex.lock();
+		if !input.can_read() || !output.can_write() {
+			return Err(errno!(EBADF));
+		}
+		if output.get_flags() & O_APPEND != 0 {
+			return Err(errno!(EINVAL));
+		}
+		let start_off = match off {
+			Some(off) => {
+				if input.get_file().lock().get_type() == FileType::Fifo {
+					return Err(errno!(ESPIPE));
+				}
+				u64::try_from(off.into()).map_err(|_| errno!(EINVAL))?
+			}
+			None => input.get_offset(),
+		};
+		(start_off, output.get_flags() & O_NONBLOCK != 0)
+	};
+	if start_off >= max {
+		return Err(errno!(EOVERFLOW));
+	}
+	let count = min(count, i32::MAX as usize);
+	let count = min(count as u64, max - start_off) as usize;
+
+	// Data is copied one page at a time through a kernel buffer
+	let mut buf = crate::vec![0; memory::PAGE_SIZE]?;
+	let mut total = 0;
+	'copy: while total < count {
+		let l = min(memory::PAGE_SIZE, count - total);
+		let n = {
+			let mut input = input_mutex.lock();
+			let prev_off = input.get_offset();
+			input.set_offset(start_off + total as u64);
+			let res = input.read(0, &mut buf[..l]);
+			input.set_offset(prev_off);
+			match res {
+				Ok((n, _)) => n as usize,
+				Err(e) if total == 0 => return Err(e),
+				Err(_) => break,
+			}
+		};
+		if n == 0 {
+			break;
+		}
+
+		let mut written = 0;
+		while written < n {
+			match write_chunk(&proc_mutex, &output_mutex, &buf[written..n], nonblock) {
+				Ok(len) => written += len,
+				Err(e) if total + written == 0 => return Err(e),
+				Err(_) => {
+					total += written;
+					break 'copy;
+				}
+			}
+		}
+		total += written;
+		if n < l {
+			break;
+		}
+	}
+
+	// Only the bytes actually written count as consumed from the input
+	let end_off = start_off + total as u64;
+	if off.is_some() {
+		let mut mem_space_guard = mem_space.lock();
+		let off = offset
+			.get_mut(&mut mem_space_guard)?
+			.ok_or_else(|| errno!(EFAULT))?;
+		*off = T::try_from(end_off).map_err(|_| errno!(EOVERFLOW))?;
+	} else {
+		input_mutex.lock().set_offset(end_off);
+	}
+	Ok(total as _)
+}
+
+#[syscall(0x0bb)]
+pub fn sendfile(
+	out_fd: c_int,
+	in_fd: c_int,
+	offset: SyscallPtr<i32>,
+	count: usize,
+) -> Result<i32, Errno> {
+	do_sendfile(out_fd, in_fd, offset, count, i32::MAX as _)
+}
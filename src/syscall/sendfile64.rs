@@ -0,0 +1,16 @@
+//! The `sendfile64` system call is the same as `sendfile`, except it takes a 64 bits offset.
+
+use crate::errno::Errno;
+use crate::process::mem_space::ptr::SyscallPtr;
+use core::ffi::c_int;
+use macros::syscall;
+
+#[syscall(0x0ef)]
+pub fn sendfile64(
+	out_fd: c_int,
+	in_fd: c_int,
+	offset: SyscallPtr<i64>,
+	count: usize,
+) -> Result<i32, Errno> {
+	super::sendfile::do_sendfile(out_fd, in_fd, offset, count, i64::MAX as _)
+}
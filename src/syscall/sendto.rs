@@ -11,7 +11,7 @@ use macros::syscall;
 
 // TODO implement flags
 
-#[syscall]
+#[syscall(0x171)]
 pub fn sendto(
 	sockfd: c_int,
 	buf: SyscallSlice<u8>,
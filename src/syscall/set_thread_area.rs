@@ -35,7 +35,7 @@ pub fn get_entry(
 	let end_entry = (TLS_BEGIN_INDEX + process::TLS_ENTRIES_COUNT) as i32;
 
 	// Checking the entry number is in bound
-	if entry_number != -1 && entry_number < TLS_BEGIN_INDEX as i32 || entry_number > end_entry {
+	if entry_number != -1 && !(TLS_BEGIN_INDEX as i32..end_entry).contains(&entry_number) {
 		return Err(errno!(EINVAL));
 	}
 
@@ -45,14 +45,54 @@ pub fn get_entry(
 			// Allocating an entry
 			get_free_entry(proc)?
 		} else {
-			entry_number as usize
+			entry_number as usize - TLS_BEGIN_INDEX
 		}
 	};
 
 	Ok((id, &mut proc.get_tls_entries()[id]))
 }
 
-#[syscall]
+/// Returns the ID of the TLS entry designated by the given segment selector, if any.
+pub fn selector_to_entry(selector: u32) -> Option<usize> {
+	let id = ((selector >> 3) as usize).checked_sub(TLS_BEGIN_INDEX)?;
+	(id < process::TLS_ENTRIES_COUNT).then_some(id)
+}
+
+/// Returns the segment selector for the TLS entry with the given ID.
+pub fn entry_to_selector(id: usize) -> u32 {
+	gdt::make_segment_selector((gdt::TLS_OFFSET + id * size_of::<gdt::Entry>()) as _, 3) as _
+}
+
+/// Sets the TLS entry described by `info` for the given process.
+///
+/// If the entry number is `-1` and `can_allocate` is `true`, a free entry is allocated and its
+/// number is written back to `info`.
+///
+/// The function returns the ID of the entry. The GDT is not updated.
+pub fn do_set_thread_area(
+	proc: &mut Process,
+	info: &mut UserDesc,
+	can_allocate: bool,
+) -> Result<usize, Errno> {
+	let entry_number = info.get_entry_number();
+	if entry_number == -1 && !can_allocate {
+		return Err(errno!(EINVAL));
+	}
+
+	// Get the entry with its id
+	let (id, entry) = get_entry(proc, entry_number)?;
+	// Update the entry
+	*entry = info.to_descriptor();
+
+	// If the entry is allocated, tell the userspace its ID
+	if entry_number == -1 {
+		info.set_entry_number((TLS_BEGIN_INDEX + id) as _);
+	}
+
+	Ok(id)
+}
+
+#[syscall(0x0f3)]
 pub fn set_thread_area(u_info: SyscallPtr<UserDesc>) -> Result<i32, Errno> {
 	let proc_mutex = Process::current_assert();
 	let mut proc = proc_mutex.lock();
@@ -65,19 +105,9 @@ pub fn set_thread_area(u_info: SyscallPtr<UserDesc>) -> Result<i32, Errno> {
 		.get_mut(&mut mem_space_guard)?
 		.ok_or(errno!(EFAULT))?;
 
-	// Get the entry with its id
-	let (id, entry) = get_entry(&mut proc, info.get_entry_number())?;
-
-	// Update the entry
-	*entry = info.to_descriptor();
+	let id = do_set_thread_area(&mut proc, info, true)?;
 	proc.update_tls(id);
 	gdt::flush();
 
-	// If the entry is allocated, tell the userspace its ID
-	let entry_number = info.get_entry_number();
-	if entry_number == -1 {
-		info.set_entry_number((TLS_BEGIN_INDEX + id) as _);
-	}
-
 	Ok(0)
 }
@@ -8,7 +8,7 @@ use core::ffi::c_int;
 use core::ptr::NonNull;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x102)]
 pub fn set_tid_address(tidptr: SyscallPtr<c_int>) -> Result<i32, Errno> {
 	let proc_mutex = Process::current_assert();
 	let mut proc = proc_mutex.lock();
@@ -0,0 +1,10 @@
+//! The `setdomainname` syscall sets the NIS domain name of the system.
+
+use crate::errno::Errno;
+use crate::process::mem_space::ptr::SyscallSlice;
+use macros::syscall;
+
+#[syscall(0x079)]
+pub fn setdomainname(name: SyscallSlice<u8>, len: usize) -> Result<i32, Errno> {
+	super::sethostname::do_set_uts_name(name, len, |uts| &mut uts.domainname)
+}
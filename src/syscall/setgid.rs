@@ -5,7 +5,7 @@ use crate::file::perm::Gid;
 use crate::process::Process;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x02e)]
 pub fn setgid(gid: Gid) -> Result<i32, Errno> {
 	let proc_mutex = Process::current_assert();
 	let mut proc = proc_mutex.lock();
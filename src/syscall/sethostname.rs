@@ -1,14 +1,26 @@
 //! The `sethostname` syscall sets the hostname of the system.
 
+use crate::errno::EResult;
 use crate::errno::Errno;
 use crate::limits;
 use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::uts::UtsNamespace;
 use crate::process::Process;
+use crate::util::container::vec::Vec;
 use macros::syscall;
 
-#[syscall]
-pub fn sethostname(name: SyscallSlice<u8>, len: usize) -> Result<i32, Errno> {
-	// Check the size of the hostname is in bounds
+/// Sets a name of the UTS namespace of the current process.
+///
+/// Arguments:
+/// - `name` is the new name.
+/// - `len` is the length of the new name.
+/// - `field` returns the name to set in the namespace.
+pub fn do_set_uts_name<F: FnOnce(&mut UtsNamespace) -> &mut Vec<u8>>(
+	name: SyscallSlice<u8>,
+	len: usize,
+	field: F,
+) -> EResult<i32> {
+	// Check the size of the name is in bounds
 	if len > limits::HOST_NAME_MAX {
 		return Err(errno!(EINVAL));
 	}
@@ -21,13 +33,20 @@ pub fn sethostname(name: SyscallSlice<u8>, len: usize) -> Result<i32, Errno> {
 		return Err(errno!(EPERM));
 	}
 
-	let mem_space = proc.get_mem_space().unwrap();
-	let mem_space_guard = mem_space.lock();
-	let name_slice = name.get(&mem_space_guard, len)?.ok_or(errno!(EFAULT))?;
+	let new_name = {
+		let mem_space = proc.get_mem_space().unwrap();
+		let mem_space_guard = mem_space.lock();
+		let name_slice = name.get(&mem_space_guard, len)?.ok_or(errno!(EFAULT))?;
+		Vec::from_slice(name_slice)?
+	};
 
-	let mut hostname = crate::HOSTNAME.lock();
-	hostname.resize(len)?;
-	hostname.as_mut_slice().copy_from_slice(name_slice);
+	let mut uts = proc.get_uts().lock();
+	*field(&mut *uts) = new_name;
 
 	Ok(0)
 }
+
+#[syscall(0x04a)]
+pub fn sethostname(name: SyscallSlice<u8>, len: usize) -> Result<i32, Errno> {
+	do_set_uts_name(name, len, |uts| &mut uts.hostname)
+}
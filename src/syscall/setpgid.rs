@@ -7,7 +7,7 @@ use crate::process::pid::Pid;
 use crate::process::Process;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x039)]
 pub fn setpgid(pid: Pid, pgid: Pid) -> Result<i32, Errno> {
 	let mut pid = pid;
 	let mut pgid = pgid;
@@ -5,11 +5,11 @@ use crate::file::perm::Uid;
 use crate::process::Process;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x0d5)]
 pub fn setuid32(uid: Uid) -> Result<i32, Errno> {
 	let proc_mutex = Process::current_assert();
 	let mut proc = proc_mutex.lock();
 
-	proc.access_profile.set_uid(uid)?;
+	proc.set_uid(uid)?;
 	Ok(0)
 }
@@ -0,0 +1,86 @@
+//! The `setxattr` system call sets the value of an extended attribute of a file.
+
+use super::util;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::vfs;
+use crate::file::xattr::XATTR_SIZE_MAX;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::mem_space::ptr::SyscallString;
+use crate::process::Process;
+use crate::util::container::vec::Vec;
+use core::ffi::c_int;
+use macros::syscall;
+
+/// Performs the `*setxattr` system calls.
+///
+/// Arguments:
+/// - `pathname` is the path to the file. If `None`, the file descriptor `fd` is used instead.
+/// - `fd` is the file descriptor of the file.
+/// - `follow_links` tells whether symbolic links are followed.
+/// - `name` is the name of the attribute.
+/// - `value` is the new value of the attribute, of `size` bytes.
+/// - `flags` are `XATTR_*` flags restricting whether the attribute may be created or replaced.
+pub fn do_setxattr(
+	pathname: Option<SyscallString>,
+	fd: c_int,
+	follow_links: bool,
+	name: SyscallString,
+	value: SyscallSlice<u8>,
+	size: usize,
+	flags: c_int,
+) -> EResult<i32> {
+	if size > XATTR_SIZE_MAX {
+		return Err(errno!(E2BIG));
+	}
+
+	let (file_mutex, name, value, ap) = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+
+		let ap = proc.access_profile;
+
+		let mem_space = proc.get_mem_space().unwrap().clone();
+		let mem_space_guard = mem_space.lock();
+
+		let name = name.get(&mem_space_guard)?.ok_or_else(|| errno!(EFAULT))?;
+		let name = Vec::from_slice(name)?;
+		let value = if size > 0 {
+			value
+				.get(&mem_space_guard, size)?
+				.ok_or_else(|| errno!(EFAULT))?
+		} else {
+			&[]
+		};
+		let value = Vec::from_slice(value)?;
+		let pathname = pathname
+			.map(|pathname| {
+				let pathname = pathname
+					.get(&mem_space_guard)?
+					.ok_or_else(|| errno!(EFAULT))?;
+				Ok::<_, Errno>(Vec::from_slice(pathname)?)
+			})
+			.transpose()?;
+		// Unlock to avoid deadlock with procfs
+		drop(mem_space_guard);
+
+		let file_mutex =
+			util::get_file_by_path_or_fd(proc, pathname.as_deref(), fd, follow_links)?;
+		(file_mutex, name, value, ap)
+	};
+
+	let file = file_mutex.lock();
+	vfs::set_xattr(&file, &name, &value, flags, &ap)?;
+	Ok(0)
+}
+
+#[syscall(0x0e2)]
+pub fn setxattr(
+	pathname: SyscallString,
+	name: SyscallString,
+	value: SyscallSlice<u8>,
+	size: usize,
+	flags: c_int,
+) -> EResult<i32> {
+	do_setxattr(Some(pathname), -1, true, name, value, size, flags)
+}
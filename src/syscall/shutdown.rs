@@ -15,7 +15,7 @@ const SHUT_WR: c_int = 1;
 /// Both sides are shutdown.
 const SHUT_RDWR: c_int = 2;
 
-#[syscall]
+#[syscall(0x175)]
 pub fn shutdown(sockfd: c_int, how: c_int) -> Result<i32, Errno> {
 	if sockfd < 0 {
 		return Err(errno!(EBADF));
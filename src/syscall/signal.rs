@@ -15,7 +15,7 @@ use core::mem::transmute;
 use core::ptr::null;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x030)]
 pub fn signal(signum: c_int, handler: *const c_void) -> Result<i32, Errno> {
 	if signum < 0 {
 		return Err(errno!(EINVAL));
@@ -30,7 +30,6 @@ pub fn signal(signum: c_int, handler: *const c_void) -> Result<i32, Errno> {
 
 			SignalHandler::Handler(SigAction {
 				sa_handler: Some(handler_fn),
-				sa_sigaction: None,
 				sa_mask: 0,
 				sa_flags: 0,
 				sa_restorer: None,
@@ -8,7 +8,7 @@ use crate::errno::Errno;
 use crate::process::Process;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x077)]
 pub fn sigreturn() -> Result<i32, Errno> {
 	cli!();
 
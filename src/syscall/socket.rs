@@ -4,24 +4,28 @@ use crate::errno;
 use crate::errno::Errno;
 use crate::file::buffer;
 use crate::file::buffer::socket::Socket;
+use crate::file::fd::FD_CLOEXEC;
 use crate::file::open_file;
 use crate::file::open_file::OpenFile;
 use crate::file::vfs;
 use crate::net::SocketDesc;
 use crate::net::SocketDomain;
 use crate::net::SocketType;
+use crate::net::SOCK_CLOEXEC;
+use crate::net::SOCK_NONBLOCK;
 use crate::process::Process;
 use core::ffi::c_int;
 use macros::syscall;
 
 /// The implementation of the `socket` syscall.
-#[syscall]
+#[syscall(0x167)]
 pub fn socket(domain: c_int, r#type: c_int, protocol: c_int) -> Result<i32, Errno> {
 	let proc_mutex = Process::current_assert();
 	let proc = proc_mutex.lock();
 
 	let sock_domain = SocketDomain::try_from(domain as u32)?;
-	let sock_type = SocketType::try_from(r#type as u32)?;
+	let flags = r#type & (SOCK_NONBLOCK | SOCK_CLOEXEC);
+	let sock_type = SocketType::try_from((r#type & !flags) as u32)?;
 	if !proc.access_profile.can_use_sock_domain(&sock_domain)
 		|| !proc.access_profile.can_use_sock_type(&sock_type)
 	{
@@ -39,11 +43,20 @@ pub fn socket(domain: c_int, r#type: c_int, protocol: c_int) -> Result<i32, Errn
 	let loc = buffer::register(None, sock)?;
 	let file = vfs::get_file_by_location(&loc)?;
 
-	let open_file = OpenFile::new(file, open_file::O_RDWR)?;
+	let mut open_flags = open_file::O_RDWR;
+	if flags & SOCK_NONBLOCK != 0 {
+		open_flags |= open_file::O_NONBLOCK;
+	}
+	let open_file = OpenFile::new(file, open_flags)?;
 
+	let fd_flags = if flags & SOCK_CLOEXEC != 0 {
+		FD_CLOEXEC
+	} else {
+		0
+	};
 	let fds_mutex = proc.get_fds().unwrap();
 	let mut fds = fds_mutex.lock();
-	let sock_fd = fds.create_fd(0, open_file)?;
+	let sock_fd = fds.create_fd(fd_flags, open_file)?;
 
 	Ok(sock_fd.get_id() as _)
 }
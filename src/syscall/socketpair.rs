@@ -5,6 +5,7 @@ use crate::errno;
 use crate::errno::Errno;
 use crate::file::buffer;
 use crate::file::buffer::socket::Socket;
+use crate::file::buffer::socket::UCred;
 use crate::file::open_file;
 use crate::file::open_file::OpenFile;
 use crate::file::vfs;
@@ -16,7 +17,7 @@ use crate::process::Process;
 use core::ffi::c_int;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x168)]
 pub fn socketpair(
 	domain: c_int,
 	r#type: c_int,
@@ -44,6 +45,13 @@ pub fn socketpair(
 	};
 
 	let sock = Socket::new(desc)?;
+	// Both ends belong to the creating process
+	{
+		let mut sock = sock.lock();
+		let cred = UCred::from_process(&proc);
+		sock.set_cred(cred);
+		sock.set_peer_cred(cred);
+	}
 	let loc = buffer::register(None, sock)?;
 	let file = vfs::get_file_by_location(&loc)?;
 
@@ -1,32 +1,246 @@
-//! The `splice` system call splice data from one pipe to another.
+//! The `splice` system call moves data between a pipe and a file descriptor without copying it
+//! through userspace.
 
+use crate::errno;
+use crate::errno::EResult;
 use crate::errno::Errno;
+use crate::file::buffer;
+use crate::file::buffer::pipe::PipeBuffer;
+use crate::file::buffer::Buffer;
+use crate::file::open_file::OpenFile;
+use crate::file::open_file::O_NONBLOCK;
 use crate::file::FileType;
-use crate::memory::malloc;
+use crate::memory;
 use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::scheduler;
+use crate::process::signal::Signal;
 use crate::process::Process;
+use crate::util::io;
 use crate::util::io::IO;
+use crate::util::lock::IntMutex;
+use crate::util::lock::Mutex;
+use crate::util::ptr::arc::Arc;
+use core::any::Any;
 use core::cmp::min;
 use core::ffi::c_int;
 use core::ffi::c_uint;
 use core::num::NonZeroUsize;
+use core::ptr;
 use macros::syscall;
 
-#[syscall]
+/// Hint to move pages instead of copying them.
+const SPLICE_F_MOVE: c_uint = 1;
+/// Do not block on I/O.
+pub const SPLICE_F_NONBLOCK: c_uint = 2;
+/// Hint that more data will be coming in a subsequent splice.
+const SPLICE_F_MORE: c_uint = 4;
+/// Hint that the pages given to `vmsplice` are not to be modified anymore by the caller.
+const SPLICE_F_GIFT: c_uint = 8;
+/// The mask of all valid flags.
+pub const SPLICE_F_ALL: c_uint = SPLICE_F_MOVE | SPLICE_F_NONBLOCK | SPLICE_F_MORE | SPLICE_F_GIFT;
+
+/// Returns the buffer of the pipe opened by `file`.
+pub fn get_pipe(file: &Mutex<OpenFile>) -> EResult<Arc<Mutex<dyn Buffer>>> {
+	let loc = file.lock().get_location().clone();
+	Ok(buffer::get_or_default::<PipeBuffer>(&loc)?)
+}
+
+/// Returns the given buffer as a pipe.
+///
+/// If the buffer is not a pipe, the function panics.
+pub fn as_pipe(buff: &mut dyn Buffer) -> &mut PipeBuffer {
+	(buff as &mut dyn Any).downcast_mut::<PipeBuffer>().unwrap()
+}
+
+/// Waits until the pipe `pipe_mutex` has data to be read.
+///
+/// Arguments:
+/// - `proc` is the current process.
+/// - `nonblock` tells whether the function returns [`errno::EAGAIN`] instead of blocking when the
+/// pipe is empty.
+///
+/// If the pipe is empty and has no writing end, the function returns `false`.
+pub fn wait_pipe_input(
+	proc: &IntMutex<Process>,
+	pipe_mutex: &Mutex<dyn Buffer>,
+	nonblock: bool,
+) -> EResult<bool> {
+	loop {
+		{
+			let mut pipe_guard = pipe_mutex.lock();
+			let pipe = as_pipe(&mut *pipe_guard);
+			if pipe.get_data_len() > 0 {
+				return Ok(true);
+			}
+			if !pipe.has_writers() {
+				return Ok(false);
+			}
+			if nonblock {
+				return Err(errno!(EAGAIN));
+			}
+			super::util::signal_check()?;
+			pipe.add_waiting_process(&mut proc.lock(), io::POLLIN | io::POLLHUP)?;
+		}
+		scheduler::end_tick();
+	}
+}
+
+/// Moves up to `len` bytes from `input` to the pipe `output`.
+///
+/// The data is read into whole pages which are inserted in the pipe's ring, instead of being
+/// copied into its segments.
+///
+/// Arguments:
+/// - `proc` is the current process.
+/// - `off_in` is the offset to read from in `input`. If `None`, the current offset is used.
+/// - `nonblock` tells whether the function returns [`errno::EAGAIN`] instead of blocking when the
+/// pipe is full.
+fn splice_to_pipe(
+	proc: &IntMutex<Process>,
+	input: &Mutex<OpenFile>,
+	off_in: Option<u64>,
+	output: &Mutex<OpenFile>,
+	len: usize,
+	nonblock: bool,
+) -> EResult<usize> {
+	// Splicing a pipe to itself is not allowed
+	if ptr::eq(input, output) {
+		return Err(errno!(EINVAL));
+	}
+	let pipe_loc = output.lock().get_location().clone();
+	if *input.lock().get_location() == pipe_loc {
+		return Err(errno!(EINVAL));
+	}
+	let pipe_mutex = buffer::get_or_default::<PipeBuffer>(&pipe_loc)?;
+
+	let mut total = 0;
+	while total < len {
+		let mut input = input.lock();
+		let mut pipe_guard = pipe_mutex.lock();
+		let pipe = as_pipe(&mut *pipe_guard);
+		let free = match pipe.can_insert_page() {
+			Ok(free) => free,
+			Err(e) => {
+				if e.as_int() == errno::EPIPE {
+					proc.lock().kill(&Signal::SIGPIPE, false);
+				}
+				return Err(e);
+			}
+		};
+		if !free {
+			if total > 0 {
+				break;
+			}
+			if nonblock {
+				return Err(errno!(EAGAIN));
+			}
+			super::util::signal_check()?;
+			pipe.add_waiting_process(&mut proc.lock(), io::POLLOUT | io::POLLERR)?;
+			drop(pipe_guard);
+			drop(input);
+			scheduler::end_tick();
+			continue;
+		}
+
+		// The pipe stays locked so that the slot remains free until the page is inserted
+		let mut page = crate::vec![0; memory::PAGE_SIZE]?;
+		let l = min(memory::PAGE_SIZE, len - total);
+		let prev_off = input.get_offset();
+		if let Some(off) = off_in {
+			input.set_offset(off + total as u64);
+		}
+		let (n, _) = input.read(0, &mut page[..l])?;
+		if off_in.is_some() {
+			input.set_offset(prev_off);
+		}
+		let n = n as usize;
+		if n == 0 {
+			break;
+		}
+		pipe.insert_page(page, n)?;
+		total += n;
+		if n < l {
+			break;
+		}
+	}
+	Ok(total)
+}
+
+/// Moves up to `len` bytes from the pipe `input` to `output`.
+///
+/// Data is removed from the pipe only once it has been written, so that nothing is lost if
+/// `output` accepts less than what has been read.
+///
+/// Arguments:
+/// - `proc` is the current process.
+/// - `off_out` is the offset to write at in `output`. If `None`, the current offset is used.
+/// - `nonblock` tells whether the function returns [`errno::EAGAIN`] instead of blocking when the
+/// pipe is empty.
+fn splice_from_pipe(
+	proc: &IntMutex<Process>,
+	input: &Mutex<OpenFile>,
+	output: &Mutex<OpenFile>,
+	off_out: Option<u64>,
+	len: usize,
+	nonblock: bool,
+) -> EResult<usize> {
+	let pipe_mutex = get_pipe(input)?;
+	if !wait_pipe_input(proc, &pipe_mutex, nonblock)? {
+		return Ok(0);
+	}
+
+	let mut buf = crate::vec![0; memory::PAGE_SIZE]?;
+	let mut total = 0;
+	while total < len {
+		let mut output = output.lock();
+		let mut pipe_guard = pipe_mutex.lock();
+		let pipe = as_pipe(&mut *pipe_guard);
+
+		let l = min(memory::PAGE_SIZE, len - total);
+		let n = pipe.peek(0, &mut buf[..l]);
+		if n == 0 {
+			break;
+		}
+		let prev_off = output.get_offset();
+		if let Some(off) = off_out {
+			output.set_offset(off + total as u64);
+		}
+		let res = output.write(0, &buf[..n]);
+		if off_out.is_some() {
+			output.set_offset(prev_off);
+		}
+		let written = match res {
+			Ok(written) => written as usize,
+			Err(e) if total == 0 => return Err(e),
+			Err(_) => break,
+		};
+		pipe.consume(written);
+		total += written;
+		if written < n {
+			break;
+		}
+	}
+	Ok(total)
+}
+
+#[syscall(0x139)]
 pub fn splice(
 	fd_in: c_int,
 	off_in: SyscallPtr<u64>,
 	fd_out: c_int,
 	off_out: SyscallPtr<u64>,
 	len: usize,
-	_flags: c_uint,
+	flags: c_uint,
 ) -> Result<i32, Errno> {
 	if fd_in < 0 || fd_out < 0 {
 		return Err(errno!(EBADF));
 	}
+	if flags & !SPLICE_F_ALL != 0 {
+		return Err(errno!(EINVAL));
+	}
 
+	let proc_mutex = Process::current_assert();
 	let (input_mutex, off_in, output_mutex, off_out) = {
-		let proc_mutex = Process::current_assert();
 		let proc = proc_mutex.lock();
 
 		let fds_mutex = proc.get_fds().unwrap();
@@ -52,9 +266,14 @@ pub fn splice(
 		(input, off_in, output, off_out)
 	};
 
-	{
-		let input_type = input_mutex.lock().get_file().lock().get_type();
-		let output_type = output_mutex.lock().get_file().lock().get_type();
+	let (in_is_pipe, out_is_pipe) = {
+		let input = input_mutex.lock();
+		let output = output_mutex.lock();
+		if !input.can_read() || !output.can_write() {
+			return Err(errno!(EBADF));
+		}
+		let input_type = input.get_file().lock().get_type();
+		let output_type = output.get_file().lock().get_type();
 
 		let in_is_pipe = matches!(input_type, FileType::Fifo);
 		let out_is_pipe = matches!(output_type, FileType::Fifo);
@@ -68,48 +287,41 @@ pub fn splice(
 		if out_is_pipe && off_out.is_some() {
 			return Err(errno!(ESPIPE));
 		}
-	}
+		(in_is_pipe, out_is_pipe)
+	};
 
 	let len = min(len, i32::MAX as usize);
 	let Some(len) = NonZeroUsize::new(len) else {
 		return Ok(0);
 	};
 
-	// TODO implement flags
-
-	let mut buff = unsafe {
-		// Safe because initialized memory is never read
-		malloc::Alloc::<u8>::new(len)
-	}?;
-
-	let len = {
-		let mut input = input_mutex.lock();
-		let prev_off = input.get_offset();
-		let (len, _) = input.read(off_in.unwrap_or(0), buff.as_slice_mut())?;
-		if off_in.is_some() {
-			input.set_offset(prev_off);
-		}
-		len
+	let nonblock = |file: &Mutex<OpenFile>| {
+		flags & SPLICE_F_NONBLOCK != 0 || file.lock().get_flags() & O_NONBLOCK != 0
 	};
-
-	let in_slice = &buff.as_slice()[..(len as usize)];
-	let mut i = 0;
-
-	while i < len {
-		// TODO Check for signal (and handle syscall restart correctly with offsets)
-
-		let mut output = output_mutex.lock();
-
-		let prev_off = output.get_offset();
-
-		let l = output.write(off_out.unwrap_or(0), in_slice)?;
-
-		if off_out.is_some() {
-			output.set_offset(prev_off);
+	let len = if out_is_pipe {
+		if in_is_pipe {
+			let pipe_mutex = get_pipe(&input_mutex)?;
+			if !wait_pipe_input(&proc_mutex, &pipe_mutex, nonblock(&input_mutex))? {
+				return Ok(0);
+			}
 		}
-
-		i += l;
-	}
-
+		splice_to_pipe(
+			&proc_mutex,
+			&input_mutex,
+			off_in,
+			&output_mutex,
+			len.get(),
+			nonblock(&output_mutex),
+		)?
+	} else {
+		splice_from_pipe(
+			&proc_mutex,
+			&input_mutex,
+			&output_mutex,
+			off_out,
+			len.get(),
+			nonblock(&input_mutex),
+		)?
+	};
 	Ok(len as _)
 }
@@ -1,8 +1,10 @@
 //! The `statfs` system call returns information about a mounted file system.
 
 use crate::errno;
+use crate::errno::EResult;
 use crate::errno::Errno;
 use crate::file::fs::Statfs;
+use crate::file::fs::Statfs32;
 use crate::file::path::Path;
 use crate::file::vfs;
 use crate::process::mem_space::ptr::SyscallPtr;
@@ -10,8 +12,8 @@ use crate::process::mem_space::ptr::SyscallString;
 use crate::process::Process;
 use macros::syscall;
 
-#[syscall]
-pub fn statfs(path: SyscallString, buf: SyscallPtr<Statfs>) -> Result<i32, Errno> {
+/// Returns the statistics of the filesystem on which the file at `path` is located.
+pub fn do_statfs(path: SyscallString) -> EResult<Statfs> {
 	let (path, ap) = {
 		let proc_mutex = Process::current_assert();
 		let proc = proc_mutex.lock();
@@ -28,31 +30,27 @@ pub fn statfs(path: SyscallString, buf: SyscallPtr<Statfs>) -> Result<i32, Errno
 
 	let file_mutex = vfs::get_file_from_path(&path, &ap, true)?;
 	let file = file_mutex.lock();
+	file.get_statfs()
+}
 
-	let mountpoint_mutex = file.get_location().get_mountpoint().unwrap();
-	let mountpoint = mountpoint_mutex.lock();
-
-	let io_mutex = mountpoint.get_source().get_io()?;
-	let mut io = io_mutex.lock();
-
-	let fs_mutex = mountpoint.get_filesystem();
-	let fs = fs_mutex.lock();
+/// Writes the statistics `stat` to the userspace buffer `buf`.
+pub fn write_statfs<T>(buf: SyscallPtr<T>, stat: T) -> EResult<()> {
+	let proc_mutex = Process::current_assert();
+	let proc = proc_mutex.lock();
 
-	let stat = fs.get_stat(&mut *io)?;
+	let mem_space = proc.get_mem_space().unwrap();
+	let mut mem_space_guard = mem_space.lock();
 
-	// Writing the statfs structure to userspace
-	{
-		let proc_mutex = Process::current_assert();
-		let proc = proc_mutex.lock();
-
-		let mem_space = proc.get_mem_space().unwrap();
-		let mut mem_space_guard = mem_space.lock();
-
-		let buf = buf
-			.get_mut(&mut mem_space_guard)?
-			.ok_or_else(|| errno!(EFAULT))?;
-		*buf = stat;
-	}
+	let buf = buf
+		.get_mut(&mut mem_space_guard)?
+		.ok_or_else(|| errno!(EFAULT))?;
+	*buf = stat;
+	Ok(())
+}
 
+#[syscall(0x063)]
+pub fn statfs(path: SyscallString, buf: SyscallPtr<Statfs32>) -> Result<i32, Errno> {
+	let stat = do_statfs(path)?;
+	write_statfs(buf, Statfs32::try_from(&stat)?)?;
 	Ok(0)
 }
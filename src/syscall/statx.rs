@@ -1,9 +1,8 @@
 //! The statx system call returns the extended status of a file.
 
 use super::util;
+use crate::device::id;
 use crate::errno::Errno;
-use crate::file::mountpoint::MountSource;
-use crate::file::FileContent;
 use crate::process::mem_space::ptr::SyscallPtr;
 use crate::process::mem_space::ptr::SyscallString;
 use crate::process::Process;
@@ -12,6 +11,24 @@ use core::ffi::c_int;
 use core::ffi::c_uint;
 use macros::syscall;
 
+/// Flag: Do not follow the symbolic link if the path refers to one.
+const AT_SYMLINK_NOFOLLOW: c_int = super::access::AT_SYMLINK_NOFOLLOW;
+/// Flag: Do not trigger automounts.
+const AT_NO_AUTOMOUNT: c_int = 0x800;
+/// Flag: If the path is empty, the file referred to by `dirfd` is used.
+const AT_EMPTY_PATH: c_int = super::access::AT_EMPTY_PATH;
+/// Mask of the flags specifying how to synchronize the attributes with a remote filesystem.
+const AT_STATX_SYNC_TYPE: c_int = 0x6000;
+
+/// Mask of the fields of the basic `stat` structure.
+const STATX_BASIC_STATS: u32 = 0x7ff;
+/// Field: Creation timestamp.
+const STATX_BTIME: u32 = 0x800;
+/// Field: Mount ID.
+const STATX_MNT_ID: u32 = 0x1000;
+/// Reserved bit, which must not be set in the mask.
+const STATX_RESERVED: u32 = 0x80000000;
+
 /// Structure representing a timestamp with the statx syscall.
 #[repr(C)]
 #[derive(Debug)]
@@ -80,19 +97,27 @@ struct Statx {
 	__padding1: [u64; 13],
 }
 
-#[syscall]
+#[syscall(0x17f)]
 pub fn statx(
 	dirfd: c_int,
 	pathname: SyscallString,
 	flags: c_int,
-	_mask: c_uint,
+	mask: c_uint,
 	statxbuff: SyscallPtr<Statx>,
 ) -> Result<i32, Errno> {
-	if pathname.is_null() || statxbuff.is_null() {
+	if flags & !(AT_SYMLINK_NOFOLLOW | AT_NO_AUTOMOUNT | AT_EMPTY_PATH | AT_STATX_SYNC_TYPE) != 0
+		|| flags & AT_STATX_SYNC_TYPE == AT_STATX_SYNC_TYPE
+		|| mask & STATX_RESERVED != 0
+	{
 		return Err(errno!(EINVAL));
 	}
-
-	// TODO Implement all flags
+	if statxbuff.is_null() {
+		return Err(errno!(EFAULT));
+	}
+	// A null path is allowed with `AT_EMPTY_PATH`
+	if pathname.is_null() && flags & AT_EMPTY_PATH == 0 {
+		return Err(errno!(EFAULT));
+	}
 
 	// Getting the file
 	let file_mutex = {
@@ -102,57 +127,34 @@ pub fn statx(
 		let mem_space = proc.get_mem_space().unwrap().clone();
 		let mem_space_guard = mem_space.lock();
 
-		let pathname = pathname
-			.get(&mem_space_guard)?
-			.ok_or_else(|| errno!(EFAULT))?;
+		let pathname = if pathname.is_null() {
+			b""
+		} else {
+			pathname
+				.get(&mem_space_guard)?
+				.ok_or_else(|| errno!(EFAULT))?
+		};
 		util::get_file_at(proc, dirfd, pathname, true, flags)?
 	};
 	let file = file_mutex.lock();
 
-	// TODO Use mask?
-
-	// If the file is a device, get the major and minor numbers
-	let (stx_rdev_major, stx_rdev_minor) = match file.get_content() {
-		FileContent::BlockDevice {
-			major,
-			minor,
-		}
-		| FileContent::CharDevice {
-			major,
-			minor,
-		} => (*major, *minor),
-		_ => (0, 0),
-	};
-
-	// Getting the major and minor numbers of the device of the file's filesystem
-	let (stx_dev_major, stx_dev_minor) = {
-		if let Some(mountpoint_mutex) = file.get_location().get_mountpoint() {
-			// TODO Clean: This is a quick fix to avoid a deadlock because vfs is also using
-			// the mountpoint and locking vfs requires disabling interrupts
-			crate::idt::wrap_disable_interrupts(|| {
-				let mountpoint = mountpoint_mutex.lock();
-
-				match mountpoint.get_source() {
-					MountSource::Device {
-						major,
-						minor,
-						..
-					} => (*major, *minor),
-
-					_ => (0, 0),
-				}
-			})
-		} else {
-			(0, 0)
-		}
-	};
+	// Fields that are not requested may be filled anyway, except the creation timestamp which
+	// is not available on every filesystem
+	let btime = file.btime.filter(|_| mask & STATX_BTIME != 0);
+	let mut stx_mask = STATX_BASIC_STATS | STATX_MNT_ID;
+	if btime.is_some() {
+		stx_mask |= STATX_BTIME;
+	}
 
+	let rdev = file.get_rdev();
+	let dev = file.get_dev();
+	let mnt_id = file.get_location().get_mountpoint_id().unwrap_or(0);
 	let inode = file.get_location().get_inode();
 
 	// Filling the structure
 	let statx_val = Statx {
-		stx_mask: !0,      // TODO
-		stx_blksize: 512,  // TODO
+		stx_mask,
+		stx_blksize: file.get_block_size()? as _,
 		stx_attributes: 0, // TODO
 		stx_nlink: file.get_hard_links_count() as _,
 		stx_uid: file.get_uid() as _,
@@ -172,7 +174,7 @@ pub fn statx(
 			__reserved: 0,
 		},
 		stx_btime: StatxTimestamp {
-			tv_sec: 0,  // TODO
+			tv_sec: btime.unwrap_or(0) as _,
 			tv_nsec: 0, // TODO
 			__reserved: 0,
 		},
@@ -187,12 +189,12 @@ pub fn statx(
 			__reserved: 0,
 		},
 
-		stx_rdev_major,
-		stx_rdev_minor,
-		stx_dev_major,
-		stx_dev_minor,
+		stx_rdev_major: id::major(rdev),
+		stx_rdev_minor: id::minor(rdev),
+		stx_dev_major: id::major(dev),
+		stx_dev_minor: id::minor(dev),
 
-		stx_mnt_id: 0, // TODO
+		stx_mnt_id: mnt_id as _,
 
 		__padding1: [0; 13],
 	};
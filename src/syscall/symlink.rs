@@ -10,7 +10,7 @@ use crate::process::Process;
 use crate::util::container::string::String;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x053)]
 pub fn symlink(target: SyscallString, linkpath: SyscallString) -> Result<i32, Errno> {
 	let (target, linkpath, ap) = {
 		let proc_mutex = Process::current_assert();
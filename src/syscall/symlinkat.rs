@@ -10,7 +10,7 @@ use crate::util::container::string::String;
 use core::ffi::c_int;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x130)]
 pub fn symlinkat(
 	target: SyscallString,
 	newdirfd: c_int,
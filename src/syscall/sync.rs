@@ -0,0 +1,12 @@
+//! The `sync` system call writes every pending change of every filesystem to storage.
+
+use crate::errno::Errno;
+use crate::file::mountpoint;
+use macros::syscall;
+
+#[syscall(0x024)]
+pub fn sync() -> Result<i32, Errno> {
+	// The system call cannot fail
+	let _ = mountpoint::sync_all();
+	Ok(0)
+}
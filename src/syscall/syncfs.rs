@@ -1,12 +1,13 @@
 //! The `syncfs` system call allows to synchronize the filesystem containing the
 //! file pointed by the given file descriptor.
 
+use crate::device::storage;
 use crate::errno::Errno;
 use crate::process::Process;
 use core::ffi::c_int;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x158)]
 pub fn syncfs(fd: c_int) -> Result<i32, Errno> {
 	if fd < 0 {
 		return Err(errno!(EBADF));
@@ -30,10 +31,10 @@ pub fn syncfs(fd: c_int) -> Result<i32, Errno> {
 	let file_mutex = open_file.get_file();
 	let file = file_mutex.lock();
 
-	let location = file.get_location();
-	let _mountpoint = location.get_mountpoint();
-
-	// TODO Sync all files on mountpoint
+	if let Some(mountpoint_mutex) = file.get_location().get_mountpoint() {
+		mountpoint_mutex.lock().sync()?;
+	}
+	storage::flush_all()?;
 
 	Ok(0)
 }
@@ -0,0 +1,125 @@
+//! The `tee` system call duplicates data from one pipe to another without consuming it.
+
+use super::splice::as_pipe;
+use super::splice::get_pipe;
+use super::splice::wait_pipe_input;
+use super::splice::SPLICE_F_ALL;
+use super::splice::SPLICE_F_NONBLOCK;
+use crate::errno;
+use crate::errno::Errno;
+use crate::file::buffer::Buffer;
+use crate::file::open_file::O_NONBLOCK;
+use crate::file::FileType;
+use crate::memory;
+use crate::process::scheduler;
+use crate::process::signal::Signal;
+use crate::process::Process;
+use crate::util::io;
+use crate::util::io::IO;
+use core::cmp::min;
+use core::ffi::c_int;
+use core::ffi::c_uint;
+use macros::syscall;
+
+#[syscall(0x13b)]
+pub fn tee(fd_in: c_int, fd_out: c_int, len: usize, flags: c_uint) -> Result<i32, Errno> {
+	if fd_in < 0 || fd_out < 0 {
+		return Err(errno!(EBADF));
+	}
+	if flags & !SPLICE_F_ALL != 0 {
+		return Err(errno!(EINVAL));
+	}
+
+	let proc_mutex = Process::current_assert();
+	let (input_mutex, output_mutex) = {
+		let proc = proc_mutex.lock();
+
+		let fds_mutex = proc.get_fds().unwrap();
+		let fds = fds_mutex.lock();
+
+		let input = fds
+			.get_fd(fd_in as _)
+			.ok_or_else(|| errno!(EBADF))?
+			.get_open_file()
+			.clone();
+		let output = fds
+			.get_fd(fd_out as _)
+			.ok_or_else(|| errno!(EBADF))?
+			.get_open_file()
+			.clone();
+
+		(input, output)
+	};
+
+	let (in_nonblock, out_nonblock) = {
+		let input = input_mutex.lock();
+		let output = output_mutex.lock();
+		if !input.can_read() || !output.can_write() {
+			return Err(errno!(EBADF));
+		}
+		// Both ends must be pipes, and different ones
+		let in_is_pipe = input.get_file().lock().get_type() == FileType::Fifo;
+		let out_is_pipe = output.get_file().lock().get_type() == FileType::Fifo;
+		if !in_is_pipe || !out_is_pipe || input.get_location() == output.get_location() {
+			return Err(errno!(EINVAL));
+		}
+		let nonblock = flags & SPLICE_F_NONBLOCK != 0;
+		(
+			nonblock || input.get_flags() & O_NONBLOCK != 0,
+			nonblock || output.get_flags() & O_NONBLOCK != 0,
+		)
+	};
+
+	let len = min(len, i32::MAX as usize);
+	if len == 0 {
+		return Ok(0);
+	}
+
+	let in_pipe_mutex = get_pipe(&input_mutex)?;
+	let out_pipe_mutex = get_pipe(&output_mutex)?;
+	if !wait_pipe_input(&proc_mutex, &in_pipe_mutex, in_nonblock)? {
+		return Ok(0);
+	}
+
+	// Both pipes are never locked at the same time, to avoid deadlocks with a concurrent `tee` in
+	// the opposite direction
+	let mut buf = crate::vec![0; memory::PAGE_SIZE]?;
+	let mut total = 0;
+	while total < len {
+		let l = min(memory::PAGE_SIZE, len - total);
+		let n = as_pipe(&mut *in_pipe_mutex.lock()).peek(total, &mut buf[..l]);
+		if n == 0 {
+			break;
+		}
+
+		let written = loop {
+			super::util::signal_check()?;
+			{
+				let mut out_pipe_guard = out_pipe_mutex.lock();
+				let out_pipe = as_pipe(&mut *out_pipe_guard);
+				let written = match out_pipe.write(0, &buf[..n]) {
+					Ok(written) => written as usize,
+					Err(e) => {
+						if e.as_int() == errno::EPIPE {
+							proc_mutex.lock().kill(&Signal::SIGPIPE, false);
+						}
+						return Err(e);
+					}
+				};
+				if written > 0 || total > 0 {
+					break written;
+				}
+				if out_nonblock {
+					return Err(errno!(EAGAIN));
+				}
+				out_pipe.add_waiting_process(&mut proc_mutex.lock(), io::POLLOUT | io::POLLERR)?;
+			}
+			scheduler::end_tick();
+		};
+		total += written;
+		if written < n {
+			break;
+		}
+	}
+	Ok(total as _)
+}
@@ -0,0 +1,52 @@
+//! The `tgkill` system call allows to send a signal to a specific thread of a thread group.
+
+use crate::errno;
+use crate::errno::Errno;
+use crate::process::pid::Pid;
+use crate::process::signal::Signal;
+use crate::process::Process;
+use core::ffi::c_int;
+use macros::syscall;
+
+#[syscall(0x10e)]
+pub fn tgkill(tgid: Pid, tid: Pid, sig: c_int) -> Result<i32, Errno> {
+	if sig < 0 {
+		return Err(errno!(EINVAL));
+	}
+	let signal = if sig > 0 {
+		Some(Signal::try_from(sig as u32)?)
+	} else {
+		None
+	};
+
+	let proc_mutex = Process::current_assert();
+	let mut proc = proc_mutex.lock();
+
+	// Check if the thread to kill is the current
+	if proc.tid == tid {
+		if proc.tgid != tgid {
+			return Err(errno!(ESRCH));
+		}
+		if let Some(signal) = signal {
+			proc.kill(&signal, false);
+		}
+	} else {
+		// Get the thread
+		let thread_mutex = Process::get_by_tid(tid).ok_or(errno!(ESRCH))?;
+		let mut thread = thread_mutex.lock();
+		if thread.tgid != tgid {
+			return Err(errno!(ESRCH));
+		}
+
+		// Check permissions
+		if !proc.access_profile.can_kill(&thread) {
+			return Err(errno!(EPERM));
+		}
+
+		if let Some(signal) = signal {
+			thread.kill(&signal, false);
+		}
+	}
+
+	Ok(0)
+}
@@ -11,7 +11,7 @@ use macros::syscall;
 
 // TODO Watch for timestamp overflow
 
-#[syscall]
+#[syscall(0x00d)]
 pub fn time(tloc: SyscallPtr<u32>) -> Result<i32, Errno> {
 	let proc_mutex = Process::current_assert();
 	let proc = proc_mutex.lock();
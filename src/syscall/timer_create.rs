@@ -13,7 +13,7 @@ use core::mem::transmute;
 use core::ptr::null;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x103)]
 pub fn timer_create(
 	clockid: ClockIdT,
 	sevp: SyscallPtr<SigEvent>,
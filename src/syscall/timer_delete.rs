@@ -5,7 +5,7 @@ use crate::process::Process;
 use crate::time::unit::TimerT;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x107)]
 pub fn timer_delete(timerid: TimerT) -> Result<i32, Errno> {
 	let proc_mutex = Process::current_assert();
 	let proc = proc_mutex.lock();
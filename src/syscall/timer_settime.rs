@@ -11,7 +11,7 @@ use macros::syscall;
 /// If set, the specified time is *not* relative to the timer's current counter.
 const TIMER_ABSTIME: c_int = 1;
 
-#[syscall]
+#[syscall(0x104)]
 pub fn timer_settime(
 	timerid: TimerT,
 	flags: c_int,
@@ -1,26 +1,53 @@
 //! The truncate syscall allows to truncate a file.
 
+use crate::errno;
+use crate::errno::EResult;
 use crate::errno::Errno;
 use crate::file::path::Path;
 use crate::file::vfs;
+use crate::file::FileType;
 use crate::process::mem_space::ptr::SyscallString;
 use crate::process::Process;
+use core::ffi::c_long;
 use macros::syscall;
 
-#[syscall]
-pub fn truncate(path: SyscallString, length: usize) -> Result<i32, Errno> {
-	let proc_mutex = Process::current_assert();
-	let proc = proc_mutex.lock();
+/// Performs the truncate system call, with `length` the new size of the file.
+pub fn do_truncate(path: SyscallString, length: i64) -> EResult<i32> {
+	if length < 0 {
+		return Err(errno!(EINVAL));
+	}
 
-	let mem_space_mutex = proc.get_mem_space().unwrap();
-	let mem_space = mem_space_mutex.lock();
+	let (path, ap) = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
 
-	let path = Path::from_str(path.get(&mem_space)?.ok_or(errno!(EFAULT))?, true)?;
-	let path = super::util::get_absolute_path(&proc, path)?;
+		let mem_space_mutex = proc.get_mem_space().unwrap();
+		let mem_space = mem_space_mutex.lock();
 
-	let file_mutex = vfs::get_file_from_path(&path, &proc.access_profile, true)?;
+		let path = Path::from_str(path.get(&mem_space)?.ok_or(errno!(EFAULT))?, true)?;
+		let path = super::util::get_absolute_path(&proc, path)?;
+
+		(path, proc.access_profile)
+	};
+
+	let file_mutex = vfs::get_file_from_path(&path, &ap, true)?;
 	let mut file = file_mutex.lock();
-	file.set_size(length as _);
+	match file.get_type() {
+		FileType::Regular => {}
+		FileType::Directory => return Err(errno!(EISDIR)),
+		_ => return Err(errno!(EINVAL)),
+	}
+	if !ap.can_write_file(&*file) {
+		return Err(errno!(EACCES));
+	}
+
+	file.truncate(length as _)?;
+	file.sync()?;
 
 	Ok(0)
 }
+
+#[syscall(0x05c)]
+pub fn truncate(path: SyscallString, length: c_long) -> Result<i32, Errno> {
+	do_truncate(path, length as _)
+}
@@ -0,0 +1,11 @@
+//! The `truncate64` system call is like `truncate`, but with a 64 bits length.
+
+use crate::errno::Errno;
+use crate::process::mem_space::ptr::SyscallString;
+use macros::syscall;
+
+#[syscall(0x0c1)]
+pub fn truncate64(path: SyscallString, length_low: u32, length_high: u32) -> Result<i32, Errno> {
+	let length = ((length_high as u64) << 32) | (length_low as u64);
+	super::truncate::do_truncate(path, length as _)
+}
@@ -5,7 +5,7 @@ use crate::file;
 use crate::process::Process;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x03c)]
 pub fn umask(mask: file::Mode) -> Result<i32, Errno> {
 	let proc_mutex = Process::current_assert();
 	let mut proc = proc_mutex.lock();
@@ -9,7 +9,7 @@ use crate::process::mem_space::ptr::SyscallString;
 use crate::process::Process;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x016)]
 pub fn umount(target: SyscallString) -> Result<i32, Errno> {
 	let proc_mutex = Process::current_assert();
 	let proc = proc_mutex.lock();
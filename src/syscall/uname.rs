@@ -23,9 +23,11 @@ struct Utsname {
 	version: [u8; UTSNAME_LENGTH],
 	/// Hardware identifier.
 	machine: [u8; UTSNAME_LENGTH],
+	/// NIS domain name.
+	domainname: [u8; UTSNAME_LENGTH],
 }
 
-#[syscall]
+#[syscall(0x07a)]
 pub fn uname(buf: SyscallPtr<Utsname>) -> Result<i32, Errno> {
 	let proc_mutex = Process::current_assert();
 	let proc = proc_mutex.lock();
@@ -40,15 +42,23 @@ pub fn uname(buf: SyscallPtr<Utsname>) -> Result<i32, Errno> {
 		release: [0; UTSNAME_LENGTH],
 		version: [0; UTSNAME_LENGTH],
 		machine: [0; UTSNAME_LENGTH],
+		domainname: [0; UTSNAME_LENGTH],
 	};
 
 	util::slice_copy(crate::NAME.as_bytes(), &mut utsname.sysname);
 
-	let hostname = crate::HOSTNAME.lock();
-	util::slice_copy(&hostname, &mut utsname.nodename);
+	{
+		let uts = proc.get_uts().lock();
+		util::slice_copy(&uts.hostname, &mut utsname.nodename);
+		util::slice_copy(&uts.domainname, &mut utsname.domainname);
+	}
 
 	util::slice_copy(crate::VERSION.as_bytes(), &mut utsname.release);
-	util::slice_copy(&[], &mut utsname.version);
+	// Keep the terminating null byte
+	util::slice_copy(
+		crate::BUILD_INFO.as_bytes(),
+		&mut utsname.version[..(UTSNAME_LENGTH - 1)],
+	);
 	util::slice_copy(crate::ARCH.as_bytes(), &mut utsname.machine);
 
 	Ok(0)
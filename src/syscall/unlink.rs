@@ -9,7 +9,7 @@ use crate::process::mem_space::ptr::SyscallString;
 use crate::process::Process;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x00a)]
 pub fn unlink(pathname: SyscallString) -> Result<i32, Errno> {
 	let (path, ap) = {
 		let proc_mutex = Process::current_assert();
@@ -3,14 +3,18 @@
 use crate::errno;
 use crate::errno::AllocResult;
 use crate::errno::EResult;
+use crate::errno::Errno;
 use crate::file::path::Path;
 use crate::file::vfs;
 use crate::file::File;
 use crate::file::FileContent;
 use crate::file::Mode;
+use crate::limits;
 use crate::process::mem_space::ptr::SyscallString;
 use crate::process::regs::Regs;
 use crate::process::scheduler;
+use crate::process::signal::SignalHandler;
+use crate::process::signal::SA_RESTART;
 use crate::process::Process;
 use crate::process::State;
 use crate::util::container::string::String;
@@ -18,7 +22,12 @@ use crate::util::container::vec::Vec;
 use crate::util::lock::Mutex;
 use crate::util::lock::MutexGuard;
 use crate::util::ptr::arc::Arc;
+use crate::util::TryClone;
 use core::mem::size_of;
+use core::ptr;
+
+/// The ID of the `restart_syscall` system call.
+const RESTART_SYSCALL_ID: u32 = 0x000;
 
 /// Returns the absolute path according to the process's current working
 /// directory.
@@ -36,48 +45,89 @@ pub fn get_absolute_path(process: &Process, path: Path) -> AllocResult<Path> {
 	process.chroot.concat(&path)
 }
 
-// TODO Find a safer and cleaner solution
-/// Checks that the given array of strings at pointer `ptr` is accessible to
-/// process `proc`, then returns its content.
+/// The maximum number of strings in an array copied by [`get_str_array`].
+const STR_ARRAY_MAX: usize = limits::ARG_MAX / 8;
+
+/// Copies the null-terminated array of strings at pointer `ptr` from the memory space of
+/// process `process`.
+///
+/// The array is read in a single pass: each pointer is read exactly once and the string it
+/// points to is copied immediately, so that another thread modifying the array concurrently
+/// cannot make the kernel use a pointer it has not checked.
+///
+/// `size` is the number of bytes already used by arguments. It is increased by the size taken
+/// by each string, its terminating nullbyte and its pointer. If it exceeds
+/// [`limits::ARG_MAX`], or if the array has too many elements, the function returns
+/// [`errno::E2BIG`].
 ///
-/// If the array or its content strings are not accessible by the process, the
-/// function returns an error.
-pub unsafe fn get_str_array(process: &Process, ptr: *const *const u8) -> EResult<Vec<String>> {
+/// If the array or its content strings are not accessible by the process, the function returns
+/// an error.
+pub unsafe fn get_str_array(
+	process: &Process,
+	ptr: *const *const u8,
+	size: &mut usize,
+) -> EResult<Vec<String>> {
 	let mem_space = process.get_mem_space().unwrap();
 	let mem_space_guard = mem_space.lock();
 
-	// Checking every elements of the array and counting the number of elements
-	let mut len = 0;
+	let mut arr = Vec::new();
 	loop {
-		let elem_ptr = ptr.add(len);
-
-		// Checking access on elem_ptr
+		let elem_ptr = ptr.wrapping_add(arr.len());
 		if !mem_space_guard.can_access(elem_ptr as _, size_of::<*const u8>(), true, false) {
 			return Err(errno!(EFAULT));
 		}
-
 		// Safe because the access is checked before
-		let elem = *elem_ptr;
+		let elem = ptr::read_volatile(elem_ptr);
 		if elem.is_null() {
 			break;
 		}
+		if arr.len() >= STR_ARRAY_MAX {
+			return Err(errno!(E2BIG));
+		}
 
-		len += 1;
-	}
-
-	// Filling the array
-	// TODO collect
-	let mut arr = Vec::with_capacity(len)?;
-	for i in 0..len {
-		let elem = *ptr.add(i);
 		let s: SyscallString = (elem as usize).into();
-
-		arr.push(String::try_from(s.get(&mem_space_guard)?.unwrap())?)?;
+		let s = s.get(&mem_space_guard)?.ok_or_else(|| errno!(EFAULT))?;
+		*size = size
+			.checked_add(s.len() + 1 + size_of::<*const u8>())
+			.filter(|size| *size <= limits::ARG_MAX)
+			.ok_or_else(|| errno!(E2BIG))?;
+		arr.push(String::try_from(s)?)?;
 	}
 
 	Ok(arr)
 }
 
+/// Returns the path of the directory relative paths are resolved from, given the directory file
+/// descriptor `dirfd`.
+///
+/// If `dirfd` is [`super::access::AT_FDCWD`], the current working directory is used.
+///
+/// `process` is the guard of the current process.
+pub fn get_dir_path(process: MutexGuard<Process, false>, dirfd: i32) -> EResult<Path> {
+	if dirfd == super::access::AT_FDCWD {
+		return Ok((*process.cwd).try_clone()?);
+	}
+	if dirfd < 0 {
+		return Err(errno!(EBADF));
+	}
+	let fds_mutex = process.get_fds().unwrap();
+	let fds = fds_mutex.lock();
+	let open_file_mutex = fds
+		.get_fd(dirfd as _)
+		.ok_or(errno!(EBADF))?
+		.get_open_file()
+		.clone();
+	drop(fds);
+
+	// Unlock to avoid deadlock with procfs
+	drop(process);
+
+	let open_file = open_file_mutex.lock();
+	let file_mutex = open_file.get_file();
+	let file = file_mutex.lock();
+	file.get_path()
+}
+
 /// Builds a path with the given directory file descriptor `dirfd` as a base,
 /// concatenated with the given pathname `pathname`.
 ///
@@ -92,31 +142,9 @@ fn build_path_from_fd(
 	if path.is_absolute() {
 		// Using the given absolute path
 		Ok(path)
-	} else if dirfd == super::access::AT_FDCWD {
-		// Using path relative to the current working directory
-		Ok(process.cwd.concat(&path)?)
 	} else {
 		// Using path relative to the directory given by `dirfd`
-
-		if dirfd < 0 {
-			return Err(errno!(EBADF));
-		}
-		let fds_mutex = process.get_fds().unwrap();
-		let fds = fds_mutex.lock();
-		let open_file_mutex = fds
-			.get_fd(dirfd as _)
-			.ok_or(errno!(EBADF))?
-			.get_open_file()
-			.clone();
-		drop(fds);
-
-		// Unlock to avoid deadlock with procfs
-		drop(process);
-
-		let open_file = open_file_mutex.lock();
-		let file_mutex = open_file.get_file();
-		let file = file_mutex.lock();
-		Ok(file.get_path()?.concat(&path)?)
+		Ok(get_dir_path(process, dirfd)?.concat(&path)?)
 	}
 }
 
@@ -174,6 +202,29 @@ pub fn get_file_at(
 	}
 }
 
+/// Returns the file designated either by the path `pathname` or, if `None`, by the file
+/// descriptor `fd`.
+///
+/// This function is useful for system calls that come in a path and a file descriptor flavour,
+/// such as `getxattr` and `fgetxattr`.
+///
+/// Arguments:
+/// - `process` is the mutex guard of the current process.
+/// - `pathname` is the path to the file, relative to the current working directory.
+/// - `fd` is the file descriptor of the file, used if `pathname` is `None`.
+/// - `follow_links` tells whether symbolic links are followed.
+pub fn get_file_by_path_or_fd(
+	process: MutexGuard<Process, false>,
+	pathname: Option<&[u8]>,
+	fd: i32,
+	follow_links: bool,
+) -> EResult<Arc<Mutex<File>>> {
+	match pathname {
+		Some(pathname) => get_file_at(process, super::access::AT_FDCWD, pathname, follow_links, 0),
+		None => get_file_at(process, fd, b"", true, super::access::AT_EMPTY_PATH),
+	}
+}
+
 /// Returns the parent directory of the file for the given path `pathname`.
 ///
 /// This function is useful for system calls with the `at` prefix.
@@ -287,31 +338,92 @@ pub fn handle_proc_state() {
 
 /// Checks whether the current syscall must be interrupted to execute a signal.
 ///
-/// If interrupted, the function doesn't return and the control flow jumps
-/// directly to handling the signal.
+/// If interrupted, the function returns [`errno::ERESTARTSYS`], which must be returned by the
+/// system call. The system call is then either restarted or fails with `EINTR`, according to the
+/// signal's action.
 ///
 /// The functions locks the mutex of the current process. Thus, the caller must
 /// ensure the mutex isn't already locked to prevent a deadlock.
+pub fn signal_check() -> EResult<()> {
+	let proc_mutex = Process::current_assert();
+	let proc = proc_mutex.lock();
+
+	if proc.get_next_signal().is_some() {
+		return Err(errno!(ERESTARTSYS));
+	}
+	Ok(())
+}
+
+/// Tells whether the given errno tells that the system call has been interrupted by a signal.
+pub fn is_restart_errno(errno: &Errno) -> bool {
+	matches!(
+		errno.as_int(),
+		errno::ERESTARTSYS
+			| errno::ERESTARTNOINTR
+			| errno::ERESTARTNOHAND
+			| errno::ERESTART_RESTARTBLOCK
+	)
+}
+
+/// Handles a system call that has been interrupted by a signal.
+///
+/// Arguments:
+/// - `regs` is the registers state passed to the system call.
+/// - `errno` is the errno returned by the system call, for which [`is_restart_errno`] returns
+/// `true`.
 ///
-/// `regs` is the registers state passed to the current syscall.
-pub fn signal_check(regs: &Regs) {
+/// According to `errno` and the signal's action, the system call is either restarted after the
+/// signal has been handled, or fails with `EINTR`. Then, the control flow jumps directly to
+/// handling the signal.
+///
+/// The functions locks the mutex of the current process. Thus, the caller must
+/// ensure the mutex isn't already locked to prevent a deadlock.
+pub fn handle_interrupted(regs: &Regs, errno: Errno) -> ! {
 	let proc_mutex = Process::current_assert();
 	let mut proc = proc_mutex.lock();
 
-	if proc.get_next_signal().is_some() {
-		// Returning the system call early to resume it later
-		let mut r = regs.clone();
-		// TODO Clean
-		r.eip -= 2; // TODO Handle the case where the instruction insn't two bytes long (sysenter)
-		proc.regs = r;
-		proc.syscalling = false;
+	let (has_handler, sa_restart) = match proc
+		.get_next_signal()
+		.map(|sig| proc.get_signal_handler(&sig))
+	{
+		Some(SignalHandler::Handler(action)) => (true, action.sa_flags & SA_RESTART != 0),
+		_ => (false, false),
+	};
+	let restart = match errno.as_int() {
+		errno::ERESTARTSYS => !has_handler || sa_restart,
+		errno::ERESTARTNOINTR => true,
+		_ => !has_handler,
+	};
 
-		// Switching to handle the signal
-		proc.prepare_switch();
+	let mut r = regs.clone();
+	if restart {
+		// Execute the `int 0x80` instruction again
+		r.eip -= 2;
+		if errno.as_int() == errno::ERESTART_RESTARTBLOCK {
+			r.eax = RESTART_SYSCALL_ID;
+		}
+	} else {
+		r.set_syscall_return(Err(errno!(EINTR)));
+		proc.restart_block = None;
+	}
+	proc.regs = r;
+	proc.syscalling = false;
 
-		drop(proc);
-		drop(proc_mutex);
+	// Switching to handle the signal
+	proc.prepare_switch();
 
-		handle_proc_state();
+	drop(proc);
+	drop(proc_mutex);
+
+	handle_proc_state();
+
+	// The signal did not require to execute a handler, resume execution
+	let regs = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+		proc.regs.clone()
+	};
+	unsafe {
+		regs.switch(true);
 	}
 }
@@ -13,7 +13,7 @@ use crate::util::lock::Mutex;
 use core::ffi::c_int;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x140)]
 pub fn utimensat(
 	dirfd: c_int,
 	pathname: SyscallString,
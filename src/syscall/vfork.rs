@@ -10,7 +10,7 @@ use crate::process::Process;
 use crate::util::ptr::arc::Arc;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x0be)]
 pub fn vfork() -> Result<i32, Errno> {
 	let new_pid = {
 		// The current process
@@ -0,0 +1,158 @@
+//! The `vmsplice` system call moves data between the memory of the process and a pipe.
+//!
+//! If the pipe is open for writing, data is moved from memory into the pipe. Else, data is moved
+//! from the pipe into memory.
+
+use super::splice::as_pipe;
+use super::splice::get_pipe;
+use super::splice::SPLICE_F_ALL;
+use super::splice::SPLICE_F_NONBLOCK;
+use crate::errno;
+use crate::errno::EResult;
+use crate::errno::Errno;
+use crate::file::buffer::pipe::PipeBuffer;
+use crate::file::buffer::Buffer;
+use crate::file::open_file::O_NONBLOCK;
+use crate::file::FileType;
+use crate::limits;
+use crate::process::iovec::IOVec;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::mem_space::MemSpace;
+use crate::process::scheduler;
+use crate::process::signal::Signal;
+use crate::process::Process;
+use crate::util::container::vec::Vec;
+use crate::util::io;
+use crate::util::io::IO;
+use core::cmp::min;
+use core::ffi::c_int;
+use core::ffi::c_uint;
+use macros::syscall;
+
+/// Moves data between the chunks `iov` and the pipe `pipe`.
+///
+/// If `to_pipe` is `true`, data is written to the pipe. Else, it is read from it.
+///
+/// The function returns the number of bytes transferred.
+fn transfer(
+	mem_space: &mut MemSpace,
+	iov: &[IOVec],
+	pipe: &mut PipeBuffer,
+	to_pipe: bool,
+) -> EResult<usize> {
+	let mut total = 0;
+	for i in iov {
+		// The size to transfer. This is limited to avoid an overflow on the total length
+		let l = min(i.iov_len, i32::MAX as usize - total);
+		if l == 0 {
+			continue;
+		}
+		let ptr = SyscallSlice::<u8>::from(i.iov_base as usize);
+		let len = if to_pipe {
+			let slice = ptr.get(mem_space, l)?.ok_or_else(|| errno!(EFAULT))?;
+			pipe.write(0, slice)? as usize
+		} else {
+			let slice = ptr.get_mut(mem_space, l)?.ok_or_else(|| errno!(EFAULT))?;
+			pipe.read(0, slice)?.0 as usize
+		};
+		total += len;
+		if len < l {
+			break;
+		}
+	}
+	Ok(total)
+}
+
+#[syscall(0x13c)]
+pub fn vmsplice(
+	fd: c_int,
+	iov: SyscallSlice<IOVec>,
+	nr_segs: usize,
+	flags: c_uint,
+) -> Result<i32, Errno> {
+	if fd < 0 {
+		return Err(errno!(EBADF));
+	}
+	if flags & !SPLICE_F_ALL != 0 || nr_segs > limits::IOV_MAX {
+		return Err(errno!(EINVAL));
+	}
+
+	let (proc_mutex, mem_space, open_file_mutex) = {
+		let proc_mutex = Process::current_assert();
+		let proc = proc_mutex.lock();
+
+		let mem_space = proc.get_mem_space().unwrap().clone();
+
+		let fds_mutex = proc.get_fds().unwrap().clone();
+		let fds = fds_mutex.lock();
+		let open_file_mutex = fds
+			.get_fd(fd as _)
+			.ok_or(errno!(EBADF))?
+			.get_open_file()
+			.clone();
+
+		drop(proc);
+		(proc_mutex, mem_space, open_file_mutex)
+	};
+
+	let (to_pipe, nonblock) = {
+		let open_file = open_file_mutex.lock();
+		if open_file.get_file().lock().get_type() != FileType::Fifo {
+			return Err(errno!(EBADF));
+		}
+		let nonblock = flags & SPLICE_F_NONBLOCK != 0 || open_file.get_flags() & O_NONBLOCK != 0;
+		(open_file.can_write(), nonblock)
+	};
+	let iov = {
+		let mem_space_guard = mem_space.lock();
+		let iov_slice = iov
+			.get(&mem_space_guard, nr_segs)?
+			.ok_or(errno!(EFAULT))?;
+		let mut iov = Vec::new();
+		iov.extend_from_slice(iov_slice)?;
+		iov
+	};
+
+	let pipe_mutex = get_pipe(&open_file_mutex)?;
+	loop {
+		super::util::signal_check()?;
+
+		{
+			let mut pipe_guard = pipe_mutex.lock();
+			let pipe = as_pipe(&mut *pipe_guard);
+
+			let mut mem_space_guard = mem_space.lock();
+			let len = match transfer(&mut mem_space_guard, &iov, pipe, to_pipe) {
+				Ok(len) => len,
+				Err(e) => {
+					// If writing to a broken pipe, kill with SIGPIPE
+					if e.as_int() == errno::EPIPE {
+						proc_mutex.lock().kill(&Signal::SIGPIPE, false);
+					}
+					return Err(e);
+				}
+			};
+
+			if len > 0 || iov.iter().all(|i| i.iov_len == 0) {
+				return Ok(len as _);
+			}
+			// Reading from an empty pipe without writers
+			if !to_pipe && !pipe.has_writers() {
+				return Ok(0);
+			}
+			if nonblock {
+				return Err(errno!(EAGAIN));
+			}
+
+			// Block on the pipe
+			let mask = if to_pipe {
+				io::POLLOUT | io::POLLERR
+			} else {
+				io::POLLIN | io::POLLHUP
+			};
+			pipe.add_waiting_process(&mut proc_mutex.lock(), mask)?;
+		}
+
+		scheduler::end_tick();
+	}
+}
@@ -7,7 +7,7 @@ use crate::process::rusage::RUsage;
 use core::ffi::c_int;
 use macros::syscall;
 
-#[syscall]
+#[syscall(0x072)]
 pub fn wait4(
 	pid: c_int,
 	wstatus: SyscallPtr<c_int>,
@@ -15,8 +15,8 @@ pub fn wait4(
 	rusage: SyscallPtr<RUsage>,
 ) -> Result<i32, Errno> {
 	if rusage.is_null() {
-		waitpid::do_waitpid(regs, pid, wstatus, options | waitpid::WEXITED, None)
+		waitpid::do_waitpid(pid, wstatus, options | waitpid::WEXITED, None)
 	} else {
-		waitpid::do_waitpid(regs, pid, wstatus, options | waitpid::WEXITED, Some(rusage))
+		waitpid::do_waitpid(pid, wstatus, options | waitpid::WEXITED, Some(rusage))
 	}
 }
@@ -5,7 +5,6 @@ use crate::errno::Errno;
 use crate::process;
 use crate::process::mem_space::ptr::SyscallPtr;
 use crate::process::pid::Pid;
-use crate::process::regs::Regs;
 use crate::process::rusage::RUsage;
 use crate::process::scheduler;
 use crate::process::Process;
@@ -130,10 +129,15 @@ fn check_waitable(
 
 					// If the process was a zombie, remove it
 					if exit_check {
+						// A thread group is waitable only once all its threads have exited, so
+						// the remaining threads can be removed along with the leader
+						let threads = p.take_threads();
 						drop(p);
 
 						curr_proc.remove_child(pid);
-						sched.remove_process(pid);
+						for tid in threads {
+							sched.remove_process(tid);
+						}
 					}
 				}
 
@@ -155,13 +159,11 @@ fn check_waitable(
 /// Executes the `waitpid` system call.
 ///
 /// Arguments:
-/// - `regs` is the registers state.
 /// - `pid` is the PID to wait for.
 /// - `wstatus` is the pointer on which to write the status.
 /// - `options` are flags passed with the syscall.
 /// - `rusage` is the pointer to the resource usage structure.
 pub fn do_waitpid(
-	regs: &Regs,
 	pid: i32,
 	wstatus: SyscallPtr<i32>,
 	options: i32,
@@ -169,7 +171,7 @@ pub fn do_waitpid(
 ) -> Result<i32, Errno> {
 	// Sleeping until a target process is waitable
 	loop {
-		super::util::signal_check(regs);
+		super::util::signal_check()?;
 
 		cli!();
 
@@ -218,7 +220,7 @@ pub fn do_waitpid(
 	}
 }
 
-#[syscall]
+#[syscall(0x007)]
 pub fn waitpid(pid: c_int, wstatus: SyscallPtr<c_int>, options: c_int) -> Result<i32, Errno> {
-	do_waitpid(regs, pid, wstatus, options | WEXITED, None)
+	do_waitpid(pid, wstatus, options | WEXITED, None)
 }
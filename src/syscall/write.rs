@@ -16,7 +16,7 @@ use macros::syscall;
 
 // TODO O_ASYNC
 
-#[syscall]
+#[syscall(0x004)]
 pub fn write(fd: c_int, buf: SyscallSlice<u8>, count: usize) -> Result<i32, Errno> {
 	if fd < 0 {
 		return Err(errno!(EBADF));
@@ -46,7 +46,7 @@ pub fn write(fd: c_int, buf: SyscallSlice<u8>, count: usize) -> Result<i32, Errn
 	};
 
 	loop {
-		super::util::signal_check(regs);
+		super::util::signal_check()?;
 
 		{
 			let mem_space_guard = mem_space.lock();
@@ -108,7 +108,7 @@ pub fn do_writev(
 	};
 
 	loop {
-		// TODO super::util::signal_check(regs);
+		// TODO super::util::signal_check()?;
 
 		{
 			let mut open_file = open_file_mutex.lock();
@@ -155,7 +155,7 @@ pub fn do_writev(
 	}
 }
 
-#[syscall]
+#[syscall(0x092)]
 pub fn writev(fd: c_int, iov: SyscallSlice<IOVec>, iovcnt: c_int) -> Result<i32, Errno> {
 	do_writev(fd, iov, iovcnt, None, None)
 }
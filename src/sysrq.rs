@@ -0,0 +1,183 @@
+//! Magic SysRq keys allow to perform low-level commands to recover a wedged system.
+//!
+//! A command is triggered by a key, which is either:
+//! - pressed while holding `Alt` and `SysRq` on the keyboard
+//! - received right after a break on the serial console
+//! - written to `/proc/sysrq-trigger`
+//!
+//! Keyboard and serial input are handled in interrupt context, and `/proc/sysrq-trigger` is
+//! written while its filesystem is locked. Since accessing filesystems or devices may then
+//! deadlock, commands that require it are deferred to the next system call.
+
+use crate::debug::gdbstub;
+use crate::file::mountpoint;
+use crate::memory::stats;
+use crate::power;
+use crate::process;
+use crate::process::signal::Signal;
+use crate::util::DisplayableStr;
+use core::sync::atomic;
+use core::sync::atomic::AtomicU32;
+
+/// A SysRq command.
+struct Command {
+	/// The key triggering the command.
+	key: u8,
+	/// The description of the command, printed by the help.
+	help: &'static str,
+	/// Tells whether the command is deferred to the next system call.
+	deferred: bool,
+	/// The function performing the command.
+	action: fn(),
+}
+
+/// The list of commands.
+static COMMANDS: [Command; 8] = [
+	Command {
+		key: b'b',
+		help: "reboot(b)",
+		deferred: false,
+		action: || {
+			power::reboot();
+		},
+	},
+	Command {
+		key: b'e',
+		help: "terminate-all-tasks(e)",
+		deferred: false,
+		action: || kill_all(Signal::SIGTERM),
+	},
+	Command {
+		key: b'g',
+		help: "debug(g)",
+		deferred: false,
+		action: gdbstub::enter,
+	},
+	Command {
+		key: b'i',
+		help: "kill-all-tasks(i)",
+		deferred: false,
+		action: || kill_all(Signal::SIGKILL),
+	},
+	Command {
+		key: b'm',
+		help: "show-memory-usage(m)",
+		deferred: true,
+		action: show_memory,
+	},
+	Command {
+		key: b's',
+		help: "sync(s)",
+		deferred: true,
+		action: sync,
+	},
+	Command {
+		key: b't',
+		help: "show-task-states(t)",
+		deferred: false,
+		action: show_tasks,
+	},
+	Command {
+		key: b'u',
+		help: "unmount(u)",
+		deferred: true,
+		action: remount_readonly,
+	},
+];
+
+/// The bitmask of deferred commands, by index in [`COMMANDS`], waiting to be performed.
+static PENDING: AtomicU32 = AtomicU32::new(0);
+
+/// Sends `sig` to all processes except init.
+fn kill_all(sig: Signal) {
+	let count = process::kill_all(&sig, None);
+	crate::println!("SysRq: sent {sig:?} to {count} processes");
+}
+
+/// Prints the memory usage.
+fn show_memory() {
+	let mem_info = stats::MEM_INFO.lock();
+	crate::println!(
+		"SysRq: memory: {} kB total, {} kB free",
+		mem_info.mem_total,
+		mem_info.mem_free
+	);
+}
+
+/// Writes the pending changes of every filesystem and flushes the caches of storage devices.
+fn sync() {
+	match mountpoint::sync_all() {
+		Ok(()) => crate::println!("SysRq: sync complete"),
+		Err(e) => crate::println!("SysRq: sync failed: {e}"),
+	}
+}
+
+/// Prints the state of every process.
+fn show_tasks() {
+	crate::println!("SysRq: tasks:");
+	crate::println!("  PID  PPID  UID S COMMAND");
+	let mut sched = process::get_scheduler().lock();
+	for (pid, proc_mutex) in sched.iter_process() {
+		let proc = proc_mutex.lock();
+		let name = proc
+			.argv
+			.first()
+			.map(|name| name.as_bytes())
+			.unwrap_or_default();
+		crate::println!(
+			"{pid:5} {ppid:5} {uid:4} {state} {name}",
+			ppid = proc.get_parent_pid(),
+			uid = proc.access_profile.get_euid(),
+			state = proc.get_state().get_char(),
+			name = DisplayableStr(name)
+		);
+	}
+}
+
+/// Makes all filesystems read-only.
+fn remount_readonly() {
+	mountpoint::unmount_all();
+	crate::println!("SysRq: filesystems remounted read-only");
+}
+
+/// Prints the list of commands.
+fn help() {
+	crate::print!("SysRq: HELP:");
+	for cmd in COMMANDS.iter() {
+		crate::print!(" {}", cmd.help);
+	}
+	crate::println!();
+}
+
+/// Handles the SysRq key `key`.
+///
+/// Commands that cannot run in the current context are deferred to [`run_pending`].
+///
+/// If the key does not correspond to a command, the help is printed.
+pub fn handle(key: u8) {
+	let key = key.to_ascii_lowercase();
+	let Some((i, cmd)) = COMMANDS.iter().enumerate().find(|(_, cmd)| cmd.key == key) else {
+		help();
+		return;
+	};
+	if cmd.deferred {
+		PENDING.fetch_or(1 << i, atomic::Ordering::Relaxed);
+	} else {
+		(cmd.action)();
+	}
+}
+
+/// Performs the commands that have been deferred.
+///
+/// This function must be called from a process' context, with no lock held.
+pub fn run_pending() {
+	if PENDING.load(atomic::Ordering::Relaxed) == 0 {
+		return;
+	}
+	let pending = PENDING.swap(0, atomic::Ordering::Relaxed);
+	for (i, cmd) in COMMANDS.iter().enumerate() {
+		if pending & (1 << i) != 0 {
+			(cmd.action)();
+		}
+	}
+}
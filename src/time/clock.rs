@@ -1,12 +1,15 @@
 //! This module implements system clocks.
 
-use super::AtomicTimestamp;
 use crate::errno::EResult;
+use crate::memory;
 use crate::time::unit::ClockIdT;
 use crate::time::unit::TimeUnit;
-use crate::time::Timestamp;
-use crate::time::TimestampScale;
-use core::cmp::max;
+use crate::time::unit::Timestamp;
+use crate::time::unit::TimestampScale;
+use crate::util::lock::seqlock::SeqLock;
+use core::mem::offset_of;
+use core::mem::size_of;
+use core::ptr::NonNull;
 
 /// System clock ID
 pub const CLOCK_REALTIME: ClockIdT = 0;
@@ -33,21 +36,95 @@ pub const CLOCK_SGI_CYCLE: ClockIdT = 10;
 /// System clock ID
 pub const CLOCK_TAI: ClockIdT = 11;
 
-// TODO allow accessing clocks through an address shared with userspace (vDSO)
+/// The value of a clock.
+#[repr(C, align(8))]
+#[derive(Clone, Copy)]
+struct ClockValue {
+	/// Seconds.
+	sec: u64,
+	/// Nanoseconds, in the range `0..1_000_000_000`.
+	nsec: u32,
+	/// Padding.
+	_padding: u32,
+}
+
+impl ClockValue {
+	/// A value of zero.
+	const ZERO: Self = Self {
+		sec: 0,
+		nsec: 0,
+		_padding: 0,
+	};
+
+	/// Adds `delta` nanoseconds to the value.
+	fn add(&mut self, delta: Timestamp) {
+		let nsec = self.nsec as u64 + delta;
+		self.sec += nsec / 1_000_000_000;
+		self.nsec = (nsec % 1_000_000_000) as _;
+	}
+
+	/// Returns the value in nanoseconds.
+	fn as_nanos(&self) -> Timestamp {
+		self.sec * 1_000_000_000 + self.nsec as u64
+	}
+}
+
+/// The values of the clocks.
+///
+/// This structure is shared read-only with userspace through the vDSO, which reads it at fixed
+/// offsets. Its layout must remain consistent with the vDSO's code.
+#[repr(C, align(8))]
+#[derive(Clone, Copy)]
+struct ClockData {
+	/// The real time clock.
+	realtime: ClockValue,
+	/// The monotonic clock. On time adjustement, it keeps its value if the real time clock went
+	/// backwards in time.
+	monotonic: ClockValue,
+	/// The time elapsed since boot time.
+	boottime: ClockValue,
+}
+
+/// A page containing only the clocks' data, so that it can be mapped into userspace without
+/// exposing anything else.
+#[repr(C, align(4096))]
+struct ClockPage(SeqLock<ClockData>);
 
-/// The current timestamp of the real time clock, in nanoseconds.
-static REALTIME: AtomicTimestamp = AtomicTimestamp::new(0);
-/// On time adjustement, this value is updated with the previous value of the real time clock so
-/// that it can be used if the clock went backwards in time.
-static MONOTONIC: AtomicTimestamp = AtomicTimestamp::new(0);
-/// The time elapsed since boot time, in nanoseconds.
-static BOOTTIME: AtomicTimestamp = AtomicTimestamp::new(0);
+// The vDSO expects the sequence counter at offset `0` and the data at offset `8`
+const _: () = assert!(size_of::<ClockPage>() == memory::PAGE_SIZE);
+const _: () = assert!(offset_of!(ClockData, monotonic) == 16);
+const _: () = assert!(offset_of!(ClockData, boottime) == 32);
+const _: () = assert!(offset_of!(ClockValue, nsec) == 8);
+
+/// The values of the clocks.
+///
+/// Since they are updated at each tick and read often, they are protected by a sequence lock so
+/// that reading never blocks.
+static CLOCKS: ClockPage = ClockPage(SeqLock::new(ClockData {
+	realtime: ClockValue::ZERO,
+	monotonic: ClockValue::ZERO,
+	boottime: ClockValue::ZERO,
+}));
+
+/// Returns the physical address of the page containing the clocks' data.
+///
+/// The page is meant to be mapped read-only into userspace.
+pub fn data_page() -> NonNull<[u8; memory::PAGE_SIZE]> {
+	let ptr = memory::kern_to_phys(&CLOCKS as *const _ as *const [u8; memory::PAGE_SIZE]);
+	NonNull::new(ptr as *mut _).unwrap()
+}
 
 /// Updates clocks with the given delta value in nanoseconds.
 pub fn update(delta: Timestamp) {
-	REALTIME.fetch_add(delta as _);
-	MONOTONIC.fetch_add(delta as _);
-	BOOTTIME.fetch_add(delta as _);
+	CLOCKS.0.write(|data| {
+		data.realtime.add(delta);
+		data.monotonic.add(delta);
+		data.boottime.add(delta);
+
+		if data.realtime.as_nanos() > data.monotonic.as_nanos() {
+			data.monotonic = data.realtime;
+		}
+	});
 }
 
 /// Returns the current timestamp according to the clock with the given ID.
@@ -59,15 +136,11 @@ pub fn update(delta: Timestamp) {
 /// If the clock is invalid, the function returns an error.
 pub fn current_time(clk: ClockIdT, scale: TimestampScale) -> EResult<Timestamp> {
 	// TODO implement all clocks
+	let data = CLOCKS.0.read();
 	let raw_ts = match clk {
-		CLOCK_REALTIME | CLOCK_REALTIME_ALARM => REALTIME.load(),
-		CLOCK_MONOTONIC => {
-			let realtime = REALTIME.load();
-			let monotonic = MONOTONIC.load();
-
-			max(realtime, monotonic)
-		}
-		CLOCK_BOOTTIME | CLOCK_BOOTTIME_ALARM => BOOTTIME.load(),
+		CLOCK_REALTIME | CLOCK_REALTIME_COARSE | CLOCK_REALTIME_ALARM => data.realtime.as_nanos(),
+		CLOCK_MONOTONIC | CLOCK_MONOTONIC_COARSE => data.monotonic.as_nanos(),
+		CLOCK_BOOTTIME | CLOCK_BOOTTIME_ALARM => data.boottime.as_nanos(),
 
 		_ => return Err(errno!(EINVAL)),
 	};
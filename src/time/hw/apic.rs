@@ -0,0 +1,73 @@
+//! The local APIC timer triggers interruptions at a fixed interval, like the PIT. It is used
+//! instead of the PIT when the APIC is in use.
+
+use super::pit;
+use super::HwClock;
+use crate::idt::apic;
+use crate::util::math::rational::Rational;
+
+/// The duration of the timer's calibration against the PIT, in milliseconds.
+const CALIBRATION_DELAY: u16 = 10;
+
+/// The local APIC timer of the current CPU core.
+pub struct ApicTimer {
+	/// The frequency at which the timer ticks, in hertz.
+	tick_frequency: u64,
+	/// The number of ticks between two interruptions. Zero if the frequency is undefined.
+	count: u32,
+	/// Tells whether the timer is enabled.
+	enabled: bool,
+}
+
+impl ApicTimer {
+	/// Creates a new instance, measuring the frequency of the timer with the PIT.
+	///
+	/// By default, the timer is disabled and its frequency is undefined.
+	pub fn new() -> Self {
+		// The timer is too slow to reach zero during calibration
+		apic::timer_start(u32::MAX, false);
+		pit::busy_wait(CALIBRATION_DELAY);
+		let elapsed = u32::MAX - apic::timer_current();
+		apic::timer_stop();
+
+		Self {
+			tick_frequency: elapsed as u64 * 1000 / CALIBRATION_DELAY as u64,
+			count: 0,
+			enabled: false,
+		}
+	}
+}
+
+impl HwClock for ApicTimer {
+	fn set_enabled(&mut self, enable: bool) {
+		self.enabled = enable;
+		if enable && self.count != 0 {
+			apic::timer_start(self.count, true);
+		} else {
+			apic::timer_stop();
+		}
+	}
+
+	fn set_frequency(&mut self, freq: Rational) {
+		self.count = if freq != Rational::from(0) {
+			let count = i64::from(Rational::from_integer(self.tick_frequency as _) / freq);
+			count.clamp(1, u32::MAX as _) as _
+		} else {
+			0
+		};
+		// Apply the new frequency
+		if self.enabled {
+			self.set_enabled(true);
+		}
+	}
+
+	fn get_interrupt_vector(&self) -> u32 {
+		apic::TIMER_VECTOR
+	}
+}
+
+impl Drop for ApicTimer {
+	fn drop(&mut self) {
+		self.set_enabled(false);
+	}
+}
@@ -0,0 +1,119 @@
+//! kvmclock is a paravirtual clock provided by KVM.
+//!
+//! The hypervisor keeps a structure in guest memory up to date, allowing to compute the time
+//! elapsed since boot from the timestamp counter without exiting to the hypervisor.
+
+use super::ClockSource;
+use crate::cpu;
+use crate::cpu::kvm;
+use crate::memory;
+use crate::time::unit::Timestamp;
+use core::arch::x86::_rdtsc;
+use core::cmp::max;
+use core::ptr;
+use core::ptr::addr_of;
+use core::sync::atomic;
+
+/// Flag of [`TimeInfo`] telling the clock is monotonic across CPU cores.
+const FLAG_TSC_STABLE: u8 = 1 << 0;
+
+/// The structure shared with the hypervisor.
+///
+/// The structure must not cross a page boundary, hence the alignment.
+#[repr(C, align(32))]
+#[derive(Clone, Copy)]
+struct TimeInfo {
+	/// The version of the structure. Odd while the hypervisor is updating it.
+	version: u32,
+	/// Padding.
+	_pad0: u32,
+	/// The value of the timestamp counter at the time of the update.
+	tsc_timestamp: u64,
+	/// The time elapsed since boot at the time of the update, in nanoseconds.
+	system_time: u64,
+	/// The multiplier converting timestamp counter ticks into nanoseconds, as a 32.32 fixed point
+	/// number.
+	tsc_to_system_mul: u32,
+	/// The shift to apply to timestamp counter ticks before multiplication.
+	tsc_shift: i8,
+	/// Flags.
+	flags: u8,
+	/// Padding.
+	_pad1: [u8; 2],
+}
+
+impl TimeInfo {
+	/// Returns the time elapsed since boot for the timestamp counter value `tsc`, in nanoseconds.
+	fn time_at(&self, tsc: u64) -> Timestamp {
+		let mut delta = tsc.wrapping_sub(self.tsc_timestamp);
+		if self.tsc_shift >= 0 {
+			delta <<= self.tsc_shift;
+		} else {
+			delta >>= -self.tsc_shift;
+		}
+		let delta = (delta as u128 * self.tsc_to_system_mul as u128) >> 32;
+		self.system_time.wrapping_add(delta as u64)
+	}
+}
+
+/// The structure shared with the hypervisor.
+static mut TIME_INFO: TimeInfo = TimeInfo {
+	version: 0,
+	_pad0: 0,
+	tsc_timestamp: 0,
+	system_time: 0,
+	tsc_to_system_mul: 0,
+	tsc_shift: 0,
+	flags: 0,
+	_pad1: [0; 2],
+};
+
+/// The paravirtual clock of KVM.
+pub struct KvmClock {
+	/// The latest value returned by the clock, used to keep it monotonic.
+	last: Timestamp,
+}
+
+impl KvmClock {
+	/// Registers the clock's structure to the hypervisor and returns a new instance.
+	///
+	/// If the kernel does not run under KVM or if the hypervisor doesn't support the clock, the
+	/// function returns `None`.
+	pub fn new() -> Option<Self> {
+		if !kvm::has(kvm::FEATURE_CLOCKSOURCE2) {
+			return None;
+		}
+		let phys_addr = memory::kern_to_phys(unsafe { addr_of!(TIME_INFO) }) as u64;
+		// Safe because the feature is available and the structure is properly aligned
+		unsafe {
+			cpu::wrmsr(kvm::MSR_SYSTEM_TIME_NEW, phys_addr | kvm::MSR_ENABLED);
+		}
+		Some(Self {
+			last: 0,
+		})
+	}
+}
+
+impl ClockSource for KvmClock {
+	fn read(&mut self) -> Timestamp {
+		let (info, tsc) = loop {
+			// The structure is written by the hypervisor at any time
+			let info = unsafe { ptr::read_volatile(addr_of!(TIME_INFO)) };
+			atomic::compiler_fence(atomic::Ordering::Acquire);
+			let tsc = unsafe { _rdtsc() };
+			atomic::compiler_fence(atomic::Ordering::Acquire);
+			let version = unsafe { ptr::read_volatile(addr_of!(TIME_INFO.version)) };
+			if info.version & 1 == 0 && info.version == version {
+				break (info, tsc);
+			}
+		};
+		let now = info.time_at(tsc);
+
+		// Without the stable flag, the values computed on each CPU core may go slightly backwards
+		// relative to each other
+		let stable =
+			kvm::has(kvm::FEATURE_CLOCKSOURCE_STABLE) && info.flags & FLAG_TSC_STABLE != 0;
+		self.last = if stable { now } else { max(self.last, now) };
+		self.last
+	}
+}
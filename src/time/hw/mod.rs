@@ -1,14 +1,20 @@
 //! This module implements hardware clocks.
 
+#[cfg(target_arch = "x86")]
+pub mod apic;
+#[cfg(target_arch = "x86")]
+pub mod kvmclock;
 #[cfg(target_arch = "x86")]
 pub mod pit;
 #[cfg(target_arch = "x86")]
 pub mod rtc;
 
+use crate::idt;
 use crate::time::unit::Timestamp;
 use crate::util::boxed::Box;
 use crate::util::container::hashmap::HashMap;
 use crate::util::container::string::String;
+use crate::util::lock::IntMutex;
 use crate::util::lock::Mutex;
 use crate::util::math::rational::Rational;
 
@@ -31,7 +37,52 @@ pub trait HwClock {
 	fn get_interrupt_vector(&self) -> u32;
 }
 
+/// Trait representing a counter from which the elapsed time can be read directly.
+///
+/// Updating software clocks from a clock source doesn't drift, unlike accumulating the nominal
+/// period of a timer's interruptions, which may be delayed or lost.
+pub trait ClockSource {
+	/// Returns the current value of the counter, in nanoseconds.
+	///
+	/// The value never decreases, but its origin is unspecified.
+	fn read(&mut self) -> Timestamp;
+}
+
+/// The clock source in use, along with its value at the previous call to
+/// [`clock_source_elapsed`].
+static CLOCK_SOURCE: IntMutex<Option<(Box<dyn ClockSource>, Timestamp)>> = IntMutex::new(None);
+
+/// Sets the clock source used to update software clocks.
+pub fn set_clock_source(mut src: Box<dyn ClockSource>) {
+	let now = src.read();
+	*CLOCK_SOURCE.lock() = Some((src, now));
+}
+
+/// Returns the number of nanoseconds elapsed since the previous call, according to the clock
+/// source.
+///
+/// If no clock source is in use, the function returns `None`.
+pub fn clock_source_elapsed() -> Option<Timestamp> {
+	let mut guard = CLOCK_SOURCE.lock();
+	let (src, last) = guard.as_mut()?;
+	let now = src.read();
+	let elapsed = now.saturating_sub(*last);
+	*last = now;
+	Some(elapsed)
+}
+
 /// The list of hardware clock sources.
 ///
 /// The key is the name of the clock.
 pub static CLOCKS: Mutex<HashMap<String, Box<dyn HwClock>>> = Mutex::new(HashMap::new());
+
+/// Returns the name of the clock producing the scheduler's ticks.
+///
+/// The local APIC timer is preferred over the PIT when the APIC is in use.
+pub fn get_tick_clock() -> &'static [u8] {
+	if idt::apic::is_enabled() {
+		b"apic"
+	} else {
+		b"pit"
+	}
+}
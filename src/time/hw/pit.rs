@@ -3,7 +3,6 @@
 
 use super::HwClock;
 use crate::idt;
-use crate::idt::pic;
 use crate::io;
 use crate::util::math::rational::Rational;
 
@@ -18,6 +17,12 @@ const PIT_COMMAND: u16 = 0x43;
 
 /// The command to enable the PC speaker.
 const BEEPER_ENABLE_COMMAND: u8 = 0x61;
+/// Flag of [`BEEPER_ENABLE_COMMAND`]: the gate of channel 2 is enabled.
+const GATE_CHANNEL_2: u8 = 0b1;
+/// Flag of [`BEEPER_ENABLE_COMMAND`]: the output of channel 2 is connected to the PC speaker.
+const SPEAKER_DATA: u8 = 0b10;
+/// Flag of [`BEEPER_ENABLE_COMMAND`]: the output of channel 2 is high.
+const OUTPUT_CHANNEL_2: u8 = 0b100000;
 
 /// Select PIT channel 0.
 const SELECT_CHANNEL_0: u8 = 0b00 << 6;
@@ -84,12 +89,37 @@ impl PIT {
 	}
 }
 
+/// Busy-waits for `ms` milliseconds using channel 2, without relying on interruptions.
+///
+/// `ms` must not exceed `54`, which is the longest delay the channel can count.
+pub fn busy_wait(ms: u16) {
+	let count = (i64::from(BASE_FREQUENCY) * ms as i64 / 1000) as u16;
+
+	idt::wrap_disable_interrupts(|| unsafe {
+		// Disconnect the PC speaker and disable the gate while configuring the channel
+		let port = io::inb(BEEPER_ENABLE_COMMAND as _) & !(SPEAKER_DATA | GATE_CHANNEL_2);
+		io::outb(BEEPER_ENABLE_COMMAND as _, port);
+
+		io::outb(
+			PIT_COMMAND,
+			SELECT_CHANNEL_2 | ACCESS_LOBYTE_HIBYTE | MODE_0,
+		);
+		io::outb(CHANNEL_2, (count & 0xff) as u8);
+		io::outb(CHANNEL_2, ((count >> 8) & 0xff) as u8);
+
+		// Counting starts when the gate is enabled. The output goes high once it is done
+		io::outb(BEEPER_ENABLE_COMMAND as _, port | GATE_CHANNEL_2);
+		while io::inb(BEEPER_ENABLE_COMMAND as _) & OUTPUT_CHANNEL_2 == 0 {}
+		io::outb(BEEPER_ENABLE_COMMAND as _, port);
+	});
+}
+
 impl HwClock for PIT {
 	fn set_enabled(&mut self, enable: bool) {
 		if enable {
-			pic::enable_irq(0x0);
+			idt::enable_irq(0x0);
 		} else {
-			pic::disable_irq(0x0);
+			idt::disable_irq(0x0);
 		}
 	}
 
@@ -14,83 +14,10 @@ pub mod unit;
 use crate::errno::EResult;
 use crate::event;
 use crate::event::CallbackResult;
+use crate::idt;
 use crate::util::boxed::Box;
-use crate::util::lock::IntMutex;
 use crate::util::math::rational::Rational;
 use core::mem::ManuallyDrop;
-use unit::Timestamp;
-use unit::TimestampScale;
-
-/// Atomic storage for a timestamp.
-///
-/// This wrapper is required because timestamps span 64 bits, but 32 bits architectures may not
-/// support atomic operations on 64 bits operands.
-pub struct AtomicTimestamp {
-	#[cfg(target_pointer_width = "32")]
-	inner: IntMutex<Timestamp>,
-	#[cfg(target_pointer_width = "64")]
-	inner: AtomicU64,
-}
-
-impl AtomicTimestamp {
-	pub const fn new(val: Timestamp) -> Self {
-		Self {
-			#[cfg(target_pointer_width = "32")]
-			inner: IntMutex::new(val),
-			#[cfg(target_pointer_width = "64")]
-			inner: AtomicU64::new(val),
-		}
-	}
-
-	/// Loads and returns the value.
-	#[inline]
-	pub fn load(&self) -> Timestamp {
-		#[cfg(target_pointer_width = "32")]
-		{
-			*self.inner.lock()
-		}
-
-		#[cfg(target_pointer_width = "64")]
-		{
-			self.inner.load(core::sync::atomic::Ordering::Relaxed)
-		}
-	}
-
-	/// Stores the given value and returns the previous.
-	#[inline]
-	pub fn store(&self, val: Timestamp) -> Timestamp {
-		#[cfg(target_pointer_width = "32")]
-		{
-			let mut guard = self.inner.lock();
-			let prev = *guard;
-			*guard = val;
-			prev
-		}
-
-		#[cfg(target_pointer_width = "64")]
-		{
-			self.inner.store(val, core::sync::atomic::Ordering::Relaxed)
-		}
-	}
-
-	/// Adds the given value and returns the previous.
-	#[inline]
-	pub fn fetch_add(&self, val: Timestamp) -> Timestamp {
-		#[cfg(target_pointer_width = "32")]
-		{
-			let mut guard = self.inner.lock();
-			let prev = *guard;
-			*guard = prev.wrapping_add(val);
-			prev
-		}
-
-		#[cfg(target_pointer_width = "64")]
-		{
-			self.inner
-				.fetch_add(val, core::sync::atomic::Ordering::Relaxed)
-		}
-	}
-}
 
 /// Initializes time management.
 pub fn init() -> EResult<()> {
@@ -100,8 +27,13 @@ pub fn init() -> EResult<()> {
 	{
 		hw_clocks.insert(b"pit".try_into()?, Box::new(hw::pit::PIT::new())?)?;
 		hw_clocks.insert(b"rtc".try_into()?, Box::new(hw::rtc::RTC::new())?)?;
+		if idt::apic::is_enabled() {
+			hw_clocks.insert(b"apic".try_into()?, Box::new(hw::apic::ApicTimer::new())?)?;
+		}
 		// TODO implement HPET
-		// TODO implement APIC timer
+		if let Some(kvmclock) = hw::kvmclock::KvmClock::new() {
+			hw::set_clock_source(Box::new(kvmclock)?);
+		}
 	}
 
 	// Link hardware clock to software clock
@@ -113,8 +45,10 @@ pub fn init() -> EResult<()> {
 
 		let hook = event::register_callback(rtc.get_interrupt_vector(), move |_, _, _, _| {
 			hw::rtc::RTC::reset();
-			// FIXME: the value is probably not right
-			clock::update(i64::from(freq * 1_000_000_000) as _);
+			// FIXME: without a clock source, the value is probably not right
+			let delta =
+				hw::clock_source_elapsed().unwrap_or_else(|| i64::from(freq * 1_000_000_000) as _);
+			clock::update(delta);
 			timer::tick();
 
 			CallbackResult::Continue
@@ -1,11 +1,13 @@
 //! This module implements timers.
 
 use super::clock;
+use super::clock::CLOCK_MONOTONIC;
 use super::unit::ClockIdT;
 use super::unit::ITimerspec32;
 use super::unit::TimeUnit;
 use super::unit::TimerT;
 use super::unit::Timespec;
+use super::unit::Timestamp;
 use super::unit::TimestampScale;
 use crate::errno::AllocResult;
 use crate::errno::EResult;
@@ -275,8 +277,48 @@ impl Drop for TimerManager {
 /// - the ID of the timer
 static TIMERS_QUEUE: IntMutex<Map<(Timespec, Pid, TimerT), ()>> = IntMutex::new(Map::new());
 
+/// The queue of processes to be woken up.
+///
+/// The key has the following elements:
+/// - the timestamp on [`CLOCK_MONOTONIC`], in nanoseconds, at which the process is woken up
+/// - the PID of the process
+static WAKEUP_QUEUE: IntMutex<Map<(Timestamp, Pid), ()>> = IntMutex::new(Map::new());
+
+/// Wakes up the process with PID `pid` if sleeping once [`CLOCK_MONOTONIC`] reaches `ts`, in
+/// nanoseconds.
+pub fn wake_at(pid: Pid, ts: Timestamp) -> AllocResult<()> {
+	WAKEUP_QUEUE.lock().insert((ts, pid), ())?;
+	Ok(())
+}
+
+/// Cancels a wake up previously scheduled with [`wake_at`].
+///
+/// If the wake up has already happened, the function does nothing.
+pub fn cancel_wake(pid: Pid, ts: Timestamp) {
+	WAKEUP_QUEUE.lock().remove(&(ts, pid));
+}
+
+/// Wakes up the processes whose wake up timestamp has been reached.
+fn wake_processes() {
+	let Ok(now) = clock::current_time(CLOCK_MONOTONIC, TimestampScale::Nanosecond) else {
+		return;
+	};
+	let mut queue = WAKEUP_QUEUE.lock();
+	while let Some(((ts, pid), _)) = queue.first_key_value() {
+		if *ts > now {
+			break;
+		}
+		if let Some(proc_mutex) = Process::get_by_pid(*pid) {
+			proc_mutex.lock().wake();
+		}
+		queue.pop_first();
+	}
+}
+
 /// Ticks active timers and triggers them if necessary.
 pub(super) fn tick() {
+	wake_processes();
+
 	let mut times: [Option<Timespec>; 12] = [None; 12];
 	let mut queue = TIMERS_QUEUE.lock();
 
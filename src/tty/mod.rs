@@ -5,13 +5,20 @@
 //!
 //! At startup, the kernel has one TTY: the init TTY, which is stored separately
 //! because at the time of creation, memory management isn't initialized yet.
+//!
+//! Once memory management is available, the other virtual terminals are allocated. The init TTY
+//! is the first virtual terminal (`tty1`), the others follow, up to [`VT_COUNT`].
 
 mod ansi;
 pub mod termios;
 
 use crate::device::serial;
+use crate::device::virtio;
+use crate::errno;
+use crate::errno::AllocResult;
 use crate::errno::Errno;
 use crate::file::blocking::BlockHandler;
+use crate::file::blocking::PollTable;
 use crate::memory::vmem;
 use crate::process::pid::Pid;
 use crate::process::signal::Signal;
@@ -24,6 +31,7 @@ use crate::util::lock::IntMutex;
 use crate::util::lock::MutexGuard;
 use crate::util::ptr::arc::Arc;
 use crate::vga;
+use crate::vga::font;
 use core::cmp::*;
 use core::mem::MaybeUninit;
 use core::ptr;
@@ -42,6 +50,14 @@ const TAB_SIZE: usize = 4;
 /// The maximum number of characters in the input buffer of a TTY.
 const INPUT_MAX: usize = 4096;
 
+/// The number of virtual terminals, including the init TTY.
+pub const VT_COUNT: usize = 6;
+
+/// Display mode: the TTY is rendered on screen.
+pub const KD_TEXT: i32 = 0;
+/// Display mode: the screen is left to userspace and the TTY isn't rendered.
+pub const KD_GRAPHICS: i32 = 1;
+
 /// The frequency of the bell in Hz.
 const BELL_FREQUENCY: u32 = 2000;
 /// The duraction of the bell in ms.
@@ -92,14 +108,22 @@ pub struct TTY {
 
 	/// The Y position of the screen in the history
 	screen_y: vga::Pos,
+	/// The number of lines the view is scrolled back from the screen.
+	scroll: vga::Pos,
 
 	/// The current color for the text to be written
 	current_color: vga::Color,
+	/// The bytes of the UTF-8 sequence being written.
+	utf8_buf: [u8; 4],
+	/// The number of bytes in `utf8_buf`.
+	utf8_len: usize,
 
 	/// The content of the TTY's history
 	history: [vga::Char; HISTORY_SIZE],
 	/// Tells whether TTY updates are enabled or not
 	update: bool,
+	/// The display mode, either [`KD_TEXT`] or [`KD_GRAPHICS`].
+	mode: i32,
 
 	/// The buffer containing characters from TTY input.
 	input_buffer: [u8; INPUT_MAX],
@@ -182,6 +206,22 @@ pub fn current() -> Option<TTYHandle> {
 	get(*CURRENT_TTY.lock())
 }
 
+/// Returns the ID of the TTY of the virtual terminal with number `num`, starting at `1`.
+///
+/// If the virtual terminal doesn't exist, the function returns `None`.
+pub fn vt_id(num: usize) -> Option<Option<usize>> {
+	match num {
+		1 => Some(None),
+		2..=VT_COUNT => Some(Some(num - 2)),
+		_ => None,
+	}
+}
+
+/// Returns the number of the virtual terminal currently displayed on screen.
+pub fn current_vt() -> usize {
+	CURRENT_TTY.lock().map(|id| id + 2).unwrap_or(1)
+}
+
 /// Initializes the init TTY.
 pub fn init() {
 	let init_tty_mutex = get(None).unwrap();
@@ -191,6 +231,17 @@ pub fn init() {
 	init_tty.show();
 }
 
+/// Allocates the virtual terminals following the init TTY.
+pub fn init_vts() -> AllocResult<()> {
+	let mut ttys = TTYS.lock();
+	for id in ttys.len()..(VT_COUNT - 1) {
+		let mut tty = unsafe { MaybeUninit::<TTY>::zeroed().assume_init() };
+		tty.init(Some(id));
+		ttys.push(Arc::new(IntMutex::new(tty))?)?;
+	}
+	Ok(())
+}
+
 /// Switches to TTY with id `id`.
 ///
 /// If `id` is `None`, the init TTY is used.
@@ -216,11 +267,14 @@ impl TTY {
 		self.cursor_visible = true;
 
 		self.screen_y = 0;
+		self.scroll = 0;
 
 		self.current_color = vga::DEFAULT_COLOR;
+		self.utf8_len = 0;
 
 		self.history = [(vga::DEFAULT_COLOR as vga::Char) << 8; HISTORY_SIZE];
 		self.update = true;
+		self.mode = KD_TEXT;
 
 		self.ansi_buffer = ansi::ANSIBuffer::new();
 
@@ -241,14 +295,39 @@ impl TTY {
 		self.id
 	}
 
+	/// Returns the number of the TTY's virtual terminal.
+	pub fn get_vt(&self) -> usize {
+		self.id.map(|id| id + 2).unwrap_or(1)
+	}
+
+	/// Returns the display mode of the TTY.
+	pub fn get_mode(&self) -> i32 {
+		self.mode
+	}
+
+	/// Sets the display mode of the TTY.
+	///
+	/// If the mode is invalid, the function returns [`errno::EINVAL`].
+	pub fn set_mode(&mut self, mode: i32) -> Result<(), Errno> {
+		if !matches!(mode, KD_TEXT | KD_GRAPHICS) {
+			return Err(errno!(EINVAL));
+		}
+		self.mode = mode;
+		if mode == KD_TEXT {
+			self.show();
+		}
+		Ok(())
+	}
+
 	/// Updates the TTY to the screen.
 	pub fn update(&mut self) {
 		let current_tty = *CURRENT_TTY.lock();
-		if self.id != current_tty || !self.update {
+		if self.id != current_tty || !self.update || self.mode != KD_TEXT {
 			return;
 		}
 
-		let buff = &self.history[get_history_offset(0, self.screen_y)];
+		let view_y = self.screen_y - self.scroll;
+		let buff = &self.history[get_history_offset(0, view_y)];
 		unsafe {
 			vmem::write_lock_wrap(|| {
 				ptr::copy_nonoverlapping(
@@ -259,12 +338,31 @@ impl TTY {
 			});
 		}
 
-		let y = self.cursor_y - self.screen_y;
-		vga::move_cursor(self.cursor_x, y);
+		let y = self.cursor_y - view_y;
+		if y < vga::HEIGHT {
+			vga::move_cursor(self.cursor_x, y);
+		}
+	}
+
+	/// Scrolls the view `n` lines back into the history.
+	pub fn scroll_up(&mut self, n: usize) {
+		let n = min(n, self.screen_y as usize) as vga::Pos;
+		self.scroll = min(self.scroll + n, self.screen_y);
+		self.update();
+	}
+
+	/// Scrolls the view `n` lines forward, towards the screen.
+	pub fn scroll_down(&mut self, n: usize) {
+		let n = min(n, self.scroll as usize) as vga::Pos;
+		self.scroll -= n;
+		self.update();
 	}
 
 	/// Shows the TTY on screen.
 	pub fn show(&mut self) {
+		if self.mode != KD_TEXT {
+			return;
+		}
 		self.set_cursor_visible(self.cursor_visible);
 		self.update();
 	}
@@ -330,6 +428,7 @@ impl TTY {
 		self.cursor_x = 0;
 		self.cursor_y = 0;
 		self.screen_y = 0;
+		self.scroll = 0;
 		for i in 0..self.history.len() {
 			self.history[i] = (vga::DEFAULT_COLOR as vga::Char) << 8;
 		}
@@ -417,8 +516,58 @@ impl TTY {
 		// TODO
 	}
 
+	/// Draws the glyph `glyph` at the cursor's position.
+	fn put_glyph(&mut self, glyph: u8) {
+		let tty_char = (glyph as vga::Char) | ((self.current_color as vga::Char) << 8);
+		let pos = get_history_offset(self.cursor_x, self.cursor_y);
+		self.history[pos] = tty_char;
+		self.cursor_forward(1, 0);
+	}
+
+	/// Writes the byte `c` of a UTF-8 sequence to the TTY.
+	///
+	/// The character is drawn once the sequence is complete. Invalid sequences are drawn with the
+	/// replacement character.
+	fn putchar_utf8(&mut self, c: u8) {
+		let continuation = c & 0xc0 == 0x80;
+		if !continuation && self.utf8_len > 0 {
+			// The previous sequence is truncated
+			self.utf8_len = 0;
+			self.put_glyph(font::glyph(char::REPLACEMENT_CHARACTER));
+		}
+		if continuation && self.utf8_len == 0 {
+			self.put_glyph(font::glyph(char::REPLACEMENT_CHARACTER));
+			return;
+		}
+
+		self.utf8_buf[self.utf8_len] = c;
+		self.utf8_len += 1;
+		let len = match self.utf8_buf[0] {
+			0xc2..=0xdf => 2,
+			0xe0..=0xef => 3,
+			0xf0..=0xf4 => 4,
+			_ => 0,
+		};
+		if self.utf8_len < len {
+			return;
+		}
+		let seq = &self.utf8_buf[..self.utf8_len];
+		self.utf8_len = 0;
+		let c = core::str::from_utf8(seq)
+			.ok()
+			.and_then(|s| s.chars().next())
+			.unwrap_or(char::REPLACEMENT_CHARACTER);
+		self.put_glyph(font::glyph(c));
+	}
+
 	/// Writes the character `c` to the TTY.
 	fn putchar(&mut self, mut c: u8) {
+		if c >= 0x80 {
+			self.putchar_utf8(c);
+			return;
+		}
+		self.utf8_len = 0;
+
 		if self.termios.c_oflag & termios::OLCUC != 0 && (c as char).is_ascii_uppercase() {
 			c = (c as char).to_ascii_lowercase() as u8;
 		}
@@ -442,12 +591,7 @@ impl TTY {
 			b'\r' => self.cursor_x = 0,
 			0x08 | 0x7f => self.cursor_backward(1, 0),
 
-			_ => {
-				let tty_char = (c as vga::Char) | ((self.current_color as vga::Char) << 8);
-				let pos = get_history_offset(self.cursor_x, self.cursor_y);
-				self.history[pos] = tty_char;
-				self.cursor_forward(1, 0);
-			}
+			_ => self.put_glyph(c),
 		}
 	}
 
@@ -457,6 +601,10 @@ impl TTY {
 		if let Some(serial) = serial::get(serial::COM1) {
 			serial.lock().write(buffer);
 		}
+		virtio::console::write_console(buffer);
+
+		// Writing brings the view back to the screen
+		self.scroll = 0;
 
 		let mut i = 0;
 		while i < buffer.len() {
@@ -739,4 +887,11 @@ impl TTY {
 	pub fn add_waiting_process(&mut self, proc: &mut Process, mask: u32) -> Result<(), Errno> {
 		self.block_handler.add_waiting_process(proc, mask)
 	}
+
+	/// Registers the given poll table on the TTY.
+	///
+	/// `mask` is the mask of poll event to wait for.
+	pub fn register_poll(&mut self, table: &PollTable, mask: u32) -> AllocResult<()> {
+		self.block_handler.register(table, mask)
+	}
 }
@@ -9,6 +9,8 @@ use crate::util::TryClone;
 use crate::util::TryDefault;
 use core::borrow::{Borrow, BorrowMut};
 use core::fmt;
+use core::hash::Hash;
+use core::hash::Hasher;
 use core::marker::Unsize;
 use core::mem;
 use core::mem::{size_of_val, ManuallyDrop};
@@ -158,6 +160,20 @@ impl<T: ?Sized + Unsize<U>, U: ?Sized> CoerceUnsized<Box<U>> for Box<T> {}
 
 impl<T: ?Sized + Unsize<U>, U: ?Sized> DispatchFromDyn<Box<U>> for Box<T> {}
 
+impl<T: ?Sized + PartialEq> PartialEq for Box<T> {
+	fn eq(&self, other: &Self) -> bool {
+		self.as_ref() == other.as_ref()
+	}
+}
+
+impl<T: ?Sized + Eq> Eq for Box<T> {}
+
+impl<T: ?Sized + Hash> Hash for Box<T> {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		self.as_ref().hash(state)
+	}
+}
+
 impl<T: ?Sized + fmt::Display> fmt::Display for Box<T> {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 		write!(f, "{}", self.as_ref())
@@ -5,6 +5,8 @@ use crate::util::bit_size_of;
 use crate::util::container::vec::Vec;
 use crate::util::math::ceil_div;
 use crate::util::TryClone;
+use core::cmp::min;
+use core::ops::Range;
 
 /// A bitfield is a data structure meant to contain only boolean values.
 ///
@@ -99,6 +101,28 @@ impl Bitfield {
 		(0..self.len).find(|i| !self.is_set(*i))
 	}
 
+	/// Finds a clear bit in the given range of offsets.
+	///
+	/// Bytes with every bits set are skipped at once.
+	///
+	/// The function returns the offset to the bit. If none is found, the function returns `None`.
+	pub fn find_clear_in(&self, range: Range<usize>) -> Option<usize> {
+		let end = min(range.end, self.len);
+		let mut i = range.start;
+		while i < end {
+			let byte = self.data[i / bit_size_of::<u8>()];
+			if i % bit_size_of::<u8>() == 0 && byte == !0 {
+				i += bit_size_of::<u8>();
+				continue;
+			}
+			if !self.is_set(i) {
+				return Some(i);
+			}
+			i += 1;
+		}
+		None
+	}
+
 	/// Clears every elements in the bitfield.
 	pub fn clear_all(&mut self) {
 		self.data.fill(0);
@@ -194,5 +218,18 @@ mod test {
 		}
 	}
 
+	#[test_case]
+	fn bitfield_find_clear_in0() {
+		let mut bitfield = Bitfield::new(42).unwrap();
+		for i in 0..20 {
+			bitfield.set(i);
+		}
+
+		assert_eq!(bitfield.find_clear_in(0..42), Some(20));
+		assert_eq!(bitfield.find_clear_in(25..42), Some(25));
+		assert_eq!(bitfield.find_clear_in(0..20), None);
+		assert_eq!(bitfield.find_clear_in(40..100), Some(40));
+	}
+
 	// TODO Write more tests
 }
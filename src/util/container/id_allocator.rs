@@ -4,6 +4,7 @@
 use crate::errno::AllocError;
 use crate::errno::AllocResult;
 use crate::util::container::bitfield::Bitfield;
+use core::ops::Range;
 
 /// Structure representing an identifier allocator.
 pub struct IDAllocator {
@@ -50,6 +51,19 @@ impl IDAllocator {
 		}
 	}
 
+	/// Allocates the first free identifier in the given range.
+	///
+	/// If no identifier is free in the range, the function returns an error.
+	#[must_use = "not freeing a PID shall cause a leak"]
+	pub fn alloc_in(&mut self, range: Range<u32>) -> AllocResult<u32> {
+		let i = self
+			.used
+			.find_clear_in((range.start as _)..(range.end as _))
+			.ok_or(AllocError)?;
+		self.used.set(i);
+		Ok(i as _)
+	}
+
 	/// Frees the given identifier `id`.
 	pub fn free(&mut self, id: u32) {
 		if id <= self.used.len() as _ {
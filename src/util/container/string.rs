@@ -1,11 +1,14 @@
 //! This module implements the String structure which wraps the `str` type.
 
 use crate::errno::AllocResult;
+use crate::errno::CollectResult;
 use crate::util::container::vec::Vec;
 use crate::util::AllocError;
+use crate::util::DisplayableStr;
 use crate::util::TryClone;
 use core::borrow::Borrow;
 use core::borrow::BorrowMut;
+use core::cmp::min;
 use core::fmt;
 use core::fmt::Debug;
 use core::fmt::Write;
@@ -86,23 +89,10 @@ impl String {
 		self.data.push(b)
 	}
 
-	/// Appends the given char `ch` to the end of the string.
+	/// Appends the given char `ch` to the end of the string, encoded in UTF-8.
 	pub fn push_char(&mut self, ch: char) -> AllocResult<()> {
-		if ch.len_utf8() == 1 {
-			return self.data.push(ch as u8);
-		}
-
-		let val = ch as u32;
-		for i in 0..4 {
-			let b = ((val >> (8 * i)) & 0xff) as u8;
-			if let Err(e) = self.data.push(b) {
-				// Cancelling previous iterations
-				self.data.truncate(self.data.len() - i);
-				return Err(e);
-			}
-		}
-
-		Ok(())
+		let mut buf = [0; 4];
+		self.push_str(ch.encode_utf8(&mut buf))
 	}
 
 	/// Removes the last byte from the string and returns it.
@@ -124,6 +114,36 @@ impl String {
 	pub fn clear(&mut self) {
 		self.data.clear();
 	}
+
+	/// Appends the bytes of the given iterator to the end of the string.
+	///
+	/// If an allocation fails, the string is left unchanged.
+	pub fn try_extend<I: IntoIterator<Item = u8>>(&mut self, iter: I) -> AllocResult<()> {
+		let len = self.len();
+		for b in iter {
+			if let Err(e) = self.push(b) {
+				// Cancelling previous iterations
+				self.data.truncate(len);
+				return Err(e);
+			}
+		}
+		Ok(())
+	}
+
+	/// Appends the chars of the given iterator to the end of the string, encoded in UTF-8.
+	///
+	/// If an allocation fails, the string is left unchanged.
+	pub fn try_extend_chars<I: IntoIterator<Item = char>>(&mut self, iter: I) -> AllocResult<()> {
+		let len = self.len();
+		for ch in iter {
+			if let Err(e) = self.push_char(ch) {
+				// Cancelling previous iterations
+				self.data.truncate(len);
+				return Err(e);
+			}
+		}
+		Ok(())
+	}
 }
 
 impl TryFrom<&[u8]> for String {
@@ -237,25 +257,36 @@ impl TryClone for String {
 	}
 }
 
-// TODO Iterators
+impl FromIterator<u8> for CollectResult<String> {
+	fn from_iter<I: IntoIterator<Item = u8>>(iter: I) -> Self {
+		let res = iter
+			.into_iter()
+			.collect::<CollectResult<Vec<u8>>>()
+			.0
+			.map(|data| String {
+				data,
+			});
+		Self(res)
+	}
+}
+
+impl FromIterator<char> for CollectResult<String> {
+	fn from_iter<I: IntoIterator<Item = char>>(iter: I) -> Self {
+		let mut s = String::new();
+		let res = s.try_extend_chars(iter).map(|_| s);
+		Self(res)
+	}
+}
 
 impl Debug for String {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-		for b in self.as_bytes() {
-			f.write_char(*b as char)?;
-		}
-
-		Ok(())
+		fmt::Debug::fmt(&DisplayableStr(self.as_bytes()), f)
 	}
 }
 
 impl fmt::Display for String {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-		for b in self.as_bytes() {
-			f.write_char(*b as char)?;
-		}
-
-		Ok(())
+		fmt::Display::fmt(&DisplayableStr(self.as_bytes()), f)
 	}
 }
 
@@ -299,10 +330,115 @@ macro_rules! format {
 	}};
 }
 
+/// Writer used to turn a format into a fixed-size buffer, without allocating.
+struct BufferWriter<'b> {
+	/// The buffer to write into.
+	buf: &'b mut [u8],
+	/// The number of bytes written so far.
+	len: usize,
+}
+
+impl<'b> Write for BufferWriter<'b> {
+	fn write_str(&mut self, s: &str) -> Result<(), fmt::Error> {
+		let avail = self.buf.len() - self.len;
+		let mut n = min(avail, s.len());
+		// Do not cut a character in half
+		while !s.is_char_boundary(n) {
+			n -= 1;
+		}
+		self.buf[self.len..(self.len + n)].copy_from_slice(&s.as_bytes()[..n]);
+		self.len += n;
+		if n < s.len() {
+			Err(fmt::Error)
+		} else {
+			Ok(())
+		}
+	}
+}
+
+/// Writes the given format into the buffer `buf`, then returns the written string.
+///
+/// Since this function does not allocate memory, it can be used on paths where allocations are
+/// not possible, such as kernel panics.
+///
+/// If the buffer is too small, the output is truncated on a character boundary.
+pub fn format_into<'b>(buf: &'b mut [u8], args: fmt::Arguments) -> &'b str {
+	let mut w = BufferWriter {
+		buf,
+		len: 0,
+	};
+	// Truncation is the only possible error
+	let _ = fmt::write(&mut w, args);
+	let BufferWriter {
+		buf,
+		len,
+	} = w;
+	// Safe because only complete characters have been written
+	unsafe { str::from_utf8_unchecked(&buf[..len]) }
+}
+
+/// Writes the given format into the given buffer, without allocating. See [`format_into`].
+#[macro_export]
+macro_rules! format_into {
+	($buf:expr, $($arg:tt)*) => {{
+		$crate::util::container::string::format_into($buf, format_args!($($arg)*))
+	}};
+}
+
 #[cfg(test)]
 mod test {
 	use super::*;
 
+	#[test_case]
+	fn string_display_utf8() {
+		let s = String::try_from(&b"caf\xc3\xa9 \xd0\xaf\xff\xe2\x82"[..]).unwrap();
+		let formatted = crate::format!("{s}").unwrap();
+		assert_eq!(formatted.as_bytes(), "café Я\u{fffd}\u{fffd}".as_bytes());
+	}
+
+	#[test_case]
+	fn string_debug() {
+		let s = String::try_from(&b"a\"b'\n\xff"[..]).unwrap();
+		let formatted = crate::format!("{s:?}").unwrap();
+		assert_eq!(formatted.as_bytes(), b"\"a\\\"b'\\n\\xff\"");
+	}
+
+	#[test_case]
+	fn string_push_char() {
+		let mut s = String::new();
+		s.push_char('é').unwrap();
+		s.push_char('€').unwrap();
+		assert_eq!(s, "é€");
+	}
+
+	#[test_case]
+	fn string_collect() {
+		let s = "abc"
+			.chars()
+			.rev()
+			.collect::<CollectResult<String>>()
+			.0
+			.unwrap();
+		assert_eq!(s, "cba");
+		let s = b"xyz"
+			.iter()
+			.copied()
+			.collect::<CollectResult<String>>()
+			.0
+			.unwrap();
+		assert_eq!(s, "xyz");
+	}
+
+	#[test_case]
+	fn string_format_into() {
+		let mut buf = [0u8; 8];
+		assert_eq!(crate::format_into!(&mut buf, "{}-{}", 12, "ab"), "12-ab");
+		let mut buf = [0u8; 4];
+		assert_eq!(crate::format_into!(&mut buf, "ab{}", 'é'), "abé");
+		let mut buf = [0u8; 3];
+		assert_eq!(crate::format_into!(&mut buf, "ab{}", 'é'), "ab");
+	}
+
 	#[test_case]
 	fn string_push0() {
 		let mut s = String::new();
@@ -371,6 +371,14 @@ impl<T> Vec<T> {
 		}
 	}
 
+	/// Reduces the capacity of the vector to its length, releasing unused memory.
+	pub fn shrink_to_fit(&mut self) -> AllocResult<()> {
+		if self.capacity() > self.len {
+			self.realloc(self.len)?;
+		}
+		Ok(())
+	}
+
 	/// Clears the vector, removing all values.
 	pub fn clear(&mut self) {
 		for e in self.as_mut_slice() {
@@ -392,6 +400,11 @@ impl<T: Default> Vec<T> {
 			self.truncate(new_len);
 		} else {
 			self.increase_capacity(new_len - self.len)?;
+			for i in self.len..new_len {
+				unsafe {
+					ptr::write(&mut self.data.as_mut().unwrap()[i], T::default());
+				}
+			}
 			self.len = new_len;
 		}
 
@@ -939,6 +952,20 @@ mod test {
 		assert_eq!(v.len(), 0);
 	}
 
+	#[test_case]
+	fn vec_shrink_to_fit() {
+		let mut v = Vec::<usize>::with_capacity(16).unwrap();
+		v.push(1).unwrap();
+		v.push(2).unwrap();
+		v.shrink_to_fit().unwrap();
+		assert_eq!(v.capacity(), 2);
+		assert_eq!(v.as_slice(), &[1, 2]);
+
+		v.clear();
+		v.shrink_to_fit().unwrap();
+		assert_eq!(v.capacity(), 0);
+	}
+
 	// TODO Test resize
 
 	// TODO Test range functions
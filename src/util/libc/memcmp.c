@@ -16,9 +16,9 @@ int memcmp(const void *s1, const void *s2, size_t n)
 		&& !(IS_ALIGNED(s1, sizeof(long)) && IS_ALIGNED(s2, sizeof(long)))
 		&& ((volatile char *) s1)[i] == ((volatile char *) s2)[i])
 		++i;
-	while (i < align_end
+	while (i + sizeof(long) <= align_end
 		&& *((volatile long *) (s1 + i)) == *((volatile long *) (s2 + i)))
-		++i;
+		i += sizeof(long);
 	while (i < n
 		&& ((volatile char *) s1)[i] == ((volatile char *) s2)[i])
 		++i;
@@ -1,9 +1,28 @@
 .section .text
 
 .global memcpy
+.global memcpy_generic
+.global memcpy_erms
+.global memcpy_impl
 .type memcpy, @function
+.type memcpy_generic, @function
+.type memcpy_erms, @function
 
+/*
+ * Jumps to the implementation selected according to the CPU's features.
+ *
+ * There is no SSE2 implementation. The kernel is built without SSE, so vector registers may hold
+ * the state of a process, which is switched lazily. Using them would require saving this state
+ * around each copy, and a copy from or to userspace may fault and switch to another process in
+ * the middle of it. On CPUs with ERMS, `rep movsb` is as fast for large copies anyway.
+ */
 memcpy:
+	jmp *memcpy_impl
+
+/*
+ * Implementation copying 4 bytes at a time, working on every CPU.
+ */
+memcpy_generic:
 	push %esi
 	push %edi
 
@@ -40,3 +59,30 @@ end:
 	pop %edi
 	pop %esi
 	ret
+
+/*
+ * Implementation for CPUs with Enhanced REP MOVSB (ERMS), on which a single `rep movsb` is the
+ * fastest way to copy.
+ */
+memcpy_erms:
+	push %esi
+	push %edi
+
+	mov 12(%esp), %edi
+	mov 16(%esp), %esi
+	mov 20(%esp), %ecx
+
+	mov %edi, %eax
+	rep movsb
+
+	pop %edi
+	pop %esi
+	ret
+
+.section .data
+
+/*
+ * The implementation of `memcpy` in use. It is patched at boot.
+ */
+memcpy_impl:
+	.long memcpy_generic
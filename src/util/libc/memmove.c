@@ -5,18 +5,33 @@
 
 void *memcpy(void *dest, const void *src, size_t n);
 
-// TODO Optimize
 void *memmove(void *dest, const void *src, size_t n)
 {
-       void *begin = dest;
-       size_t i = 0;
+	char *d = dest;
+	const char *s = src;
 
-       if (dest < src)
-               return memcpy(dest, src, n);
-       while (i < n)
-       {
-               ((char *) dest)[n - i - 1] = ((char *) src)[n - i - 1];
-               ++i;
-       }
-       return begin;
+	// Copying forward is safe if the destination is before the source
+	if (d <= s || (size_t) (d - s) >= n)
+		return memcpy(dest, src, n);
+
+	// Copy backward, one word at a time if the distance allows it
+	while (n > 0 && !IS_ALIGNED(d + n, sizeof(long)))
+	{
+		--n;
+		d[n] = s[n];
+	}
+	if ((size_t) (d - s) >= sizeof(long))
+	{
+		while (n >= sizeof(long))
+		{
+			n -= sizeof(long);
+			*((long *) (d + n)) = *((const long *) (s + n));
+		}
+	}
+	while (n > 0)
+	{
+		--n;
+		d[n] = s[n];
+	}
+	return dest;
 }
@@ -1,10 +1,21 @@
 .section .text
 
 .global memset
+.global memset_generic
+.global memset_erms
+.global memset_impl
 .type memset, @function
+.type memset_generic, @function
+.type memset_erms, @function
 
-# Code taken from musl. License: https://git.musl-libc.org/cgit/musl/tree/COPYRIGHT
+/*
+ * Jumps to the implementation selected according to the CPU's features.
+ */
 memset:
+	jmp *memset_impl
+
+# Code taken from musl. License: https://git.musl-libc.org/cgit/musl/tree/COPYRIGHT
+memset_generic:
 	mov 12(%esp),%ecx
 	cmp $62,%ecx
 	ja 2f
@@ -78,3 +89,28 @@ memset:
 	sub %edx,%ecx
 	add %edx,%edi
 	jmp 1b
+
+/*
+ * Implementation for CPUs with Enhanced REP MOVSB (ERMS), on which a single `rep stosb` is the
+ * fastest way to fill large buffers. Small buffers are handled by the generic implementation.
+ */
+memset_erms:
+	mov 12(%esp), %ecx
+	cmp $62, %ecx
+	jbe memset_generic
+
+	mov %edi, %edx
+	mov 4(%esp), %edi
+	movzbl 8(%esp), %eax
+	rep stosb
+	mov %edx, %edi
+	mov 4(%esp), %eax
+	ret
+
+.section .data
+
+/*
+ * The implementation of `memset` in use. It is patched at boot.
+ */
+memset_impl:
+	.long memset_generic
@@ -17,6 +17,7 @@
 //! If an exception is raised while a mutex that disables interruptions is
 //! acquired, the behaviour is undefined.
 
+pub mod seqlock;
 pub mod spinlock;
 
 use crate::idt;
@@ -0,0 +1,89 @@
+//! A sequence lock allows readers to access data without ever blocking, at the cost of retrying
+//! when a write happened concurrently.
+//!
+//! The writer increments a sequence counter before and after modifying the data, so that the
+//! counter is odd while a write is in progress. A reader reads the counter, copies the data, then
+//! reads the counter again. If the counter was odd or has changed, the copy may be torn and the
+//! reader retries.
+//!
+//! Since readers never write to the lock, the data may be shared read-only with userspace.
+
+use crate::idt;
+use core::cell::UnsafeCell;
+use core::hint;
+use core::ptr;
+use core::sync::atomic::fence;
+use core::sync::atomic::AtomicU32;
+use core::sync::atomic::Ordering::Acquire;
+use core::sync::atomic::Ordering::Relaxed;
+use core::sync::atomic::Ordering::Release;
+
+/// A sequence lock.
+///
+/// The layout of the structure is stable: the sequence counter is located at the beginning,
+/// followed by the data.
+#[repr(C)]
+pub struct SeqLock<T: Copy> {
+	/// The sequence counter. Odd while a write is in progress.
+	seq: AtomicU32,
+	/// The protected data.
+	data: UnsafeCell<T>,
+}
+
+unsafe impl<T: Copy + Send> Sync for SeqLock<T> {}
+
+impl<T: Copy> SeqLock<T> {
+	/// Creates a new instance with the given data.
+	pub const fn new(data: T) -> Self {
+		Self {
+			seq: AtomicU32::new(0),
+			data: UnsafeCell::new(data),
+		}
+	}
+
+	/// Returns a copy of the data.
+	pub fn read(&self) -> T {
+		loop {
+			let seq = self.seq.load(Acquire);
+			if seq & 1 != 0 {
+				hint::spin_loop();
+				continue;
+			}
+
+			// Volatile since the data may be modified concurrently
+			let data = unsafe { ptr::read_volatile(self.data.get()) };
+			fence(Acquire);
+			if self.seq.load(Relaxed) == seq {
+				return data;
+			}
+		}
+	}
+
+	/// Modifies the data with the given closure.
+	///
+	/// Interrupts are disabled during the write so that a reader cannot run on the same core
+	/// while the write is in progress, which would never terminate.
+	pub fn write<R, F: FnOnce(&mut T) -> R>(&self, f: F) -> R {
+		idt::wrap_disable_interrupts(|| {
+			// Acquire the lock against other writers
+			let seq = loop {
+				let seq = self.seq.load(Relaxed);
+				if seq & 1 == 0
+					&& self
+						.seq
+						.compare_exchange_weak(seq, seq.wrapping_add(1), Acquire, Relaxed)
+						.is_ok()
+				{
+					break seq;
+				}
+				hint::spin_loop();
+			};
+			fence(Release);
+
+			let res = f(unsafe { &mut *self.data.get() });
+
+			self.seq.store(seq.wrapping_add(2), Release);
+			res
+		})
+	}
+}
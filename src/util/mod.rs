@@ -18,6 +18,7 @@ use core::fmt;
 use core::fmt::Write;
 use core::mem::size_of;
 use core::slice;
+use core::str;
 
 // C functions required by LLVM
 extern "C" {
@@ -190,18 +191,72 @@ impl<T: Default + Sized> TryDefault for T {
 
 /// Wrapper structure allowing to implement the Display trait on the [u8] type
 /// to display it as a string.
+///
+/// The bytes are decoded as UTF-8. Invalid sequences are displayed as the replacement character.
 pub struct DisplayableStr<'a>(pub &'a [u8]);
 
 impl<'a> fmt::Display for DisplayableStr<'a> {
 	fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-		for b in self.0 {
-			fmt.write_char(*b as char)?;
+		let mut bytes = self.0;
+		while !bytes.is_empty() {
+			match str::from_utf8(bytes) {
+				Ok(s) => {
+					fmt.write_str(s)?;
+					break;
+				}
+				Err(e) => {
+					let (valid, rest) = bytes.split_at(e.valid_up_to());
+					// Safe because the bytes have been validated
+					fmt.write_str(unsafe { str::from_utf8_unchecked(valid) })?;
+					fmt.write_char(char::REPLACEMENT_CHARACTER)?;
+					// If the sequence is truncated, the remaining bytes are all invalid
+					let invalid_len = e.error_len().unwrap_or(rest.len());
+					bytes = &rest[invalid_len..];
+				}
+			}
 		}
 
 		Ok(())
 	}
 }
 
+/// Displays the bytes as a quoted string, escaping special characters. Invalid UTF-8 sequences are
+/// displayed as hexadecimal escapes.
+impl<'a> fmt::Debug for DisplayableStr<'a> {
+	fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+		fmt.write_char('"')?;
+		let mut bytes = self.0;
+		while !bytes.is_empty() {
+			let (valid, invalid) = match str::from_utf8(bytes) {
+				Ok(s) => (s, &[][..]),
+				Err(e) => {
+					let (valid, rest) = bytes.split_at(e.valid_up_to());
+					let invalid_len = e.error_len().unwrap_or(rest.len());
+					// Safe because the bytes have been validated
+					(
+						unsafe { str::from_utf8_unchecked(valid) },
+						&rest[..invalid_len],
+					)
+				}
+			};
+			for c in valid.chars() {
+				// Unlike `escape_debug`, do not escape single quotes inside of a double-quoted
+				// string
+				if c == '\'' {
+					fmt.write_char(c)?;
+				} else {
+					write!(fmt, "{}", c.escape_debug())?;
+				}
+			}
+			for b in invalid {
+				write!(fmt, "\\x{b:02x}")?;
+			}
+			bytes = &bytes[(valid.len() + invalid.len())..];
+		}
+		fmt.write_char('"')
+	}
+}
+
 /// Structure used to store data given the given memory alignment.
 #[repr(C)]
 pub struct Aligned<Align, Data: ?Sized> {
@@ -306,5 +361,28 @@ mod test {
 
 	// TODO More tests on memcmp
 
-	// TODO Test `memset`
+	#[test_case]
+	fn memmove_overlap() {
+		let mut buf: [u8; 200] = [0; 200];
+		for (i, b) in buf.iter_mut().enumerate() {
+			*b = i as _;
+		}
+		unsafe {
+			memmove(buf.as_mut_ptr().add(3) as _, buf.as_ptr() as _, 150);
+		}
+		for i in 0..150 {
+			assert_eq!(buf[i + 3], i as u8);
+		}
+	}
+
+	#[test_case]
+	fn memset0() {
+		let mut buf: [u8; 300] = [0; 300];
+		unsafe {
+			memset(buf.as_mut_ptr().add(1) as _, 0xaa, 298);
+		}
+		assert_eq!(buf[0], 0);
+		assert!(buf[1..299].iter().all(|b| *b == 0xaa));
+		assert_eq!(buf[299], 0);
+	}
 }
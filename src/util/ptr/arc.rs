@@ -157,6 +157,11 @@ impl<T: ?Sized> Arc<T> {
 		});
 	}
 
+	/// Returns the number of strong references to the object.
+	pub fn strong_count(this: &Self) -> usize {
+		this.inner().strong.load(atomic::Ordering::Relaxed)
+	}
+
 	/// Returns a pointer to the inner object.
 	pub fn as_ptr(&self) -> *const T {
 		&self.inner().obj
@@ -253,6 +258,13 @@ impl<T: ?Sized> Weak<T> {
 		unsafe { self.inner.as_ref() }
 	}
 
+	/// Returns the number of strong references to the object.
+	///
+	/// If the value has already been dropped, the function returns zero.
+	pub fn strong_count(&self) -> usize {
+		self.inner().strong.load(atomic::Ordering::Relaxed)
+	}
+
 	/// Attempts to upgrade into an `Arc`.
 	///
 	/// If the value has already been dropped, the function returns `None`.
@@ -284,6 +296,12 @@ impl<T: ?Sized> Clone for Weak<T> {
 	}
 }
 
+impl<T: ?Sized> fmt::Debug for Weak<T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "(Weak)")
+	}
+}
+
 impl<T: ?Sized> Drop for Weak<T> {
 	fn drop(&mut self) {
 		let inner = self.inner();
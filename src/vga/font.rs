@@ -0,0 +1,354 @@
+//! Console fonts, in the PC Screen Font (PSF) format.
+//!
+//! The VGA text mode draws characters using a font of up to 256 glyphs stored in the plane 2 of
+//! the video memory. A Unicode map tells which glyph is used to draw each character.
+//!
+//! Until a font is loaded, the firmware's font is used, which follows the code page 437.
+
+use crate::errno;
+use crate::errno::AllocResult;
+use crate::errno::EResult;
+use crate::file::path::Path;
+use crate::file::perm::AccessProfile;
+use crate::file::vfs;
+use crate::io;
+use crate::memory;
+use crate::memory::vmem;
+use crate::util::container::vec::Vec;
+use crate::util::io::IO;
+use crate::util::lock::IntMutex;
+
+/// The magic number of PSF version 1.
+const PSF1_MAGIC: [u8; 2] = [0x36, 0x04];
+/// PSF1 mode: the font has 512 glyphs.
+const PSF1_MODE512: u8 = 0x01;
+/// PSF1 mode: the font has a Unicode table.
+const PSF1_MODEHASTAB: u8 = 0x02;
+/// PSF1 mode: the font has a Unicode table, with sequences.
+const PSF1_MODEHASSEQ: u8 = 0x04;
+/// PSF1 Unicode table: the end of a glyph's entries.
+const PSF1_SEPARATOR: u16 = 0xffff;
+/// PSF1 Unicode table: the beginning of a sequence.
+const PSF1_STARTSEQ: u16 = 0xfffe;
+
+/// The magic number of PSF version 2.
+const PSF2_MAGIC: [u8; 4] = [0x72, 0xb5, 0x4a, 0x86];
+/// PSF2 flag: the font has a Unicode table.
+const PSF2_HAS_UNICODE_TABLE: u32 = 0x01;
+/// PSF2 Unicode table: the end of a glyph's entries.
+const PSF2_SEPARATOR: u8 = 0xff;
+/// PSF2 Unicode table: the beginning of a sequence.
+const PSF2_STARTSEQ: u8 = 0xfe;
+
+/// The maximum number of glyphs the VGA text mode can use.
+pub const MAX_GLYPHS: usize = 256;
+/// The maximum height of a glyph in pixels.
+pub const MAX_HEIGHT: usize = 16;
+/// The number of bytes between two glyphs in the plane 2 of the video memory.
+const GLYPH_STRIDE: usize = 32;
+/// The physical address at which the plane 2 of the video memory is accessed.
+const FONT_PHYS: usize = 0xa0000;
+/// The maximum size of a font file in bytes.
+const MAX_FILE_SIZE: u64 = 65536;
+
+/// The glyph used when a character cannot be drawn with the current font.
+const REPLACEMENT_GLYPH: u8 = b'?';
+
+/// The characters of the upper half of the code page 437, used by the firmware's font.
+const CP437: [char; 128] = [
+	'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å', //
+	'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ', //
+	'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º', '¿', '⌐', '¬', '½', '¼', '¡', '«', '»', //
+	'░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕', '╣', '║', '╗', '╝', '╜', '╛', '┐', //
+	'└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩', '╦', '╠', '═', '╬', '╧', //
+	'╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘', '┌', '█', '▄', '▌', '▐', '▀', //
+	'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ', 'Φ', 'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩', //
+	'≡', '±', '≥', '≤', '⌠', '⌡', '÷', '≈', '°', '∙', '·', '√', 'ⁿ', '²', '■', '\u{a0}',
+];
+
+/// The Unicode map of the current font, sorted by character. If empty, the code page 437 is used.
+static UNICODE_MAP: IntMutex<Vec<(char, u8)>> = IntMutex::new(Vec::new());
+
+/// A console font.
+pub struct Font {
+	/// The height of a glyph in pixels. The width is always `8`.
+	height: usize,
+	/// The glyphs' bitmaps, each being `height` bytes long.
+	glyphs: Vec<u8>,
+	/// The Unicode map of the font, associating characters to glyphs.
+	unicode: Vec<(char, u8)>,
+}
+
+/// Returns the `u32` at offset `off` in `data`, in little endian.
+fn read_u32(data: &[u8], off: usize) -> EResult<u32> {
+	let bytes = data.get(off..(off + 4)).ok_or_else(|| errno!(EINVAL))?;
+	Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+impl Font {
+	/// Creates a font from raw glyphs.
+	///
+	/// Arguments:
+	/// - `height` is the height of a glyph in pixels.
+	/// - `stride` is the number of bytes between the beginning of two glyphs in `data`.
+	/// - `count` is the number of glyphs.
+	/// - `data` is the glyphs' bitmaps.
+	///
+	/// If the glyphs cannot be used by the VGA text mode, the function returns
+	/// [`errno::EINVAL`].
+	pub fn from_raw(height: usize, stride: usize, count: usize, data: &[u8]) -> EResult<Self> {
+		if height == 0 || height > MAX_HEIGHT || stride < height || count > MAX_GLYPHS {
+			return Err(errno!(EINVAL));
+		}
+		let mut glyphs = Vec::with_capacity(count * height)?;
+		for i in 0..count {
+			let begin = i * stride;
+			let glyph = data
+				.get(begin..(begin + height))
+				.ok_or_else(|| errno!(EINVAL))?;
+			glyphs.extend_from_slice(glyph)?;
+		}
+		Ok(Self {
+			height,
+			glyphs,
+			unicode: Vec::new(),
+		})
+	}
+
+	/// Parses the PSF font `data`, in version 1 or 2.
+	///
+	/// The VGA text mode can use only the first [`MAX_GLYPHS`] glyphs, so others are ignored.
+	///
+	/// If the font is invalid or cannot be used by the VGA text mode, the function returns
+	/// [`errno::EINVAL`].
+	pub fn parse(data: &[u8]) -> EResult<Self> {
+		if data.starts_with(&PSF1_MAGIC) {
+			Self::parse_psf1(data)
+		} else if data.starts_with(&PSF2_MAGIC) {
+			Self::parse_psf2(data)
+		} else {
+			Err(errno!(EINVAL))
+		}
+	}
+
+	/// Parses a font in PSF version 1.
+	fn parse_psf1(data: &[u8]) -> EResult<Self> {
+		let (Some(mode), Some(height)) = (data.get(2), data.get(3)) else {
+			return Err(errno!(EINVAL));
+		};
+		let height = *height as usize;
+		let count = if mode & PSF1_MODE512 != 0 { 512 } else { 256 };
+		let glyphs_data = &data[4..];
+		let mut font = Self::from_raw(height, height, count.min(MAX_GLYPHS), glyphs_data)?;
+		if mode & (PSF1_MODEHASTAB | PSF1_MODEHASSEQ) == 0 {
+			return Ok(font);
+		}
+
+		// Unicode table
+		let table = glyphs_data
+			.get((count * height)..)
+			.ok_or_else(|| errno!(EINVAL))?;
+		let mut entries = table
+			.chunks_exact(2)
+			.map(|c| u16::from_le_bytes([c[0], c[1]]));
+		for glyph in 0..count {
+			let mut in_seq = false;
+			loop {
+				let entry = entries.next().ok_or_else(|| errno!(EINVAL))?;
+				match entry {
+					PSF1_SEPARATOR => break,
+					PSF1_STARTSEQ => in_seq = true,
+					_ if in_seq || glyph >= MAX_GLYPHS => {}
+					_ => {
+						if let Some(c) = char::from_u32(entry as _) {
+							font.unicode.push((c, glyph as _))?;
+						}
+					}
+				}
+			}
+		}
+		Ok(font)
+	}
+
+	/// Parses a font in PSF version 2.
+	fn parse_psf2(data: &[u8]) -> EResult<Self> {
+		let header_size = read_u32(data, 8)? as usize;
+		let flags = read_u32(data, 12)?;
+		let count = read_u32(data, 16)? as usize;
+		let glyph_size = read_u32(data, 20)? as usize;
+		let height = read_u32(data, 24)? as usize;
+		let width = read_u32(data, 28)?;
+		if width != 8 || glyph_size != height {
+			return Err(errno!(EINVAL));
+		}
+		let glyphs_data = data.get(header_size..).ok_or_else(|| errno!(EINVAL))?;
+		let mut font = Self::from_raw(height, glyph_size, count.min(MAX_GLYPHS), glyphs_data)?;
+		if flags & PSF2_HAS_UNICODE_TABLE == 0 {
+			return Ok(font);
+		}
+
+		// Unicode table
+		let table_off = count
+			.checked_mul(glyph_size)
+			.ok_or_else(|| errno!(EINVAL))?;
+		let mut table = glyphs_data.get(table_off..).ok_or_else(|| errno!(EINVAL))?;
+		for glyph in 0..count.min(MAX_GLYPHS) {
+			let end = table
+				.iter()
+				.position(|b| *b == PSF2_SEPARATOR)
+				.ok_or_else(|| errno!(EINVAL))?;
+			// Sequences are ignored since each character is drawn with a single glyph
+			let entries = &table[..end];
+			let entries = match entries.iter().position(|b| *b == PSF2_STARTSEQ) {
+				Some(i) => &entries[..i],
+				None => entries,
+			};
+			let entries = core::str::from_utf8(entries).map_err(|_| errno!(EINVAL))?;
+			for c in entries.chars() {
+				font.unicode.push((c, glyph as _))?;
+			}
+			table = &table[(end + 1)..];
+		}
+		Ok(font)
+	}
+
+	/// Returns the number of glyphs in the font.
+	pub fn glyphs_count(&self) -> usize {
+		self.glyphs.len() / self.height
+	}
+}
+
+/// Returns the glyph used to draw the character `c` with the current font.
+pub fn glyph(c: char) -> u8 {
+	let map = UNICODE_MAP.lock();
+	if !map.is_empty() {
+		if let Ok(i) = map.binary_search_by_key(&c, |(c, _)| *c) {
+			return map[i].1;
+		}
+	}
+	if c.is_ascii() {
+		return c as u8;
+	}
+	if map.is_empty() {
+		if let Some(i) = CP437.iter().position(|c2| *c2 == c) {
+			return 0x80 + i as u8;
+		}
+	}
+	REPLACEMENT_GLYPH
+}
+
+/// Sets the Unicode map of the current font.
+///
+/// If `map` is empty, the code page 437 is used.
+pub fn set_unicode_map(mut map: Vec<(char, u8)>) {
+	map.sort_unstable_by_key(|(c, _)| *c);
+	*UNICODE_MAP.lock() = map;
+}
+
+/// Adds the entries `entries` to the Unicode map of the current font.
+pub fn add_unicode_map(entries: &[(char, u8)]) -> AllocResult<()> {
+	let mut map = UNICODE_MAP.lock();
+	map.extend_from_slice(entries)?;
+	map.sort_unstable_by_key(|(c, _)| *c);
+	Ok(())
+}
+
+/// Writes the glyphs of `font` to the video memory.
+fn write_glyphs(font: &Font) {
+	let base = (memory::PROCESS_END as usize + FONT_PHYS) as *mut u8;
+	unsafe {
+		// Giving access to plane 2
+		io::outw(0x3c4, 0x0402);
+		io::outw(0x3c4, 0x0704);
+		io::outw(0x3ce, 0x0204);
+		io::outw(0x3ce, 0x0005);
+		io::outw(0x3ce, 0x0406);
+
+		vmem::write_lock_wrap(|| {
+			for i in 0..MAX_GLYPHS {
+				let dst = base.add(i * GLYPH_STRIDE);
+				let glyph = font
+					.glyphs
+					.get((i * font.height)..((i + 1) * font.height))
+					.unwrap_or(&[]);
+				for j in 0..GLYPH_STRIDE {
+					dst.add(j)
+						.write_volatile(glyph.get(j).cloned().unwrap_or(0));
+				}
+			}
+		});
+
+		// Restoring the text mode's memory layout
+		io::outw(0x3c4, 0x0302);
+		io::outw(0x3c4, 0x0304);
+		io::outw(0x3ce, 0x0004);
+		io::outw(0x3ce, 0x1005);
+		io::outw(0x3ce, 0x0e06);
+	}
+}
+
+/// Loads `font` as the current font.
+///
+/// If the font has a Unicode map, it replaces the current one.
+pub fn load(font: Font) {
+	// Locking the map prevents the screen from being updated while the video memory is remapped
+	let mut map = UNICODE_MAP.lock();
+	write_glyphs(&font);
+	if !font.unicode.is_empty() {
+		let mut unicode = font.unicode;
+		unicode.sort_unstable_by_key(|(c, _)| *c);
+		*map = unicode;
+	}
+}
+
+/// Loads the PSF font in the file at `path` as the current font.
+pub fn load_file(path: &Path) -> EResult<()> {
+	let file_mutex = vfs::get_file_from_path(path, &AccessProfile::KERNEL, true)?;
+	let mut file = file_mutex.lock();
+	let size = file.get_size();
+	if size > MAX_FILE_SIZE {
+		return Err(errno!(EFBIG));
+	}
+
+	let mut buf = crate::vec![0u8; size as usize]?;
+	let mut off = 0;
+	while off < buf.len() {
+		let (len, eof) = file.read(off as _, &mut buf[off..])?;
+		off += len as usize;
+		if eof || len == 0 {
+			break;
+		}
+	}
+	drop(file);
+
+	let font = Font::parse(&buf[..off])?;
+	load(font);
+	Ok(())
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test_case]
+	fn font_parse_psf2() {
+		let mut data = crate::vec![0u8; 32].unwrap();
+		data[..4].copy_from_slice(&PSF2_MAGIC);
+		data[8..12].copy_from_slice(&32u32.to_le_bytes());
+		data[12..16].copy_from_slice(&PSF2_HAS_UNICODE_TABLE.to_le_bytes());
+		data[16..20].copy_from_slice(&2u32.to_le_bytes());
+		data[20..24].copy_from_slice(&16u32.to_le_bytes());
+		data[24..28].copy_from_slice(&16u32.to_le_bytes());
+		data[28..32].copy_from_slice(&8u32.to_le_bytes());
+		for _ in 0..32 {
+			data.push(0xaa).unwrap();
+		}
+		data.extend_from_slice(b"A\xff").unwrap();
+		data.extend_from_slice("Я".as_bytes()).unwrap();
+		data.extend_from_slice(b"\xfee\xcc\x81\xff").unwrap();
+
+		let font = Font::parse(&data).unwrap();
+		assert_eq!(font.glyphs_count(), 2);
+		assert_eq!(font.unicode.as_slice(), &[('A', 0), ('Я', 1)]);
+	}
+}
@@ -5,6 +5,8 @@
 //!
 //! Note: The VGA text mode runs only when booting with a Legacy BIOS.
 
+pub mod font;
+
 use crate::io;
 use crate::memory;
 use crate::memory::vmem;
@@ -7,6 +7,21 @@
 .global __vdso_gettimeofday
 .global __vdso_time
 
+/*
+ * The virtual address of the beginning of the text section. It must match the linker script.
+ */
+.set TEXT_ADDR, 0x1000
+
+/*
+ * Offsets of clocks in the data page. Each clock is made of the seconds on 64 bits, followed by
+ * the nanoseconds on 32 bits. The data page begins with the sequence counter.
+ */
+.set CLOCK_REALTIME_OFF, 8
+.set CLOCK_MONOTONIC_OFF, 24
+.set CLOCK_BOOTTIME_OFF, 40
+
+text_start:
+
 __kernel_vsyscall:
 	int $0x80
 	ret
@@ -19,14 +34,124 @@ __kernel_sigreturn:
 	# TODO
 	ud2
 
+/*
+ * Sets %edx to the address of the clocks' data page, which is mapped right before the vDSO.
+ */
+data_page:
+	call 1f
+1:
+	pop %edx
+	sub $(1b - text_start + TEXT_ADDR + 0x1000), %edx
+	ret
+
+/*
+ * Sets %ecx to the offset in the data page of the clock with the ID in %eax, or zero if the clock
+ * cannot be read from the vDSO.
+ */
+clock_offset:
+	mov $CLOCK_REALTIME_OFF, %ecx
+	cmp $0, %eax # CLOCK_REALTIME
+	je 1f
+	cmp $5, %eax # CLOCK_REALTIME_COARSE
+	je 1f
+	cmp $8, %eax # CLOCK_REALTIME_ALARM
+	je 1f
+	mov $CLOCK_MONOTONIC_OFF, %ecx
+	cmp $1, %eax # CLOCK_MONOTONIC
+	je 1f
+	cmp $6, %eax # CLOCK_MONOTONIC_COARSE
+	je 1f
+	mov $CLOCK_BOOTTIME_OFF, %ecx
+	cmp $7, %eax # CLOCK_BOOTTIME
+	je 1f
+	cmp $9, %eax # CLOCK_BOOTTIME_ALARM
+	je 1f
+	xor %ecx, %ecx
+1:
+	ret
+
+/*
+ * Reads the clock at offset %ecx in the data page, retrying while the kernel is updating it.
+ *
+ * On return, %eax contains the lower 32 bits of the seconds and %edx the nanoseconds. %ecx is
+ * clobbered.
+ */
+clock_read:
+	push %esi
+	push %edi
+	call data_page
+	add %edx, %ecx
+1:
+	mov (%edx), %esi
+	test $1, %esi
+	jz 2f
+	pause
+	jmp 1b
+2:
+	mov (%ecx), %eax
+	mov 8(%ecx), %edi
+	cmp (%edx), %esi
+	jne 1b
+	mov %edi, %edx
+	pop %edi
+	pop %esi
+	ret
+
 __vdso_clock_gettime:
-	# TODO
-	ud2
+	mov 4(%esp), %eax
+	call clock_offset
+	test %ecx, %ecx
+	jz 1f
+	call clock_read
+	mov 8(%esp), %ecx
+	mov %eax, (%ecx)
+	mov %edx, 4(%ecx)
+	xor %eax, %eax
+	ret
+1:
+	# Fallback to the system call
+	push %ebx
+	mov $0x109, %eax
+	mov 8(%esp), %ebx
+	mov 12(%esp), %ecx
+	int $0x80
+	pop %ebx
+	ret
 
 __vdso_gettimeofday:
-	# TODO
-	ud2
+	mov 4(%esp), %ecx
+	test %ecx, %ecx
+	jz 1f
+	push %ecx
+	mov $CLOCK_REALTIME_OFF, %ecx
+	call clock_read
+	pop %ecx
+	mov %eax, (%ecx)
+	# Convert nanoseconds to microseconds
+	push %ebx
+	mov %edx, %eax
+	xor %edx, %edx
+	mov $1000, %ebx
+	div %ebx
+	pop %ebx
+	mov %eax, 4(%ecx)
+1:
+	# The timezone is always UTC
+	mov 8(%esp), %ecx
+	test %ecx, %ecx
+	jz 2f
+	movl $0, (%ecx)
+	movl $0, 4(%ecx)
+2:
+	xor %eax, %eax
+	ret
 
 __vdso_time:
-	# TODO
-	ud2
+	mov $CLOCK_REALTIME_OFF, %ecx
+	call clock_read
+	mov 4(%esp), %ecx
+	test %ecx, %ecx
+	jz 1f
+	mov %eax, (%ecx)
+1:
+	ret